@@ -0,0 +1,549 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use std::ops::Range;
+
+use super::{DebugInfo, function_die::FunctionDie};
+
+/// Maps the addresses a program executed to the lines and functions of its source code.
+///
+/// The map is built from the line tables and the function DIEs of the debug information.
+/// Executed code is recorded either as single addresses, e.g. program counter samples, or as
+/// address ranges, e.g. from an instruction or branch trace. As the hit counts of a line or a
+/// function are the highest count of any of its instructions, they are execution counts for
+/// traced code and sample counts for sampled code.
+pub struct CoverageMap {
+    /// The source files, as displayed in the reports.
+    files: Vec<String>,
+    /// The rows of the line tables, sorted by address.
+    rows: Vec<LineRow>,
+    functions: Vec<Function>,
+}
+
+/// The instructions of a line table row.
+struct LineRow {
+    addresses: Range<u64>,
+    file: usize,
+    line: u64,
+    hits: u64,
+}
+
+struct Function {
+    name: String,
+    file: usize,
+    line: u64,
+    ranges: Vec<Range<u64>>,
+}
+
+impl CoverageMap {
+    /// Collect the lines and functions of all compilation units in `debug_info`.
+    pub fn new(debug_info: &DebugInfo) -> Self {
+        let mut map = Self {
+            files: Vec::new(),
+            rows: Vec::new(),
+            functions: Vec::new(),
+        };
+        let mut file_indices = HashMap::new();
+
+        for unit_info in &debug_info.unit_infos {
+            let unit = &unit_info.unit;
+
+            if let Some(program) = unit.line_program.clone() {
+                let mut unit_files = HashMap::new();
+                let mut rows = program.rows();
+                let mut previous: Option<(u64, usize, u64)> = None;
+                let mut sequence_start = true;
+                let mut discarded = false;
+
+                while let Ok(Some((_, row))) = rows.next_row() {
+                    if sequence_start {
+                        // The code of functions removed by the linker is relocated to a
+                        // tombstone address, it must not be reported as uncovered.
+                        discarded = is_tombstone(row.address());
+                        sequence_start = false;
+                    }
+
+                    if let Some((address, file, line)) = previous.take()
+                        && !discarded
+                        && row.address() > address
+                    {
+                        map.rows.push(LineRow {
+                            addresses: address..row.address(),
+                            file,
+                            line,
+                            hits: 0,
+                        });
+                    }
+
+                    if row.end_sequence() {
+                        sequence_start = true;
+                        continue;
+                    }
+
+                    let Some(line) = row.line() else {
+                        continue;
+                    };
+                    let file = *unit_files.entry(row.file_index()).or_insert_with(|| {
+                        debug_info
+                            .find_file_and_directory(unit, row.file_index())
+                            .map(|path| map.file_index(&mut file_indices, path.to_path().display()))
+                    });
+                    if let Some(file) = file {
+                        previous = Some((row.address(), file, line.get()));
+                    }
+                }
+            }
+
+            let mut entries = unit.entries();
+            while let Ok(Some(entry)) = entries.next_dfs() {
+                let Some(ranges) =
+                    FunctionDie::function_ranges(entry, unit_info, &debug_info.dwarf)
+                        .ok()
+                        .flatten()
+                else {
+                    continue;
+                };
+                let Some(low_pc) = ranges.first().map(|range| range.start) else {
+                    continue;
+                };
+                if is_tombstone(low_pc) {
+                    continue;
+                }
+                let Ok(Some(function)) =
+                    FunctionDie::new(entry.clone(), unit_info, debug_info, low_pc)
+                else {
+                    continue;
+                };
+                if function.is_inline() {
+                    continue;
+                }
+                let Some(name) = function
+                    .qualified_name(debug_info)
+                    .or_else(|| function.function_name(debug_info))
+                else {
+                    continue;
+                };
+                let Some(location) = debug_info.get_source_location(low_pc) else {
+                    continue;
+                };
+
+                let file = map.file_index(&mut file_indices, location.path.to_path().display());
+                map.functions.push(Function {
+                    name,
+                    file,
+                    line: location.line.unwrap_or(0),
+                    ranges,
+                });
+            }
+        }
+
+        map.rows.sort_by_key(|row| row.addresses.start);
+        map
+    }
+
+    fn file_index(
+        &mut self,
+        file_indices: &mut HashMap<String, usize>,
+        path: impl std::fmt::Display,
+    ) -> usize {
+        *file_indices
+            .entry(path.to_string())
+            .or_insert_with_key(|path| {
+                self.files.push(path.clone());
+                self.files.len() - 1
+            })
+    }
+
+    /// Record that the instruction at `address` was sampled `count` times.
+    pub fn add_sample(&mut self, address: u64, count: u64) {
+        let index = self
+            .rows
+            .partition_point(|row| row.addresses.start <= address);
+        if let Some(row) = index.checked_sub(1).map(|index| &mut self.rows[index])
+            && row.addresses.contains(&address)
+        {
+            row.hits += count;
+        }
+    }
+
+    /// Record that all instructions in `addresses` were executed `count` times.
+    pub fn add_range(&mut self, addresses: Range<u64>, count: u64) {
+        let first = self
+            .rows
+            .partition_point(|row| row.addresses.end <= addresses.start);
+        for row in &mut self.rows[first..] {
+            if row.addresses.start >= addresses.end {
+                break;
+            }
+            row.hits += count;
+        }
+    }
+
+    /// Summarize the recorded hits per source file.
+    pub fn report(&self) -> CoverageReport {
+        let mut files: BTreeMap<&str, FileCoverage> = BTreeMap::new();
+
+        for row in &self.rows {
+            let path = &self.files[row.file];
+            let file = files.entry(path).or_insert_with(|| FileCoverage::new(path));
+            let hits = file.lines.entry(row.line).or_default();
+            *hits = (*hits).max(row.hits);
+        }
+
+        for function in &self.functions {
+            let hits = function
+                .ranges
+                .iter()
+                .flat_map(|range| self.rows_in(range))
+                .map(|row| row.hits)
+                .max()
+                .unwrap_or(0);
+
+            let path = &self.files[function.file];
+            let file = files.entry(path).or_insert_with(|| FileCoverage::new(path));
+            // Every instance of a generic function has the same name, so they are merged.
+            match file
+                .functions
+                .iter_mut()
+                .find(|existing| existing.name == function.name)
+            {
+                Some(existing) => {
+                    existing.line = existing.line.min(function.line);
+                    existing.hits += hits;
+                }
+                None => file.functions.push(FunctionCoverage {
+                    name: function.name.clone(),
+                    line: function.line,
+                    hits,
+                }),
+            }
+        }
+
+        let mut files = files.into_values().collect::<Vec<_>>();
+        for file in &mut files {
+            file.functions
+                .sort_by(|a, b| a.line.cmp(&b.line).then_with(|| a.name.cmp(&b.name)));
+        }
+
+        CoverageReport { files }
+    }
+
+    fn rows_in(&self, addresses: &Range<u64>) -> impl Iterator<Item = &LineRow> {
+        let first = self
+            .rows
+            .partition_point(|row| row.addresses.end <= addresses.start);
+        self.rows[first..]
+            .iter()
+            .take_while(move |row| row.addresses.start < addresses.end)
+    }
+}
+
+/// Returns true for the addresses that linkers use for the debug information of discarded code.
+fn is_tombstone(address: u64) -> bool {
+    address == 0 || address >= u64::MAX - 1 || matches!(address, 0xffff_fffe..=0xffff_ffff)
+}
+
+/// The coverage of a program, per source file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoverageReport {
+    /// The source files with code in the program, sorted by path.
+    pub files: Vec<FileCoverage>,
+}
+
+/// The coverage of a single source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileCoverage {
+    /// The path of the source file.
+    pub path: String,
+    /// The hit count of every line with code.
+    pub lines: BTreeMap<u64, u64>,
+    /// The functions defined in the file, sorted by line.
+    pub functions: Vec<FunctionCoverage>,
+}
+
+/// The coverage of a function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionCoverage {
+    /// The qualified name of the function.
+    pub name: String,
+    /// The line the function starts at.
+    pub line: u64,
+    /// How often the function was hit.
+    pub hits: u64,
+}
+
+impl FileCoverage {
+    fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            lines: BTreeMap::new(),
+            functions: Vec::new(),
+        }
+    }
+
+    /// The number of lines with code that were hit.
+    pub fn lines_hit(&self) -> usize {
+        self.lines.values().filter(|&&hits| hits > 0).count()
+    }
+
+    /// The number of functions that were hit.
+    pub fn functions_hit(&self) -> usize {
+        self.functions
+            .iter()
+            .filter(|function| function.hits > 0)
+            .count()
+    }
+}
+
+impl CoverageReport {
+    /// The number of lines with code in the program.
+    pub fn lines_found(&self) -> usize {
+        self.files.iter().map(|file| file.lines.len()).sum()
+    }
+
+    /// The number of lines with code that were hit.
+    pub fn lines_hit(&self) -> usize {
+        self.files.iter().map(FileCoverage::lines_hit).sum()
+    }
+
+    /// Write the report as an lcov tracefile, as read by `genhtml` and most CI services.
+    pub fn write_lcov(&self, writer: &mut impl Write) -> io::Result<()> {
+        for file in &self.files {
+            writeln!(writer, "TN:")?;
+            writeln!(writer, "SF:{}", file.path)?;
+            for function in &file.functions {
+                writeln!(writer, "FN:{},{}", function.line, function.name)?;
+            }
+            for function in &file.functions {
+                writeln!(writer, "FNDA:{},{}", function.hits, function.name)?;
+            }
+            writeln!(writer, "FNF:{}", file.functions.len())?;
+            writeln!(writer, "FNH:{}", file.functions_hit())?;
+            for (line, hits) in &file.lines {
+                writeln!(writer, "DA:{line},{hits}")?;
+            }
+            writeln!(writer, "LF:{}", file.lines.len())?;
+            writeln!(writer, "LH:{}", file.lines_hit())?;
+            writeln!(writer, "end_of_record")?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the report as Cobertura XML, with one package per directory and one class per
+    /// source file. `timestamp` is the time the coverage was collected at, in seconds since the
+    /// Unix epoch.
+    pub fn write_cobertura(&self, writer: &mut impl Write, timestamp: u64) -> io::Result<()> {
+        let mut packages: BTreeMap<&str, Vec<&FileCoverage>> = BTreeMap::new();
+        for file in &self.files {
+            let directory = file
+                .path
+                .rsplit_once(['/', '\\'])
+                .map_or("", |(directory, _)| directory);
+            packages.entry(directory).or_default().push(file);
+        }
+
+        writeln!(writer, r#"<?xml version="1.0" ?>"#)?;
+        writeln!(
+            writer,
+            r#"<!DOCTYPE coverage SYSTEM "http://cobertura.sourceforge.net/xml/coverage-04.dtd">"#
+        )?;
+        writeln!(
+            writer,
+            r#"<coverage line-rate="{}" branch-rate="0" lines-covered="{}" lines-valid="{}" branches-covered="0" branches-valid="0" complexity="0" version="{}" timestamp="{timestamp}">"#,
+            rate(self.lines_hit(), self.lines_found()),
+            self.lines_hit(),
+            self.lines_found(),
+            env!("CARGO_PKG_VERSION"),
+        )?;
+        writeln!(writer, "  <sources>")?;
+        writeln!(writer, "    <source>.</source>")?;
+        writeln!(writer, "  </sources>")?;
+        writeln!(writer, "  <packages>")?;
+        for (directory, files) in packages {
+            let found = files.iter().map(|file| file.lines.len()).sum();
+            let hit = files.iter().map(|file| file.lines_hit()).sum();
+            writeln!(
+                writer,
+                r#"    <package name="{}" line-rate="{}" branch-rate="0" complexity="0">"#,
+                escape_xml(directory),
+                rate(hit, found),
+            )?;
+            writeln!(writer, "      <classes>")?;
+            for file in files {
+                let path = escape_xml(&file.path);
+                writeln!(
+                    writer,
+                    r#"        <class name="{path}" filename="{path}" line-rate="{}" branch-rate="0" complexity="0">"#,
+                    rate(file.lines_hit(), file.lines.len()),
+                )?;
+                writeln!(writer, "          <methods>")?;
+                for function in &file.functions {
+                    writeln!(
+                        writer,
+                        r#"            <method name="{}" signature="" line-rate="{}" branch-rate="0" complexity="0">"#,
+                        escape_xml(&function.name),
+                        if function.hits > 0 { 1 } else { 0 },
+                    )?;
+                    writeln!(writer, "              <lines>")?;
+                    writeln!(
+                        writer,
+                        r#"                <line number="{}" hits="{}" branch="false"/>"#,
+                        function.line, function.hits
+                    )?;
+                    writeln!(writer, "              </lines>")?;
+                    writeln!(writer, "            </method>")?;
+                }
+                writeln!(writer, "          </methods>")?;
+                writeln!(writer, "          <lines>")?;
+                for (line, hits) in &file.lines {
+                    writeln!(
+                        writer,
+                        r#"            <line number="{line}" hits="{hits}" branch="false"/>"#
+                    )?;
+                }
+                writeln!(writer, "          </lines>")?;
+                writeln!(writer, "        </class>")?;
+            }
+            writeln!(writer, "      </classes>")?;
+            writeln!(writer, "    </package>")?;
+        }
+        writeln!(writer, "  </packages>")?;
+        writeln!(writer, "</coverage>")?;
+
+        Ok(())
+    }
+}
+
+fn rate(hit: usize, found: usize) -> f64 {
+    if found == 0 {
+        0.0
+    } else {
+        hit as f64 / found as f64
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Two functions in one file, each in two address ranges: `main` on lines 1 to 3, and
+    /// `helper::<u32>` on line 10.
+    fn map() -> CoverageMap {
+        let row = |addresses: Range<u64>, line| LineRow {
+            addresses,
+            file: 0,
+            line,
+            hits: 0,
+        };
+        CoverageMap {
+            files: vec!["/src/main.rs".to_string()],
+            rows: vec![
+                row(0x100..0x104, 1),
+                row(0x104..0x108, 2),
+                row(0x108..0x10c, 3),
+                row(0x10c..0x110, 2),
+                row(0x200..0x208, 10),
+            ],
+            functions: vec![
+                Function {
+                    name: "app::main".to_string(),
+                    file: 0,
+                    line: 1,
+                    ranges: vec![0x100..0x108, 0x108..0x110],
+                },
+                Function {
+                    name: "app::helper<u32>".to_string(),
+                    file: 0,
+                    line: 10,
+                    ranges: vec![0x200..0x204, 0x204..0x208],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn samples_and_ranges() {
+        let mut map = map();
+        map.add_sample(0x106, 3);
+        map.add_sample(0x10e, 1);
+        // Outside of any row.
+        map.add_sample(0x180, 1);
+        map.add_range(0x100..0x106, 2);
+
+        let report = map.report();
+        assert_eq!(report.files.len(), 1);
+        let file = &report.files[0];
+        assert_eq!(
+            file.lines,
+            BTreeMap::from([(1, 2), (2, 5), (3, 0), (10, 0)])
+        );
+        assert_eq!(file.functions[0].hits, 5);
+        assert_eq!(file.functions[1].hits, 0);
+        assert_eq!(report.lines_found(), 4);
+        assert_eq!(report.lines_hit(), 2);
+    }
+
+    #[test]
+    fn lcov() {
+        let mut map = map();
+        map.add_sample(0x100, 1);
+
+        let mut output = Vec::new();
+        map.report().write_lcov(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "TN:\n\
+             SF:/src/main.rs\n\
+             FN:1,app::main\n\
+             FN:10,app::helper<u32>\n\
+             FNDA:1,app::main\n\
+             FNDA:0,app::helper<u32>\n\
+             FNF:2\n\
+             FNH:1\n\
+             DA:1,1\n\
+             DA:2,0\n\
+             DA:3,0\n\
+             DA:10,0\n\
+             LF:4\n\
+             LH:1\n\
+             end_of_record\n"
+        );
+    }
+
+    #[test]
+    fn cobertura() {
+        let mut map = map();
+        map.add_sample(0x100, 1);
+
+        let mut output = Vec::new();
+        map.report()
+            .write_cobertura(&mut output, 1700000000)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(
+            output
+                .contains(r#"line-rate="0.25" branch-rate="0" lines-covered="1" lines-valid="4""#)
+        );
+        assert!(output.contains(r#"<package name="/src" line-rate="0.25""#));
+        assert!(output.contains(r#"<class name="/src/main.rs" filename="/src/main.rs""#));
+        assert!(output.contains(r#"<method name="app::helper&lt;u32&gt;" signature="""#));
+        assert!(output.contains(r#"<line number="1" hits="1" branch="false"/>"#));
+        assert!(output.contains(r#"timestamp="1700000000""#));
+    }
+}
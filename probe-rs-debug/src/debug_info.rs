@@ -902,6 +902,73 @@ impl DebugInfo {
         VerifiedBreakpoint::for_source_location(self, path, line, column)
     }
 
+    /// Find the program counters where breakpoints should be set for every instance of the
+    /// function `name`.
+    ///
+    /// `name` is matched against the end of each function's path at a `::` boundary, so both
+    /// `my_crate::driver::handle_irq` and `handle_irq` find the same function, and C functions
+    /// match by their plain name. Generic arguments are ignored, so every monomorphised instance
+    /// is returned, as well as every inlined copy of the function. A function can also be found
+    /// by its exact symbol name, e.g. `rust_begin_unwind` for the `#[panic_handler]`.
+    #[tracing::instrument(skip_all)]
+    pub fn get_function_breakpoint_locations(
+        &self,
+        name: &str,
+    ) -> Result<Vec<VerifiedBreakpoint>, DebugError> {
+        tracing::debug!("Looking for breakpoint locations for function {name}");
+        let requested = strip_generic_arguments(name.trim().trim_start_matches("::"));
+
+        let mut breakpoints: Vec<VerifiedBreakpoint> = Vec::new();
+        for unit_info in &self.unit_infos {
+            let mut entries = unit_info.unit.entries();
+            while let Ok(Some(entry)) = entries.next_dfs() {
+                let Some(low_pc) = FunctionDie::function_ranges(entry, unit_info, &self.dwarf)
+                    .ok()
+                    .flatten()
+                    .and_then(|ranges| ranges.first().map(|range| range.start))
+                else {
+                    continue;
+                };
+                let Ok(Some(function)) = FunctionDie::new(entry.clone(), unit_info, self, low_pc)
+                else {
+                    continue;
+                };
+                let name_matches = function.qualified_name(self).is_some_and(|qualified_name| {
+                    function_name_matches(&qualified_name, &requested)
+                });
+                if !name_matches && function.linkage_name(self).as_deref() != Some(name.trim()) {
+                    continue;
+                }
+
+                // Prefer the first statement after the prologue, but stay within the function.
+                let breakpoint = match VerifiedBreakpoint::for_address(self, low_pc) {
+                    Ok(breakpoint) if function.range_contains(breakpoint.address) => breakpoint,
+                    _ => match self.get_source_location(low_pc) {
+                        Some(source_location) => VerifiedBreakpoint {
+                            address: low_pc,
+                            source_location,
+                        },
+                        None => continue,
+                    },
+                };
+                if !breakpoints
+                    .iter()
+                    .any(|bp| bp.address == breakpoint.address)
+                {
+                    breakpoints.push(breakpoint);
+                }
+            }
+        }
+
+        if breakpoints.is_empty() {
+            return Err(DebugError::Other(format!(
+                "No function named `{name}` was found in the debug information."
+            )));
+        }
+        breakpoints.sort_by_key(|breakpoint| breakpoint.address);
+        Ok(breakpoints)
+    }
+
     /// Get the path for an entry in a line program header, using the compilation unit's directory and file entries.
     // TODO: Determine if it is necessary to navigate the include directories to find the file absolute path for C files.
     pub(crate) fn get_path(
@@ -986,6 +1053,34 @@ impl DebugInfo {
         })
     }
 
+    /// The address ranges of the code of the compilation units whose name `matches_name`, like
+    /// the units of assembly files that have no functions in the debug information.
+    pub(crate) fn compile_unit_ranges(
+        &self,
+        matches_name: impl Fn(&str) -> bool,
+    ) -> Vec<std::ops::Range<u64>> {
+        let mut unit_ranges = Vec::new();
+        for header in &self.unit_infos {
+            let Some(name) = header
+                .unit
+                .name
+                .as_ref()
+                .and_then(|name| gimli::Reader::to_string_lossy(name).ok())
+            else {
+                continue;
+            };
+            if !matches_name(&name) {
+                continue;
+            }
+            if let Ok(mut ranges) = self.dwarf.unit_ranges(&header.unit) {
+                while let Ok(Some(range)) = ranges.next() {
+                    unit_ranges.push(range.begin..range.end);
+                }
+            }
+        }
+        unit_ranges
+    }
+
     /// Search across all compilation units, and retrieve the DIEs for the function containing the given address.
     /// This is distinct from [`UnitInfo::get_function_dies`] in that it will search all compilation units.
     /// - The first entry in the vector will be the outermost function containing the address.
@@ -1117,6 +1212,30 @@ pub(crate) fn path_matches(full_path: TypedPath, partial_path: TypedPath) -> boo
     }
 }
 
+/// Removes generic arguments from a function path, e.g. `Vec<u8>::push` becomes `Vec::push`.
+fn strip_generic_arguments(name: &str) -> String {
+    let mut depth = 0_usize;
+    name.chars()
+        .filter(|&c| {
+            match c {
+                '<' => depth += 1,
+                '>' => depth = depth.saturating_sub(1),
+                _ => return depth == 0,
+            }
+            false
+        })
+        .collect()
+}
+
+/// Returns `true` if `requested`, without generic arguments, is the function path
+/// `qualified_name` or a suffix of it, starting at a `::` boundary.
+fn function_name_matches(qualified_name: &str, requested: &str) -> bool {
+    let qualified_name = strip_generic_arguments(qualified_name);
+    qualified_name
+        .strip_suffix(requested)
+        .is_some_and(|prefix| prefix.is_empty() || prefix.ends_with("::"))
+}
+
 /// Get a handle to the [`gimli::UnwindTableRow`] for this call frame, so that we can reference it to unwind register values.
 pub fn get_unwind_info<'a>(
     unwind_context: &'a mut UnwindContext<GimliReaderOffset>,
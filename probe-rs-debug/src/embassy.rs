@@ -0,0 +1,642 @@
+//! Tasks of the embassy async executor.
+//!
+//! The `#[embassy_executor::task]` macro puts the futures of each task into a static pool. The
+//! pool is either a `TaskPool` static of its own, or part of the executor's arena, which a
+//! `TaskPoolRef` static points to once the task was spawned. Each slot of a pool is a
+//! `TaskStorage`, which starts with the header of the task, followed by the future of the task.
+//!
+//! The header holds the state flags of the task, the executor it was spawned on and the link to
+//! the next task in the run queue of the executor. The future is the state machine the compiler
+//! generated for the `async fn` of the task, and is explored like any other variable.
+
+use std::fmt;
+
+use gimli::UnitOffset;
+use probe_rs::MemoryInterface;
+
+use crate::static_variable::type_ref;
+use crate::unit_info::UnitInfo;
+use crate::{DebugError, DebugInfo, StackFrameInfo, VariableCache, VariableLocation, VariableName};
+
+/// More tasks than this in a run queue means the queue is corrupted.
+const MAX_TASKS_PER_RUN_QUEUE: usize = 1024;
+
+/// The state flags of a task, from the header of the task.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaskState {
+    /// The task was spawned and did not finish yet.
+    pub spawned: bool,
+    /// The task was woken, and waits in the run queue of its executor to be polled.
+    pub run_queued: bool,
+    /// The task waits in the timer queue for a timer to expire.
+    pub timer_queued: bool,
+}
+
+impl fmt::Display for TaskState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags = [
+            (self.spawned, "spawned"),
+            (self.run_queued, "run queued"),
+            (self.timer_queued, "timer queued"),
+        ]
+        .into_iter()
+        .filter_map(|(set, flag)| set.then_some(flag))
+        .collect::<Vec<_>>();
+
+        if flags.is_empty() {
+            f.write_str("not spawned")
+        } else {
+            f.write_str(&flags.join(", "))
+        }
+    }
+}
+
+/// A spawned task of the embassy executor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbassyTask {
+    /// The address of the task, which is the address of its header.
+    pub address: u64,
+    /// The path of the function of the task, like `app::blink`.
+    pub name: String,
+    /// The type of the future of the task, the state machine of its `async fn`.
+    pub future_type: String,
+    /// The address of the future of the task.
+    pub future_address: u64,
+    /// The state flags of the task.
+    pub state: TaskState,
+    /// The address of the executor the task was spawned on.
+    pub executor: u64,
+    /// The unit and the offset of the type of the future in the debug information.
+    future_type_entry: (usize, UnitOffset),
+}
+
+/// An executor that tasks were spawned on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbassyExecutor {
+    /// The address of the executor.
+    pub address: u64,
+    /// The addresses of the tasks in the run queue of the executor, in the order they are
+    /// polled next.
+    pub run_queue: Vec<u64>,
+}
+
+/// How much of the arena the task pools were allocated from is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArenaUsage {
+    /// The number of bytes allocated for task pools.
+    pub used: u64,
+    /// The size of the arena in bytes.
+    pub size: u64,
+}
+
+/// The tasks and the executors of the program, read from the target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbassyTasks {
+    /// The spawned tasks.
+    pub tasks: Vec<EmbassyTask>,
+    /// The executors the tasks were spawned on.
+    pub executors: Vec<EmbassyExecutor>,
+    /// The use of the arena, if the task pools are allocated from one.
+    pub arena: Option<ArenaUsage>,
+}
+
+/// The offsets of the fields of a task header, and how its state flags are kept.
+#[derive(Debug, Clone, PartialEq)]
+struct Layout {
+    /// `TaskHeader::executor`
+    executor: u64,
+    /// `TaskHeader::poll_fn`
+    poll_fn: u64,
+    /// `TaskHeader::run_queue_item.next`
+    run_queue_next: Option<u64>,
+    /// `SyncExecutor::run_queue.head`
+    run_queue_head: Option<u64>,
+    state: StateLayout,
+}
+
+/// How the state flags of a task are kept, which depends on the target and the version of embassy.
+#[derive(Debug, Clone, PartialEq)]
+enum StateLayout {
+    /// The flags are bits of a word at this offset, like on targets with atomic integers.
+    Bits(u64),
+    /// Each flag is a byte at its own offset, like on ARM targets.
+    Bytes {
+        spawned: u64,
+        run_queued: Option<u64>,
+        timer_queued: Option<u64>,
+    },
+}
+
+impl StateLayout {
+    const SPAWNED: u32 = 1 << 0;
+    const RUN_QUEUED: u32 = 1 << 1;
+    const TIMER_QUEUED: u32 = 1 << 2;
+
+    fn read(&self, memory: &mut dyn MemoryInterface, task: u64) -> Result<TaskState, DebugError> {
+        match *self {
+            StateLayout::Bits(offset) => {
+                let bits = memory.read_word_32(task + offset)?;
+                Ok(TaskState {
+                    spawned: bits & Self::SPAWNED != 0,
+                    run_queued: bits & Self::RUN_QUEUED != 0,
+                    timer_queued: bits & Self::TIMER_QUEUED != 0,
+                })
+            }
+            StateLayout::Bytes {
+                spawned,
+                run_queued,
+                timer_queued,
+            } => {
+                let mut flag = |offset: Option<u64>| -> Result<bool, DebugError> {
+                    match offset {
+                        Some(offset) => Ok(memory.read_word_8(task + offset)? != 0),
+                        None => Ok(false),
+                    }
+                };
+                Ok(TaskState {
+                    spawned: flag(Some(spawned))?,
+                    run_queued: flag(run_queued)?,
+                    timer_queued: flag(timer_queued)?,
+                })
+            }
+        }
+    }
+}
+
+impl Layout {
+    fn from_debug_info(debug_info: &DebugInfo) -> Result<Self, DebugError> {
+        let member = |type_name: &str, path: &str| {
+            debug_info
+                .resolve_type_member(type_name, path)
+                .map(|member| member.offset)
+        };
+
+        let state = match member("TaskHeader", "state.state") {
+            Ok(bits) => StateLayout::Bits(bits),
+            Err(_) => StateLayout::Bytes {
+                spawned: member("TaskHeader", "state.spawned")?,
+                run_queued: member("TaskHeader", "state.run_queued").ok(),
+                timer_queued: member("TaskHeader", "state.timer_queued").ok(),
+            },
+        };
+
+        Ok(Self {
+            executor: member("TaskHeader", "executor")?,
+            poll_fn: member("TaskHeader", "poll_fn")?,
+            run_queue_next: member("TaskHeader", "run_queue_item.next").ok(),
+            run_queue_head: member("SyncExecutor", "run_queue.head").ok(),
+            state,
+        })
+    }
+}
+
+/// A static the futures of a task are kept in.
+#[derive(Debug, Clone, PartialEq)]
+struct Pool {
+    /// The path of the function of the task.
+    name: String,
+    /// The address of the static.
+    address: u64,
+    /// The name of the `TaskPool` type, if the static is the pool itself. Otherwise the static is
+    /// a `TaskPoolRef`, which points to the pool in the arena.
+    pool_type: Option<String>,
+}
+
+/// The types of the futures in a pool, resolved from the debug information.
+struct PoolLayout {
+    /// The type of the future.
+    future_type: String,
+    future_type_entry: (usize, UnitOffset),
+    /// The offset of the future in a slot of the pool.
+    future_offset: u64,
+    /// The size of a slot of the pool.
+    slot_size: u64,
+    /// The number of slots in the pool.
+    slots: u64,
+}
+
+/// The tasks of the embassy executor, found from the task pools in the debug information.
+#[derive(Debug)]
+pub struct Embassy {
+    pools: Vec<Pool>,
+    layout: Layout,
+    /// The addresses of `ARENA.buf` and `ARENA.ptr`, and the size of the arena.
+    arena: Option<(u64, u64, u64)>,
+}
+
+impl Embassy {
+    /// Finds the task pools in the debug information, if the program uses the embassy executor.
+    pub fn from_debug_info(debug_info: &DebugInfo) -> Result<Option<Self>, DebugError> {
+        let statics = debug_info.find_statics(|type_name| {
+            type_name == "TaskPoolRef" || type_name.starts_with("TaskPool<")
+        })?;
+        if statics.is_empty() {
+            return Ok(None);
+        }
+
+        let pools = statics
+            .into_iter()
+            .map(|pool| {
+                // The pool is declared in the function that spawns the task.
+                let mut name = pool.name;
+                name.pop();
+                Pool {
+                    name: name.join("::"),
+                    address: pool.address,
+                    pool_type: (pool.type_name != "TaskPoolRef").then_some(pool.type_name),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let arena = match (
+            debug_info.resolve_static_location("embassy_executor::_export::ARENA.buf"),
+            debug_info.resolve_static_location("embassy_executor::_export::ARENA.ptr"),
+        ) {
+            (Ok(buf), Ok(ptr)) => Some((buf.address, ptr.address, buf.byte_size)),
+            _ => None,
+        };
+
+        tracing::debug!("Found {} embassy task pools", pools.len());
+        Ok(Some(Self {
+            pools,
+            layout: Layout::from_debug_info(debug_info)?,
+            arena,
+        }))
+    }
+
+    /// Reads the spawned tasks, the run queues of their executors and the use of the arena.
+    ///
+    /// The core should be halted, so the state of the tasks is consistent.
+    pub fn tasks(
+        &self,
+        debug_info: &DebugInfo,
+        memory: &mut dyn MemoryInterface,
+    ) -> Result<EmbassyTasks, DebugError> {
+        let mut tasks = Vec::new();
+        for pool in &self.pools {
+            let address = match pool.pool_type {
+                Some(_) => pool.address,
+                None => u64::from(memory.read_word_32(pool.address)?),
+            };
+            // The pool in the arena is only allocated when the task is spawned the first time.
+            if address == 0 {
+                continue;
+            }
+
+            let Some(pool_layout) = self.pool_layout(debug_info, memory, pool, address)? else {
+                continue;
+            };
+            for slot in 0..pool_layout.slots {
+                let task = address + slot * pool_layout.slot_size;
+                let state = self.layout.state.read(memory, task)?;
+                if !state.spawned {
+                    continue;
+                }
+
+                tasks.push(EmbassyTask {
+                    address: task,
+                    name: pool.name.clone(),
+                    future_type: pool_layout.future_type.clone(),
+                    future_address: task + pool_layout.future_offset,
+                    state,
+                    executor: u64::from(memory.read_word_32(task + self.layout.executor)?),
+                    future_type_entry: pool_layout.future_type_entry,
+                });
+            }
+        }
+
+        let mut executors = Vec::<EmbassyExecutor>::new();
+        for task in &tasks {
+            if task.executor == 0
+                || executors
+                    .iter()
+                    .any(|executor| executor.address == task.executor)
+            {
+                continue;
+            }
+            executors.push(EmbassyExecutor {
+                address: task.executor,
+                run_queue: self.run_queue(memory, task.executor)?,
+            });
+        }
+
+        let arena = match self.arena {
+            Some((buf, ptr, size)) => {
+                // The arena points to its next free byte once the first pool is allocated.
+                let next = u64::from(memory.read_word_32(ptr)?);
+                Some(ArenaUsage {
+                    used: if next == 0 {
+                        0
+                    } else {
+                        next.saturating_sub(buf)
+                    },
+                    size,
+                })
+            }
+            None => None,
+        };
+
+        Ok(EmbassyTasks {
+            tasks,
+            executors,
+            arena,
+        })
+    }
+
+    /// Builds a cache of variables with the future of each task as a child of the root, so the
+    /// state of the async state machines can be explored.
+    pub fn task_variables(
+        &self,
+        debug_info: &DebugInfo,
+        memory: &mut dyn MemoryInterface,
+        tasks: &[EmbassyTask],
+        frame_info: StackFrameInfo<'_>,
+    ) -> Result<VariableCache, DebugError> {
+        let mut cache = VariableCache::new_named_cache("Tasks");
+        let root = cache.root_variable().clone();
+
+        for task in tasks {
+            let (unit_index, type_offset) = task.future_type_entry;
+            let Some(unit_info) = debug_info.unit_infos.get(unit_index) else {
+                continue;
+            };
+
+            let mut variable = cache.create_variable(root.variable_key(), Some(unit_info))?;
+            variable.name = VariableName::Named(format!("{} @ {:#010x}", task.name, task.address));
+            variable.memory_location = VariableLocation::Address(task.future_address);
+
+            let type_node = unit_info.unit.entry(type_offset)?;
+            unit_info.extract_type(
+                debug_info,
+                &type_node,
+                &root,
+                &mut variable,
+                memory,
+                &mut cache,
+                frame_info,
+            )?;
+            cache.update_variable(&variable)?;
+        }
+
+        Ok(cache)
+    }
+
+    /// The addresses of the tasks in the run queue of an executor.
+    fn run_queue(
+        &self,
+        memory: &mut dyn MemoryInterface,
+        executor: u64,
+    ) -> Result<Vec<u64>, DebugError> {
+        let (Some(head), Some(next)) = (self.layout.run_queue_head, self.layout.run_queue_next)
+        else {
+            return Ok(Vec::new());
+        };
+
+        let mut run_queue = Vec::new();
+        let mut task = u64::from(memory.read_word_32(executor + head)?);
+        while task != 0 {
+            if run_queue.len() >= MAX_TASKS_PER_RUN_QUEUE || run_queue.contains(&task) {
+                tracing::warn!(
+                    "Skipping the corrupted run queue of the executor at {executor:#010x}"
+                );
+                return Ok(Vec::new());
+            }
+            run_queue.push(task);
+            task = u64::from(memory.read_word_32(task + next)?);
+        }
+
+        Ok(run_queue)
+    }
+
+    /// Resolves the type of the futures in a pool, and the layout of the pool.
+    ///
+    /// A pool in the arena has no type of its own in the debug information. Its future type is
+    /// found from the poll function of its first task instead, which is set when it is spawned.
+    fn pool_layout(
+        &self,
+        debug_info: &DebugInfo,
+        memory: &mut dyn MemoryInterface,
+        pool: &Pool,
+        address: u64,
+    ) -> Result<Option<PoolLayout>, DebugError> {
+        let future_type = match &pool.pool_type {
+            Some(pool_type) => pool_type
+                .strip_prefix("TaskPool<")
+                .and_then(|arguments| arguments.rsplit_once(", "))
+                .map(|(future_type, _)| future_type.to_string()),
+            None => {
+                let poll_fn = u64::from(memory.read_word_32(address + self.layout.poll_fn)?);
+                if poll_fn == 0 {
+                    return Ok(None);
+                }
+                debug_info
+                    .get_function_dies(poll_fn)
+                    .ok()
+                    .and_then(|(_, functions)| functions.first()?.function_name(debug_info))
+                    .as_deref()
+                    .and_then(future_type_of_poll_fn)
+                    .map(str::to_string)
+            }
+        };
+        let Some(future_type) = future_type else {
+            tracing::warn!("Unknown future type of the embassy task `{}`", pool.name);
+            return Ok(None);
+        };
+
+        let storage_type = format!("TaskStorage<{future_type}>");
+        let Some((unit_info, storage_offset, _)) =
+            debug_info.find_type(|name| name == storage_type)
+        else {
+            tracing::warn!("No type `{storage_type}` found in the debug information");
+            return Ok(None);
+        };
+        let Some(future_type_offset) =
+            template_parameter(debug_info, unit_info, storage_offset, "F")?
+        else {
+            return Ok(None);
+        };
+        let unit_index = debug_info
+            .unit_infos
+            .iter()
+            .position(|unit| std::ptr::eq(unit, unit_info))
+            .unwrap_or_default();
+
+        let slot_size = debug_info.resolve_type_member(&storage_type, "")?.byte_size;
+        let pool_prefix = format!("TaskPool<{future_type}, ");
+        let pool_type = match &pool.pool_type {
+            Some(pool_type) => Some(pool_type.clone()),
+            None => debug_info
+                .find_type(|name| name.starts_with(&pool_prefix))
+                .map(|(_, _, name)| name),
+        };
+        let slots = match pool_type {
+            Some(pool_type) => {
+                debug_info.resolve_type_member(&pool_type, "")?.byte_size / slot_size
+            }
+            None => 1,
+        };
+
+        Ok(Some(PoolLayout {
+            future_type,
+            future_type_entry: (unit_index, future_type_offset),
+            future_offset: debug_info
+                .resolve_type_member(&storage_type, "future")?
+                .offset,
+            slot_size,
+            slots,
+        }))
+    }
+}
+
+/// The type of the future that the `poll` function of a `TaskStorage` polls, from the name of
+/// the function like `poll<app::__blink_task::{async_fn_env#0}>`, or
+/// `TaskStorage<app::__blink_task::{async_fn_env#0}>::poll` if it is qualified by its type.
+fn future_type_of_poll_fn(name: &str) -> Option<&str> {
+    name.strip_suffix(">::poll")
+        .and_then(|name| name.split_once("TaskStorage<"))
+        .or_else(|| name.strip_suffix('>')?.split_once("poll<"))
+        .map(|(_, future_type)| future_type)
+}
+
+/// The type of the template parameter `name` of the type at `type_offset`.
+fn template_parameter(
+    debug_info: &DebugInfo,
+    unit_info: &UnitInfo,
+    type_offset: UnitOffset,
+    name: &str,
+) -> Result<Option<UnitOffset>, DebugError> {
+    let mut tree = unit_info.unit.entries_tree(Some(type_offset))?;
+    let mut children = tree.root()?.children();
+    while let Some(child) = children.next()? {
+        let entry = child.entry();
+        if entry.tag() != gimli::DW_TAG_template_type_parameter {
+            continue;
+        }
+        if debug_info.entry_name(unit_info, entry).as_deref() == Some(name) {
+            return Ok(type_ref(entry));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use probe_rs::test::MockMemory;
+
+    use super::*;
+    use crate::DebugRegisters;
+
+    /// The task of `#[embassy_executor::main]`, in the arena of embassy-executor 0.7.
+    const TASK: u32 = 0x3fc8_9f00;
+    const EXECUTOR: u32 = 0x3fc8_a000;
+    /// `TaskStorage::<F>::poll` of the task.
+    const POLL_FN: u32 = 0x4200_0c70;
+
+    fn debug_info() -> DebugInfo {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("tests/debug-unwind-tests/esp32s3_coredump_elf.elf");
+        DebugInfo::from_file(&path).unwrap()
+    }
+
+    fn words(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    /// The memory of the spawned main task, which waits in the run queue of its executor.
+    fn memory() -> MockMemory {
+        let mut memory = MockMemory::new();
+        // `coredump_c6::__embassy_main::POOL` points to the pool in the arena.
+        memory.add_range(0x3fc8_9e4c, words(&[TASK]));
+        // The header of the task and its future, which was not polled yet.
+        let mut task = words(&[0, 0, 0, 0, 0b11, 0, EXECUTOR, POLL_FN]);
+        task.resize(0x38, 0);
+        memory.add_range(TASK.into(), task);
+        // The run queue of the executor.
+        memory.add_range(EXECUTOR.into(), words(&[TASK, 0]));
+        // `ARENA.ptr`, after the pool of the task.
+        memory.add_range(0x3fc8_ef00, words(&[TASK + 0x38]));
+        memory
+    }
+
+    #[test]
+    fn tasks_in_the_arena() {
+        let debug_info = debug_info();
+        let mut memory = memory();
+
+        let embassy = Embassy::from_debug_info(&debug_info).unwrap().unwrap();
+        let tasks = embassy.tasks(&debug_info, &mut memory).unwrap();
+
+        assert_eq!(tasks.tasks.len(), 1);
+        let task = &tasks.tasks[0];
+        assert_eq!(task.name, "coredump_c6::__embassy_main");
+        assert_eq!(
+            task.future_type,
+            "coredump_c6::____embassy_main_task::{async_fn_env#0}"
+        );
+        assert_eq!(task.address, u64::from(TASK));
+        assert_eq!(task.future_address, u64::from(TASK) + 0x20);
+        assert_eq!(task.executor, u64::from(EXECUTOR));
+        assert_eq!(task.state.to_string(), "spawned, run queued");
+
+        assert_eq!(
+            tasks.executors,
+            vec![EmbassyExecutor {
+                address: EXECUTOR.into(),
+                run_queue: vec![TASK.into()],
+            }]
+        );
+        assert_eq!(
+            tasks.arena,
+            Some(ArenaUsage {
+                used: 0x38,
+                size: 20480,
+            })
+        );
+    }
+
+    #[test]
+    fn task_futures_as_variables() {
+        let debug_info = debug_info();
+        let mut memory = memory();
+        let embassy = Embassy::from_debug_info(&debug_info).unwrap().unwrap();
+        let tasks = embassy.tasks(&debug_info, &mut memory).unwrap();
+
+        let registers = DebugRegisters(Vec::new());
+        let frame_info = StackFrameInfo {
+            registers: &registers,
+            frame_base: None,
+            canonical_frame_address: None,
+        };
+        let mut cache = embassy
+            .task_variables(&debug_info, &mut memory, &tasks.tasks, frame_info)
+            .unwrap();
+        cache.recurse_deferred_variables(&debug_info, &mut memory, 5, frame_info);
+
+        let root = cache.root_variable().variable_key();
+        let futures = cache.get_children(root).collect::<Vec<_>>();
+        assert_eq!(futures.len(), 1);
+        assert_eq!(
+            futures[0].name,
+            VariableName::Named("coredump_c6::__embassy_main @ 0x3fc89f00".to_string())
+        );
+        assert_eq!(
+            futures[0].memory_location,
+            VariableLocation::Address(u64::from(TASK) + 0x20)
+        );
+
+        // The future was not polled yet, so it holds the arguments of the task.
+        let state = cache
+            .get_children(futures[0].variable_key())
+            .next()
+            .unwrap();
+        assert_eq!(state.type_name(), "Unresumed");
+        let captured = cache
+            .get_children(state.variable_key())
+            .map(|variable| variable.name.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(captured, ["spawner"]);
+    }
+}
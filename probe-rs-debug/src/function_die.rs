@@ -185,6 +185,66 @@ impl<'a> FunctionDie<'a> {
         }
     }
 
+    /// Returns the path of the function, e.g. `my_crate::driver::handle_irq`, built from the
+    /// namespaces and types enclosing its declaration.
+    ///
+    /// Anonymous namespaces like `{impl#0}` are left out.
+    pub(crate) fn qualified_name(&self, debug_info: &super::DebugInfo) -> Option<String> {
+        let name = self.attribute(debug_info, gimli::DW_AT_name)?;
+        let name = debug_info
+            .dwarf
+            .attr_string(&self.unit_info.unit, name.value())
+            .ok()?;
+        let mut path = vec![String::from_utf8_lossy(&name).into_owned()];
+
+        // The declaration of an inlined function is its abstract origin, or the specification of
+        // that.
+        let (unit, mut offset) = match (&self.specification_die, &self.abstract_die) {
+            (Some((unit, die)), _) | (None, Some((unit, die))) => (*unit, die.offset()),
+            (None, None) => (self.unit_info, self.function_die.offset()),
+        };
+        while let Some(parent) = unit.parent_offset(offset) {
+            offset = parent;
+            let Ok(entry) = unit.unit.entry(parent) else {
+                break;
+            };
+            if !matches!(
+                entry.tag(),
+                gimli::DW_TAG_namespace
+                    | gimli::DW_TAG_structure_type
+                    | gimli::DW_TAG_class_type
+                    | gimli::DW_TAG_union_type
+                    | gimli::DW_TAG_enumeration_type
+            ) {
+                continue;
+            }
+            let Some(Ok(name)) = entry
+                .attr_value(gimli::DW_AT_name)
+                .map(|name| debug_info.dwarf.attr_string(&unit.unit, name))
+            else {
+                continue;
+            };
+            let name = String::from_utf8_lossy(&name);
+            if !name.starts_with('{') {
+                path.push(name.into_owned());
+            }
+        }
+
+        path.reverse();
+        Some(path.join("::"))
+    }
+
+    /// Returns the symbol name of the function, e.g. `rust_begin_unwind` for a
+    /// `#[panic_handler]`, if the compiler recorded one.
+    pub(crate) fn linkage_name(&self, debug_info: &super::DebugInfo) -> Option<String> {
+        let name = self.attribute(debug_info, gimli::DW_AT_linkage_name)?;
+        let name = debug_info
+            .dwarf
+            .attr_string(&self.unit_info.unit, name.value())
+            .ok()?;
+        Some(String::from_utf8_lossy(&name).into_owned())
+    }
+
     /// Get the call site of an inlined function.
     ///
     /// If this function is not inlined (`is_inline()` returns false),
@@ -3,20 +3,30 @@
 //! The `debug` module contains various debug functionality, which can be
 //! used to implement a debugger based on `probe-rs`.
 
+/// Line and function coverage of the code a program executed.
+pub mod coverage;
 /// Debug information which is parsed from DWARF debugging information.
 pub mod debug_info;
 /// Stepping through a program during debug, at various granularities.
 pub mod debug_step;
+/// Tasks of the embassy async executor, read from its task pools.
+pub mod embassy;
 /// References to the DIE (debug information entry) of functions.
 pub mod function_die;
 /// Programming languages
 pub(crate) mod language;
 /// Target Register definitions, expanded from [`crate::core::registers::CoreRegister`] to include unwind specific information.
 pub mod registers;
+/// Threads of real-time operating systems, read from the data structures of the kernel.
+pub mod rtos;
 /// The source statement information used while identifying haltpoints for debug stepping and breakpoints.
 pub(crate) mod source_instructions;
 /// The stack frame information used while unwinding the stack from a specific program counter.
 pub mod stack_frame;
+/// Stack usage, measured by painting the stack before the program starts.
+pub mod stack_paint;
+/// Static variables of scalar types, resolved by their path for sampling their values.
+pub mod static_variable;
 /// Information about a Unit in the debug information.
 pub mod unit_info;
 /// Variable information used during debug.
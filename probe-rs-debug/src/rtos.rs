@@ -0,0 +1,114 @@
+//! Awareness of the threads of real-time operating systems.
+//!
+//! Each supported RTOS is recognised by the kernel symbols in the debug information of the
+//! program. Its threads are then read from the kernel's data structures in target memory, and
+//! the register context of each thread that is not running is rebuilt from its stack, so its
+//! call stack can be unwound like the one of the core.
+
+use std::fmt;
+
+use probe_rs::{CoreRegisters, MemoryInterface};
+use probe_rs_target::CoreType;
+use serde::Serialize;
+
+use crate::{DebugError, DebugInfo, DebugRegisters};
+
+pub(crate) mod freertos;
+pub(crate) mod zephyr;
+
+/// The scheduling state of an RTOS thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ThreadState {
+    /// The thread is executing on the core.
+    Running,
+    /// The thread is ready to run, and waits for the scheduler to pick it.
+    Ready,
+    /// The thread waits for an event or for a delay to pass.
+    Blocked,
+    /// The thread was suspended, and does not run until it is resumed.
+    Suspended,
+    /// The thread was deleted, but its resources were not freed yet.
+    Deleted,
+}
+
+impl fmt::Display for ThreadState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ThreadState::Running => "Running",
+            ThreadState::Ready => "Ready",
+            ThreadState::Blocked => "Blocked",
+            ThreadState::Suspended => "Suspended",
+            ThreadState::Deleted => "Deleted",
+        })
+    }
+}
+
+/// A thread of the RTOS, read from the data structures of the kernel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RtosThread {
+    /// An ID that is unique among the threads, like the address of the thread's control block.
+    pub id: u64,
+    /// The name of the thread.
+    pub name: String,
+    /// The priority of the thread, as the RTOS numbers it. In FreeRTOS higher values are more
+    /// urgent, in Zephyr lower values are.
+    pub priority: i32,
+    /// The scheduling state of the thread.
+    pub state: ThreadState,
+    /// The registers of the thread, rebuilt from the context saved on its stack.
+    ///
+    /// This is `None` for the running thread, whose registers are the ones of the core.
+    pub registers: Option<DebugRegisters>,
+    /// The stack of the thread, if the kernel keeps where it is.
+    pub stack: Option<ThreadStack>,
+}
+
+/// The memory the stack of an RTOS thread was allocated in. The stack grows down, from the top
+/// towards the bottom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadStack {
+    /// The lowest address of the stack.
+    pub bottom: u64,
+    /// The address above the highest address of the stack, if the kernel keeps it.
+    pub top: Option<u64>,
+    /// The stack pointer the context of the thread was last saved at. The stack was used at
+    /// least down to it, which bounds the search for the unused part when the top is not known.
+    pub stack_pointer: u64,
+    /// The byte the kernel fills the stack with when it creates the thread, if it is
+    /// configured to.
+    pub fill: u8,
+}
+
+/// Reads the threads of an RTOS from the target.
+pub trait RtosAwareness: Send + Sync {
+    /// The name of the RTOS.
+    fn name(&self) -> &'static str;
+
+    /// Reads the threads from the kernel's data structures. The registers of threads that are
+    /// not running are given in the registers of the core, `core_registers`.
+    ///
+    /// The core should be halted, so the data structures are consistent.
+    fn threads(
+        &self,
+        memory: &mut dyn MemoryInterface,
+        core_registers: &'static CoreRegisters,
+    ) -> Result<Vec<RtosThread>, DebugError>;
+}
+
+/// Looks for the kernel symbols of a supported RTOS in the debug information, and returns the
+/// awareness for the threads of the RTOS that runs on a core of type `core_type`.
+///
+/// Returns `Ok(None)` if the program does not use a supported RTOS.
+pub fn rtos_for_debug_info(
+    debug_info: &DebugInfo,
+    core_type: CoreType,
+) -> Result<Option<Box<dyn RtosAwareness>>, DebugError> {
+    if let Some(freertos) = freertos::FreeRtos::from_debug_info(debug_info, core_type)? {
+        return Ok(Some(Box::new(freertos)));
+    }
+    if let Some(zephyr) = zephyr::Zephyr::from_debug_info(debug_info, core_type)? {
+        return Ok(Some(Box::new(zephyr)));
+    }
+
+    Ok(None)
+}
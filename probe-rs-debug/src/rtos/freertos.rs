@@ -0,0 +1,511 @@
+//! Threads of FreeRTOS, on the Cortex-M ports of the kernel.
+
+use probe_rs::{CoreRegisters, MemoryInterface, RegisterId, RegisterValue};
+use probe_rs_target::CoreType;
+
+use super::{RtosAwareness, RtosThread, ThreadStack, ThreadState};
+use crate::exception_handling::armv6m_armv7m_shared::{ExcReturn, Xpsr};
+use crate::static_variable::TypeMember;
+use crate::{DebugError, DebugInfo, DebugRegisters};
+
+/// More tasks than this in a list means the list is corrupted, or not initialized yet.
+const MAX_TASKS_PER_LIST: u32 = 1024;
+
+/// `tskSTACK_FILL_BYTE`, which the kernel fills the stacks of new tasks with when it checks for
+/// stack overflows or reports the high-water mark of stacks.
+const STACK_FILL_BYTE: u8 = 0xa5;
+
+/// The task lists of the kernel that are optional, depending on its configuration.
+const OPTIONAL_TASK_LISTS: &[(&str, ThreadState)] = &[
+    ("xPendingReadyList", ThreadState::Ready),
+    ("xSuspendedTaskList", ThreadState::Suspended),
+    ("xTasksWaitingTermination", ThreadState::Deleted),
+];
+
+/// How the port of the kernel saves the context of a task on its stack, on top of the
+/// exception frame the core pushes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CortexMPort {
+    /// R4 to R11 are saved, like in the `ARM_CM0` and `ARM_CM3` ports.
+    Basic,
+    /// R4 to R11 and the EXC_RETURN value are saved, followed by S16 to S31 if the task used
+    /// the FPU, like in the `ARM_CM4F` and `ARM_CM7` ports.
+    Fpu,
+}
+
+/// The offsets of the fields the kernel keeps its tasks in, from the debug information.
+#[derive(Debug, Clone, PartialEq)]
+struct Layout {
+    /// `List_t::uxNumberOfItems`
+    list_length: u64,
+    /// `List_t::xListEnd`, the end marker of the list.
+    list_end: u64,
+    /// `List_t::xListEnd.pxNext`, the first item of the list.
+    list_first: u64,
+    /// `ListItem_t::pxNext`
+    item_next: u64,
+    /// `ListItem_t::pvOwner`, the TCB of the task.
+    item_owner: u64,
+    /// `TCB_t::pxTopOfStack`, where the context of the task was saved.
+    top_of_stack: u64,
+    /// `TCB_t::pxStack`, the lowest address of the stack of the task.
+    stack: u64,
+    /// `TCB_t::pxEndOfStack`, the highest word of the stack of the task, only kept with
+    /// `configRECORD_STACK_HIGH_ADDRESS`.
+    end_of_stack: Option<u64>,
+    /// `TCB_t::uxPriority`
+    priority: u64,
+    /// `TCB_t::pcTaskName`
+    name: TypeMember,
+    /// `TCB_t::xEventListItem.pxContainer`, the list of an event the task waits for.
+    event_list: Option<u64>,
+}
+
+impl Layout {
+    fn from_debug_info(debug_info: &DebugInfo) -> Result<Self, DebugError> {
+        let member = |type_name: &str, path: &str| {
+            debug_info
+                .resolve_type_member(type_name, path)
+                .map(|member| member.offset)
+        };
+
+        // The container of a list item was renamed in FreeRTOS 10.
+        let event_list = member("TCB_t", "xEventListItem.pxContainer")
+            .or_else(|_| member("TCB_t", "xEventListItem.pvContainer"))
+            .ok();
+
+        Ok(Self {
+            list_length: member("List_t", "uxNumberOfItems")?,
+            list_end: member("List_t", "xListEnd")?,
+            list_first: member("List_t", "xListEnd.pxNext")?,
+            item_next: member("ListItem_t", "pxNext")?,
+            item_owner: member("ListItem_t", "pvOwner")?,
+            top_of_stack: member("TCB_t", "pxTopOfStack")?,
+            stack: member("TCB_t", "pxStack")?,
+            end_of_stack: member("TCB_t", "pxEndOfStack").ok(),
+            priority: member("TCB_t", "uxPriority")?,
+            name: debug_info.resolve_type_member("TCB_t", "pcTaskName")?,
+            event_list,
+        })
+    }
+}
+
+/// The threads of FreeRTOS, which are called tasks by the kernel.
+#[derive(Debug)]
+pub(crate) struct FreeRtos {
+    /// The address of `pxCurrentTCB`.
+    current_tcb: u64,
+    /// The addresses of the lists the tasks are kept in, and the state of the tasks in them.
+    task_lists: Vec<(u64, ThreadState)>,
+    layout: Layout,
+    port: CortexMPort,
+}
+
+impl FreeRtos {
+    /// Finds the kernel in the debug information, if the program uses FreeRTOS.
+    pub(crate) fn from_debug_info(
+        debug_info: &DebugInfo,
+        core_type: CoreType,
+    ) -> Result<Option<Self>, DebugError> {
+        let Ok(current_tcb) = debug_info.resolve_static_location("pxCurrentTCB") else {
+            return Ok(None);
+        };
+        if !matches!(
+            core_type,
+            CoreType::Armv6m | CoreType::Armv7m | CoreType::Armv7em
+        ) {
+            tracing::warn!("FreeRTOS tasks are only supported on Armv6-M and Armv7-M cores");
+            return Ok(None);
+        }
+
+        let layout = Layout::from_debug_info(debug_info)?;
+
+        let list_size = debug_info.resolve_type_member("List_t", "")?.byte_size;
+        let ready_lists = debug_info.resolve_static_location("pxReadyTasksLists")?;
+        let mut task_lists = (0..ready_lists.byte_size / list_size)
+            .map(|priority| {
+                (
+                    ready_lists.address + priority * list_size,
+                    ThreadState::Ready,
+                )
+            })
+            .collect::<Vec<_>>();
+        for name in ["xDelayedTaskList1", "xDelayedTaskList2"] {
+            let list = debug_info.resolve_static_location(name)?;
+            task_lists.push((list.address, ThreadState::Blocked));
+        }
+        for (name, state) in OPTIONAL_TASK_LISTS {
+            if let Ok(list) = debug_info.resolve_static_location(name) {
+                task_lists.push((list.address, *state));
+            }
+        }
+
+        // Only the ports that save the FPU context need to enable it.
+        let port = if debug_info
+            .get_function_breakpoint_locations("vPortEnableVFP")
+            .is_ok_and(|locations| !locations.is_empty())
+        {
+            CortexMPort::Fpu
+        } else {
+            CortexMPort::Basic
+        };
+        tracing::debug!("Found FreeRTOS with {port:?} task contexts");
+
+        Ok(Some(Self {
+            current_tcb: current_tcb.address,
+            task_lists,
+            layout,
+            port,
+        }))
+    }
+
+    /// The TCBs of the tasks in the list at `list`.
+    fn list_tasks(
+        &self,
+        memory: &mut dyn MemoryInterface,
+        list: u64,
+    ) -> Result<Vec<u64>, DebugError> {
+        let length = memory.read_word_32(list + self.layout.list_length)?;
+        if length > MAX_TASKS_PER_LIST {
+            tracing::warn!("Skipping the task list at {list:#010x} with {length} tasks");
+            return Ok(Vec::new());
+        }
+
+        let end = list + self.layout.list_end;
+        let mut item = u64::from(memory.read_word_32(list + self.layout.list_first)?);
+        let mut tasks = Vec::new();
+        while item != end && item != 0 && tasks.len() < length as usize {
+            tasks.push(u64::from(
+                memory.read_word_32(item + self.layout.item_owner)?,
+            ));
+            item = u64::from(memory.read_word_32(item + self.layout.item_next)?);
+        }
+
+        Ok(tasks)
+    }
+
+    fn task(
+        &self,
+        memory: &mut dyn MemoryInterface,
+        core_registers: &'static CoreRegisters,
+        tcb: u64,
+        mut state: ThreadState,
+    ) -> Result<RtosThread, DebugError> {
+        let mut name = vec![0; self.layout.name.byte_size as usize];
+        memory.read_8(tcb + self.layout.name.offset, &mut name)?;
+        let length = name
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(name.len());
+
+        // Tasks that wait for an event without a timeout are kept in the suspended list.
+        if state == ThreadState::Suspended
+            && let Some(event_list) = self.layout.event_list
+            && memory.read_word_32(tcb + event_list)? != 0
+        {
+            state = ThreadState::Blocked;
+        }
+
+        let top_of_stack = u64::from(memory.read_word_32(tcb + self.layout.top_of_stack)?);
+        let registers = if state == ThreadState::Running {
+            None
+        } else {
+            Some(
+                self.port
+                    .saved_registers(memory, top_of_stack, core_registers)?,
+            )
+        };
+
+        let end_of_stack = match self.layout.end_of_stack {
+            Some(end_of_stack) => Some(u64::from(memory.read_word_32(tcb + end_of_stack)?) + 4),
+            None => None,
+        };
+        let stack = ThreadStack {
+            bottom: memory.read_word_32(tcb + self.layout.stack)?.into(),
+            top: end_of_stack,
+            stack_pointer: top_of_stack,
+            fill: STACK_FILL_BYTE,
+        };
+
+        Ok(RtosThread {
+            id: tcb,
+            name: String::from_utf8_lossy(&name[..length]).into_owned(),
+            priority: memory.read_word_32(tcb + self.layout.priority)? as i32,
+            state,
+            registers,
+            stack: Some(stack),
+        })
+    }
+}
+
+impl RtosAwareness for FreeRtos {
+    fn name(&self) -> &'static str {
+        "FreeRTOS"
+    }
+
+    fn threads(
+        &self,
+        memory: &mut dyn MemoryInterface,
+        core_registers: &'static CoreRegisters,
+    ) -> Result<Vec<RtosThread>, DebugError> {
+        let current_tcb = u64::from(memory.read_word_32(self.current_tcb)?);
+
+        let mut threads = Vec::<RtosThread>::new();
+        for &(list, state) in &self.task_lists {
+            for tcb in self.list_tasks(memory, list)? {
+                if threads.iter().any(|thread| thread.id == tcb) {
+                    continue;
+                }
+                let state = if tcb == current_tcb {
+                    ThreadState::Running
+                } else {
+                    state
+                };
+                threads.push(self.task(memory, core_registers, tcb, state)?);
+            }
+        }
+
+        // A task that deletes or suspends itself is in no list until the next context switch.
+        if current_tcb != 0 && threads.iter().all(|thread| thread.id != current_tcb) {
+            threads.push(self.task(memory, core_registers, current_tcb, ThreadState::Running)?);
+        }
+
+        Ok(threads)
+    }
+}
+
+impl CortexMPort {
+    /// Rebuilds the registers of a task from the context saved at the top of its stack.
+    fn saved_registers(
+        self,
+        memory: &mut dyn MemoryInterface,
+        top_of_stack: u64,
+        core_registers: &'static CoreRegisters,
+    ) -> Result<DebugRegisters, DebugError> {
+        let mut saved = [0; 9];
+        let saved = match self {
+            CortexMPort::Basic => &mut saved[..8],
+            CortexMPort::Fpu => &mut saved[..],
+        };
+        memory.read_32(top_of_stack, saved)?;
+
+        let mut exception_frame = top_of_stack + saved.len() as u64 * 4;
+        // S16 to S31 are saved by the kernel if the core pushed the extended frame with S0 to S15.
+        let extended_frame =
+            self == CortexMPort::Fpu && !ExcReturn(saved[8]).use_standard_stackframe();
+        if extended_frame {
+            exception_frame += 16 * 4;
+        }
+
+        let mut stacked = [0; 8];
+        memory.read_32(exception_frame, &mut stacked)?;
+        let [r0, r1, r2, r3, r12, lr, pc, xpsr] = stacked;
+
+        let mut stack_pointer = exception_frame + if extended_frame { 0x68 } else { 0x20 };
+        if Xpsr(xpsr).stack_was_realigned() {
+            stack_pointer += 4;
+        }
+
+        let mut values = vec![r0, r1, r2, r3];
+        values.extend_from_slice(&saved[..8]);
+        values.extend_from_slice(&[r12, stack_pointer as u32, lr, pc, xpsr]);
+
+        // R0 to R15 have the IDs 0 to 15, followed by xPSR.
+        Ok(DebugRegisters::from_core_registers(core_registers, |id| {
+            let RegisterId(id) = *id;
+            values
+                .get(usize::from(id))
+                .map(|&value| RegisterValue::U32(value))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use probe_rs::RegisterRole;
+    use probe_rs::architecture::arm::core::registers::cortex_m::CORTEX_M_CORE_REGISTERS;
+    use probe_rs::test::MockMemory;
+
+    use super::*;
+
+    /// The layout of FreeRTOS 10 on 32-bit targets, without the integrity checks of the lists.
+    fn layout() -> Layout {
+        Layout {
+            list_length: 0,
+            list_end: 8,
+            list_first: 12,
+            item_next: 4,
+            item_owner: 12,
+            top_of_stack: 0,
+            stack: 48,
+            end_of_stack: None,
+            priority: 44,
+            name: TypeMember {
+                offset: 52,
+                byte_size: 16,
+            },
+            event_list: Some(24 + 16),
+        }
+    }
+
+    fn words(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    /// Adds a list of 20 bytes at `list`, with the given items and TCBs.
+    fn add_list(memory: &mut MockMemory, list: u32, items: &[(u32, u32)]) {
+        let end = list + 8;
+        let first = items.first().map_or(end, |(item, _)| *item);
+        memory.add_range(
+            list.into(),
+            words(&[items.len() as u32, end, u32::MAX, first, end]),
+        );
+        for (index, (item, tcb)) in items.iter().enumerate() {
+            let next = items.get(index + 1).map_or(end, |(next, _)| *next);
+            memory.add_range((*item).into(), words(&[0, next, 0, *tcb, list]));
+        }
+    }
+
+    /// Adds a TCB at `tcb`, and a context like the `ARM_CM3` port saves it at `top_of_stack`,
+    /// 0x400 bytes above the bottom of the stack.
+    fn add_task(memory: &mut MockMemory, tcb: u32, name: &str, priority: u32, top_of_stack: u32) {
+        let mut data = words(&[top_of_stack]);
+        data.resize(44, 0);
+        data.extend(words(&[priority, top_of_stack - 0x400]));
+        let mut name = name.as_bytes().to_vec();
+        name.resize(16, 0);
+        data.extend(name);
+        memory.add_range(tcb.into(), data);
+
+        // R4 to R11, then R0 to R3, R12, LR, PC and xPSR.
+        memory.add_range(
+            top_of_stack.into(),
+            words(&[
+                4,
+                5,
+                6,
+                7,
+                8,
+                9,
+                10,
+                11,
+                0,
+                1,
+                2,
+                3,
+                12,
+                0x1000_0101,
+                0x1000_0200,
+                0x0100_0000,
+            ]),
+        );
+    }
+
+    fn freertos() -> FreeRtos {
+        FreeRtos {
+            current_tcb: 0x2000_0000,
+            task_lists: vec![
+                (0x2000_0100, ThreadState::Ready),
+                (0x2000_0200, ThreadState::Blocked),
+            ],
+            layout: layout(),
+            port: CortexMPort::Basic,
+        }
+    }
+
+    #[test]
+    fn tasks_from_lists() {
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, words(&[0x2000_1000]));
+        add_list(
+            &mut memory,
+            0x2000_0100,
+            &[(0x2000_0400, 0x2000_1000), (0x2000_0500, 0x2000_2000)],
+        );
+        add_list(&mut memory, 0x2000_0200, &[(0x2000_0600, 0x2000_3000)]);
+        add_task(&mut memory, 0x2000_1000, "main", 2, 0x2000_1800);
+        add_task(&mut memory, 0x2000_2000, "IDLE", 0, 0x2000_2800);
+        add_task(&mut memory, 0x2000_3000, "sensor", 1, 0x2000_3800);
+
+        let threads = freertos()
+            .threads(&mut memory, &CORTEX_M_CORE_REGISTERS)
+            .unwrap();
+        let summary = threads
+            .iter()
+            .map(|thread| {
+                (
+                    thread.id,
+                    thread.name.as_str(),
+                    thread.priority,
+                    thread.state,
+                    thread.registers.is_some(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (0x2000_1000, "main", 2, ThreadState::Running, false),
+                (0x2000_2000, "IDLE", 0, ThreadState::Ready, true),
+                (0x2000_3000, "sensor", 1, ThreadState::Blocked, true),
+            ]
+        );
+
+        let registers = threads[2].registers.as_ref().unwrap();
+        let value = |role| registers.get_register_value_by_role(&role).unwrap();
+        assert_eq!(value(RegisterRole::ProgramCounter), 0x1000_0200);
+        assert_eq!(value(RegisterRole::ReturnAddress), 0x1000_0101);
+        assert_eq!(value(RegisterRole::StackPointer), 0x2000_3840);
+        assert_eq!(value(RegisterRole::Core("R4")), 4);
+        assert_eq!(value(RegisterRole::Core("R12")), 12);
+
+        assert_eq!(
+            threads[2].stack,
+            Some(ThreadStack {
+                bottom: 0x2000_3400,
+                top: None,
+                stack_pointer: 0x2000_3800,
+                fill: STACK_FILL_BYTE,
+            })
+        );
+    }
+
+    #[test]
+    fn fpu_context() {
+        let mut memory = MockMemory::new();
+        // R4 to R11, an EXC_RETURN with an extended frame, S16 to S31, then the extended frame
+        // with the xPSR telling that the stack was realigned.
+        let mut stack = vec![4, 5, 6, 7, 8, 9, 10, 11, 0xffff_ffed];
+        stack.extend([0; 16]);
+        stack.extend([0, 1, 2, 3, 12, 0x1000_0101, 0x1000_0200, 0x0100_0200]);
+        stack.extend([0; 18]);
+        memory.add_range(0x2000_0000, words(&stack));
+
+        let registers = CortexMPort::Fpu
+            .saved_registers(&mut memory, 0x2000_0000, &CORTEX_M_CORE_REGISTERS)
+            .unwrap();
+        let value = |role| registers.get_register_value_by_role(&role).unwrap();
+        assert_eq!(value(RegisterRole::ProgramCounter), 0x1000_0200);
+        assert_eq!(value(RegisterRole::Core("R11")), 11);
+        assert_eq!(
+            value(RegisterRole::StackPointer),
+            0x2000_0000 + (9 + 16) * 4 + 0x68 + 4
+        );
+
+        // Without the FPU context, only the EXC_RETURN value is saved in addition.
+        let mut memory = MockMemory::new();
+        let mut stack = vec![4, 5, 6, 7, 8, 9, 10, 11, 0xffff_fffd];
+        stack.extend([0, 1, 2, 3, 12, 0x1000_0101, 0x1000_0200, 0x0100_0000]);
+        memory.add_range(0x2000_0000, words(&stack));
+
+        let registers = CortexMPort::Fpu
+            .saved_registers(&mut memory, 0x2000_0000, &CORTEX_M_CORE_REGISTERS)
+            .unwrap();
+        assert_eq!(
+            registers
+                .get_register_value_by_role(&RegisterRole::StackPointer)
+                .unwrap(),
+            0x2000_0000 + 9 * 4 + 0x20
+        );
+    }
+}
@@ -0,0 +1,757 @@
+//! Threads of Zephyr, on Cortex-M and 32-bit RISC-V cores.
+//!
+//! With `CONFIG_DEBUG_THREAD_INFO`, the kernel exports the offsets of the fields of its threads
+//! that debuggers need in `_kernel_thread_info_offsets`, and with `CONFIG_THREAD_MONITOR` it keeps
+//! all threads in the list at `_kernel.threads`.
+//!
+//! The kernel saves the callee-saved registers of a thread in the thread's `callee_saved` struct
+//! when it switches the thread out. The other registers are in the exception stack frame on the
+//! stack of the thread, if the thread was preempted by an interrupt.
+
+use std::ops::Range;
+
+use probe_rs::{CoreRegisters, MemoryInterface, RegisterId, RegisterValue};
+use probe_rs_target::CoreType;
+
+use super::{RtosAwareness, RtosThread, ThreadStack, ThreadState};
+use crate::exception_handling::armv6m_armv7m_shared::{ExcReturn, Xpsr};
+use crate::static_variable::TypeMember;
+use crate::{DebugError, DebugInfo, DebugRegisters};
+
+/// More threads than this in the list means the list is corrupted, or not initialized yet.
+const MAX_THREADS: usize = 1024;
+
+/// The version of the layout of `_kernel_thread_info_offsets` this supports.
+const THREAD_INFO_VERSION: u32 = 1;
+
+/// The value of the offsets of fields that the kernel does not have in its configuration.
+const THREAD_INFO_UNIMPLEMENTED: u32 = u32::MAX;
+
+/// The byte the kernel fills the stacks of new threads with, with `CONFIG_INIT_STACKS`.
+const STACK_FILL_BYTE: u8 = 0xaa;
+
+/// The indices of the offsets in `_kernel_thread_info_offsets`.
+mod offset {
+    pub(super) const VERSION: usize = 0;
+    pub(super) const K_CURR_THREAD: usize = 1;
+    pub(super) const K_THREADS: usize = 2;
+    pub(super) const T_NEXT_THREAD: usize = 4;
+    pub(super) const T_STATE: usize = 5;
+    pub(super) const T_PRIO: usize = 7;
+    pub(super) const T_STACK_PTR: usize = 8;
+    pub(super) const T_NAME: usize = 9;
+    pub(super) const T_ARM_EXC_RETURN: usize = 13;
+}
+
+/// The bits of `_thread_base::thread_state`.
+mod state {
+    pub(super) const PENDING: u8 = 1 << 1;
+    /// `_THREAD_SLEEPING`, which was `_THREAD_PRESTART` before Zephyr 3.7. Either way the thread
+    /// does not run yet.
+    pub(super) const SLEEPING: u8 = 1 << 2;
+    pub(super) const DEAD: u8 = 1 << 3;
+    pub(super) const SUSPENDED: u8 = 1 << 4;
+    pub(super) const ABORTING: u8 = 1 << 5;
+}
+
+/// The offsets of the fields of a thread, read from `_kernel_thread_info_offsets`.
+#[derive(Debug, Clone, PartialEq)]
+struct Offsets {
+    /// `_kernel.cpus[0].current`
+    current: u64,
+    /// `_kernel.threads`
+    threads: u64,
+    /// `k_thread::next_thread`
+    next_thread: u64,
+    /// `k_thread::base.thread_state`
+    state: u64,
+    /// `k_thread::base.prio`
+    priority: u64,
+    /// `k_thread::callee_saved.psp` on Cortex-M, `k_thread::callee_saved.sp` on RISC-V.
+    stack_pointer: u64,
+    /// `k_thread::name`, without `CONFIG_THREAD_NAME`.
+    name: Option<u64>,
+    /// `k_thread::arch.mode_exc_return`, only on Cortex-M with the FPU in use.
+    exc_return: Option<u64>,
+}
+
+impl Offsets {
+    fn read(memory: &mut dyn MemoryInterface, symbols: &Symbols) -> Result<Self, DebugError> {
+        let size_t_size = memory.read_word_8(symbols.size_t_size)?;
+        if size_t_size != 4 {
+            return Err(DebugError::Other(format!(
+                "Zephyr threads are only supported with a 4 byte size_t, not {size_t_size} bytes"
+            )));
+        }
+
+        let count = memory.read_word_32(symbols.num_offsets)? as usize;
+        let mut offsets = vec![0; count.min(64)];
+        memory.read_32(symbols.offsets, &mut offsets)?;
+
+        let version = offsets.get(offset::VERSION).copied().unwrap_or_default();
+        if version != THREAD_INFO_VERSION {
+            return Err(DebugError::Other(format!(
+                "Unsupported version {version} of `_kernel_thread_info_offsets`"
+            )));
+        }
+
+        let get = |index: usize| match offsets.get(index) {
+            Some(&offset) if offset != THREAD_INFO_UNIMPLEMENTED => Some(u64::from(offset)),
+            _ => None,
+        };
+        let required = |index: usize| {
+            get(index).ok_or_else(|| {
+                DebugError::Other(format!(
+                    "Offset {index} of `_kernel_thread_info_offsets` is not available, is `CONFIG_THREAD_MONITOR` enabled?"
+                ))
+            })
+        };
+
+        Ok(Self {
+            current: required(offset::K_CURR_THREAD)?,
+            threads: required(offset::K_THREADS)?,
+            next_thread: required(offset::T_NEXT_THREAD)?,
+            state: required(offset::T_STATE)?,
+            priority: required(offset::T_PRIO)?,
+            stack_pointer: required(offset::T_STACK_PTR)?,
+            name: get(offset::T_NAME),
+            exc_return: get(offset::T_ARM_EXC_RETURN),
+        })
+    }
+}
+
+/// The addresses of the symbols the kernel exports for debuggers.
+#[derive(Debug, Clone, PartialEq)]
+struct Symbols {
+    /// `_kernel`
+    kernel: u64,
+    /// `_kernel_thread_info_offsets`
+    offsets: u64,
+    /// `_kernel_thread_info_num_offsets`
+    num_offsets: u64,
+    /// `_kernel_thread_info_size_t_size`
+    size_t_size: u64,
+}
+
+/// How the context of a thread is saved, on the architecture of the core.
+#[derive(Debug, Clone, PartialEq)]
+enum Port {
+    /// R4 to R11 are saved in `callee_saved.v1` to `v8`, and PSP in `callee_saved.psp` points to
+    /// the exception stack frame that PendSV was entered with.
+    CortexM {
+        /// `k_thread::callee_saved.v1`
+        callee_saved: u64,
+    },
+    /// The callee-saved registers are saved in `callee_saved` by `z_riscv_switch`, which is
+    /// called from the interrupt wrapper if an interrupt preempted the thread.
+    Riscv {
+        /// The offsets of the members of `k_thread::callee_saved`, and the IDs of the registers
+        /// saved in them.
+        callee_saved: Vec<(u64, RegisterId)>,
+        /// The code of the interrupt wrapper, in `isr.S`.
+        isr_wrapper: Vec<Range<u64>>,
+        /// The offsets of the members of the exception stack frame, and the IDs of the registers
+        /// saved in them.
+        esf: Vec<(u64, RegisterId)>,
+        /// The offset of `mepc` in the exception stack frame.
+        esf_mepc: Option<u64>,
+        /// The size of the exception stack frame.
+        esf_size: u64,
+    },
+}
+
+/// The RISC-V registers in the callee-saved struct and the exception stack frame of Zephyr, and
+/// their IDs.
+const RISCV_REGISTERS: &[(&str, u16)] = &[
+    ("ra", 0x1001),
+    ("t0", 0x1005),
+    ("t1", 0x1006),
+    ("t2", 0x1007),
+    ("s0", 0x1008),
+    ("s1", 0x1009),
+    ("a0", 0x100A),
+    ("a1", 0x100B),
+    ("a2", 0x100C),
+    ("a3", 0x100D),
+    ("a4", 0x100E),
+    ("a5", 0x100F),
+    ("a6", 0x1010),
+    ("a7", 0x1011),
+    ("s2", 0x1012),
+    ("s3", 0x1013),
+    ("s4", 0x1014),
+    ("s5", 0x1015),
+    ("s6", 0x1016),
+    ("s7", 0x1017),
+    ("s8", 0x1018),
+    ("s9", 0x1019),
+    ("s10", 0x101A),
+    ("s11", 0x101B),
+    ("t3", 0x101C),
+    ("t4", 0x101D),
+    ("t5", 0x101E),
+    ("t6", 0x101F),
+];
+const RISCV_SP: RegisterId = RegisterId(0x1002);
+const RISCV_PC: RegisterId = RegisterId(0x7b1);
+
+impl Port {
+    fn from_debug_info(debug_info: &DebugInfo, core_type: CoreType) -> Result<Self, DebugError> {
+        match core_type {
+            CoreType::Armv6m | CoreType::Armv7m | CoreType::Armv7em | CoreType::Armv8m => {
+                Ok(Port::CortexM {
+                    callee_saved: debug_info
+                        .resolve_type_member("k_thread", "callee_saved.v1")?
+                        .offset,
+                })
+            }
+            CoreType::Riscv => {
+                let registers = |type_name: &str, prefix: &str| {
+                    RISCV_REGISTERS
+                        .iter()
+                        .filter_map(|&(name, id)| {
+                            let member = debug_info
+                                .resolve_type_member(type_name, &format!("{prefix}{name}"))
+                                .ok()?;
+                            Some((member.offset, RegisterId(id)))
+                        })
+                        .collect::<Vec<_>>()
+                };
+
+                // The exception stack frame was renamed in Zephyr 3.7.
+                let esf_type = ["arch_esf", "__esf"]
+                    .into_iter()
+                    .find(|name| debug_info.resolve_type_member(name, "").is_ok());
+                let (esf, esf_mepc, esf_size) = match esf_type {
+                    Some(esf_type) => (
+                        registers(esf_type, ""),
+                        debug_info
+                            .resolve_type_member(esf_type, "mepc")
+                            .ok()
+                            .map(|member| member.offset),
+                        debug_info.resolve_type_member(esf_type, "")?.byte_size,
+                    ),
+                    None => (Vec::new(), None, 0),
+                };
+
+                Ok(Port::Riscv {
+                    callee_saved: registers("k_thread", "callee_saved."),
+                    isr_wrapper: debug_info.compile_unit_ranges(|name| name.ends_with("isr.S")),
+                    esf,
+                    esf_mepc,
+                    esf_size,
+                })
+            }
+            _ => Err(DebugError::Other(format!(
+                "Zephyr threads are not supported on {core_type:?} cores"
+            ))),
+        }
+    }
+
+    /// Rebuilds the registers of a thread from the context saved when it was switched out.
+    fn saved_registers(
+        &self,
+        memory: &mut dyn MemoryInterface,
+        offsets: &Offsets,
+        thread: u64,
+        core_registers: &'static CoreRegisters,
+    ) -> Result<DebugRegisters, DebugError> {
+        let stack_pointer = u64::from(memory.read_word_32(thread + offsets.stack_pointer)?);
+
+        match self {
+            Port::CortexM { callee_saved } => {
+                let mut saved = [0; 8];
+                memory.read_32(thread + callee_saved, &mut saved)?;
+
+                let mut stacked = [0; 8];
+                memory.read_32(stack_pointer, &mut stacked)?;
+                let [r0, r1, r2, r3, r12, lr, pc, xpsr] = stacked;
+
+                // Only the low byte of EXC_RETURN is saved, which has the bit for the frame type.
+                let extended_frame = match offsets.exc_return {
+                    Some(exc_return) => {
+                        let exc_return = memory.read_word_8(thread + exc_return)?;
+                        !ExcReturn(u32::from(exc_return)).use_standard_stackframe()
+                    }
+                    None => false,
+                };
+                let mut sp = stack_pointer + if extended_frame { 0x68 } else { 0x20 };
+                if Xpsr(xpsr).stack_was_realigned() {
+                    sp += 4;
+                }
+
+                let mut values = vec![r0, r1, r2, r3];
+                values.extend_from_slice(&saved);
+                values.extend_from_slice(&[r12, sp as u32, lr, pc, xpsr]);
+
+                // R0 to R15 have the IDs 0 to 15, followed by xPSR.
+                Ok(DebugRegisters::from_core_registers(core_registers, |id| {
+                    let RegisterId(id) = *id;
+                    values
+                        .get(usize::from(id))
+                        .map(|&value| RegisterValue::U32(value))
+                }))
+            }
+            Port::Riscv {
+                callee_saved,
+                isr_wrapper,
+                esf,
+                esf_mepc,
+                esf_size,
+            } => {
+                let mut values = Vec::new();
+                for &(offset, id) in callee_saved {
+                    values.push((id, memory.read_word_32(thread + offset)?));
+                }
+                let return_address = values
+                    .iter()
+                    .find(|(id, _)| *id == RegisterId(0x1001))
+                    .map_or(0, |&(_, value)| value);
+
+                // A thread preempted by an interrupt was switched out by the interrupt wrapper,
+                // with the exception stack frame of the interrupt at the top of its stack.
+                let preempted = isr_wrapper
+                    .iter()
+                    .any(|range| range.contains(&u64::from(return_address)));
+                let (pc, sp) = match esf_mepc {
+                    Some(mepc) if preempted => {
+                        for &(offset, id) in esf {
+                            let value = memory.read_word_32(stack_pointer + offset)?;
+                            values.retain(|(saved, _)| *saved != id);
+                            values.push((id, value));
+                        }
+                        (
+                            memory.read_word_32(stack_pointer + mepc)?,
+                            stack_pointer + esf_size,
+                        )
+                    }
+                    // The thread called into the kernel, and resumes at the return address.
+                    _ => (return_address, stack_pointer),
+                };
+                values.push((RISCV_SP, sp as u32));
+                values.push((RISCV_PC, pc));
+
+                Ok(DebugRegisters::from_core_registers(core_registers, |id| {
+                    values
+                        .iter()
+                        .find(|(saved, _)| saved == id)
+                        .map(|&(_, value)| RegisterValue::U32(value))
+                }))
+            }
+        }
+    }
+}
+
+/// The threads of Zephyr.
+#[derive(Debug)]
+pub(crate) struct Zephyr {
+    symbols: Symbols,
+    /// `k_thread::name`, if the kernel keeps the names of its threads.
+    name: Option<TypeMember>,
+    /// `k_thread::stack_info.start` and `k_thread::stack_info.size`, with
+    /// `CONFIG_THREAD_STACK_INFO`.
+    stack_info: Option<(u64, u64)>,
+    port: Port,
+}
+
+impl Zephyr {
+    /// Finds the kernel in the debug information, if the program uses Zephyr.
+    pub(crate) fn from_debug_info(
+        debug_info: &DebugInfo,
+        core_type: CoreType,
+    ) -> Result<Option<Self>, DebugError> {
+        let Ok(offsets) = debug_info.resolve_static_location("_kernel_thread_info_offsets") else {
+            return Ok(None);
+        };
+        let port = match Port::from_debug_info(debug_info, core_type) {
+            Ok(port) => port,
+            Err(error) => {
+                tracing::warn!("{error}");
+                return Ok(None);
+            }
+        };
+
+        let symbol = |name: &str| {
+            debug_info
+                .resolve_static_location(name)
+                .map(|location| location.address)
+        };
+        let symbols = Symbols {
+            kernel: symbol("_kernel")?,
+            offsets: offsets.address,
+            num_offsets: symbol("_kernel_thread_info_num_offsets")?,
+            size_t_size: symbol("_kernel_thread_info_size_t_size")?,
+        };
+        tracing::debug!("Found Zephyr with {port:?} thread contexts");
+
+        let member = |path: &str| {
+            debug_info
+                .resolve_type_member("k_thread", path)
+                .map(|member| member.offset)
+        };
+        let stack_info = member("stack_info.start")
+            .and_then(|start| Ok((start, member("stack_info.size")?)))
+            .ok();
+
+        Ok(Some(Self {
+            symbols,
+            name: debug_info.resolve_type_member("k_thread", "name").ok(),
+            stack_info,
+            port,
+        }))
+    }
+
+    fn thread(
+        &self,
+        memory: &mut dyn MemoryInterface,
+        offsets: &Offsets,
+        core_registers: &'static CoreRegisters,
+        thread: u64,
+        current: u64,
+    ) -> Result<RtosThread, DebugError> {
+        let name = match (offsets.name, self.name) {
+            (Some(offset), Some(name)) => {
+                let mut bytes = vec![0; name.byte_size as usize];
+                memory.read_8(thread + offset, &mut bytes)?;
+                let length = bytes
+                    .iter()
+                    .position(|&byte| byte == 0)
+                    .unwrap_or(bytes.len());
+                String::from_utf8_lossy(&bytes[..length]).into_owned()
+            }
+            _ => String::new(),
+        };
+
+        let thread_state = memory.read_word_8(thread + offsets.state)?;
+        let state = if thread == current {
+            ThreadState::Running
+        } else if thread_state & (state::DEAD | state::ABORTING) != 0 {
+            ThreadState::Deleted
+        } else if thread_state & state::SUSPENDED != 0 {
+            ThreadState::Suspended
+        } else if thread_state & (state::PENDING | state::SLEEPING) != 0 {
+            ThreadState::Blocked
+        } else {
+            ThreadState::Ready
+        };
+
+        let registers = if state == ThreadState::Running {
+            None
+        } else {
+            Some(
+                self.port
+                    .saved_registers(memory, offsets, thread, core_registers)?,
+            )
+        };
+
+        let stack = match self.stack_info {
+            Some((start, size)) => {
+                let bottom = u64::from(memory.read_word_32(thread + start)?);
+                Some(ThreadStack {
+                    bottom,
+                    top: Some(bottom + u64::from(memory.read_word_32(thread + size)?)),
+                    stack_pointer: memory.read_word_32(thread + offsets.stack_pointer)?.into(),
+                    fill: STACK_FILL_BYTE,
+                })
+            }
+            None => None,
+        };
+
+        Ok(RtosThread {
+            id: thread,
+            name: if name.is_empty() {
+                format!("{thread:#010x}")
+            } else {
+                name
+            },
+            priority: i32::from(memory.read_word_8(thread + offsets.priority)? as i8),
+            state,
+            registers,
+            stack,
+        })
+    }
+}
+
+impl RtosAwareness for Zephyr {
+    fn name(&self) -> &'static str {
+        "Zephyr"
+    }
+
+    fn threads(
+        &self,
+        memory: &mut dyn MemoryInterface,
+        core_registers: &'static CoreRegisters,
+    ) -> Result<Vec<RtosThread>, DebugError> {
+        let offsets = Offsets::read(memory, &self.symbols)?;
+        let current = u64::from(memory.read_word_32(self.symbols.kernel + offsets.current)?);
+
+        let mut threads = Vec::<RtosThread>::new();
+        let mut thread = u64::from(memory.read_word_32(self.symbols.kernel + offsets.threads)?);
+        while thread != 0 {
+            if threads.len() >= MAX_THREADS || threads.iter().any(|known| known.id == thread) {
+                tracing::warn!("Stopping at the corrupted thread list of Zephyr");
+                break;
+            }
+            threads.push(self.thread(memory, &offsets, core_registers, thread, current)?);
+            thread = u64::from(memory.read_word_32(thread + offsets.next_thread)?);
+        }
+
+        Ok(threads)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use probe_rs::RegisterRole;
+    use probe_rs::architecture::arm::core::registers::cortex_m::CORTEX_M_CORE_REGISTERS;
+    use probe_rs::architecture::riscv::registers::RISCV_CORE_REGISTERS;
+    use probe_rs::test::MockMemory;
+
+    use super::*;
+
+    const KERNEL: u32 = 0x2000_0000;
+    const MAIN: u32 = 0x2000_1000;
+    const IDLE: u32 = 0x2000_2000;
+
+    fn words(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    fn symbols() -> Symbols {
+        Symbols {
+            kernel: KERNEL.into(),
+            offsets: 0x1000_0000,
+            num_offsets: 0x1000_0100,
+            size_t_size: 0x1000_0104,
+        }
+    }
+
+    /// Adds the offsets the kernel exports, and `_kernel` with its current thread and the first
+    /// thread in its list of threads.
+    fn add_kernel(memory: &mut MockMemory, stack_pointer: u32, current: u32, first: u32) {
+        memory.add_range(
+            0x1000_0000,
+            words(&[
+                THREAD_INFO_VERSION,
+                8,
+                0x18,
+                0x60,
+                0x70,
+                0x0d,
+                0x0c,
+                0x0e,
+                stack_pointer,
+                0x80,
+                0x58,
+                THREAD_INFO_UNIMPLEMENTED,
+                THREAD_INFO_UNIMPLEMENTED,
+                THREAD_INFO_UNIMPLEMENTED,
+            ]),
+        );
+        let mut counts = words(&[14]);
+        counts.push(4);
+        memory.add_range(0x1000_0100, counts);
+
+        let mut kernel = vec![0; 0x20];
+        kernel[8..12].copy_from_slice(&current.to_le_bytes());
+        kernel[0x18..0x1c].copy_from_slice(&first.to_le_bytes());
+        memory.add_range(KERNEL.into(), kernel);
+    }
+
+    /// Adds a `k_thread` at `thread`, with the words of its `callee_saved` at 0x20, and a stack of
+    /// 0x400 bytes at 0x400 bytes above the thread.
+    fn add_thread(
+        memory: &mut MockMemory,
+        thread: u32,
+        name: &str,
+        state: u8,
+        priority: i8,
+        callee_saved: &[u32],
+        next: u32,
+    ) {
+        let mut data = vec![0; 0x90];
+        data[0x0d] = state;
+        data[0x0e] = priority as u8;
+        let callee_saved = words(callee_saved);
+        data[0x20..0x20 + callee_saved.len()].copy_from_slice(&callee_saved);
+        data[0x70..0x74].copy_from_slice(&next.to_le_bytes());
+        data[0x74..0x7c].copy_from_slice(&words(&[thread + 0x400, 0x400]));
+        data[0x80..0x80 + name.len()].copy_from_slice(name.as_bytes());
+        memory.add_range(thread.into(), data);
+    }
+
+    fn zephyr(port: Port) -> Zephyr {
+        Zephyr {
+            symbols: symbols(),
+            name: Some(TypeMember {
+                offset: 0x80,
+                byte_size: 16,
+            }),
+            stack_info: Some((0x74, 0x78)),
+            port,
+        }
+    }
+
+    #[test]
+    fn threads_on_cortex_m() {
+        let mut memory = MockMemory::new();
+        // `callee_saved.psp` follows R4 to R11.
+        add_kernel(&mut memory, 0x40, MAIN, MAIN);
+        add_thread(&mut memory, MAIN, "main", 0, 0, &[0; 9], IDLE);
+        add_thread(
+            &mut memory,
+            IDLE,
+            "idle",
+            state::PENDING,
+            15,
+            &[4, 5, 6, 7, 8, 9, 10, 11, 0x2000_3000],
+            0,
+        );
+        // R0 to R3, R12, LR, PC and xPSR, stacked on entry to PendSV.
+        memory.add_range(
+            0x2000_3000,
+            words(&[0, 1, 2, 3, 12, 0x1000_0101, 0x1000_0200, 0x0100_0000]),
+        );
+
+        let threads = zephyr(Port::CortexM { callee_saved: 0x20 })
+            .threads(&mut memory, &CORTEX_M_CORE_REGISTERS)
+            .unwrap();
+        let summary = threads
+            .iter()
+            .map(|thread| {
+                (
+                    thread.id,
+                    thread.name.as_str(),
+                    thread.priority,
+                    thread.state,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (MAIN.into(), "main", 0, ThreadState::Running),
+                (IDLE.into(), "idle", 15, ThreadState::Blocked),
+            ]
+        );
+        assert!(threads[0].registers.is_none());
+        assert_eq!(
+            threads[1].stack,
+            Some(ThreadStack {
+                bottom: 0x2000_2400,
+                top: Some(0x2000_2800),
+                stack_pointer: 0x2000_3000,
+                fill: STACK_FILL_BYTE,
+            })
+        );
+
+        let registers = threads[1].registers.as_ref().unwrap();
+        let value = |role| {
+            registers
+                .get_register_by_role(&role)
+                .ok()
+                .and_then(|register| register.value)
+        };
+        assert_eq!(
+            value(RegisterRole::ProgramCounter),
+            Some(RegisterValue::U32(0x1000_0200))
+        );
+        assert_eq!(
+            value(RegisterRole::StackPointer),
+            Some(RegisterValue::U32(0x2000_3020))
+        );
+        assert_eq!(
+            value(RegisterRole::ReturnAddress),
+            Some(RegisterValue::U32(0x1000_0101))
+        );
+        assert_eq!(
+            registers
+                .get_register(RegisterId(11))
+                .and_then(|register| register.value),
+            Some(RegisterValue::U32(11))
+        );
+    }
+
+    #[test]
+    fn threads_on_risc_v() {
+        const RA: RegisterId = RegisterId(0x1001);
+        const S0: RegisterId = RegisterId(0x1008);
+        const A0: RegisterId = RegisterId(0x100A);
+
+        let mut memory = MockMemory::new();
+        // `callee_saved` starts with SP, followed by RA and S0.
+        // The thread list starts with `idle`, and no thread is current.
+        add_kernel(&mut memory, 0x20, 0, IDLE);
+        // The `main` thread was preempted by an interrupt, after which it was switched out.
+        add_thread(
+            &mut memory,
+            MAIN,
+            "main",
+            0,
+            -1,
+            &[0x2000_3000, 0x4000_0010, 0x800],
+            0,
+        );
+        // RA, A0 and MEPC in the exception stack frame of the interrupt.
+        memory.add_range(0x2000_3000, words(&[0x4200_0100, 0xa0, 0x4200_0200, 0]));
+        // The idle thread called into the kernel.
+        add_thread(
+            &mut memory,
+            IDLE,
+            "idle",
+            state::SUSPENDED,
+            15,
+            &[0x2000_4000, 0x4200_0300, 0x900],
+            MAIN,
+        );
+
+        let zephyr = zephyr(Port::Riscv {
+            callee_saved: vec![(0x24, RA), (0x28, S0)],
+            isr_wrapper: std::iter::once(0x4000_0000..0x4000_0100).collect(),
+            esf: vec![(0x0, RA), (0x4, A0)],
+            esf_mepc: Some(0x8),
+            esf_size: 0x10,
+        });
+        let threads = zephyr.threads(&mut memory, &RISCV_CORE_REGISTERS).unwrap();
+        assert_eq!(threads.len(), 2);
+        assert_eq!(threads[0].state, ThreadState::Suspended);
+        assert_eq!(threads[1].priority, -1);
+        assert_eq!(threads[1].state, ThreadState::Ready);
+
+        let value = |thread: &RtosThread, id| {
+            thread
+                .registers
+                .as_ref()
+                .unwrap()
+                .get_register(id)
+                .and_then(|register| register.value)
+        };
+
+        // The idle thread resumes where it called into the kernel.
+        assert_eq!(
+            value(&threads[0], RISCV_PC),
+            Some(RegisterValue::U32(0x4200_0300))
+        );
+        assert_eq!(
+            value(&threads[0], RISCV_SP),
+            Some(RegisterValue::U32(0x2000_4000))
+        );
+        assert_eq!(value(&threads[0], S0), Some(RegisterValue::U32(0x900)));
+
+        // The preempted thread resumes where the interrupt was taken.
+        assert_eq!(
+            value(&threads[1], RISCV_PC),
+            Some(RegisterValue::U32(0x4200_0200))
+        );
+        assert_eq!(
+            value(&threads[1], RISCV_SP),
+            Some(RegisterValue::U32(0x2000_3010))
+        );
+        assert_eq!(
+            value(&threads[1], RA),
+            Some(RegisterValue::U32(0x4200_0100))
+        );
+        assert_eq!(value(&threads[1], A0), Some(RegisterValue::U32(0xa0)));
+        assert_eq!(value(&threads[1], S0), Some(RegisterValue::U32(0x800)));
+    }
+}
@@ -0,0 +1,231 @@
+//! Measuring how much of a stack was used, by painting it with a known byte before the program
+//! starts.
+//!
+//! All supported architectures grow their stacks down, so the part of a stack that was never
+//! used is the part above its bottom that still holds the paint. The lowest address that does
+//! not is the high-water mark of the stack.
+
+use std::ops::Range;
+
+use object::{Object, ObjectSymbol};
+use probe_rs::MemoryInterface;
+
+use crate::DebugError;
+use crate::rtos::ThreadStack;
+
+/// The byte the stack is painted with.
+pub const STACK_PAINT_BYTE: u8 = 0xcc;
+
+/// The linker symbols at the top of the stack, the initial stack pointer.
+const STACK_TOP_SYMBOLS: &[&str] = &["_stack_start", "__StackTop", "_estack"];
+
+/// The linker symbols at the bottom of the stack, which it must not grow below.
+///
+/// Before `_stack_end`, `cortex-m-rt` let the stack grow down to the end of the static data.
+const STACK_BOTTOM_SYMBOLS: &[&str] = &[
+    "_stack_end",
+    "__stack_end",
+    "__StackLimit",
+    "__euninit",
+    "__ebss",
+];
+
+/// How much of the stack is read at once, while looking for the high-water mark.
+const CHUNK_SIZE: u64 = 1024;
+
+/// Finds the stack of the program in the linker symbols of an ELF file.
+///
+/// Returns `Ok(None)` if the file does not have the symbols for both ends of the stack.
+pub fn stack_region_from_elf(elf: &[u8]) -> Result<Option<Range<u64>>, DebugError> {
+    let file = object::File::parse(elf)?;
+    let symbol = |names: &[&str]| {
+        names.iter().find_map(|&name| {
+            file.symbols()
+                .find(|symbol| symbol.name() == Ok(name))
+                .map(|symbol| symbol.address())
+        })
+    };
+
+    let (Some(bottom), Some(top)) = (symbol(STACK_BOTTOM_SYMBOLS), symbol(STACK_TOP_SYMBOLS))
+    else {
+        return Ok(None);
+    };
+    if bottom >= top {
+        return Err(DebugError::Other(format!(
+            "The stack found in the linker symbols, {bottom:#010x}..{top:#010x}, is empty"
+        )));
+    }
+
+    Ok(Some(bottom..top))
+}
+
+/// Fills the word-aligned part of `region` with [`STACK_PAINT_BYTE`].
+///
+/// This has to happen before the program starts, while the stack is not in use yet.
+pub fn paint_stack(memory: &mut dyn MemoryInterface, region: Range<u64>) -> Result<(), DebugError> {
+    let Range { start, end } = painted_range(region);
+    if start >= end {
+        return Ok(());
+    }
+
+    let words = vec![u32::from_ne_bytes([STACK_PAINT_BYTE; 4]); ((end - start) / 4) as usize];
+    memory.write_32(start, &words)?;
+
+    Ok(())
+}
+
+/// The word-aligned part of `region`, which is the part [`paint_stack`] fills.
+fn painted_range(region: Range<u64>) -> Range<u64> {
+    region.start.next_multiple_of(4)..region.end & !3
+}
+
+/// How much of a stack was used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StackUsage {
+    /// The lowest address of the stack.
+    pub bottom: u64,
+    /// The address above the highest address of the stack, if it is known.
+    pub top: Option<u64>,
+    /// The lowest address the stack was used at.
+    pub high_water_mark: u64,
+}
+
+impl StackUsage {
+    /// Measures the usage of the stack in `region`, which was painted with [`paint_stack`].
+    ///
+    /// Only the word-aligned part of `region` is painted, so only that part is measured.
+    pub fn of_painted_stack(
+        memory: &mut dyn MemoryInterface,
+        region: Range<u64>,
+    ) -> Result<Self, DebugError> {
+        let Range { start, end } = painted_range(region);
+        let end = end.max(start);
+        Self::measure(memory, start, Some(end), end, STACK_PAINT_BYTE)
+    }
+
+    /// Measures the usage of the stack of an RTOS thread, from the fill of the kernel.
+    ///
+    /// If the kernel is not configured to fill the stacks of its threads, the stacks are
+    /// reported as fully used.
+    pub fn of_thread_stack(
+        memory: &mut dyn MemoryInterface,
+        stack: &ThreadStack,
+    ) -> Result<Self, DebugError> {
+        Self::measure(
+            memory,
+            stack.bottom,
+            stack.top,
+            stack.top.unwrap_or(stack.stack_pointer),
+            stack.fill,
+        )
+    }
+
+    /// Looks for the first byte from `bottom` up to `end` that is not `fill`.
+    fn measure(
+        memory: &mut dyn MemoryInterface,
+        bottom: u64,
+        top: Option<u64>,
+        end: u64,
+        fill: u8,
+    ) -> Result<Self, DebugError> {
+        let mut address = bottom;
+        let mut chunk = Vec::new();
+        while address < end {
+            chunk.resize(CHUNK_SIZE.min(end - address) as usize, 0);
+            memory.read_8(address, &mut chunk)?;
+
+            if let Some(used) = chunk.iter().position(|&byte| byte != fill) {
+                address += used as u64;
+                break;
+            }
+            address += chunk.len() as u64;
+        }
+
+        Ok(Self {
+            bottom,
+            top,
+            high_water_mark: address,
+        })
+    }
+
+    /// The bytes of the stack that were never used.
+    pub fn headroom(&self) -> u64 {
+        self.high_water_mark - self.bottom
+    }
+
+    /// The bytes of the stack that were used at the high-water mark, if the top of the stack is
+    /// known.
+    pub fn used(&self) -> Option<u64> {
+        self.top.map(|top| top - self.high_water_mark)
+    }
+
+    /// The size of the stack in bytes, if its top is known.
+    pub fn size(&self) -> Option<u64> {
+        self.top.map(|top| top - self.bottom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use probe_rs::test::MockMemory;
+
+    use super::*;
+
+    #[test]
+    fn usage_of_a_painted_stack() {
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, vec![0; 0x1000]);
+
+        paint_stack(&mut memory, 0x2000_0000..0x2000_1000).unwrap();
+        // The program used the stack down to 0x2000_0a02.
+        memory.write_8(0x2000_0a02, &[0; 0x5fe]).unwrap();
+
+        let usage = StackUsage::of_painted_stack(&mut memory, 0x2000_0000..0x2000_1000).unwrap();
+        assert_eq!(usage.high_water_mark, 0x2000_0a02);
+        assert_eq!(usage.headroom(), 0xa02);
+        assert_eq!(usage.used(), Some(0x5fe));
+        assert_eq!(usage.size(), Some(0x1000));
+    }
+
+    #[test]
+    fn usage_of_an_unaligned_painted_stack() {
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, vec![0; 0x100]);
+
+        paint_stack(&mut memory, 0x2000_0002..0x2000_00fe).unwrap();
+        memory.write_8(0x2000_0080, &[0; 0x7c]).unwrap();
+
+        let usage = StackUsage::of_painted_stack(&mut memory, 0x2000_0002..0x2000_00fe).unwrap();
+        assert_eq!(usage.bottom, 0x2000_0004);
+        assert_eq!(usage.high_water_mark, 0x2000_0080);
+        assert_eq!(usage.size(), Some(0xf8));
+    }
+
+    #[test]
+    fn overflowed_stack_has_no_headroom() {
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, vec![0; 0x100]);
+
+        let usage = StackUsage::of_painted_stack(&mut memory, 0x2000_0000..0x2000_0100).unwrap();
+        assert_eq!(usage.headroom(), 0);
+        assert_eq!(usage.used(), Some(0x100));
+    }
+
+    #[test]
+    fn usage_of_a_thread_stack_without_top() {
+        let mut memory = MockMemory::new();
+        let mut stack = vec![0xa5; 0x300];
+        stack.extend([0x12; 0x100]);
+        memory.add_range(0x2000_0000, stack);
+
+        let stack = ThreadStack {
+            bottom: 0x2000_0000,
+            top: None,
+            stack_pointer: 0x2000_0380,
+            fill: 0xa5,
+        };
+        let usage = StackUsage::of_thread_stack(&mut memory, &stack).unwrap();
+        assert_eq!(usage.headroom(), 0x300);
+        assert_eq!(usage.used(), None);
+    }
+}
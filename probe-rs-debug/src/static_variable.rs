@@ -0,0 +1,1030 @@
+use std::fmt;
+
+use gimli::{AttributeValue, RunTimeEndian, UnitOffset};
+
+use super::{DebugError, DebugInfo, debug_info::GimliReader, unit_info::UnitInfo};
+
+/// A static variable of a scalar type, resolved from the debug information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticVariable {
+    /// The fully qualified path of the variable, including the fields and indices selected in it.
+    pub path: String,
+    /// The address of the value.
+    pub address: u64,
+    /// The type of the value.
+    pub scalar_type: ScalarType,
+}
+
+/// A static variable of any type, resolved from the debug information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticLocation {
+    /// The fully qualified path of the variable, including the fields and indices selected in it.
+    pub path: String,
+    /// The address of the value.
+    pub address: u64,
+    /// The size of the value in bytes.
+    pub byte_size: u64,
+}
+
+/// A member of a type, resolved from the debug information.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeMember {
+    /// The offset of the member from the start of the type.
+    pub offset: u64,
+    /// The size of the member in bytes.
+    pub byte_size: u64,
+}
+
+/// A static variable found by the type of its value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FoundStatic {
+    /// The name of the static, preceded by the namespaces it is declared in.
+    pub(crate) name: Vec<String>,
+    /// The address of the static.
+    pub(crate) address: u64,
+    /// The name of the type of the static, without the namespaces it is declared in.
+    pub(crate) type_name: String,
+}
+
+/// The type of a value that can be read from the target in one access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalarType {
+    /// A boolean of the given size in bytes.
+    Bool(usize),
+    /// An unsigned integer of the given size in bytes. Characters, enumerations and pointers are
+    /// read as unsigned integers.
+    Unsigned(usize),
+    /// A signed integer of the given size in bytes.
+    Signed(usize),
+    /// A floating point number of the given size in bytes, either 4 or 8.
+    Float(usize),
+}
+
+impl ScalarType {
+    /// The size of the value in bytes.
+    pub fn byte_size(self) -> usize {
+        match self {
+            ScalarType::Bool(size)
+            | ScalarType::Unsigned(size)
+            | ScalarType::Signed(size)
+            | ScalarType::Float(size) => size,
+        }
+    }
+
+    /// The size of the value in bits.
+    pub fn bit_size(self) -> usize {
+        match self {
+            ScalarType::Bool(_) => 1,
+            _ => self.byte_size() * 8,
+        }
+    }
+
+    /// Decodes a value read from the target. `bytes` holds [`Self::byte_size`] bytes.
+    pub fn decode(self, bytes: &[u8], endianness: RunTimeEndian) -> ScalarValue {
+        let bytes = &bytes[..self.byte_size()];
+        let raw = match endianness {
+            RunTimeEndian::Little => bytes
+                .iter()
+                .rev()
+                .fold(0, |raw, byte| (raw << 8) | u64::from(*byte)),
+            RunTimeEndian::Big => bytes
+                .iter()
+                .fold(0, |raw, byte| (raw << 8) | u64::from(*byte)),
+        };
+
+        match self {
+            ScalarType::Bool(_) => ScalarValue::Bool(raw != 0),
+            ScalarType::Unsigned(_) => ScalarValue::Unsigned(raw),
+            ScalarType::Signed(size) => {
+                let shift = 64 - 8 * size as u32;
+                ScalarValue::Signed(((raw << shift) as i64) >> shift)
+            }
+            ScalarType::Float(4) => ScalarValue::Float(f32::from_bits(raw as u32).into()),
+            ScalarType::Float(_) => ScalarValue::Float(f64::from_bits(raw)),
+        }
+    }
+}
+
+/// A value read from the target.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScalarValue {
+    /// A boolean.
+    Bool(bool),
+    /// An unsigned integer.
+    Unsigned(u64),
+    /// A signed integer.
+    Signed(i64),
+    /// A floating point number.
+    Float(f64),
+}
+
+impl ScalarValue {
+    /// The raw bits of the value, as written to a waveform. Floating point values are truncated
+    /// to integers.
+    pub fn to_bits(self) -> u64 {
+        match self {
+            ScalarValue::Bool(value) => value.into(),
+            ScalarValue::Unsigned(value) => value,
+            ScalarValue::Signed(value) => value as u64,
+            ScalarValue::Float(value) => value as i64 as u64,
+        }
+    }
+}
+
+impl fmt::Display for ScalarValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScalarValue::Bool(value) => value.fmt(f),
+            ScalarValue::Unsigned(value) => value.fmt(f),
+            ScalarValue::Signed(value) => value.fmt(f),
+            ScalarValue::Float(value) => value.fmt(f),
+        }
+    }
+}
+
+/// A step from a value into one of its parts.
+enum Accessor<'a> {
+    Field(&'a str),
+    Index(u64),
+}
+
+impl DebugInfo {
+    /// Resolves `path` to a static variable of a scalar type.
+    ///
+    /// The path is the name of the static, optionally qualified by the modules or namespaces it is
+    /// declared in, followed by struct fields and array indices, e.g. `my_mod::STATE.counter` or
+    /// `BUFFER[3]`. The qualified name only needs to match the end of the full path, so the crate
+    /// name can be left out. Structs with a single field, like atomics and cells, are unwrapped to
+    /// the value they hold.
+    pub fn resolve_static_variable(&self, path: &str) -> Result<StaticVariable, DebugError> {
+        let (path, address, scalar_type) =
+            self.resolve_static(path, |unit_info, type_offset, accessors| {
+                self.resolve_scalar(unit_info, type_offset, accessors)
+            })?;
+        Ok(StaticVariable {
+            path,
+            address,
+            scalar_type,
+        })
+    }
+
+    /// Resolves `path` to a static variable of any type, like a struct or an array.
+    ///
+    /// The path is given like for [`Self::resolve_static_variable`], but structs with a single
+    /// field are not unwrapped.
+    pub fn resolve_static_location(&self, path: &str) -> Result<StaticLocation, DebugError> {
+        let (path, address, byte_size) =
+            self.resolve_static(path, |unit_info, type_offset, accessors| {
+                self.resolve_member(unit_info, type_offset, accessors)
+            })?;
+        Ok(StaticLocation {
+            path,
+            address,
+            byte_size,
+        })
+    }
+
+    /// Resolves `path` to a member of the struct, union or typedef named `type_name`.
+    ///
+    /// The path consists of fields and array indices like `header.flags` or `buffer[2]`, and is
+    /// empty to get the size of the type itself.
+    pub fn resolve_type_member(
+        &self,
+        type_name: &str,
+        path: &str,
+    ) -> Result<TypeMember, DebugError> {
+        let path = match path.chars().next() {
+            None | Some('[') => path.to_string(),
+            Some(_) => format!(".{path}"),
+        };
+        let accessors = parse_accessors(&path)?;
+
+        let mut last_error = None;
+        for unit_info in &self.unit_infos {
+            let mut entries = unit_info.unit.entries();
+            while let Ok(Some(entry)) = entries.next_dfs() {
+                if !matches!(
+                    entry.tag(),
+                    gimli::DW_TAG_structure_type
+                        | gimli::DW_TAG_class_type
+                        | gimli::DW_TAG_union_type
+                        | gimli::DW_TAG_typedef
+                ) || entry.attr_value(gimli::DW_AT_declaration).is_some()
+                    || self.entry_name(unit_info, entry).as_deref() != Some(type_name)
+                {
+                    continue;
+                }
+
+                // The type may only be declared in this unit, so keep looking in the others.
+                match self.resolve_member(unit_info, entry.offset(), &accessors) {
+                    Ok((offset, byte_size)) => return Ok(TypeMember { offset, byte_size }),
+                    Err(error) => last_error = Some(error),
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            DebugError::Other(format!(
+                "No type `{type_name}` found in the debug information"
+            ))
+        }))
+    }
+
+    /// Finds the statics at fixed addresses whose type has a name that `matches_type`.
+    pub(crate) fn find_statics(
+        &self,
+        matches_type: impl Fn(&str) -> bool,
+    ) -> Result<Vec<FoundStatic>, DebugError> {
+        let mut found = Vec::<FoundStatic>::new();
+        for unit_info in &self.unit_infos {
+            let mut entries = unit_info.unit.entries();
+            while let Ok(Some(entry)) = entries.next_dfs() {
+                if entry.tag() != gimli::DW_TAG_variable {
+                    continue;
+                }
+                let Some(address) = self.static_address(unit_info, entry)? else {
+                    continue;
+                };
+
+                let declaration = match entry.attr_value(gimli::DW_AT_specification) {
+                    Some(AttributeValue::UnitRef(offset)) => unit_info.unit.entry(offset)?,
+                    _ => entry.clone(),
+                };
+                let Some(type_offset) = type_ref(entry).or_else(|| type_ref(&declaration)) else {
+                    continue;
+                };
+                let type_offset = self.strip_modifiers(unit_info, type_offset)?;
+                let Some(type_name) =
+                    self.entry_name(unit_info, &unit_info.unit.entry(type_offset)?)
+                else {
+                    continue;
+                };
+                if !matches_type(&type_name) || found.iter().any(|f| f.address == address) {
+                    continue;
+                }
+                let Some(name) = self.qualified_variable_name(unit_info, &declaration) else {
+                    continue;
+                };
+
+                found.push(FoundStatic {
+                    name,
+                    address,
+                    type_name,
+                });
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// Finds the definition of the first struct, union or typedef whose name `matches_name`, and
+    /// returns the unit it is defined in, its offset in the unit and its name.
+    pub(crate) fn find_type(
+        &self,
+        matches_name: impl Fn(&str) -> bool,
+    ) -> Option<(&UnitInfo, UnitOffset, String)> {
+        for unit_info in &self.unit_infos {
+            let mut entries = unit_info.unit.entries();
+            while let Ok(Some(entry)) = entries.next_dfs() {
+                if !matches!(
+                    entry.tag(),
+                    gimli::DW_TAG_structure_type
+                        | gimli::DW_TAG_class_type
+                        | gimli::DW_TAG_union_type
+                        | gimli::DW_TAG_typedef
+                ) || entry.attr_value(gimli::DW_AT_declaration).is_some()
+                {
+                    continue;
+                }
+                if let Some(name) = self.entry_name(unit_info, entry)
+                    && matches_name(&name)
+                {
+                    return Some((unit_info, entry.offset(), name));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Finds the static variable named at the start of `path`, and resolves the fields and
+    /// indices after the name into its type with `resolve`.
+    fn resolve_static<T>(
+        &self,
+        path: &str,
+        resolve: impl Fn(&UnitInfo, UnitOffset, &[Accessor<'_>]) -> Result<(u64, T), DebugError>,
+    ) -> Result<(String, u64, T), DebugError> {
+        let split = path.find(['.', '[']).unwrap_or(path.len());
+        let (name, accessors) = path.split_at(split);
+        let name = name.split("::").collect::<Vec<_>>();
+        let accessors = parse_accessors(accessors)?;
+
+        let mut found = Vec::<(String, u64, T)>::new();
+        let mut last_error = None;
+        for unit_info in &self.unit_infos {
+            let mut entries = unit_info.unit.entries();
+            while let Ok(Some(entry)) = entries.next_dfs() {
+                if entry.tag() != gimli::DW_TAG_variable {
+                    continue;
+                }
+                let Some(address) = self.static_address(unit_info, entry)? else {
+                    continue;
+                };
+
+                // A C definition outside of its declaration refers to it for the name and type.
+                let declaration = match entry.attr_value(gimli::DW_AT_specification) {
+                    Some(AttributeValue::UnitRef(offset)) => unit_info.unit.entry(offset)?,
+                    _ => entry.clone(),
+                };
+                let Some(qualified_name) = self.qualified_variable_name(unit_info, &declaration)
+                else {
+                    continue;
+                };
+                if qualified_name.len() < name.len()
+                    || !qualified_name
+                        .iter()
+                        .rev()
+                        .zip(name.iter().rev())
+                        .all(|(qualified, name)| qualified == name)
+                {
+                    continue;
+                }
+
+                let Some(type_offset) = type_ref(entry).or_else(|| type_ref(&declaration)) else {
+                    continue;
+                };
+                match resolve(unit_info, type_offset, &accessors) {
+                    Ok((offset, resolved)) => {
+                        let address = address + offset;
+                        if found.iter().all(|(_, found, _)| *found != address) {
+                            found.push((
+                                format!("{}{}", qualified_name.join("::"), &path[split..]),
+                                address,
+                                resolved,
+                            ));
+                        }
+                    }
+                    Err(error) => last_error = Some(error),
+                }
+            }
+        }
+
+        match found.len() {
+            0 => Err(last_error.unwrap_or_else(|| {
+                DebugError::Other(format!(
+                    "No static variable `{}` found in the debug information",
+                    name.join("::")
+                ))
+            })),
+            1 => Ok(found.remove(0)),
+            _ => Err(DebugError::Other(format!(
+                "`{path}` is ambiguous, it matches {}",
+                found
+                    .iter()
+                    .map(|(path, _, _)| format!("`{path}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+
+    /// The address of a variable that lives at a fixed address.
+    fn static_address(
+        &self,
+        unit_info: &UnitInfo,
+        entry: &gimli::DebuggingInformationEntry<GimliReader>,
+    ) -> Result<Option<u64>, DebugError> {
+        let Some(AttributeValue::Exprloc(expression)) = entry.attr_value(gimli::DW_AT_location)
+        else {
+            return Ok(None);
+        };
+
+        let mut operations = expression.operations(unit_info.unit.encoding());
+        let address = match operations.next()? {
+            Some(gimli::Operation::Address { address }) => address,
+            Some(gimli::Operation::AddressIndex { index }) => {
+                self.dwarf.address(&unit_info.unit, index)?
+            }
+            _ => return Ok(None),
+        };
+
+        // Anything else in the expression makes the location more than a plain address.
+        if operations.next()?.is_some() {
+            return Ok(None);
+        }
+
+        Ok(Some(address))
+    }
+
+    /// The name of a variable, preceded by the names of the namespaces and types it is declared in.
+    fn qualified_variable_name(
+        &self,
+        unit_info: &UnitInfo,
+        entry: &gimli::DebuggingInformationEntry<GimliReader>,
+    ) -> Option<Vec<String>> {
+        let mut path = vec![self.entry_name(unit_info, entry)?];
+
+        let mut offset = entry.offset();
+        while let Some(parent) = unit_info.parent_offset(offset) {
+            offset = parent;
+            let Ok(entry) = unit_info.unit.entry(parent) else {
+                break;
+            };
+            if !matches!(
+                entry.tag(),
+                gimli::DW_TAG_namespace
+                    | gimli::DW_TAG_structure_type
+                    | gimli::DW_TAG_class_type
+                    | gimli::DW_TAG_union_type
+                    | gimli::DW_TAG_enumeration_type
+            ) {
+                continue;
+            }
+            if let Some(name) = self.entry_name(unit_info, &entry)
+                && !name.starts_with('{')
+            {
+                path.push(name);
+            }
+        }
+
+        path.reverse();
+        Some(path)
+    }
+
+    pub(crate) fn entry_name(
+        &self,
+        unit_info: &UnitInfo,
+        entry: &gimli::DebuggingInformationEntry<GimliReader>,
+    ) -> Option<String> {
+        let name = entry.attr_value(gimli::DW_AT_name)?;
+        let name = self.dwarf.attr_string(&unit_info.unit, name).ok()?;
+        Some(String::from_utf8_lossy(&name).into_owned())
+    }
+
+    /// Follows `accessors` into the type at `type_offset`, and returns the offset of the selected
+    /// value and its type.
+    fn resolve_scalar(
+        &self,
+        unit_info: &UnitInfo,
+        mut type_offset: UnitOffset,
+        accessors: &[Accessor<'_>],
+    ) -> Result<(u64, ScalarType), DebugError> {
+        let mut offset = 0;
+        let mut accessors = accessors.iter().peekable();
+        // The name of the typedef that led to an unnamed type, for the error messages.
+        let mut alias = None;
+
+        loop {
+            let entry = unit_info.unit.entry(type_offset)?;
+            let type_name = self
+                .entry_name(unit_info, &entry)
+                .or_else(|| alias.clone())
+                .unwrap_or_else(|| entry.tag().static_string().unwrap_or("?").to_string());
+
+            match (accessors.peek(), entry.tag()) {
+                (
+                    _,
+                    gimli::DW_TAG_typedef
+                    | gimli::DW_TAG_const_type
+                    | gimli::DW_TAG_volatile_type
+                    | gimli::DW_TAG_atomic_type,
+                ) => {
+                    type_offset = type_ref(&entry).ok_or_else(|| {
+                        DebugError::Other(format!("`{type_name}` has no underlying type"))
+                    })?;
+                    alias = Some(type_name);
+                    continue;
+                }
+                (None, gimli::DW_TAG_base_type) => {
+                    let size = byte_size(&entry)?;
+                    let scalar_type = match entry.attr_value(gimli::DW_AT_encoding) {
+                        Some(AttributeValue::Encoding(gimli::DW_ATE_boolean)) => {
+                            ScalarType::Bool(size)
+                        }
+                        Some(AttributeValue::Encoding(
+                            gimli::DW_ATE_signed | gimli::DW_ATE_signed_char,
+                        )) => ScalarType::Signed(size),
+                        Some(AttributeValue::Encoding(
+                            gimli::DW_ATE_unsigned
+                            | gimli::DW_ATE_unsigned_char
+                            | gimli::DW_ATE_UTF,
+                        )) => ScalarType::Unsigned(size),
+                        Some(AttributeValue::Encoding(gimli::DW_ATE_float))
+                            if size == 4 || size == 8 =>
+                        {
+                            ScalarType::Float(size)
+                        }
+                        _ => {
+                            return Err(DebugError::Other(format!(
+                                "Values of type `{type_name}` cannot be sampled"
+                            )));
+                        }
+                    };
+                    if !matches!(scalar_type.byte_size(), 1 | 2 | 4 | 8) {
+                        return Err(DebugError::Other(format!(
+                            "Values of type `{type_name}` cannot be sampled"
+                        )));
+                    }
+                    return Ok((offset, scalar_type));
+                }
+                (None, gimli::DW_TAG_pointer_type | gimli::DW_TAG_reference_type) => {
+                    let size = match entry.attr_value(gimli::DW_AT_byte_size) {
+                        Some(size) => byte_size_value(size, &type_name)?,
+                        None => unit_info.unit.encoding().address_size.into(),
+                    };
+                    return Ok((offset, ScalarType::Unsigned(size)));
+                }
+                (None, gimli::DW_TAG_enumeration_type) => match type_ref(&entry) {
+                    Some(underlying) => type_offset = underlying,
+                    None => return Ok((offset, ScalarType::Unsigned(byte_size(&entry)?))),
+                },
+                (
+                    accessor,
+                    gimli::DW_TAG_structure_type
+                    | gimli::DW_TAG_class_type
+                    | gimli::DW_TAG_union_type,
+                ) => {
+                    let member = match accessor {
+                        Some(Accessor::Field(field)) => {
+                            accessors.next();
+                            self.find_member(unit_info, type_offset, field)?
+                                .ok_or_else(|| {
+                                    DebugError::Other(format!(
+                                        "`{type_name}` has no field `{field}`"
+                                    ))
+                                })?
+                        }
+                        Some(Accessor::Index(_)) => {
+                            return Err(DebugError::Other(format!(
+                                "`{type_name}` is not an array"
+                            )));
+                        }
+                        None => self.single_member(unit_info, type_offset)?.ok_or_else(|| {
+                            DebugError::Other(format!(
+                                "`{type_name}` is not a scalar, select one of its fields"
+                            ))
+                        })?,
+                    };
+                    offset += member.0;
+                    type_offset = member.1;
+                    alias = None;
+                }
+                (Some(Accessor::Index(index)), gimli::DW_TAG_array_type) => {
+                    let index = *index;
+                    accessors.next();
+
+                    let (element_offset, element) =
+                        self.array_element(unit_info, type_offset, index, &type_name)?;
+                    offset += element_offset;
+                    type_offset = element;
+                    alias = None;
+                }
+                (Some(Accessor::Field(field)), _) => {
+                    return Err(DebugError::Other(format!(
+                        "`{type_name}` has no field `{field}`"
+                    )));
+                }
+                (Some(Accessor::Index(_)), _) => {
+                    return Err(DebugError::Other(format!("`{type_name}` is not an array")));
+                }
+                (None, _) => {
+                    return Err(DebugError::Other(format!(
+                        "Values of type `{type_name}` cannot be sampled"
+                    )));
+                }
+            }
+        }
+    }
+
+    /// Follows `accessors` into the type at `type_offset`, and returns the offset and the size of
+    /// the selected value, which can be of any type.
+    fn resolve_member(
+        &self,
+        unit_info: &UnitInfo,
+        mut type_offset: UnitOffset,
+        accessors: &[Accessor<'_>],
+    ) -> Result<(u64, u64), DebugError> {
+        let mut offset = 0;
+        for accessor in accessors {
+            let type_name = self
+                .entry_name(unit_info, &unit_info.unit.entry(type_offset)?)
+                .unwrap_or_else(|| "?".to_string());
+            type_offset = self.strip_modifiers(unit_info, type_offset)?;
+            let entry = unit_info.unit.entry(type_offset)?;
+
+            let (member_offset, member_type) = match (accessor, entry.tag()) {
+                (
+                    Accessor::Field(field),
+                    gimli::DW_TAG_structure_type
+                    | gimli::DW_TAG_class_type
+                    | gimli::DW_TAG_union_type,
+                ) => self
+                    .find_member(unit_info, type_offset, field)?
+                    .ok_or_else(|| {
+                        DebugError::Other(format!("`{type_name}` has no field `{field}`"))
+                    })?,
+                (Accessor::Index(index), gimli::DW_TAG_array_type) => {
+                    self.array_element(unit_info, type_offset, *index, &type_name)?
+                }
+                (Accessor::Field(field), _) => {
+                    return Err(DebugError::Other(format!(
+                        "`{type_name}` has no field `{field}`"
+                    )));
+                }
+                (Accessor::Index(_), _) => {
+                    return Err(DebugError::Other(format!("`{type_name}` is not an array")));
+                }
+            };
+            offset += member_offset;
+            type_offset = member_type;
+        }
+
+        Ok((offset, self.value_byte_size(unit_info, type_offset)?))
+    }
+
+    /// The offset and the type of the element at `index` of the array at `type_offset`.
+    fn array_element(
+        &self,
+        unit_info: &UnitInfo,
+        type_offset: UnitOffset,
+        index: u64,
+        type_name: &str,
+    ) -> Result<(u64, UnitOffset), DebugError> {
+        let entry = unit_info.unit.entry(type_offset)?;
+        let element = type_ref(&entry)
+            .ok_or_else(|| DebugError::Other(format!("`{type_name}` has no element type")))?;
+        if let Some(count) = self.array_count(unit_info, type_offset)?
+            && index >= count
+        {
+            return Err(DebugError::Other(format!(
+                "Index {index} is out of bounds of an array of {count} elements"
+            )));
+        }
+        let stride = match entry.attr_value(gimli::DW_AT_byte_stride) {
+            Some(stride) => byte_size_value(stride, type_name)? as u64,
+            None => self.type_byte_size(unit_info, element)?,
+        };
+
+        Ok((index * stride, element))
+    }
+
+    /// The size of a value of the type at `type_offset`, including arrays without a size of
+    /// their own.
+    fn value_byte_size(
+        &self,
+        unit_info: &UnitInfo,
+        type_offset: UnitOffset,
+    ) -> Result<u64, DebugError> {
+        let type_offset = self.strip_modifiers(unit_info, type_offset)?;
+        let entry = unit_info.unit.entry(type_offset)?;
+        if entry.tag() != gimli::DW_TAG_array_type
+            || entry.attr_value(gimli::DW_AT_byte_size).is_some()
+        {
+            return self.type_byte_size(unit_info, type_offset);
+        }
+
+        let element = type_ref(&entry)
+            .ok_or_else(|| DebugError::Other("An array has no element type".to_string()))?;
+        let count = self
+            .array_count(unit_info, type_offset)?
+            .ok_or_else(|| DebugError::Other("The length of an array is unknown".to_string()))?;
+        Ok(count * self.type_byte_size(unit_info, element)?)
+    }
+
+    /// Finds the member `name` of a struct or union, also looking into anonymous members.
+    fn find_member(
+        &self,
+        unit_info: &UnitInfo,
+        type_offset: UnitOffset,
+        name: &str,
+    ) -> Result<Option<(u64, UnitOffset)>, DebugError> {
+        let mut tree = unit_info.unit.entries_tree(Some(type_offset))?;
+        let mut children = tree.root()?.children();
+        while let Some(child) = children.next()? {
+            let entry = child.entry();
+            if entry.tag() != gimli::DW_TAG_member {
+                continue;
+            }
+            let Some(member_type) = type_ref(entry) else {
+                continue;
+            };
+            let member_offset = member_offset(entry)?;
+
+            match self.entry_name(unit_info, entry) {
+                Some(member_name) if member_name == name => {
+                    if entry.attr_value(gimli::DW_AT_bit_size).is_some() {
+                        return Err(DebugError::Other(format!(
+                            "`{name}` is a bit field, which cannot be sampled"
+                        )));
+                    }
+                    return Ok(Some((member_offset, member_type)));
+                }
+                Some(_) => {}
+                None => {
+                    let anonymous = self.strip_modifiers(unit_info, member_type)?;
+                    if let Some((offset, found)) = self.find_member(unit_info, anonymous, name)? {
+                        return Ok(Some((member_offset + offset, found)));
+                    }
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// The only member of a struct, if it has exactly one.
+    fn single_member(
+        &self,
+        unit_info: &UnitInfo,
+        type_offset: UnitOffset,
+    ) -> Result<Option<(u64, UnitOffset)>, DebugError> {
+        let mut tree = unit_info.unit.entries_tree(Some(type_offset))?;
+        let mut children = tree.root()?.children();
+        let mut single = None;
+        while let Some(child) = children.next()? {
+            let entry = child.entry();
+            match entry.tag() {
+                gimli::DW_TAG_member => {}
+                // The variants of a Rust enum.
+                gimli::DW_TAG_variant_part => return Ok(None),
+                _ => continue,
+            }
+            if single.is_some() || entry.attr_value(gimli::DW_AT_bit_size).is_some() {
+                return Ok(None);
+            }
+            let Some(member_type) = type_ref(entry) else {
+                return Ok(None);
+            };
+            single = Some((member_offset(entry)?, member_type));
+        }
+
+        Ok(single)
+    }
+
+    /// The number of elements of a one-dimensional array, if it is known.
+    fn array_count(
+        &self,
+        unit_info: &UnitInfo,
+        type_offset: UnitOffset,
+    ) -> Result<Option<u64>, DebugError> {
+        let mut tree = unit_info.unit.entries_tree(Some(type_offset))?;
+        let mut children = tree.root()?.children();
+        let mut count = None;
+        let mut dimensions = 0;
+        while let Some(child) = children.next()? {
+            let entry = child.entry();
+            if entry.tag() != gimli::DW_TAG_subrange_type {
+                continue;
+            }
+            dimensions += 1;
+
+            let lower_bound = entry
+                .attr_value(gimli::DW_AT_lower_bound)
+                .and_then(|bound| bound.udata_value())
+                .unwrap_or(0);
+            count = entry
+                .attr_value(gimli::DW_AT_count)
+                .and_then(|count| count.udata_value())
+                .or_else(|| {
+                    entry
+                        .attr_value(gimli::DW_AT_upper_bound)
+                        .and_then(|bound| bound.udata_value())
+                        .map(|upper_bound| upper_bound + 1 - lower_bound)
+                });
+        }
+
+        if dimensions > 1 {
+            return Err(DebugError::NotImplemented(
+                "Indexing arrays with more than one dimension",
+            ));
+        }
+
+        Ok(count)
+    }
+
+    /// The size of the type at `type_offset`, looking through typedefs and qualifiers.
+    fn type_byte_size(
+        &self,
+        unit_info: &UnitInfo,
+        type_offset: UnitOffset,
+    ) -> Result<u64, DebugError> {
+        let type_offset = self.strip_modifiers(unit_info, type_offset)?;
+        let entry = unit_info.unit.entry(type_offset)?;
+        match entry.attr_value(gimli::DW_AT_byte_size) {
+            Some(size) => Ok(byte_size_value(size, "type")? as u64),
+            None if matches!(
+                entry.tag(),
+                gimli::DW_TAG_pointer_type | gimli::DW_TAG_reference_type
+            ) =>
+            {
+                Ok(unit_info.unit.encoding().address_size.into())
+            }
+            None => Err(DebugError::Other(
+                "The size of a type is unknown".to_string(),
+            )),
+        }
+    }
+
+    /// Follows typedefs and type qualifiers to the type they refer to.
+    fn strip_modifiers(
+        &self,
+        unit_info: &UnitInfo,
+        mut type_offset: UnitOffset,
+    ) -> Result<UnitOffset, DebugError> {
+        loop {
+            let entry = unit_info.unit.entry(type_offset)?;
+            if !matches!(
+                entry.tag(),
+                gimli::DW_TAG_typedef
+                    | gimli::DW_TAG_const_type
+                    | gimli::DW_TAG_volatile_type
+                    | gimli::DW_TAG_atomic_type
+            ) {
+                return Ok(type_offset);
+            }
+            let Some(underlying) = type_ref(&entry) else {
+                return Ok(type_offset);
+            };
+            type_offset = underlying;
+        }
+    }
+}
+
+fn parse_accessors(path: &str) -> Result<Vec<Accessor<'_>>, DebugError> {
+    let invalid = || DebugError::Other(format!("`{path}` is not a field or index path"));
+
+    let mut accessors = path;
+    let mut parsed = Vec::new();
+    while !accessors.is_empty() {
+        if let Some(rest) = accessors.strip_prefix('.') {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(invalid());
+            }
+            parsed.push(Accessor::Field(&rest[..end]));
+            accessors = &rest[end..];
+        } else if let Some(rest) = accessors.strip_prefix('[') {
+            let (index, rest) = rest.split_once(']').ok_or_else(invalid)?;
+            parsed.push(Accessor::Index(
+                index.trim().parse().map_err(|_| invalid())?,
+            ));
+            accessors = rest;
+        } else {
+            return Err(invalid());
+        }
+    }
+
+    Ok(parsed)
+}
+
+pub(crate) fn type_ref(
+    entry: &gimli::DebuggingInformationEntry<GimliReader>,
+) -> Option<UnitOffset> {
+    match entry.attr_value(gimli::DW_AT_type) {
+        Some(AttributeValue::UnitRef(offset)) => Some(offset),
+        _ => None,
+    }
+}
+
+fn member_offset(entry: &gimli::DebuggingInformationEntry<GimliReader>) -> Result<u64, DebugError> {
+    match entry.attr_value(gimli::DW_AT_data_member_location) {
+        None => Ok(0),
+        Some(location) => location.udata_value().ok_or(DebugError::NotImplemented(
+            "Members at a location that is not a constant offset",
+        )),
+    }
+}
+
+fn byte_size(entry: &gimli::DebuggingInformationEntry<GimliReader>) -> Result<usize, DebugError> {
+    let size = entry
+        .attr_value(gimli::DW_AT_byte_size)
+        .ok_or_else(|| DebugError::Other("A type has no size".to_string()))?;
+    byte_size_value(size, "type")
+}
+
+fn byte_size_value(value: AttributeValue<GimliReader>, what: &str) -> Result<usize, DebugError> {
+    value
+        .udata_value()
+        .map(|size| size as usize)
+        .ok_or_else(|| DebugError::Other(format!("The size of the {what} is not a constant")))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn debug_info(elf: &str) -> DebugInfo {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("tests/debug-unwind-tests");
+        path.push(elf);
+        DebugInfo::from_file(&path).unwrap()
+    }
+
+    #[test]
+    fn c_struct_fields() {
+        let debug_info = debug_info("atsamd51p19a.elf");
+
+        // `x` is a member of an anonymous union.
+        let variable = debug_info.resolve_static_variable("foo.x").unwrap();
+        assert_eq!(variable.address, 0x2000_0000);
+        assert_eq!(variable.scalar_type, ScalarType::Unsigned(4));
+
+        let error = debug_info.resolve_static_variable("foo.y").unwrap_err();
+        assert_eq!(error.to_string(), "`Foo_t` has no field `y`");
+
+        let error = debug_info
+            .resolve_static_variable("foo.unsigned_bitfields.f")
+            .unwrap_err();
+        assert!(error.to_string().contains("bit field"), "{error}");
+
+        assert!(debug_info.resolve_static_variable("foo").is_err());
+        assert!(
+            debug_info
+                .resolve_static_variable("no_such_static")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn c_struct_layout() {
+        let debug_info = debug_info("atsamd51p19a.elf");
+
+        let foo = debug_info.resolve_static_location("foo").unwrap();
+        assert_eq!(foo.address, 0x2000_0000);
+        assert_eq!(
+            debug_info.resolve_type_member("Foo_t", "").unwrap(),
+            TypeMember {
+                offset: 0,
+                byte_size: foo.byte_size,
+            }
+        );
+        assert_eq!(
+            debug_info
+                .resolve_type_member("Foo_t", "unsigned_bitfields")
+                .unwrap(),
+            TypeMember {
+                offset: 4,
+                byte_size: 1,
+            }
+        );
+
+        // The size of an array is the size of its elements times their number.
+        let array = debug_info.resolve_static_location("foo_array").unwrap();
+        let element = debug_info.resolve_static_location("foo_array[1]").unwrap();
+        assert_eq!(array.byte_size, 2 * foo.byte_size);
+        assert_eq!(element.address, array.address + foo.byte_size);
+
+        assert!(debug_info.resolve_type_member("Foo_t", "y").is_err());
+        assert!(debug_info.resolve_type_member("No_such_t", "").is_err());
+    }
+
+    #[test]
+    fn rust_statics() {
+        let debug_info = debug_info("RP2040_full_unwind.elf");
+
+        // The atomic is unwrapped to the integer it holds, and the crate name can be left out.
+        let variable = debug_info
+            .resolve_static_variable("rom_data::memset4::ptr::CACHED_PTR")
+            .unwrap();
+        assert_eq!(variable.address, 0x2000_0070);
+        assert_eq!(variable.scalar_type, ScalarType::Unsigned(2));
+        assert_eq!(
+            variable.path,
+            "rp2040_hal::rom_data::memset4::ptr::CACHED_PTR"
+        );
+
+        let field = debug_info
+            .resolve_static_variable("rom_data::memset4::ptr::CACHED_PTR.v.value")
+            .unwrap();
+        assert_eq!(field.address, variable.address);
+
+        let error = debug_info
+            .resolve_static_variable("ptr::CACHED_PTR")
+            .unwrap_err();
+        assert!(error.to_string().contains("ambiguous"), "{error}");
+    }
+
+    #[test]
+    fn decode_values() {
+        use RunTimeEndian::{Big, Little};
+
+        assert_eq!(
+            ScalarType::Signed(2).decode(&[0xfe, 0xff], Little),
+            ScalarValue::Signed(-2)
+        );
+        assert_eq!(
+            ScalarType::Unsigned(2).decode(&[0x12, 0x34], Big),
+            ScalarValue::Unsigned(0x1234)
+        );
+        assert_eq!(
+            ScalarType::Float(4).decode(&1.5f32.to_le_bytes(), Little),
+            ScalarValue::Float(1.5)
+        );
+        assert_eq!(
+            ScalarType::Bool(1).decode(&[1], Little),
+            ScalarValue::Bool(true)
+        );
+    }
+}
@@ -1015,7 +1015,7 @@ impl UnitInfo {
     /// [e]: Self::extract_type()
     /// [p]: Self::process_tree()
     #[expect(clippy::too_many_arguments)]
-    fn extract_type(
+    pub(crate) fn extract_type(
         &self,
         debug_info: &DebugInfo,
         node: &gimli::DebuggingInformationEntry<GimliReader>,
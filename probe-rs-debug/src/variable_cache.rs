@@ -152,6 +152,15 @@ impl VariableCache {
         VariableCache::new(static_root_variable)
     }
 
+    /// Create a cache with a root variable named `name`, whose children are added by the caller.
+    pub(crate) fn new_named_cache(name: &str) -> Self {
+        let mut root_variable = Variable::new(None);
+        root_variable.variable_node_type = VariableNodeType::DoNotRecurse;
+        root_variable.name = VariableName::Named(name.to_string());
+
+        VariableCache::new(root_variable)
+    }
+
     /// Get the root variable of the cache
     pub fn root_variable(&self) -> &Variable {
         &self.variable_hash_map[&self.root_variable_key]
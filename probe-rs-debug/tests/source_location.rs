@@ -98,6 +98,50 @@ fn source_location() {
     }
 }
 
+#[test]
+fn function_breakpoint_locations() {
+    let di = DebugInfo::from_file("tests/probe-rs-debugger-test").unwrap();
+
+    // The first statement of the function, after the prologue.
+    let entry = di
+        .get_function_breakpoint_locations("probe_rs_debugger_test::__cortex_m_rt_main")
+        .unwrap();
+    assert_eq!(entry.len(), 1);
+    assert_eq!(entry[0].address, 0x80006EA);
+    assert_eq!(entry[0].source_location.line, Some(240));
+
+    // Every monomorphised and inlined instance, with or without the path.
+    let addresses = |name| {
+        di.get_function_breakpoint_locations(name)
+            .unwrap()
+            .iter()
+            .map(|breakpoint| breakpoint.address)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        addresses("core::ptr::write_volatile"),
+        [0x800221E, 0x800222C, 0x800223A, 0x8002D5E]
+    );
+    assert_eq!(
+        addresses("write_volatile"),
+        addresses("ptr::write_volatile")
+    );
+
+    // The panic handler is named `panic`, but exported as `rust_begin_unwind`.
+    let panic_handler = addresses("rust_begin_unwind");
+    assert_eq!(panic_handler.len(), 1);
+    assert!((0x800527E..0x8005306).contains(&panic_handler[0]));
+
+    assert!(
+        di.get_function_breakpoint_locations("ite_volatile")
+            .is_err()
+    );
+    assert!(
+        di.get_function_breakpoint_locations("no_such_function")
+            .is_err()
+    );
+}
+
 #[test]
 fn find_non_existing_unit_by_path() {
     let unit_path =
@@ -105,6 +105,31 @@ impl XtensaDebugSequence for ESP32 {
         self.disable_wdts(interface)
     }
 
+    fn trax_memory_setup(
+        &self,
+        interface: &mut XtensaCommunicationInterface,
+        core_id: usize,
+    ) -> Result<(), Error> {
+        // The two trace memory blocks are the last 32 KiB of SRAM2, which the firmware has to
+        // leave free, like with `CONFIG_ESP32_TRAX` in ESP-IDF. The PRO CPU records into block 0,
+        // the APP CPU into block 1.
+        const DPORT_BASE: u64 = 0x3ff00000;
+        const DPORT_TRACEMEM_MUX_MODE: u64 = DPORT_BASE | 0x070;
+        const DPORT_PRO_TRACEMEM_ENA: u64 = DPORT_BASE | 0x074;
+        const DPORT_APP_TRACEMEM_ENA: u64 = DPORT_BASE | 0x078;
+        const TRACEMEM_MUX_PROBLK0_APPBLK1: u32 = 0;
+
+        interface.write_word_32(DPORT_TRACEMEM_MUX_MODE, TRACEMEM_MUX_PROBLK0_APPBLK1)?;
+        let enable = if core_id == 0 {
+            DPORT_PRO_TRACEMEM_ENA
+        } else {
+            DPORT_APP_TRACEMEM_ENA
+        };
+        interface.write_word_32(enable, 1)?;
+
+        Ok(())
+    }
+
     fn reset_system_and_halt(
         &self,
         core: &mut XtensaCommunicationInterface,
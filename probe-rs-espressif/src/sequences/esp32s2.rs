@@ -164,6 +164,22 @@ impl XtensaDebugSequence for ESP32S2 {
         self.disable_wdts(interface)
     }
 
+    fn trax_memory_setup(
+        &self,
+        interface: &mut XtensaCommunicationInterface,
+        _core_id: usize,
+    ) -> Result<(), Error> {
+        // The trace memory is an internal SRAM block the firmware has to leave free, like with
+        // `CONFIG_ESP32S2_TRAX` in ESP-IDF, which uses block 20.
+        const SENSITIVE_BASE: u64 = 0x3f4c1000;
+        const PMS_OCCUPY_3: u64 = SENSITIVE_BASE | 0x0E0;
+        const TRACEMEM_BLOCK: u32 = 20;
+
+        interface.write_word_32(PMS_OCCUPY_3, 1 << (TRACEMEM_BLOCK - 4))?;
+
+        Ok(())
+    }
+
     fn reset_system_and_halt(
         &self,
         core: &mut XtensaCommunicationInterface,
@@ -129,6 +129,32 @@ impl XtensaDebugSequence for ESP32S3 {
         self.disable_wdts(interface)
     }
 
+    fn trax_memory_setup(
+        &self,
+        interface: &mut XtensaCommunicationInterface,
+        core_id: usize,
+    ) -> Result<(), Error> {
+        // The trace memory is an internal SRAM block the firmware has to leave free, like with
+        // `CONFIG_ESP32S3_TRAX` in ESP-IDF, which uses block 22 for core 0 and 23 for core 1.
+        // Each core selects its block with a bit for the 64 KiB section of the SRAM, and the
+        // index of the block within the section.
+        const SENSITIVE_BASE: u64 = 0x600c1000;
+        const INTERNAL_SRAM_USAGE_2: u64 = SENSITIVE_BASE | 0x018;
+
+        let (block, section_shift, alloc_shift) = if core_id == 0 {
+            (22 - 2, 0, 14)
+        } else {
+            (23 - 2, 7, 16)
+        };
+        let mask = (0x7F << section_shift) | (0x3 << alloc_shift);
+        let bits = (1 << (section_shift + block / 4)) | ((block % 4) << alloc_shift);
+
+        let usage = interface.read_word_32(INTERNAL_SRAM_USAGE_2)?;
+        interface.write_word_32(INTERNAL_SRAM_USAGE_2, (usage & !mask) | bits)?;
+
+        Ok(())
+    }
+
     fn reset_system_and_halt(
         &self,
         core: &mut XtensaCommunicationInterface,
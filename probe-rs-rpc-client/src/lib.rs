@@ -17,6 +17,7 @@ use tokio::{
 };
 
 use std::{
+    ops::Range,
     path::{Path, PathBuf},
     sync::Arc,
     time::Duration,
@@ -27,24 +28,33 @@ mod upload_cache;
 use upload_cache::UploadCache;
 pub use upload_cache::{ContentHash, ResolvedUpload};
 
+use probe_rs_rpc::branch_trace::{StartBranchTraceRequest, TakeBranchTraceRequest, WireBranch};
 use probe_rs_rpc::breakpoints::{
-    BreakpointResolution, ResolveSourceBreakpointsRequest, ResolveSourceLocationsRequest,
-    SourceBreakpointLocation, WireSourceLocation,
+    BreakpointResolution, BreakpointTarget, ResolveSourceBreakpointsRequest,
+    ResolveSourceLocationsRequest, WireSourceLocation,
 };
 use probe_rs_rpc::chip::{ChipData, ChipFamily, ChipInfoRequest, LoadChipFamilyRequest};
 use probe_rs_rpc::core_ops::{
     CoreAccessRequest, CoreBreakpointsRequest, CoreDumpRequest, CoreHaltRequest,
-    CoreReadRegistersRequest, CoreVectorCatchRequest, CoreWriteRegRequest,
+    CoreReadRegistersRequest, CoreVectorCatchRequest, CoreWatchpointRequest, CoreWriteRegRequest,
     HandleSemihostingRequest, HandleSemihostingResult, StepRequest, StepResponse, WireCoreDump,
     WireCoreInformation, WireCoreMetadata, WireCoreStatus, WireRegisterId, WireRegisterReadResult,
-    WireRegisterValue, WireSteppingMode, WireVectorCatchCondition,
+    WireRegisterValue, WireSteppingMode, WireVectorCatchCondition, WireWatchpoint,
+};
+use probe_rs_rpc::cores::{
+    CoresRequest, CoresStatusMap, CreateRunControlGroupRequest, HaltCoresRequest,
+    RemoveRunControlGroupRequest,
+};
+use probe_rs_rpc::coverage::{
+    CoverageMethod, StartCoverageRequest, TakeCoverageRequest, WireCoverage,
 };
-use probe_rs_rpc::cores::{CoresRequest, CoresStatusMap, HaltCoresRequest};
 use probe_rs_rpc::debug_vars::{
-    ClearCoreDebugStateRequest, EvaluateRequest, LoadSvdRequest, ScopesRequest, SetVariableRequest,
-    VariablesRequest, WireEvaluateResponse, WireScope, WireSetVariableResponse, WireVariable,
+    ClearCoreDebugStateRequest, DataBreakpointInfoRequest, EvaluateRequest, LoadSvdRequest,
+    ScopesRequest, SetVariableRequest, VariablesRequest, WireDataBreakpointInfo,
+    WireEvaluateResponse, WireScope, WireSetVariableResponse, WireVariable,
 };
 use probe_rs_rpc::disassemble::{DisassembleRequest, WireDisassembledInstruction};
+use probe_rs_rpc::embassy::{EmbassyTasksRequest, WireEmbassyTasks};
 use probe_rs_rpc::file::{AppendFileRequest, TempFile};
 use probe_rs_rpc::flash::{
     BootInfo, BootRequest, BuildRequest, BuildResult, DownloadOptions, EraseAllRequest,
@@ -55,6 +65,7 @@ use probe_rs_rpc::format::FormatOptions;
 use probe_rs_rpc::info::{
     InfoEvent, TargetInfoRequest, TargetMetadataRequest, WireSessionTargetMetadata,
 };
+use probe_rs_rpc::itm::{ItmEvent, ItmSink, ReadItmRequest, StartItmRequest};
 use probe_rs_rpc::memory::{ReadBytesRequest, ReadMemoryRequest, WriteMemoryRequest};
 use probe_rs_rpc::monitor::{
     MonitorExitReason, MonitorMode, MonitorOptions, MonitorRequest, RttEvent, SemihostingEvent,
@@ -64,12 +75,14 @@ use probe_rs_rpc::probe::{
     SelectProbeResult,
 };
 use probe_rs_rpc::reset::{ResetCoreAndHaltRequest, ResetCoreRequest};
+use probe_rs_rpc::rtos::{RtosThreadsRequest, WireRtosThreads};
 use probe_rs_rpc::rtt_client::{
     CreateRttClientRequest, PollRttUpRequest, RttChannelRequest, RttChannels, RttClientData,
     RttDownRequest, RttPollResult, ScanRegion,
 };
 use probe_rs_rpc::rtt_config::RttChannelConfig;
 use probe_rs_rpc::semihosting_options::SemihostingOptions;
+use probe_rs_rpc::stack_paint::{StackUsageRequest, WireStackUsageReport};
 use probe_rs_rpc::stack_trace::{
     LoadDebugInfoRequest, RichStackTraces, StackTraces, TakeRichStackTraceRequest,
     TakeStackTraceRequest,
@@ -80,23 +93,29 @@ use probe_rs_rpc::test::{
 use probe_rs_rpc::transport::memory::{PostcardReceiver, PostcardSender, WireRx, WireTx};
 use probe_rs_rpc::{
     AttachEndpoint, BootEndpoint, BuildEndpoint, ChipInfoEndpoint, CleanUpRttEndpoint,
-    ClearCoreDebugStateEndpoint, ClearRttControlBlockEndpoint, CoreClearHwBpsEndpoint,
-    CoreDumpEndpoint, CoreEnableVcEndpoint, CoreHaltEndpoint, CoreMetadataEndpoint,
-    CoreReadRegistersEndpoint, CoreRunEndpoint, CoreSetHwBpsEndpoint, CoreStatusEndpoint,
+    ClearCoreDebugStateEndpoint, ClearRttControlBlockEndpoint, CoreClearFlashBpsEndpoint,
+    CoreClearHwBpsEndpoint, CoreClearHwWpEndpoint, CoreClearSwBpsEndpoint, CoreDisableVcEndpoint,
+    CoreDumpEndpoint, CoreEnableVcEndpoint, CoreHaltEndpoint, CoreHitWpEndpoint,
+    CoreMetadataEndpoint, CoreReadRegistersEndpoint, CoreRunEndpoint, CoreSetFlashBpsEndpoint,
+    CoreSetHwBpsEndpoint, CoreSetHwWpEndpoint, CoreSetSwBpsEndpoint, CoreStatusEndpoint,
     CoreStepEndpoint, CoreWriteRegEndpoint, CoresStatusEndpoint, CreateRttClientEndpoint,
-    CreateTempFileEndpoint, DisassembleEndpoint, EraseAllEndpoint, EraseRangeEndpoint,
+    CreateRunControlGroupEndpoint, CreateTempFileEndpoint, DataBreakpointInfoEndpoint,
+    DisassembleEndpoint, EmbassyTasksEndpoint, EraseAllEndpoint, EraseRangeEndpoint,
     EvaluateEndpoint, FlashEndpoint, GetRttChannelsEndpoint, HaltCoresEndpoint,
-    HandleSemihostingEndpoint, ListChipFamiliesEndpoint, ListProbesEndpoint, ListTestsEndpoint,
-    LoadChipFamilyEndpoint, LoadDebugInfoEndpoint, LoadRegionEndpoint, LoadSvdEndpoint,
-    MonitorEndpoint, NewFlashLoaderEndpoint, PollRttUpEndpoint, ProgressEventTopic,
-    ReadBytesEndpoint, ReadMemory8Endpoint, ReadMemory16Endpoint, ReadMemory32Endpoint,
-    ReadMemory64Endpoint, ResetCoreAndHaltEndpoint, ResetCoreEndpoint,
+    HandleSemihostingEndpoint, ItmTopic, ListChipFamiliesEndpoint, ListProbesEndpoint,
+    ListTestsEndpoint, LoadChipFamilyEndpoint, LoadDebugInfoEndpoint, LoadRegionEndpoint,
+    LoadSvdEndpoint, MonitorEndpoint, NewFlashLoaderEndpoint, PollRttUpEndpoint,
+    ProgressEventTopic, ReadBytesEndpoint, ReadItmEndpoint, ReadMemory8Endpoint,
+    ReadMemory16Endpoint, ReadMemory32Endpoint, ReadMemory64Endpoint,
+    RemoveRunControlGroupEndpoint, ResetCoreAndHaltEndpoint, ResetCoreEndpoint,
     ResolveSourceBreakpointsEndpoint, ResolveSourceLocationsEndpoint, ResumeCoresEndpoint,
-    RpcError, RpcResult, RttDownEndpoint, RttTopic, RunTestEndpoint, ScopesEndpoint,
-    SelectProbeEndpoint, SemihostingTopic, SetVariableEndpoint, TakeRichStackTraceEndpoint,
-    TakeStackTraceEndpoint, TargetInfoDataTopic, TargetInfoEndpoint, TargetMetadataEndpoint,
-    TempFileDataEndpoint, TestKickoffEndpoint, TokioSpawner, VariablesEndpoint, VerifyEndpoint,
-    WriteMemory8Endpoint, WriteMemory16Endpoint, WriteMemory32Endpoint, WriteMemory64Endpoint,
+    RpcError, RpcResult, RtosThreadsEndpoint, RttDownEndpoint, RttTopic, RunTestEndpoint,
+    ScopesEndpoint, SelectProbeEndpoint, SemihostingTopic, SetVariableEndpoint, StackUsageEndpoint,
+    StartBranchTraceEndpoint, StartCoverageEndpoint, StartItmEndpoint, TakeBranchTraceEndpoint,
+    TakeCoverageEndpoint, TakeRichStackTraceEndpoint, TakeStackTraceEndpoint, TargetInfoDataTopic,
+    TargetInfoEndpoint, TargetMetadataEndpoint, TempFileDataEndpoint, TestKickoffEndpoint,
+    TokioSpawner, VariablesEndpoint, VerifyEndpoint, WriteMemory8Endpoint, WriteMemory16Endpoint,
+    WriteMemory32Endpoint, WriteMemory64Endpoint,
 };
 use probe_rs_rpc::{FlashLoader, Key, RttClient, Session};
 
@@ -668,6 +687,96 @@ impl SessionInterface {
             .await
     }
 
+    /// Start recording branches with the MTB into `buffer`, an unused region of the MTB SRAM.
+    pub async fn start_branch_trace(&self, buffer: Range<u64>) -> Result<(), ClientError> {
+        self.client
+            .send_resp::<StartBranchTraceEndpoint, _>(&StartBranchTraceRequest {
+                sessid: self.sessid,
+                buffer,
+            })
+            .await
+    }
+
+    /// Stop the branch trace and read the recorded branches, oldest first.
+    pub async fn take_branch_trace(&self) -> Result<Vec<WireBranch>, ClientError> {
+        self.client
+            .send_resp::<TakeBranchTraceEndpoint, _>(&TakeBranchTraceRequest {
+                sessid: self.sessid,
+            })
+            .await
+    }
+
+    /// Start recording the code executed by the following runs and tests, for coverage reports.
+    ///
+    /// `code` lists the address ranges of the program code, which the ETM trace is decoded
+    /// with. Returns the method used to record the executed code.
+    pub async fn start_coverage(
+        &self,
+        method: CoverageMethod,
+        mtb_buffer: Option<Range<u64>>,
+        code: Vec<Range<u64>>,
+    ) -> Result<CoverageMethod, ClientError> {
+        self.client
+            .send_resp::<StartCoverageEndpoint, _>(&StartCoverageRequest {
+                sessid: self.sessid,
+                method,
+                mtb_buffer,
+                code,
+            })
+            .await
+    }
+
+    /// Read the code executed since coverage was started or last taken.
+    pub async fn take_coverage(&self) -> Result<WireCoverage, ClientError> {
+        self.client
+            .send_resp::<TakeCoverageEndpoint, _>(&TakeCoverageRequest {
+                sessid: self.sessid,
+            })
+            .await
+    }
+
+    /// Start tracing the ITM of `core` into `sink`.
+    pub async fn start_itm(&self, core: u32, sink: ItmSink) -> Result<(), ClientError> {
+        self.client
+            .send_resp::<StartItmEndpoint, _>(&StartItmRequest {
+                sessid: self.sessid,
+                core,
+                sink,
+            })
+            .await
+    }
+
+    /// Read the ITM trace data captured since tracing was started or last read.
+    pub async fn read_itm(&self) -> Result<Vec<u8>, ClientError> {
+        self.client
+            .send_resp::<ReadItmEndpoint, _>(&ReadItmRequest {
+                sessid: self.sessid,
+            })
+            .await
+    }
+
+    /// Wire `cores` into a run-control group, so they halt and resume at the same moment.
+    ///
+    /// Returns the number of the new group.
+    pub async fn create_run_control_group(&self, cores: Vec<u32>) -> Result<u8, ClientError> {
+        self.client
+            .send_resp::<CreateRunControlGroupEndpoint, _>(&CreateRunControlGroupRequest {
+                sessid: self.sessid,
+                cores,
+            })
+            .await
+    }
+
+    /// Dissolve the run-control group `core` is a member of.
+    pub async fn remove_run_control_group(&self, core: u32) -> Result<(), ClientError> {
+        self.client
+            .send_resp::<RemoveRunControlGroupEndpoint, _>(&RemoveRunControlGroupRequest {
+                sessid: self.sessid,
+                core,
+            })
+            .await
+    }
+
     /// Read the status of selected cores.
     ///
     /// When `cores` is `None`, every session core is considered. Disabled cores
@@ -692,7 +801,19 @@ impl SessionInterface {
     /// If the image runs from RAM, the target does not get a reset. If the image
     /// runs from flash, the target gets a reset.
     pub async fn boot(&self, boot_info: BootInfo, core_id: usize) -> Result<(), ClientError> {
-        self.boot_with_resume(boot_info, core_id, true).await
+        self.boot_with_resume(boot_info, core_id, true, None).await
+    }
+
+    /// Like [`Self::boot`], but paints the stack at `paint_stack` before the cores are
+    /// started, so its usage can be measured with [`CoreInterface::stack_usage`].
+    pub async fn boot_with_painted_stack(
+        &self,
+        boot_info: BootInfo,
+        core_id: usize,
+        paint_stack: Range<u64>,
+    ) -> Result<(), ClientError> {
+        self.boot_with_resume(boot_info, core_id, true, Some(paint_stack))
+            .await
     }
 
     /// Prepares the core to execute the loaded image and leaves cores halted.
@@ -701,7 +822,7 @@ impl SessionInterface {
         boot_info: BootInfo,
         core_id: usize,
     ) -> Result<(), ClientError> {
-        self.boot_with_resume(boot_info, core_id, false).await
+        self.boot_with_resume(boot_info, core_id, false, None).await
     }
 
     async fn boot_with_resume(
@@ -709,6 +830,7 @@ impl SessionInterface {
         boot_info: BootInfo,
         core_id: usize,
         resume: bool,
+        paint_stack: Option<Range<u64>>,
     ) -> Result<(), ClientError> {
         self.client
             .send_resp::<BootEndpoint, _>(&BootRequest {
@@ -716,6 +838,7 @@ impl SessionInterface {
                 boot_info,
                 core_id: core_id as u32,
                 resume,
+                paint_stack,
             })
             .await
     }
@@ -1055,10 +1178,11 @@ impl SessionInterface {
         self.client.resolve_upload(path).await
     }
 
-    /// Resolve source file/line requests against the server-owned debug info.
+    /// Resolve source file/line and function breakpoint requests against the server-owned debug
+    /// info.
     pub async fn resolve_source_breakpoints(
         &self,
-        locations: Vec<SourceBreakpointLocation>,
+        locations: Vec<BreakpointTarget>,
     ) -> Result<Vec<BreakpointResolution>, ClientError> {
         self.client
             .send_resp::<ResolveSourceBreakpointsEndpoint, _>(&ResolveSourceBreakpointsRequest {
@@ -1110,17 +1234,20 @@ impl SessionInterface {
     /// Fetch a rich stack trace (per-frame register state + display metadata,
     /// no local variables) for the requested core(s). Requires server-side
     /// debug state from [`Self::load_debug_info`]; does not upload or parse a
-    /// binary path.
+    /// binary path. With `rtos_threads`, the threads of the RTOS that are not
+    /// running are unwound as well.
     pub async fn take_rich_stack_trace(
         &self,
         core: Option<u32>,
         stack_frame_limit: u32,
+        rtos_threads: bool,
     ) -> Result<RichStackTraces, ClientError> {
         self.client
             .send_resp::<TakeRichStackTraceEndpoint, _>(&TakeRichStackTraceRequest {
                 sessid: self.sessid,
                 core,
                 stack_frame_limit,
+                rtos_threads,
             })
             .await
     }
@@ -1223,6 +1350,27 @@ impl SessionInterface {
             .await
     }
 
+    /// Resolve the address and size of a variable in the server-side
+    /// `VariableCache`, for use as a data breakpoint. Returns `None` if the
+    /// variable does not live in memory.
+    pub async fn data_breakpoint_info(
+        &self,
+        core: u32,
+        variables_reference: Option<i64>,
+        frame_id: Option<u32>,
+        name: String,
+    ) -> Result<Option<WireDataBreakpointInfo>, ClientError> {
+        self.client
+            .send_resp::<DataBreakpointInfoEndpoint, _>(&DataBreakpointInfoRequest {
+                sessid: self.sessid,
+                core,
+                variables_reference,
+                frame_id,
+                name,
+            })
+            .await
+    }
+
     pub async fn disassemble(
         &self,
         core: u32,
@@ -1507,6 +1655,130 @@ impl CoreInterface {
             .await
     }
 
+    /// Sets software breakpoints by patching break instructions into RAM.
+    pub async fn set_sw_breakpoints(
+        &self,
+        addresses: Vec<u64>,
+    ) -> Result<Vec<Result<(), RpcError>>, ClientError> {
+        self.client
+            .send_resp::<CoreSetSwBpsEndpoint, _>(&CoreBreakpointsRequest {
+                sessid: self.sessid,
+                core: self.core,
+                addresses,
+            })
+            .await
+    }
+
+    /// Clears software breakpoints, restoring the original instructions.
+    pub async fn clear_sw_breakpoints(&self, addresses: Vec<u64>) -> Result<(), ClientError> {
+        self.client
+            .send_resp::<CoreClearSwBpsEndpoint, _>(&CoreBreakpointsRequest {
+                sessid: self.sessid,
+                core: self.core,
+                addresses,
+            })
+            .await
+    }
+
+    /// Sets flash breakpoints by reprogramming flash sectors with break instructions.
+    ///
+    /// The sectors are programmed when the core is next resumed or stepped.
+    pub async fn set_flash_breakpoints(
+        &self,
+        addresses: Vec<u64>,
+    ) -> Result<Vec<Result<(), RpcError>>, ClientError> {
+        self.client
+            .send_resp::<CoreSetFlashBpsEndpoint, _>(&CoreBreakpointsRequest {
+                sessid: self.sessid,
+                core: self.core,
+                addresses,
+            })
+            .await
+    }
+
+    /// Clears flash breakpoints. The original flash contents are restored when the core is
+    /// next resumed or stepped.
+    pub async fn clear_flash_breakpoints(&self, addresses: Vec<u64>) -> Result<(), ClientError> {
+        self.client
+            .send_resp::<CoreClearFlashBpsEndpoint, _>(&CoreBreakpointsRequest {
+                sessid: self.sessid,
+                core: self.core,
+                addresses,
+            })
+            .await
+    }
+
+    /// Sets a hardware watchpoint on a free watchpoint unit.
+    pub async fn set_hw_watchpoint(&self, watchpoint: WireWatchpoint) -> Result<(), ClientError> {
+        self.client
+            .send_resp::<CoreSetHwWpEndpoint, _>(&CoreWatchpointRequest {
+                sessid: self.sessid,
+                core: self.core,
+                watchpoint,
+            })
+            .await
+    }
+
+    /// Clears the hardware watchpoint starting at `watchpoint.address`, if there is one.
+    pub async fn clear_hw_watchpoint(&self, watchpoint: WireWatchpoint) -> Result<(), ClientError> {
+        self.client
+            .send_resp::<CoreClearHwWpEndpoint, _>(&CoreWatchpointRequest {
+                sessid: self.sessid,
+                core: self.core,
+                watchpoint,
+            })
+            .await
+    }
+
+    /// Reads the threads of the RTOS the program on this core uses, from the
+    /// server-side debug info loaded with [`SessionInterface::load_debug_info`].
+    ///
+    /// Returns `None` if the program does not use a supported RTOS.
+    pub async fn rtos_threads(&self) -> Result<Option<WireRtosThreads>, ClientError> {
+        self.client
+            .send_resp::<RtosThreadsEndpoint, _>(&RtosThreadsRequest {
+                sessid: self.sessid,
+                core: self.core,
+            })
+            .await
+    }
+
+    /// Measures how much of the stack painted when the program was started was used, and of
+    /// the stacks of the RTOS threads. The threads are found using the server-side debug info
+    /// loaded with [`SessionInterface::load_debug_info`].
+    pub async fn stack_usage(
+        &self,
+        painted_stack: Option<Range<u64>>,
+    ) -> Result<WireStackUsageReport, ClientError> {
+        self.client
+            .send_resp::<StackUsageEndpoint, _>(&StackUsageRequest {
+                sessid: self.sessid,
+                core: self.core,
+                painted_stack,
+            })
+            .await
+    }
+
+    /// Reads the spawned tasks of the embassy executor, using the server-side
+    /// debug info loaded with [`SessionInterface::load_debug_info`].
+    ///
+    /// Returns `None` if the program does not use the embassy executor.
+    pub async fn embassy_tasks(&self) -> Result<Option<WireEmbassyTasks>, ClientError> {
+        self.client
+            .send_resp::<EmbassyTasksEndpoint, _>(&EmbassyTasksRequest {
+                sessid: self.sessid,
+                core: self.core,
+            })
+            .await
+    }
+
+    /// Returns the watchpoint that halted the core, if the target can tell.
+    pub async fn triggered_watchpoint(&self) -> Result<Option<WireWatchpoint>, ClientError> {
+        self.client
+            .send_resp::<CoreHitWpEndpoint, _>(&self.access_request())
+            .await
+    }
+
     pub async fn enable_vector_catch(
         &self,
         condition: WireVectorCatchCondition,
@@ -1520,6 +1792,19 @@ impl CoreInterface {
             .await
     }
 
+    pub async fn disable_vector_catch(
+        &self,
+        condition: WireVectorCatchCondition,
+    ) -> Result<(), ClientError> {
+        self.client
+            .send_resp::<CoreDisableVcEndpoint, _>(&CoreVectorCatchRequest {
+                sessid: self.sessid,
+                core: self.core,
+                condition,
+            })
+            .await
+    }
+
     pub async fn metadata(&self) -> Result<WireCoreMetadata, ClientError> {
         self.client
             .send_resp::<CoreMetadataEndpoint, _>(&self.access_request())
@@ -1672,6 +1957,7 @@ pub(crate) trait MultiSubscription {
 pub enum MonitorEvent {
     Rtt(RttEvent),
     Semihosting(SemihostingEvent),
+    Itm(ItmEvent),
 }
 
 impl MultiTopic for MonitorEvent {
@@ -1689,13 +1975,19 @@ impl MultiTopic for MonitorEvent {
         // one for RTT, one for semihosting, then introduce a MultiSubscription impl for them
         let rtt = RttTopic::subscribe(client, depth).await?;
         let semihosting = SemihostingTopic::subscribe(client, depth).await?;
-        Ok(MonitorSubscription { rtt, semihosting })
+        let itm = ItmTopic::subscribe(client, depth).await?;
+        Ok(MonitorSubscription {
+            rtt,
+            semihosting,
+            itm,
+        })
     }
 }
 
 pub(crate) struct MonitorSubscription {
     rtt: <RttTopic as MultiTopic>::Subscription,
     semihosting: <SemihostingTopic as MultiTopic>::Subscription,
+    itm: <ItmTopic as MultiTopic>::Subscription,
 }
 impl MultiSubscription for MonitorSubscription {
     type Message = MonitorEvent;
@@ -1704,6 +1996,7 @@ impl MultiSubscription for MonitorSubscription {
         tokio::select! {
             message = self.rtt.recv() => message.map(MonitorEvent::Rtt),
             message = self.semihosting.recv() => message.map(MonitorEvent::Semihosting),
+            message = self.itm.recv() => message.map(MonitorEvent::Itm),
         }
     }
 }
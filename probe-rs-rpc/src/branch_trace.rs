@@ -0,0 +1,30 @@
+use std::ops::Range;
+
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+
+use crate::{Key, RpcResult, Session};
+
+/// Start recording branches with the MTB into `buffer`, an unused region of the MTB SRAM.
+#[derive(Serialize, Deserialize, Schema, Clone)]
+pub struct StartBranchTraceRequest {
+    pub sessid: Key<Session>,
+    pub buffer: Range<u64>,
+}
+
+#[derive(Serialize, Deserialize, Schema, Clone)]
+pub struct TakeBranchTraceRequest {
+    pub sessid: Key<Session>,
+}
+
+/// Wire form of `probe_rs::architecture::arm::component::MtbBranch`.
+#[derive(Serialize, Deserialize, Schema, Clone, Copy)]
+pub struct WireBranch {
+    pub source: u64,
+    pub target: u64,
+    pub exception: bool,
+    pub trace_start: bool,
+}
+
+/// The recorded branches, oldest first.
+pub type TakeBranchTraceResponse = RpcResult<Vec<WireBranch>>;
@@ -24,10 +24,19 @@ pub struct SourceBreakpointLocation {
     pub column: Option<u64>,
 }
 
+/// A breakpoint location to resolve against the server-owned debug info.
+#[derive(Serialize, Deserialize, Schema)]
+pub enum BreakpointTarget {
+    /// A line (and optionally column) in a source file.
+    Source(SourceBreakpointLocation),
+    /// Every instance of a function, by name.
+    Function(String),
+}
+
 #[derive(Serialize, Deserialize, Schema)]
 pub struct ResolveSourceBreakpointsRequest {
     pub sessid: Key<Session>,
-    pub locations: Vec<SourceBreakpointLocation>,
+    pub locations: Vec<BreakpointTarget>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Schema)]
@@ -38,7 +47,8 @@ pub struct WireVerifiedBreakpoint {
 
 #[derive(Clone, Debug, Serialize, Deserialize, Schema)]
 pub struct BreakpointResolution {
-    pub breakpoint: Option<WireVerifiedBreakpoint>,
+    /// One breakpoint for a source location, or one per instance of a function.
+    pub breakpoints: Vec<WireVerifiedBreakpoint>,
     pub error: Option<String>,
 }
 
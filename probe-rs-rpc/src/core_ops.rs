@@ -62,6 +62,13 @@ pub struct CoreBreakpointsRequest {
     pub addresses: Vec<u64>,
 }
 
+#[derive(Serialize, Deserialize, Schema, Clone)]
+pub struct CoreWatchpointRequest {
+    pub sessid: Key<Session>,
+    pub core: u32,
+    pub watchpoint: WireWatchpoint,
+}
+
 #[derive(Serialize, Deserialize, Schema, Clone)]
 pub struct CoreVectorCatchRequest {
     pub sessid: Key<Session>,
@@ -153,6 +160,38 @@ pub struct WireExitErrorDetails {
     pub subcode: Option<u32>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Schema, Copy, Clone, PartialEq, Eq)]
+pub enum WireWatchpointKind {
+    Read,
+    Write,
+    Access,
+}
+
+#[derive(Debug, Serialize, Deserialize, Schema, Copy, Clone, PartialEq, Eq)]
+pub struct WireWatchpoint {
+    pub address: u64,
+    pub size: u64,
+    pub kind: WireWatchpointKind,
+}
+
+impl WireWatchpoint {
+    /// Hardware can only watch naturally aligned power-of-two regions, so the
+    /// `address`/`len` range is widened to the smallest such region containing it.
+    pub fn covering(address: u64, len: u64, kind: WireWatchpointKind) -> Self {
+        let end = address + len.max(1);
+        let mut size = len.max(1).next_power_of_two();
+        while (address & !(size - 1)) + size < end {
+            size *= 2;
+        }
+
+        Self {
+            address: address & !(size - 1),
+            size,
+            kind,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Schema, Copy, Clone, PartialEq, Eq)]
 pub enum WireVectorCatchCondition {
     HardFault,
@@ -262,3 +301,21 @@ pub struct HandleSemihostingResult {
 }
 
 pub type HandleSemihostingResponse = RpcResult<HandleSemihostingResult>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn covering_watchpoint_is_aligned() {
+        let wp = WireWatchpoint::covering(0x2000_0004, 4, WireWatchpointKind::Write);
+        assert_eq!((wp.address, wp.size), (0x2000_0004, 4));
+
+        // A 4-byte range straddling an 8-byte boundary needs a 16-byte region.
+        let wp = WireWatchpoint::covering(0x2000_0006, 4, WireWatchpointKind::Write);
+        assert_eq!((wp.address, wp.size), (0x2000_0000, 16));
+
+        let wp = WireWatchpoint::covering(0x2000_0003, 0, WireWatchpointKind::Read);
+        assert_eq!((wp.address, wp.size), (0x2000_0003, 1));
+    }
+}
@@ -33,3 +33,20 @@ pub struct CoresStatusMap {
 }
 
 pub type CoresStatusResponse = RpcResult<CoresStatusMap>;
+
+/// Wire a set of cores into a run-control group, which halts and resumes together.
+#[derive(Serialize, Deserialize, Schema, Clone)]
+pub struct CreateRunControlGroupRequest {
+    pub sessid: Key<Session>,
+    pub cores: Vec<u32>,
+}
+
+/// The number of the new run-control group.
+pub type CreateRunControlGroupResponse = RpcResult<u8>;
+
+/// Dissolve the run-control group of `core`.
+#[derive(Serialize, Deserialize, Schema, Clone)]
+pub struct RemoveRunControlGroupRequest {
+    pub sessid: Key<Session>,
+    pub core: u32,
+}
@@ -0,0 +1,66 @@
+use std::ops::Range;
+
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+
+use crate::{Key, RpcResult, Session};
+
+/// How the executed code is recorded while the target runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Schema)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum CoverageMethod {
+    /// Use the best method the core supports: the ETM, the MTB if a buffer is given, PC
+    /// sampling with the DWT, or halt sampling.
+    Auto,
+    /// Halt the core periodically and read its program counter.
+    Halt,
+    /// Sample the program counter with the PCSR register of the DWT, without halting the core.
+    Pcsr,
+    /// Record branches with the MTB and read them back periodically.
+    Mtb,
+    /// Trace instructions with the ETM and read the trace back periodically.
+    Etm,
+}
+
+/// Start recording the executed code during the following `monitor` and `tests/run` requests.
+#[derive(Serialize, Deserialize, Schema, Clone)]
+pub struct StartCoverageRequest {
+    pub sessid: Key<Session>,
+    pub method: CoverageMethod,
+    /// An unused region of the MTB SRAM to record branches into.
+    pub mtb_buffer: Option<Range<u64>>,
+    /// The address ranges of the program code. The code is read back from the target to decode
+    /// the ETM trace.
+    pub code: Vec<Range<u64>>,
+}
+
+/// The method used to record the executed code.
+pub type StartCoverageResponse = RpcResult<CoverageMethod>;
+
+#[derive(Serialize, Deserialize, Schema, Clone)]
+pub struct TakeCoverageRequest {
+    pub sessid: Key<Session>,
+}
+
+/// A program counter value and how often it was sampled.
+#[derive(Serialize, Deserialize, Schema, Clone, Copy)]
+pub struct CoverageSample {
+    pub address: u64,
+    pub count: u64,
+}
+
+/// A range of instructions and how often it was executed.
+#[derive(Serialize, Deserialize, Schema, Clone)]
+pub struct ExecutedRange {
+    pub range: Range<u64>,
+    pub count: u64,
+}
+
+/// The code executed since coverage was started or last taken.
+#[derive(Serialize, Deserialize, Schema, Clone, Default)]
+pub struct WireCoverage {
+    pub samples: Vec<CoverageSample>,
+    pub ranges: Vec<ExecutedRange>,
+}
+
+pub type TakeCoverageResponse = RpcResult<WireCoverage>;
@@ -102,3 +102,27 @@ pub struct WireSetVariableResponse {
 }
 
 pub type SetVariableResult = RpcResult<WireSetVariableResponse>;
+
+#[derive(Serialize, Deserialize, Schema)]
+pub struct DataBreakpointInfoRequest {
+    pub sessid: Key<Session>,
+    pub core: u32,
+    /// The container of the variable, as returned by the `variables` request.
+    /// If `None`, `name` is looked up in the locals of `frame_id` and then in
+    /// the statics.
+    pub variables_reference: Option<i64>,
+    pub frame_id: Option<u32>,
+    pub name: String,
+}
+
+/// The memory backing a variable, suitable for a hardware watchpoint.
+#[derive(Serialize, Deserialize, Schema, Clone)]
+pub struct WireDataBreakpointInfo {
+    pub name: String,
+    pub type_: String,
+    pub address: u64,
+    pub size: u64,
+}
+
+/// `Ok(None)` if the variable exists but does not live in memory.
+pub type DataBreakpointInfoResponse = RpcResult<Option<WireDataBreakpointInfo>>;
@@ -0,0 +1,45 @@
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+
+use crate::{Key, RpcResult, Session};
+
+#[derive(Serialize, Deserialize, Schema)]
+pub struct EmbassyTasksRequest {
+    pub sessid: Key<Session>,
+    pub core: u32,
+}
+
+/// A spawned task of the embassy executor.
+#[derive(Serialize, Deserialize, Schema, Clone, Debug, PartialEq, Eq)]
+pub struct WireEmbassyTask {
+    /// The address of the header of the task.
+    pub address: u64,
+    /// The path of the function of the task.
+    pub name: String,
+    /// The type of the future of the task.
+    pub future_type: String,
+    pub future_address: u64,
+    pub spawned: bool,
+    pub run_queued: bool,
+    pub timer_queued: bool,
+    /// The address of the executor the task was spawned on.
+    pub executor: u64,
+}
+
+#[derive(Serialize, Deserialize, Schema, Clone, Debug, PartialEq, Eq)]
+pub struct WireEmbassyExecutor {
+    pub address: u64,
+    /// The addresses of the tasks in the run queue, in the order they are polled next.
+    pub run_queue: Vec<u64>,
+}
+
+#[derive(Serialize, Deserialize, Schema, Clone, Debug, PartialEq, Eq)]
+pub struct WireEmbassyTasks {
+    pub tasks: Vec<WireEmbassyTask>,
+    pub executors: Vec<WireEmbassyExecutor>,
+    /// The bytes of the arena used for task pools, and the size of the arena.
+    pub arena: Option<(u64, u64)>,
+}
+
+/// `None` if the program running on the core does not use the embassy executor.
+pub type EmbassyTasksResponse = RpcResult<Option<WireEmbassyTasks>>;
@@ -1,5 +1,8 @@
 use postcard_rpc::{TopicDirection, endpoints, topics};
 
+use crate::branch_trace::{
+    StartBranchTraceRequest, TakeBranchTraceRequest, TakeBranchTraceResponse,
+};
 use crate::breakpoints::{
     ResolveSourceBreakpointsRequest, ResolveSourceBreakpointsResponse,
     ResolveSourceLocationsRequest, ResolveSourceLocationsResponse,
@@ -7,17 +10,24 @@ use crate::breakpoints::{
 use crate::chip::{ChipInfoRequest, ChipInfoResponse, ListFamiliesResponse, LoadChipFamilyRequest};
 use crate::core_ops::{
     CoreAccessRequest, CoreBreakpointsRequest, CoreDumpRequest, CoreHaltRequest,
-    CoreReadRegistersRequest, CoreVectorCatchRequest, CoreWriteRegRequest,
+    CoreReadRegistersRequest, CoreVectorCatchRequest, CoreWatchpointRequest, CoreWriteRegRequest,
     HandleSemihostingRequest, HandleSemihostingResponse, StepRequest, StepResult, WireCoreDump,
-    WireCoreInformation, WireCoreMetadata, WireCoreStatus, WireRegisterReadResult,
+    WireCoreInformation, WireCoreMetadata, WireCoreStatus, WireRegisterReadResult, WireWatchpoint,
+};
+use crate::cores::{
+    CoresRequest, CoresStatusResponse, CreateRunControlGroupRequest, CreateRunControlGroupResponse,
+    HaltCoresRequest, RemoveRunControlGroupRequest,
+};
+use crate::coverage::{
+    StartCoverageRequest, StartCoverageResponse, TakeCoverageRequest, TakeCoverageResponse,
 };
-use crate::cores::{CoresRequest, CoresStatusResponse, HaltCoresRequest};
 use crate::debug_vars::{
-    ClearCoreDebugStateRequest, EvaluateRequest, EvaluateResponse, LoadSvdRequest, LoadSvdResponse,
-    ScopesRequest, ScopesResponse, SetVariableRequest, SetVariableResult, VariablesRequest,
-    VariablesResponse,
+    ClearCoreDebugStateRequest, DataBreakpointInfoRequest, DataBreakpointInfoResponse,
+    EvaluateRequest, EvaluateResponse, LoadSvdRequest, LoadSvdResponse, ScopesRequest,
+    ScopesResponse, SetVariableRequest, SetVariableResult, VariablesRequest, VariablesResponse,
 };
 use crate::disassemble::{DisassembleRequest, DisassembleResponse};
+use crate::embassy::{EmbassyTasksRequest, EmbassyTasksResponse};
 use crate::file::{AppendFileRequest, CreateFileResponse};
 use crate::flash::{
     BootRequest, BuildRequest, BuildResponse, EraseAllRequest, EraseRangeRequest, FlashRequest,
@@ -25,16 +35,19 @@ use crate::flash::{
     VerifyResponse,
 };
 use crate::info::{InfoEvent, TargetInfoRequest, TargetMetadataRequest, TargetMetadataResponse};
+use crate::itm::{ItmEvent, ReadItmRequest, ReadItmResponse, StartItmRequest};
 use crate::memory::{ReadBytesRequest, ReadMemoryRequest, WriteMemoryRequest};
 use crate::monitor::{MonitorRequest, MonitorResponse, RttEvent, SemihostingEvent};
 use crate::probe::{
     AttachRequest, AttachResponse, ListProbesResponse, SelectProbeRequest, SelectProbeResponse,
 };
 use crate::reset::{ResetCoreAndHaltRequest, ResetCoreRequest};
+use crate::rtos::{RtosThreadsRequest, RtosThreadsResponse};
 use crate::rtt_client::{
     CreateRttClientRequest, CreateRttClientResponse, PollRttUpRequest, PollRttUpResponse,
     RttChannelRequest, RttChannelsResponse, RttDownRequest, RttDownResponse,
 };
+use crate::stack_paint::{StackUsageRequest, StackUsageResponse};
 use crate::stack_trace::{
     LoadDebugInfoRequest, LoadDebugInfoResponse, TakeRichStackTraceRequest,
     TakeRichStackTraceResponse, TakeStackTraceRequest, TakeStackTraceResponse,
@@ -65,6 +78,9 @@ type CoreMetadataResponse = RpcResult<WireCoreMetadata>;
 type CoreReadRegistersResponse = RpcResult<Vec<WireRegisterReadResult>>;
 type CoreDumpResponse = RpcResult<WireCoreDump>;
 type CoreSetHwBpsResponse = RpcResult<Vec<Result<(), RpcError>>>;
+type CoreSetSwBpsResponse = RpcResult<Vec<Result<(), RpcError>>>;
+type CoreSetFlashBpsResponse = RpcResult<Vec<Result<(), RpcError>>>;
+type CoreHitWpResponse = RpcResult<Option<WireWatchpoint>>;
 
 endpoints! {
     list = ENDPOINT_LIST;
@@ -77,6 +93,10 @@ endpoints! {
     | HaltCoresEndpoint         | HaltCoresRequest        | CoresStatusResponse     | "cores/halt"       |
     | ResumeCoresEndpoint       | CoresRequest            | CoresStatusResponse     | "cores/resume"     |
     | CoresStatusEndpoint       | CoresRequest            | CoresStatusResponse     | "cores/status"     |
+
+    | CreateRunControlGroupEndpoint | CreateRunControlGroupRequest | CreateRunControlGroupResponse | "cores/group/create" |
+    | RemoveRunControlGroupEndpoint | RemoveRunControlGroupRequest | NoResponse                    | "cores/group/remove" |
+
     | NewFlashLoaderEndpoint    | NewFlashLoaderRequest   | NewFlashLoaderResponse  | "flash/new"        |
     | BuildEndpoint             | BuildRequest            | BuildResponse           | "flash/build"      |
     | LoadRegionEndpoint        | LoadRegionRequest       | NoResponse              | "flash/load_region"|
@@ -87,18 +107,32 @@ endpoints! {
     | BootEndpoint              | BootRequest             | NoResponse              | "flash/boot"       |
     | MonitorEndpoint           | MonitorRequest          | MonitorResponse         | "monitor"          |
 
+    | StartBranchTraceEndpoint  | StartBranchTraceRequest | NoResponse              | "branch_trace/start" |
+    | TakeBranchTraceEndpoint   | TakeBranchTraceRequest  | TakeBranchTraceResponse | "branch_trace/take"  |
+
+    | StartCoverageEndpoint     | StartCoverageRequest    | StartCoverageResponse   | "coverage/start"   |
+    | TakeCoverageEndpoint      | TakeCoverageRequest     | TakeCoverageResponse    | "coverage/take"    |
+
+    | StackUsageEndpoint        | StackUsageRequest       | StackUsageResponse      | "stack_usage"      |
+
+    | StartItmEndpoint          | StartItmRequest         | NoResponse              | "itm/start"        |
+    | ReadItmEndpoint           | ReadItmRequest          | ReadItmResponse         | "itm/read"         |
+
     | TakeStackTraceEndpoint     | TakeStackTraceRequest     | TakeStackTraceResponse     | "stack_trace"              |
     | TakeRichStackTraceEndpoint | TakeRichStackTraceRequest | TakeRichStackTraceResponse | "stack_trace/rich"         |
     | ScopesEndpoint             | ScopesRequest             | ScopesResponse             | "stack_trace/scopes"       |
     | VariablesEndpoint          | VariablesRequest          | VariablesResponse          | "stack_trace/variables"    |
     | EvaluateEndpoint           | EvaluateRequest           | EvaluateResponse           | "stack_trace/evaluate"     |
     | SetVariableEndpoint        | SetVariableRequest        | SetVariableResponse        | "stack_trace/set_variable" |
+    | DataBreakpointInfoEndpoint | DataBreakpointInfoRequest | DataBreakpointInfoResponse | "stack_trace/data_bp_info" |
 
     | LoadDebugInfoEndpoint            | LoadDebugInfoRequest            | LoadDebugInfoResponse            | "debug_state/load_debug_info"            |
     | ResolveSourceBreakpointsEndpoint | ResolveSourceBreakpointsRequest | ResolveSourceBreakpointsResponse | "debug_state/resolve_source_breakpoints" |
     | ResolveSourceLocationsEndpoint   | ResolveSourceLocationsRequest   | ResolveSourceLocationsResponse   | "debug_state/resolve_source_locations"   |
     | ClearCoreDebugStateEndpoint      | ClearCoreDebugStateRequest      | NoResponse                       | "debug_state/clear_core"                 |
     | LoadSvdEndpoint                  | LoadSvdRequest                  | LoadSvdResponse                  | "debug_state/load_svd"                   |
+    | RtosThreadsEndpoint              | RtosThreadsRequest              | RtosThreadsResponse              | "debug_state/rtos_threads"               |
+    | EmbassyTasksEndpoint             | EmbassyTasksRequest             | EmbassyTasksResponse             | "debug_state/embassy_tasks"              |
 
     | CreateRttClientEndpoint      | CreateRttClientRequest | CreateRttClientResponse | "create_rtt"              |
     | RttDownEndpoint              | RttDownRequest         | RttDownResponse         | "rtt/down"                |
@@ -130,7 +164,15 @@ endpoints! {
     | CoreWriteRegEndpoint         | CoreWriteRegRequest      | NoResponse                 | "core/write_reg"          |
     | CoreSetHwBpsEndpoint         | CoreBreakpointsRequest   | CoreSetHwBpsResponse       | "core/set_hw_bps"         |
     | CoreClearHwBpsEndpoint       | CoreBreakpointsRequest   | NoResponse                 | "core/clear_hw_bps"       |
+    | CoreSetSwBpsEndpoint         | CoreBreakpointsRequest   | CoreSetSwBpsResponse       | "core/set_sw_bps"         |
+    | CoreClearSwBpsEndpoint       | CoreBreakpointsRequest   | NoResponse                 | "core/clear_sw_bps"       |
+    | CoreSetFlashBpsEndpoint      | CoreBreakpointsRequest   | CoreSetFlashBpsResponse    | "core/set_flash_bps"      |
+    | CoreClearFlashBpsEndpoint    | CoreBreakpointsRequest   | NoResponse                 | "core/clear_flash_bps"    |
+    | CoreSetHwWpEndpoint          | CoreWatchpointRequest    | NoResponse                 | "core/set_hw_wp"          |
+    | CoreClearHwWpEndpoint        | CoreWatchpointRequest    | NoResponse                 | "core/clear_hw_wp"        |
+    | CoreHitWpEndpoint            | CoreAccessRequest        | CoreHitWpResponse          | "core/hit_wp"             |
     | CoreEnableVcEndpoint         | CoreVectorCatchRequest   | NoResponse                 | "core/enable_vc"          |
+    | CoreDisableVcEndpoint        | CoreVectorCatchRequest   | NoResponse                 | "core/disable_vc"         |
     | CoreMetadataEndpoint         | CoreAccessRequest        | CoreMetadataResponse       | "core/metadata"           |
     | CoreReadRegistersEndpoint    | CoreReadRegistersRequest | CoreReadRegistersResponse  | "core/read_registers"     |
     | CoreDumpEndpoint             | CoreDumpRequest          | CoreDumpResponse           | "core/dump"               |
@@ -166,4 +208,5 @@ topics! {
     | ProgressEventTopic  | ProgressEvent    | "flash/progress" |     |
     | RttTopic            | RttEvent         | "rtt"            |     |
     | SemihostingTopic    | SemihostingEvent | "semihosting"    |     |
+    | ItmTopic            | ItmEvent         | "itm"            |     |
 }
@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 use postcard_schema::Schema;
 use serde::{Deserialize, Serialize};
 
@@ -174,6 +176,9 @@ pub struct BootRequest {
     pub core_id: u32,
     /// When true, resume all cores after prepare. When false, leave them halted.
     pub resume: bool,
+    /// Paint this stack after prepare, so its usage can be measured with the `stack_usage`
+    /// endpoint.
+    pub paint_stack: Option<Range<u64>>,
 }
 
 #[derive(Serialize, Deserialize, Schema)]
@@ -0,0 +1,40 @@
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+
+use crate::{Key, RpcResult, Session};
+
+/// Where the ITM trace data is captured.
+#[derive(Serialize, Deserialize, Schema, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ItmSink {
+    /// The Embedded Trace Buffer/FIFO, which is drained through the debug interface.
+    TraceMemory,
+    /// The SWO pin, received by the probe.
+    Swo {
+        /// The frequency of the clock feeding the TPIU/SWO module in Hz.
+        clock: u32,
+        /// The baud rate of the SWO output.
+        baud: u32,
+    },
+}
+
+/// Start tracing the ITM into `sink`. The trace data is then read with the `itm/read` endpoint.
+#[derive(Serialize, Deserialize, Schema, Clone)]
+pub struct StartItmRequest {
+    pub sessid: Key<Session>,
+    pub core: u32,
+    pub sink: ItmSink,
+}
+
+#[derive(Serialize, Deserialize, Schema, Clone)]
+pub struct ReadItmRequest {
+    pub sessid: Key<Session>,
+}
+
+/// The ITM trace data captured since the last read.
+pub type ReadItmResponse = RpcResult<Vec<u8>>;
+
+/// ITM trace data captured while the `monitor` endpoint runs the target.
+#[derive(Serialize, Deserialize, Schema)]
+pub struct ItmEvent {
+    pub bytes: Vec<u8>,
+}
@@ -153,23 +153,29 @@ impl host_client::WireSpawn for TokioSpawner {
 mod endpoints;
 pub use endpoints::*;
 
+pub mod branch_trace;
 pub mod breakpoints;
 pub mod chip;
 pub mod core_ops;
 pub mod cores;
+pub mod coverage;
 pub mod debug_vars;
 pub mod disassemble;
+pub mod embassy;
 pub mod file;
 pub mod flash;
 pub mod format;
 pub mod info;
+pub mod itm;
 pub mod memory;
 pub mod monitor;
 pub mod probe;
 pub mod reset;
+pub mod rtos;
 pub mod rtt_client;
 pub mod rtt_config;
 pub mod semihosting_options;
+pub mod stack_paint;
 pub mod stack_trace;
 pub mod test;
 pub mod transport;
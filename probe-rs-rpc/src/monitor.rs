@@ -1,7 +1,10 @@
+use std::ops::Range;
+
 use postcard_schema::Schema;
 use serde::{Deserialize, Serialize};
 
 use crate::flash::BootInfo;
+use crate::itm::ItmSink;
 use crate::semihosting_options::SemihostingOptions;
 use crate::{Key, RpcResult, RttClient, Session};
 
@@ -29,6 +32,14 @@ pub struct MonitorOptions {
     pub catch_hlt: bool,
     pub rtt_client: Option<Key<RttClient>>,
     pub semihosting_options: SemihostingOptions,
+    /// Record branches with the MTB into this buffer, so the branch history leading up to an
+    /// unexpected halt can be read with the `branch_trace/take` endpoint.
+    pub branch_trace_buffer: Option<Range<u64>>,
+    /// Trace the ITM into this sink, and publish the trace data on the `itm` topic.
+    pub itm: Option<ItmSink>,
+    /// Paint this stack once the target has been prepared for running, so its usage can be
+    /// measured with the `stack_usage` endpoint.
+    pub paint_stack: Option<Range<u64>>,
 }
 
 #[derive(Serialize, Deserialize, Schema)]
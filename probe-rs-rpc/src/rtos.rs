@@ -0,0 +1,44 @@
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+
+use crate::stack_trace::WireDebugRegister;
+use crate::{Key, RpcResult, Session};
+
+#[derive(Serialize, Deserialize, Schema)]
+pub struct RtosThreadsRequest {
+    pub sessid: Key<Session>,
+    pub core: u32,
+}
+
+#[derive(Serialize, Deserialize, Schema, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WireThreadState {
+    Running,
+    Ready,
+    Blocked,
+    Suspended,
+    Deleted,
+}
+
+/// A thread of the RTOS that runs on a core.
+#[derive(Serialize, Deserialize, Schema, Clone)]
+pub struct WireRtosThread {
+    /// Unique among the threads of the RTOS, like the address of the thread's control block.
+    pub id: u64,
+    pub name: String,
+    /// The priority, as the RTOS numbers it.
+    pub priority: i32,
+    pub state: WireThreadState,
+    /// The registers rebuilt from the context saved on the thread's stack. Empty for the
+    /// running thread, whose registers are the ones of the core.
+    pub registers: Vec<WireDebugRegister>,
+}
+
+#[derive(Serialize, Deserialize, Schema, Clone)]
+pub struct WireRtosThreads {
+    /// The name of the RTOS, for example `FreeRTOS`.
+    pub rtos: String,
+    pub threads: Vec<WireRtosThread>,
+}
+
+/// `None` if the program running on the core does not use a supported RTOS.
+pub type RtosThreadsResponse = RpcResult<Option<WireRtosThreads>>;
@@ -0,0 +1,46 @@
+use std::ops::Range;
+
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+
+use crate::{Key, RpcResult, Session};
+
+/// Measure how much of the stacks of the program on a core was used.
+#[derive(Serialize, Deserialize, Schema, Clone)]
+pub struct StackUsageRequest {
+    pub sessid: Key<Session>,
+    pub core: u32,
+    /// The stack that was painted when the program was started, if any.
+    pub painted_stack: Option<Range<u64>>,
+}
+
+/// How much of a stack was used.
+#[derive(Serialize, Deserialize, Schema, Clone, Copy, Debug, PartialEq)]
+pub struct WireStackUsage {
+    /// The lowest address of the stack.
+    pub bottom: u64,
+    /// The address above the highest address of the stack, if it is known.
+    pub top: Option<u64>,
+    /// The lowest address the stack was used at.
+    pub high_water_mark: u64,
+}
+
+/// How much of the stack of an RTOS thread was used.
+#[derive(Serialize, Deserialize, Schema, Clone, Debug, PartialEq)]
+pub struct WireThreadStackUsage {
+    pub name: String,
+    pub usage: WireStackUsage,
+}
+
+#[derive(Serialize, Deserialize, Schema, Clone, Debug, PartialEq)]
+pub struct WireStackUsageReport {
+    /// The usage of the painted stack, if one was given.
+    pub painted_stack: Option<WireStackUsage>,
+    /// The name of the RTOS, if the program uses a supported one, for example `FreeRTOS`. Needs
+    /// the debug info preloaded with `load_debug_info`.
+    pub rtos: Option<String>,
+    /// The usage of the stacks of the RTOS threads whose stacks the kernel keeps.
+    pub threads: Vec<WireThreadStackUsage>,
+}
+
+pub type StackUsageResponse = RpcResult<WireStackUsageReport>;
@@ -4,6 +4,7 @@ use postcard_schema::Schema;
 use serde::{Deserialize, Serialize};
 
 use crate::core_ops::{WireRegisterId, WireRegisterValue};
+use crate::rtos::WireRtosThread;
 use crate::{Key, NoResponse, RpcResult, Session};
 
 #[derive(Serialize, Deserialize, Schema)]
@@ -82,6 +83,9 @@ pub struct TakeRichStackTraceRequest {
     /// unwound.
     pub core: Option<u32>,
     pub stack_frame_limit: u32,
+    /// Also unwind the threads of the RTOS that are not running, from the
+    /// registers saved on their stacks.
+    pub rtos_threads: bool,
 }
 
 pub type TakeStackTraceResponse = RpcResult<StackTraces>;
@@ -125,6 +129,18 @@ pub struct RichStackTraceFrame {
 pub struct RichStackTrace {
     pub core: u32,
     pub frames: Vec<RichStackTraceFrame>,
+    /// The RTOS threads of the core, if requested and the program uses a
+    /// supported RTOS. The frames of the running thread are the ones of the
+    /// core.
+    pub threads: Vec<RichThreadStackTrace>,
+}
+
+/// The stack of an RTOS thread. Its frame ids share the id space of the
+/// core's frames, so `scopes`/`variables` requests resolve them alike.
+#[derive(Serialize, Deserialize, Schema, Clone)]
+pub struct RichThreadStackTrace {
+    pub thread: WireRtosThread,
+    pub frames: Vec<RichStackTraceFrame>,
 }
 
 #[derive(Serialize, Deserialize, Schema, Clone)]
@@ -145,6 +161,7 @@ mod tests {
             sessid: Key::test(1),
             core: Some(0),
             stack_frame_limit: 64,
+            rtos_threads: false,
         };
         let encoded = to_allocvec(&request).unwrap();
         let decoded: TakeRichStackTraceRequest = postcard::from_bytes(&encoded).unwrap();
@@ -20,6 +20,8 @@ pub mod reset;
 pub mod run;
 #[cfg(feature = "remote")]
 pub mod serve;
+pub mod stack_usage;
+pub mod stack_watermark;
 pub mod trace;
 pub mod verify;
 pub mod write;
@@ -61,6 +61,7 @@ impl Cmd {
                 catch_svc: !self.run_options.no_catch_svc,
                 catch_hlt: !self.run_options.no_catch_hlt,
             },
+            None,
         )
         .await?;
 
@@ -7,6 +7,7 @@ use probe_rs::probe::WireProtocol;
 use serde::{Deserialize, Serialize};
 use std::{net::SocketAddr, path::PathBuf, time::Duration};
 
+use crate::util::itm::ItmPortConfig;
 use crate::util::logging::LevelFilter;
 use probe_rs_rpc::rtt_config::{ChannelMode, DataFormat};
 
@@ -27,6 +28,7 @@ pub struct Config {
     pub reset: Reset,
     pub probe: Probe,
     pub rtt: Rtt,
+    pub itm: Itm,
     pub gdb: Gdb,
     pub remote: Remote,
 }
@@ -147,6 +149,20 @@ impl Rtt {
     }
 }
 
+/// The itm config struct holding all the possible ITM options.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Itm {
+    pub enabled: bool,
+    /// The trace clock frequency in Hz. When set, the trace is captured through SWO instead of
+    /// the trace memory of the target.
+    pub swo_clock: Option<u32>,
+    /// The SWO baud rate.
+    pub swo_baud: u32,
+    /// The stimulus ports to decode, and where to send their data.
+    pub ports: Vec<ItmPortConfig>,
+}
+
 mod duration_ms {
     use std::time::Duration;
 
@@ -303,8 +319,10 @@ impl Configs {
 mod test {
     use figment::providers::{Format, Toml};
     use probe_rs::probe::WireProtocol;
+    use probe_rs_rpc::rtt_config::DataFormat;
 
     use super::Configs;
+    use crate::util::itm::ItmDestination;
 
     #[test]
     fn default_profile() {
@@ -329,6 +347,35 @@ mod test {
 
         assert_eq!(config.probe.protocol, Some(WireProtocol::Jtag));
     }
+    /// ITM ports are configured with a table per port.
+    #[test]
+    fn itm_ports_are_parsed() {
+        let mut configs = Configs::new(std::env::current_dir().unwrap());
+        configs.figment = configs.figment.merge(
+            Toml::string(
+                r#"
+                [default.itm]
+                enabled = true
+                ports = [
+                    { port = 0 },
+                    { port = 2, format = "BinaryLE", destination = { tcp = "127.0.0.1:9000" } },
+                ]
+                "#,
+            )
+            .nested(),
+        );
+        let config = configs.select_defined("default").unwrap();
+
+        assert!(config.itm.enabled);
+        assert_eq!(config.itm.swo_clock, None);
+        assert_eq!(config.itm.ports[0].format, DataFormat::String);
+        assert_eq!(config.itm.ports[0].destination, ItmDestination::Stdout);
+        assert_eq!(config.itm.ports[1].format, DataFormat::BinaryLE);
+        assert_eq!(
+            config.itm.ports[1].destination,
+            ItmDestination::Tcp("127.0.0.1:9000".parse().unwrap())
+        );
+    }
     #[test]
     fn non_existent_profile_is_error() {
         // Selecting a non-existent profile.
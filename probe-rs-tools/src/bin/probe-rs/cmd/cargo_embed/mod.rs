@@ -10,6 +10,7 @@ use probe_rs::rtt::find_rtt_control_block_in_raw_file;
 use probe_rs_rpc::core_ops::WireCoreStatus;
 use probe_rs_rpc::flash::BootInfo;
 use probe_rs_rpc::format::{FormatKind, FormatOptions};
+use probe_rs_rpc::itm::ItmSink;
 use probe_rs_rpc::rtt_client::ScanRegion;
 use probe_rs_rpc::rtt_config::RttChannelConfig;
 use probe_rs_rpc::{Key, RttClient};
@@ -29,8 +30,9 @@ use tokio::runtime::Handle;
 use crate::util::cargo::cargo_target;
 use crate::util::cli;
 use crate::util::common_options::{BinaryDownloadOptions, ProbeOptions};
+use crate::util::itm::{ItmDestination, ItmStream};
 use crate::util::logging::setup_logging;
-use crate::util::rtt::RttConfig;
+use crate::util::rtt::{DefmtState, RttConfig};
 use crate::util::{cargo::build_artifact, common_options::CargoOptions, logging};
 use crate::{Config, parse_and_resolve_cli_args, run_app};
 
@@ -400,14 +402,52 @@ async fn run_embed(
         }));
     }
 
-    if config.rtt.enabled {
-        run_rttui_app(name, elf, &session, rtt_handle, core_id, config, offset).await?;
-    } else if should_resume_core(&config) {
-        let status = core.status().await?;
-        if matches!(status, WireCoreStatus::Halted(_)) {
-            core.run().await?;
+    let itm = if config.itm.enabled {
+        Some(start_itm(&session, core_id, &config, elf.as_deref(), offset).await?)
+    } else {
+        None
+    };
+
+    let result = match &itm {
+        Some(itm) if config.rtt.enabled => {
+            tokio::select! {
+                result = pump_itm(&session, itm) => {
+                    rttui::app::clean_up_terminal();
+                    result
+                }
+                result = run_rttui_app(name, elf, &session, rtt_handle, core_id, config, offset) => result,
+            }
         }
+        Some(itm) => {
+            if should_resume_core(&config) {
+                let status = core.status().await?;
+                if matches!(status, WireCoreStatus::Halted(_)) {
+                    core.run().await?;
+                }
+            }
+            tokio::select! {
+                result = pump_itm(&session, itm) => result,
+                _ = tokio::signal::ctrl_c() => Ok(()),
+            }
+        }
+        None if config.rtt.enabled => {
+            run_rttui_app(name, elf, &session, rtt_handle, core_id, config, offset).await
+        }
+        None => {
+            if should_resume_core(&config) {
+                let status = core.status().await?;
+                if matches!(status, WireCoreStatus::Halted(_)) {
+                    core.run().await?;
+                }
+            }
+            Ok(())
+        }
+    };
+
+    if let Some(itm) = itm {
+        itm.finish().context("Failed to decode the ITM trace")?;
     }
+    result?;
 
     if let Some(gdb_task) = gdb_task {
         let _ = gdb_task.await;
@@ -430,6 +470,52 @@ fn should_resume_core(config: &config::Config) -> bool {
     }
 }
 
+/// Starts capturing the ITM trace of the core, and decoding the configured stimulus ports.
+async fn start_itm(
+    session: &SessionInterface,
+    core_id: usize,
+    config: &config::Config,
+    elf: Option<&[u8]>,
+    timezone_offset: UtcOffset,
+) -> Result<ItmStream> {
+    if config.rtt.enabled
+        && config
+            .itm
+            .ports
+            .iter()
+            .any(|port| port.destination == ItmDestination::Stdout)
+    {
+        return Err(anyhow!(
+            "ITM ports cannot be printed while the RTTUI is enabled, send them to a file or TCP socket instead"
+        ));
+    }
+
+    let sink = match config.itm.swo_clock {
+        Some(clock) => ItmSink::Swo {
+            clock,
+            baud: config.itm.swo_baud,
+        },
+        None => ItmSink::TraceMemory,
+    };
+    session.start_itm(core_id as u32, sink).await?;
+
+    let defmt_data = match elf {
+        Some(elf) => DefmtState::try_from_bytes(elf)?,
+        None => None,
+    };
+
+    ItmStream::spawn(config.itm.ports.clone(), defmt_data, Some(timezone_offset))
+}
+
+/// Forwards the captured ITM trace to the decoder, until reading the trace fails.
+async fn pump_itm(session: &SessionInterface, itm: &ItmStream) -> Result<()> {
+    loop {
+        itm.push(session.read_itm().await?);
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
 /// After flashing or reset, leave the core halted with the image ready to run.
 async fn prepare_halted_image(
     session: &SessionInterface,
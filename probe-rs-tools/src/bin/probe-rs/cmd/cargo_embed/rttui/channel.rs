@@ -2,9 +2,9 @@ use std::net::SocketAddr;
 
 use probe_rs::rtt::Error;
 
-use crate::{
-    cmd::cargo_embed::rttui::tcp::TcpPublisher,
-    util::rtt::{ProcessedRttData, RttDataHandler, RttDecoder},
+use crate::util::{
+    rtt::{ProcessedRttData, RttDataHandler, RttDecoder},
+    tcp::TcpPublisher,
 };
 
 pub enum ChannelData {
@@ -2,4 +2,3 @@ pub mod app;
 pub mod channel;
 pub mod event;
 pub mod tab;
-pub mod tcp;
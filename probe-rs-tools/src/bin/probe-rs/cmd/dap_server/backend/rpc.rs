@@ -14,6 +14,7 @@ use crate::cmd::dap_server::debug_adapter::dap::dap_types::{
     DisassembledInstruction, EvaluateArguments, EvaluateResponseBody, Scope, Source, Variable,
 };
 use crate::cmd::dap_server::server::configuration::FlashingConfig;
+use crate::cmd::dap_server::server::core_data::RtosThreadStack;
 use crate::rpc::{
     Key, Session,
     functions::{
@@ -35,16 +36,20 @@ use probe_rs_debug::{
 };
 use probe_rs_rpc::RpcError;
 use probe_rs_rpc::breakpoints::{
-    SourceBreakpointLocation, WireSourceLocation as WireBreakpointSourceLocation,
+    BreakpointTarget, SourceBreakpointLocation, WireSourceLocation as WireBreakpointSourceLocation,
 };
-use probe_rs_rpc::core_ops::{WireCoreMetadata, WireCoreStatus, WireRegisterId, WireSteppingMode};
+use probe_rs_rpc::core_ops::{
+    WireCoreMetadata, WireCoreStatus, WireRegisterId, WireSteppingMode, WireWatchpoint,
+};
+use probe_rs_rpc::debug_vars::WireDataBreakpointInfo;
 use probe_rs_rpc::disassemble::{WireDisassembledInstruction, WireSource};
+use probe_rs_rpc::embassy::WireEmbassyTasks;
 use probe_rs_rpc::flash::{
     DownloadOptions as WireDownloadOptions, ProgressEvent as WireProgressEvent, VerifyResult,
 };
 use probe_rs_rpc::info::WireSessionTargetMetadata;
 use probe_rs_rpc::stack_trace::{
-    RichStackTraces, SourceLocation as WireSourceLocation, WireDebugRegister,
+    RichStackTraceFrame, RichStackTraces, SourceLocation as WireSourceLocation, WireDebugRegister,
 };
 use probe_rs_rpc_client::{
     CoreInterface as RpcCoreClient, ResolvedUpload, RpcClient, SessionInterface,
@@ -66,6 +71,44 @@ pub(crate) fn rpc_err(err: impl Into<anyhow::Error>) -> Error {
     Error::Other(format!("{err:?}"))
 }
 
+/// Rebuild the frames of one unwound stack from the wire, grouping the
+/// consecutive frames that share a register dump.
+fn rebuild_stack_frames(
+    core_registers: &'static CoreRegisters,
+    wire_frames: &[RichStackTraceFrame],
+) -> Vec<StackFrame> {
+    let mut frames: Vec<StackFrame> = Vec::with_capacity(wire_frames.len());
+
+    let mut idx = 0;
+    while idx < wire_frames.len() {
+        let group_start = idx;
+        let group_regs = wire_frames[idx].registers.clone();
+        while idx < wire_frames.len() && wire_frames[idx].registers == group_regs {
+            idx += 1;
+        }
+        let group = &wire_frames[group_start..idx];
+        let cfa = group[0].canonical_frame_address;
+        let registers = rebuild_debug_registers(core_registers, &group_regs);
+
+        for wf in group {
+            let pc_value: RegisterValue = from_wire_register_value(wf.program_counter);
+            frames.push(StackFrame {
+                id: ObjectRef::from(wf.id as i64),
+                function_name: wf.function_name.clone(),
+                source_location: wf.location.as_ref().map(from_wire_location),
+                registers: registers.clone(),
+                pc: pc_value,
+                frame_base: wf.frame_base,
+                is_inlined: wf.is_inlined,
+                local_variables: None,
+                canonical_frame_address: cfa,
+            });
+        }
+    }
+
+    frames
+}
+
 /// Rebuild a [`DebugRegisters`] from a wire register dump, using the same
 /// static [`CoreRegisters`] file the server used so the resulting
 /// `DebugRegister` ordering and DWARF ids match what the server's `unwind`
@@ -114,6 +157,8 @@ pub struct RpcBackend {
     /// Per-core metadata cached at attach-time so static-property methods
     /// (register file, architecture, ...) need no round trip.
     pub(crate) core_metadata: Vec<CoreMetadata>,
+    /// Fall back to flash breakpoints when no hardware breakpoint unit is free.
+    flash_breakpoints: bool,
 }
 
 #[derive(Clone)]
@@ -217,9 +262,16 @@ impl RpcBackend {
             sessid,
             target_metadata,
             core_metadata,
+            flash_breakpoints: false,
         }
     }
 
+    /// Enables reprogramming flash sectors with break instructions once the hardware
+    /// breakpoint units run out.
+    pub(crate) fn use_flash_breakpoints(&mut self, enabled: bool) {
+        self.flash_breakpoints = enabled;
+    }
+
     pub(crate) async fn read_memory_8(
         &self,
         core_index: usize,
@@ -232,6 +284,18 @@ impl RpcBackend {
             .map_err(rpc_err)
     }
 
+    pub(crate) async fn read_memory_32(
+        &self,
+        core_index: usize,
+        address: u64,
+        count: usize,
+    ) -> Result<Vec<u32>, Error> {
+        self.core(core_index)
+            .read_memory_32(address, count)
+            .await
+            .map_err(rpc_err)
+    }
+
     pub(crate) async fn read_bytes(
         &self,
         core_index: usize,
@@ -256,6 +320,15 @@ impl RpcBackend {
             .map_err(rpc_err)
     }
 
+    /// The spawned tasks of the embassy executor, or `None` if the program
+    /// does not use it.
+    pub(crate) async fn embassy_tasks(
+        &self,
+        core_index: usize,
+    ) -> Result<Option<WireEmbassyTasks>, Error> {
+        self.core(core_index).embassy_tasks().await.map_err(rpc_err)
+    }
+
     pub(crate) async fn reset_and_halt(
         &self,
         core_index: usize,
@@ -279,25 +352,54 @@ impl RpcBackend {
         &self,
         locations: Vec<SourceBreakpointLocation>,
     ) -> Result<Vec<Result<VerifiedBreakpoint, String>>, Error> {
+        let resolved = self
+            .resolve_breakpoint_targets(
+                locations
+                    .into_iter()
+                    .map(BreakpointTarget::Source)
+                    .collect(),
+            )
+            .await?;
+        Ok(resolved
+            .into_iter()
+            .map(|resolution| resolution.map(|mut breakpoints| breakpoints.swap_remove(0)))
+            .collect())
+    }
+
+    /// Resolve every instance of each named function to a breakpoint location.
+    pub(crate) async fn resolve_function_breakpoints(
+        &self,
+        names: Vec<String>,
+    ) -> Result<Vec<Result<Vec<VerifiedBreakpoint>, String>>, Error> {
+        self.resolve_breakpoint_targets(names.into_iter().map(BreakpointTarget::Function).collect())
+            .await
+    }
+
+    async fn resolve_breakpoint_targets(
+        &self,
+        targets: Vec<BreakpointTarget>,
+    ) -> Result<Vec<Result<Vec<VerifiedBreakpoint>, String>>, Error> {
         let resolved = self
             .session_interface()
-            .resolve_source_breakpoints(locations)
+            .resolve_source_breakpoints(targets)
             .await
             .map_err(rpc_err)?;
         Ok(resolved
             .into_iter()
-            .map(
-                |resolution| match (resolution.breakpoint, resolution.error) {
-                    (Some(breakpoint), _) => Ok(VerifiedBreakpoint {
+            .map(|resolution| match resolution.error {
+                Some(error) => Err(error),
+                None if resolution.breakpoints.is_empty() => {
+                    Err("Server returned an empty breakpoint resolution.".to_string())
+                }
+                None => Ok(resolution
+                    .breakpoints
+                    .into_iter()
+                    .map(|breakpoint| VerifiedBreakpoint {
                         address: breakpoint.address,
                         source_location: from_wire_source_location(breakpoint.source_location),
-                    }),
-                    (None, Some(error)) => Err(error),
-                    (None, None) => {
-                        Err("Server returned an empty breakpoint resolution.".to_string())
-                    }
-                },
-            )
+                    })
+                    .collect()),
+            })
             .collect())
     }
 
@@ -330,22 +432,58 @@ impl RpcBackend {
     }
 
     /// Set a batch of hardware breakpoints, reporting per-address failures in
-    /// place rather than failing the whole batch.
+    /// place rather than failing the whole batch. If enabled, addresses without a
+    /// free hardware unit get a flash breakpoint instead.
     pub(crate) async fn set_hw_breakpoints(
         &mut self,
         core_index: usize,
         addresses: Vec<u64>,
     ) -> Result<Vec<Result<(), RpcError>>, Error> {
         let client = self.core(core_index);
-        client.set_hw_breakpoints(addresses).await.map_err(rpc_err)
+        let mut results = client
+            .set_hw_breakpoints(addresses.clone())
+            .await
+            .map_err(rpc_err)?;
+        if !self.flash_breakpoints {
+            return Ok(results);
+        }
+
+        let failed: Vec<usize> = results
+            .iter()
+            .enumerate()
+            .filter_map(|(i, result)| result.is_err().then_some(i))
+            .collect();
+        if failed.is_empty() {
+            return Ok(results);
+        }
+
+        let flash_results = client
+            .set_flash_breakpoints(failed.iter().map(|&i| addresses[i]).collect())
+            .await
+            .map_err(rpc_err)?;
+        for (i, flash_result) in failed.into_iter().zip(flash_results) {
+            // Keep the hardware breakpoint error if the address is not in flash either.
+            if flash_result.is_ok() {
+                results[i] = flash_result;
+            }
+        }
+
+        Ok(results)
     }
 
+    /// Clears hardware breakpoints, and the flash breakpoints they may have fallen back to.
     pub(crate) async fn clear_hw_breakpoints(
         &mut self,
         core_index: usize,
         addresses: Vec<u64>,
     ) -> Result<(), Error> {
         let client = self.core(core_index);
+        if self.flash_breakpoints {
+            client
+                .clear_flash_breakpoints(addresses.clone())
+                .await
+                .map_err(rpc_err)?;
+        }
         client
             .clear_hw_breakpoints(addresses)
             .await
@@ -398,6 +536,48 @@ impl RpcBackend {
         client.set_hw_breakpoint(address).await.map_err(rpc_err)
     }
 
+    pub(crate) async fn set_hw_watchpoint(
+        &mut self,
+        core_index: usize,
+        watchpoint: WireWatchpoint,
+    ) -> Result<(), Error> {
+        let client = self.core(core_index);
+        client.set_hw_watchpoint(watchpoint).await.map_err(rpc_err)
+    }
+
+    pub(crate) async fn clear_hw_watchpoint(
+        &mut self,
+        core_index: usize,
+        watchpoint: WireWatchpoint,
+    ) -> Result<(), Error> {
+        let client = self.core(core_index);
+        client
+            .clear_hw_watchpoint(watchpoint)
+            .await
+            .map_err(rpc_err)
+    }
+
+    pub(crate) async fn triggered_watchpoint(
+        &mut self,
+        core_index: usize,
+    ) -> Result<Option<WireWatchpoint>, Error> {
+        let client = self.core(core_index);
+        client.triggered_watchpoint().await.map_err(rpc_err)
+    }
+
+    pub(crate) async fn data_breakpoint_info(
+        &mut self,
+        core_index: usize,
+        variables_reference: Option<i64>,
+        frame_id: Option<u32>,
+        name: String,
+    ) -> Result<Option<WireDataBreakpointInfo>, Error> {
+        self.session_interface()
+            .data_breakpoint_info(core_index as u32, variables_reference, frame_id, name)
+            .await
+            .map_err(rpc_err)
+    }
+
     pub(crate) async fn set_variable(
         &mut self,
         core_index: usize,
@@ -546,6 +726,18 @@ impl RpcBackend {
             .map_err(rpc_err)
     }
 
+    pub(crate) async fn disable_vector_catch(
+        &mut self,
+        core_index: usize,
+        condition: VectorCatchCondition,
+    ) -> Result<(), Error> {
+        let client = self.core(core_index);
+        client
+            .disable_vector_catch(to_wire_vector_catch_condition(condition))
+            .await
+            .map_err(rpc_err)
+    }
+
     /// Halt if running, enable each requested condition, then resume if the
     /// core was halted on entry.
     pub(crate) async fn apply_vector_catch(
@@ -579,6 +771,31 @@ impl RpcBackend {
         Ok(())
     }
 
+    /// Halt if running, enable or disable each condition, then resume if the
+    /// core was running on entry. Returns the result for each condition.
+    pub(crate) async fn set_vector_catch(
+        &mut self,
+        core_index: usize,
+        conditions: Vec<(VectorCatchCondition, bool)>,
+    ) -> Result<Vec<Result<(), Error>>, Error> {
+        let was_halted = self.core_halted(core_index).await?;
+        if !was_halted {
+            self.halt(core_index, Duration::from_millis(100)).await?;
+        }
+        let mut results = Vec::with_capacity(conditions.len());
+        for (condition, enabled) in conditions {
+            results.push(if enabled {
+                self.enable_vector_catch(core_index, condition).await
+            } else {
+                self.disable_vector_catch(core_index, condition).await
+            });
+        }
+        if !was_halted {
+            self.run(core_index).await?;
+        }
+        Ok(results)
+    }
+
     pub(crate) async fn debug_step(
         &mut self,
         core_index: usize,
@@ -615,9 +832,31 @@ impl RpcBackend {
         core_index: usize,
         max_frames: usize,
     ) -> Result<Vec<StackFrame>, Error> {
+        let (frames, _) = self.unwind(core_index, max_frames, false).await?;
+        Ok(frames)
+    }
+
+    /// Like [`Self::unwind_stack`], but also unwinds the threads of the RTOS
+    /// that are not running on the core, from the registers saved on their
+    /// stacks. The threads are empty if the program does not use a supported
+    /// RTOS.
+    pub(crate) async fn unwind_stack_and_threads(
+        &mut self,
+        core_index: usize,
+        max_frames: usize,
+    ) -> Result<(Vec<StackFrame>, Vec<RtosThreadStack>), Error> {
+        self.unwind(core_index, max_frames, true).await
+    }
+
+    async fn unwind(
+        &mut self,
+        core_index: usize,
+        max_frames: usize,
+        rtos_threads: bool,
+    ) -> Result<(Vec<StackFrame>, Vec<RtosThreadStack>), Error> {
         let session = self.session_interface();
         let rich: RichStackTraces = session
-            .take_rich_stack_trace(Some(core_index as u32), max_frames as u32)
+            .take_rich_stack_trace(Some(core_index as u32), max_frames as u32, rtos_threads)
             .await
             .map_err(rpc_err)?;
 
@@ -625,7 +864,7 @@ impl RpcBackend {
         // any core in a session that has no debug info. Neither is an error
         // here — the core simply has no frames to display.
         let Some(rich_core) = rich.cores.into_iter().find(|c| c.core == core_index as u32) else {
-            return Ok(Vec::new());
+            return Ok((Vec::new(), Vec::new()));
         };
 
         // Clone metadata before borrowing `self` via `self.core(...)`.
@@ -637,37 +876,17 @@ impl RpcBackend {
             .find_map(|((idx, _), meta)| (*idx == core_index).then_some(meta.clone()))
             .ok_or(Error::CoreNotFound(core_index))?;
 
-        let wire_frames = rich_core.frames;
-        let mut frames: Vec<StackFrame> = Vec::with_capacity(wire_frames.len());
-
-        let mut idx = 0;
-        while idx < wire_frames.len() {
-            let group_start = idx;
-            let group_regs = wire_frames[idx].registers.clone();
-            while idx < wire_frames.len() && wire_frames[idx].registers == group_regs {
-                idx += 1;
-            }
-            let group = &wire_frames[group_start..idx];
-            let cfa = group[0].canonical_frame_address;
-            let registers = rebuild_debug_registers(metadata.registers, &group_regs);
-
-            for wf in group {
-                let pc_value: RegisterValue = from_wire_register_value(wf.program_counter);
-                frames.push(StackFrame {
-                    id: ObjectRef::from(wf.id as i64),
-                    function_name: wf.function_name.clone(),
-                    source_location: wf.location.as_ref().map(from_wire_location),
-                    registers: registers.clone(),
-                    pc: pc_value,
-                    frame_base: wf.frame_base,
-                    is_inlined: wf.is_inlined,
-                    local_variables: None,
-                    canonical_frame_address: cfa,
-                });
-            }
-        }
+        let frames = rebuild_stack_frames(metadata.registers, &rich_core.frames);
+        let threads = rich_core
+            .threads
+            .into_iter()
+            .map(|thread| RtosThreadStack {
+                stack_frames: rebuild_stack_frames(metadata.registers, &thread.frames),
+                thread: thread.thread,
+            })
+            .collect();
 
-        Ok(frames)
+        Ok((frames, threads))
     }
 
     /// Synchronize the server's per-core SVD state with the configured path.
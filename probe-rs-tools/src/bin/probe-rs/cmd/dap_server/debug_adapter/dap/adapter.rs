@@ -12,8 +12,10 @@ use crate::cmd::dap_server::{
         protocol::{BoxedAdapter, ProtocolAdapter, ProtocolHelper},
     },
     server::{
+        breakpoint_conditions::BreakpointConditions,
         configuration::ConsoleLog,
         core_data::CoreData,
+        fault_analysis::ExceptionFilter,
         session_data::{ActiveBreakpoint, BreakpointType, SessionData, SourceLocationScope},
     },
 };
@@ -30,6 +32,8 @@ use probe_rs_debug::{
     registers::{DebugRegister, DebugRegisters},
 };
 use probe_rs_rpc::breakpoints::SourceBreakpointLocation;
+use probe_rs_rpc::core_ops::{WireWatchpoint, WireWatchpointKind};
+use probe_rs_rpc::rtos::{WireRtosThread, WireThreadState};
 use probe_rs_rpc::rtt_config::DataFormat;
 use serde::{Serialize, de::DeserializeOwned};
 use serde_json::Value;
@@ -667,12 +671,17 @@ impl DebugAdapter {
                 // state. Do not expose the old display cache while the
                 // server refresh is pending or if it fails.
                 session_data.core_data[cd_idx].invalidate_stack_frame_cache();
-                match session_data.backend.unwind_stack(core_index, 500).await {
-                    Ok(frames) => {
+                match session_data
+                    .backend
+                    .unwind_stack_and_threads(core_index, 500)
+                    .await
+                {
+                    Ok((frames, threads)) => {
                         // Keep the ids assigned by the authoritative
                         // server cache; scopes/variables resolve those
                         // exact ids on subsequent requests.
                         session_data.core_data[cd_idx].replace_stack_frame_cache(frames);
+                        session_data.core_data[cd_idx].replace_rtos_thread_cache(threads);
                     }
                     Err(error) => {
                         let message = format!(
@@ -883,6 +892,29 @@ impl DebugAdapter {
                 );
             }
         };
+        // Conditions are evaluated by the debugger, so a breakpoint with a condition that can't be
+        // parsed is rejected instead of being set unconditionally.
+        let mut conditions = Vec::with_capacity(requested_bps.len());
+        let resolved: Vec<Result<VerifiedBreakpoint, String>> = resolved
+            .into_iter()
+            .zip(&requested_bps)
+            .map(|(result, bp)| {
+                match BreakpointConditions::new(
+                    bp.condition.as_deref(),
+                    bp.hit_condition.as_deref(),
+                    bp.log_message.as_deref(),
+                ) {
+                    Ok(parsed) => {
+                        conditions.push(parsed);
+                        result
+                    }
+                    Err(error) => {
+                        conditions.push(None);
+                        Err(error)
+                    }
+                }
+            })
+            .collect();
 
         // One round trip to clear the old set, one to set the new set.
         if let Err(error) = session_data
@@ -908,7 +940,7 @@ impl DebugAdapter {
             .map_err(|e| DebuggerError::Other(anyhow!("Failed to set breakpoints: {e}")))?;
 
         let mut created_breakpoints: Vec<Breakpoint> = Vec::with_capacity(requested_bps.len());
-        let mut to_cache: Vec<(u64, SourceLocation)> = Vec::new();
+        let mut to_cache: Vec<(u64, SourceLocation, Option<BreakpointConditions>)> = Vec::new();
         let mut set_idx = 0;
         for (i, bp) in requested_bps.iter().enumerate() {
             match &resolved[i] {
@@ -932,7 +964,7 @@ impl DebugAdapter {
                     let set_result = set_results.get(set_idx);
                     set_idx += 1;
                     if matches!(set_result, Some(Ok(()))) {
-                        to_cache.push((*address, source_location.clone()));
+                        to_cache.push((*address, source_location.clone(), conditions[i].take()));
                         created_breakpoints.push(Breakpoint {
                             column: source_location.column.map(|col| match col {
                                 ColumnType::LeftEdge => 0_i64,
@@ -979,13 +1011,14 @@ impl DebugAdapter {
 
         // Update the client-side breakpoint cache.
         if let Ok(core_data) = session_data.core_data_mut(core_index) {
-            for (address, source_location) in to_cache {
+            for (address, source_location, conditions) in to_cache {
                 core_data.breakpoints.push(ActiveBreakpoint {
                     breakpoint_type: BreakpointType::SourceBreakpoint {
                         source: Box::new(source.clone()),
                         location: SourceLocationScope::Specific(source_location),
                     },
                     address,
+                    conditions,
                 });
             }
         }
@@ -1005,8 +1038,8 @@ impl DebugAdapter {
         let arguments: SetInstructionBreakpointsArguments = get_arguments(self, request)?;
         let requested: Vec<InstructionBreakpoint> = arguments.breakpoints;
 
-        // Parse memory references and collect existing instruction bps to clear.
-        let (parsed, clear_addrs) = {
+        // Parse memory references and conditions, and collect existing instruction bps to clear.
+        let (parsed, mut conditions, clear_addrs) = {
             let core_data = match session_data.core_data_mut(core_index) {
                 Err(error) => return self.send_response::<()>(request, Err(&error)),
                 Ok(core_data) => core_data,
@@ -1020,15 +1053,28 @@ impl DebugAdapter {
             core_data
                 .breakpoints
                 .retain(|ab| !matches!(ab.breakpoint_type, BreakpointType::InstructionBreakpoint));
-            let parsed: Vec<Option<u64>> = requested
+            let mut conditions = Vec::with_capacity(requested.len());
+            let parsed: Vec<Result<u64, String>> = requested
                 .iter()
                 .map(|rb| {
-                    MemoryAddress::try_from(rb.instruction_reference.as_str())
-                        .ok()
-                        .map(|MemoryAddress(addr)| addr)
+                    let MemoryAddress(address) = MemoryAddress::try_from(
+                        rb.instruction_reference.as_str(),
+                    )
+                    .map_err(|_| {
+                        format!(
+                            "Invalid memory reference specified: {:?}",
+                            rb.instruction_reference
+                        )
+                    })?;
+                    conditions.push(BreakpointConditions::new(
+                        rb.condition.as_deref(),
+                        rb.hit_condition.as_deref(),
+                        None,
+                    )?);
+                    Ok(address)
                 })
                 .collect();
-            (parsed, clear_addrs)
+            (parsed, conditions.into_iter(), clear_addrs)
         };
 
         if let Err(error) = session_data
@@ -1038,7 +1084,7 @@ impl DebugAdapter {
         {
             tracing::warn!("Failed to clear instruction breakpoints. {}", error);
         }
-        let set_addrs: Vec<u64> = parsed.iter().copied().flatten().collect();
+        let set_addrs: Vec<u64> = parsed.iter().flatten().copied().collect();
         let set_results = session_data
             .backend
             .set_hw_breakpoints(core_index, set_addrs.clone())
@@ -1057,31 +1103,30 @@ impl DebugAdapter {
             });
 
         let mut breakpoints: Vec<Breakpoint> = Vec::with_capacity(requested.len());
-        let mut to_cache: Vec<u64> = Vec::new();
+        let mut to_cache: Vec<(u64, Option<BreakpointConditions>)> = Vec::new();
         let mut set_idx = 0;
         for (i, rb) in requested.iter().enumerate() {
-            match parsed[i] {
-                None => breakpoints.push(Breakpoint {
+            match &parsed[i] {
+                Err(message) => breakpoints.push(Breakpoint {
                     column: None,
                     end_column: None,
                     end_line: None,
                     id: None,
                     instruction_reference: Some(rb.instruction_reference.clone()),
                     line: None,
-                    message: Some(format!(
-                        "Invalid memory reference specified: {:?}",
-                        rb.instruction_reference
-                    )),
+                    message: Some(message.clone()),
                     offset: None,
                     source: None,
                     verified: false,
                     reason: None,
                 }),
-                Some(memory_reference) => {
+                Ok(memory_reference) => {
+                    let memory_reference = *memory_reference;
                     let set_result = set_results.get(set_idx);
                     set_idx += 1;
+                    let conditions = conditions.next().flatten();
                     if matches!(set_result, Some(Ok(()))) {
-                        to_cache.push(memory_reference);
+                        to_cache.push((memory_reference, conditions));
                     }
                     let source_location = if matches!(set_result, Some(Ok(()))) {
                         source_locations.get(set_idx - 1).cloned().flatten()
@@ -1101,10 +1146,11 @@ impl DebugAdapter {
         }
 
         if let Ok(core_data) = session_data.core_data_mut(core_index) {
-            for address in to_cache {
+            for (address, conditions) in to_cache {
                 core_data.breakpoints.push(ActiveBreakpoint {
                     breakpoint_type: BreakpointType::InstructionBreakpoint,
                     address,
+                    conditions,
                 });
             }
         }
@@ -1124,6 +1170,485 @@ impl DebugAdapter {
         )
     }
 
+    pub(crate) async fn set_function_breakpoints(
+        &mut self,
+        session_data: &mut SessionData,
+        core_index: usize,
+        request: &Request,
+    ) -> Result<()> {
+        let arguments: SetFunctionBreakpointsArguments = get_arguments(self, request)?;
+        let requested = arguments.breakpoints;
+
+        let clear_addrs = {
+            let core_data = match session_data.core_data_mut(core_index) {
+                Err(error) => return self.send_response::<()>(request, Err(&error)),
+                Ok(core_data) => core_data,
+            };
+            let clear_addrs: Vec<u64> = core_data
+                .breakpoints
+                .iter()
+                .filter(|ab| {
+                    matches!(
+                        ab.breakpoint_type,
+                        BreakpointType::FunctionBreakpoint { .. }
+                    )
+                })
+                .map(|ab| ab.address)
+                .collect();
+            core_data.breakpoints.retain(|ab| {
+                !matches!(
+                    ab.breakpoint_type,
+                    BreakpointType::FunctionBreakpoint { .. }
+                )
+            });
+            clear_addrs
+        };
+
+        let resolved = match session_data
+            .backend
+            .resolve_function_breakpoints(requested.iter().map(|fb| fb.name.clone()).collect())
+            .await
+        {
+            Ok(resolved) => resolved,
+            Err(error) => {
+                return self.send_response::<()>(
+                    request,
+                    Err(&DebuggerError::Other(anyhow!(
+                        "Cannot set function breakpoint without debug information: {error}"
+                    ))),
+                );
+            }
+        };
+        // A function breakpoint with a condition that can't be parsed is rejected, like a source
+        // breakpoint.
+        let resolved: Vec<_> = resolved
+            .into_iter()
+            .zip(&requested)
+            .map(|(instances, fb)| -> Result<_, String> {
+                let conditions = BreakpointConditions::new(
+                    fb.condition.as_deref(),
+                    fb.hit_condition.as_deref(),
+                    None,
+                )?;
+                Ok((instances?, conditions))
+            })
+            .collect();
+
+        if let Err(error) = session_data
+            .backend
+            .clear_hw_breakpoints(core_index, clear_addrs)
+            .await
+        {
+            tracing::warn!("Failed to clear function breakpoints. {}", error);
+        }
+        let set_addrs: Vec<u64> = resolved
+            .iter()
+            .flatten()
+            .flat_map(|(instances, _)| instances.iter().map(|instance| instance.address))
+            .collect();
+        let set_results = session_data
+            .backend
+            .set_hw_breakpoints(core_index, set_addrs)
+            .await
+            .map_err(|e| {
+                DebuggerError::Other(anyhow!("Failed to set function breakpoints: {e}"))
+            })?;
+
+        let mut breakpoints: Vec<Breakpoint> = Vec::with_capacity(requested.len());
+        let mut to_cache: Vec<ActiveBreakpoint> = Vec::new();
+        let mut set_results = set_results.into_iter();
+        for (fb, resolution) in requested.iter().zip(resolved) {
+            let (instances, conditions) = match resolution {
+                Ok(resolution) => resolution,
+                Err(error) => {
+                    breakpoints.push(Breakpoint {
+                        column: None,
+                        end_column: None,
+                        end_line: None,
+                        id: None,
+                        instruction_reference: None,
+                        line: None,
+                        message: Some(format!(
+                            "Cannot set function breakpoint on `{}`: {error}",
+                            fb.name
+                        )),
+                        offset: None,
+                        source: None,
+                        verified: false,
+                        reason: Some("failed".to_string()),
+                    });
+                    continue;
+                }
+            };
+
+            let mut set_instances = Vec::new();
+            let mut set_error = None;
+            for instance in instances {
+                match set_results.next() {
+                    Some(Ok(())) => set_instances.push(instance),
+                    Some(Err(error)) => set_error = Some(error.to_string()),
+                    None => {}
+                }
+            }
+            for instance in &set_instances {
+                to_cache.push(ActiveBreakpoint {
+                    breakpoint_type: BreakpointType::FunctionBreakpoint {
+                        name: fb.name.clone(),
+                    },
+                    address: instance.address,
+                    conditions: conditions.clone(),
+                });
+            }
+
+            let Some(first) = set_instances.first() else {
+                breakpoints.push(Breakpoint {
+                    column: None,
+                    end_column: None,
+                    end_line: None,
+                    id: None,
+                    instruction_reference: None,
+                    line: None,
+                    message: Some(format!(
+                        "Failed to set function breakpoint on `{}`: {}",
+                        fb.name,
+                        set_error.unwrap_or_else(|| "no breakpoint could be set".to_string())
+                    )),
+                    offset: None,
+                    source: None,
+                    verified: false,
+                    reason: Some("failed".to_string()),
+                });
+                continue;
+            };
+            let address = first.address;
+            let location = &first.source_location;
+            breakpoints.push(Breakpoint {
+                column: location.column.map(|col| match col {
+                    ColumnType::LeftEdge => 0_i64,
+                    ColumnType::Column(c) => c as i64,
+                }),
+                end_column: None,
+                end_line: None,
+                id: Some(address as i64),
+                instruction_reference: Some(format!("{address:#010X}")),
+                line: location.line.map(|line| line as i64),
+                message: Some(match set_instances.len() {
+                    1 => format!("Function breakpoint at memory address: {address:#010X}"),
+                    count => format!("Function breakpoint at {count} locations"),
+                }),
+                offset: None,
+                source: get_dap_source(location),
+                verified: true,
+                reason: None,
+            });
+        }
+
+        if let Ok(core_data) = session_data.core_data_mut(core_index) {
+            core_data.breakpoints.extend(to_cache);
+        }
+
+        for breakpoint_response in &breakpoints {
+            if !breakpoint_response.verified
+                && let Some(message) = &breakpoint_response.message
+            {
+                self.log_to_console(format!("Warning: {message}"));
+                self.show_message(MessageSeverity::Warning, message.clone());
+            }
+        }
+
+        self.send_response(
+            request,
+            Ok(Some(SetFunctionBreakpointsResponseBody { breakpoints })),
+        )
+    }
+
+    /// Enables the vector catch conditions and the panic breakpoint selected in the client, and
+    /// disables the others. The selection replaces the `catch_*` options of the launch
+    /// configuration.
+    pub(crate) async fn set_exception_breakpoints(
+        &mut self,
+        session_data: &mut SessionData,
+        core_index: usize,
+        request: &Request,
+    ) -> Result<()> {
+        let arguments: SetExceptionBreakpointsArguments = get_arguments(self, request)?;
+        let mut selected: Vec<ExceptionFilter> = Vec::new();
+        for id in arguments.filters.iter().chain(
+            arguments
+                .filter_options
+                .iter()
+                .flatten()
+                .map(|option| &option.filter_id),
+        ) {
+            match ExceptionFilter::from_id(id) {
+                Some(filter) if !selected.contains(&filter) => selected.push(filter),
+                Some(_) => {}
+                None => tracing::warn!("Ignoring unknown exception filter `{id}`."),
+            }
+        }
+
+        let vector_catches: Vec<_> = ExceptionFilter::ALL
+            .into_iter()
+            .filter_map(|filter| {
+                let condition = filter.vector_catch()?;
+                Some((filter, condition, selected.contains(&filter)))
+            })
+            .collect();
+        let vector_catch_results = match session_data
+            .backend
+            .set_vector_catch(
+                core_index,
+                vector_catches
+                    .iter()
+                    .map(|&(_, condition, enabled)| (condition, enabled))
+                    .collect(),
+            )
+            .await
+        {
+            Ok(results) => results,
+            Err(error) => {
+                return self.send_response::<()>(request, Err(&DebuggerError::ProbeRs(error)));
+            }
+        };
+
+        let mut errors: Vec<(ExceptionFilter, String)> = Vec::new();
+        for ((filter, _, enabled), result) in vector_catches.into_iter().zip(vector_catch_results) {
+            match result {
+                Err(error) if enabled => errors.push((filter, error.to_string())),
+                // Not every core supports every condition, so disabling one can fail harmlessly.
+                Err(error) => tracing::debug!("Failed to disable {filter:?} vector catch: {error}"),
+                Ok(()) => {}
+            }
+        }
+        if let Err(error) = session_data
+            .set_panic_breakpoint(core_index, selected.contains(&ExceptionFilter::RustPanic))
+            .await
+        {
+            errors.push((ExceptionFilter::RustPanic, error));
+        }
+
+        let breakpoints = selected
+            .iter()
+            .map(|filter| {
+                let error = errors
+                    .iter()
+                    .find_map(|(failed, error)| (failed == filter).then_some(error));
+                Breakpoint {
+                    column: None,
+                    end_column: None,
+                    end_line: None,
+                    id: None,
+                    instruction_reference: None,
+                    line: None,
+                    message: error
+                        .map(|error| format!("Cannot halt on {}: {error}", filter.label())),
+                    offset: None,
+                    source: None,
+                    verified: error.is_none(),
+                    reason: error.map(|_| "failed".to_string()),
+                }
+            })
+            .collect();
+        self.send_response(
+            request,
+            Ok(Some(SetExceptionBreakpointsResponseBody {
+                breakpoints: Some(breakpoints),
+            })),
+        )
+    }
+
+    /// Reports the exception or panic the core halted on, as decoded when it halted.
+    pub(crate) async fn exception_info(
+        &mut self,
+        session_data: &mut SessionData,
+        core_index: usize,
+        request: &Request,
+    ) -> Result<()> {
+        let _arguments: ExceptionInfoArguments = get_arguments(self, request)?;
+        let report = match session_data.core_data(core_index) {
+            Ok(core_data) => core_data.exception_report.clone(),
+            Err(error) => return self.send_response::<()>(request, Err(&error)),
+        };
+        let Some(report) = report else {
+            return self.send_response::<()>(
+                request,
+                Err(&DebuggerError::UserMessage(
+                    "The core did not halt on an exception.".to_string(),
+                )),
+            );
+        };
+
+        self.send_response(
+            request,
+            Ok(Some(ExceptionInfoResponseBody {
+                break_mode: ExceptionBreakMode::Always,
+                description: Some(report.description),
+                details: Some(ExceptionDetails {
+                    message: (!report.details.is_empty()).then(|| report.details.join("\n")),
+                    type_name: Some(report.exception_id.clone()),
+                    ..Default::default()
+                }),
+                exception_id: report.exception_id,
+            })),
+        )
+    }
+
+    pub(crate) async fn data_breakpoint_info(
+        &mut self,
+        session_data: &mut SessionData,
+        core_index: usize,
+        request: &Request,
+    ) -> Result<()> {
+        let arguments: DataBreakpointInfoArguments = get_arguments(self, request)?;
+
+        let info = session_data
+            .backend
+            .data_breakpoint_info(
+                core_index,
+                arguments.variables_reference,
+                arguments.frame_id.map(|id| id as u32),
+                arguments.name.clone(),
+            )
+            .await;
+        let body = match info {
+            Ok(Some(info)) => DataBreakpointInfoResponseBody {
+                access_types: Some(vec![
+                    DataBreakpointAccessType::Write,
+                    DataBreakpointAccessType::Read,
+                    DataBreakpointAccessType::ReadWrite,
+                ]),
+                can_persist: Some(false),
+                data_id: Some(data_breakpoint_id(&info.name, info.address, info.size)),
+                description: format!(
+                    "`{}` ({}, {} bytes @{:#010x})",
+                    info.name, info.type_, info.size, info.address
+                ),
+            },
+            Ok(None) => DataBreakpointInfoResponseBody {
+                access_types: None,
+                can_persist: None,
+                data_id: None,
+                description: format!(
+                    "`{}` is not stored in memory, so it cannot be watched.",
+                    arguments.name
+                ),
+            },
+            Err(error) => DataBreakpointInfoResponseBody {
+                access_types: None,
+                can_persist: None,
+                data_id: None,
+                description: format!(
+                    "Cannot set a data breakpoint on `{}`: {error}",
+                    arguments.name
+                ),
+            },
+        };
+
+        self.send_response(request, Ok(Some(body)))
+    }
+
+    pub(crate) async fn set_data_breakpoints(
+        &mut self,
+        session_data: &mut SessionData,
+        core_index: usize,
+        request: &Request,
+    ) -> Result<()> {
+        let arguments: SetDataBreakpointsArguments = get_arguments(self, request)?;
+
+        // The request replaces all existing data breakpoints.
+        let cleared: Vec<WireWatchpoint> = {
+            let core_data = match session_data.core_data_mut(core_index) {
+                Err(error) => return self.send_response::<()>(request, Err(&error)),
+                Ok(core_data) => core_data,
+            };
+            let cleared = core_data
+                .breakpoints
+                .iter()
+                .filter_map(|ab| match &ab.breakpoint_type {
+                    BreakpointType::DataBreakpoint { watchpoint, .. } => Some(*watchpoint),
+                    _ => None,
+                })
+                .collect();
+            core_data
+                .breakpoints
+                .retain(|ab| !matches!(ab.breakpoint_type, BreakpointType::DataBreakpoint { .. }));
+            cleared
+        };
+        for watchpoint in cleared {
+            if let Err(error) = session_data
+                .backend
+                .clear_hw_watchpoint(core_index, watchpoint)
+                .await
+            {
+                tracing::warn!("Failed to clear data breakpoint. {}", error);
+            }
+        }
+
+        let mut breakpoints: Vec<Breakpoint> = Vec::with_capacity(arguments.breakpoints.len());
+        let mut to_cache: Vec<(String, WireWatchpoint)> = Vec::new();
+        for requested in &arguments.breakpoints {
+            let Some((address, size, name)) = parse_data_breakpoint_id(&requested.data_id) else {
+                breakpoints.push(data_breakpoint_response(
+                    None,
+                    format!("Invalid data breakpoint id: {:?}", requested.data_id),
+                ));
+                continue;
+            };
+            let kind = match requested.access_type {
+                Some(DataBreakpointAccessType::Read) => WireWatchpointKind::Read,
+                Some(DataBreakpointAccessType::ReadWrite) => WireWatchpointKind::Access,
+                Some(DataBreakpointAccessType::Write) | None => WireWatchpointKind::Write,
+            };
+            let watchpoint = WireWatchpoint::covering(address, size, kind);
+
+            match session_data
+                .backend
+                .set_hw_watchpoint(core_index, watchpoint)
+                .await
+            {
+                Ok(()) => {
+                    let mut message = format!("Data breakpoint set on `{name}`");
+                    if (watchpoint.address, watchpoint.size) != (address, size) {
+                        message.push_str(&format!(
+                            ", watching {} bytes @{:#010x} to cover it",
+                            watchpoint.size, watchpoint.address
+                        ));
+                    }
+                    breakpoints.push(data_breakpoint_response(Some(watchpoint), message));
+                    to_cache.push((name.to_string(), watchpoint));
+                }
+                Err(error) => breakpoints.push(data_breakpoint_response(
+                    None,
+                    format!("Could not set data breakpoint on `{name}`: {error}"),
+                )),
+            }
+        }
+
+        if let Ok(core_data) = session_data.core_data_mut(core_index) {
+            for (name, watchpoint) in to_cache {
+                core_data.breakpoints.push(ActiveBreakpoint {
+                    breakpoint_type: BreakpointType::DataBreakpoint { name, watchpoint },
+                    address: watchpoint.address,
+                    conditions: None,
+                });
+            }
+        }
+
+        for breakpoint_response in &breakpoints {
+            if !breakpoint_response.verified
+                && let Some(message) = &breakpoint_response.message
+            {
+                self.log_to_console(format!("Warning: {message}"));
+                self.show_message(MessageSeverity::Warning, message.clone());
+            }
+        }
+
+        self.send_response(
+            request,
+            Ok(Some(SetDataBreakpointsResponseBody { breakpoints })),
+        )
+    }
+
     pub(crate) async fn threads(
         &mut self,
         session_data: &mut SessionData,
@@ -1142,13 +1667,32 @@ impl DebugAdapter {
                 );
         }
 
-        let threads = vec![Thread {
+        let Some(core_data) = session_data.core_data_opt(core_index) else {
+            return self.send_response(request, Ok(Some(ThreadsResponseBody { threads: vec![] })));
+        };
+
+        // The running RTOS thread is the core's own thread. The others are
+        // identified by the ids the RTOS gives them, like the address of their
+        // control block.
+        let mut threads = vec![Thread {
             id: core_index as i64,
-            name: session_data
-                .core_data_opt(core_index)
-                .map(|cd| cd.target_name.clone())
-                .unwrap_or_default(),
+            name: match core_data.running_rtos_thread() {
+                Some(running) => {
+                    format!("{}: {}", core_data.target_name, rtos_thread_name(running))
+                }
+                None => core_data.target_name.clone(),
+            },
         }];
+        threads.extend(
+            core_data
+                .rtos_threads
+                .iter()
+                .filter(|thread| thread.thread.state != WireThreadState::Running)
+                .map(|thread| Thread {
+                    id: thread.thread.id as i64,
+                    name: rtos_thread_name(&thread.thread),
+                }),
+        );
         self.send_response(request, Ok(Some(ThreadsResponseBody { threads })))
     }
 
@@ -1186,7 +1730,26 @@ impl DebugAdapter {
             }
         };
 
-        let total_frames = core_data.stack_frames.len() as i64;
+        // Requests for other threads than the core's are for RTOS threads.
+        let stack_frames = if arguments.thread_id == core_index as i64 {
+            &core_data.stack_frames
+        } else if let Some(thread) = core_data
+            .rtos_threads
+            .iter()
+            .find(|thread| thread.thread.id as i64 == arguments.thread_id)
+        {
+            &thread.stack_frames
+        } else {
+            return self.send_response::<()>(
+                request,
+                Err(&DebuggerError::Other(anyhow!(
+                    "Unknown thread {}",
+                    arguments.thread_id
+                ))),
+            );
+        };
+
+        let total_frames = stack_frames.len() as i64;
 
         let mut levels = arguments.levels.unwrap_or(0);
         let start_frame = arguments.start_frame.unwrap_or(0);
@@ -1206,7 +1769,7 @@ impl DebugAdapter {
             start_frame + levels
         } as usize;
 
-        let Some(frames) = core_data.stack_frames.get(first_frame..last_frame) else {
+        let Some(frames) = stack_frames.get(first_frame..last_frame) else {
             return self.send_response::<()>(
                 request,
                 Err(&DebuggerError::Other(anyhow!(
@@ -1332,7 +1895,14 @@ impl DebugAdapter {
         core_index: usize,
         request: &Request,
     ) -> Result<()> {
-        match self
+        let single_thread = request
+            .arguments
+            .clone()
+            .and_then(|arguments| serde_json::from_value::<ContinueArguments>(arguments).ok())
+            .and_then(|arguments| arguments.single_thread)
+            .unwrap_or(false);
+
+        let mut result = self
             .continue_impl_async(
                 &mut session_data.backend,
                 session_data
@@ -1343,8 +1913,27 @@ impl DebugAdapter {
                         DebuggerError::Other(anyhow!("No core data for core {core_index}"))
                     })?,
             )
-            .await
-        {
+            .await;
+
+        // Without `singleThread`, the request resumes every halted core. Cores that share a
+        // run-control group with `core_index` were already released together with it, resuming
+        // them again is a no-op on the server.
+        if !single_thread && request.command == "continue" {
+            for core_data in session_data
+                .core_data
+                .iter_mut()
+                .filter(|c| c.core_index != core_index && c.last_known_status.is_halted())
+            {
+                if result.is_err() {
+                    break;
+                }
+                result = self
+                    .continue_impl_async(&mut session_data.backend, core_data)
+                    .await;
+            }
+        }
+
+        match result {
             Ok(all_continued) if request.command == "continue" => {
                 // If this continue was initiated as part of some other request, then do not respond.
                 self.send_response(
@@ -1801,6 +2390,17 @@ impl DebugAdapter {
                 probe_rs::Error::Other(format!("No core metadata for core {core_index}"))
             })
             .map_err(DebuggerError::ProbeRs)?;
+        // The MTB is reset together with the target.
+        if let Some(buffer) = core_data.mtb_buffer.clone() {
+            backend
+                .session_interface()
+                .start_branch_trace(buffer)
+                .await
+                .map_err(|e| {
+                    DebuggerError::Other(anyhow!("Failed to restart the MTB branch trace: {e}"))
+                })?;
+        }
+
         if [Architecture::Riscv, Architecture::Xtensa].contains(&arch) {
             let addrs: Vec<u64> = core_data.breakpoints.iter().map(|bp| bp.address).collect();
             if !addrs.is_empty() {
@@ -1972,6 +2572,44 @@ fn register_write_requires_stack_frame_refresh(register: &CoreRegister) -> bool
         || register.register_has_role(RegisterRole::ReturnAddress)
 }
 
+/// The name of an RTOS thread in the list of threads, with its state and priority.
+fn rtos_thread_name(thread: &WireRtosThread) -> String {
+    format!(
+        "{} ({:?}, priority {})",
+        thread.name, thread.state, thread.priority
+    )
+}
+
+/// Encode the resolved memory of a variable as a DAP `dataId`, so that
+/// `setDataBreakpoints` does not depend on the (short-lived) variable cache.
+fn data_breakpoint_id(name: &str, address: u64, size: u64) -> String {
+    format!("{address:#x}:{size}:{name}")
+}
+
+/// Decode a `dataId` created by [`data_breakpoint_id`] into `(address, size, name)`.
+fn parse_data_breakpoint_id(data_id: &str) -> Option<(u64, u64, &str)> {
+    let mut parts = data_id.splitn(3, ':');
+    let MemoryAddress(address) = MemoryAddress::try_from(parts.next()?).ok()?;
+    let size = parts.next()?.parse().ok().filter(|size| *size > 0)?;
+    Some((address, size, parts.next()?))
+}
+
+fn data_breakpoint_response(watchpoint: Option<WireWatchpoint>, message: String) -> Breakpoint {
+    Breakpoint {
+        column: None,
+        end_column: None,
+        end_line: None,
+        id: watchpoint.map(|watchpoint| watchpoint.address as i64),
+        instruction_reference: None,
+        line: None,
+        message: Some(message),
+        offset: None,
+        source: None,
+        verified: watchpoint.is_some(),
+        reason: None,
+    }
+}
+
 pub fn get_arguments<T: DeserializeOwned>(
     debug_adapter: &mut DebugAdapter,
     req: &Request,
@@ -2087,6 +2725,24 @@ mod tests {
         unwind_rule: UnwindRule::Preserve,
     };
 
+    #[test]
+    fn data_breakpoint_id_round_trips() {
+        let id = data_breakpoint_id("COUNTER", 0x2000_0010, 4);
+        assert_eq!(
+            parse_data_breakpoint_id(&id),
+            Some((0x2000_0010, 4, "COUNTER"))
+        );
+
+        let id = data_breakpoint_id("crate::STATE", 0x2000_0100, 8);
+        assert_eq!(
+            parse_data_breakpoint_id(&id),
+            Some((0x2000_0100, 8, "crate::STATE"))
+        );
+
+        assert_eq!(parse_data_breakpoint_id("COUNTER"), None);
+        assert_eq!(parse_data_breakpoint_id("0x20000010:0:COUNTER"), None);
+    }
+
     #[test]
     fn evaluate_dispatch_preserves_commands_and_routes_expressions_to_server() {
         assert_eq!(
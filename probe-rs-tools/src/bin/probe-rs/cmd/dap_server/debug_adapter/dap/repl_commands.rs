@@ -15,6 +15,7 @@ use std::pin::Pin;
 use std::time::Duration;
 
 pub(crate) mod backtrace;
+pub(crate) mod branch_trace;
 pub(crate) mod breakpoint;
 pub(crate) mod cpu;
 pub(crate) mod embedded_test;
@@ -22,6 +23,7 @@ pub(crate) mod info;
 pub(crate) mod inspect;
 pub(crate) mod registers;
 pub(crate) mod rtt;
+pub(crate) mod tasks;
 
 /// Returns a boxed future so handlers can `.await` backend round trips without
 /// a `block_on` bridge, while still being stored as a plain `fn` pointer in
@@ -0,0 +1,53 @@
+use crate::cmd::dap_server::{
+    DebuggerError,
+    backend::rpc::RpcBackend,
+    debug_adapter::dap::{
+        adapter::DebugAdapter,
+        dap_types::EvaluateArguments,
+        repl_commands::{EvalResponse, EvalResult, ReplCommand, async_fn},
+    },
+    server::core_data::CoreData,
+};
+use crate::util::cli::branch_trace;
+
+/// Only available when the `mtbBuffer` of the core is configured.
+pub(crate) static BRANCH_TRACE: ReplCommand = ReplCommand {
+    command: "trace",
+    help_text: "Print the branches recorded by the MTB since the last `trace` command, oldest first.",
+    requires_target_halted: true,
+    sub_commands: &[],
+    args: &[],
+    handler: async_fn!(print_branch_trace),
+};
+
+async fn print_branch_trace<'a>(
+    backend: &'a mut RpcBackend,
+    core_data: &'a mut CoreData,
+    _command_arguments: &'a str,
+    _evaluate_arguments: &'a EvaluateArguments,
+    _adapter: &'a mut DebugAdapter,
+) -> EvalResult {
+    let Some(buffer) = core_data.mtb_buffer.clone() else {
+        return Err(DebuggerError::UserMessage(
+            "The branch trace is not configured. Set `mtbBuffer` in the core configuration."
+                .to_string(),
+        ));
+    };
+
+    let session = backend.session_interface();
+    let branches = branch_trace(&session).await.map_err(DebuggerError::Other)?;
+
+    // Reading the trace stops it, so keep recording for the next halt.
+    session
+        .start_branch_trace(buffer)
+        .await
+        .map_err(|error| DebuggerError::Other(error.into()))?;
+
+    if branches.is_empty() {
+        return Ok(EvalResponse::Message(
+            "No branches were recorded.".to_string(),
+        ));
+    }
+
+    Ok(EvalResponse::Message(branches.join("\n")))
+}
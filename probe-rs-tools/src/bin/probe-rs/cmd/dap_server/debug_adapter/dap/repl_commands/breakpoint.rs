@@ -191,6 +191,7 @@ async fn create_breakpoint<'a>(
                     location: SourceLocationScope::Specific(source_location.clone()),
                 },
                 address,
+                conditions: None,
             });
             let body = serde_json::to_value(BreakpointEventBody {
                 breakpoint: Breakpoint {
@@ -0,0 +1,153 @@
+use std::fmt::Write;
+
+use linkme::distributed_slice;
+use probe_rs_rpc::embassy::{WireEmbassyTask, WireEmbassyTasks};
+
+use crate::cmd::dap_server::{
+    DebuggerError,
+    backend::rpc::RpcBackend,
+    debug_adapter::dap::{
+        adapter::DebugAdapter,
+        dap_types::EvaluateArguments,
+        repl_commands::{EvalResponse, EvalResult, REPL_COMMANDS, ReplCommand, async_fn},
+    },
+    server::core_data::CoreData,
+};
+
+#[distributed_slice(REPL_COMMANDS)]
+static TASKS: ReplCommand = ReplCommand {
+    command: "tasks",
+    help_text: "List the spawned tasks of the embassy executor, with their state and the type of their future. The futures can be explored in the `Tasks` scope.",
+    requires_target_halted: true,
+    sub_commands: &[],
+    args: &[],
+    handler: async_fn!(print_tasks),
+};
+
+async fn print_tasks<'a>(
+    backend: &'a mut RpcBackend,
+    core_data: &'a mut CoreData,
+    _command_arguments: &'a str,
+    _evaluate_arguments: &'a EvaluateArguments,
+    _adapter: &'a mut DebugAdapter,
+) -> EvalResult {
+    let Some(tasks) = backend
+        .embassy_tasks(core_data.core_index)
+        .await
+        .map_err(|error| DebuggerError::Other(error.into()))?
+    else {
+        return Err(DebuggerError::UserMessage(
+            "The program does not use the embassy executor.".to_string(),
+        ));
+    };
+
+    Ok(EvalResponse::Message(format_tasks(&tasks)))
+}
+
+fn format_tasks(tasks: &WireEmbassyTasks) -> String {
+    if tasks.tasks.is_empty() {
+        return "No tasks were spawned.".to_string();
+    }
+
+    let name = |address: u64| {
+        tasks
+            .tasks
+            .iter()
+            .find(|task| task.address == address)
+            .map_or_else(|| format!("{address:#010x}"), |task| task.name.clone())
+    };
+
+    let mut message = String::new();
+    for task in &tasks.tasks {
+        #[allow(clippy::unwrap_used, reason = "Writing to a string is infallible")]
+        writeln!(
+            &mut message,
+            "{} @ {:#010x}: {}\n    future: {} @ {:#010x}",
+            task.name,
+            task.address,
+            task_state(task),
+            task.future_type,
+            task.future_address
+        )
+        .unwrap();
+    }
+    for executor in &tasks.executors {
+        let run_queue = executor
+            .run_queue
+            .iter()
+            .map(|&task| name(task))
+            .collect::<Vec<_>>();
+        #[allow(clippy::unwrap_used, reason = "Writing to a string is infallible")]
+        writeln!(
+            &mut message,
+            "Executor @ {:#010x}, run queue: [{}]",
+            executor.address,
+            run_queue.join(", ")
+        )
+        .unwrap();
+    }
+    if let Some((used, size)) = tasks.arena {
+        #[allow(clippy::unwrap_used, reason = "Writing to a string is infallible")]
+        writeln!(&mut message, "Arena: {used} of {size} bytes used").unwrap();
+    }
+
+    message
+}
+
+fn task_state(task: &WireEmbassyTask) -> String {
+    let flags = [
+        (task.spawned, "spawned"),
+        (task.run_queued, "run queued"),
+        (task.timer_queued, "timer queued"),
+    ]
+    .into_iter()
+    .filter_map(|(set, flag)| set.then_some(flag))
+    .collect::<Vec<_>>();
+
+    flags.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use probe_rs_rpc::embassy::WireEmbassyExecutor;
+
+    use super::*;
+
+    fn task(name: &str, address: u64, run_queued: bool) -> WireEmbassyTask {
+        WireEmbassyTask {
+            address,
+            name: name.to_string(),
+            future_type: format!("{name}::{{async_fn_env#0}}"),
+            future_address: address + 0x20,
+            spawned: true,
+            run_queued,
+            timer_queued: false,
+            executor: 0x2000_0000,
+        }
+    }
+
+    #[test]
+    fn tasks_with_run_queue_and_arena() {
+        let tasks = WireEmbassyTasks {
+            tasks: vec![
+                task("app::blink", 0x2000_1000, true),
+                task("app::button", 0x2000_1040, false),
+            ],
+            executors: vec![WireEmbassyExecutor {
+                address: 0x2000_0000,
+                run_queue: vec![0x2000_1000],
+            }],
+            arena: Some((0x80, 4096)),
+        };
+
+        assert_eq!(
+            format_tasks(&tasks),
+            "app::blink @ 0x20001000: spawned, run queued\n    \
+             future: app::blink::{async_fn_env#0} @ 0x20001020\n\
+             app::button @ 0x20001040: spawned\n    \
+             future: app::button::{async_fn_env#0} @ 0x20001060\n\
+             Executor @ 0x20000000, run queue: [app::blink]\n\
+             Arena: 128 of 4096 bytes used\n"
+        );
+    }
+}
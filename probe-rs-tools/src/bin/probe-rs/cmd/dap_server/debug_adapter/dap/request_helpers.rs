@@ -375,7 +375,8 @@ pub(crate) fn instruction_breakpoint_response(
     }
 }
 
-fn get_capstone_le(
+/// Creates a little-endian disassembler for `instruction_set`, which skips over data in the code.
+pub(crate) fn get_capstone_le(
     instruction_set: InstructionSet,
     core_type: CoreType,
 ) -> Result<Capstone, DebuggerError> {
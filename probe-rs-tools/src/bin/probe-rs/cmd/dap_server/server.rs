@@ -1,3 +1,5 @@
+/// Host-side evaluation of conditional breakpoints, hit counts and logpoints.
+pub(crate) mod breakpoint_conditions;
 /// All the shared options that control the behaviour of the debugger.
 pub(crate) mod configuration;
 /// The data structures borrowed from the [`session_data::SessionData`], that applies to a specific core.
@@ -6,6 +8,8 @@ pub(crate) mod core_data;
 pub(crate) mod debug_rtt;
 /// Implements the part of the debug server that processes incoming requests from the [`DebugAdapter`](crate::cmd::dap_server::debug_adapter::dap::adapter::DebugAdapter).
 pub(crate) mod debugger;
+/// Exception breakpoint filters, and decoding of the faults and panics they halt on.
+pub(crate) mod fault_analysis;
 /// Manage the logging/tracing associated with the debugger.
 pub(crate) mod logger;
 /// Per-DAP-client RPC connection open/close for TCP multi-session mode.
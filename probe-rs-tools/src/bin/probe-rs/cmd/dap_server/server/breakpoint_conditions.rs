@@ -0,0 +1,802 @@
+//! Host-side evaluation of the `condition`, `hitCondition` and `logMessage` of DAP breakpoints.
+//!
+//! The target only knows about unconditional breakpoints. When the core halts on a breakpoint
+//! with any of these attributes, the debugger evaluates them against the top stack frame, and
+//! resumes the core straight away unless the breakpoint should really stop.
+//!
+//! Conditions support a small expression language: variable and register names, paths to fields
+//! and statics like `state.counter` or `my_mod::STATE`, integer, float, boolean and character
+//! literals, the arithmetic operators `+`, `-`, `*` and `/`, the comparisons `==`, `!=`, `<`, `<=`,
+//! `>` and `>=`, and the logical operators `!`, `&&` and `||`, with parentheses for grouping. A bare
+//! value is true if it is non-zero.
+
+use std::cell::Cell;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+/// The conditions attached to a breakpoint, and the number of times it was hit.
+///
+/// Clones share the hit count, so that a breakpoint set at several addresses, like a function
+/// breakpoint on a function with several instances, counts the hits of all of them.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct BreakpointConditions {
+    condition: Option<Expression>,
+    hit_condition: Option<HitCondition>,
+    log_message: Option<LogMessage>,
+    /// The number of hits where `condition` was met.
+    hits: Rc<Cell<u64>>,
+}
+
+/// What the debugger should do after a breakpoint with conditions was hit.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum BreakpointAction {
+    /// Resume the core without notifying the client.
+    Continue,
+    /// Report the halt to the client.
+    Stop,
+    /// Log a message, then resume the core.
+    Log(String),
+}
+
+impl BreakpointConditions {
+    /// Parses the conditions of a DAP breakpoint. Empty attributes are ignored, and `None` is
+    /// returned if no attribute is left.
+    pub(crate) fn new(
+        condition: Option<&str>,
+        hit_condition: Option<&str>,
+        log_message: Option<&str>,
+    ) -> Result<Option<Self>, String> {
+        fn non_empty(value: Option<&str>) -> Option<&str> {
+            value.map(str::trim).filter(|v| !v.is_empty())
+        }
+
+        let condition = non_empty(condition)
+            .map(Expression::parse)
+            .transpose()
+            .map_err(|error| format!("Invalid condition: {error}"))?;
+        let hit_condition = non_empty(hit_condition)
+            .map(HitCondition::parse)
+            .transpose()
+            .map_err(|error| format!("Invalid hit condition: {error}"))?;
+        let log_message = log_message
+            .filter(|message| !message.is_empty())
+            .map(LogMessage::parse)
+            .transpose()
+            .map_err(|error| format!("Invalid log message: {error}"))?;
+
+        if condition.is_none() && hit_condition.is_none() && log_message.is_none() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            condition,
+            hit_condition,
+            log_message,
+            hits: Rc::default(),
+        }))
+    }
+
+    /// The names of the variables and registers the condition and log message refer to.
+    pub(crate) fn variables(&self) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        if let Some(condition) = &self.condition {
+            condition.variables(&mut names);
+        }
+        if let Some(log_message) = &self.log_message {
+            log_message.variables(&mut names);
+        }
+        names
+    }
+
+    /// Evaluates the conditions for a single hit of the breakpoint, and updates the hit count.
+    ///
+    /// `lookup` resolves a variable or register name to its value, as formatted by the debugger.
+    pub(crate) fn on_hit(
+        &mut self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<BreakpointAction, String> {
+        if let Some(condition) = &self.condition
+            && !condition.evaluate(&lookup)?.is_truthy()?
+        {
+            return Ok(BreakpointAction::Continue);
+        }
+
+        let hits = self.hits.get() + 1;
+        self.hits.set(hits);
+        if let Some(hit_condition) = &self.hit_condition
+            && !hit_condition.is_met(hits)
+        {
+            return Ok(BreakpointAction::Continue);
+        }
+
+        match &self.log_message {
+            Some(log_message) => Ok(BreakpointAction::Log(log_message.format(&lookup)?)),
+            None => Ok(BreakpointAction::Stop),
+        }
+    }
+}
+
+/// A `hitCondition`: an optional operator, followed by a hit count. A bare count means `==`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct HitCondition {
+    operator: HitOperator,
+    count: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum HitOperator {
+    Equal,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    /// Stop on every n-th hit.
+    Multiple,
+}
+
+impl HitCondition {
+    fn parse(text: &str) -> Result<Self, String> {
+        let (operator, count) = [
+            (">=", HitOperator::GreaterOrEqual),
+            ("<=", HitOperator::LessOrEqual),
+            ("==", HitOperator::Equal),
+            (">", HitOperator::Greater),
+            ("<", HitOperator::Less),
+            ("%", HitOperator::Multiple),
+        ]
+        .into_iter()
+        .find_map(|(prefix, operator)| Some((operator, text.strip_prefix(prefix)?)))
+        .unwrap_or((HitOperator::Equal, text));
+
+        let count = count
+            .trim()
+            .parse()
+            .map_err(|_| format!("expected a hit count, found {:?}", count.trim()))?;
+        if operator == HitOperator::Multiple && count == 0 {
+            return Err("the hit count for `%` must not be zero".to_string());
+        }
+
+        Ok(Self { operator, count })
+    }
+
+    fn is_met(&self, hits: u64) -> bool {
+        match self.operator {
+            HitOperator::Equal => hits == self.count,
+            HitOperator::Less => hits < self.count,
+            HitOperator::LessOrEqual => hits <= self.count,
+            HitOperator::Greater => hits > self.count,
+            HitOperator::GreaterOrEqual => hits >= self.count,
+            HitOperator::Multiple => hits.is_multiple_of(self.count),
+        }
+    }
+}
+
+/// A `logMessage`, with the `{expression}` placeholders split out.
+#[derive(Clone, Debug, PartialEq)]
+struct LogMessage(Vec<LogSegment>);
+
+#[derive(Clone, Debug, PartialEq)]
+enum LogSegment {
+    Text(String),
+    Expression(String),
+}
+
+impl LogMessage {
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut segments = vec![];
+        let mut literal = String::new();
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut expression = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => expression.push(c),
+                            None => return Err("unterminated `{` placeholder".to_string()),
+                        }
+                    }
+                    let expression = expression.trim();
+                    if expression.is_empty() {
+                        return Err("empty `{}` placeholder".to_string());
+                    }
+                    if !literal.is_empty() {
+                        segments.push(LogSegment::Text(std::mem::take(&mut literal)));
+                    }
+                    segments.push(LogSegment::Expression(expression.to_string()));
+                }
+                '}' => return Err("unmatched `}`, use `}}` to log a brace".to_string()),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(LogSegment::Text(literal));
+        }
+
+        Ok(Self(segments))
+    }
+
+    fn variables(&self, names: &mut BTreeSet<String>) {
+        for segment in &self.0 {
+            if let LogSegment::Expression(expression) = segment {
+                names.insert(expression.clone());
+            }
+        }
+    }
+
+    fn format(&self, lookup: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+        let mut message = String::new();
+        for segment in &self.0 {
+            match segment {
+                LogSegment::Text(text) => message.push_str(text),
+                LogSegment::Expression(expression) => {
+                    let value = lookup(expression)
+                        .ok_or_else(|| format!("Cannot evaluate `{expression}`"))?;
+                    message.push_str(&value);
+                }
+            }
+        }
+        Ok(message)
+    }
+}
+
+/// A parsed breakpoint condition.
+#[derive(Clone, Debug, PartialEq)]
+enum Expression {
+    Literal(Value),
+    Variable(String),
+    Not(Box<Expression>),
+    Negate(Box<Expression>),
+    Arithmetic(Box<Expression>, Operator, Box<Expression>),
+    Compare(Box<Expression>, Comparison, Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// The value of a literal, or of a variable as formatted by the debugger.
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Integer(i128),
+    Float(f64),
+    Bool(bool),
+    Text(String),
+}
+
+impl Value {
+    /// Interprets the formatted value of a variable or register.
+    fn from_formatted(text: &str) -> Self {
+        let text = text.trim();
+        match text {
+            "true" => return Value::Bool(true),
+            "false" => return Value::Bool(false),
+            _ => {}
+        }
+        if let Some(value) = parse_integer(text) {
+            return Value::Integer(value);
+        }
+        if let Ok(value) = text.parse::<f64>() {
+            return Value::Float(value);
+        }
+        if let Some(c) = parse_char(text) {
+            return Value::Integer(c as i128);
+        }
+        Value::Text(text.trim_matches('"').to_string())
+    }
+
+    fn is_truthy(&self) -> Result<bool, String> {
+        match self {
+            Value::Integer(value) => Ok(*value != 0),
+            Value::Float(value) => Ok(*value != 0.0),
+            Value::Bool(value) => Ok(*value),
+            Value::Text(text) => Err(format!("`{text}` is not a boolean or numeric value")),
+        }
+    }
+
+    fn compare(&self, comparison: Comparison, other: &Value) -> Result<bool, String> {
+        use std::cmp::Ordering;
+
+        let ordering = match (self, other) {
+            (Value::Integer(a), Value::Integer(b)) => a.partial_cmp(b),
+            (Value::Bool(a), Value::Bool(b)) => a.partial_cmp(b),
+            (Value::Text(a), Value::Text(b)) => a.partial_cmp(b),
+            (a, b) => match (a.as_float(), b.as_float()) {
+                (Some(a), Some(b)) => a.partial_cmp(&b),
+                _ => return Err(format!("cannot compare {a:?} with {b:?}")),
+            },
+        };
+        // NaN compares unequal to everything.
+        let Some(ordering) = ordering else {
+            return Ok(comparison == Comparison::NotEqual);
+        };
+
+        Ok(match comparison {
+            Comparison::Equal => ordering == Ordering::Equal,
+            Comparison::NotEqual => ordering != Ordering::Equal,
+            Comparison::Less => ordering == Ordering::Less,
+            Comparison::LessOrEqual => ordering != Ordering::Greater,
+            Comparison::Greater => ordering == Ordering::Greater,
+            Comparison::GreaterOrEqual => ordering != Ordering::Less,
+        })
+    }
+
+    fn negate(&self) -> Result<Value, String> {
+        match self {
+            Value::Integer(value) => Ok(Value::Integer(-value)),
+            Value::Float(value) => Ok(Value::Float(-value)),
+            other => Err(format!("cannot negate {other:?}")),
+        }
+    }
+
+    /// Applies an arithmetic operator. Integers stay integers, unless one side is a float.
+    fn apply(&self, operator: Operator, other: &Value) -> Result<Value, String> {
+        if let (Value::Integer(a), Value::Integer(b)) = (self, other) {
+            let result = match operator {
+                Operator::Add => a.checked_add(*b),
+                Operator::Subtract => a.checked_sub(*b),
+                Operator::Multiply => a.checked_mul(*b),
+                Operator::Divide if *b == 0 => return Err("division by zero".to_string()),
+                Operator::Divide => a.checked_div(*b),
+            };
+            return result
+                .map(Value::Integer)
+                .ok_or_else(|| "integer overflow".to_string());
+        }
+
+        match (self.as_float(), other.as_float()) {
+            (Some(a), Some(b)) => Ok(Value::Float(match operator {
+                Operator::Add => a + b,
+                Operator::Subtract => a - b,
+                Operator::Multiply => a * b,
+                Operator::Divide => a / b,
+            })),
+            _ => Err(format!(
+                "cannot apply {operator:?} to {self:?} and {other:?}"
+            )),
+        }
+    }
+
+    fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Integer(value) => Some(*value as f64),
+            Value::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+fn parse_integer(text: &str) -> Option<i128> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let digits = digits.replace('_', "");
+    let value = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        i128::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits
+        .strip_prefix("0b")
+        .or_else(|| digits.strip_prefix("0B"))
+    {
+        i128::from_str_radix(binary, 2).ok()?
+    } else {
+        digits.parse::<u128>().ok()?.try_into().ok()?
+    };
+
+    Some(if negative { -value } else { value })
+}
+
+fn parse_char(text: &str) -> Option<char> {
+    let inner = text.strip_prefix('\'')?.strip_suffix('\'')?;
+    let mut chars = inner.chars();
+    match (chars.next()?, chars.next()) {
+        ('\\', Some(escaped)) if chars.next().is_none() => match escaped {
+            'n' => Some('\n'),
+            'r' => Some('\r'),
+            't' => Some('\t'),
+            '0' => Some('\0'),
+            '\\' | '\'' | '"' => Some(escaped),
+            _ => None,
+        },
+        (c, None) => Some(c),
+        _ => None,
+    }
+}
+
+impl Expression {
+    fn parse(text: &str) -> Result<Self, String> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+        let expression = parser.or()?;
+        match parser.tokens.get(parser.position) {
+            None => Ok(expression),
+            Some(token) => Err(format!("unexpected {token:?}")),
+        }
+    }
+
+    fn variables(&self, names: &mut BTreeSet<String>) {
+        match self {
+            Expression::Literal(_) => {}
+            Expression::Variable(name) => {
+                names.insert(name.clone());
+            }
+            Expression::Not(inner) | Expression::Negate(inner) => inner.variables(names),
+            Expression::Arithmetic(lhs, _, rhs)
+            | Expression::Compare(lhs, _, rhs)
+            | Expression::And(lhs, rhs)
+            | Expression::Or(lhs, rhs) => {
+                lhs.variables(names);
+                rhs.variables(names);
+            }
+        }
+    }
+
+    fn evaluate(&self, lookup: &impl Fn(&str) -> Option<String>) -> Result<Value, String> {
+        Ok(match self {
+            Expression::Literal(value) => value.clone(),
+            Expression::Variable(name) => lookup(name)
+                .map(|value| Value::from_formatted(&value))
+                .ok_or_else(|| format!("Cannot evaluate `{name}`"))?,
+            Expression::Not(inner) => Value::Bool(!inner.evaluate(lookup)?.is_truthy()?),
+            Expression::Negate(inner) => inner.evaluate(lookup)?.negate()?,
+            Expression::Arithmetic(lhs, operator, rhs) => lhs
+                .evaluate(lookup)?
+                .apply(*operator, &rhs.evaluate(lookup)?)?,
+            Expression::Compare(lhs, comparison, rhs) => Value::Bool(
+                lhs.evaluate(lookup)?
+                    .compare(*comparison, &rhs.evaluate(lookup)?)?,
+            ),
+            Expression::And(lhs, rhs) => Value::Bool(
+                lhs.evaluate(lookup)?.is_truthy()? && rhs.evaluate(lookup)?.is_truthy()?,
+            ),
+            Expression::Or(lhs, rhs) => Value::Bool(
+                lhs.evaluate(lookup)?.is_truthy()? || rhs.evaluate(lookup)?.is_truthy()?,
+            ),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Literal(Value),
+    Identifier(String),
+    Arithmetic(Operator),
+    Compare(Comparison),
+    Not,
+    And,
+    Or,
+    OpenParen,
+    CloseParen,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let mut next_is = |expected: char| chars.next_if(|&(_, c)| c == expected).is_some();
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::OpenParen,
+            ')' => Token::CloseParen,
+            '&' if next_is('&') => Token::And,
+            '|' if next_is('|') => Token::Or,
+            '=' if next_is('=') => Token::Compare(Comparison::Equal),
+            '!' if next_is('=') => Token::Compare(Comparison::NotEqual),
+            '!' => Token::Not,
+            '<' if next_is('=') => Token::Compare(Comparison::LessOrEqual),
+            '<' => Token::Compare(Comparison::Less),
+            '>' if next_is('=') => Token::Compare(Comparison::GreaterOrEqual),
+            '>' => Token::Compare(Comparison::Greater),
+            '+' => Token::Arithmetic(Operator::Add),
+            '-' => Token::Arithmetic(Operator::Subtract),
+            '*' => Token::Arithmetic(Operator::Multiply),
+            '/' => Token::Arithmetic(Operator::Divide),
+            '\'' => {
+                let mut end = start + 1;
+                let mut escaped = false;
+                for (index, c) in chars.by_ref() {
+                    end = index + c.len_utf8();
+                    if c == '\'' && !escaped {
+                        break;
+                    }
+                    escaped = c == '\\' && !escaped;
+                }
+                let literal = &text[start..end];
+                let c = parse_char(literal)
+                    .ok_or_else(|| format!("invalid character literal {literal}"))?;
+                Token::Literal(Value::Integer(c as i128))
+            }
+            c if c.is_ascii_digit() => {
+                let mut end = start + c.len_utf8();
+                while let Some((index, c)) =
+                    chars.next_if(|&(_, c)| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+                {
+                    end = index + c.len_utf8();
+                }
+                let literal = &text[start..end];
+                if let Some(value) = parse_integer(literal) {
+                    Token::Literal(Value::Integer(value))
+                } else if let Ok(value) = literal.parse::<f64>() {
+                    Token::Literal(Value::Float(value))
+                } else {
+                    return Err(format!("invalid number {literal}"));
+                }
+            }
+            c if is_identifier_start(c) => {
+                let end = path_end(text, start);
+                while chars.next_if(|&(index, _)| index < end).is_some() {}
+                match &text[start..end] {
+                    "true" => Token::Literal(Value::Bool(true)),
+                    "false" => Token::Literal(Value::Bool(false)),
+                    name => Token::Identifier(name.to_string()),
+                }
+            }
+            c => return Err(format!("unexpected character {c:?}")),
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+/// The end of the path starting at `start`: identifiers joined by `.` or `::`.
+fn path_end(text: &str, start: usize) -> usize {
+    let mut end = start;
+    loop {
+        let rest = &text[end..];
+        let identifier = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        end += identifier;
+
+        let rest = &text[end..];
+        let separator = if rest.starts_with("::") {
+            2
+        } else if rest.starts_with('.') {
+            1
+        } else {
+            return end;
+        };
+        if !rest[separator..].starts_with(is_identifier_start) {
+            return end;
+        }
+        end += separator;
+    }
+}
+
+/// A recursive descent parser, with the usual precedence: `||` < `&&` < comparisons < `+` and `-`
+/// < `*` and `/` < `!` and unary `-`.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next_if(&mut self, expected: &Token) -> bool {
+        let matches = self.tokens.get(self.position) == Some(expected);
+        if matches {
+            self.position += 1;
+        }
+        matches
+    }
+
+    fn or(&mut self) -> Result<Expression, String> {
+        let mut lhs = self.and()?;
+        while self.next_if(&Token::Or) {
+            lhs = Expression::Or(Box::new(lhs), Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expression, String> {
+        let mut lhs = self.comparison()?;
+        while self.next_if(&Token::And) {
+            lhs = Expression::And(Box::new(lhs), Box::new(self.comparison()?));
+        }
+        Ok(lhs)
+    }
+
+    fn comparison(&mut self) -> Result<Expression, String> {
+        let lhs = self.sum()?;
+        if let Some(Token::Compare(comparison)) = self.tokens.get(self.position).cloned() {
+            self.position += 1;
+            return Ok(Expression::Compare(
+                Box::new(lhs),
+                comparison,
+                Box::new(self.sum()?),
+            ));
+        }
+        Ok(lhs)
+    }
+
+    fn sum(&mut self) -> Result<Expression, String> {
+        let mut lhs = self.product()?;
+        while let Some(Token::Arithmetic(operator @ (Operator::Add | Operator::Subtract))) =
+            self.tokens.get(self.position).cloned()
+        {
+            self.position += 1;
+            lhs = Expression::Arithmetic(Box::new(lhs), operator, Box::new(self.product()?));
+        }
+        Ok(lhs)
+    }
+
+    fn product(&mut self) -> Result<Expression, String> {
+        let mut lhs = self.unary()?;
+        while let Some(Token::Arithmetic(operator @ (Operator::Multiply | Operator::Divide))) =
+            self.tokens.get(self.position).cloned()
+        {
+            self.position += 1;
+            lhs = Expression::Arithmetic(Box::new(lhs), operator, Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expression, String> {
+        if self.next_if(&Token::Not) {
+            return Ok(Expression::Not(Box::new(self.unary()?)));
+        }
+        if self.next_if(&Token::Arithmetic(Operator::Subtract)) {
+            return Ok(Expression::Negate(Box::new(self.unary()?)));
+        }
+
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        match token {
+            Some(Token::Literal(value)) => Ok(Expression::Literal(value)),
+            Some(Token::Identifier(name)) => Ok(Expression::Variable(name)),
+            Some(Token::OpenParen) => {
+                let expression = self.or()?;
+                if !self.next_if(&Token::CloseParen) {
+                    return Err("missing `)`".to_string());
+                }
+                Ok(expression)
+            }
+            Some(token) => Err(format!("unexpected {token:?}")),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "count" => Some("42".to_string()),
+            "flag" => Some("true".to_string()),
+            "ratio" => Some("0.5".to_string()),
+            "R0" => Some("0x0000002a".to_string()),
+            "letter" => Some("'a'".to_string()),
+            "state" => Some("Idle".to_string()),
+            "state.counter" => Some("7".to_string()),
+            "my_mod::STATE" => Some("3".to_string()),
+            _ => None,
+        }
+    }
+
+    fn condition(text: &str) -> Result<bool, String> {
+        Expression::parse(text)?.evaluate(&lookup)?.is_truthy()
+    }
+
+    #[test]
+    fn conditions() {
+        assert_eq!(condition("count == 42"), Ok(true));
+        assert_eq!(condition("count == 0x2a && R0 == count"), Ok(true));
+        assert_eq!(condition("count > 100 || !flag"), Ok(false));
+        assert_eq!(condition("!(count >= 43) && ratio < 1"), Ok(true));
+        assert_eq!(condition("letter == 'a'"), Ok(true));
+        assert_eq!(condition("count - 1 == 41"), Ok(true));
+        assert_eq!(condition("count-1 == 41 && -count == -42"), Ok(true));
+        assert_eq!(condition("count + 2 * 3 == 48"), Ok(true));
+        assert_eq!(condition("(count + 2) / 4 == 11"), Ok(true));
+        assert_eq!(condition("ratio * 2 == 1.0"), Ok(true));
+        assert_eq!(condition("state.counter > 3"), Ok(true));
+        assert_eq!(condition("my_mod::STATE == state.counter - 4"), Ok(true));
+        assert!(condition("count / 0").is_err());
+        assert_eq!(condition("count"), Ok(true));
+        assert_eq!(condition("count != -1"), Ok(true));
+        assert!(condition("state").is_err());
+        assert!(condition("missing == 1").is_err());
+        assert!(Expression::parse("(count == 1").is_err());
+        assert!(Expression::parse("count == ").is_err());
+    }
+
+    #[test]
+    fn hit_conditions() {
+        let hits = |text: &str| {
+            let condition = HitCondition::parse(text).unwrap();
+            (1..=6)
+                .filter(|&hit| condition.is_met(hit))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(hits("3"), vec![3]);
+        assert_eq!(hits(">= 5"), vec![5, 6]);
+        assert_eq!(hits("<3"), vec![1, 2]);
+        assert_eq!(hits("%2"), vec![2, 4, 6]);
+        assert!(HitCondition::parse("%0").is_err());
+        assert!(HitCondition::parse("often").is_err());
+    }
+
+    #[test]
+    fn log_messages() {
+        let message = LogMessage::parse("count = {count}, {{flag}} = { flag }").unwrap();
+        assert_eq!(
+            message.format(lookup),
+            Ok("count = 42, {flag} = true".to_string())
+        );
+        assert!(LogMessage::parse("{count").is_err());
+        assert!(LogMessage::parse("count}").is_err());
+    }
+
+    #[test]
+    fn hit_count_only_includes_hits_meeting_the_condition() {
+        let mut conditions =
+            BreakpointConditions::new(Some("flag"), Some("2"), Some("hit {count}"))
+                .unwrap()
+                .unwrap();
+        assert_eq!(conditions.variables().len(), 2);
+        assert_eq!(conditions.on_hit(lookup), Ok(BreakpointAction::Continue));
+        assert_eq!(
+            conditions.on_hit(|_| Some("false".to_string())),
+            Ok(BreakpointAction::Continue)
+        );
+        assert_eq!(
+            conditions.on_hit(lookup),
+            Ok(BreakpointAction::Log("hit 42".to_string()))
+        );
+
+        assert_eq!(
+            BreakpointConditions::new(Some(" "), None, Some("")),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn instances_of_a_breakpoint_share_the_hit_count() {
+        let mut first = BreakpointConditions::new(None, Some("2"), None)
+            .unwrap()
+            .unwrap();
+        let mut second = first.clone();
+        assert_eq!(first.on_hit(lookup), Ok(BreakpointAction::Continue));
+        assert_eq!(second.on_hit(lookup), Ok(BreakpointAction::Stop));
+    }
+}
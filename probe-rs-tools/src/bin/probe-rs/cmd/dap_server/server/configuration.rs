@@ -5,7 +5,7 @@ use anyhow::{Result, anyhow};
 use probe_rs::probe::{DebugProbeSelector, WireProtocol};
 use probe_rs_rpc::format::FormatOptions;
 use serde::{Deserialize, Serialize};
-use std::{env::current_dir, path::PathBuf, time::Duration};
+use std::{env::current_dir, ops::Range, path::PathBuf, time::Duration};
 
 use super::startup::TargetSessionType;
 use super::uploaded_files::UploadedFiles;
@@ -327,6 +327,10 @@ pub struct FlashingConfig {
     #[serde(default)]
     pub(crate) verify_after_flashing: bool,
 
+    /// Reprogram flash sectors with break instructions once the hardware breakpoint units run out
+    #[serde(default)]
+    pub(crate) flash_breakpoints: bool,
+
     /// [`FormatOptions`] to control the flashing operation, depending on the type of binary (`probe_rs::flashing::loader::ImageFormat`) to be flashed.
     #[serde(default)]
     pub(crate) format_options: FormatOptions,
@@ -372,6 +376,16 @@ pub struct CoreConfig {
     /// Enable HLT vector catch (ARMv7-A/R only).
     #[serde(default = "default_true")]
     pub(crate) catch_hlt: bool,
+
+    /// Other cores that halt and resume at the same moment as this one, wired together through
+    /// the cross triggers of the target (CTI on ARM).
+    #[serde(default)]
+    pub(crate) run_control_group: Vec<usize>,
+
+    /// Record the branch history with the MTB (Micro Trace Buffer) into this RAM region, which
+    /// must not be used by the firmware. The size of the region has to be a power of two, and the
+    /// region has to be aligned to its size. The branch history is printed by the `trace` command.
+    pub(crate) mtb_buffer: Option<Range<u64>>,
 }
 
 fn default_console_log() -> Option<ConsoleLog> {
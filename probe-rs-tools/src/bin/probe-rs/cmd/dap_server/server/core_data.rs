@@ -1,13 +1,15 @@
 use std::any::Any;
+use std::ops::Range;
 
 use super::session_data;
 use crate::cmd::dap_server::debug_adapter::dap::repl_commands::ReplCommand;
 use crate::rpc::{Key, RttClient};
+use probe_rs_rpc::rtos::{WireRtosThread, WireThreadState};
 use probe_rs_rpc::rtt_client::ScanRegion as WireScanRegion;
 
 /// `(channel number, channel name)` pairs returned while attaching to RTT.
 pub(crate) type ChannelNames = Vec<(u32, String)>;
-use crate::cmd::dap_server::server::debug_rtt;
+use crate::cmd::dap_server::server::{debug_rtt, fault_analysis::FaultReport};
 use probe_rs::CoreStatus;
 
 /// [CoreData] is used to cache data needed by the debugger, on a per-core basis.
@@ -28,19 +30,35 @@ pub struct CoreData {
     /// target's registers or execution state, and replaced only after a
     /// complete server unwind succeeds.
     pub stack_frames: Vec<probe_rs_debug::stack_frame::StackFrame>,
+    /// Display cache of the RTOS threads that run on the core, invalidated
+    /// and replaced together with [`Self::stack_frames`]. Empty if the
+    /// program does not use a supported RTOS.
+    pub rtos_threads: Vec<RtosThreadStack>,
     pub breakpoints: Vec<session_data::ActiveBreakpoint>,
+    /// The decoded exception or panic the core last halted on, for the `exceptionInfo` request.
+    pub(crate) exception_report: Option<FaultReport>,
     pub rtt_scan_ranges: WireScanRegion,
     pub rtt_connection: Option<debug_rtt::RttConnection>,
     /// Cache of the server-side RTT client handle between attach attempts,
     /// so we only call `create_rtt` once per core (RPC backend).
     pub rtt_remote_handle: Option<Key<RttClient>>,
+    /// The RAM region the MTB records the branch history into, if configured.
+    pub mtb_buffer: Option<Range<u64>>,
     pub repl_commands: Vec<ReplCommand>,
     pub test_data: Box<dyn Any>,
 }
 
+/// An RTOS thread, with the frames unwound from the registers saved on its
+/// stack. The frames of the running thread are the ones of the core.
+pub struct RtosThreadStack {
+    pub thread: WireRtosThread,
+    pub stack_frames: Vec<probe_rs_debug::stack_frame::StackFrame>,
+}
+
 impl CoreData {
     pub(crate) fn invalidate_stack_frame_cache(&mut self) {
         self.stack_frames.clear();
+        self.rtos_threads.clear();
     }
 
     pub(crate) fn replace_stack_frame_cache(
@@ -49,6 +67,18 @@ impl CoreData {
     ) {
         self.stack_frames = frames;
     }
+
+    pub(crate) fn replace_rtos_thread_cache(&mut self, threads: Vec<RtosThreadStack>) {
+        self.rtos_threads = threads;
+    }
+
+    /// The RTOS thread that runs on the core, if the program uses a supported RTOS.
+    pub(crate) fn running_rtos_thread(&self) -> Option<&WireRtosThread> {
+        self.rtos_threads
+            .iter()
+            .map(|thread| &thread.thread)
+            .find(|thread| thread.state == WireThreadState::Running)
+    }
 }
 
 #[test]
@@ -75,10 +105,13 @@ fn stack_frame_display_cache_is_invalidated_before_replacement() {
         last_known_status: CoreStatus::Unknown,
         target_name: String::new(),
         stack_frames: vec![frame(1)],
+        rtos_threads: vec![],
         breakpoints: vec![],
+        exception_report: None,
         rtt_scan_ranges: WireScanRegion::Ranges(vec![]),
         rtt_connection: None,
         rtt_remote_handle: None,
+        mtb_buffer: None,
         repl_commands: vec![],
         test_data: Box::new(()),
     };
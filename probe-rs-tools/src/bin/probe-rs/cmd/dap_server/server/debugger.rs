@@ -1,5 +1,6 @@
 use super::{
     configuration::{self, ConsoleLog},
+    fault_analysis::ExceptionFilter,
     logger::DebugLogger,
     session_data::SessionData,
     startup::{TargetSessionType, get_file_timestamp},
@@ -10,8 +11,8 @@ use crate::cmd::dap_server::{
     debug_adapter::dap::{
         adapter::{DebugAdapter, get_arguments},
         dap_types::{
-            Capabilities, DisconnectResponse, Event, ExitedEventBody, InitializeRequestArguments,
-            MessageSeverity, Request, TerminatedEventBody,
+            Capabilities, DisconnectResponse, Event, ExceptionBreakpointsFilter, ExitedEventBody,
+            InitializeRequestArguments, MessageSeverity, Request, TerminatedEventBody,
         },
     },
     server::configuration::SessionConfig,
@@ -39,6 +40,27 @@ fn dap_capabilities() -> Capabilities {
         supports_set_variable: Some(true),
         supports_disassemble_request: Some(true),
         supports_instruction_breakpoints: Some(true),
+        supports_data_breakpoints: Some(true),
+        supports_function_breakpoints: Some(true),
+        // Conditions, hit counts and log messages are evaluated host-side on every hit.
+        supports_conditional_breakpoints: Some(true),
+        supports_hit_conditional_breakpoints: Some(true),
+        supports_log_points: Some(true),
+        // Faults are caught with vector catch, panics with a breakpoint on the panic handler.
+        exception_breakpoint_filters: Some(
+            ExceptionFilter::ALL
+                .into_iter()
+                .map(|filter| ExceptionBreakpointsFilter {
+                    filter: filter.id().to_string(),
+                    label: filter.label().to_string(),
+                    description: Some(filter.description().to_string()),
+                    default: Some(filter.default_enabled()),
+                    supports_condition: None,
+                    condition_description: None,
+                })
+                .collect(),
+        ),
+        supports_exception_info_request: Some(true),
         supports_stepping_granularity: Some(true),
         supports_completions_request: Some(true),
         // ANSI output is emitted only when the client also opts in.
@@ -178,6 +200,9 @@ impl Debugger {
                 "configurationDone"
                     | "setBreakpoints"
                     | "setInstructionBreakpoints"
+                    | "setFunctionBreakpoints"
+                    | "setExceptionBreakpoints"
+                    | "setDataBreakpoints"
                     | "clearBreakpoint"
                     | "stackTrace"
                     | "threads"
@@ -213,6 +238,31 @@ impl Debugger {
                     .set_instruction_breakpoints(session_data, core_index, &request)
                     .await?;
             }
+            "setFunctionBreakpoints" => {
+                debug_adapter
+                    .set_function_breakpoints(session_data, core_index, &request)
+                    .await?;
+            }
+            "setExceptionBreakpoints" => {
+                debug_adapter
+                    .set_exception_breakpoints(session_data, core_index, &request)
+                    .await?;
+            }
+            "exceptionInfo" => {
+                debug_adapter
+                    .exception_info(session_data, core_index, &request)
+                    .await?;
+            }
+            "dataBreakpointInfo" => {
+                debug_adapter
+                    .data_breakpoint_info(session_data, core_index, &request)
+                    .await?;
+            }
+            "setDataBreakpoints" => {
+                debug_adapter
+                    .set_data_breakpoints(session_data, core_index, &request)
+                    .await?;
+            }
             "readMemory" => {
                 debug_adapter
                     .read_memory(session_data, core_index, &request)
@@ -1080,6 +1130,8 @@ mod test {
         assert_eq!(capabilities.supports_set_variable, Some(true));
         assert_eq!(capabilities.supports_disassemble_request, Some(true));
         assert_eq!(capabilities.supports_instruction_breakpoints, Some(true));
+        assert_eq!(capabilities.supports_data_breakpoints, Some(true));
+        assert_eq!(capabilities.supports_function_breakpoints, Some(true));
         assert_eq!(capabilities.supports_completions_request, Some(true));
 
         // Behavior capabilities implemented by existing request handlers.
@@ -1088,6 +1140,20 @@ mod test {
         assert_eq!(capabilities.support_suspend_debuggee, Some(true));
         assert_eq!(capabilities.support_terminate_debuggee, Some(true));
         assert_eq!(capabilities.supports_ansi_styling, Some(true));
+        assert_eq!(capabilities.supports_conditional_breakpoints, Some(true));
+        assert_eq!(
+            capabilities.supports_hit_conditional_breakpoints,
+            Some(true)
+        );
+        assert_eq!(capabilities.supports_log_points, Some(true));
+        assert_eq!(capabilities.supports_exception_info_request, Some(true));
+        let filters: Vec<_> = capabilities
+            .exception_breakpoint_filters
+            .iter()
+            .flatten()
+            .map(|filter| filter.filter.as_str())
+            .collect();
+        assert_eq!(filters, ["hardfault", "reset", "svc", "hlt", "rust_panic"]);
         assert_eq!(
             capabilities.supports_delayed_stack_trace_loading,
             Some(false)
@@ -1098,10 +1164,8 @@ mod test {
         assert_ne!(capabilities.supports_terminate_request, Some(true));
         assert_ne!(capabilities.supports_modules_request, Some(true));
         assert_ne!(capabilities.supports_loaded_sources_request, Some(true));
-        assert_ne!(capabilities.supports_exception_info_request, Some(true));
         assert_ne!(capabilities.supports_exception_options, Some(true));
         assert_ne!(capabilities.supports_exception_filter_options, Some(true));
-        assert!(capabilities.exception_breakpoint_filters.is_none());
     }
 
     fn default_initialize_args() -> InitializeRequestArguments {
@@ -0,0 +1,710 @@
+//! Exception breakpoint filters, and reports for the DAP `exceptionInfo` request.
+//!
+//! The filters map to vector catch conditions of the core, except for the Rust panic filter,
+//! which sets a breakpoint on the panic handler. When a core halts on a caught exception, the fault status registers of the architecture are
+//! read and decoded into a [`FaultReport`]. When it halts on the Rust panic handler, the report
+//! holds the panic message and location, taken from the `PanicInfo` argument of the handler.
+
+use std::collections::VecDeque;
+
+use probe_rs::{
+    Architecture, CoreType, Error, RegisterValue, VectorCatchCondition,
+    architecture::riscv::registers,
+};
+
+use crate::cmd::dap_server::backend::rpc::RpcBackend;
+
+/// The symbol names the Rust panic handler is exported under, in order of preference.
+pub(crate) const PANIC_HANDLER_SYMBOLS: [&str; 2] = ["rust_begin_unwind", "panic_impl"];
+
+/// The exception breakpoint filters offered to the DAP client.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ExceptionFilter {
+    HardFault,
+    Reset,
+    Svc,
+    Hlt,
+    RustPanic,
+}
+
+impl ExceptionFilter {
+    pub(crate) const ALL: [ExceptionFilter; 5] = [
+        ExceptionFilter::HardFault,
+        ExceptionFilter::Reset,
+        ExceptionFilter::Svc,
+        ExceptionFilter::Hlt,
+        ExceptionFilter::RustPanic,
+    ];
+
+    /// The id used in the `setExceptionBreakpoints` request.
+    pub(crate) fn id(self) -> &'static str {
+        match self {
+            ExceptionFilter::HardFault => "hardfault",
+            ExceptionFilter::Reset => "reset",
+            ExceptionFilter::Svc => "svc",
+            ExceptionFilter::Hlt => "hlt",
+            ExceptionFilter::RustPanic => "rust_panic",
+        }
+    }
+
+    pub(crate) fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|filter| filter.id() == id)
+    }
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            ExceptionFilter::HardFault => "HardFault",
+            ExceptionFilter::Reset => "Core reset",
+            ExceptionFilter::Svc => "SVC",
+            ExceptionFilter::Hlt => "HLT",
+            ExceptionFilter::RustPanic => "Rust panic",
+        }
+    }
+
+    pub(crate) fn description(self) -> &'static str {
+        match self {
+            ExceptionFilter::HardFault => "Halt when the core takes a HardFault.",
+            ExceptionFilter::Reset => "Halt at the reset vector when the core is reset.",
+            ExceptionFilter::Svc => "Halt on supervisor calls (ARMv7-A/R only).",
+            ExceptionFilter::Hlt => "Halt on HLT instructions (ARMv7-A/R only).",
+            ExceptionFilter::RustPanic => {
+                "Halt in the panic handler, using one hardware breakpoint."
+            }
+        }
+    }
+
+    /// The initial state in the client, matching the default `catch_*` launch options.
+    pub(crate) fn default_enabled(self) -> bool {
+        self != ExceptionFilter::RustPanic
+    }
+
+    /// The vector catch condition that implements the filter, if it is one.
+    pub(crate) fn vector_catch(self) -> Option<VectorCatchCondition> {
+        match self {
+            ExceptionFilter::HardFault => Some(VectorCatchCondition::HardFault),
+            ExceptionFilter::Reset => Some(VectorCatchCondition::CoreReset),
+            ExceptionFilter::Svc => Some(VectorCatchCondition::Svc),
+            ExceptionFilter::Hlt => Some(VectorCatchCondition::Hlt),
+            ExceptionFilter::RustPanic => None,
+        }
+    }
+}
+
+/// A decoded exception, as reported to the DAP client.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct FaultReport {
+    /// A short name for the exception, e.g. `HardFault` or `Illegal instruction`.
+    pub(crate) exception_id: String,
+    /// A one line summary of the cause.
+    pub(crate) description: String,
+    /// The decoded register contents, one finding per entry.
+    pub(crate) details: Vec<String>,
+}
+
+/// System Control Block addresses of CFSR, HFSR, DFSR, MMFAR and BFAR, in that order.
+const CORTEX_M_FAULT_REGISTERS: u64 = 0xE000_ED28;
+
+/// CFSR bits, with the fault they report.
+const CFSR_BITS: [(u32, &str); 18] = [
+    (0, "MemManage: instruction access violation (IACCVIOL)"),
+    (1, "MemManage: data access violation (DACCVIOL)"),
+    (
+        3,
+        "MemManage: fault on unstacking for a return from exception (MUNSTKERR)",
+    ),
+    (
+        4,
+        "MemManage: fault on stacking for exception entry (MSTKERR)",
+    ),
+    (
+        5,
+        "MemManage: fault during floating-point lazy state preservation (MLSPERR)",
+    ),
+    (8, "BusFault: instruction bus error (IBUSERR)"),
+    (9, "BusFault: precise data bus error (PRECISERR)"),
+    (10, "BusFault: imprecise data bus error (IMPRECISERR)"),
+    (
+        11,
+        "BusFault: fault on unstacking for a return from exception (UNSTKERR)",
+    ),
+    (
+        12,
+        "BusFault: fault on stacking for exception entry (STKERR)",
+    ),
+    (
+        13,
+        "BusFault: fault during floating-point lazy state preservation (LSPERR)",
+    ),
+    (16, "UsageFault: undefined instruction (UNDEFINSTR)"),
+    (
+        17,
+        "UsageFault: invalid state, e.g. a branch to an ARM-mode address (INVSTATE)",
+    ),
+    (
+        18,
+        "UsageFault: invalid EXC_RETURN value loaded into the PC (INVPC)",
+    ),
+    (
+        19,
+        "UsageFault: coprocessor access while disabled or absent (NOCP)",
+    ),
+    (20, "UsageFault: stack limit violation (STKOF)"),
+    (24, "UsageFault: unaligned access (UNALIGNED)"),
+    (25, "UsageFault: divide by zero (DIVBYZERO)"),
+];
+const CFSR_MMARVALID: u32 = 1 << 7;
+const CFSR_BFARVALID: u32 = 1 << 15;
+
+/// HFSR bits, with the cause they report.
+const HFSR_BITS: [(u32, &str); 3] = [
+    (1, "HardFault: bus fault on a vector table read (VECTTBL)"),
+    (
+        30,
+        "HardFault: escalated from a configurable fault (FORCED)",
+    ),
+    (31, "HardFault: debug event (DEBUGEVT)"),
+];
+
+/// The fault status registers of a Cortex-M core.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct CortexMFaultRegisters {
+    /// The active exception number, from the IPSR bits of xPSR.
+    pub(crate) exception_number: u32,
+    /// The CFSR, HFSR, MMFAR and BFAR, if the core implements them (ARMv6-M does not).
+    pub(crate) status: Option<[u32; 4]>,
+}
+
+fn cortex_m_exception_name(exception_number: u32) -> String {
+    match exception_number {
+        // Thread mode, which is where a reset vector catch halts.
+        0 | 1 => "Reset".to_string(),
+        2 => "NMI".to_string(),
+        3 => "HardFault".to_string(),
+        4 => "MemManage".to_string(),
+        5 => "BusFault".to_string(),
+        6 => "UsageFault".to_string(),
+        7 => "SecureFault".to_string(),
+        11 => "SVCall".to_string(),
+        12 => "DebugMonitor".to_string(),
+        14 => "PendSV".to_string(),
+        15 => "SysTick".to_string(),
+        n if n >= 16 => format!("IRQ {}", n - 16),
+        n => format!("Exception {n}"),
+    }
+}
+
+/// Decodes the fault status registers of a Cortex-M core.
+pub(crate) fn decode_cortex_m(registers: CortexMFaultRegisters) -> FaultReport {
+    let exception_id = cortex_m_exception_name(registers.exception_number);
+    let mut details = Vec::new();
+    let Some([cfsr, hfsr, mmfar, bfar]) = registers.status else {
+        return FaultReport {
+            description: if registers.exception_number == 0 {
+                "Halted at the reset vector.".to_string()
+            } else {
+                format!("Halted in the {exception_id} handler.")
+            },
+            exception_id,
+            details: vec!["This core has no fault status registers.".to_string()],
+        };
+    };
+
+    details.extend(
+        HFSR_BITS
+            .iter()
+            .filter(|(bit, _)| hfsr & (1 << bit) != 0)
+            .map(|(_, fault)| fault.to_string()),
+    );
+    details.extend(
+        CFSR_BITS
+            .iter()
+            .filter(|(bit, _)| cfsr & (1 << bit) != 0)
+            .map(|(_, fault)| fault.to_string()),
+    );
+    if cfsr & CFSR_MMARVALID != 0 {
+        details.push(format!("Faulting data address (MMFAR): {mmfar:#010x}"));
+    }
+    if cfsr & CFSR_BFARVALID != 0 {
+        details.push(format!("Faulting data address (BFAR): {bfar:#010x}"));
+    }
+    details.push(format!("CFSR: {cfsr:#010x}, HFSR: {hfsr:#010x}"));
+
+    // The most specific cause is the configurable fault a HardFault was escalated from.
+    let description = details
+        .iter()
+        .find(|detail| !detail.starts_with("HardFault: escalated"))
+        .filter(|detail| !detail.starts_with("CFSR:"))
+        .cloned()
+        .unwrap_or_else(|| {
+            if registers.exception_number == 0 {
+                "Halted at the reset vector.".to_string()
+            } else {
+                format!("Halted in the {exception_id} handler, no fault is recorded.")
+            }
+        });
+
+    FaultReport {
+        exception_id,
+        description,
+        details,
+    }
+}
+
+fn riscv_exception_name(code: u64) -> String {
+    match code {
+        0 => "Instruction address misaligned",
+        1 => "Instruction access fault",
+        2 => "Illegal instruction",
+        3 => "Breakpoint",
+        4 => "Load address misaligned",
+        5 => "Load access fault",
+        6 => "Store/AMO address misaligned",
+        7 => "Store/AMO access fault",
+        8 => "Environment call from U-mode",
+        9 => "Environment call from S-mode",
+        11 => "Environment call from M-mode",
+        12 => "Instruction page fault",
+        13 => "Load page fault",
+        15 => "Store/AMO page fault",
+        18 => "Software check",
+        19 => "Hardware error",
+        code => return format!("Exception {code}"),
+    }
+    .to_string()
+}
+
+fn riscv_interrupt_name(code: u64) -> String {
+    match code {
+        1 => "Supervisor software interrupt",
+        3 => "Machine software interrupt",
+        5 => "Supervisor timer interrupt",
+        7 => "Machine timer interrupt",
+        9 => "Supervisor external interrupt",
+        11 => "Machine external interrupt",
+        code => return format!("Interrupt {code}"),
+    }
+    .to_string()
+}
+
+/// Decodes `mcause` and `mtval` of a RISC-V core, where `xlen` is the register width in bits.
+pub(crate) fn decode_riscv(mcause: u64, mtval: u64, xlen: u32) -> FaultReport {
+    let interrupt = mcause >> (xlen - 1) & 1 == 1;
+    let code = mcause & !(1 << (xlen - 1));
+    let (exception_id, mtval_meaning) = if interrupt {
+        (riscv_interrupt_name(code), None)
+    } else {
+        let meaning = match code {
+            0 | 1 | 4..=7 | 12 | 13 | 15 => Some("faulting address"),
+            2 => Some("instruction"),
+            3 => Some("breakpoint address"),
+            _ => None,
+        };
+        (riscv_exception_name(code), meaning)
+    };
+
+    let mut details = vec![format!("mcause: {mcause:#x} ({exception_id})")];
+    match mtval_meaning {
+        Some(meaning) if mtval != 0 => details.push(format!("mtval: {mtval:#x} ({meaning})")),
+        _ => details.push(format!("mtval: {mtval:#x}")),
+    }
+
+    FaultReport {
+        description: match mtval_meaning {
+            Some(meaning) if mtval != 0 => format!("{exception_id}, {meaning} {mtval:#x}"),
+            _ => exception_id.clone(),
+        },
+        exception_id,
+        details,
+    }
+}
+
+fn xtensa_exception_name(exccause: u32) -> String {
+    match exccause {
+        0 => "IllegalInstruction",
+        1 => "Syscall",
+        2 => "InstructionFetchError",
+        3 => "LoadStoreError",
+        4 => "Level1Interrupt",
+        5 => "Alloca",
+        6 => "IntegerDivideByZero",
+        8 => "Privileged",
+        9 => "LoadStoreAlignment",
+        12 => "InstrPIFDataError",
+        13 => "LoadStorePIFDataError",
+        14 => "InstrPIFAddrError",
+        15 => "LoadStorePIFAddrError",
+        16 => "InstTLBMiss",
+        17 => "InstTLBMultiHit",
+        18 => "InstFetchPrivilege",
+        20 => "InstFetchProhibited",
+        24 => "LoadStoreTLBMiss",
+        25 => "LoadStoreTLBMultiHit",
+        26 => "LoadStorePrivilege",
+        28 => "LoadProhibited",
+        29 => "StoreProhibited",
+        32..=39 => return format!("Coprocessor{}Disabled", exccause - 32),
+        cause => return format!("Exception {cause}"),
+    }
+    .to_string()
+}
+
+/// Decodes `EXCCAUSE` and `EXCVADDR` of an Xtensa core.
+pub(crate) fn decode_xtensa(exccause: u32, excvaddr: u32) -> FaultReport {
+    let exception_id = xtensa_exception_name(exccause);
+    // EXCVADDR only holds an address for memory access exceptions.
+    let has_address = matches!(exccause, 2 | 3 | 9 | 12..=29);
+
+    let mut details = vec![format!("EXCCAUSE: {exccause} ({exception_id})")];
+    if has_address {
+        details.push(format!("EXCVADDR: {excvaddr:#010x} (faulting address)"));
+    }
+
+    FaultReport {
+        description: if has_address {
+            format!("{exception_id} at address {excvaddr:#010x}")
+        } else {
+            exception_id.clone()
+        },
+        exception_id,
+        details,
+    }
+}
+
+/// Builds the report for a halt on the panic handler.
+///
+/// `pieces` are the literal parts of the `fmt::Arguments` of the panic message, and `arguments`
+/// is the number of formatted values, which can't be rendered without running the formatter and
+/// are shown as `{..}`.
+pub(crate) fn panic_report(
+    pieces: Option<&[String]>,
+    arguments: usize,
+    location: Option<(String, Option<u64>, Option<u64>)>,
+) -> FaultReport {
+    let message = pieces.map(|pieces| {
+        let mut message = String::new();
+        for (index, piece) in pieces.iter().enumerate() {
+            message.push_str(piece);
+            if index < arguments {
+                message.push_str("{..}");
+            }
+        }
+        for _ in pieces.len()..arguments {
+            message.push_str("{..}");
+        }
+        message
+    });
+    let location = location.map(|(file, line, column)| match (line, column) {
+        (Some(line), Some(column)) => format!("{file}:{line}:{column}"),
+        (Some(line), None) => format!("{file}:{line}"),
+        _ => file,
+    });
+
+    let description = match (&message, &location) {
+        (Some(message), Some(location)) => format!("panicked at {location}:\n{message}"),
+        (Some(message), None) => format!("panicked: {message}"),
+        (None, Some(location)) => format!("panicked at {location}"),
+        (None, None) => "panicked, but the panic message could not be read".to_string(),
+    };
+
+    FaultReport {
+        exception_id: "panic".to_string(),
+        details: message.into_iter().collect(),
+        description,
+    }
+}
+
+/// Reads and decodes the fault status registers of a halted core.
+pub(crate) async fn read_fault_report(
+    backend: &mut RpcBackend,
+    core_index: usize,
+) -> Result<FaultReport, Error> {
+    let core_type = backend
+        .target_metadata
+        .cores
+        .iter()
+        .find_map(|(index, core_type)| (*index == core_index).then_some(*core_type))
+        .ok_or(Error::CoreNotFound(core_index))?;
+    let registers = backend
+        .core_metadata
+        .get(core_index)
+        .map(|metadata| metadata.registers)
+        .ok_or(Error::CoreNotFound(core_index))?;
+
+    match core_type.architecture() {
+        Architecture::Arm if core_type.is_cortex_m() => {
+            let psr = registers
+                .psr()
+                .ok_or_else(|| Error::Other("No xPSR register for this core".to_string()))?;
+            let xpsr: u32 = backend
+                .read_core_reg(core_index, psr.id)
+                .await?
+                .try_into()?;
+            let status = if core_type == CoreType::Armv6m {
+                None
+            } else {
+                let words = backend
+                    .read_memory_32(core_index, CORTEX_M_FAULT_REGISTERS, 5)
+                    .await?;
+                let &[cfsr, hfsr, _dfsr, mmfar, bfar] = words.as_slice() else {
+                    return Err(Error::Other(
+                        "Short read of the fault status registers".to_string(),
+                    ));
+                };
+                Some([cfsr, hfsr, mmfar, bfar])
+            };
+            Ok(decode_cortex_m(CortexMFaultRegisters {
+                exception_number: xpsr & 0x1FF,
+                status,
+            }))
+        }
+        Architecture::Riscv => {
+            let values = backend
+                .read_core_registers(core_index, vec![registers::MCAUSE, registers::MTVAL])
+                .await?;
+            let [Some(mcause), mtval] = values.as_slice() else {
+                return Err(Error::Other("Could not read mcause".to_string()));
+            };
+            let xlen = match mcause {
+                RegisterValue::U64(_) => 64,
+                _ => 32,
+            };
+            let mcause: u64 = (*mcause).try_into()?;
+            let mtval: Option<u64> = mtval.map(TryInto::try_into).transpose()?;
+            Ok(decode_riscv(mcause, mtval.unwrap_or(0), xlen))
+        }
+        Architecture::Xtensa => {
+            let register = |name| {
+                registers
+                    .other_by_name(name)
+                    .map(|register| register.id)
+                    .ok_or_else(|| Error::Other(format!("No {name} register for this core")))
+            };
+            let ids = vec![register("EXCCAUSE")?, register("EXCVADDR")?];
+            let values = backend.read_core_registers(core_index, ids).await?;
+            let [Some(exccause), excvaddr] = values.as_slice() else {
+                return Err(Error::Other("Could not read EXCCAUSE".to_string()));
+            };
+            let exccause: u32 = (*exccause).try_into()?;
+            let excvaddr: Option<u32> = excvaddr.map(TryInto::try_into).transpose()?;
+            Ok(decode_xtensa(exccause, excvaddr.unwrap_or(0)))
+        }
+        Architecture::Arm => Ok(FaultReport {
+            exception_id: "Exception".to_string(),
+            description: "Halted on an exception vector.".to_string(),
+            details: Vec::new(),
+        }),
+    }
+}
+
+/// Reads the panic message and location from the `PanicInfo` argument of the panic handler the
+/// core is halted in.
+///
+/// This unwinds the top frame on the server, so it must run before the client sees the halt.
+pub(crate) async fn read_panic_report(backend: &mut RpcBackend, core_index: usize) -> FaultReport {
+    let Ok(Some(panic_info)) = find_panic_info(backend, core_index).await else {
+        return panic_report(None, 0, None);
+    };
+
+    let mut pieces = None;
+    let mut arguments = 0;
+    if let Some(message) = find_variable(backend, core_index, panic_info, "message").await {
+        if let Some(reference) = find_variable(backend, core_index, message, "pieces").await {
+            pieces = backend
+                .variables(core_index, reference, None)
+                .await
+                .ok()
+                .map(|pieces| {
+                    pieces
+                        .into_iter()
+                        .map(|piece| unquote(&piece.value).to_string())
+                        .collect::<Vec<_>>()
+                });
+        }
+        if let Some(reference) = find_variable(backend, core_index, message, "args").await {
+            arguments = backend
+                .variables(core_index, reference, None)
+                .await
+                .map(|arguments| arguments.len())
+                .unwrap_or(0);
+        }
+    }
+
+    let mut location = None;
+    if let Some(reference) = find_variable(backend, core_index, panic_info, "location").await
+        && let Ok(fields) = backend.variables(core_index, reference, None).await
+    {
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|field| field.name == name)
+                .map(|field| field.value.clone())
+        };
+        location = field("file").map(|file| {
+            (
+                unquote(&file).to_string(),
+                field("line").and_then(|line| line.parse().ok()),
+                field("col").and_then(|column| column.parse().ok()),
+            )
+        });
+    }
+
+    panic_report(pieces.as_deref(), arguments, location)
+}
+
+/// Returns the variables reference of the `PanicInfo` argument in the top frame.
+async fn find_panic_info(
+    backend: &mut RpcBackend,
+    core_index: usize,
+) -> Result<Option<u32>, Error> {
+    let frames = backend.unwind_stack(core_index, 1).await?;
+    let Some(frame) = frames.first() else {
+        return Ok(None);
+    };
+    let frame_id = i64::from(frame.id) as u32;
+    for scope in backend.scopes(core_index, frame_id).await? {
+        if scope.name != "Variables" {
+            continue;
+        }
+        let variables = backend
+            .variables(core_index, scope.variables_reference as u32, None)
+            .await?;
+        // `PanicInfo` was renamed to `PanicHookInfo` in `std`, but `core` still uses the old name.
+        if let Some(panic_info) = variables.iter().find(|variable| {
+            variable
+                .type_
+                .as_deref()
+                .is_some_and(|type_name| type_name.contains("PanicInfo"))
+        }) {
+            return Ok(Some(panic_info.variables_reference as u32));
+        }
+    }
+    Ok(None)
+}
+
+/// Searches the children of `reference` for a variable called `name`, looking through
+/// references and wrappers up to a few levels deep.
+async fn find_variable(
+    backend: &mut RpcBackend,
+    core_index: usize,
+    reference: u32,
+    name: &str,
+) -> Option<u32> {
+    const MAX_DEPTH: usize = 4;
+
+    let mut queue = VecDeque::from([(reference, 0)]);
+    while let Some((reference, depth)) = queue.pop_front() {
+        let Ok(children) = backend.variables(core_index, reference, None).await else {
+            continue;
+        };
+        for child in children {
+            if child.variables_reference <= 0 {
+                continue;
+            }
+            if child.name == name {
+                return Some(child.variables_reference as u32);
+            }
+            if depth + 1 < MAX_DEPTH {
+                queue.push_back((child.variables_reference as u32, depth + 1));
+            }
+        }
+    }
+    None
+}
+
+/// Strips the quotes the debugger puts around string values.
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decodes_escalated_cortex_m_fault() {
+        // A forced HardFault from a precise bus fault at 0x2004_0000.
+        let report = decode_cortex_m(CortexMFaultRegisters {
+            exception_number: 3,
+            status: Some([(1 << 9) | CFSR_BFARVALID, 1 << 30, 0, 0x2004_0000]),
+        });
+
+        assert_eq!(report.exception_id, "HardFault");
+        assert_eq!(
+            report.description,
+            "BusFault: precise data bus error (PRECISERR)"
+        );
+        assert!(
+            report
+                .details
+                .contains(&"Faulting data address (BFAR): 0x20040000".to_string())
+        );
+        assert!(!report.details.iter().any(|detail| detail.contains("MMFAR")));
+    }
+
+    #[test]
+    fn decodes_cortex_m_without_fault_status() {
+        let report = decode_cortex_m(CortexMFaultRegisters {
+            exception_number: 0,
+            status: None,
+        });
+        assert_eq!(report.exception_id, "Reset");
+        assert_eq!(report.description, "Halted at the reset vector.");
+
+        let report = decode_cortex_m(CortexMFaultRegisters {
+            exception_number: 3,
+            status: Some([0; 4]),
+        });
+        assert_eq!(
+            report.description,
+            "Halted in the HardFault handler, no fault is recorded."
+        );
+    }
+
+    #[test]
+    fn decodes_riscv_mcause() {
+        let report = decode_riscv(5, 0x1000, 32);
+        assert_eq!(report.exception_id, "Load access fault");
+        assert_eq!(
+            report.description,
+            "Load access fault, faulting address 0x1000"
+        );
+
+        let report = decode_riscv(0x8000_0000_0000_0007, 0, 64);
+        assert_eq!(report.exception_id, "Machine timer interrupt");
+        assert_eq!(report.details[1], "mtval: 0x0");
+    }
+
+    #[test]
+    fn decodes_xtensa_exccause() {
+        let report = decode_xtensa(29, 0x4);
+        assert_eq!(report.exception_id, "StoreProhibited");
+        assert_eq!(report.description, "StoreProhibited at address 0x00000004");
+
+        let report = decode_xtensa(6, 0x4);
+        assert_eq!(report.description, "IntegerDivideByZero");
+        assert_eq!(report.details.len(), 1);
+    }
+
+    #[test]
+    fn renders_panic_message() {
+        let pieces = [
+            "index out of bounds: the len is ".to_string(),
+            " but the index is ".to_string(),
+        ];
+        let report = panic_report(
+            Some(&pieces),
+            2,
+            Some(("src/main.rs".to_string(), Some(12), Some(5))),
+        );
+        assert_eq!(
+            report.description,
+            "panicked at src/main.rs:12:5:\nindex out of bounds: the len is {..} but the index is {..}"
+        );
+
+        let report = panic_report(None, 0, None);
+        assert!(report.details.is_empty());
+    }
+}
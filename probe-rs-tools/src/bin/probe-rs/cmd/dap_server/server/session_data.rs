@@ -1,6 +1,8 @@
 use super::{
+    breakpoint_conditions::{BreakpointAction, BreakpointConditions},
     configuration::{self, CoreConfig, SessionConfig},
     core_data::{ChannelNames, CoreData},
+    fault_analysis::{self, PANIC_HANDLER_SYMBOLS},
 };
 use crate::cmd::dap_server::debug_adapter::dap::dap_types::PromptKind;
 use crate::cmd::dap_server::server::debug_rtt;
@@ -12,7 +14,9 @@ use crate::cmd::{
             adapter::DebugAdapter,
             core_status::DapStatus,
             dap_types::{ContinuedEventBody, MessageSeverity, Source, StoppedEventBody},
-            repl_commands::{REPL_COMMANDS, embedded_test::EMBEDDED_TEST},
+            repl_commands::{
+                REPL_COMMANDS, branch_trace::BRANCH_TRACE, embedded_test::EMBEDDED_TEST,
+            },
         },
     },
     run::EmbeddedTestElfInfo,
@@ -24,10 +28,11 @@ use anyhow::{Result, anyhow};
 use probe_rs::{BreakpointCause, CoreStatus, HaltReason, rtt::find_rtt_control_block_in_raw_file};
 use probe_rs_debug::SourceLocation;
 use probe_rs_rpc::breakpoints::SourceBreakpointLocation;
+use probe_rs_rpc::core_ops::WireWatchpoint;
 use probe_rs_rpc::format::FormatKind;
 use probe_rs_rpc::rtt_client::ScanRegion as WireScanRegion;
 use probe_rs_rpc_client::{ResolvedUpload, RpcClient};
-use std::{any::Any, env::set_current_dir, path::Path};
+use std::{any::Any, collections::HashMap, env::set_current_dir, path::Path};
 use time::UtcOffset;
 
 use crate::util::rtt::RttConfig;
@@ -35,6 +40,7 @@ use probe_rs_rpc::rtt_config::DataFormat;
 
 /// The supported breakpoint types
 #[derive(Clone, Debug, PartialEq)]
+#[expect(clippy::enum_variant_names)]
 pub(crate) enum BreakpointType {
     /// A breakpoint was requested using an instruction address, and usually a result of a user requesting a
     /// breakpoint while in a 'disassembly' view.
@@ -44,6 +50,21 @@ pub(crate) enum BreakpointType {
         source: Box<Source>,
         location: SourceLocationScope,
     },
+    /// A breakpoint on every instance of a function, usually a result of a user requesting a
+    /// breakpoint by function name in the 'breakpoints' view.
+    FunctionBreakpoint {
+        /// The function name, as requested by the user.
+        name: String,
+    },
+    /// A data breakpoint on a variable, usually a result of a user requesting a breakpoint on a
+    /// value change in the 'variables' view.
+    DataBreakpoint {
+        /// The name of the variable, reported when the breakpoint is hit.
+        name: String,
+        watchpoint: WireWatchpoint,
+    },
+    /// A breakpoint on the Rust panic handler, set through the `rust_panic` exception filter.
+    PanicBreakpoint,
 }
 
 /// Breakpoint requests refer to a specific `SourceLocation` for a `Source`.
@@ -57,6 +78,9 @@ pub(crate) enum SourceLocationScope {
 pub struct ActiveBreakpoint {
     pub(crate) breakpoint_type: BreakpointType,
     pub(crate) address: u64,
+    /// The condition, hit condition and log message, which are evaluated by the debugger when
+    /// the breakpoint is hit.
+    pub(crate) conditions: Option<BreakpointConditions>,
 }
 
 /// DAP-session state and per-core display metadata.
@@ -140,12 +164,35 @@ impl SessionData {
         }
 
         let mut backend = RpcBackend::new(client.clone(), sessid, target_metadata, per_core);
+        backend.use_flash_breakpoints(config.flashing_config.flash_breakpoints);
 
         let core_data_vec = initialize_core_data(&mut backend, config)?;
         for core_config in config.core_configs.iter() {
             backend
                 .apply_vector_catch(core_config.core_index, core_config)
                 .await?;
+
+            if let Some(buffer) = core_config.mtb_buffer.clone() {
+                backend
+                    .session_interface()
+                    .start_branch_trace(buffer)
+                    .await
+                    .map_err(|e| {
+                        DebuggerError::Other(anyhow!("Failed to set up the MTB branch trace: {e}"))
+                    })?;
+            }
+        }
+
+        for cores in run_control_groups(&config.core_configs) {
+            backend
+                .session_interface()
+                .create_run_control_group(cores.iter().map(|&core| core as u32).collect())
+                .await
+                .map_err(|e| {
+                    DebuggerError::Other(anyhow!(
+                        "Failed to create the run-control group of cores {cores:?}: {e}"
+                    ))
+                })?;
         }
 
         // Eagerly populate the authoritative server-side `DebugInfo` so
@@ -324,12 +371,26 @@ impl SessionData {
         &mut self,
         core_index: usize,
     ) -> Result<(), DebuggerError> {
+        self.recompute_function_breakpoints(core_index).await?;
+        let has_panic_breakpoint = self.core_data_opt(core_index).is_some_and(|core_data| {
+            core_data
+                .breakpoints
+                .iter()
+                .any(|bp| bp.breakpoint_type == BreakpointType::PanicBreakpoint)
+        });
+        if has_panic_breakpoint
+            && let Err(error) = self.set_panic_breakpoint(core_index, true).await
+        {
+            tracing::warn!("Dropping the panic breakpoint. Error: {error}");
+        }
+
         let (old_addrs, pending) = {
             let Some(core_data) = self.core_data_opt(core_index) else {
                 return Ok(());
             };
             let mut old_addrs: Vec<u64> = Vec::new();
-            let mut pending: Vec<(Box<Source>, SourceLocation)> = Vec::new();
+            let mut pending: Vec<(Box<Source>, SourceLocation, Option<BreakpointConditions>)> =
+                Vec::new();
             for bp in &core_data.breakpoints {
                 let BreakpointType::SourceBreakpoint {
                     source,
@@ -339,7 +400,7 @@ impl SessionData {
                     continue;
                 };
                 old_addrs.push(bp.address);
-                pending.push((source.clone(), loc.clone()));
+                pending.push((source.clone(), loc.clone(), bp.conditions.clone()));
             }
             (old_addrs, pending)
         };
@@ -348,7 +409,7 @@ impl SessionData {
         }
         let requests = pending
             .iter()
-            .map(|(_, location)| SourceBreakpointLocation {
+            .map(|(_, location, _)| SourceBreakpointLocation {
                 path: location.path.to_path().display().to_string(),
                 line: location.line.unwrap_or(0),
                 column: location.column.map(|column| match column {
@@ -363,9 +424,14 @@ impl SessionData {
             .await
             .map_err(DebuggerError::ProbeRs)?;
         let mut to_set = Vec::with_capacity(resolved.len());
-        for ((source, location), result) in pending.into_iter().zip(resolved) {
+        for ((source, location, conditions), result) in pending.into_iter().zip(resolved) {
             match result {
-                Ok(verified) => to_set.push((verified.address, source, verified.source_location)),
+                Ok(verified) => to_set.push((
+                    verified.address,
+                    source,
+                    verified.source_location,
+                    conditions,
+                )),
                 Err(error) => {
                     return Err(DebuggerError::Other(anyhow!(
                         "Failed to recompute breakpoint at {location:?} in {source:?}. Error: {error}"
@@ -382,14 +448,14 @@ impl SessionData {
                 !matches!(&bp.breakpoint_type, BreakpointType::SourceBreakpoint { .. })
             });
         }
-        let set_addrs: Vec<u64> = to_set.iter().map(|(a, _, _)| *a).collect();
+        let set_addrs: Vec<u64> = to_set.iter().map(|(a, _, _, _)| *a).collect();
         let set_results = self
             .backend
             .set_hw_breakpoints(core_index, set_addrs)
             .await
             .map_err(DebuggerError::ProbeRs)?;
         if let Ok(core_data) = self.core_data_mut(core_index) {
-            for (i, (addr, source, loc)) in to_set.into_iter().enumerate() {
+            for (i, (addr, source, loc, conditions)) in to_set.into_iter().enumerate() {
                 if set_results.get(i).is_some_and(|result| result.is_ok()) {
                     core_data.breakpoints.push(ActiveBreakpoint {
                         breakpoint_type: BreakpointType::SourceBreakpoint {
@@ -397,6 +463,7 @@ impl SessionData {
                             location: SourceLocationScope::Specific(loc),
                         },
                         address: addr,
+                        conditions,
                     });
                 }
             }
@@ -404,6 +471,160 @@ impl SessionData {
         Ok(())
     }
 
+    /// Recompute function breakpoint addresses after a restart, like
+    /// [`Self::recompute_breakpoints`] does for source breakpoints. The new
+    /// binary may have a different set of instances for a function, so each
+    /// function name is resolved again.
+    async fn recompute_function_breakpoints(
+        &mut self,
+        core_index: usize,
+    ) -> Result<(), DebuggerError> {
+        let Some(core_data) = self.core_data_opt(core_index) else {
+            return Ok(());
+        };
+        let mut old_addrs: Vec<u64> = Vec::new();
+        let mut functions: Vec<(String, Option<BreakpointConditions>)> = Vec::new();
+        for bp in &core_data.breakpoints {
+            let BreakpointType::FunctionBreakpoint { name } = &bp.breakpoint_type else {
+                continue;
+            };
+            old_addrs.push(bp.address);
+            if !functions.iter().any(|(function, _)| function == name) {
+                functions.push((name.clone(), bp.conditions.clone()));
+            }
+        }
+        if old_addrs.is_empty() {
+            return Ok(());
+        }
+
+        let resolved = self
+            .backend
+            .resolve_function_breakpoints(functions.iter().map(|(name, _)| name.clone()).collect())
+            .await
+            .map_err(DebuggerError::ProbeRs)?;
+        self.backend
+            .clear_hw_breakpoints(core_index, old_addrs)
+            .await
+            .map_err(DebuggerError::ProbeRs)?;
+        if let Ok(core_data) = self.core_data_mut(core_index) {
+            core_data.breakpoints.retain(|bp| {
+                !matches!(
+                    &bp.breakpoint_type,
+                    BreakpointType::FunctionBreakpoint { .. }
+                )
+            });
+        }
+
+        let mut to_set = Vec::new();
+        for ((name, conditions), result) in functions.into_iter().zip(resolved) {
+            match result {
+                Ok(instances) => to_set.extend(
+                    instances
+                        .into_iter()
+                        .map(|instance| (instance.address, name.clone(), conditions.clone())),
+                ),
+                Err(error) => {
+                    tracing::warn!("Dropping function breakpoint on `{name}`. Error: {error}")
+                }
+            }
+        }
+        let set_addrs: Vec<u64> = to_set.iter().map(|(address, _, _)| *address).collect();
+        let set_results = self
+            .backend
+            .set_hw_breakpoints(core_index, set_addrs)
+            .await
+            .map_err(DebuggerError::ProbeRs)?;
+        if let Ok(core_data) = self.core_data_mut(core_index) {
+            for ((address, name, conditions), result) in to_set.into_iter().zip(set_results) {
+                if result.is_ok() {
+                    core_data.breakpoints.push(ActiveBreakpoint {
+                        breakpoint_type: BreakpointType::FunctionBreakpoint { name },
+                        address,
+                        conditions,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Set or clear the breakpoints on the Rust panic handler, for the `rust_panic` exception
+    /// filter.
+    pub(crate) async fn set_panic_breakpoint(
+        &mut self,
+        core_index: usize,
+        enabled: bool,
+    ) -> Result<(), String> {
+        let core_data = self.core_data_mut(core_index).map_err(|e| e.to_string())?;
+        let old_addrs: Vec<u64> = core_data
+            .breakpoints
+            .iter()
+            .filter(|bp| bp.breakpoint_type == BreakpointType::PanicBreakpoint)
+            .map(|bp| bp.address)
+            .collect();
+        core_data
+            .breakpoints
+            .retain(|bp| bp.breakpoint_type != BreakpointType::PanicBreakpoint);
+        if !old_addrs.is_empty()
+            && let Err(error) = self
+                .backend
+                .clear_hw_breakpoints(core_index, old_addrs)
+                .await
+        {
+            tracing::warn!("Failed to clear the panic breakpoint. {error}");
+        }
+        if !enabled {
+            return Ok(());
+        }
+
+        let resolved = self
+            .backend
+            .resolve_function_breakpoints(
+                PANIC_HANDLER_SYMBOLS
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect(),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        let Some(instances) = resolved.into_iter().find_map(Result::ok) else {
+            return Err(format!(
+                "No panic handler ({}) was found in the debug information.",
+                PANIC_HANDLER_SYMBOLS.map(|s| format!("`{s}`")).join(" or ")
+            ));
+        };
+        let addresses: Vec<u64> = instances.iter().map(|instance| instance.address).collect();
+        let results = self
+            .backend
+            .set_hw_breakpoints(core_index, addresses.clone())
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let mut error = None;
+        let core_data = self.core_data_mut(core_index).map_err(|e| e.to_string())?;
+        for (address, result) in addresses.into_iter().zip(results) {
+            match result {
+                Ok(()) => core_data.breakpoints.push(ActiveBreakpoint {
+                    breakpoint_type: BreakpointType::PanicBreakpoint,
+                    address,
+                    conditions: None,
+                }),
+                Err(e) => error = Some(e.to_string()),
+            }
+        }
+        match error {
+            Some(error)
+                if !core_data
+                    .breakpoints
+                    .iter()
+                    .any(|bp| bp.breakpoint_type == BreakpointType::PanicBreakpoint) =>
+            {
+                Err(error)
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Publish server-owned debug info from a prior [`ResolvedUpload`].
     pub(crate) async fn reload_debug_info_resolved(
         &mut self,
@@ -444,21 +665,194 @@ impl SessionData {
     ) -> Result<(), DebuggerError> {
         let core_index = self.core_data[cd_idx].core_index;
         let program_counter = self.backend.program_counter(core_index).await;
-        let (reason, description) = status.short_long_status(program_counter);
+        let (reason, mut description) = status.short_long_status(program_counter);
+        let mut reason = reason.to_string();
+        let mut hit_breakpoint_ids = None;
+        if let Some((name, watchpoint)) = self.triggered_data_breakpoint(cd_idx, status).await {
+            reason = "data breakpoint".to_string();
+            description = format!(
+                "Halted on data breakpoint on `{name}` ({} bytes @{:#010x}).",
+                watchpoint.size, watchpoint.address
+            );
+            hit_breakpoint_ids = Some(vec![watchpoint.address as i64]);
+        }
+        let exception_report = self.exception_report(cd_idx, status, program_counter).await;
+        let mut text = None;
+        if let Some(report) = &exception_report {
+            reason = "exception".to_string();
+            description = report.description.clone();
+            text = Some(report.exception_id.clone());
+        }
+        self.core_data[cd_idx].exception_report = exception_report;
         let event_body = Some(StoppedEventBody {
-            reason: reason.to_string(),
+            reason,
             description: Some(description),
             thread_id: Some(core_index as i64),
             preserve_focus_hint: Some(false),
-            text: None,
+            text,
             all_threads_stopped: Some(debug_adapter.all_cores_halted),
-            hit_breakpoint_ids: None,
+            hit_breakpoint_ids,
         });
         debug_adapter.send_event("stopped", event_body)?;
         tracing::trace!("Notified DAP client that the core halted: {:?}", status);
         Ok(())
     }
 
+    /// Decode the exception or panic the core halted on, if it did.
+    async fn exception_report(
+        &mut self,
+        cd_idx: usize,
+        status: CoreStatus,
+        program_counter: Option<u64>,
+    ) -> Option<fault_analysis::FaultReport> {
+        let core_index = self.core_data[cd_idx].core_index;
+        match status {
+            CoreStatus::Halted(HaltReason::Exception) | CoreStatus::LockedUp => {
+                match fault_analysis::read_fault_report(&mut self.backend, core_index).await {
+                    Ok(report) => Some(report),
+                    Err(error) => {
+                        tracing::debug!("Could not decode the exception: {error}");
+                        None
+                    }
+                }
+            }
+            CoreStatus::Halted(HaltReason::Breakpoint(_))
+                if self.core_data[cd_idx].breakpoints.iter().any(|bp| {
+                    bp.breakpoint_type == BreakpointType::PanicBreakpoint
+                        && Some(bp.address) == program_counter
+                }) =>
+            {
+                Some(fault_analysis::read_panic_report(&mut self.backend, core_index).await)
+            }
+            _ => None,
+        }
+    }
+
+    /// If the core halted on one of the DAP client's data breakpoints, return the
+    /// name of the watched variable and the watchpoint that triggered.
+    async fn triggered_data_breakpoint(
+        &mut self,
+        cd_idx: usize,
+        status: CoreStatus,
+    ) -> Option<(String, WireWatchpoint)> {
+        // Depending on the architecture, a watchpoint hit is reported either as
+        // a watchpoint or as a hardware breakpoint.
+        if !matches!(
+            status,
+            CoreStatus::Halted(
+                HaltReason::Watchpoint | HaltReason::Breakpoint(BreakpointCause::Hardware)
+            )
+        ) {
+            return None;
+        }
+        let core_data = &self.core_data[cd_idx];
+        if !core_data
+            .breakpoints
+            .iter()
+            .any(|bp| matches!(bp.breakpoint_type, BreakpointType::DataBreakpoint { .. }))
+        {
+            return None;
+        }
+
+        let hit = match self
+            .backend
+            .triggered_watchpoint(core_data.core_index)
+            .await
+        {
+            Ok(hit) => hit?,
+            Err(error) => {
+                tracing::debug!("Could not determine the triggered watchpoint: {error}");
+                return None;
+            }
+        };
+        self.core_data[cd_idx]
+            .breakpoints
+            .iter()
+            .find_map(|bp| match &bp.breakpoint_type {
+                BreakpointType::DataBreakpoint { name, watchpoint }
+                    if watchpoint.address == hit.address =>
+                {
+                    Some((name.clone(), *watchpoint))
+                }
+                _ => None,
+            })
+    }
+
+    /// Evaluate the conditions of the breakpoint the core halted on, and return `false` if the
+    /// core should be resumed without notifying the client. Logpoint messages are written to the
+    /// debug console.
+    ///
+    /// If the conditions can't be evaluated, the core stays halted so the user can investigate.
+    async fn should_stop_at_breakpoint(
+        &mut self,
+        debug_adapter: &mut DebugAdapter,
+        cd_idx: usize,
+    ) -> bool {
+        let core_index = self.core_data[cd_idx].core_index;
+        let Some(program_counter) = self.backend.program_counter(core_index).await else {
+            return true;
+        };
+        let Some(bp_idx) = self.core_data[cd_idx].breakpoints.iter().position(|bp| {
+            bp.address == program_counter
+                && bp.conditions.is_some()
+                && !matches!(bp.breakpoint_type, BreakpointType::DataBreakpoint { .. })
+        }) else {
+            return true;
+        };
+        let names = self.core_data[cd_idx].breakpoints[bp_idx]
+            .conditions
+            .as_ref()
+            .map(BreakpointConditions::variables)
+            .unwrap_or_default();
+
+        // Variables are resolved against the server-side stack frames, which only exist after
+        // an unwind. The top frame is all that is needed.
+        let mut values = HashMap::new();
+        if !names.is_empty() {
+            if let Err(error) = self.backend.unwind_stack(core_index, 1).await {
+                debug_adapter.log_to_console(format!(
+                    "Could not evaluate the breakpoint condition at {program_counter:#010x}: {error}"
+                ));
+                return true;
+            }
+            for name in names {
+                match self
+                    .backend
+                    .session_interface()
+                    .evaluate(core_index as u32, None, name.clone())
+                    .await
+                {
+                    Ok(response) if !response.result.starts_with("<invalid expression") => {
+                        values.insert(name, response.result);
+                    }
+                    Ok(_) => {}
+                    Err(error) => tracing::debug!("Could not evaluate `{name}`: {error}"),
+                }
+            }
+        }
+
+        let Some(conditions) = self.core_data[cd_idx].breakpoints[bp_idx]
+            .conditions
+            .as_mut()
+        else {
+            return true;
+        };
+        match conditions.on_hit(|name| values.get(name).cloned()) {
+            Ok(BreakpointAction::Stop) => true,
+            Ok(BreakpointAction::Continue) => false,
+            Ok(BreakpointAction::Log(message)) => {
+                debug_adapter.log_to_console(message);
+                false
+            }
+            Err(error) => {
+                debug_adapter.log_to_console(format!(
+                    "Could not evaluate the breakpoint condition at {program_counter:#010x}: {error}"
+                ));
+                true
+            }
+        }
+    }
+
     /// Update `last_known_status` and emit the appropriate DAP event for a
     /// status transition, without a live `Core`. Semihosting halts are
     /// skipped here (the poll loop handles them separately) and the PC for
@@ -485,6 +879,19 @@ impl SessionData {
             }
             CoreStatus::Halted(HaltReason::Step) => {}
             CoreStatus::Halted(HaltReason::Breakpoint(BreakpointCause::Semihosting(_))) => {}
+            CoreStatus::Halted(HaltReason::Breakpoint(_)) => {
+                if !self.should_stop_at_breakpoint(debug_adapter, cd_idx).await {
+                    let core_index = self.core_data[cd_idx].core_index;
+                    self.backend
+                        .run(core_index)
+                        .await
+                        .map_err(DebuggerError::ProbeRs)?;
+                    // The client never saw the halt, so there is no `continued` event to send.
+                    self.core_data[cd_idx].last_known_status = CoreStatus::Running;
+                    return Ok(CoreStatus::Running);
+                }
+                self.notify_halted(debug_adapter, cd_idx, status).await?
+            }
             CoreStatus::Halted(_) => self.notify_halted(debug_adapter, cd_idx, status).await?,
             CoreStatus::LockedUp => {
                 debug_adapter.show_message(
@@ -825,9 +1232,9 @@ impl SessionData {
         // Ask the RPC server to unwind each newly halted core, then replace
         // the client metadata-only display cache with the returned frames.
         for &core_index in &needs_unwind {
-            let frames = self
+            let (frames, threads) = self
                 .backend
-                .unwind_stack(core_index, 500)
+                .unwind_stack_and_threads(core_index, 500)
                 .await
                 .map_err(DebuggerError::ProbeRs)?;
 
@@ -837,6 +1244,7 @@ impl SessionData {
                 .find(|cd| cd.core_index == core_index)
             {
                 core_data.replace_stack_frame_cache(frames);
+                core_data.replace_rtos_thread_cache(threads);
             }
         }
         Ok(suggest_delay_required)
@@ -865,6 +1273,36 @@ impl SessionData {
     }
 }
 
+/// The run-control groups of the configured cores.
+///
+/// A core can only be part of one group, so the groups of cores which list each other, or a
+/// common core, are merged into one.
+fn run_control_groups(core_configs: &[configuration::CoreConfig]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for core_config in core_configs {
+        if core_config.run_control_group.is_empty() {
+            continue;
+        }
+
+        let mut group = vec![core_config.core_index];
+        group.extend(&core_config.run_control_group);
+        // Merge the groups that share a core with this one.
+        groups.retain(|other| {
+            if other.iter().any(|core| group.contains(core)) {
+                group.extend(other);
+                false
+            } else {
+                true
+            }
+        });
+        group.sort_unstable();
+        group.dedup();
+        groups.push(group);
+    }
+
+    groups
+}
+
 /// Apply the session config's requested working directory if one was
 /// supplied. Shared between the local and RPC attach paths.
 ///
@@ -908,16 +1346,22 @@ fn build_core_data(
             test_data = Box::new(elf_info);
         }
     }
+    if core_configuration.mtb_buffer.is_some() {
+        repl_commands.push(BRANCH_TRACE);
+    }
 
     Ok(CoreData {
         core_index: core_configuration.core_index,
         last_known_status: CoreStatus::Unknown,
         target_name: format!("{}-{}", core_configuration.core_index, target_name),
         stack_frames: vec![],
+        rtos_threads: vec![],
         breakpoints: vec![],
+        exception_report: None,
         rtt_scan_ranges: WireScanRegion::Ranges(vec![]),
         rtt_connection: None,
         rtt_remote_handle: None,
+        mtb_buffer: core_configuration.mtb_buffer.clone(),
         repl_commands,
         test_data,
     })
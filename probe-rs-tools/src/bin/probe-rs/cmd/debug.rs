@@ -301,6 +301,8 @@ impl Cmd {
                         catch_reset: !self.no_catch_reset,
                         catch_svc: !self.no_catch_svc,
                         catch_hlt: !self.no_catch_hlt,
+                        run_control_group: vec![],
+                        mtb_buffer: None,
                     }],
                 })
                 .ok(),
@@ -5,6 +5,7 @@ use probe_rs_rpc_client::RpcClient;
 use crate::util::cli;
 use crate::util::common_options::BinaryDownloadOptions;
 use crate::util::common_options::ProbeOptions;
+use crate::util::common_options::StackPaintOptions;
 use probe_rs_rpc::format::FormatOptions;
 
 #[derive(clap::Parser)]
@@ -26,10 +27,20 @@ pub struct Cmd {
     /// The target gets a reset only if the firmware needs one to start.
     #[clap(long, help_heading = "DOWNLOAD CONFIGURATION")]
     pub start: bool,
+
+    #[clap(flatten)]
+    pub stack_paint_options: StackPaintOptions,
 }
 
 impl Cmd {
     pub async fn run(self, client: RpcClient) -> anyhow::Result<()> {
+        if self.stack_paint_options.paint_stack && !self.start {
+            anyhow::bail!(
+                "The stack can only be painted when the firmware is started with `--start`"
+            );
+        }
+        let paint_stack = self.stack_paint_options.region(&self.path)?;
+
         let session = cli::attach_probe(&client, self.probe_options, None, false).await?;
 
         let boot_info = cli::flash(
@@ -43,7 +54,14 @@ impl Cmd {
         .await?;
 
         if self.start {
-            session.boot(boot_info, 0).await?;
+            match paint_stack {
+                Some(region) => {
+                    session
+                        .boot_with_painted_stack(boot_info, 0, region)
+                        .await?
+                }
+                None => session.boot(boot_info, 0).await?,
+            }
         }
 
         Ok(())
@@ -43,9 +43,17 @@ pub struct Cmd {
     #[clap(long, help = "Spawn gdb after starting the gdbserver.")]
     gdb: Option<String>,
 
+    /// Halt and resume all cores of a GDB instance at the same moment.
+    ///
+    /// The cores are wired together with the cross triggers of the target, so a core hitting a
+    /// breakpoint stops the others immediately, instead of when the debugger gets to halt them.
+    #[clap(long)]
+    run_control_groups: bool,
+
     /// The path to the ELF file to debug.
     ///
-    /// This only needs to be specified when using `--gdb`.
+    /// This is passed to gdb when using `--gdb`, and is needed for `monitor break <function>`
+    /// and to list the tasks of the RTOS as threads.
     #[clap(index = 1)]
     path: Option<PathBuf>,
 
@@ -74,6 +82,12 @@ impl Cmd {
                 .await?;
         }
 
+        if let Some(path) = &self.path
+            && let Err(error) = session.load_debug_info(path.clone()).await
+        {
+            tracing::warn!("Could not load debug info from {}: {error}", path.display());
+        }
+
         let gdb_connection_string = self
             .gdb_connection_string
             .unwrap_or_else(|| "localhost:1337".to_string());
@@ -87,6 +101,17 @@ impl Cmd {
                 "Firing up GDB stub for {:?} cores at {:?}",
                 instance.core_type, instance.socket_addrs
             );
+
+            if self.run_control_groups && instance.cores.len() > 1 {
+                let cores = instance.cores.iter().map(|core| *core as u32).collect();
+                match session.create_run_control_group(cores).await {
+                    Ok(_) => println!("Cores {:?} halt and resume together", instance.cores),
+                    Err(error) => tracing::warn!(
+                        "Could not create a run-control group of cores {:?}: {error}",
+                        instance.cores
+                    ),
+                }
+            }
         }
 
         let gdb = if let Some(gdb) = self.gdb {
@@ -0,0 +1,147 @@
+use super::{GdbErrorExt, RuntimeTarget};
+
+use gdbstub::{
+    arch::Arch,
+    target::ext::breakpoints::{
+        Breakpoints, HwBreakpoint, HwBreakpointOps, HwWatchpoint, HwWatchpointOps, SwBreakpoint,
+        SwBreakpointOps, WatchKind,
+    },
+};
+use probe_rs_rpc::core_ops::{WireWatchpoint, WireWatchpointKind};
+
+impl Breakpoints for RuntimeTarget {
+    fn support_sw_breakpoint(&mut self) -> Option<SwBreakpointOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_hw_breakpoint(&mut self) -> Option<HwBreakpointOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_hw_watchpoint(&mut self) -> Option<HwWatchpointOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SwBreakpoint for RuntimeTarget {
+    fn add_sw_breakpoint(
+        &mut self,
+        addr: u64,
+        kind: <Self::Arch as Arch>::BreakpointKind,
+    ) -> gdbstub::target::TargetResult<bool, Self> {
+        // The break instruction is patched into memory once, and shared by the cores that run
+        // from it. Cores with their own memory at `addr` get their own patch.
+        let mut patched = true;
+        for core_info in &self.cores {
+            let core = self.session.core(core_info.index);
+            let results = self
+                .block_on(core.set_sw_breakpoints(vec![addr]))
+                .into_target_result()?;
+            patched &= results.iter().all(|result| result.is_ok());
+        }
+
+        if patched {
+            return Ok(true);
+        }
+
+        // Code that is not in RAM (e.g. in flash) can't be patched, use a hardware unit instead.
+        tracing::debug!("Using a hardware breakpoint for {addr:#010x}");
+        self.add_hw_breakpoint(addr, kind)
+    }
+
+    fn remove_sw_breakpoint(
+        &mut self,
+        addr: u64,
+        kind: <Self::Arch as Arch>::BreakpointKind,
+    ) -> gdbstub::target::TargetResult<bool, Self> {
+        for core_info in &self.cores {
+            let core = self.session.core(core_info.index);
+            self.block_on(core.clear_sw_breakpoints(vec![addr]))
+                .into_target_result()?;
+        }
+
+        // Also clear the hardware breakpoint this may have fallen back to.
+        self.remove_hw_breakpoint(addr, kind)
+    }
+}
+
+impl HwBreakpoint for RuntimeTarget {
+    fn add_hw_breakpoint(
+        &mut self,
+        addr: u64,
+        _kind: <Self::Arch as Arch>::BreakpointKind,
+    ) -> gdbstub::target::TargetResult<bool, Self> {
+        for core_info in &self.cores {
+            let core = self.session.core(core_info.index);
+            self.block_on(core.set_hw_breakpoint(addr))
+                .into_target_result()?;
+        }
+
+        Ok(true)
+    }
+
+    fn remove_hw_breakpoint(
+        &mut self,
+        addr: u64,
+        _kind: <Self::Arch as Arch>::BreakpointKind,
+    ) -> gdbstub::target::TargetResult<bool, Self> {
+        for core_info in &self.cores {
+            let core = self.session.core(core_info.index);
+            self.block_on(core.clear_hw_breakpoints(vec![addr]))
+                .into_target_result()?;
+        }
+
+        Ok(true)
+    }
+}
+
+impl HwWatchpoint for RuntimeTarget {
+    fn add_hw_watchpoint(
+        &mut self,
+        addr: u64,
+        len: u64,
+        kind: WatchKind,
+    ) -> gdbstub::target::TargetResult<bool, Self> {
+        let watchpoint = covering_watchpoint(addr, len, kind);
+        for core_info in &self.cores {
+            let core = self.session.core(core_info.index);
+            self.block_on(core.set_hw_watchpoint(watchpoint))
+                .into_target_result()?;
+        }
+
+        Ok(true)
+    }
+
+    fn remove_hw_watchpoint(
+        &mut self,
+        addr: u64,
+        len: u64,
+        kind: WatchKind,
+    ) -> gdbstub::target::TargetResult<bool, Self> {
+        let watchpoint = covering_watchpoint(addr, len, kind);
+        for core_info in &self.cores {
+            let core = self.session.core(core_info.index);
+            self.block_on(core.clear_hw_watchpoint(watchpoint))
+                .into_target_result()?;
+        }
+
+        Ok(true)
+    }
+}
+
+fn covering_watchpoint(addr: u64, len: u64, kind: WatchKind) -> WireWatchpoint {
+    let kind = match kind {
+        WatchKind::Read => WireWatchpointKind::Read,
+        WatchKind::Write => WireWatchpointKind::Write,
+        WatchKind::ReadWrite => WireWatchpointKind::Access,
+    };
+    WireWatchpoint::covering(addr, len, kind)
+}
+
+pub(super) fn to_watch_kind(kind: WireWatchpointKind) -> WatchKind {
+    match kind {
+        WireWatchpointKind::Read => WatchKind::Read,
+        WireWatchpointKind::Write => WatchKind::Write,
+        WireWatchpointKind::Access => WatchKind::ReadWrite,
+    }
+}
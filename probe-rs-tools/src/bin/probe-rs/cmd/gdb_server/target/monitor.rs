@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use super::RuntimeTarget;
+
+use gdbstub::target::ext::monitor_cmd::ConsoleOutput;
+use gdbstub::target::ext::monitor_cmd::MonitorCmd;
+use gdbstub::target::ext::monitor_cmd::outputln;
+use probe_rs_rpc::breakpoints::BreakpointTarget;
+
+const HELP_TEXT: &str = r#"Supported Commands:
+
+    info - print session information
+    reset - reset target
+    reset halt - reset target and halt afterwards
+    break <function> - set a hardware breakpoint on every instance of a function
+    delete <function> - remove the breakpoints set with `break <function>`
+"#;
+
+impl MonitorCmd for RuntimeTarget {
+    fn handle_monitor_cmd(
+        &mut self,
+        cmd: &[u8],
+        mut out: ConsoleOutput<'_>,
+    ) -> Result<(), Self::Error> {
+        match cmd {
+            b"info" => {
+                outputln!(out, "Target: {}", self.target_name);
+                for core in &self.cores {
+                    outputln!(
+                        out,
+                        "  core {}: {} ({:?})",
+                        core.index,
+                        core.name,
+                        core.core_type
+                    );
+                }
+            }
+            b"reset" => {
+                outputln!(out, "Resetting target");
+                match self.block_on(self.session.core(0).reset()) {
+                    Ok(_) => outputln!(out, "Done"),
+                    Err(e) => outputln!(out, "Error while resetting target:\n\t{}", e),
+                }
+            }
+            b"reset halt" => {
+                let timeout = Duration::from_secs(1);
+                outputln!(out, "Resetting and halting target");
+                match self.block_on(self.session.core(0).reset_and_halt(timeout)) {
+                    Ok(_) => outputln!(out, "Target halted"),
+                    Err(e) => outputln!(out, "Error while halting target:\n\t{}", e),
+                }
+            }
+            _ if cmd.starts_with(b"break ") => {
+                let function = String::from_utf8_lossy(&cmd[b"break ".len()..]);
+                let function = function.trim();
+                match self.function_breakpoint_addresses(function) {
+                    Ok(addresses) => {
+                        for core_info in &self.cores {
+                            let core = self.session.core(core_info.index);
+                            for &address in &addresses {
+                                if let Err(e) = self.block_on(core.set_hw_breakpoint(address)) {
+                                    outputln!(out, "Error while setting breakpoint:\n\t{}", e);
+                                    return Ok(());
+                                }
+                            }
+                        }
+                        for address in addresses {
+                            outputln!(out, "Breakpoint on {} at {:#010x}", function, address);
+                        }
+                    }
+                    Err(e) => outputln!(out, "Cannot set breakpoint on {}:\n\t{}", function, e),
+                }
+            }
+            _ if cmd.starts_with(b"delete ") => {
+                let function = String::from_utf8_lossy(&cmd[b"delete ".len()..]);
+                let function = function.trim();
+                match self.function_breakpoint_addresses(function) {
+                    Ok(addresses) => {
+                        for core_info in &self.cores {
+                            let core = self.session.core(core_info.index);
+                            if let Err(e) =
+                                self.block_on(core.clear_hw_breakpoints(addresses.clone()))
+                            {
+                                outputln!(out, "Error while removing breakpoint:\n\t{}", e);
+                                return Ok(());
+                            }
+                        }
+                        outputln!(
+                            out,
+                            "Removed {} breakpoint(s) on {}",
+                            addresses.len(),
+                            function
+                        );
+                    }
+                    Err(e) => {
+                        outputln!(out, "Cannot remove breakpoint on {}:\n\t{}", function, e)
+                    }
+                }
+            }
+            _ => outputln!(out, "{}", HELP_TEXT),
+        }
+
+        Ok(())
+    }
+}
+
+impl RuntimeTarget {
+    /// Resolves every instance of `function` with the debug info loaded on the server.
+    fn function_breakpoint_addresses(&self, function: &str) -> anyhow::Result<Vec<u64>> {
+        let resolution =
+            self.block_on(self.session.resolve_source_breakpoints(vec![
+                BreakpointTarget::Function(function.to_string()),
+            ]))?
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("Server returned no breakpoint resolution."))?;
+        if let Some(error) = resolution.error {
+            anyhow::bail!(error);
+        }
+
+        Ok(resolution
+            .breakpoints
+            .iter()
+            .map(|breakpoint| breakpoint.address)
+            .collect())
+    }
+}
@@ -0,0 +1,114 @@
+use crate::cmd::gdb_server::target::utils::copy_to_buf;
+use crate::rpc::functions::core_ops::convert::from_wire_register_value;
+
+use super::RuntimeTarget;
+
+use gdbstub::common::Tid;
+use gdbstub::target::ext::thread_extra_info::ThreadExtraInfo;
+use probe_rs::RegisterValue;
+use probe_rs_rpc::rtos::{WireRtosThread, WireThreadState};
+
+/// A thread of the RTOS running on a core.
+///
+/// The running thread is the thread of its core. The others are listed as threads of their own,
+/// with the ID the RTOS gives them as thread ID, and their registers read from their stacks.
+pub(crate) struct RtosThread {
+    core_index: usize,
+    thread: WireRtosThread,
+}
+
+impl RtosThread {
+    fn tid(&self) -> Option<Tid> {
+        Tid::new(self.thread.id as usize)
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "{} ({:?}, priority {})",
+            self.thread.name, self.thread.state, self.thread.priority
+        )
+    }
+
+    /// The value of a register, saved when the thread was switched out.
+    pub(crate) fn register(&self, id: u16) -> Option<RegisterValue> {
+        self.thread
+            .registers
+            .iter()
+            .find(|register| register.id.0 == id)
+            .and_then(|register| register.value)
+            .map(from_wire_register_value)
+    }
+}
+
+impl RuntimeTarget {
+    /// Reads the threads of the RTOS from the halted cores again.
+    pub(crate) fn refresh_rtos_threads(&mut self) {
+        let mut threads = Vec::new();
+        for core in &self.cores {
+            match self.block_on(self.core(core.index).rtos_threads()) {
+                Ok(Some(rtos)) => {
+                    threads.extend(rtos.threads.into_iter().map(|thread| RtosThread {
+                        core_index: core.index,
+                        thread,
+                    }))
+                }
+                Ok(None) => {}
+                Err(error) => {
+                    tracing::warn!(
+                        "Failed to read the RTOS threads of core {}: {error}",
+                        core.index
+                    )
+                }
+            }
+        }
+        self.rtos_threads = threads;
+    }
+
+    /// The thread IDs of the RTOS threads that do not run.
+    pub(crate) fn rtos_thread_ids(&self) -> impl Iterator<Item = Tid> + '_ {
+        self.rtos_threads
+            .iter()
+            .filter(|thread| thread.thread.state != WireThreadState::Running)
+            .filter_map(RtosThread::tid)
+            .filter(|tid| self.cores.iter().all(|core| core.index + 1 != tid.get()))
+    }
+
+    /// The RTOS thread with the ID `tid`, if it is one that does not run.
+    pub(crate) fn rtos_thread(&self, tid: Tid) -> Option<&RtosThread> {
+        if self.cores.iter().any(|core| core.index + 1 == tid.get()) {
+            return None;
+        }
+        self.rtos_threads
+            .iter()
+            .find(|thread| thread.tid() == Some(tid))
+    }
+
+    /// The index of the core a thread runs on.
+    pub(crate) fn thread_core(&self, tid: Tid) -> usize {
+        match self.rtos_thread(tid) {
+            Some(thread) => thread.core_index,
+            None => tid.get() - 1,
+        }
+    }
+}
+
+impl ThreadExtraInfo for RuntimeTarget {
+    fn thread_extra_info(&self, tid: Tid, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if let Some(thread) = self.rtos_thread(tid) {
+            return Ok(copy_to_buf(thread.description().as_bytes(), buf));
+        }
+
+        let Some(core) = self.cores.iter().find(|c| c.index + 1 == tid.get()) else {
+            return Ok(copy_to_buf(b"unknown", buf));
+        };
+        let running = self.rtos_threads.iter().find(|thread| {
+            thread.core_index == core.index && thread.thread.state == WireThreadState::Running
+        });
+        let info = match running {
+            Some(thread) => format!("{}: {}", core.name, thread.description()),
+            None => core.name.clone(),
+        };
+
+        Ok(copy_to_buf(info.as_bytes(), buf))
+    }
+}
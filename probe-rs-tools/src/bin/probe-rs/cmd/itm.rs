@@ -1,13 +1,22 @@
 //! Provides ITM tracing capabilities.
 
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
+use anyhow::Context;
 use probe_rs::architecture::arm::{component::TraceSink, swo::SwoConfig};
 use probe_rs::config::Registry;
 use probe_rs::probe::list::Lister;
 
 use crate::CoreOptions;
 use crate::util::common_options::ProbeOptions;
+use crate::util::itm::{ItmDemux, ItmPortConfig};
+use crate::util::rtt::DefmtState;
+
+use timeline::{TargetSymbols, Timeline, TimelineFormat};
+
+mod timeline;
+mod watch;
 
 #[derive(clap::Subcommand)]
 pub(crate) enum ItmSource {
@@ -54,6 +63,14 @@ pub(crate) enum ItmSource {
         /// The desired baud rate of the SWO output.
         baud: u32,
     },
+
+    /// Record the values written to up to four variables with the DWT data trace, over SWO.
+    ///
+    /// Each variable is watched by a DWT comparator, which makes the ITM emit the value and the
+    /// program counter of every access to it, without halting the core. The values are written
+    /// with their timestamps as a VCD waveform, CSV or JSON lines.
+    #[clap(name = "watch")]
+    Watch(watch::WatchCmd),
 }
 
 #[derive(clap::Parser)]
@@ -64,12 +81,66 @@ pub struct Cmd {
     #[clap(flatten)]
     common: ProbeOptions,
 
+    /// Write the exception, data and stimulus port trace to this file as a timeline, instead of
+    /// printing the packets. The timeline can be viewed with <https://ui.perfetto.dev>.
+    #[clap(long)]
+    timeline: Option<PathBuf>,
+
+    /// The file format of the timeline.
+    #[clap(long, value_enum, default_value_t = TimelineFormat::Chrome)]
+    timeline_format: TimelineFormat,
+
+    /// Decode the data the target writes to a stimulus port instead of printing the packets,
+    /// given as `<PORT>=<FORMAT>[,<DESTINATION>]`. The format is `string`, `defmt` or `binary`,
+    /// and the destination is `stdout` (the default), `file:<PATH>` or `tcp:<ADDRESS>`, for
+    /// example `--port 0=string --port 1=defmt,file:out/defmt.txt`.
+    #[clap(long)]
+    port: Vec<ItmPortConfig>,
+
+    /// The ELF file running on the target, used to name the exception handlers in the timeline,
+    /// to decode defmt data and to look up watched variables.
+    #[clap(long)]
+    elf: Option<PathBuf>,
+
     #[clap(subcommand)]
     source: ItmSource,
 }
 
 impl Cmd {
     pub fn run(self, registry: &mut Registry, lister: &Lister) -> anyhow::Result<()> {
+        let elf = match &self.elf {
+            Some(path) => Some(
+                std::fs::read(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?,
+            ),
+            None => None,
+        };
+
+        if let ItmSource::Watch(watch) = self.source {
+            if self.timeline.is_some() || !self.port.is_empty() {
+                anyhow::bail!("`--timeline` and `--port` cannot be used to watch variables");
+            }
+            let variables = watch.resolve(elf.as_deref())?;
+            let (mut session, _probe_options) = self.common.simple_attach(registry, lister)?;
+            return watch.run(&mut session, self.shared.core, variables);
+        }
+
+        let mut timeline = match (&self.timeline, &elf) {
+            (None, _) => None,
+            (Some(_), None) => Some(Timeline::new(TargetSymbols::default())),
+            (Some(_), Some(elf)) => Some(Timeline::new(TargetSymbols::from_elf(elf)?)),
+        };
+
+        let mut demux = if self.port.is_empty() {
+            None
+        } else {
+            let defmt_data = match &elf {
+                Some(elf) => DefmtState::try_from_bytes(elf)?,
+                None => None,
+            };
+            Some(ItmDemux::new(&self.port, defmt_data.as_ref(), None)?)
+        };
+
         let (mut session, _probe_options) = self.common.simple_attach(registry, lister)?;
 
         match self.source {
@@ -85,8 +156,19 @@ impl Cmd {
                     lts_prescaler: itm::LocalTimestampOptions::Enabled,
                     expect_malformed: false,
                 };
-                for packet in decoder.timestamps(timestamp_cfg) {
-                    println!("{packet:?}");
+                for packets in decoder.timestamps(timestamp_cfg) {
+                    let packets = packets?;
+                    if let Some(timeline) = &mut timeline {
+                        timeline.add(&packets);
+                    }
+                    if let Some(demux) = &mut demux {
+                        for packet in &packets.packets {
+                            demux.process(packet)?;
+                        }
+                    }
+                    if timeline.is_none() && demux.is_none() {
+                        println!("{packets:?}");
+                    }
                 }
             }
 
@@ -107,14 +189,48 @@ impl Cmd {
 
                 let start = Instant::now();
                 let stop = Duration::from_millis(duration);
-                for packet in decoder.singles() {
-                    println!("{packet:?}");
-                    if start.elapsed() > stop {
-                        return Ok(());
+                if let Some(timeline) = &mut timeline {
+                    let timestamp_cfg = itm::TimestampsConfiguration {
+                        clock_frequency: clk,
+                        lts_prescaler: itm::LocalTimestampOptions::Enabled,
+                        expect_malformed: true,
+                    };
+                    for packets in decoder.timestamps(timestamp_cfg) {
+                        let packets = packets?;
+                        timeline.add(&packets);
+                        if let Some(demux) = &mut demux {
+                            for packet in &packets.packets {
+                                demux.process(packet)?;
+                            }
+                        }
+                        if start.elapsed() > stop {
+                            break;
+                        }
+                    }
+                } else {
+                    for packet in decoder.singles() {
+                        match &mut demux {
+                            Some(demux) => demux.process(&packet?)?,
+                            None => println!("{packet:?}"),
+                        }
+                        if start.elapsed() > stop {
+                            break;
+                        }
                     }
                 }
             }
+            ItmSource::Watch(_) => unreachable!("watching variables is handled above"),
         };
+
+        if let (Some(timeline), Some(path)) = (timeline, &self.timeline) {
+            let mut file = std::io::BufWriter::new(
+                std::fs::File::create(path)
+                    .with_context(|| format!("Failed to create {}", path.display()))?,
+            );
+            timeline.write(self.timeline_format, &mut file)?;
+            std::io::Write::flush(&mut file)?;
+        }
+
         Ok(())
     }
 }
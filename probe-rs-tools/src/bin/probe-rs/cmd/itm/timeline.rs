@@ -0,0 +1,600 @@
+//! Turns the ITM and DWT trace into a timeline for trace viewers like <https://ui.perfetto.dev>.
+
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::io::Write;
+use std::time::Duration;
+
+use anyhow::Context;
+use itm::cortex_m::VectActive;
+use itm::{ExceptionAction, Timestamp, TimestampedTracePackets, TracePacket};
+use object::{Object, ObjectSection, ObjectSymbol, SymbolKind};
+
+use crate::util::protobuf::{bytes_field, message, varint_field};
+
+/// The file formats a timeline can be written in.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TimelineFormat {
+    /// Chrome trace event JSON.
+    Chrome,
+    /// Perfetto protobuf trace.
+    Perfetto,
+}
+
+/// Names for the addresses of the program running on the target.
+#[derive(Default)]
+pub(crate) struct TargetSymbols {
+    /// The handler names, indexed by exception number.
+    handlers: HashMap<u16, String>,
+    /// Functions, sorted by address.
+    functions: Vec<(u64, u64, String)>,
+}
+
+/// The sections that the vector table is placed in by common runtimes and vendor startup code.
+const VECTOR_TABLE_SECTIONS: &[&str] = &[".vector_table", ".isr_vector", ".vectors"];
+
+/// The symbols that mark the vector table in common startup code.
+const VECTOR_TABLE_SYMBOLS: &[&str] = &["__isr_vector", "g_pfnVectors", "__Vectors"];
+
+impl TargetSymbols {
+    /// Reads the function symbols and the vector table of an ELF file.
+    pub(crate) fn from_elf(elf: &[u8]) -> anyhow::Result<Self> {
+        let file = object::File::parse(elf).context("Failed to parse the ELF file")?;
+
+        let mut functions = file
+            .symbols()
+            .filter(|symbol| symbol.kind() == SymbolKind::Text && symbol.size() > 0)
+            .filter_map(|symbol| {
+                let name = symbol.name().ok()?;
+                let name = addr2line::demangle_auto(Cow::Borrowed(name), None).into_owned();
+                // The address of Thumb functions has the lowest bit set.
+                Some((symbol.address() & !1, symbol.size(), name))
+            })
+            .collect::<Vec<_>>();
+        functions.sort();
+
+        let mut symbols = Self {
+            handlers: HashMap::new(),
+            functions,
+        };
+
+        let vector_table = VECTOR_TABLE_SECTIONS
+            .iter()
+            .find_map(|name| file.section_by_name(name))
+            .and_then(|section| section.data().ok())
+            .or_else(|| {
+                let symbol = file.symbols().find(|symbol| {
+                    symbol
+                        .name()
+                        .is_ok_and(|name| VECTOR_TABLE_SYMBOLS.contains(&name))
+                })?;
+                let section = file.section_by_index(symbol.section_index()?).ok()?;
+                let data = section.data().ok()?;
+                data.get((symbol.address() - section.address()) as usize..)
+            });
+
+        if let Some(vector_table) = vector_table {
+            // The first entry is the initial stack pointer, the handlers follow.
+            for (number, entry) in vector_table.chunks_exact(4).enumerate().skip(1) {
+                let handler = u32::from_le_bytes(entry.try_into().unwrap());
+                if handler == 0 || number >= 16 + 512 {
+                    continue;
+                }
+                if let Some(name) = symbols.function_name(u64::from(handler)) {
+                    symbols.handlers.insert(number as u16, name.to_string());
+                }
+            }
+        }
+
+        Ok(symbols)
+    }
+
+    /// Returns the name of the function that contains `address`.
+    pub(crate) fn function_name(&self, address: u64) -> Option<&str> {
+        let address = address & !1;
+        let index = self
+            .functions
+            .partition_point(|(start, _, _)| *start <= address);
+        let (start, size, name) = self.functions.get(index.checked_sub(1)?)?;
+        (address < start + size).then_some(name.as_str())
+    }
+
+    /// Returns the name of the handler of an exception.
+    fn exception_name(&self, exception: &VectActive) -> String {
+        let number = match exception {
+            VectActive::ThreadMode => return "Thread mode".to_string(),
+            VectActive::Exception(exception) => (16 + i16::from(exception.irqn())) as u16,
+            VectActive::Interrupt { irqn } => 16 + irqn,
+        };
+
+        match (self.handlers.get(&number), exception) {
+            (Some(name), _) => name.clone(),
+            (None, VectActive::Exception(exception)) => format!("{exception:?}"),
+            (None, _) => format!("IRQ {}", number - 16),
+        }
+    }
+}
+
+/// A track of the timeline, shown as one row by trace viewers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Track {
+    /// Exception handlers, as nested slices.
+    Exceptions,
+    /// Trace events like overflows.
+    Trace,
+    /// Text written to a stimulus port, one event per line.
+    PortText(u8),
+    /// Binary values written to a stimulus port.
+    PortValue(u8),
+    /// Matches of a DWT comparator.
+    Comparator(u8),
+    /// Data values traced by a DWT comparator.
+    ComparatorValue(u8),
+}
+
+impl Track {
+    fn name(&self) -> String {
+        match self {
+            Track::Exceptions => "Exceptions".to_string(),
+            Track::Trace => "Trace".to_string(),
+            Track::PortText(port) => format!("ITM port {port}"),
+            Track::PortValue(port) => format!("ITM port {port} value"),
+            Track::Comparator(comparator) => format!("DWT comparator {comparator}"),
+            Track::ComparatorValue(comparator) => format!("DWT comparator {comparator} value"),
+        }
+    }
+
+    fn is_counter(&self) -> bool {
+        matches!(self, Track::PortValue(_) | Track::ComparatorValue(_))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum EventKind {
+    Begin(String),
+    End,
+    Instant(String),
+    Counter(u64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Event {
+    /// Nanoseconds since the start of the trace.
+    timestamp: u64,
+    track: Track,
+    kind: EventKind,
+}
+
+/// Collects the ITM and DWT packets of a trace as timeline events.
+pub(crate) struct Timeline {
+    symbols: TargetSymbols,
+    events: Vec<Event>,
+    /// Text written to a stimulus port that is not terminated by a newline yet, and when the
+    /// first character of it was written.
+    pending_text: HashMap<u8, (u64, String)>,
+}
+
+impl Timeline {
+    pub(crate) fn new(symbols: TargetSymbols) -> Self {
+        Self {
+            symbols,
+            events: Vec::new(),
+            pending_text: HashMap::new(),
+        }
+    }
+
+    /// Adds the packets that were generated at the same time.
+    pub(crate) fn add(&mut self, packets: &TimestampedTracePackets) {
+        // If the exact time is not known, the packets were generated before the current timestamp.
+        let timestamp = match packets.timestamp {
+            Timestamp::Sync(time) | Timestamp::AssocEventDelay(time) => time,
+            Timestamp::UnknownDelay { curr, .. }
+            | Timestamp::UnknownAssocEventDelay { curr, .. } => curr,
+        };
+
+        for packet in &packets.packets {
+            self.add_packet(timestamp, packet);
+        }
+    }
+
+    fn add_packet(&mut self, timestamp: Duration, packet: &TracePacket) {
+        let timestamp = timestamp.as_nanos() as u64;
+
+        let (track, kind) = match packet {
+            TracePacket::ExceptionTrace { exception, action } => {
+                let kind = match action {
+                    ExceptionAction::Entered => {
+                        EventKind::Begin(self.symbols.exception_name(exception))
+                    }
+                    ExceptionAction::Exited => EventKind::End,
+                    ExceptionAction::Returned => return,
+                };
+                (Track::Exceptions, kind)
+            }
+            TracePacket::Instrumentation { port, payload } => {
+                if let Ok(text) = std::str::from_utf8(payload)
+                    && text
+                        .chars()
+                        .all(|c| !c.is_control() || c.is_ascii_whitespace())
+                {
+                    self.add_text(timestamp, *port, text);
+                    return;
+                }
+                (
+                    Track::PortValue(*port),
+                    EventKind::Counter(le_value(payload)),
+                )
+            }
+            TracePacket::DataTraceValue {
+                comparator, value, ..
+            } => (
+                Track::ComparatorValue(*comparator),
+                EventKind::Counter(le_value(value)),
+            ),
+            TracePacket::DataTracePC { comparator, pc } => {
+                let name = match self.symbols.function_name(u64::from(*pc)) {
+                    Some(function) => format!("PC {pc:#010x} in {function}"),
+                    None => format!("PC {pc:#010x}"),
+                };
+                (Track::Comparator(*comparator), EventKind::Instant(name))
+            }
+            TracePacket::DataTraceAddress { comparator, data } => (
+                Track::Comparator(*comparator),
+                EventKind::Instant(format!("Address {:#06x}", le_value(data))),
+            ),
+            TracePacket::Overflow => (Track::Trace, EventKind::Instant("Overflow".to_string())),
+            _ => return,
+        };
+
+        self.events.push(Event {
+            timestamp,
+            track,
+            kind,
+        });
+    }
+
+    fn add_text(&mut self, timestamp: u64, port: u8, text: &str) {
+        for c in text.chars() {
+            let (start, line) = self
+                .pending_text
+                .entry(port)
+                .or_insert_with(|| (timestamp, String::new()));
+            if c != '\n' {
+                line.push(c);
+                continue;
+            }
+
+            let (start, line) = (*start, std::mem::take(line));
+            self.pending_text.remove(&port);
+            self.push_line(start, port, line);
+        }
+    }
+
+    fn push_line(&mut self, timestamp: u64, port: u8, line: String) {
+        let line = line.trim_end();
+        if !line.is_empty() {
+            self.events.push(Event {
+                timestamp,
+                track: Track::PortText(port),
+                kind: EventKind::Instant(line.to_string()),
+            });
+        }
+    }
+
+    /// Adds the text that was not terminated by a newline.
+    fn finish(&mut self) {
+        let mut pending = self.pending_text.drain().collect::<Vec<_>>();
+        pending.sort_by_key(|(port, _)| *port);
+        for (port, (timestamp, line)) in pending {
+            self.push_line(timestamp, port, line);
+        }
+        self.events.sort_by_key(|event| event.timestamp);
+    }
+
+    fn tracks(&self) -> Vec<Track> {
+        self.events
+            .iter()
+            .map(|event| event.track)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    pub(crate) fn write(
+        mut self,
+        format: TimelineFormat,
+        writer: &mut impl Write,
+    ) -> anyhow::Result<()> {
+        self.finish();
+        match format {
+            TimelineFormat::Chrome => self.write_chrome(writer),
+            TimelineFormat::Perfetto => self.write_perfetto(writer),
+        }
+    }
+
+    /// Writes the timeline as Chrome trace event JSON, with one thread per track.
+    fn write_chrome(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        let tracks = self.tracks();
+        let tid = |track: Track| tracks.iter().position(|t| *t == track).unwrap() + 1;
+
+        let mut events = vec![serde_json::json!({
+            "ph": "M", "name": "process_name", "pid": 1, "args": { "name": PROCESS_NAME },
+        })];
+        for track in tracks.iter().filter(|track| !track.is_counter()) {
+            events.push(serde_json::json!({
+                "ph": "M", "name": "thread_name", "pid": 1, "tid": tid(*track),
+                "args": { "name": track.name() },
+            }));
+        }
+
+        for event in &self.events {
+            let ts = event.timestamp as f64 / 1000.0;
+            let tid = tid(event.track);
+            events.push(match &event.kind {
+                EventKind::Begin(name) => {
+                    serde_json::json!({ "ph": "B", "name": name, "pid": 1, "tid": tid, "ts": ts })
+                }
+                EventKind::End => serde_json::json!({ "ph": "E", "pid": 1, "tid": tid, "ts": ts }),
+                EventKind::Instant(name) => serde_json::json!({
+                    "ph": "i", "s": "t", "name": name, "pid": 1, "tid": tid, "ts": ts,
+                }),
+                EventKind::Counter(value) => serde_json::json!({
+                    "ph": "C", "name": event.track.name(), "pid": 1, "ts": ts,
+                    "args": { "value": value },
+                }),
+            });
+        }
+
+        serde_json::to_writer(
+            &mut *writer,
+            &serde_json::json!({ "traceEvents": events, "displayTimeUnit": "ns" }),
+        )?;
+        Ok(())
+    }
+
+    /// Writes the timeline as a Perfetto `Trace` protobuf message, with one track per track.
+    fn write_perfetto(&self, writer: &mut impl Write) -> anyhow::Result<()> {
+        const PROCESS_UUID: u64 = 1;
+        let tracks = self.tracks();
+        let uuid = |track: Track| tracks.iter().position(|t| *t == track).unwrap() as u64 + 2;
+
+        let mut trace = Vec::new();
+
+        message(&mut trace, trace_field::PACKET, |packet| {
+            message(packet, packet_field::TRACK_DESCRIPTOR, |descriptor| {
+                varint_field(descriptor, track_descriptor_field::UUID, PROCESS_UUID);
+                message(descriptor, track_descriptor_field::PROCESS, |process| {
+                    varint_field(process, process_descriptor_field::PID, 1);
+                    bytes_field(
+                        process,
+                        process_descriptor_field::PROCESS_NAME,
+                        PROCESS_NAME.as_bytes(),
+                    );
+                });
+            });
+        });
+
+        for track in &tracks {
+            message(&mut trace, trace_field::PACKET, |packet| {
+                message(packet, packet_field::TRACK_DESCRIPTOR, |descriptor| {
+                    varint_field(descriptor, track_descriptor_field::UUID, uuid(*track));
+                    varint_field(
+                        descriptor,
+                        track_descriptor_field::PARENT_UUID,
+                        PROCESS_UUID,
+                    );
+                    bytes_field(
+                        descriptor,
+                        track_descriptor_field::NAME,
+                        track.name().as_bytes(),
+                    );
+                    if track.is_counter() {
+                        message(descriptor, track_descriptor_field::COUNTER, |_| {});
+                    }
+                });
+            });
+        }
+
+        for event in &self.events {
+            message(&mut trace, trace_field::PACKET, |packet| {
+                varint_field(packet, packet_field::TIMESTAMP, event.timestamp);
+                varint_field(packet, packet_field::TRUSTED_PACKET_SEQUENCE_ID, 1);
+                message(packet, packet_field::TRACK_EVENT, |track_event| {
+                    let (kind, name) = match &event.kind {
+                        EventKind::Begin(name) => (track_event_type::SLICE_BEGIN, Some(name)),
+                        EventKind::End => (track_event_type::SLICE_END, None),
+                        EventKind::Instant(name) => (track_event_type::INSTANT, Some(name)),
+                        EventKind::Counter(_) => (track_event_type::COUNTER, None),
+                    };
+                    varint_field(track_event, track_event_field::TYPE, kind);
+                    varint_field(
+                        track_event,
+                        track_event_field::TRACK_UUID,
+                        uuid(event.track),
+                    );
+                    if let Some(name) = name {
+                        bytes_field(track_event, track_event_field::NAME, name.as_bytes());
+                    }
+                    if let EventKind::Counter(value) = event.kind {
+                        varint_field(track_event, track_event_field::COUNTER_VALUE, value);
+                    }
+                });
+            });
+        }
+
+        writer.write_all(&trace)?;
+        Ok(())
+    }
+}
+
+const PROCESS_NAME: &str = "ITM trace";
+
+/// Interprets a little-endian payload of up to 8 bytes as an unsigned value.
+fn le_value(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .take(8)
+        .rev()
+        .fold(0, |value, byte| value << 8 | u64::from(*byte))
+}
+
+// Field numbers of the Perfetto trace protobuf messages, see
+// https://github.com/google/perfetto/tree/main/protos/perfetto/trace.
+
+mod trace_field {
+    pub const PACKET: u32 = 1;
+}
+
+mod packet_field {
+    pub const TIMESTAMP: u32 = 8;
+    pub const TRUSTED_PACKET_SEQUENCE_ID: u32 = 10;
+    pub const TRACK_EVENT: u32 = 11;
+    pub const TRACK_DESCRIPTOR: u32 = 60;
+}
+
+mod track_descriptor_field {
+    pub const UUID: u32 = 1;
+    pub const NAME: u32 = 2;
+    pub const PROCESS: u32 = 3;
+    pub const PARENT_UUID: u32 = 5;
+    pub const COUNTER: u32 = 8;
+}
+
+mod process_descriptor_field {
+    pub const PID: u32 = 1;
+    pub const PROCESS_NAME: u32 = 6;
+}
+
+mod track_event_field {
+    pub const TYPE: u32 = 9;
+    pub const TRACK_UUID: u32 = 11;
+    pub const NAME: u32 = 23;
+    pub const COUNTER_VALUE: u32 = 30;
+}
+
+mod track_event_type {
+    pub const SLICE_BEGIN: u64 = 1;
+    pub const SLICE_END: u64 = 2;
+    pub const INSTANT: u64 = 3;
+    pub const COUNTER: u64 = 4;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use itm::cortex_m::Exception;
+
+    fn timeline() -> Timeline {
+        let mut symbols = TargetSymbols::default();
+        symbols.handlers.insert(15, "SysTick".to_string());
+        symbols.handlers.insert(16 + 3, "TIM2".to_string());
+
+        let mut timeline = Timeline::new(symbols);
+        let mut add = |micros, packets| {
+            timeline.add(&TimestampedTracePackets {
+                timestamp: Timestamp::Sync(Duration::from_micros(micros)),
+                packets,
+                malformed_packets: vec![],
+                consumed_packets: 0,
+            })
+        };
+        add(
+            1,
+            vec![TracePacket::ExceptionTrace {
+                exception: VectActive::Interrupt { irqn: 3 },
+                action: ExceptionAction::Entered,
+            }],
+        );
+        add(
+            2,
+            vec![
+                TracePacket::Instrumentation {
+                    port: 0,
+                    payload: b"he".to_vec(),
+                },
+                TracePacket::DataTraceValue {
+                    comparator: 1,
+                    access_type: itm::MemoryAccessType::Write,
+                    value: vec![0x34, 0x12],
+                },
+            ],
+        );
+        add(
+            3,
+            vec![
+                TracePacket::Instrumentation {
+                    port: 0,
+                    payload: b"y\n".to_vec(),
+                },
+                TracePacket::ExceptionTrace {
+                    exception: VectActive::Interrupt { irqn: 3 },
+                    action: ExceptionAction::Exited,
+                },
+                TracePacket::ExceptionTrace {
+                    exception: VectActive::Exception(Exception::PendSV),
+                    action: ExceptionAction::Entered,
+                },
+            ],
+        );
+        timeline
+    }
+
+    #[test]
+    fn events() {
+        let mut timeline = timeline();
+        timeline.finish();
+
+        assert_eq!(
+            timeline.events,
+            [
+                Event {
+                    timestamp: 1000,
+                    track: Track::Exceptions,
+                    kind: EventKind::Begin("TIM2".to_string()),
+                },
+                Event {
+                    timestamp: 2000,
+                    track: Track::ComparatorValue(1),
+                    kind: EventKind::Counter(0x1234),
+                },
+                Event {
+                    timestamp: 2000,
+                    track: Track::PortText(0),
+                    kind: EventKind::Instant("hey".to_string()),
+                },
+                Event {
+                    timestamp: 3000,
+                    track: Track::Exceptions,
+                    kind: EventKind::End,
+                },
+                Event {
+                    timestamp: 3000,
+                    track: Track::Exceptions,
+                    kind: EventKind::Begin("PendSV".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn chrome() {
+        let mut output = Vec::new();
+        timeline()
+            .write(TimelineFormat::Chrome, &mut output)
+            .unwrap();
+        let trace: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        let events = trace["traceEvents"].as_array().unwrap();
+
+        assert!(events.contains(&serde_json::json!({
+            "ph": "B", "name": "TIM2", "pid": 1, "tid": 1, "ts": 1.0,
+        })));
+        assert!(events.contains(&serde_json::json!({
+            "ph": "C", "name": "DWT comparator 1 value", "pid": 1, "ts": 2.0,
+            "args": { "value": 0x1234 },
+        })));
+        assert!(events.contains(&serde_json::json!({
+            "ph": "M", "name": "thread_name", "pid": 1, "tid": 2,
+            "args": { "name": "ITM port 0" },
+        })));
+    }
+}
@@ -0,0 +1,287 @@
+//! Records the values written to variables with the DWT data trace.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use addr2line::gimli::RunTimeEndian;
+use anyhow::{Context, bail};
+use itm::{Timestamp, TracePacket};
+use probe_rs::Session;
+use probe_rs::architecture::arm::{component::TraceSink, swo::SwoConfig};
+use probe_rs_debug::DebugInfo;
+use probe_rs_debug::static_variable::{ScalarType, ScalarValue};
+
+use crate::util::signals::{Signal, SignalFormat, SignalWriter};
+use crate::util::{parse_duration_secs, parse_u64};
+
+/// The number of variables that can be watched, one per DWT comparator.
+const MAX_VARIABLES: usize = 4;
+
+#[derive(clap::Args)]
+pub(crate) struct WatchCmd {
+    /// The variables to watch, given by an address or by the path of a static variable like
+    /// `my_mod::STATE.counter`, which needs the ELF file in `--elf`. Variables given by an
+    /// address are read as 32-bit unsigned integers.
+    #[clap(required = true, num_args = 1..=MAX_VARIABLES)]
+    variables: Vec<String>,
+
+    /// The speed of the clock feeding the TPIU/SWO module in Hz.
+    #[clap(long)]
+    clk: u32,
+
+    /// The desired baud rate of the SWO output.
+    #[clap(long, default_value_t = 1_000_000)]
+    baud: u32,
+
+    /// How long to watch the variables for, in seconds. Without it, the variables are watched
+    /// until the command is stopped.
+    #[clap(long, value_parser = parse_duration_secs)]
+    duration: Option<Duration>,
+
+    /// The format the values are written in.
+    #[clap(long, value_enum, default_value_t = SignalFormat::Vcd)]
+    format: SignalFormat,
+
+    /// Write the values to this file instead of the standard output.
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+}
+
+/// A variable traced by the DWT comparator with the same index.
+pub(crate) struct Watched {
+    name: String,
+    address: u32,
+    scalar_type: ScalarType,
+    /// The bytes of the value, as far as they are known from the traced writes.
+    bytes: [u8; 4],
+}
+
+impl Watched {
+    /// Updates the value with the data of a traced access, which may be smaller than the value.
+    fn update(&mut self, data: &[u8]) -> ScalarValue {
+        let len = data.len().min(self.bytes.len());
+        self.bytes[..len].copy_from_slice(&data[..len]);
+        self.scalar_type.decode(&self.bytes, RunTimeEndian::Little)
+    }
+}
+
+impl WatchCmd {
+    /// Looks up the addresses and types of the variables.
+    pub(crate) fn resolve(&self, elf: Option<&[u8]>) -> anyhow::Result<Vec<Watched>> {
+        let debug_info = match elf {
+            Some(elf) => {
+                Some(DebugInfo::from_raw(elf).context("Failed to read the debug information")?)
+            }
+            None => None,
+        };
+
+        self.variables
+            .iter()
+            .map(|name| {
+                let (address, scalar_type) = match parse_u64(name) {
+                    Ok(address) => (address, ScalarType::Unsigned(4)),
+                    Err(_) => {
+                        let Some(debug_info) = &debug_info else {
+                            bail!(
+                                "`{name}` is not an address, the ELF file must be given with `--elf` to watch variables by name"
+                            );
+                        };
+                        let variable = debug_info
+                            .resolve_static_variable(name)
+                            .with_context(|| format!("Failed to resolve `{name}`"))?;
+                        tracing::info!(
+                            "Watching `{}` at {:#010x} as {:?}",
+                            variable.path,
+                            variable.address,
+                            variable.scalar_type
+                        );
+                        (variable.address, variable.scalar_type)
+                    }
+                };
+
+                if scalar_type.byte_size() > 4 {
+                    bail!(
+                        "`{name}` is {} bytes large, only variables of up to 4 bytes can be traced",
+                        scalar_type.byte_size()
+                    );
+                }
+
+                Ok(Watched {
+                    name: name.clone(),
+                    address: u32::try_from(address)
+                        .with_context(|| format!("`{name}` is not in the 32-bit address space"))?,
+                    scalar_type,
+                    bytes: [0; 4],
+                })
+            })
+            .collect()
+    }
+
+    /// Traces the writes to `variables` over SWO, until the duration is over.
+    pub(crate) fn run(
+        self,
+        session: &mut Session,
+        core: usize,
+        mut variables: Vec<Watched>,
+    ) -> anyhow::Result<()> {
+        session.setup_tracing(
+            core,
+            TraceSink::Swo(SwoConfig::new(self.clk).set_baud(self.baud)),
+        )?;
+        for (unit, variable) in variables.iter().enumerate() {
+            session
+                .add_swv_data_trace(unit, variable.address)
+                .with_context(|| {
+                    format!("Failed to set up the data trace of `{}`", variable.name)
+                })?;
+        }
+
+        let output: Box<dyn Write> = match &self.output {
+            Some(path) => {
+                Box::new(BufWriter::new(File::create(path).with_context(|| {
+                    format!("Failed to create {}", path.display())
+                })?))
+            }
+            None => Box::new(std::io::stdout().lock()),
+        };
+        // Every variable is followed by the address of the instructions that write it.
+        let signals = variables
+            .iter()
+            .flat_map(|variable| {
+                [
+                    Signal {
+                        name: variable.name.clone(),
+                        scalar_type: variable.scalar_type,
+                    },
+                    Signal {
+                        name: format!("{}@pc", variable.name),
+                        scalar_type: ScalarType::Unsigned(4),
+                    },
+                ]
+            })
+            .collect();
+        let mut writer = SignalWriter::new(output, self.format, signals)?;
+
+        let result = self.record(session, &mut variables, &mut writer);
+
+        for unit in 0..variables.len() {
+            session.remove_swv_data_trace(unit)?;
+        }
+
+        result
+    }
+
+    fn record(
+        &self,
+        session: &mut Session,
+        variables: &mut [Watched],
+        writer: &mut SignalWriter<impl Write>,
+    ) -> anyhow::Result<()> {
+        let decoder = itm::Decoder::new(
+            session.swo_reader()?,
+            itm::DecoderOptions { ignore_eof: true },
+        );
+        let timestamp_cfg = itm::TimestampsConfiguration {
+            clock_frequency: self.clk,
+            lts_prescaler: itm::LocalTimestampOptions::Enabled,
+            expect_malformed: true,
+        };
+
+        let start = Instant::now();
+        let mut values = vec![None; variables.len() * 2];
+        for packets in decoder.timestamps(timestamp_cfg) {
+            let packets = packets?;
+            // If the exact time is not known, the packets were generated before the timestamp.
+            let time = match packets.timestamp {
+                Timestamp::Sync(time) | Timestamp::AssocEventDelay(time) => time,
+                Timestamp::UnknownDelay { curr, .. }
+                | Timestamp::UnknownAssocEventDelay { curr, .. } => curr,
+            };
+
+            for packet in &packets.packets {
+                let (signal, value) = match packet {
+                    TracePacket::DataTraceValue {
+                        comparator, value, ..
+                    } => {
+                        let Some(variable) = variables.get_mut(usize::from(*comparator)) else {
+                            continue;
+                        };
+                        (usize::from(*comparator) * 2, variable.update(value))
+                    }
+                    TracePacket::DataTracePC { comparator, pc }
+                        if usize::from(*comparator) < variables.len() =>
+                    {
+                        (
+                            usize::from(*comparator) * 2 + 1,
+                            ScalarValue::Unsigned((*pc).into()),
+                        )
+                    }
+                    TracePacket::Overflow => {
+                        tracing::warn!("The ITM overflowed, some accesses were not traced");
+                        continue;
+                    }
+                    _ => continue,
+                };
+
+                // Accesses at the same time are written separately, so none of them is lost.
+                if values[signal].is_some() {
+                    writer.write(time, &values)?;
+                    values.fill(None);
+                }
+                values[signal] = Some(value);
+            }
+
+            if values.iter().any(Option::is_some) {
+                writer.write(time, &values)?;
+                values.fill(None);
+            }
+
+            if self
+                .duration
+                .is_some_and(|duration| start.elapsed() > duration)
+            {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn partial_writes_update_the_value() {
+        let mut variable = Watched {
+            name: "STATE.counter".to_string(),
+            address: 0x2000_0000,
+            scalar_type: ScalarType::Signed(4),
+            bytes: [0; 4],
+        };
+
+        assert_eq!(
+            variable.update(&[0xfe, 0xff, 0xff, 0xff]),
+            ScalarValue::Signed(-2)
+        );
+        // A byte write to the variable only replaces its lowest byte.
+        assert_eq!(variable.update(&[0x00]), ScalarValue::Signed(-256));
+    }
+
+    #[test]
+    fn at_most_four_variables() {
+        use clap::Parser;
+
+        #[derive(clap::Parser)]
+        struct Cli {
+            #[clap(flatten)]
+            watch: WatchCmd,
+        }
+
+        assert!(Cli::try_parse_from(["watch", "--clk", "1", "A", "B", "C", "D"]).is_ok());
+        assert!(Cli::try_parse_from(["watch", "--clk", "1", "A", "B", "C", "D", "E"]).is_err());
+    }
+}
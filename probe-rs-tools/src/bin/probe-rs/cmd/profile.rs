@@ -1,5 +1,9 @@
 mod callstack;
-mod flat;
+pub(crate) mod diff;
+mod export;
+pub(crate) mod flat;
+
+use std::path::PathBuf;
 
 use probe_rs::config::Registry;
 use probe_rs::probe::list::Lister;
@@ -20,6 +24,10 @@ pub(crate) struct ProfileCmd {
     /// Duration of profile in seconds.
     #[clap(long)]
     duration: u64, // Option<u64> If we could catch ctrl-c we can make this optional
+    /// Save the profile together with the build ID of the ELF file, to compare it with the
+    /// profile of another build using `probe-rs profile-diff`.
+    #[clap(long, value_name = "PATH")]
+    save: Option<PathBuf>,
     /// Profiling type
     #[clap(subcommand)]
     profile_type: ProfileType,
@@ -68,22 +76,22 @@ impl ProfileCmd {
 
         info!("Profiling...");
 
-        match self.profile_type {
-            ProfileType::Flat(flat_args) => flat::flat_profile(
-                &flat_args.method,
-                &mut session,
-                flat_args.line_info,
-                self.duration,
-                flat_args.core,
-                file_location,
-                flat_args.limit,
-            ),
+        let profile = match self.profile_type {
+            ProfileType::Flat(flat_args) => {
+                flat::flat_profile(&mut session, self.duration, file_location, &flat_args)?
+            }
             ProfileType::Callstack(callstack_args) => callstack::callstack_profile(
                 &mut session,
                 self.duration,
                 file_location,
                 &callstack_args,
-            ),
+            )?,
+        };
+
+        if let Some(path) = &self.save {
+            diff::SavedProfile::new(&profile, file_location)?.save(path)?;
         }
+
+        Ok(())
     }
 }
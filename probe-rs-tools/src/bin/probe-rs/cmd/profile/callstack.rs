@@ -7,6 +7,9 @@ use probe_rs_debug::DebugInfo;
 
 use anyhow::Context;
 use object::{Object, ObjectSymbol};
+
+use super::export::{ExportFormat, Profile};
+use super::flat::Symbols;
 mod dwarf;
 mod frame_pointer;
 mod fxprof;
@@ -47,10 +50,16 @@ pub(crate) enum OutputFormat {
     /// Firefox profiler output format that can be opened using:
     /// samply load probe-rs-profile.json.gz
     FirefoxProfiler,
+    /// gzip compressed pprof protobuf, written to probe-rs-profile.pb.gz, that can be opened
+    /// using: go tool pprof -http=: probe-rs-profile.pb.gz
+    Pprof,
+    /// Collapsed stacks, one line per call stack, written to probe-rs-profile.folded, that can be
+    /// turned into a flamegraph using: inferno-flamegraph probe-rs-profile.folded > flamegraph.svg
+    Collapsed,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum FunctionAddress {
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum FunctionAddress {
     ProgramCounter(u64),
     // Return address adjusted to point to start of call instruction
     // See `fxprofpp::Frame::AdjustedReturnAddress`
@@ -127,7 +136,7 @@ pub(super) fn callstack_profile(
     duration: u64,
     executable_location: &Path,
     callstack_profile_args: &CallstackProfileArgs,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<Profile> {
     let duration = Duration::from_secs(duration);
     let sampling_interval = Duration::from_nanos((1e9 / callstack_profile_args.rate) as u64);
 
@@ -182,6 +191,13 @@ pub(super) fn callstack_profile(
         std::thread::sleep(sampling_interval.saturating_sub(current_sample_time));
     }
 
+    let mut profile = Profile::new(start_sys_time, start.elapsed());
+    for CoreSamples { core, callstacks } in &samples {
+        for sample in callstacks {
+            profile.add(*core, sample.callstack.clone(), 1);
+        }
+    }
+
     // output profiling data
     match callstack_profile_args.output_format {
         OutputFormat::FirefoxProfiler => {
@@ -197,9 +213,23 @@ pub(super) fn callstack_profile(
             let profile_name = "probe-rs-profile";
             fxprof::save_fx_profile(&profile, &output_dir, profile_name)?;
         }
+        OutputFormat::Pprof => export_profile(&profile, executable_location, ExportFormat::Pprof)?,
+        OutputFormat::Collapsed => {
+            export_profile(&profile, executable_location, ExportFormat::Collapsed)?
+        }
     }
 
-    Ok(())
+    Ok(profile)
+}
+
+/// Writes the sampled call stacks of all cores in one of the formats shared with flat profiles.
+fn export_profile(
+    profile: &Profile,
+    executable_location: &Path,
+    format: ExportFormat,
+) -> anyhow::Result<()> {
+    let symbols = Symbols::load(executable_location)?;
+    profile.save(format, &symbols, executable_location, "probe-rs-profile")
 }
 
 #[cfg(test)]
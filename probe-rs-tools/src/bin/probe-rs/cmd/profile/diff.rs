@@ -0,0 +1,303 @@
+//! Compares the profiles of two firmware builds, saved with `probe-rs profile --save`.
+//!
+//! Functions are matched across the builds by their demangled name, so the comparison still
+//! works when the functions moved to other addresses.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use serde::{Deserialize, Serialize};
+
+use super::export::{Profile, build_id};
+use super::flat::Symbols;
+use crate::util::logging;
+
+/// The name of the source file of functions without debug information.
+const UNKNOWN_FILE: &str = "<unknown>";
+
+/// A profile reduced to the samples taken in each function, together with the ELF file it was
+/// taken from.
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) struct SavedProfile {
+    elf: PathBuf,
+    build_id: Option<String>,
+    samples: u64,
+    functions: Vec<FunctionSamples>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FunctionSamples {
+    name: String,
+    file: Option<String>,
+    samples: u64,
+}
+
+impl SavedProfile {
+    pub(super) fn new(profile: &Profile, elf: &Path) -> anyhow::Result<Self> {
+        let symbols = Symbols::load(elf)?;
+        let functions = profile
+            .self_samples(&symbols)
+            .into_iter()
+            .map(|((name, file), samples)| FunctionSamples {
+                name,
+                file,
+                samples,
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Self {
+            elf: elf.to_path_buf(),
+            build_id: build_id(elf)?,
+            samples: functions.iter().map(|function| function.samples).sum(),
+            functions,
+        })
+    }
+
+    pub(super) fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let file =
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+        logging::println(format!("Saved profile to {}", path.display()));
+        Ok(())
+    }
+
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("{} is not a saved profile", path.display()))
+    }
+
+    fn by_function(&self) -> BTreeMap<&str, u64> {
+        let mut functions = BTreeMap::new();
+        for function in &self.functions {
+            *functions.entry(function.name.as_str()).or_default() += function.samples;
+        }
+        functions
+    }
+
+    fn by_file(&self) -> BTreeMap<&str, u64> {
+        let mut files = BTreeMap::new();
+        for function in &self.functions {
+            let file = function.file.as_deref().unwrap_or(UNKNOWN_FILE);
+            *files.entry(file).or_default() += function.samples;
+        }
+        files
+    }
+}
+
+/// The share of the samples of a function or file in both builds, in percent.
+#[derive(Debug, PartialEq)]
+struct Change {
+    name: String,
+    before: f64,
+    after: f64,
+}
+
+impl Change {
+    /// The change of the share in percentage points, positive if more time is spent.
+    fn delta(&self) -> f64 {
+        self.after - self.before
+    }
+}
+
+/// Compares the shares of the samples, ordered by the size of the change.
+fn changes(before: BTreeMap<&str, u64>, after: BTreeMap<&str, u64>) -> Vec<Change> {
+    let share = |samples: u64, total: u64| {
+        if total == 0 {
+            0.0
+        } else {
+            samples as f64 / total as f64 * 100.0
+        }
+    };
+    let total_before = before.values().sum();
+    let total_after = after.values().sum();
+
+    let mut names = before.keys().chain(after.keys()).collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+
+    let mut changes = names
+        .into_iter()
+        .map(|name| Change {
+            name: name.to_string(),
+            before: share(before.get(name).copied().unwrap_or(0), total_before),
+            after: share(after.get(name).copied().unwrap_or(0), total_after),
+        })
+        .collect::<Vec<_>>();
+    changes.sort_by(|a, b| b.delta().abs().total_cmp(&a.delta().abs()));
+    changes
+}
+
+#[derive(clap::Args, Debug, Clone, PartialEq)]
+pub(crate) struct DiffCmd {
+    /// The profile of the old build, saved with `probe-rs profile --save`.
+    before: PathBuf,
+    /// The profile of the new build, saved with `probe-rs profile --save`.
+    after: PathBuf,
+    /// The number of functions and files to list.
+    #[clap(long, default_value_t = 25)]
+    limit: usize,
+    /// Fail if the share of samples of any function grew by more than this many percentage
+    /// points.
+    #[clap(long, value_name = "PERCENT")]
+    fail_on_regression: Option<f64>,
+}
+
+impl DiffCmd {
+    pub fn run(self) -> anyhow::Result<()> {
+        let before = SavedProfile::load(&self.before)?;
+        let after = SavedProfile::load(&self.after)?;
+
+        for (label, profile) in [("Before", &before), ("After", &after)] {
+            println!(
+                "{label:<6} {} ({}, {} samples)",
+                profile.elf.display(),
+                profile.build_id.as_deref().unwrap_or("no build ID"),
+                profile.samples
+            );
+        }
+        if before.build_id.is_some() && before.build_id == after.build_id {
+            tracing::warn!("Both profiles were taken from the same build");
+        }
+
+        let functions = changes(before.by_function(), after.by_function());
+        let files = changes(before.by_file(), after.by_file());
+
+        println!();
+        self.print(&functions, "Function");
+        println!();
+        self.print(&files, "File");
+
+        if let Some(threshold) = self.fail_on_regression {
+            let regressions = regressions(&functions, threshold);
+            if !regressions.is_empty() {
+                bail!(
+                    "The share of samples grew by more than {threshold:.2} percentage points in: {}",
+                    regressions.join(", ")
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn print(&self, changes: &[Change], kind: &str) {
+        println!("{:>8} {:>8} {:>8}  {kind}", "Before", "After", "Change");
+        for change in changes.iter().take(self.limit) {
+            println!(
+                "{:>7.2}% {:>7.2}% {:>+8.2}  {}",
+                change.before,
+                change.after,
+                change.delta(),
+                change.name
+            );
+        }
+    }
+}
+
+/// The names of the functions whose share grew by more than `threshold` percentage points.
+fn regressions(changes: &[Change], threshold: f64) -> Vec<&str> {
+    changes
+        .iter()
+        .filter(|change| change.delta() > threshold)
+        .map(|change| change.name.as_str())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn profile(functions: &[(&str, Option<&str>, u64)]) -> SavedProfile {
+        SavedProfile {
+            elf: PathBuf::from("firmware.elf"),
+            build_id: None,
+            samples: functions.iter().map(|(_, _, samples)| samples).sum(),
+            functions: functions
+                .iter()
+                .map(|(name, file, samples)| FunctionSamples {
+                    name: name.to_string(),
+                    file: file.map(str::to_string),
+                    samples: *samples,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn changes_are_sorted_by_size() {
+        let before = profile(&[
+            ("main", Some("main.rs"), 50),
+            ("idle", Some("main.rs"), 40),
+            ("memcpy", None, 10),
+        ]);
+        let after = profile(&[
+            ("main", Some("main.rs"), 80),
+            ("memcpy", None, 10),
+            ("crc32", Some("crc.rs"), 10),
+        ]);
+
+        let functions = changes(before.by_function(), after.by_function());
+        assert_eq!(
+            functions,
+            [
+                Change {
+                    name: "idle".to_string(),
+                    before: 40.0,
+                    after: 0.0,
+                },
+                Change {
+                    name: "main".to_string(),
+                    before: 50.0,
+                    after: 80.0,
+                },
+                Change {
+                    name: "crc32".to_string(),
+                    before: 0.0,
+                    after: 10.0,
+                },
+                Change {
+                    name: "memcpy".to_string(),
+                    before: 10.0,
+                    after: 10.0,
+                },
+            ]
+        );
+
+        let files = changes(before.by_file(), after.by_file());
+        let files = files
+            .iter()
+            .map(|change| (change.name.as_str(), change.delta()))
+            .collect::<Vec<_>>();
+        // Equal changes are ordered by name.
+        assert_eq!(
+            files,
+            [("crc.rs", 10.0), ("main.rs", -10.0), (UNKNOWN_FILE, 0.0)]
+        );
+    }
+
+    #[test]
+    fn regressions_over_the_threshold() {
+        let before = profile(&[("main", None, 50), ("idle", None, 50)]);
+        let after = profile(&[("main", None, 55), ("idle", None, 45)]);
+        let functions = changes(before.by_function(), after.by_function());
+
+        assert_eq!(regressions(&functions, 4.0), ["main"]);
+        assert!(regressions(&functions, 6.0).is_empty());
+    }
+
+    #[test]
+    fn saved_profile_round_trip() {
+        let saved = profile(&[("main", Some("main.rs"), 3)]);
+
+        let json = serde_json::to_string(&saved).unwrap();
+        let loaded: SavedProfile = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded.by_function(), saved.by_function());
+        assert_eq!(loaded.by_file(), saved.by_file());
+    }
+}
@@ -0,0 +1,540 @@
+//! Writes profiles as pprof protobuf or as collapsed stacks, for all profiling modes.
+//!
+//! Inlined functions are split out into frames of their own, so they show up in flamegraphs
+//! and in `go tool pprof` like regular functions.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use super::callstack::FunctionAddress;
+use super::flat::{SymbolFrame, Symbols};
+use crate::util::logging;
+use crate::util::protobuf::{bytes_field, message, packed_field, varint_field};
+
+/// The formats any profile can be exported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ExportFormat {
+    /// gzip compressed pprof protobuf, for `go tool pprof`.
+    Pprof,
+    /// A line per call stack, with the functions separated by semicolons and followed by the
+    /// number of samples, for `inferno` and `flamegraph.pl`.
+    Collapsed,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Pprof => "pb.gz",
+            Self::Collapsed => "folded",
+        }
+    }
+}
+
+/// The call stacks sampled during profiling, and how often each of them was sampled.
+pub(super) struct Profile {
+    /// The sample counts by core and call stack, with the root of the call stack first.
+    samples: BTreeMap<(usize, Vec<FunctionAddress>), u64>,
+    start_time: SystemTime,
+    duration: Duration,
+}
+
+impl Profile {
+    pub(super) fn new(start_time: SystemTime, duration: Duration) -> Self {
+        Self {
+            samples: BTreeMap::new(),
+            start_time,
+            duration,
+        }
+    }
+
+    /// Adds `count` samples of a call stack, given with its root first.
+    pub(super) fn add(&mut self, core: usize, callstack: Vec<FunctionAddress>, count: u64) {
+        *self.samples.entry((core, callstack)).or_default() += count;
+    }
+
+    /// Writes the profile into the current directory, as `<name>.<extension>`.
+    pub(super) fn save(
+        &self,
+        format: ExportFormat,
+        symbols: &Symbols,
+        binary_path: &Path,
+        name: &str,
+    ) -> anyhow::Result<()> {
+        let output_path = std::env::current_dir()?
+            .join(name)
+            .with_extension(format.extension());
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(&output_path)?);
+
+        match format {
+            ExportFormat::Pprof => {
+                let build_id = build_id(binary_path)?;
+                let profile = self.to_pprof(symbols, binary_path, build_id.as_deref());
+
+                let mut gz = flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+                gz.write_all(&profile)?;
+                gz.finish()?.flush()?;
+            }
+            ExportFormat::Collapsed => {
+                self.write_collapsed(symbols, &mut writer)?;
+                writer.flush()?;
+            }
+        }
+
+        logging::println(format!("Wrote profile to {}", output_path.display()));
+        Ok(())
+    }
+
+    /// Sums up the samples by the function and source file they were taken in, without the
+    /// samples of the functions it called.
+    pub(super) fn self_samples(
+        &self,
+        symbols: &Symbols,
+    ) -> BTreeMap<(String, Option<String>), u64> {
+        let mut symbolizer = Symbolizer::new(symbols);
+
+        let mut functions = BTreeMap::new();
+        for ((_core, callstack), count) in &self.samples {
+            let Some(leaf) = callstack.last() else {
+                continue;
+            };
+            // The innermost inlined function is the one that was executing.
+            let Some(frame) = symbolizer.frames(*leaf).first() else {
+                continue;
+            };
+            *functions
+                .entry((frame.function.clone(), frame.file.clone()))
+                .or_default() += count;
+        }
+        functions
+    }
+
+    /// Writes a line per distinct stack of functions, from the root to the leaf. Cores are
+    /// added as the root frame if more than one core was sampled.
+    pub(super) fn write_collapsed(
+        &self,
+        symbols: &Symbols,
+        writer: &mut impl Write,
+    ) -> std::io::Result<()> {
+        let mut symbolizer = Symbolizer::new(symbols);
+        let multicore = self.cores() > 1;
+
+        let mut stacks = BTreeMap::<String, u64>::new();
+        for ((core, callstack), count) in &self.samples {
+            let mut names = Vec::new();
+            if multicore {
+                names.push(format!("core {core}"));
+            }
+            for address in callstack {
+                // The frames are ordered from the innermost inlined function outwards.
+                for frame in symbolizer.frames(*address).iter().rev() {
+                    names.push(frame.function.replace(';', ":"));
+                }
+            }
+            *stacks.entry(names.join(";")).or_default() += count;
+        }
+
+        for (stack, count) in stacks {
+            writeln!(writer, "{stack} {count}")?;
+        }
+        Ok(())
+    }
+
+    /// Encodes the profile as an uncompressed pprof `Profile` message.
+    pub(super) fn to_pprof(
+        &self,
+        symbols: &Symbols,
+        binary_path: &Path,
+        build_id: Option<&str>,
+    ) -> Vec<u8> {
+        let mut encoder = PprofEncoder::new(symbols);
+        let sample_type = encoder.strings.index("samples");
+        let sample_unit = encoder.strings.index("count");
+        let core_key = encoder.strings.index("core");
+
+        let mut samples = Vec::new();
+        for ((core, callstack), count) in &self.samples {
+            // pprof expects the leaf of the call stack first.
+            let location_ids = callstack
+                .iter()
+                .rev()
+                .map(|address| encoder.location(*address))
+                .collect::<Vec<_>>();
+
+            message(&mut samples, profile_field::SAMPLE, |sample| {
+                packed_field(sample, sample_field::LOCATION_ID, location_ids);
+                packed_field(sample, sample_field::VALUE, [*count]);
+                message(sample, sample_field::LABEL, |label| {
+                    varint_field(label, label_field::KEY, core_key);
+                    varint_field(label, label_field::NUM, *core as u64);
+                });
+            });
+        }
+
+        let mut profile = Vec::new();
+        message(&mut profile, profile_field::SAMPLE_TYPE, |value_type| {
+            varint_field(value_type, value_type_field::TYPE, sample_type);
+            varint_field(value_type, value_type_field::UNIT, sample_unit);
+        });
+        profile.extend_from_slice(&samples);
+
+        let filename = encoder.strings.index(&binary_path.display().to_string());
+        let build_id = encoder.strings.index(build_id.unwrap_or(""));
+        message(&mut profile, profile_field::MAPPING, |mapping| {
+            varint_field(mapping, mapping_field::ID, PprofEncoder::MAPPING_ID);
+            varint_field(mapping, mapping_field::MEMORY_LIMIT, u64::MAX);
+            varint_field(mapping, mapping_field::FILENAME, filename);
+            varint_field(mapping, mapping_field::BUILD_ID, build_id);
+            varint_field(mapping, mapping_field::HAS_FUNCTIONS, 1);
+            varint_field(mapping, mapping_field::HAS_FILENAMES, 1);
+            varint_field(mapping, mapping_field::HAS_LINE_NUMBERS, 1);
+            varint_field(mapping, mapping_field::HAS_INLINE_FRAMES, 1);
+        });
+        profile.extend_from_slice(&encoder.locations);
+        profile.extend_from_slice(&encoder.functions);
+
+        for string in &encoder.strings.strings {
+            bytes_field(&mut profile, profile_field::STRING_TABLE, string.as_bytes());
+        }
+
+        let time_nanos = self
+            .start_time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+        varint_field(&mut profile, profile_field::TIME_NANOS, time_nanos);
+        varint_field(
+            &mut profile,
+            profile_field::DURATION_NANOS,
+            self.duration.as_nanos() as u64,
+        );
+
+        profile
+    }
+
+    fn cores(&self) -> usize {
+        let mut cores = self
+            .samples
+            .keys()
+            .map(|(core, _)| *core)
+            .collect::<Vec<_>>();
+        cores.dedup();
+        cores.len()
+    }
+}
+
+impl FunctionAddress {
+    /// The address to look up the function and line of the frame at.
+    fn lookup_address(self) -> u64 {
+        match self {
+            Self::ProgramCounter(address) | Self::AdjustedReturnAddress(address) => address,
+        }
+    }
+}
+
+/// Looks up the frames of addresses, once per address.
+struct Symbolizer<'a> {
+    symbols: &'a Symbols,
+    cache: HashMap<u64, Vec<SymbolFrame>>,
+}
+
+impl<'a> Symbolizer<'a> {
+    fn new(symbols: &'a Symbols) -> Self {
+        Self {
+            symbols,
+            cache: HashMap::new(),
+        }
+    }
+
+    fn frames(&mut self, address: FunctionAddress) -> &[SymbolFrame] {
+        let address = address.lookup_address();
+        self.cache
+            .entry(address)
+            .or_insert_with(|| self.symbols.get_frames(address))
+    }
+}
+
+/// Collects the locations and functions of a pprof profile, which the samples refer to by ID.
+struct PprofEncoder<'a> {
+    symbolizer: Symbolizer<'a>,
+    strings: StringTable,
+    location_ids: HashMap<u64, u64>,
+    function_ids: HashMap<(String, Option<String>), u64>,
+    /// The encoded `Location` messages.
+    locations: Vec<u8>,
+    /// The encoded `Function` messages.
+    functions: Vec<u8>,
+}
+
+impl<'a> PprofEncoder<'a> {
+    /// All addresses are in the single mapping of the ELF file.
+    const MAPPING_ID: u64 = 1;
+
+    fn new(symbols: &'a Symbols) -> Self {
+        Self {
+            symbolizer: Symbolizer::new(symbols),
+            strings: StringTable::default(),
+            location_ids: HashMap::new(),
+            function_ids: HashMap::new(),
+            locations: Vec::new(),
+            functions: Vec::new(),
+        }
+    }
+
+    /// Returns the ID of the location of an address, adding the location if it is new.
+    fn location(&mut self, address: FunctionAddress) -> u64 {
+        let pc = address.lookup_address();
+        if let Some(id) = self.location_ids.get(&pc) {
+            return *id;
+        }
+        let id = self.location_ids.len() as u64 + 1;
+        self.location_ids.insert(pc, id);
+
+        // Inlined functions come first, followed by the functions they were inlined into.
+        let lines = self
+            .symbolizer
+            .frames(address)
+            .to_vec()
+            .into_iter()
+            .map(|frame| (self.function(&frame), frame.line.unwrap_or(0)))
+            .collect::<Vec<_>>();
+
+        message(&mut self.locations, profile_field::LOCATION, |location| {
+            varint_field(location, location_field::ID, id);
+            varint_field(location, location_field::MAPPING_ID, Self::MAPPING_ID);
+            varint_field(location, location_field::ADDRESS, pc);
+            for (function_id, line_number) in lines {
+                message(location, location_field::LINE, |line| {
+                    varint_field(line, line_field::FUNCTION_ID, function_id);
+                    varint_field(line, line_field::LINE, line_number.into());
+                });
+            }
+        });
+
+        id
+    }
+
+    /// Returns the ID of the function of a frame, adding the function if it is new.
+    fn function(&mut self, frame: &SymbolFrame) -> u64 {
+        let key = (frame.function.clone(), frame.file.clone());
+        if let Some(id) = self.function_ids.get(&key) {
+            return *id;
+        }
+        let id = self.function_ids.len() as u64 + 1;
+        self.function_ids.insert(key, id);
+
+        let name = self.strings.index(&frame.function);
+        let filename = self.strings.index(frame.file.as_deref().unwrap_or(""));
+        message(&mut self.functions, profile_field::FUNCTION, |function| {
+            varint_field(function, function_field::ID, id);
+            varint_field(function, function_field::NAME, name);
+            varint_field(function, function_field::SYSTEM_NAME, name);
+            varint_field(function, function_field::FILENAME, filename);
+        });
+
+        id
+    }
+}
+
+/// The string table of a pprof profile, whose first entry is always the empty string.
+struct StringTable {
+    strings: Vec<String>,
+    indices: HashMap<String, u64>,
+}
+
+impl Default for StringTable {
+    fn default() -> Self {
+        Self {
+            strings: vec![String::new()],
+            indices: HashMap::from([(String::new(), 0)]),
+        }
+    }
+}
+
+impl StringTable {
+    fn index(&mut self, string: &str) -> u64 {
+        if let Some(index) = self.indices.get(string) {
+            return *index;
+        }
+        let index = self.strings.len() as u64;
+        self.strings.push(string.to_string());
+        self.indices.insert(string.to_string(), index);
+        index
+    }
+}
+
+/// Reads the build ID of an ELF file as a hex string, if it has one.
+pub(super) fn build_id(path: &Path) -> anyhow::Result<Option<String>> {
+    let elf = std::fs::read(path)?;
+    Ok(object::File::parse(elf.as_slice())
+        .ok()
+        .and_then(|file| object::Object::build_id(&file).ok().flatten())
+        .map(hex_string))
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+// Field numbers of the pprof protobuf messages, see
+// https://github.com/google/pprof/blob/main/proto/profile.proto.
+
+mod profile_field {
+    pub const SAMPLE_TYPE: u32 = 1;
+    pub const SAMPLE: u32 = 2;
+    pub const MAPPING: u32 = 3;
+    pub const LOCATION: u32 = 4;
+    pub const FUNCTION: u32 = 5;
+    pub const STRING_TABLE: u32 = 6;
+    pub const TIME_NANOS: u32 = 9;
+    pub const DURATION_NANOS: u32 = 10;
+}
+
+mod value_type_field {
+    pub const TYPE: u32 = 1;
+    pub const UNIT: u32 = 2;
+}
+
+mod sample_field {
+    pub const LOCATION_ID: u32 = 1;
+    pub const VALUE: u32 = 2;
+    pub const LABEL: u32 = 3;
+}
+
+mod label_field {
+    pub const KEY: u32 = 1;
+    pub const NUM: u32 = 3;
+}
+
+mod mapping_field {
+    pub const ID: u32 = 1;
+    pub const MEMORY_LIMIT: u32 = 3;
+    pub const FILENAME: u32 = 5;
+    pub const BUILD_ID: u32 = 6;
+    pub const HAS_FUNCTIONS: u32 = 7;
+    pub const HAS_FILENAMES: u32 = 8;
+    pub const HAS_LINE_NUMBERS: u32 = 9;
+    pub const HAS_INLINE_FRAMES: u32 = 10;
+}
+
+mod location_field {
+    pub const ID: u32 = 1;
+    pub const MAPPING_ID: u32 = 2;
+    pub const ADDRESS: u32 = 3;
+    pub const LINE: u32 = 4;
+}
+
+mod line_field {
+    pub const FUNCTION_ID: u32 = 1;
+    pub const LINE: u32 = 2;
+}
+
+mod function_field {
+    pub const ID: u32 = 1;
+    pub const NAME: u32 = 2;
+    pub const SYSTEM_NAME: u32 = 3;
+    pub const FILENAME: u32 = 4;
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn elf_path() -> PathBuf {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.pop();
+        path.push("probe-rs-debug/tests/debug-unwind-tests/RP2040_full_unwind.elf");
+        path
+    }
+
+    fn profile() -> Profile {
+        let mut profile = Profile::new(SystemTime::UNIX_EPOCH, Duration::from_secs(1));
+        // `Option::unwrap` is inlined at this address.
+        profile.add(0, vec![FunctionAddress::ProgramCounter(0x1000_0220)], 3);
+        profile.add(0, vec![FunctionAddress::ProgramCounter(0x1000_0210)], 1);
+        profile.add(0, vec![FunctionAddress::ProgramCounter(0x1000_0212)], 1);
+        profile
+    }
+
+    #[test]
+    fn collapsed_stacks_split_inlined_functions() {
+        let symbols = Symbols::try_from(&elf_path()).unwrap();
+
+        let mut output = Vec::new();
+        profile().write_collapsed(&symbols, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "RP2040::__cortex_m_rt_main 2\n\
+             RP2040::__cortex_m_rt_main;core::option::Option<T>::unwrap 3\n"
+        );
+    }
+
+    #[test]
+    fn collapsed_stacks_of_multiple_cores() {
+        let symbols = Symbols::try_from(&elf_path()).unwrap();
+        let mut profile = profile();
+        profile.add(1, vec![FunctionAddress::ProgramCounter(0x1000_0210)], 4);
+
+        let mut output = Vec::new();
+        profile.write_collapsed(&symbols, &mut output).unwrap();
+
+        assert!(
+            String::from_utf8(output)
+                .unwrap()
+                .contains("core 1;RP2040::__cortex_m_rt_main 4\n")
+        );
+    }
+
+    #[test]
+    fn self_samples_of_inlined_functions() {
+        let symbols = Symbols::try_from(&elf_path()).unwrap();
+
+        let samples = profile().self_samples(&symbols);
+        let counts = samples
+            .iter()
+            .map(|((function, _file), count)| (function.as_str(), *count))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            counts,
+            [
+                ("RP2040::__cortex_m_rt_main", 2),
+                ("core::option::Option<T>::unwrap", 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn pprof_profile() {
+        let symbols = Symbols::try_from(&elf_path()).unwrap();
+
+        let pprof = profile().to_pprof(&symbols, &elf_path(), Some("abcd"));
+
+        // The sample type comes first, with the first strings after the empty string.
+        assert!(pprof.starts_with(&[0x0a, 0x04, 0x08, 0x01, 0x10, 0x02]));
+        for string in [
+            "RP2040::__cortex_m_rt_main",
+            "core::option::Option<T>::unwrap",
+            "abcd",
+        ] {
+            assert!(
+                pprof
+                    .windows(string.len())
+                    .any(|window| window == string.as_bytes())
+            );
+        }
+    }
+
+    #[test]
+    fn string_table() {
+        let mut strings = StringTable::default();
+        assert_eq!(strings.index(""), 0);
+        assert_eq!(strings.index("samples"), 1);
+        assert_eq!(strings.index("count"), 2);
+        assert_eq!(strings.index("samples"), 1);
+    }
+}
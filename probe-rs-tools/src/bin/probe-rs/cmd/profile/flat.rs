@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::path::Path;
 use std::time::Duration;
 use std::time::Instant;
+use std::time::SystemTime;
 
 use probe_rs::Session;
 use probe_rs::architecture::arm::{
@@ -15,6 +16,9 @@ use addr2line::Loader;
 use anyhow::anyhow;
 use itm::TracePacket;
 
+use super::callstack::FunctionAddress;
+use super::export::{ExportFormat, Profile};
+
 #[derive(clap::Args, Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) struct FlatProfileArgs {
     #[clap(subcommand)]
@@ -28,6 +32,21 @@ pub(crate) struct FlatProfileArgs {
     /// Limit the number of entries to output
     #[clap(long, default_value_t = 25)]
     pub(crate) limit: usize,
+    /// Output format.
+    #[clap(long, value_enum, default_value_t = FlatOutputFormat::Table)]
+    pub(crate) output_format: FlatOutputFormat,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FlatOutputFormat {
+    /// Print the functions with the most samples.
+    Table,
+    /// gzip compressed pprof protobuf, written to probe-rs-profile.pb.gz, that can be opened
+    /// using: go tool pprof -http=: probe-rs-profile.pb.gz
+    Pprof,
+    /// Collapsed stacks, one line per function, written to probe-rs-profile.folded, that can be
+    /// turned into a flamegraph using: inferno-flamegraph probe-rs-profile.folded > flamegraph.svg
+    Collapsed,
 }
 
 #[derive(clap::Subcommand, Debug, Clone, Copy, PartialEq, Eq)]
@@ -57,25 +76,23 @@ impl std::fmt::Display for FlatProfileMethod {
 }
 
 pub(super) fn flat_profile(
-    method: &FlatProfileMethod,
     session: &mut Session,
-    line_info: bool,
     duration: u64,
-    core: usize,
     file_location: &Path,
-    limit: usize,
-) -> anyhow::Result<()> {
-    // The error returned from try_from cannot be converted directly to anyhow::Error unfortunately,
-    // due to a limitation in addr2line.
-    let symbols = Symbols::try_from(file_location).map_err(|e| {
-        anyhow!(
-            "Failed to read symbol data from {}: {}",
-            file_location.display(),
-            e
-        )
-    })?;
+    flat_profile_args: &FlatProfileArgs,
+) -> anyhow::Result<Profile> {
+    let FlatProfileArgs {
+        method,
+        line_info,
+        core,
+        limit,
+        output_format,
+    } = *flat_profile_args;
+
+    let symbols = Symbols::load(file_location)?;
 
     let start = Instant::now();
+    let start_time = SystemTime::now();
     let mut reads = 0;
     let mut samples: HashMap<u32, u64> = HashMap::with_capacity(256 * (duration as usize));
     let duration = Duration::from_secs(duration);
@@ -113,7 +130,7 @@ pub(super) fn flat_profile(
             }
         }
         FlatProfileMethod::Itm { clk, baud } => {
-            let sink = TraceSink::Swo(SwoConfig::new(*clk).set_baud(*baud));
+            let sink = TraceSink::Swo(SwoConfig::new(clk).set_baud(baud));
             session.setup_tracing(core, sink)?;
 
             let components = session.get_arm_components(DpAddress::Default)?;
@@ -141,6 +158,25 @@ pub(super) fn flat_profile(
         }
     }
 
+    let mut profile = Profile::new(start_time, start.elapsed());
+    for (&pc, &count) in &samples {
+        profile.add(
+            core,
+            vec![FunctionAddress::ProgramCounter(pc.into())],
+            count,
+        );
+    }
+
+    let export_format = match output_format {
+        FlatOutputFormat::Table => None,
+        FlatOutputFormat::Pprof => Some(ExportFormat::Pprof),
+        FlatOutputFormat::Collapsed => Some(ExportFormat::Collapsed),
+    };
+    if let Some(format) = export_format {
+        profile.save(format, &symbols, file_location, "probe-rs-profile")?;
+        return Ok(profile);
+    }
+
     let mut v = Vec::from_iter(samples);
     // sort by frequency
     v.sort_by_key(|&(_, b)| std::cmp::Reverse(b));
@@ -164,7 +200,15 @@ pub(super) fn flat_profile(
         );
     }
 
-    Ok(())
+    Ok(profile)
+}
+
+/// A function at an address, which may have been inlined into another function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SymbolFrame {
+    pub function: String,
+    pub file: Option<String>,
+    pub line: Option<u32>,
 }
 
 // Wrapper around addr2line that allows to look up function names
@@ -178,6 +222,14 @@ impl Symbols {
         Ok(Self { loader })
     }
 
+    /// Reads the symbols of an ELF file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        // The error returned from try_from cannot be converted directly to anyhow::Error
+        // unfortunately, due to a limitation in addr2line.
+        Self::try_from(path)
+            .map_err(|e| anyhow!("Failed to read symbol data from {}: {}", path.display(), e))
+    }
+
     /// Returns the name of the function at the given address, if one can be found.
     pub fn get_name(&self, addr: u64) -> Option<String> {
         // The basic steps here are:
@@ -200,6 +252,44 @@ impl Symbols {
             .or_else(|| self.loader.find_symbol(addr).map(|sym| sym.to_string()))
     }
 
+    /// Returns the functions at the given address, from the innermost inlined function to the
+    /// function it was inlined into. Addresses without debug information are named by their
+    /// symbol, or by the address itself.
+    pub fn get_frames(&self, addr: u64) -> Vec<SymbolFrame> {
+        let mut frames = Vec::new();
+        if let Ok(mut iter) = self.loader.find_frames(addr) {
+            while let Ok(Some(frame)) = iter.next() {
+                let Some(function) = frame
+                    .function
+                    .and_then(|name| name.demangle().map(|s| s.into_owned()).ok())
+                else {
+                    continue;
+                };
+                let location = frame.location.as_ref();
+                frames.push(SymbolFrame {
+                    function,
+                    file: location.and_then(|location| location.file.map(str::to_string)),
+                    line: location.and_then(|location| location.line),
+                });
+            }
+        }
+
+        if frames.is_empty() {
+            let function = self
+                .loader
+                .find_symbol(addr)
+                .map(|symbol| addr2line::demangle_auto(symbol.into(), None).into_owned())
+                .unwrap_or_else(|| format!("{addr:#010x}"));
+            frames.push(SymbolFrame {
+                function,
+                file: None,
+                line: None,
+            });
+        }
+
+        frames
+    }
+
     /// Returns the file name and line number of the function at the given address, if one can be.
     pub fn get_location(&self, addr: u64) -> Option<(&str, u32)> {
         // Find the location which `addr` is in. If we can determine a file name and
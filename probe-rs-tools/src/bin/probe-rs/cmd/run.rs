@@ -1,22 +1,31 @@
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 
+use crate::cmd::stack_watermark::print_stack_usage;
 use crate::rpc::utils::run_loop::VectorCatchConfig;
+use probe_rs_rpc::coverage::CoverageMethod;
+use probe_rs_rpc::itm::ItmSink;
 use probe_rs_rpc::monitor::MonitorMode;
 use probe_rs_rpc::rtt_client::ScanRegion;
 use probe_rs_rpc::test::{Test, TestDefinition};
-use probe_rs_rpc_client::RpcClient;
+use probe_rs_rpc_client::{RpcClient, SessionInterface};
 
 use crate::util::cli::{self, parse_metadata, rtt_client};
-use crate::util::common_options::{BinaryDownloadOptions, ProbeOptions};
+use crate::util::common_options::{BinaryDownloadOptions, ProbeOptions, StackPaintOptions};
+use crate::util::itm::ItmPortConfig;
+use crate::util::parse_range;
 use probe_rs_rpc::format::FormatOptions;
 use probe_rs_rpc::rtt_config::ChannelMode;
 
 use anyhow::{Context, anyhow};
 use libtest_mimic::{Arguments, FormatSetting};
-use object::{Object, ObjectSection, ObjectSymbol, Section, Symbol, SymbolKind};
+use object::{Object, ObjectSection, ObjectSymbol, Section, SectionKind, Symbol, SymbolKind};
 use probe_rs::flashing::FileDownloadError;
+use probe_rs_debug::DebugInfo;
+use probe_rs_debug::coverage::CoverageMap;
 use std::fs::File;
-use std::io::Read;
+use std::io::{BufWriter, Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
 use time::UtcOffset;
 
 /// Options only used in normal run mode
@@ -147,6 +156,38 @@ pub struct Cmd {
 
     #[clap(flatten)]
     pub(crate) monitor_options: MonitoringOptions,
+
+    #[clap(flatten)]
+    pub(crate) coverage_options: CoverageOptions,
+
+    #[clap(flatten)]
+    pub(crate) stack_paint_options: StackPaintOptions,
+}
+
+/// Options for collecting code coverage while the firmware runs
+#[derive(Debug, Clone, clap::Args)]
+pub(crate) struct CoverageOptions {
+    /// Collect line and function coverage, and write it as an lcov tracefile to this path.
+    #[clap(long, help_heading = "COVERAGE")]
+    pub(crate) coverage_lcov: Option<PathBuf>,
+
+    /// Collect line and function coverage, and write it as a Cobertura XML report to this path.
+    #[clap(long, help_heading = "COVERAGE")]
+    pub(crate) coverage_cobertura: Option<PathBuf>,
+
+    /// How the executed code is recorded. The MTB records into the region given with
+    /// `--mtb-buffer`.
+    ///
+    /// Sampling only reports the code the core was found executing, so rarely executed lines may
+    /// be reported as not covered.
+    #[clap(long, value_enum, default_value = "auto", help_heading = "COVERAGE")]
+    pub(crate) coverage_method: CoverageMethod,
+}
+
+impl CoverageOptions {
+    fn enabled(&self) -> bool {
+        self.coverage_lcov.is_some() || self.coverage_cobertura.is_some()
+    }
 }
 
 #[derive(Debug, Clone, clap::Args)]
@@ -219,6 +260,37 @@ pub(crate) struct MonitoringOptions {
     )]
     pub(crate) stack_frame_limit: u32,
 
+    /// Record the branch history with the MTB (Micro Trace Buffer) into this RAM region, which
+    /// must not be used by the firmware, for example `0x20000000..0x20000400`. The branch history
+    /// is printed together with the stack trace.
+    ///
+    /// The size of the region has to be a power of two, and the region has to be aligned to its
+    /// size.
+    #[clap(long, value_parser = parse_range, help_heading = "LOG CONFIGURATION / STACK TRACE")]
+    pub(crate) mtb_buffer: Option<Range<u64>>,
+
+    /// Decode the data the target writes to an ITM stimulus port, given as
+    /// `<PORT>=<FORMAT>[,<DESTINATION>]`. The format is `string`, `defmt` or `binary`, and the
+    /// destination is `stdout` (the default), `file:<PATH>` or `tcp:<ADDRESS>`, for example
+    /// `--itm-port 0=string --itm-port 1=defmt,file:out/defmt.txt`.
+    ///
+    /// The ITM is traced into the trace memory (ETB/ETF), unless `--itm-swo-clock` is given.
+    #[clap(long, help_heading = "LOG CONFIGURATION / ITM")]
+    pub(crate) itm_port: Vec<ItmPortConfig>,
+
+    /// Trace the ITM through the SWO pin instead of the trace memory. The frequency of the clock
+    /// feeding the TPIU/SWO module in Hz.
+    #[clap(long, help_heading = "LOG CONFIGURATION / ITM")]
+    pub(crate) itm_swo_clock: Option<u32>,
+
+    /// The baud rate of the SWO output.
+    #[clap(
+        long,
+        default_value = "1000000",
+        help_heading = "LOG CONFIGURATION / ITM"
+    )]
+    pub(crate) itm_swo_baud: u32,
+
     /// Suppress filename and line number information
     #[clap(long, help_heading = "LOG CONFIGURATION")]
     pub(crate) no_location: bool,
@@ -238,10 +310,36 @@ pub(crate) struct MonitoringOptions {
     pub semihosting_file: Vec<String>,
 }
 
+impl MonitoringOptions {
+    /// Returns where the ITM is traced into, if any stimulus port is decoded.
+    pub(crate) fn itm_sink(&self) -> Option<ItmSink> {
+        if self.itm_port.is_empty() {
+            return None;
+        }
+
+        Some(match self.itm_swo_clock {
+            Some(clock) => ItmSink::Swo {
+                clock,
+                baud: self.itm_swo_baud,
+            },
+            None => ItmSink::TraceMemory,
+        })
+    }
+}
+
 impl Cmd {
     pub async fn run(self, client: RpcClient, utc_offset: UtcOffset) -> anyhow::Result<()> {
         // Detect run mode based on ELF file
         let run_mode = detect_run_mode(&self)?;
+        let paint_stack = match run_mode {
+            RunMode::Normal => self.stack_paint_options.region(&self.path)?,
+            RunMode::Test(_) => {
+                if self.stack_paint_options.paint_stack {
+                    tracing::warn!("The stack is not painted when running tests");
+                }
+                None
+            }
+        };
 
         let (file_meta, elf_meta) = parse_metadata(&self.path).await?;
 
@@ -268,8 +366,19 @@ impl Cmd {
         )
         .await?;
 
+        if self.coverage_options.enabled() {
+            let method = session
+                .start_coverage(
+                    self.coverage_options.coverage_method,
+                    self.monitor_options.mtb_buffer.clone(),
+                    code_ranges(&self.path)?,
+                )
+                .await?;
+            tracing::info!("Collecting coverage with method {method:?}");
+        }
+
         // Run firmware based on run mode
-        if let RunMode::Test(elf_info) = run_mode {
+        let result = if let RunMode::Test(elf_info) = run_mode {
             cli::test(
                 &session,
                 boot_info,
@@ -308,10 +417,93 @@ impl Cmd {
                     catch_svc: !self.run_options.no_catch_svc,
                     catch_hlt: !self.run_options.no_catch_hlt,
                 },
+                paint_stack.clone(),
             )
             .await
+        };
+
+        // Failing tests are reported as an error, the coverage of the run is written regardless.
+        if self.coverage_options.enabled() {
+            write_coverage(&session, &self.path, &self.coverage_options).await?;
+        }
+        if let Some(region) = paint_stack {
+            print_stack_usage(&session, 0, &self.path, region).await?;
+        }
+
+        result
+    }
+}
+
+/// Returns the address ranges of the executable sections of an ELF file.
+fn code_ranges(path: &Path) -> anyhow::Result<Vec<Range<u64>>> {
+    let elf = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let file = object::File::parse(elf.as_slice()).context("Failed to parse the ELF file")?;
+
+    Ok(file
+        .sections()
+        .filter(|section| section.kind() == SectionKind::Text && section.size() > 0)
+        .map(|section| section.address()..section.address() + section.size())
+        .collect())
+}
+
+/// Maps the code executed during the run to source lines, and writes the coverage reports.
+async fn write_coverage(
+    session: &SessionInterface,
+    path: &Path,
+    options: &CoverageOptions,
+) -> anyhow::Result<()> {
+    let coverage = session.take_coverage().await?;
+    let debug_info = DebugInfo::from_file(path)
+        .with_context(|| format!("Failed to read the debug information of {}", path.display()))?;
+
+    let mut map = CoverageMap::new(&debug_info);
+    for sample in coverage.samples {
+        map.add_sample(sample.address, sample.count);
+    }
+    for executed in coverage.ranges {
+        map.add_range(executed.range, executed.count);
+    }
+
+    let mut report = map.report();
+    // Report the sources of the project relative to it, as CI services expect.
+    if let Ok(current_dir) = std::env::current_dir() {
+        for file in &mut report.files {
+            if let Ok(relative) = Path::new(&file.path).strip_prefix(&current_dir) {
+                file.path = relative.display().to_string();
+            }
         }
     }
+
+    if let Some(lcov) = &options.coverage_lcov {
+        let mut writer = BufWriter::new(
+            File::create(lcov).with_context(|| format!("Failed to create {}", lcov.display()))?,
+        );
+        report.write_lcov(&mut writer)?;
+        writer.flush()?;
+    }
+    if let Some(cobertura) = &options.coverage_cobertura {
+        let mut writer = BufWriter::new(
+            File::create(cobertura)
+                .with_context(|| format!("Failed to create {}", cobertura.display()))?,
+        );
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        report.write_cobertura(&mut writer, timestamp)?;
+        writer.flush()?;
+    }
+
+    let (hit, found) = (report.lines_hit(), report.lines_found());
+    println!(
+        "Line coverage: {hit} of {found} lines ({:.1}%)",
+        if found == 0 {
+            0.0
+        } else {
+            hit as f64 * 100.0 / found as f64
+        }
+    );
+
+    Ok(())
 }
 
 #[derive(PartialEq)]
@@ -0,0 +1,494 @@
+mod analysis;
+mod call_graph;
+mod frames;
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use object::{Object, ObjectSection};
+
+use analysis::{Analysis, Depth, Frame, Node};
+use call_graph::Function;
+use frames::CfaFrame;
+
+/// The names of the Cortex-M exceptions, by their number in the vector table.
+const EXCEPTION_NAMES: &[(usize, &str)] = &[
+    (1, "Reset"),
+    (2, "NMI"),
+    (3, "HardFault"),
+    (4, "MemManage"),
+    (5, "BusFault"),
+    (6, "UsageFault"),
+    (7, "SecureFault"),
+    (11, "SVCall"),
+    (12, "DebugMonitor"),
+    (14, "PendSV"),
+    (15, "SysTick"),
+];
+
+/// The bytes a Cortex-M core stacks when it takes an exception, without the floating-point
+/// context.
+const EXCEPTION_FRAME_SIZE: u64 = 32;
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    /// The ELF file to analyse.
+    path: PathBuf,
+}
+
+impl Cmd {
+    pub fn run(self) -> anyhow::Result<()> {
+        let elf = std::fs::read(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        let report = StackUsageReport::new(&elf)?;
+
+        print!("{}", report.format());
+
+        Ok(())
+    }
+}
+
+/// Where the size of the frames of the functions comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameSource {
+    StackSizes,
+    DebugFrame,
+    Disassembly,
+}
+
+/// A function the program starts executing in: the entry point, or an exception or interrupt
+/// handler.
+struct EntryPoint {
+    function: usize,
+    name: String,
+    /// The stack used before the function starts, by the core taking an exception.
+    exception_frame: u64,
+}
+
+/// The worst-case stack usage of the entry points of a program, and what limits the analysis.
+struct StackUsageReport {
+    functions: Vec<Function>,
+    frames: Vec<(Frame, FrameSource)>,
+    analysis: Analysis,
+    entry_points: Vec<EntryPoint>,
+    /// The functions that are not called directly, and are not entry points.
+    uncalled: Vec<usize>,
+    /// The calls that could not be resolved to a function, by the function making them.
+    unresolved_calls: Vec<(usize, call_graph::Call)>,
+}
+
+impl StackUsageReport {
+    fn new(elf: &[u8]) -> anyhow::Result<Self> {
+        let file = object::File::parse(elf)?;
+        let functions = call_graph::functions(&file)?;
+        let stack_sizes = frames::stack_sizes(&file)?;
+        let cfa_frames = frames::cfa_frames(&file)?;
+
+        let frames = functions
+            .iter()
+            .map(|function| frame(function, stack_sizes.as_ref(), &cfa_frames))
+            .collect::<Vec<_>>();
+
+        let mut callers = vec![0usize; functions.len()];
+        let mut unresolved_calls = Vec::new();
+        let mut nodes = Vec::with_capacity(functions.len());
+        for (index, (function, (frame, _))) in functions.iter().zip(&frames).enumerate() {
+            let mut callees = Vec::new();
+            for call in &function.calls {
+                match call
+                    .target
+                    .and_then(|target| function_at(&functions, target))
+                {
+                    Some(callee) => callees.push(callee),
+                    None => unresolved_calls.push((index, *call)),
+                }
+            }
+            callees.sort_unstable();
+            callees.dedup();
+            for &callee in &callees {
+                callers[callee] += 1;
+            }
+
+            nodes.push(Node {
+                frame: *frame,
+                callees,
+                unresolved_calls: unresolved_calls
+                    .last()
+                    .is_some_and(|(last, _)| *last == index),
+            });
+        }
+        let analysis = Analysis::new(&nodes);
+
+        let entry_points = entry_points(&file, &functions)?;
+        let uncalled = (0..functions.len())
+            .filter(|&function| {
+                callers[function] == 0
+                    && !entry_points
+                        .iter()
+                        .any(|entry_point| entry_point.function == function)
+            })
+            .collect();
+
+        Ok(Self {
+            functions,
+            frames,
+            analysis,
+            entry_points,
+            uncalled,
+            unresolved_calls,
+        })
+    }
+
+    fn format(&self) -> String {
+        let mut message = String::new();
+        let mut lower_bounds = false;
+
+        let mut entry_points = self
+            .entry_points
+            .iter()
+            .map(|entry_point| {
+                let depth = match self.analysis.depth(entry_point.function) {
+                    Depth::Bounded { bytes, lower_bound } => Depth::Bounded {
+                        bytes: bytes + entry_point.exception_frame,
+                        lower_bound,
+                    },
+                    Depth::Unbounded => Depth::Unbounded,
+                };
+                (entry_point, depth)
+            })
+            .collect::<Vec<_>>();
+        entry_points.sort_by_key(|(_, depth)| std::cmp::Reverse(sort_key(*depth)));
+
+        message.push_str("Entry points and exception handlers:\n");
+        if entry_points.is_empty() {
+            message.push_str("    None found.\n");
+        }
+        for (entry_point, depth) in entry_points {
+            lower_bounds |= matches!(
+                depth,
+                Depth::Bounded {
+                    lower_bound: true,
+                    ..
+                }
+            );
+            #[allow(clippy::unwrap_used, reason = "Writing to a string is infallible")]
+            writeln!(
+                &mut message,
+                "{:>16}  {}: {}",
+                format_depth(depth),
+                entry_point.name,
+                self.functions[entry_point.function].name
+            )
+            .unwrap();
+
+            if entry_point.exception_frame > 0 {
+                #[allow(clippy::unwrap_used, reason = "Writing to a string is infallible")]
+                writeln!(
+                    &mut message,
+                    "{:>26}  (exception frame)",
+                    entry_point.exception_frame
+                )
+                .unwrap();
+            }
+            for function in self.analysis.deepest_path(entry_point.function) {
+                let (frame, _) = self.frames[function];
+                #[allow(clippy::unwrap_used, reason = "Writing to a string is infallible")]
+                writeln!(
+                    &mut message,
+                    "{:>26}  {}",
+                    format!(
+                        "{}{}",
+                        if frame.lower_bound { ">=" } else { "" },
+                        frame.bytes
+                    ),
+                    self.functions[function].name
+                )
+                .unwrap();
+            }
+        }
+
+        let mut uncalled = self
+            .uncalled
+            .iter()
+            .map(|&function| (function, self.analysis.depth(function)))
+            .collect::<Vec<_>>();
+        uncalled.sort_by_key(|&(function, depth)| (std::cmp::Reverse(sort_key(depth)), function));
+        if !uncalled.is_empty() {
+            message.push_str("\nFunctions that are not called directly:\n");
+        }
+        for (function, depth) in uncalled {
+            lower_bounds |= matches!(
+                depth,
+                Depth::Bounded {
+                    lower_bound: true,
+                    ..
+                }
+            );
+            #[allow(clippy::unwrap_used, reason = "Writing to a string is infallible")]
+            writeln!(
+                &mut message,
+                "{:>16}  {}",
+                format_depth(depth),
+                self.functions[function].name
+            )
+            .unwrap();
+        }
+
+        if !self.analysis.cycles.is_empty() {
+            message.push_str("\nRecursion, the stack usage of these functions is unbounded:\n");
+        }
+        for cycle in &self.analysis.cycles {
+            let names = cycle
+                .iter()
+                .map(|&function| self.functions[function].name.as_str())
+                .collect::<Vec<_>>();
+            #[allow(clippy::unwrap_used, reason = "Writing to a string is infallible")]
+            writeln!(&mut message, "    {}", names.join(", ")).unwrap();
+        }
+
+        if !self.unresolved_calls.is_empty() {
+            message.push_str("\nCalls that could not be resolved:\n");
+        }
+        for (function, call) in &self.unresolved_calls {
+            let name = &self.functions[*function].name;
+            #[allow(clippy::unwrap_used, reason = "Writing to a string is infallible")]
+            match call.target {
+                Some(target) => writeln!(
+                    &mut message,
+                    "    {name} at {:#010x}, to {target:#010x}, which is not in a function",
+                    call.address
+                ),
+                None => writeln!(
+                    &mut message,
+                    "    {name} at {:#010x}, through a register",
+                    call.address
+                ),
+            }
+            .unwrap();
+        }
+
+        let estimated = self
+            .frames
+            .iter()
+            .filter(|(_, source)| *source == FrameSource::Disassembly)
+            .count();
+        if estimated > 0 {
+            #[allow(clippy::unwrap_used, reason = "Writing to a string is infallible")]
+            writeln!(
+                &mut message,
+                "\nThe frames of {estimated} functions are estimated from their code, because \
+                 neither .stack_sizes nor .debug_frame describe them."
+            )
+            .unwrap();
+        }
+        if lower_bounds {
+            message.push_str(
+                "\n`>=` marks a lower bound, because of calls that could not be resolved or \
+                 frames of unknown size.\n",
+            );
+        }
+
+        message
+    }
+}
+
+/// The stack used by the frame of `function`, from `.stack_sizes` if it has the function, from
+/// the CFA rules in `.debug_frame`, or estimated from the disassembly.
+fn frame(
+    function: &Function,
+    stack_sizes: Option<&HashMap<u64, u64>>,
+    cfa_frames: &HashMap<u64, CfaFrame>,
+) -> (Frame, FrameSource) {
+    let allocated_from = |start: u64| {
+        function
+            .allocations
+            .iter()
+            .filter(|allocation| allocation.address >= start)
+            .map(|allocation| allocation.bytes)
+            .sum::<u64>()
+    };
+
+    if let Some(&bytes) = stack_sizes.and_then(|sizes| sizes.get(&function.range.start)) {
+        let frame = Frame {
+            bytes,
+            lower_bound: function.dynamic_allocation,
+        };
+        return (frame, FrameSource::StackSizes);
+    }
+
+    if let Some(cfa) = cfa_frames.get(&function.range.start) {
+        // Once the CFA is given relative to the frame pointer, the stack allocated after that
+        // is only known from the code.
+        let bytes = cfa.sp_offset + cfa.frame_pointer_from.map_or(0, allocated_from);
+        let frame = Frame {
+            bytes,
+            lower_bound: function.dynamic_allocation,
+        };
+        return (frame, FrameSource::DebugFrame);
+    }
+
+    let frame = Frame {
+        bytes: allocated_from(function.range.start),
+        lower_bound: true,
+    };
+    (frame, FrameSource::Disassembly)
+}
+
+/// The index of the function that contains `address`.
+fn function_at(functions: &[Function], address: u64) -> Option<usize> {
+    let index = functions.partition_point(|function| function.range.start <= address);
+    index
+        .checked_sub(1)
+        .filter(|&index| functions[index].range.contains(&address))
+}
+
+/// Finds the entry point of the ELF file, and the exception and interrupt handlers in the
+/// vector table of a Cortex-M program.
+fn entry_points(file: &object::File, functions: &[Function]) -> anyhow::Result<Vec<EntryPoint>> {
+    let mut entry_points: Vec<EntryPoint> = Vec::new();
+
+    let vector_table = file
+        .section_by_name(".vector_table")
+        .or_else(|| file.section_by_name(".isr_vector"));
+    if let Some(vector_table) = vector_table.filter(|_| !file.is_64()) {
+        let data = vector_table
+            .data()
+            .context("Failed to read the vector table")?;
+        // The first word is the initial stack pointer.
+        for (number, vector) in data.chunks_exact(4).enumerate().skip(1) {
+            let mut bytes = [0; 4];
+            bytes.copy_from_slice(vector);
+            let vector = if file.is_little_endian() {
+                u32::from_le_bytes(bytes)
+            } else {
+                u32::from_be_bytes(bytes)
+            };
+            let Some(function) = function_at(functions, u64::from(vector & !1)) else {
+                continue;
+            };
+            if entry_points
+                .iter()
+                .any(|entry_point| entry_point.function == function)
+            {
+                continue;
+            }
+
+            let name = match EXCEPTION_NAMES.iter().find(|(n, _)| *n == number) {
+                Some((_, name)) => name.to_string(),
+                None if number >= 16 => format!("Interrupt {}", number - 16),
+                None => format!("Exception {number}"),
+            };
+            entry_points.push(EntryPoint {
+                function,
+                name,
+                exception_frame: if number == 1 { 0 } else { EXCEPTION_FRAME_SIZE },
+            });
+        }
+    }
+
+    let entry = file.entry() & frames::function_address_mask(file);
+    if let Some(function) = function_at(functions, entry)
+        && !entry_points
+            .iter()
+            .any(|entry_point| entry_point.function == function)
+    {
+        entry_points.insert(
+            0,
+            EntryPoint {
+                function,
+                name: "Entry point".to_string(),
+                exception_frame: 0,
+            },
+        );
+    }
+
+    Ok(entry_points)
+}
+
+/// Orders depths from the shallowest to the deepest, with unbounded ones last.
+fn sort_key(depth: Depth) -> (bool, u64) {
+    match depth {
+        Depth::Bounded { bytes, .. } => (false, bytes),
+        Depth::Unbounded => (true, 0),
+    }
+}
+
+fn format_depth(depth: Depth) -> String {
+    match depth {
+        Depth::Bounded {
+            bytes,
+            lower_bound: false,
+        } => format!("{bytes} bytes"),
+        Depth::Bounded {
+            bytes,
+            lower_bound: true,
+        } => format!(">= {bytes} bytes"),
+        Depth::Unbounded => "unbounded".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stack_usage_of_a_cortex_m_program() {
+        let elf = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../probe-rs-debug/tests/debug-unwind-tests/nRF52833_xxAA_full_unwind.elf"
+        ))
+        .unwrap();
+        let report = StackUsageReport::new(&elf).unwrap();
+
+        let entry_point = |name: &str| {
+            report
+                .entry_points
+                .iter()
+                .find(|entry_point| entry_point.name == name)
+                .unwrap_or_else(|| panic!("{name} not found"))
+        };
+        let reset = entry_point("Reset");
+        assert_eq!(report.functions[reset.function].name, "Reset");
+        assert_eq!(entry_point("SysTick").exception_frame, EXCEPTION_FRAME_SIZE);
+
+        // `__cortex_m_rt_main` pushes 8 bytes, and allocates 0x90 more after setting up the
+        // frame pointer.
+        let rt_main = report
+            .functions
+            .iter()
+            .position(|function| function.name == "nRF52833_xxAA::__cortex_m_rt_main")
+            .unwrap();
+        assert_eq!(
+            report.frames[rt_main],
+            (
+                Frame {
+                    bytes: 0x98,
+                    lower_bound: false,
+                },
+                FrameSource::DebugFrame
+            )
+        );
+
+        // The panic handler prints over RTT, which can panic again.
+        assert_eq!(report.analysis.depth(reset.function), Depth::Unbounded);
+        let path = report.analysis.deepest_path(reset.function);
+        assert!(path.contains(&rt_main));
+
+        // `SysTick` calls the handler defined with `#[exception]`, both push r7 and lr.
+        let systick = entry_point("SysTick");
+        assert_eq!(
+            report.analysis.depth(systick.function),
+            Depth::Bounded {
+                bytes: 16,
+                lower_bound: false,
+            }
+        );
+        assert!(
+            report
+                .format()
+                .contains("        48 bytes  SysTick: SysTick\n")
+        );
+    }
+}
@@ -0,0 +1,265 @@
+//! The worst-case stack depth of the functions in a call graph.
+
+/// The stack used by the frame of a function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Frame {
+    pub bytes: u64,
+    /// Whether `bytes` is a lower bound, because the size of the frame is not fully known.
+    pub lower_bound: bool,
+}
+
+/// A function in the call graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Node {
+    pub frame: Frame,
+    /// The functions this one calls.
+    pub callees: Vec<usize>,
+    /// Whether the function makes calls that could not be resolved to a function.
+    pub unresolved_calls: bool,
+}
+
+/// The most stack a function and the functions it calls can use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Depth {
+    Bounded {
+        bytes: u64,
+        /// Whether `bytes` is a lower bound, because of calls that could not be resolved or
+        /// frames of unknown size.
+        lower_bound: bool,
+    },
+    /// The function can recurse, or call a function that does.
+    Unbounded,
+}
+
+/// The worst-case stack depth of every function in a call graph.
+pub(crate) struct Analysis {
+    depths: Vec<Depth>,
+    /// The callee on the deepest path from each function.
+    deepest_callees: Vec<Option<usize>>,
+    /// The groups of functions that call each other recursively.
+    pub cycles: Vec<Vec<usize>>,
+}
+
+impl Analysis {
+    pub(crate) fn new(nodes: &[Node]) -> Self {
+        let cycles = recursive_components(nodes);
+        let mut recursive = vec![false; nodes.len()];
+        for &function in cycles.iter().flatten() {
+            recursive[function] = true;
+        }
+
+        let mut analysis = Self {
+            depths: Vec::with_capacity(nodes.len()),
+            deepest_callees: vec![None; nodes.len()],
+            cycles,
+        };
+        let mut depths = vec![None; nodes.len()];
+        for function in 0..nodes.len() {
+            analysis.compute(nodes, &recursive, &mut depths, function);
+        }
+        analysis.depths = depths.into_iter().flatten().collect();
+
+        analysis
+    }
+
+    /// The worst-case depth of `function`.
+    pub(crate) fn depth(&self, function: usize) -> Depth {
+        self.depths[function]
+    }
+
+    /// The functions on the deepest path of calls from `function`, starting with it.
+    pub(crate) fn deepest_path(&self, function: usize) -> Vec<usize> {
+        let mut path = vec![function];
+        let mut current = function;
+        while let Some(callee) = self.deepest_callees[current] {
+            path.push(callee);
+            current = callee;
+        }
+        path
+    }
+
+    fn compute(
+        &mut self,
+        nodes: &[Node],
+        recursive: &[bool],
+        depths: &mut [Option<Depth>],
+        function: usize,
+    ) -> Depth {
+        if let Some(depth) = depths[function] {
+            return depth;
+        }
+        if recursive[function] {
+            depths[function] = Some(Depth::Unbounded);
+            return Depth::Unbounded;
+        }
+
+        // The functions that are not recursive form an acyclic graph, so this terminates.
+        let node = &nodes[function];
+        let mut deepest: Option<(usize, u64)> = None;
+        let mut lower_bound = node.frame.lower_bound || node.unresolved_calls;
+        let mut depth = None;
+        for &callee in &node.callees {
+            match self.compute(nodes, recursive, depths, callee) {
+                Depth::Unbounded => {
+                    depth = Some(Depth::Unbounded);
+                    self.deepest_callees[function] = Some(callee);
+                    break;
+                }
+                Depth::Bounded {
+                    bytes,
+                    lower_bound: callee_lower_bound,
+                } => {
+                    lower_bound |= callee_lower_bound;
+                    if deepest.is_none_or(|(_, deepest)| bytes > deepest) {
+                        deepest = Some((callee, bytes));
+                    }
+                }
+            }
+        }
+
+        let depth = depth.unwrap_or_else(|| {
+            self.deepest_callees[function] = deepest.map(|(callee, _)| callee);
+            Depth::Bounded {
+                bytes: node.frame.bytes + deepest.map_or(0, |(_, bytes)| bytes),
+                lower_bound,
+            }
+        });
+        depths[function] = Some(depth);
+        depth
+    }
+}
+
+/// Finds the strongly connected components of the call graph that contain a cycle, with
+/// Tarjan's algorithm.
+fn recursive_components(nodes: &[Node]) -> Vec<Vec<usize>> {
+    struct State<'a> {
+        nodes: &'a [Node],
+        index: Vec<Option<usize>>,
+        low_link: Vec<usize>,
+        on_stack: Vec<bool>,
+        stack: Vec<usize>,
+        next_index: usize,
+        components: Vec<Vec<usize>>,
+    }
+
+    fn visit(state: &mut State, function: usize) {
+        state.index[function] = Some(state.next_index);
+        state.low_link[function] = state.next_index;
+        state.next_index += 1;
+        state.stack.push(function);
+        state.on_stack[function] = true;
+
+        for &callee in &state.nodes[function].callees {
+            match state.index[callee] {
+                None => {
+                    visit(state, callee);
+                    state.low_link[function] = state.low_link[function].min(state.low_link[callee]);
+                }
+                Some(index) if state.on_stack[callee] => {
+                    state.low_link[function] = state.low_link[function].min(index);
+                }
+                Some(_) => {}
+            }
+        }
+
+        if Some(state.low_link[function]) == state.index[function] {
+            let mut component = Vec::new();
+            while let Some(member) = state.stack.pop() {
+                state.on_stack[member] = false;
+                component.push(member);
+                if member == function {
+                    break;
+                }
+            }
+            if component.len() > 1 || state.nodes[function].callees.contains(&function) {
+                component.sort_unstable();
+                state.components.push(component);
+            }
+        }
+    }
+
+    let mut state = State {
+        nodes,
+        index: vec![None; nodes.len()],
+        low_link: vec![0; nodes.len()],
+        on_stack: vec![false; nodes.len()],
+        stack: Vec::new(),
+        next_index: 0,
+        components: Vec::new(),
+    };
+    for function in 0..nodes.len() {
+        if state.index[function].is_none() {
+            visit(&mut state, function);
+        }
+    }
+
+    state.components.sort();
+    state.components
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn node(bytes: u64, callees: &[usize]) -> Node {
+        Node {
+            frame: Frame {
+                bytes,
+                lower_bound: false,
+            },
+            callees: callees.to_vec(),
+            unresolved_calls: false,
+        }
+    }
+
+    #[test]
+    fn depth_of_the_deepest_path() {
+        let mut nodes = vec![
+            node(8, &[1, 2]),
+            node(16, &[3]),
+            node(32, &[]),
+            node(24, &[]),
+        ];
+        nodes[3].unresolved_calls = true;
+
+        let analysis = Analysis::new(&nodes);
+        assert_eq!(
+            analysis.depth(0),
+            Depth::Bounded {
+                bytes: 48,
+                lower_bound: true,
+            }
+        );
+        assert_eq!(
+            analysis.depth(2),
+            Depth::Bounded {
+                bytes: 32,
+                lower_bound: false,
+            }
+        );
+        assert_eq!(analysis.deepest_path(0), [0, 1, 3]);
+        assert!(analysis.cycles.is_empty());
+    }
+
+    #[test]
+    fn recursion_is_unbounded() {
+        let nodes = vec![
+            node(8, &[1, 3]),
+            node(16, &[2]),
+            node(16, &[1]),
+            node(4, &[3]),
+            node(4, &[]),
+        ];
+
+        let analysis = Analysis::new(&nodes);
+        assert_eq!(analysis.cycles, [vec![1, 2], vec![3]]);
+        assert_eq!(analysis.depth(0), Depth::Unbounded);
+        assert_eq!(
+            analysis.depth(4),
+            Depth::Bounded {
+                bytes: 4,
+                lower_bound: false,
+            }
+        );
+    }
+}
@@ -0,0 +1,600 @@
+//! The functions of a program, and the calls between them, from the disassembly of the code.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use anyhow::{Context, anyhow, bail};
+use capstone::arch::{ArchOperand, arm::ArmOperandType, arm64::Arm64OperandType};
+use capstone::{Capstone, InsnDetail, InsnGroupId, InsnGroupType};
+use object::{Architecture, Object, ObjectSection, ObjectSymbol, SectionKind, SymbolKind};
+use probe_rs::{CoreType, InstructionSet};
+
+use crate::cmd::dap_server::debug_adapter::dap::request_helpers::get_capstone_le;
+
+/// A function of the program.
+#[derive(Debug)]
+pub(crate) struct Function {
+    /// The demangled name of the function.
+    pub name: String,
+    /// The code of the function.
+    pub range: Range<u64>,
+    /// The calls the function makes.
+    pub calls: Vec<Call>,
+    /// The stack the function allocates, in the order of its instructions.
+    pub allocations: Vec<Allocation>,
+    /// Whether the function allocates stack of a size only known at runtime.
+    pub dynamic_allocation: bool,
+}
+
+/// A call from a function to another one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Call {
+    /// The address of the call instruction.
+    pub address: u64,
+    /// The address called, or `None` for a call through a register.
+    pub target: Option<u64>,
+}
+
+/// Stack allocated by an instruction, by pushing registers or decrementing the stack pointer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Allocation {
+    /// The address of the instruction.
+    pub address: u64,
+    /// The bytes allocated.
+    pub bytes: u64,
+}
+
+/// What an instruction does, as far as the call graph and the stack are concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Effect {
+    /// A call to an address, or through a register.
+    Call(Option<u64>),
+    /// A jump to an address, or through a register. Jumps out of the function are tail calls.
+    Jump(Option<u64>),
+    /// Stack allocated with a size known at compile time.
+    Allocate(u64),
+    /// Stack allocated with a size only known at runtime.
+    DynamicAllocation,
+    None,
+}
+
+/// The operand of an instruction, with the names of its registers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Operand {
+    Register(String),
+    Immediate(i64),
+    Memory { base: String, displacement: i64 },
+    Other,
+}
+
+/// Finds the functions in the symbols of `file`, and disassembles them.
+pub(crate) fn functions(file: &object::File) -> anyhow::Result<Vec<Function>> {
+    let architecture = file.architecture();
+    let disassembler = Disassembler::new(architecture)?;
+    let code_mappings = arm_code_mappings(file);
+
+    let mut symbols = file
+        .symbols()
+        .filter_map(|symbol| {
+            let name = symbol.name().ok()?;
+            let section_index = symbol.section_index()?;
+            // Skip the mapping symbols of ARM, like `$t` and `$d`.
+            if name.is_empty() || name.starts_with('$') {
+                return None;
+            }
+            // Functions defined in assembly, like trap handlers, may be global labels without
+            // a type.
+            let label = symbol.kind() == SymbolKind::Unknown
+                && !symbol.is_local()
+                && file.section_by_index(section_index).ok()?.kind() == SectionKind::Text;
+            if symbol.kind() != SymbolKind::Text && !label {
+                return None;
+            }
+            Some((symbol.address(), symbol.size(), section_index, name))
+        })
+        .collect::<Vec<_>>();
+    // Keep one symbol for each address, preferring the one with a size.
+    symbols.sort_by_key(|&(address, size, _, _)| (address & !1, std::cmp::Reverse(size)));
+    symbols.dedup_by_key(|(address, _, _, _)| *address & !1);
+
+    let mut functions = Vec::with_capacity(symbols.len());
+    for (index, &(address, size, section_index, name)) in symbols.iter().enumerate() {
+        let section = file.section_by_index(section_index)?;
+        let start = address & !1;
+        let section_end = section.address() + section.size();
+        // Symbols defined in assembly may have no size, they end where the next one starts.
+        let end = if size > 0 {
+            start + size
+        } else {
+            symbols
+                .get(index + 1)
+                .map(|&(next, _, _, _)| next & !1)
+                .filter(|&next| next <= section_end)
+                .unwrap_or(section_end)
+        };
+        if start < section.address() || end > section_end || start >= end {
+            continue;
+        }
+
+        let data = section
+            .data()
+            .with_context(|| format!("Failed to read the code of {name}"))?;
+        let code = &data[(start - section.address()) as usize..(end - section.address()) as usize];
+        let thumb =
+            architecture == Architecture::Arm && is_thumb(&code_mappings, section_index.0, address);
+
+        let mut function = Function {
+            name: addr2line::demangle_auto(name.into(), None).into_owned(),
+            range: start..end,
+            calls: Vec::new(),
+            allocations: Vec::new(),
+            dynamic_allocation: false,
+        };
+        disassembler.disassemble(&mut function, code, thumb)?;
+        functions.push(function);
+    }
+
+    Ok(functions)
+}
+
+/// The ARM mapping symbols `$t` and `$a`, which mark the start of Thumb and A32 code, by section
+/// and address. The value is `true` for Thumb code.
+fn arm_code_mappings(file: &object::File) -> BTreeMap<(usize, u64), bool> {
+    if file.architecture() != Architecture::Arm {
+        return BTreeMap::new();
+    }
+
+    file.symbols()
+        .filter_map(|symbol| {
+            let name = symbol.name().ok()?;
+            let thumb = match name.split_once('.').map_or(name, |(prefix, _)| prefix) {
+                "$t" => true,
+                "$a" => false,
+                _ => return None,
+            };
+            Some(((symbol.section_index()?.0, symbol.address()), thumb))
+        })
+        .collect()
+}
+
+/// Whether the ARM code at `address` is Thumb code.
+///
+/// The mapping symbol before the code decides, because labels defined in assembly don't have
+/// the Thumb bit set in their address. Without mapping symbols, only the Thumb bit is left.
+fn is_thumb(
+    code_mappings: &BTreeMap<(usize, u64), bool>,
+    section_index: usize,
+    address: u64,
+) -> bool {
+    code_mappings
+        .range((section_index, 0)..=(section_index, address & !1))
+        .next_back()
+        .map_or(address & 1 == 1, |(_, &thumb)| thumb)
+}
+
+/// Disassembles functions, and finds the effects of their instructions.
+struct Disassembler {
+    architecture: Architecture,
+    capstone: Capstone,
+    /// The disassembler for Thumb code, on ARM.
+    thumb: Option<Capstone>,
+}
+
+impl Disassembler {
+    fn new(architecture: Architecture) -> anyhow::Result<Self> {
+        let (instruction_set, thumb) = match architecture {
+            Architecture::Arm => (InstructionSet::A32, Some(InstructionSet::Thumb2)),
+            Architecture::Aarch64 => (InstructionSet::A64, None),
+            Architecture::Riscv32 => (InstructionSet::RV32C, None),
+            Architecture::Riscv64 => (InstructionSet::RV64C, None),
+            other => bail!("Stack usage analysis is not supported for {other:?}"),
+        };
+        let build = |instruction_set| -> anyhow::Result<Capstone> {
+            // Armv8-M decodes the instructions of all Cortex-M cores.
+            let mut capstone = get_capstone_le(instruction_set, CoreType::Armv8m)
+                .map_err(|error| anyhow!("Failed to create the disassembler: {error}"))?;
+            capstone
+                .set_detail(true)
+                .map_err(|error| anyhow!("Failed to create the disassembler: {error}"))?;
+            Ok(capstone)
+        };
+
+        Ok(Self {
+            architecture,
+            capstone: build(instruction_set)?,
+            thumb: thumb.map(build).transpose()?,
+        })
+    }
+
+    fn disassemble(&self, function: &mut Function, code: &[u8], thumb: bool) -> anyhow::Result<()> {
+        let capstone = match &self.thumb {
+            Some(capstone) if thumb => capstone,
+            _ => &self.capstone,
+        };
+        let instructions = capstone
+            .disasm_all(code, function.range.start)
+            .map_err(|error| anyhow!("Failed to disassemble {}: {error}", function.name))?;
+
+        // The register and the address set by the last `auipc` or `lui` on RISC-V, which the
+        // next instruction may jump relative to.
+        let mut upper_immediate = None;
+        for instruction in instructions.iter() {
+            let Ok(detail) = capstone.insn_detail(instruction) else {
+                // Data skipped in the code has no details.
+                upper_immediate = None;
+                continue;
+            };
+            let address = instruction.address();
+            let mnemonic = instruction.mnemonic().unwrap_or_default();
+            let operands = operands(capstone, &detail);
+
+            let effect = match self.architecture {
+                Architecture::Arm => arm_effect(mnemonic, &operands, &detail),
+                Architecture::Aarch64 => aarch64_effect(mnemonic, &operands, &detail),
+                _ => {
+                    let effect = riscv_effect(mnemonic, address, &operands, upper_immediate);
+                    upper_immediate = match (mnemonic, operands.as_slice()) {
+                        (
+                            "auipc" | "lui",
+                            [Operand::Register(register), Operand::Immediate(immediate)],
+                        ) => {
+                            // The immediate is the upper 20 bits, sign-extended.
+                            let upper = ((*immediate << 44) >> 44) << 12;
+                            let value = if mnemonic == "auipc" {
+                                address.wrapping_add_signed(upper)
+                            } else {
+                                upper as u64
+                            };
+                            Some((register.clone(), value))
+                        }
+                        _ => None,
+                    };
+                    effect
+                }
+            };
+
+            match effect {
+                Effect::Call(target) => function.calls.push(Call { address, target }),
+                Effect::Jump(target) => {
+                    // Jumps within the function are branches, and not calls.
+                    if !target.is_some_and(|target| function.range.contains(&target)) {
+                        function.calls.push(Call { address, target });
+                    }
+                }
+                Effect::Allocate(bytes) => function.allocations.push(Allocation { address, bytes }),
+                Effect::DynamicAllocation => function.dynamic_allocation = true,
+                Effect::None => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn operands(capstone: &Capstone, detail: &InsnDetail) -> Vec<Operand> {
+    let register = |register| capstone.reg_name(register).unwrap_or_default();
+    detail
+        .arch_detail()
+        .operands()
+        .into_iter()
+        .map(|operand| match operand {
+            ArchOperand::ArmOperand(operand) => match operand.op_type {
+                ArmOperandType::Reg(id) => Operand::Register(register(id)),
+                ArmOperandType::Imm(immediate) => Operand::Immediate(immediate.into()),
+                ArmOperandType::Mem(memory) => Operand::Memory {
+                    base: register(memory.base()),
+                    displacement: memory.disp().into(),
+                },
+                _ => Operand::Other,
+            },
+            ArchOperand::Arm64Operand(operand) => match operand.op_type {
+                Arm64OperandType::Reg(id) => Operand::Register(register(id)),
+                Arm64OperandType::Imm(immediate) => Operand::Immediate(immediate),
+                Arm64OperandType::Mem(memory) => Operand::Memory {
+                    base: register(memory.base()),
+                    displacement: memory.disp().into(),
+                },
+                _ => Operand::Other,
+            },
+            ArchOperand::RiscVOperand(operand) => match operand {
+                capstone::arch::riscv::RiscVOperand::Reg(id) => Operand::Register(register(id)),
+                capstone::arch::riscv::RiscVOperand::Imm(immediate) => {
+                    Operand::Immediate(immediate)
+                }
+                capstone::arch::riscv::RiscVOperand::Mem(memory) => Operand::Memory {
+                    base: register(memory.base()),
+                    displacement: memory.disp(),
+                },
+                _ => Operand::Other,
+            },
+        })
+        .collect()
+}
+
+fn in_group(detail: &InsnDetail, group: InsnGroupType::Type) -> bool {
+    detail.groups().contains(&InsnGroupId(group as u8))
+}
+
+/// The effect of an A32 or Thumb instruction.
+fn arm_effect(mnemonic: &str, operands: &[Operand], detail: &InsnDetail) -> Effect {
+    if in_group(detail, InsnGroupType::CS_GRP_CALL) {
+        return match operands.last() {
+            Some(Operand::Immediate(target)) => Effect::Call(Some(*target as u32 as u64 & !1)),
+            _ => Effect::Call(None),
+        };
+    }
+    if in_group(detail, InsnGroupType::CS_GRP_JUMP) {
+        return match operands.last() {
+            Some(Operand::Immediate(target)) => Effect::Jump(Some(*target as u32 as u64)),
+            // `bx lr` returns.
+            Some(Operand::Register(register)) if register != "lr" => Effect::Jump(None),
+            // `tbb` and `tbh` jump within the function.
+            _ => Effect::None,
+        };
+    }
+
+    match (mnemonic, operands) {
+        ("push" | "push.w" | "vpush", registers) => Effect::Allocate(
+            registers
+                .iter()
+                .map(|register| match register {
+                    Operand::Register(name) if name.starts_with('q') => 16,
+                    Operand::Register(name) if name.starts_with('d') => 8,
+                    _ => 4,
+                })
+                .sum(),
+        ),
+        ("sub" | "sub.w" | "subw" | "subs", [Operand::Register(sp), rest @ ..]) if sp == "sp" => {
+            match rest {
+                [Operand::Immediate(bytes)] => Effect::Allocate(*bytes as u64),
+                [Operand::Register(base), Operand::Immediate(bytes)] if base == "sp" => {
+                    Effect::Allocate(*bytes as u64)
+                }
+                [Operand::Register(_)] => Effect::DynamicAllocation,
+                [Operand::Register(base), Operand::Register(_)] if base == "sp" => {
+                    Effect::DynamicAllocation
+                }
+                // `sub sp, r7, #8` restores the stack pointer from the frame pointer.
+                _ => Effect::None,
+            }
+        }
+        // `mov sp, r7` restores the stack pointer from the frame pointer, other registers hold
+        // a stack pointer computed at runtime.
+        ("mov" | "mov.w", [Operand::Register(sp), Operand::Register(source)])
+            if sp == "sp" && source != "r7" && source != "fp" =>
+        {
+            Effect::DynamicAllocation
+        }
+        _ => Effect::None,
+    }
+}
+
+/// The effect of an A64 instruction.
+fn aarch64_effect(mnemonic: &str, operands: &[Operand], detail: &InsnDetail) -> Effect {
+    if in_group(detail, InsnGroupType::CS_GRP_RET) {
+        return Effect::None;
+    }
+    if in_group(detail, InsnGroupType::CS_GRP_CALL) {
+        return match operands.last() {
+            Some(Operand::Immediate(target)) => Effect::Call(Some(*target as u64)),
+            _ => Effect::Call(None),
+        };
+    }
+    if in_group(detail, InsnGroupType::CS_GRP_JUMP) {
+        return match operands.last() {
+            Some(Operand::Immediate(target)) => Effect::Jump(Some(*target as u64)),
+            Some(Operand::Register(_)) => Effect::Jump(None),
+            _ => Effect::None,
+        };
+    }
+
+    let writeback = detail
+        .arch_detail()
+        .arm64()
+        .is_some_and(|detail| detail.writeback());
+    match (mnemonic, operands) {
+        ("sub", [Operand::Register(sp), Operand::Register(base), amount])
+            if sp == "sp" && base == "sp" =>
+        {
+            match amount {
+                Operand::Immediate(bytes) => Effect::Allocate(*bytes as u64),
+                _ => Effect::DynamicAllocation,
+            }
+        }
+        // `stp x29, x30, [sp, #-16]!` stores below the stack pointer and moves it there.
+        ("stp" | "str", [.., Operand::Memory { base, displacement }])
+            if writeback && base == "sp" && *displacement < 0 =>
+        {
+            Effect::Allocate(displacement.unsigned_abs())
+        }
+        ("mov", [Operand::Register(sp), Operand::Register(source)])
+            if sp == "sp" && source != "x29" && source != "fp" =>
+        {
+            Effect::DynamicAllocation
+        }
+        _ => Effect::None,
+    }
+}
+
+/// The effect of a RISC-V instruction at `address`, after an `auipc` or `lui` which set a
+/// register to `upper_immediate`.
+fn riscv_effect(
+    mnemonic: &str,
+    address: u64,
+    operands: &[Operand],
+    upper_immediate: Option<(String, u64)>,
+) -> Effect {
+    let relative = |offset: i64| Some(address.wrapping_add_signed(offset));
+    // The target of a jump through `base`, if it was set by the `auipc` or `lui` before.
+    let absolute = |base: &str, offset: i64| {
+        upper_immediate
+            .as_ref()
+            .filter(|(register, _)| register == base)
+            .map(|(_, upper)| upper.wrapping_add_signed(offset))
+    };
+
+    match (mnemonic, operands) {
+        ("jal" | "c.jal", [Operand::Immediate(offset)]) => Effect::Call(relative(*offset)),
+        ("jal", [Operand::Register(link), Operand::Immediate(offset)]) => {
+            if link == "zero" {
+                Effect::Jump(relative(*offset))
+            } else {
+                Effect::Call(relative(*offset))
+            }
+        }
+        ("j" | "c.j", [Operand::Immediate(offset)]) => Effect::Jump(relative(*offset)),
+        ("jalr" | "c.jalr", [Operand::Register(base)]) => Effect::Call(absolute(base, 0)),
+        ("jalr", [Operand::Register(link), Operand::Register(base), rest @ ..]) => {
+            let offset = match rest {
+                [Operand::Immediate(offset)] => *offset,
+                _ => 0,
+            };
+            let target = absolute(base, offset);
+            if link != "zero" {
+                Effect::Call(target)
+            } else if base == "ra" && target.is_none() {
+                // A return.
+                Effect::None
+            } else {
+                Effect::Jump(target)
+            }
+        }
+        ("jr" | "c.jr", [Operand::Register(base)]) => {
+            let target = absolute(base, 0);
+            if base == "ra" && target.is_none() {
+                Effect::None
+            } else {
+                Effect::Jump(target)
+            }
+        }
+        ("addi" | "c.addi" | "c.addi16sp", [Operand::Register(sp), rest @ ..]) if sp == "sp" => {
+            match rest {
+                [Operand::Immediate(bytes)] | [Operand::Register(_), Operand::Immediate(bytes)]
+                    if *bytes < 0 =>
+                {
+                    Effect::Allocate(bytes.unsigned_abs())
+                }
+                _ => Effect::None,
+            }
+        }
+        (
+            "sub",
+            [
+                Operand::Register(sp),
+                Operand::Register(base),
+                Operand::Register(_),
+            ],
+        ) if sp == "sp" && base == "sp" => Effect::DynamicAllocation,
+        // `mv sp, s0` restores the stack pointer from the frame pointer, other registers hold a
+        // stack pointer computed at runtime.
+        ("mv" | "c.mv", [Operand::Register(sp), Operand::Register(source)])
+            if sp == "sp" && source != "s0" && source != "fp" =>
+        {
+            Effect::DynamicAllocation
+        }
+        _ => Effect::None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn functions_of(elf: &str) -> Vec<Function> {
+        let elf = std::fs::read(format!(
+            "{}/../probe-rs-debug/tests/debug-unwind-tests/{elf}",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .unwrap();
+        functions(&object::File::parse(&*elf).unwrap()).unwrap()
+    }
+
+    fn function<'a>(functions: &'a [Function], name: &str) -> &'a Function {
+        functions
+            .iter()
+            .find(|function| function.name == name)
+            .unwrap_or_else(|| panic!("{name} not found"))
+    }
+
+    #[test]
+    fn calls_and_allocations_on_thumb() {
+        let functions = functions_of("nRF52833_xxAA_full_unwind.elf");
+
+        // The handlers without a definition are aliases of `DefaultHandler_`, only the symbol
+        // with a size is kept.
+        assert_eq!(function(&functions, "DefaultHandler_").range, 0x660..0x664);
+        assert!(
+            !functions
+                .iter()
+                .any(|function| function.name == "DefaultHandler")
+        );
+
+        let main = function(&functions, "main");
+        assert_eq!(
+            main.calls,
+            [Call {
+                address: 0x2ac,
+                target: Some(0x2b0),
+            }]
+        );
+
+        let rt_main = function(&functions, "nRF52833_xxAA::__cortex_m_rt_main");
+        assert_eq!(
+            rt_main.allocations[..2],
+            [
+                Allocation {
+                    address: 0x2b0,
+                    bytes: 8,
+                },
+                Allocation {
+                    address: 0x2b4,
+                    bytes: 0x90,
+                },
+            ]
+        );
+        assert!(!rt_main.dynamic_allocation);
+    }
+
+    #[test]
+    fn mapping_symbols_decide_the_instruction_set() {
+        let section = 2;
+        let code_mappings = BTreeMap::from([
+            ((section, 0x100), true),
+            ((section, 0x200), false),
+            ((3, 0x300), false),
+        ]);
+
+        // An assembly label without the Thumb bit, in Thumb code.
+        assert!(is_thumb(&code_mappings, section, 0x180));
+        assert!(!is_thumb(&code_mappings, section, 0x201));
+        // Before the first mapping symbol of the section, only the Thumb bit is left.
+        assert!(is_thumb(&code_mappings, section, 0x81));
+        assert!(!is_thumb(&code_mappings, 3, 0x280));
+    }
+
+    #[test]
+    fn calls_through_auipc_on_risc_v() {
+        let functions = functions_of("esp32c3_full_unwind.elf");
+
+        // `_start` is a label defined in assembly, without a type or a size.
+        let start = function(&functions, "_start");
+        let start_rust = function(&functions, "_start_rust");
+        assert!(
+            start
+                .calls
+                .iter()
+                .any(|call| call.target == Some(start_rust.range.start))
+        );
+
+        let main = function(&functions, "main");
+        assert_eq!(main.allocations[0].bytes, 0xc0);
+        // `auipc ra, 0; jalr ra, ra, -0x1a2`
+        assert_eq!(
+            main.calls[0],
+            Call {
+                address: 0x4200_0520,
+                target: Some(0x4200_051c - 0x1a2),
+            }
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! The stack used by the frames of the functions of a program, from the `.stack_sizes` section
+//! emitted with `-Z emit-stack-sizes`, or from the call frame information in `.debug_frame`.
+
+use std::collections::HashMap;
+
+use addr2line::gimli::{
+    self, BaseAddresses, CfaRule, CieOrFde, DebugFrame, EndianSlice, Register, RunTimeEndian,
+    UnwindContext, UnwindSection,
+};
+use anyhow::{Context, bail};
+use object::{Architecture, Object, ObjectSection};
+
+/// Reads the `.stack_sizes` section, which maps the address of each function to the size of its
+/// frame.
+///
+/// Returns `Ok(None)` if the file has no such section.
+pub(crate) fn stack_sizes(file: &object::File) -> anyhow::Result<Option<HashMap<u64, u64>>> {
+    let Some(section) = file.section_by_name(".stack_sizes") else {
+        return Ok(None);
+    };
+    let data = section
+        .data()
+        .context("Failed to read the .stack_sizes section")?;
+
+    let address_size = if file.is_64() { 8 } else { 4 };
+    parse_stack_sizes(
+        data,
+        address_size,
+        file.is_little_endian(),
+        function_address_mask(file),
+    )
+    .map(Some)
+}
+
+/// Parses the entries of a `.stack_sizes` section: the address of a function, followed by the
+/// size of its frame as an unsigned LEB128 number.
+fn parse_stack_sizes(
+    mut data: &[u8],
+    address_size: usize,
+    little_endian: bool,
+    address_mask: u64,
+) -> anyhow::Result<HashMap<u64, u64>> {
+    let mut sizes = HashMap::new();
+    while !data.is_empty() {
+        if data.len() < address_size {
+            bail!("The .stack_sizes section ends in the middle of an entry");
+        }
+        let (address, rest) = data.split_at(address_size);
+        let mut bytes = [0; 8];
+        let address = if little_endian {
+            bytes[..address_size].copy_from_slice(address);
+            u64::from_le_bytes(bytes)
+        } else {
+            bytes[8 - address_size..].copy_from_slice(address);
+            u64::from_be_bytes(bytes)
+        };
+
+        let mut reader = EndianSlice::new(rest, RunTimeEndian::Little);
+        let size = gimli::leb128::read::unsigned(&mut reader)
+            .context("Failed to read a frame size from the .stack_sizes section")?;
+        data = reader.slice();
+
+        sizes.insert(address & address_mask, size);
+    }
+
+    Ok(sizes)
+}
+
+/// The stack used by the frame of a function, from the rules for its canonical frame address
+/// (CFA), which is the value of the stack pointer at the call site.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CfaFrame {
+    /// The largest offset of the CFA from the stack pointer.
+    pub sp_offset: u64,
+    /// The address from which the CFA is given relative to another register, like a frame
+    /// pointer. The stack the function allocates after that is not described by the CFA.
+    pub frame_pointer_from: Option<u64>,
+}
+
+/// Reads the CFA rules of the functions in `.debug_frame`, by the start address of the
+/// functions.
+///
+/// Returns an empty map if the file has no call frame information.
+pub(crate) fn cfa_frames(file: &object::File) -> anyhow::Result<HashMap<u64, CfaFrame>> {
+    let Some(section) = file.section_by_name(".debug_frame") else {
+        return Ok(HashMap::new());
+    };
+    let data = section
+        .data()
+        .context("Failed to read the .debug_frame section")?;
+    let endian = if file.is_little_endian() {
+        RunTimeEndian::Little
+    } else {
+        RunTimeEndian::Big
+    };
+    let stack_pointer = stack_pointer(file.architecture())?;
+    let address_mask = function_address_mask(file);
+
+    let mut debug_frame = DebugFrame::new(data, endian);
+    // The address size is only used for CIE versions before 4.
+    debug_frame.set_address_size(if file.is_64() { 8 } else { 4 });
+
+    let bases = BaseAddresses::default();
+    let mut context = UnwindContext::new();
+    let mut frames = HashMap::new();
+    let mut entries = debug_frame.entries(&bases);
+    while let Some(entry) = entries.next()? {
+        let CieOrFde::Fde(partial) = entry else {
+            continue;
+        };
+        let fde = partial.parse(DebugFrame::cie_from_offset)?;
+
+        let mut frame = CfaFrame {
+            sp_offset: 0,
+            frame_pointer_from: None,
+        };
+        let mut rows = fde.rows(&debug_frame, &bases, &mut context)?;
+        while let Some(row) = rows.next_row()? {
+            match row.cfa() {
+                CfaRule::RegisterAndOffset { register, offset } if *register == stack_pointer => {
+                    frame.sp_offset = frame.sp_offset.max(*offset as u64);
+                }
+                _ => {
+                    frame
+                        .frame_pointer_from
+                        .get_or_insert(row.start_address() & address_mask);
+                }
+            }
+        }
+
+        frames.insert(fde.initial_address() & address_mask, frame);
+    }
+
+    Ok(frames)
+}
+
+/// The DWARF register number of the stack pointer.
+fn stack_pointer(architecture: Architecture) -> anyhow::Result<Register> {
+    Ok(match architecture {
+        Architecture::Arm => gimli::Arm::SP,
+        Architecture::Aarch64 => gimli::AArch64::SP,
+        Architecture::Riscv32 | Architecture::Riscv64 => gimli::RiscV::SP,
+        other => bail!("Stack usage analysis is not supported for {other:?}"),
+    })
+}
+
+/// The mask that removes the Thumb bit from the addresses of functions on ARM.
+pub(crate) fn function_address_mask(file: &object::File) -> u64 {
+    if file.architecture() == Architecture::Arm {
+        !1
+    } else {
+        !0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_stack_sizes_section() {
+        let mut data = Vec::new();
+        data.extend(0x0000_0101u32.to_le_bytes());
+        data.push(0x10);
+        data.extend(0x0000_0200u32.to_le_bytes());
+        // 300, encoded as LEB128.
+        data.extend([0xac, 0x02]);
+
+        let sizes = parse_stack_sizes(&data, 4, true, !1).unwrap();
+        assert_eq!(sizes, HashMap::from([(0x100, 16), (0x200, 300)]));
+    }
+
+    #[test]
+    fn truncated_stack_sizes_section() {
+        assert!(parse_stack_sizes(&[0x00, 0x01], 4, true, !0).is_err());
+    }
+
+    #[test]
+    fn cfa_frames_with_a_frame_pointer() {
+        let elf = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../probe-rs-debug/tests/debug-unwind-tests/nRF52833_xxAA_full_unwind.elf"
+        ))
+        .unwrap();
+        let file = object::File::parse(&*elf).unwrap();
+
+        let frames = cfa_frames(&file).unwrap();
+        // `__cortex_m_rt_main` pushes r7 and lr, and then sets r7 up as the frame pointer.
+        assert_eq!(
+            frames.get(&0x2b0),
+            Some(&CfaFrame {
+                sp_offset: 8,
+                frame_pointer_from: Some(0x2b4),
+            })
+        );
+    }
+}
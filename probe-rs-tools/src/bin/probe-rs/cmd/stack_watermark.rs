@@ -0,0 +1,174 @@
+use std::fmt::Write;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use probe_rs_debug::stack_paint::stack_region_from_elf;
+use probe_rs_rpc::stack_paint::{WireStackUsage, WireStackUsageReport};
+use probe_rs_rpc_client::{RpcClient, SessionInterface};
+
+use crate::CoreOptions;
+use crate::util::cli;
+use crate::util::common_options::ProbeOptions;
+use crate::util::parse_range;
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(flatten)]
+    shared: CoreOptions,
+
+    #[clap(flatten)]
+    probe_options: ProbeOptions,
+
+    /// The ELF file of the running firmware, which was started with `--paint-stack`.
+    path: PathBuf,
+
+    /// The stack that was painted, if it was given with `--stack-range` when the firmware was
+    /// started.
+    #[clap(long, value_parser = parse_range)]
+    stack_range: Option<Range<u64>>,
+}
+
+impl Cmd {
+    pub async fn run(self, client: RpcClient) -> anyhow::Result<()> {
+        let region = stack_region(&self.path, self.stack_range)?;
+        let session = cli::attach_probe(&client, self.probe_options, None, true).await?;
+
+        print_stack_usage(&session, self.shared.core, &self.path, region).await
+    }
+}
+
+/// Returns the stack of the firmware: `range` if it is given, or the stack found from the linker
+/// symbols of the ELF file at `path`.
+pub(crate) fn stack_region(path: &Path, range: Option<Range<u64>>) -> anyhow::Result<Range<u64>> {
+    if let Some(range) = range {
+        return Ok(range);
+    }
+
+    let elf = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    stack_region_from_elf(&elf)?.with_context(|| {
+        format!(
+            "{} has no linker symbols for the stack, use `--stack-range` to give it",
+            path.display()
+        )
+    })
+}
+
+/// Measures the usage of the painted stack at `region`, and of the stacks of the RTOS threads,
+/// and prints it.
+pub(crate) async fn print_stack_usage(
+    session: &SessionInterface,
+    core: usize,
+    path: &Path,
+    region: Range<u64>,
+) -> anyhow::Result<()> {
+    session.load_debug_info(path.to_path_buf()).await?;
+    let report = session.core(core).stack_usage(Some(region)).await?;
+
+    print!("{}", format_stack_usage(&report));
+
+    Ok(())
+}
+
+fn format_stack_usage(report: &WireStackUsageReport) -> String {
+    let mut message = String::new();
+    if let Some(usage) = &report.painted_stack {
+        #[allow(clippy::unwrap_used, reason = "Writing to a string is infallible")]
+        writeln!(&mut message, "Stack: {}", format_usage(usage)).unwrap();
+    }
+
+    if let Some(rtos) = &report.rtos {
+        #[allow(clippy::unwrap_used, reason = "Writing to a string is infallible")]
+        writeln!(&mut message, "{rtos} threads:").unwrap();
+        if report.threads.is_empty() {
+            message.push_str("    The kernel does not keep the stacks of its threads.\n");
+        }
+        let width = report
+            .threads
+            .iter()
+            .map(|thread| thread.name.len())
+            .max()
+            .unwrap_or_default();
+        for thread in &report.threads {
+            #[allow(clippy::unwrap_used, reason = "Writing to a string is infallible")]
+            writeln!(
+                &mut message,
+                "    {:width$}  {}",
+                thread.name,
+                format_usage(&thread.usage)
+            )
+            .unwrap();
+        }
+    }
+
+    message
+}
+
+fn format_usage(usage: &WireStackUsage) -> String {
+    let headroom = usage.high_water_mark - usage.bottom;
+    let mut line = match usage.top {
+        Some(top) => format!(
+            "{} of {} bytes used, {headroom} bytes headroom",
+            top - usage.high_water_mark,
+            top - usage.bottom
+        ),
+        None => format!("{headroom} bytes headroom"),
+    };
+    #[allow(clippy::unwrap_used, reason = "Writing to a string is infallible")]
+    write!(
+        &mut line,
+        " (high-water mark at {:#010x})",
+        usage.high_water_mark
+    )
+    .unwrap();
+    if headroom == 0 {
+        line.push_str(", the stack may have overflowed");
+    }
+
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use probe_rs_rpc::stack_paint::WireThreadStackUsage;
+
+    use super::*;
+
+    #[test]
+    fn format_stack_usage_with_threads() {
+        let report = WireStackUsageReport {
+            painted_stack: Some(WireStackUsage {
+                bottom: 0x2000_0000,
+                top: Some(0x2000_1000),
+                high_water_mark: 0x2000_0c00,
+            }),
+            rtos: Some("FreeRTOS".to_string()),
+            threads: vec![
+                WireThreadStackUsage {
+                    name: "IDLE".to_string(),
+                    usage: WireStackUsage {
+                        bottom: 0x2000_2000,
+                        top: None,
+                        high_water_mark: 0x2000_2100,
+                    },
+                },
+                WireThreadStackUsage {
+                    name: "sensor".to_string(),
+                    usage: WireStackUsage {
+                        bottom: 0x2000_3000,
+                        top: Some(0x2000_3400),
+                        high_water_mark: 0x2000_3000,
+                    },
+                },
+            ],
+        };
+
+        assert_eq!(
+            format_stack_usage(&report),
+            "Stack: 1024 of 4096 bytes used, 3072 bytes headroom (high-water mark at 0x20000c00)\n\
+             FreeRTOS threads:\n    \
+             IDLE    256 bytes headroom (high-water mark at 0x20002100)\n    \
+             sensor  1024 of 1024 bytes used, 0 bytes headroom (high-water mark at 0x20003000), the stack may have overflowed\n"
+        );
+    }
+}
@@ -1,17 +1,28 @@
-use std::io::prelude::*;
+mod etm;
+mod trax;
+
+use std::fs::File;
+use std::io::{BufWriter, prelude::*};
+use std::path::PathBuf;
 use std::thread::sleep;
 use std::time::Duration;
 use std::time::Instant;
 
-use probe_rs::MemoryInterface;
+use addr2line::gimli::RunTimeEndian;
+use anyhow::Context;
 use probe_rs::config::Registry;
 use probe_rs::probe::list::Lister;
+use probe_rs::{Core, MemoryInterface};
+use probe_rs_debug::DebugInfo;
+use probe_rs_debug::static_variable::{ScalarValue, StaticVariable};
 use scroll::{LE, Pwrite};
 
 use crate::CoreOptions;
-use crate::util::{common_options::ProbeOptions, parse_u64};
+use crate::util::signals::{Signal, SignalFormat, SignalWriter};
+use crate::util::{common_options::ProbeOptions, parse_duration_secs, parse_u64};
 
 #[derive(clap::Parser)]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Cmd {
     #[clap(flatten)]
     shared: CoreOptions,
@@ -20,12 +31,71 @@ pub struct Cmd {
     common: ProbeOptions,
 
     /// The address of the memory to dump from the target.
-    #[clap(value_parser = parse_u64)]
-    loc: u64,
+    #[clap(value_parser = parse_u64, required_unless_present = "vars")]
+    loc: Option<u64>,
+
+    /// Sample a static variable, given by its path like `my_mod::STATE.counter` or
+    /// `BUFFER[3]`, instead of a memory address. Can be given multiple times.
+    #[clap(long = "var", requires = "elf", conflicts_with = "loc")]
+    vars: Vec<String>,
+
+    /// The ELF file running on the target, used to look up the variables.
+    #[clap(long)]
+    elf: Option<PathBuf>,
+
+    /// How many times per second the variables are sampled.
+    #[clap(long, default_value_t = 100.0, requires = "vars")]
+    rate: f64,
+
+    /// The format the sampled variables are written in.
+    #[clap(long, value_enum, default_value_t, requires = "vars")]
+    format: SignalFormat,
+
+    /// Write the sampled variables to this file instead of the standard output.
+    #[clap(long, short, requires = "vars")]
+    output: Option<PathBuf>,
+
+    /// Stop sampling the variables after this many seconds.
+    #[clap(long, value_parser = parse_duration_secs, requires = "vars")]
+    duration: Option<Duration>,
+
+    #[clap(subcommand)]
+    source: Option<TraceSource>,
+}
+
+#[derive(clap::Subcommand)]
+enum TraceSource {
+    /// Record the instruction trace of a core with the ETM and print the last executed branches.
+    ///
+    /// The trace is recorded into the Embedded Trace Buffer/FIFO (ETB/ETF), which is used as a
+    /// circular buffer, and read out once the core halts or the trace duration is over.
+    ///
+    /// Note: Only targets with an ETMv4 and an ETF are supported, and only T32 code is decoded.
+    Etm(etm::EtmCmd),
+
+    /// Record the program flow of an Xtensa core with TRAX and print the last executed branches.
+    ///
+    /// The trace is recorded into the trace memory of the chip, which is used as a circular
+    /// buffer, and read out once the core halts or the trace duration is over.
+    ///
+    /// Note: Only Xtensa cores with TRAX, like the ones of the ESP32, ESP32-S2 and ESP32-S3, are
+    /// supported.
+    Trax(trax::TraxCmd),
 }
 
 impl Cmd {
     pub fn run(self, registry: &mut Registry, lister: &Lister) -> anyhow::Result<()> {
+        match self.source {
+            Some(TraceSource::Etm(cmd)) => return cmd.run(registry, lister),
+            Some(TraceSource::Trax(cmd)) => return cmd.run(registry, lister),
+            None => {}
+        }
+        if !self.vars.is_empty() {
+            return self.sample_variables(registry, lister);
+        }
+        // Enforced by clap when no subcommand or variable is given.
+        let loc = self.loc.expect("memory address is required");
+
         let mut xs = vec![];
         let mut ys = vec![];
 
@@ -41,7 +111,7 @@ impl Cmd {
             let instant = elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis());
 
             // Read data.
-            let value: u32 = core.read_word_32(self.loc)?;
+            let value: u32 = core.read_word_32(loc)?;
 
             xs.push(instant);
             ys.push(value);
@@ -63,4 +133,104 @@ impl Cmd {
             sleep(Duration::from_millis(time_to_wait));
         }
     }
+
+    /// Samples the static variables given with `--var` at a fixed rate, while the target runs.
+    fn sample_variables(self, registry: &mut Registry, lister: &Lister) -> anyhow::Result<()> {
+        // Enforced by clap when variables are given.
+        let elf = self.elf.as_ref().expect("the ELF file is required");
+        let debug_info = DebugInfo::from_file(elf).with_context(|| {
+            format!("Failed to read the debug information of {}", elf.display())
+        })?;
+        let endianness = debug_info.endianness();
+
+        let variables = self
+            .vars
+            .iter()
+            .map(|path| {
+                debug_info
+                    .resolve_static_variable(path)
+                    .with_context(|| format!("Failed to resolve `{path}`"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        for variable in &variables {
+            tracing::info!(
+                "Sampling `{}` at {:#010x} as {:?}",
+                variable.path,
+                variable.address,
+                variable.scalar_type
+            );
+        }
+
+        let period = Duration::try_from_secs_f64(1.0 / self.rate)
+            .context("The sampling rate must be a positive number")?;
+
+        let output: Box<dyn Write> = match &self.output {
+            Some(path) => {
+                Box::new(BufWriter::new(File::create(path).with_context(|| {
+                    format!("Failed to create {}", path.display())
+                })?))
+            }
+            None => Box::new(std::io::stdout().lock()),
+        };
+        let signals = self
+            .vars
+            .iter()
+            .zip(&variables)
+            .map(|(name, variable)| Signal {
+                name: name.clone(),
+                scalar_type: variable.scalar_type,
+            })
+            .collect();
+        let mut writer = SignalWriter::new(output, self.format, signals)?;
+
+        let (mut session, _probe_options) = self.common.simple_attach(registry, lister)?;
+        let mut core = session.core(self.shared.core)?;
+
+        let start = Instant::now();
+        let mut next_sample = start;
+        loop {
+            let time = start.elapsed();
+            if self.duration.is_some_and(|duration| time > duration) {
+                return Ok(());
+            }
+
+            let values = variables
+                .iter()
+                .map(|variable| read_value(&mut core, variable, endianness).map(Some))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            writer.write(time, &values)?;
+
+            // Skip the samples that were missed instead of catching up on them.
+            next_sample += period;
+            match next_sample.checked_duration_since(Instant::now()) {
+                Some(wait) => sleep(wait),
+                None => next_sample = Instant::now(),
+            }
+        }
+    }
+}
+
+/// Reads the value of a variable, in a single access if it is aligned.
+fn read_value(
+    core: &mut Core<'_>,
+    variable: &StaticVariable,
+    endianness: RunTimeEndian,
+) -> anyhow::Result<ScalarValue> {
+    let address = variable.address;
+    let size = variable.scalar_type.byte_size();
+
+    let bytes = match size {
+        1 => vec![core.read_word_8(address)?],
+        2 if address.is_multiple_of(2) => core.read_word_16(address)?.to_le_bytes().to_vec(),
+        4 if address.is_multiple_of(4) => core.read_word_32(address)?.to_le_bytes().to_vec(),
+        8 if address.is_multiple_of(8) => core.read_word_64(address)?.to_le_bytes().to_vec(),
+        _ => {
+            let mut bytes = vec![0; size];
+            core.read(address, &mut bytes)?;
+            return Ok(variable.scalar_type.decode(&bytes, endianness));
+        }
+    };
+
+    // The words are read in the byte order of the host, and converted to little endian above.
+    Ok(variable.scalar_type.decode(&bytes, RunTimeEndian::Little))
 }
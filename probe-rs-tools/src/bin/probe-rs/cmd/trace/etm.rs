@@ -0,0 +1,196 @@
+//! Provides instruction tracing with the ETM.
+
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use object::{Object, ObjectSection, SectionKind};
+use probe_rs::architecture::arm::component::EtmConfig;
+use probe_rs::architecture::arm::trace::{CodeImage, Etmv4Decoder, TraceElement};
+use probe_rs::config::Registry;
+use probe_rs::probe::list::Lister;
+use probe_rs_debug::DebugInfo;
+
+use crate::CoreOptions;
+use crate::util::{common_options::ProbeOptions, parse_duration_secs, parse_range};
+
+#[derive(clap::Parser)]
+pub(crate) struct EtmCmd {
+    #[clap(flatten)]
+    shared: CoreOptions,
+
+    #[clap(flatten)]
+    common: ProbeOptions,
+
+    /// The ELF file running on the target.
+    path: PathBuf,
+
+    /// How long to trace for, in seconds. Tracing stops early when the core halts.
+    #[clap(long, value_parser = parse_duration_secs, default_value = "1")]
+    duration: Duration,
+
+    /// The number of branches to print.
+    #[clap(long, short = 'n', default_value_t = 32)]
+    branches: usize,
+
+    /// Only trace instructions in this address range, for example `0x8000000..0x8001000`.
+    /// Can be given multiple times.
+    #[clap(long = "range", value_parser = parse_range)]
+    ranges: Vec<Range<u64>>,
+
+    /// Emit cycle counts, with the given threshold in cycles.
+    #[clap(long)]
+    cycle_threshold: Option<u16>,
+
+    /// Trace context ID changes.
+    #[clap(long)]
+    context_id: bool,
+}
+
+/// A change of the program flow, as printed by the command.
+enum FlowChange {
+    Branch { source: u64, target: u64 },
+    Exception { number: u16, return_address: u64 },
+    Gap,
+}
+
+impl EtmCmd {
+    pub fn run(self, registry: &mut Registry, lister: &Lister) -> anyhow::Result<()> {
+        let elf = std::fs::read(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        let image = code_image(&elf)?;
+        let debug_info = DebugInfo::from_raw(&elf).ok();
+
+        let (mut session, _probe_options) = self.common.simple_attach(registry, lister)?;
+
+        let config = EtmConfig {
+            address_ranges: self.ranges,
+            cycle_counting: self.cycle_threshold,
+            context_id: self.context_id,
+            ..EtmConfig::default()
+        };
+        let parameters = session.setup_etm_tracing(self.shared.core, &config)?;
+
+        {
+            let mut core = session.core(self.shared.core)?;
+            if core.core_halted()? {
+                core.run()?;
+            }
+
+            let start = Instant::now();
+            while start.elapsed() < self.duration && !core.core_halted()? {
+                sleep(Duration::from_millis(10));
+            }
+            if !core.core_halted()? {
+                core.halt(Duration::from_millis(100))?;
+            }
+        }
+
+        let trace = session.read_etm_trace_data()?;
+        tracing::debug!("Read {} bytes of ETM trace", trace.len());
+
+        let mut changes = VecDeque::with_capacity(self.branches + 1);
+        let mut branches = 0;
+        let mut taken_branch = None;
+        for element in Etmv4Decoder::new(&trace, &image, parameters) {
+            let change = match element {
+                TraceElement::Instructions(range) => {
+                    let source =
+                        std::mem::replace(&mut taken_branch, range.taken.then_some(range.last));
+                    let Some(source) = source else {
+                        continue;
+                    };
+                    branches += 1;
+                    FlowChange::Branch {
+                        source,
+                        target: range.start,
+                    }
+                }
+                TraceElement::Exception {
+                    number,
+                    return_address,
+                } => {
+                    taken_branch = None;
+                    FlowChange::Exception {
+                        number,
+                        return_address,
+                    }
+                }
+                TraceElement::Discontinuity => {
+                    taken_branch = None;
+                    if matches!(changes.back(), Some(FlowChange::Gap)) {
+                        continue;
+                    }
+                    FlowChange::Gap
+                }
+                TraceElement::UnsupportedInstructionSet { address } => anyhow::bail!(
+                    "The core executed A32 or A64 code at {address:#010x}, \
+                    only the trace of T32 code can be decoded"
+                ),
+                TraceElement::ContextId(id) => {
+                    println!("Context ID {id:#x}");
+                    continue;
+                }
+                TraceElement::CycleCount(_) | TraceElement::Timestamp(_) => continue,
+            };
+
+            changes.push_back(change);
+            if changes.len() > self.branches {
+                changes.pop_front();
+            }
+        }
+
+        println!(
+            "Decoded {branches} branches from {} bytes of trace",
+            trace.len()
+        );
+
+        let location = |address: u64| {
+            debug_info
+                .as_ref()
+                .and_then(|debug_info| debug_info.get_source_location(address))
+                .map(|location| match location.line {
+                    Some(line) => format!("{}:{line}", location.path.to_path().display()),
+                    None => location.path.to_path().display().to_string(),
+                })
+                .unwrap_or_else(|| "<unknown>".to_string())
+        };
+
+        for change in changes {
+            match change {
+                FlowChange::Branch { source, target } => println!(
+                    "{source:#010x} -> {target:#010x}  {} -> {}",
+                    location(source),
+                    location(target)
+                ),
+                FlowChange::Exception {
+                    number,
+                    return_address,
+                } => println!(
+                    "exception {number:#x}, returning to {return_address:#010x}  {}",
+                    location(return_address)
+                ),
+                FlowChange::Gap => println!("..."),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Collects the executable sections of an ELF file.
+fn code_image(elf: &[u8]) -> anyhow::Result<CodeImage> {
+    let file = object::File::parse(elf).context("Failed to parse the ELF file")?;
+
+    let mut image = CodeImage::new();
+    for section in file.sections() {
+        if section.kind() == SectionKind::Text {
+            image.add_segment(section.address(), section.data()?.to_vec());
+        }
+    }
+
+    Ok(image)
+}
@@ -0,0 +1,160 @@
+//! Provides program flow tracing with TRAX on Xtensa cores.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use probe_rs::architecture::xtensa::trax::{TraxConfig, TraxDecoder, TraxElement};
+use probe_rs::config::Registry;
+use probe_rs::probe::list::Lister;
+
+use crate::CoreOptions;
+use crate::cmd::profile::flat::Symbols;
+use crate::util::{common_options::ProbeOptions, parse_duration_secs, parse_u32};
+
+#[derive(clap::Parser)]
+pub(crate) struct TraxCmd {
+    #[clap(flatten)]
+    shared: CoreOptions,
+
+    #[clap(flatten)]
+    common: ProbeOptions,
+
+    /// The ELF file running on the target.
+    path: PathBuf,
+
+    /// How long to trace for, in seconds. Tracing stops early when the core halts, for example
+    /// because of a panic or a breakpoint.
+    #[clap(long, value_parser = parse_duration_secs, default_value = "1")]
+    duration: Duration,
+
+    /// The number of branches to print.
+    #[clap(long, short = 'n', default_value_t = 32)]
+    branches: usize,
+
+    /// Stop tracing and halt the core once the program counter reaches this address.
+    #[clap(long, value_parser = parse_u32)]
+    stop_pc: Option<u32>,
+
+    /// The number of instructions that are still traced after `--stop-pc` was reached.
+    #[clap(long, default_value_t = 0, requires = "stop_pc")]
+    post_stop_instructions: u32,
+
+    /// Read the trace that is already in the trace memory, instead of starting a new one. This
+    /// reads a trace started by the firmware, or the trace leading up to a crash.
+    #[clap(long, conflicts_with_all = ["duration", "stop_pc"])]
+    existing: bool,
+}
+
+impl TraxCmd {
+    pub fn run(self, registry: &mut Registry, lister: &Lister) -> anyhow::Result<()> {
+        let symbols = Symbols::load(&self.path)?;
+
+        let (mut session, _probe_options) = self.common.simple_attach(registry, lister)?;
+
+        if !self.existing {
+            let config = TraxConfig {
+                stop_pc: self.stop_pc,
+                post_stop_instructions: self.post_stop_instructions,
+            };
+            session
+                .setup_trax_tracing(self.shared.core, &config)
+                .context("Failed to start TRAX, which is only available on Xtensa cores")?;
+
+            let mut core = session.core(self.shared.core)?;
+            if core.core_halted()? {
+                core.run()?;
+            }
+
+            let start = Instant::now();
+            while start.elapsed() < self.duration && !core.core_halted()? {
+                sleep(Duration::from_millis(10));
+            }
+            if !core.core_halted()? {
+                core.halt(Duration::from_millis(100))?;
+            }
+        }
+
+        let trace = session
+            .get_xtensa_interface(self.shared.core)
+            .context("TRAX is only available on Xtensa cores")?
+            .read_trax_trace()?;
+        tracing::debug!(
+            "Read {} bytes of TRAX trace, wrapped: {}",
+            trace.data.len(),
+            trace.wrapped
+        );
+
+        let mut elements = VecDeque::with_capacity(self.branches + 1);
+        let mut branches = 0;
+        for element in TraxDecoder::new(&trace) {
+            match element {
+                TraxElement::Branch { .. } => branches += 1,
+                TraxElement::Discontinuity
+                    if matches!(elements.back(), Some(TraxElement::Discontinuity)) =>
+                {
+                    continue;
+                }
+                _ => {}
+            }
+
+            elements.push_back(element);
+            if elements.len() > self.branches {
+                elements.pop_front();
+            }
+        }
+
+        println!(
+            "Decoded {branches} branches from {} bytes of trace",
+            trace.data.len()
+        );
+
+        let location = |address: u32| {
+            let address = u64::from(address);
+            let name = symbols
+                .get_name(address)
+                .unwrap_or_else(|| "<unknown>".to_string());
+            match symbols.get_location(address) {
+                Some((file, line)) => format!("{name} ({file}:{line})"),
+                None => name,
+            }
+        };
+
+        for element in elements {
+            let (instructions, kind, address) = match element {
+                TraxElement::Branch {
+                    instructions,
+                    target,
+                    exception,
+                } => {
+                    let kind = if exception {
+                        "exception to"
+                    } else {
+                        "branch to"
+                    };
+                    (instructions, kind, target)
+                }
+                TraxElement::Sync {
+                    instructions,
+                    address,
+                } => (instructions, "at", address),
+                TraxElement::Event { code, instructions } => {
+                    println!("{instructions:>6} instructions, event {code:#x}");
+                    continue;
+                }
+                TraxElement::Discontinuity => {
+                    println!("...");
+                    continue;
+                }
+            };
+            println!(
+                "{instructions:>6} instructions, {kind:<12} {address:#010x}  {}",
+                location(address)
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -143,8 +143,11 @@ impl Cli {
             Subcommand::Chip(cmd) => cmd.run(client).await,
             Subcommand::Benchmark(cmd) => cmd.run(&mut registry, &lister),
             Subcommand::Profile(cmd) => cmd.run(&mut registry, &lister),
+            Subcommand::ProfileDiff(cmd) => cmd.run(),
             Subcommand::Read(cmd) => cmd.run(client).await,
             Subcommand::Write(cmd) => cmd.run(client).await,
+            Subcommand::StackWatermark(cmd) => cmd.run(client).await,
+            Subcommand::StackUsage(cmd) => cmd.run(),
             Subcommand::Complete(cmd) => cmd.run(&lister),
             Subcommand::Mi(cmd) => cmd.run(client).await,
         }
@@ -187,7 +190,7 @@ enum Subcommand {
     /// Attach to rtt logging
     #[clap(name = "attach")]
     Attach(cmd::attach::Cmd),
-    /// Trace a memory location on the target
+    /// Trace a memory location, static variables or the executed instructions of the target
     #[clap(name = "trace")]
     Trace(cmd::trace::Cmd),
     /// Configure and monitor ITM trace packets from the target.
@@ -198,11 +201,20 @@ enum Subcommand {
     Benchmark(cmd::benchmark::Cmd),
     /// Profile on-target runtime performance of target ELF program
     Profile(cmd::profile::ProfileCmd),
+    /// Compare the profiles of two builds saved with `probe-rs profile --save`
+    #[clap(name = "profile-diff")]
+    ProfileDiff(cmd::profile::diff::DiffCmd),
     /// Start a server that accepts remote connections
     #[cfg(feature = "remote")]
     Serve(cmd::serve::Cmd),
     Read(cmd::read::Cmd),
     Write(cmd::write::Cmd),
+    /// Report the high-water mark of the stack painted with `--paint-stack`, and of the stacks
+    /// of the RTOS threads
+    StackWatermark(cmd::stack_watermark::Cmd),
+    /// Compute the worst-case stack usage of an ELF program from its call graph, without a
+    /// target
+    StackUsage(cmd::stack_usage::Cmd),
     Complete(cmd::complete::Cmd),
     Mi(cmd::mi::Cmd),
 }
@@ -215,6 +227,7 @@ impl Subcommand {
             Self::List(_)
             | Self::Read(_)
             | Self::Write(_)
+            | Self::StackWatermark(_)
             | Self::Reset(_)
             | Self::Chip(_)
             | Self::Info(_)
@@ -2,6 +2,9 @@ use std::collections::HashMap;
 use std::num::NonZeroU32;
 use std::sync::Arc;
 
+use probe_rs::CoreType;
+use probe_rs_debug::embassy::Embassy;
+use probe_rs_debug::rtos::{RtosAwareness, rtos_for_debug_info};
 use probe_rs_debug::{DebugInfo, StackFrame, VariableCache};
 
 /// Per-session server-owned debug state. The RPC server builds and owns the
@@ -18,6 +21,16 @@ pub struct ServerDebugState {
     /// file I/O happens next to the target. Decoupled from [`Self::per_core`]
     /// so it survives stack-frame refreshes on each halt.
     pub semihosting: HashMap<usize, CoreSemihostingState>,
+    /// Records the executed code while the target runs, once started by the `coverage/start`
+    /// endpoint. Taken by the run loop for the duration of a run.
+    pub coverage: Option<crate::rpc::functions::coverage::CoverageCollector>,
+    /// The RTOS awareness for each type of core, looked up in
+    /// [`Self::debug_info`] on first use. `None` if the program does not use
+    /// a supported RTOS.
+    rtos: HashMap<CoreType, Option<Arc<dyn RtosAwareness>>>,
+    /// The task pools of the embassy executor, looked up in [`Self::debug_info`]
+    /// on first use. The inner `None` means the program does not use embassy.
+    embassy: Option<Option<Arc<Embassy>>>,
 }
 
 #[derive(Default)]
@@ -29,6 +42,9 @@ pub struct CoreDebugState {
     /// `scopes`/`variables` endpoints. `None` when no SVD file was
     /// supplied for the core.
     pub svd_variables: Option<crate::rpc::svd::SvdVariableCache>,
+    /// The futures of the embassy tasks, one child of the root per task.
+    /// `None` when the program does not use the embassy executor.
+    pub embassy_tasks: Option<VariableCache>,
 }
 
 /// Server-side per-core semihosting state, mirroring the client's
@@ -52,6 +68,8 @@ impl ServerDebugState {
     /// binary, so they intentionally survive a debug-info reload.
     pub fn replace_debug_info(&mut self, debug_info: DebugInfo) {
         self.debug_info = Some(Arc::new(debug_info));
+        self.rtos.clear();
+        self.embassy = None;
         for core_state in self.per_core.values_mut() {
             core_state.clear_dwarf_derived_state();
         }
@@ -62,6 +80,7 @@ impl ServerDebugState {
         core_index: usize,
         stack_frames: Vec<StackFrame>,
         static_variables: Option<VariableCache>,
+        embassy_tasks: Option<VariableCache>,
     ) {
         // Preserve the SVD cache across stack-frame refreshes: `store_core`
         // is invoked on every halt, but the SVD cache is built once per
@@ -76,6 +95,7 @@ impl ServerDebugState {
                 stack_frames,
                 static_variables,
                 svd_variables,
+                embassy_tasks,
             },
         );
     }
@@ -95,6 +115,38 @@ impl ServerDebugState {
         self.per_core.entry(core_index).or_default().svd_variables = svd_variables;
     }
 
+    /// The RTOS awareness for the program on a core of type `core_type`, or
+    /// `None` if there is no debug info or the program does not use a
+    /// supported RTOS.
+    pub fn rtos(&mut self, core_type: CoreType) -> Option<Arc<dyn RtosAwareness>> {
+        let debug_info = self.debug_info.as_ref()?;
+        self.rtos
+            .entry(core_type)
+            .or_insert_with(|| match rtos_for_debug_info(debug_info, core_type) {
+                Ok(rtos) => rtos.map(Arc::from),
+                Err(error) => {
+                    tracing::warn!("Failed to look up the RTOS of the program: {error}");
+                    None
+                }
+            })
+            .clone()
+    }
+
+    /// The task pools of the embassy executor, or `None` if there is no debug
+    /// info or the program does not use embassy.
+    pub fn embassy(&mut self) -> Option<Arc<Embassy>> {
+        let debug_info = self.debug_info.as_ref()?;
+        self.embassy
+            .get_or_insert_with(|| match Embassy::from_debug_info(debug_info) {
+                Ok(embassy) => embassy.map(Arc::new),
+                Err(error) => {
+                    tracing::warn!("Failed to look up the embassy tasks of the program: {error}");
+                    None
+                }
+            })
+            .clone()
+    }
+
     /// Get the per-core semihosting state, creating it on first access.
     /// Handles start at 1024 to avoid collision with RTT channel numbers.
     pub fn semihosting_state(&mut self, core_index: usize) -> &mut CoreSemihostingState {
@@ -111,6 +163,7 @@ impl CoreDebugState {
     fn clear_dwarf_derived_state(&mut self) {
         self.stack_frames.clear();
         self.static_variables = None;
+        self.embassy_tasks = None;
     }
 }
 
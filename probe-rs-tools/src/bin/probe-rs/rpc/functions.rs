@@ -7,32 +7,43 @@ use crate::rpc::probe_broker::ProbeBroker;
 use crate::rpc::{
     ConnectionState, Key, Session, SessionEntry, SessionState,
     functions::{
+        branch_trace::{start_branch_trace, take_branch_trace},
         breakpoints::{resolve_source_breakpoints, resolve_source_locations},
         chip::{chip_info, list_families, load_chip_family},
         core_ops::{
-            core_clear_hw_bps, core_dump, core_enable_vc, core_halt, core_handle_semihosting,
-            core_metadata, core_read_registers, core_run, core_set_hw_bps, core_status, core_step,
+            core_clear_flash_bps, core_clear_hw_bps, core_clear_hw_wp, core_clear_sw_bps,
+            core_disable_vc, core_dump, core_enable_vc, core_halt, core_handle_semihosting,
+            core_hit_wp, core_metadata, core_read_registers, core_run, core_set_flash_bps,
+            core_set_hw_bps, core_set_hw_wp, core_set_sw_bps, core_status, core_step,
             core_write_reg,
         },
-        cores::{cores_status, halt_cores, resume_cores},
+        cores::{
+            cores_status, create_run_control_group, halt_cores, remove_run_control_group,
+            resume_cores,
+        },
+        coverage::{start_coverage, take_coverage},
         debug_vars::{
-            clear_core_debug_state, evaluate as debug_evaluate, load_svd as debug_load_svd,
-            scopes as debug_scopes, set_variable as debug_set_variable,
-            variables as debug_variables,
+            clear_core_debug_state, data_breakpoint_info as debug_data_breakpoint_info,
+            evaluate as debug_evaluate, load_svd as debug_load_svd, scopes as debug_scopes,
+            set_variable as debug_set_variable, variables as debug_variables,
         },
         disassemble::disassemble as disassemble_handler,
+        embassy::embassy_tasks,
         flash::{
             boot, build, erase_all, erase_range, flash, load_region, new_flash_loader, verify,
         },
         info::{target_info, target_metadata},
+        itm::{read_itm, start_itm},
         memory::{read_bytes, read_memory, write_memory},
         monitor::monitor,
         probe::{attach, list_probes, select_probe},
         reset::{reset, reset_and_halt},
+        rtos::rtos_threads,
         rtt_client::{
             clean_up_rtt, clear_rtt_control_block, create_rtt_client, get_rtt_channels,
             poll_rtt_up, write_rtt_down,
         },
+        stack_paint::stack_usage,
         stack_trace::{load_debug_info, take_rich_stack_trace, take_stack_trace},
         test::{list_tests, run_test, test_kickoff},
     },
@@ -58,20 +69,26 @@ use tokio::sync::{
 };
 use tokio_util::sync::CancellationToken;
 
+pub mod branch_trace;
 pub mod breakpoints;
 pub mod chip;
 pub mod core_ops;
 pub mod cores;
+pub mod coverage;
 pub mod debug_vars;
 pub mod disassemble;
+pub mod embassy;
 pub mod file;
 pub mod flash;
 pub mod info;
+pub mod itm;
 pub mod memory;
 pub mod monitor;
 pub mod probe;
 pub mod reset;
+pub mod rtos;
 pub mod rtt_client;
+pub mod stack_paint;
 pub mod stack_trace;
 pub mod test;
 
@@ -169,6 +186,10 @@ impl RpcSpawnContext {
         self.state.token.clone()
     }
 
+    pub fn debug_states(&self) -> DebugStatesMap {
+        self.state.debug_states.clone()
+    }
+
     pub(crate) fn registry_blocking(&self) -> impl DerefMut<Target = Registry> + Send + use<> {
         self.state.registry.clone().blocking_lock_owned()
     }
@@ -478,6 +499,8 @@ postcard_rpc::define_dispatch! {
         | HaltCoresEndpoint                | async | halt_cores                 |
         | ResumeCoresEndpoint              | async | resume_cores               |
         | CoresStatusEndpoint              | async | cores_status               |
+        | CreateRunControlGroupEndpoint    | async | create_run_control_group   |
+        | RemoveRunControlGroupEndpoint    | async | remove_run_control_group   |
         | CreateRttClientEndpoint          | async | create_rtt_client          |
         | TakeStackTraceEndpoint           | async | take_stack_trace           |
         | TakeRichStackTraceEndpoint       | async | take_rich_stack_trace      |
@@ -488,8 +511,12 @@ postcard_rpc::define_dispatch! {
         | VariablesEndpoint                | async | debug_variables            |
         | ClearCoreDebugStateEndpoint      | async | clear_core_debug_state     |
         | LoadSvdEndpoint                  | async | debug_load_svd             |
+        | RtosThreadsEndpoint              | async | rtos_threads               |
+        | EmbassyTasksEndpoint             | async | embassy_tasks              |
+        | StackUsageEndpoint               | async | stack_usage                |
         | EvaluateEndpoint                 | async | debug_evaluate             |
         | SetVariableEndpoint              | async | debug_set_variable         |
+        | DataBreakpointInfoEndpoint       | async | debug_data_breakpoint_info |
         | DisassembleEndpoint              | async | disassemble_handler        |
         | NewFlashLoaderEndpoint           | async | new_flash_loader           |
         | BuildEndpoint                    | async | build                      |
@@ -500,6 +527,12 @@ postcard_rpc::define_dispatch! {
         | VerifyEndpoint                   | async | verify                     |
         | BootEndpoint                     | async | boot                       |
         | MonitorEndpoint                  | spawn | monitor                    |
+        | StartBranchTraceEndpoint         | async | start_branch_trace         |
+        | TakeBranchTraceEndpoint          | async | take_branch_trace          |
+        | StartCoverageEndpoint            | async | start_coverage             |
+        | TakeCoverageEndpoint             | async | take_coverage              |
+        | StartItmEndpoint                 | async | start_itm                  |
+        | ReadItmEndpoint                  | async | read_itm                   |
         | RttDownEndpoint                  | async | write_rtt_down             |
         | GetRttChannelsEndpoint           | async | get_rtt_channels           |
         | PollRttUpEndpoint                | async | poll_rtt_up                |
@@ -529,7 +562,15 @@ postcard_rpc::define_dispatch! {
         | CoreWriteRegEndpoint             | async | core_write_reg             |
         | CoreSetHwBpsEndpoint             | async | core_set_hw_bps            |
         | CoreClearHwBpsEndpoint           | async | core_clear_hw_bps          |
+        | CoreSetSwBpsEndpoint             | async | core_set_sw_bps            |
+        | CoreClearSwBpsEndpoint           | async | core_clear_sw_bps          |
+        | CoreSetFlashBpsEndpoint          | async | core_set_flash_bps         |
+        | CoreClearFlashBpsEndpoint        | async | core_clear_flash_bps       |
+        | CoreSetHwWpEndpoint              | async | core_set_hw_wp             |
+        | CoreClearHwWpEndpoint            | async | core_clear_hw_wp           |
+        | CoreHitWpEndpoint                | async | core_hit_wp                |
         | CoreEnableVcEndpoint             | async | core_enable_vc             |
+        | CoreDisableVcEndpoint            | async | core_disable_vc            |
         | CoreMetadataEndpoint             | async | core_metadata              |
         | CoreReadRegistersEndpoint        | async | core_read_registers        |
         | CoreDumpEndpoint                 | async | core_dump                  |
@@ -0,0 +1,47 @@
+use postcard_rpc::header::VarHeader;
+use probe_rs_rpc::NoResponse;
+use probe_rs_rpc::branch_trace::{
+    StartBranchTraceRequest, TakeBranchTraceRequest, TakeBranchTraceResponse,
+};
+
+use crate::rpc::functions::RpcContext;
+
+pub async fn start_branch_trace(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: StartBranchTraceRequest,
+) -> NoResponse {
+    let mut session = ctx.session(request.sessid).await;
+
+    session
+        .setup_mtb_tracing(request.buffer)
+        .map_err(crate::rpc::functions::convert::rpc_error_probe_rs)
+}
+
+pub async fn take_branch_trace(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: TakeBranchTraceRequest,
+) -> TakeBranchTraceResponse {
+    let mut session = ctx.session(request.sessid).await;
+
+    let branches = session
+        .read_mtb_branches()
+        .map_err(crate::rpc::functions::convert::rpc_error_probe_rs)?;
+
+    Ok(branches.into_iter().map(convert::to_wire_branch).collect())
+}
+
+pub(crate) mod convert {
+    use probe_rs::architecture::arm::component::MtbBranch;
+    use probe_rs_rpc::branch_trace::WireBranch;
+
+    pub(crate) fn to_wire_branch(branch: MtbBranch) -> WireBranch {
+        WireBranch {
+            source: branch.source,
+            target: branch.target,
+            exception: branch.exception,
+            trace_start: branch.trace_start,
+        }
+    }
+}
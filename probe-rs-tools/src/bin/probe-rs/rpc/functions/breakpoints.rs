@@ -1,8 +1,9 @@
 use postcard_rpc::header::VarHeader;
 use probe_rs_debug::TypedPath;
 use probe_rs_rpc::breakpoints::{
-    BreakpointResolution, ResolveSourceBreakpointsRequest, ResolveSourceBreakpointsResponse,
-    ResolveSourceLocationsRequest, ResolveSourceLocationsResponse,
+    BreakpointResolution, BreakpointTarget, ResolveSourceBreakpointsRequest,
+    ResolveSourceBreakpointsResponse, ResolveSourceLocationsRequest,
+    ResolveSourceLocationsResponse,
 };
 
 use crate::rpc::functions::RpcContext;
@@ -27,7 +28,7 @@ pub async fn resolve_source_breakpoints(
             .locations
             .into_iter()
             .map(|_| BreakpointResolution {
-                breakpoint: None,
+                breakpoints: Vec::new(),
                 error: Some(NO_DEBUG_INFO.to_string()),
             })
             .collect());
@@ -36,18 +37,29 @@ pub async fn resolve_source_breakpoints(
     Ok(request
         .locations
         .into_iter()
-        .map(|location| {
-            match debug_info.get_breakpoint_location(
-                TypedPath::derive(location.path.as_bytes()),
-                location.line,
-                location.column,
-            ) {
-                Ok(breakpoint) => BreakpointResolution {
-                    breakpoint: Some(convert::to_wire_verified_breakpoint(breakpoint)),
+        .map(|target| {
+            let breakpoints = match target {
+                BreakpointTarget::Source(location) => debug_info
+                    .get_breakpoint_location(
+                        TypedPath::derive(location.path.as_bytes()),
+                        location.line,
+                        location.column,
+                    )
+                    .map(|breakpoint| vec![breakpoint]),
+                BreakpointTarget::Function(name) => {
+                    debug_info.get_function_breakpoint_locations(&name)
+                }
+            };
+            match breakpoints {
+                Ok(breakpoints) => BreakpointResolution {
+                    breakpoints: breakpoints
+                        .into_iter()
+                        .map(convert::to_wire_verified_breakpoint)
+                        .collect(),
                     error: None,
                 },
                 Err(error) => BreakpointResolution {
-                    breakpoint: None,
+                    breakpoints: Vec::new(),
                     error: Some(error.to_string()),
                 },
             }
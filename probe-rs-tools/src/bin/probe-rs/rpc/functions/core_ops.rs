@@ -6,12 +6,13 @@ use std::time::Duration;
 use postcard_rpc::header::VarHeader;
 use probe_rs_rpc::core_ops::{
     CoreAccessRequest, CoreBreakpointsRequest, CoreDumpRequest, CoreHaltRequest,
-    CoreReadRegistersRequest, CoreVectorCatchRequest, CoreWriteRegRequest,
+    CoreReadRegistersRequest, CoreVectorCatchRequest, CoreWatchpointRequest, CoreWriteRegRequest,
     HandleSemihostingRequest, HandleSemihostingResponse, HandleSemihostingResult, StepRequest,
     StepResponse, StepResult, WireBreakpointCause, WireCoreDump, WireCoreInformation,
     WireCoreMetadata, WireCoreStatus, WireCoreType, WireExitErrorDetails, WireHaltReason,
     WireInstructionSet, WireRegisterId, WireRegisterReadResult, WireRegisterValue,
     WireSemihostingCommand, WireSemihostingUiEvent, WireSteppingMode, WireVectorCatchCondition,
+    WireWatchpoint, WireWatchpointKind,
 };
 use probe_rs_rpc::rtt_config::DataFormat;
 
@@ -63,9 +64,13 @@ pub async fn core_halt(
     _header: VarHeader,
     request: CoreHaltRequest,
 ) -> RpcResult<WireCoreInformation> {
-    let info = with_core!(ctx, request.sessid, request.core, |core| {
-        probe_rs_try!(core.halt(request.timeout))
-    });
+    let mut session = ctx.session(request.sessid).await;
+    // A core in a run-control group takes the rest of the group with it.
+    if session.run_control_group(request.core as usize).is_some() {
+        lift(session.halt_run_control_group(request.core as usize, request.timeout))?;
+    }
+    let mut core = lift(session.core(request.core as usize))?;
+    let info = lift(core.halt(request.timeout))?;
     Ok(convert::to_wire_core_information(info))
 }
 
@@ -74,9 +79,16 @@ pub async fn core_run(
     _header: VarHeader,
     request: CoreAccessRequest,
 ) -> NoResponse {
-    with_core!(ctx, request.sessid, request.core, |core| {
-        probe_rs_try!(core.run());
-    });
+    let mut session = ctx.session(request.sessid).await;
+    session
+        .prepare_flash_breakpoints_for_run(request.core as usize)
+        .map_err(crate::rpc::functions::convert::rpc_error_flash)?;
+    if session.run_control_group(request.core as usize).is_some() {
+        lift(session.resume_run_control_group(request.core as usize))?;
+        return Ok(());
+    }
+    let mut core = lift(session.core(request.core as usize))?;
+    lift(core.run())?;
     Ok(())
 }
 
@@ -94,6 +106,9 @@ pub async fn core_step(
         .await;
 
     let mut session = ctx.session(request.sessid).await;
+    session
+        .prepare_flash_breakpoints_for_step(request.core as usize)
+        .map_err(crate::rpc::functions::convert::rpc_error_flash)?;
     let mut core = lift(session.core(request.core as usize))?;
 
     let stepping_mode = convert::from_wire_stepping_mode(request.mode);
@@ -188,6 +203,122 @@ pub async fn core_clear_hw_bps(
     Ok(())
 }
 
+pub async fn core_set_sw_bps(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: CoreBreakpointsRequest,
+) -> RpcResult<Vec<Result<(), RpcError>>> {
+    let results = with_core!(ctx, request.sessid, request.core, |core| {
+        request
+            .addresses
+            .iter()
+            .map(|address| {
+                core.set_sw_breakpoint(*address)
+                    .map_err(crate::rpc::functions::convert::rpc_error_probe_rs)
+            })
+            .collect::<Vec<_>>()
+    });
+    Ok(results)
+}
+
+pub async fn core_clear_sw_bps(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: CoreBreakpointsRequest,
+) -> NoResponse {
+    with_core!(ctx, request.sessid, request.core, |core| {
+        for address in request.addresses {
+            probe_rs_try!(core.clear_sw_breakpoint(address).or_else(|e| match e {
+                probe_rs::Error::BreakpointOperation(probe_rs::BreakpointError::NotFound(_)) => {
+                    Ok(())
+                }
+                e => Err(e),
+            }));
+        }
+    });
+    Ok(())
+}
+
+pub async fn core_set_flash_bps(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: CoreBreakpointsRequest,
+) -> RpcResult<Vec<Result<(), RpcError>>> {
+    let mut session = ctx.session(request.sessid).await;
+    Ok(request
+        .addresses
+        .iter()
+        .map(|address| {
+            session
+                .set_flash_breakpoint(request.core as usize, *address)
+                .map_err(crate::rpc::functions::convert::rpc_error_flash)
+        })
+        .collect())
+}
+
+pub async fn core_clear_flash_bps(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: CoreBreakpointsRequest,
+) -> NoResponse {
+    let mut session = ctx.session(request.sessid).await;
+    for address in request.addresses {
+        // Clearing an address without a flash breakpoint is not an error, like for the other
+        // breakpoint kinds.
+        if session.flash_breakpoints().contains(address) {
+            session
+                .clear_flash_breakpoint(address)
+                .map_err(crate::rpc::functions::convert::rpc_error_flash)?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn core_set_hw_wp(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: CoreWatchpointRequest,
+) -> NoResponse {
+    let watchpoint = request.watchpoint;
+    let kind = convert::from_wire_watchpoint_kind(watchpoint.kind);
+    with_core!(ctx, request.sessid, request.core, |core| {
+        probe_rs_try!(core.set_hw_watchpoint(watchpoint.address, watchpoint.size, kind));
+    });
+    Ok(())
+}
+
+pub async fn core_clear_hw_wp(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: CoreWatchpointRequest,
+) -> NoResponse {
+    let watchpoint = request.watchpoint;
+    let kind = convert::from_wire_watchpoint_kind(watchpoint.kind);
+    with_core!(ctx, request.sessid, request.core, |core| {
+        probe_rs_try!(
+            core.clear_hw_watchpoint(watchpoint.address, watchpoint.size, kind)
+                .or_else(|e| match e {
+                    probe_rs::Error::BreakpointOperation(
+                        probe_rs::BreakpointError::WatchpointNotFound(_),
+                    ) => Ok(()),
+                    e => Err(e),
+                })
+        );
+    });
+    Ok(())
+}
+
+pub async fn core_hit_wp(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: CoreAccessRequest,
+) -> RpcResult<Option<WireWatchpoint>> {
+    let watchpoint = with_core!(ctx, request.sessid, request.core, |core| {
+        probe_rs_try!(core.triggered_watchpoint())
+    });
+    Ok(watchpoint.map(convert::to_wire_watchpoint))
+}
+
 pub async fn core_enable_vc(
     ctx: &mut RpcContext,
     _header: VarHeader,
@@ -200,6 +331,18 @@ pub async fn core_enable_vc(
     Ok(())
 }
 
+pub async fn core_disable_vc(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: CoreVectorCatchRequest,
+) -> NoResponse {
+    let cond = convert::from_wire_vector_catch_condition(request.condition);
+    with_core!(ctx, request.sessid, request.core, |core| {
+        probe_rs_try!(core.disable_vector_catch(cond));
+    });
+    Ok(())
+}
+
 pub async fn core_metadata(
     ctx: &mut RpcContext,
     _header: VarHeader,
@@ -459,10 +602,11 @@ pub(crate) mod convert {
         WireBreakpointCause, WireCoreInformation, WireCoreStatus, WireCoreType,
         WireExitErrorDetails, WireHaltReason, WireInstructionSet, WireRegisterId,
         WireRegisterValue, WireSemihostingCommand, WireSteppingMode, WireVectorCatchCondition,
+        WireWatchpoint, WireWatchpointKind,
     };
     use probe_rs::{
         CoreInformation, CoreStatus, HaltReason, InstructionSet, RegisterId, RegisterValue,
-        VectorCatchCondition,
+        VectorCatchCondition, Watchpoint, WatchpointKind,
         semihosting::{ExitErrorDetails, SemihostingCommand, UnknownCommandDetails},
     };
     use probe_rs_debug::SteppingMode;
@@ -639,6 +783,26 @@ pub(crate) mod convert {
         }
     }
 
+    pub(crate) fn to_wire_watchpoint(value: Watchpoint) -> WireWatchpoint {
+        WireWatchpoint {
+            address: value.address,
+            size: value.size,
+            kind: match value.kind {
+                WatchpointKind::Read => WireWatchpointKind::Read,
+                WatchpointKind::Write => WireWatchpointKind::Write,
+                WatchpointKind::Access => WireWatchpointKind::Access,
+            },
+        }
+    }
+
+    pub(crate) fn from_wire_watchpoint_kind(value: WireWatchpointKind) -> WatchpointKind {
+        match value {
+            WireWatchpointKind::Read => WatchpointKind::Read,
+            WireWatchpointKind::Write => WatchpointKind::Write,
+            WireWatchpointKind::Access => WatchpointKind::Access,
+        }
+    }
+
     pub(crate) fn from_wire_vector_catch_condition(
         value: WireVectorCatchCondition,
     ) -> VectorCatchCondition {
@@ -2,7 +2,11 @@ use std::time::Duration;
 
 use postcard_rpc::header::VarHeader;
 use probe_rs::Error;
-use probe_rs_rpc::cores::{CoresRequest, CoresStatusMap, CoresStatusResponse, HaltCoresRequest};
+use probe_rs_rpc::NoResponse;
+use probe_rs_rpc::cores::{
+    CoresRequest, CoresStatusMap, CoresStatusResponse, CreateRunControlGroupRequest,
+    CreateRunControlGroupResponse, HaltCoresRequest, RemoveRunControlGroupRequest,
+};
 
 use crate::rpc::functions::RpcContext;
 use crate::rpc::functions::core_ops::convert::to_wire_core_status;
@@ -67,6 +71,35 @@ pub async fn cores_status(
     Ok(CoresStatusMap { statuses })
 }
 
+pub async fn create_run_control_group(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: CreateRunControlGroupRequest,
+) -> CreateRunControlGroupResponse {
+    let mut session = ctx.session(request.sessid).await;
+    let cores = request
+        .cores
+        .iter()
+        .map(|core| *core as usize)
+        .collect::<Vec<_>>();
+
+    session
+        .create_run_control_group(&cores)
+        .map_err(crate::rpc::functions::convert::rpc_error_probe_rs)
+}
+
+pub async fn remove_run_control_group(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: RemoveRunControlGroupRequest,
+) -> NoResponse {
+    let mut session = ctx.session(request.sessid).await;
+
+    session
+        .remove_run_control_group(request.core as usize)
+        .map_err(crate::rpc::functions::convert::rpc_error_probe_rs)
+}
+
 fn resolve_core_indices(
     session: &probe_rs::Session,
     cores: Option<&[u32]>,
@@ -82,22 +115,17 @@ fn operate_halt(
     core_index: usize,
     timeout: Duration,
 ) -> Result<probe_rs::CoreStatus, Error> {
-    let mut core = session.core(core_index)?;
-    if !core.core_halted()? {
-        core.halt(timeout)?;
-    }
-    core.status()
+    // Cores in a run-control group halt together, the others on their own.
+    session.halt_run_control_group(core_index, timeout)?;
+    session.core(core_index)?.status()
 }
 
 fn operate_resume(
     session: &mut probe_rs::Session,
     core_index: usize,
 ) -> Result<probe_rs::CoreStatus, Error> {
-    let mut core = session.core(core_index)?;
-    if core.core_halted()? {
-        core.run()?;
-    }
-    core.status()
+    session.resume_run_control_group(core_index)?;
+    session.core(core_index)?.status()
 }
 
 fn operate_status(
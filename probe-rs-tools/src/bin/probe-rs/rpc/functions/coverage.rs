@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::time::Duration;
+
+use anyhow::Context;
+use postcard_rpc::header::VarHeader;
+use probe_rs::architecture::arm::{
+    component::{Dwt, EtmConfig, EtmParameters, MtbBranch, enable_tracing, find_component},
+    dp::DpAddress,
+    memory::PeripheralType,
+    trace::{CodeImage, Etmv4Decoder, TraceElement},
+};
+use probe_rs::{Architecture, CoreStatus, HaltReason, MemoryInterface, Session};
+use probe_rs_rpc::Key;
+use probe_rs_rpc::coverage::{
+    CoverageMethod, CoverageSample, ExecutedRange, StartCoverageRequest, StartCoverageResponse,
+    TakeCoverageRequest, TakeCoverageResponse, WireCoverage,
+};
+
+use crate::rpc::functions::{DebugStatesMap, RpcContext, RpcSpawnContext, convert::lift};
+use crate::rpc::utils::run_loop::RunLoopPoller;
+
+/// How often the executed code is recorded.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The number of PCSR samples taken per poll.
+const PCSR_SAMPLES_PER_POLL: usize = 64;
+
+pub async fn start_coverage(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: StartCoverageRequest,
+) -> StartCoverageResponse {
+    let sessid = request.sessid;
+    let recorder = {
+        let mut session = ctx.session(sessid).await;
+        lift(Recorder::new(&mut session, request))?
+    };
+    let method = recorder.method();
+
+    ctx.with_server_debug_state_mut(sessid, |state| {
+        state.coverage = Some(CoverageCollector {
+            recorder,
+            samples: HashMap::new(),
+            ranges: HashMap::new(),
+        });
+    })
+    .await;
+
+    Ok(method)
+}
+
+pub async fn take_coverage(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: TakeCoverageRequest,
+) -> TakeCoverageResponse {
+    ctx.with_server_debug_state_mut(request.sessid, |state| match &mut state.coverage {
+        Some(collector) => Ok(collector.take()),
+        None => Err("Coverage collection was not started".into()),
+    })
+    .await
+}
+
+/// Records the code executed while the run loop runs the target.
+pub struct CoverageCollector {
+    recorder: Recorder,
+    samples: HashMap<u64, u64>,
+    ranges: HashMap<Range<u64>, u64>,
+}
+
+enum Recorder {
+    Halt,
+    Pcsr,
+    Mtb {
+        buffer: Range<u64>,
+    },
+    Etm {
+        image: CodeImage,
+        parameters: EtmParameters,
+    },
+}
+
+impl Recorder {
+    fn new(session: &mut Session, request: StartCoverageRequest) -> anyhow::Result<Self> {
+        let components = if session.architecture() == Architecture::Arm {
+            session.get_arm_components(DpAddress::Default)?
+        } else {
+            Vec::new()
+        };
+        let available = |peripheral| find_component(&components, peripheral).is_ok();
+
+        let method = match request.method {
+            CoverageMethod::Auto
+                if available(PeripheralType::Etm) && available(PeripheralType::Tmc) =>
+            {
+                CoverageMethod::Etm
+            }
+            CoverageMethod::Auto
+                if request.mtb_buffer.is_some() && available(PeripheralType::Mtb) =>
+            {
+                CoverageMethod::Mtb
+            }
+            CoverageMethod::Auto if available(PeripheralType::Dwt) => CoverageMethod::Pcsr,
+            CoverageMethod::Auto => CoverageMethod::Halt,
+            method => method,
+        };
+
+        Ok(match method {
+            CoverageMethod::Auto | CoverageMethod::Halt => Recorder::Halt,
+            CoverageMethod::Pcsr => Recorder::Pcsr,
+            CoverageMethod::Mtb => Recorder::Mtb {
+                buffer: request
+                    .mtb_buffer
+                    .context("Collecting coverage with the MTB requires an MTB buffer")?,
+            },
+            CoverageMethod::Etm => {
+                // The code is read back from the target, which is what the ETM actually traced.
+                let mut core = session.core(0)?;
+                let mut image = CodeImage::new();
+                for range in request.code {
+                    let mut code = vec![0; (range.end - range.start) as usize];
+                    core.read(range.start, &mut code).with_context(|| {
+                        format!("Failed to read the code at {:#010x}", range.start)
+                    })?;
+                    image.add_segment(range.start, code);
+                }
+                Recorder::Etm {
+                    image,
+                    parameters: EtmParameters::default(),
+                }
+            }
+        })
+    }
+
+    fn method(&self) -> CoverageMethod {
+        match self {
+            Recorder::Halt => CoverageMethod::Halt,
+            Recorder::Pcsr => CoverageMethod::Pcsr,
+            Recorder::Mtb { .. } => CoverageMethod::Mtb,
+            Recorder::Etm { .. } => CoverageMethod::Etm,
+        }
+    }
+}
+
+impl CoverageCollector {
+    /// Returns the code recorded so far, and starts over.
+    fn take(&mut self) -> WireCoverage {
+        WireCoverage {
+            samples: self
+                .samples
+                .drain()
+                .map(|(address, count)| CoverageSample { address, count })
+                .collect(),
+            ranges: self
+                .ranges
+                .drain()
+                .map(|(range, count)| ExecutedRange { range, count })
+                .collect(),
+        }
+    }
+
+    /// Sets up the recording. Trace units are reset together with the target, so this has to be
+    /// done every time the target was reset.
+    fn start(&mut self, session: &mut Session, core_id: usize) -> anyhow::Result<()> {
+        match &mut self.recorder {
+            Recorder::Halt => {}
+            Recorder::Pcsr => {
+                enable_tracing(&mut session.core(core_id)?)?;
+                with_dwt(session, |dwt| dwt.enable())?;
+            }
+            Recorder::Mtb { buffer } => session.setup_mtb_tracing(buffer.clone())?,
+            Recorder::Etm { parameters, .. } => {
+                *parameters = session.setup_etm_tracing(core_id, &EtmConfig::default())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn poll(&mut self, session: &mut Session, core_id: usize) -> anyhow::Result<()> {
+        match &self.recorder {
+            Recorder::Halt => {
+                let mut core = session.core(core_id)?;
+                if core.core_halted()? {
+                    return Ok(());
+                }
+
+                core.halt(Duration::from_millis(10))?;
+                let pc = core.read_core_reg::<u64>(core.program_counter())?;
+                // The core may have halted by itself in the meantime, e.g. for semihosting. The
+                // run loop has to see that halt, so the core is only resumed if we halted it.
+                if core.status()? == CoreStatus::Halted(HaltReason::Request) {
+                    core.run()?;
+                }
+                *self.samples.entry(pc).or_default() += 1;
+            }
+            Recorder::Pcsr => {
+                let mut samples = [0; PCSR_SAMPLES_PER_POLL];
+                with_dwt(session, |dwt| {
+                    for sample in &mut samples {
+                        *sample = dwt.read_pcsr()?;
+                    }
+                    Ok(())
+                })?;
+
+                if samples.iter().all(|&sample| sample == 0) {
+                    tracing::warn!(
+                        "The DWT does not implement PC sampling, falling back to halting the core"
+                    );
+                    self.recorder = Recorder::Halt;
+                    return Ok(());
+                }
+
+                // The sample is all ones while the core is halted or sleeping.
+                for sample in samples.into_iter().filter(|&sample| sample != u32::MAX) {
+                    *self.samples.entry(u64::from(sample)).or_default() += 1;
+                }
+            }
+            Recorder::Mtb { buffer } => {
+                let buffer = buffer.clone();
+                let branches = session.read_mtb_branches()?;
+                session.setup_mtb_tracing(buffer)?;
+                self.add_branches(&branches);
+            }
+            Recorder::Etm { .. } => {
+                self.read_etm_trace(session)?;
+                self.start(session, core_id)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the rest of the recording.
+    fn exit(&mut self, session: &mut Session) -> anyhow::Result<()> {
+        match &self.recorder {
+            Recorder::Halt | Recorder::Pcsr => {}
+            Recorder::Mtb { .. } => {
+                let branches = session.read_mtb_branches()?;
+                self.add_branches(&branches);
+            }
+            Recorder::Etm { .. } => self.read_etm_trace(session)?,
+        }
+
+        Ok(())
+    }
+
+    /// Records the code executed between consecutive branches.
+    fn add_branches(&mut self, branches: &[MtbBranch]) {
+        for pair in branches.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            if to.trace_start || from.target > to.source {
+                continue;
+            }
+            *self.ranges.entry(from.target..to.source + 1).or_default() += 1;
+        }
+    }
+
+    fn read_etm_trace(&mut self, session: &mut Session) -> anyhow::Result<()> {
+        let Recorder::Etm { image, parameters } = &self.recorder else {
+            return Ok(());
+        };
+
+        let trace = session.read_etm_trace_data()?;
+        for element in Etmv4Decoder::new(&trace, image, *parameters) {
+            match element {
+                TraceElement::Instructions(range) => {
+                    *self.ranges.entry(range.start..range.end).or_default() += 1;
+                }
+                TraceElement::UnsupportedInstructionSet { address } => anyhow::bail!(
+                    "The core executed A32 or A64 code at {address:#010x}, \
+                    only the trace of T32 code can be decoded"
+                ),
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn with_dwt<R>(
+    session: &mut Session,
+    f: impl FnOnce(&mut Dwt<'_>) -> Result<R, probe_rs::architecture::arm::ArmError>,
+) -> anyhow::Result<R> {
+    let components = session.get_arm_components(DpAddress::Default)?;
+    let component = find_component(&components, PeripheralType::Dwt)?;
+    let interface = session.get_arm_interface()?;
+
+    Ok(f(&mut Dwt::new(interface, component))?)
+}
+
+/// Runs the coverage collector of a session alongside the run loop.
+///
+/// The collector is taken out of the session's debug state for the duration of the run, and put
+/// back when the run loop exits.
+pub struct CoveragePoller {
+    debug_states: DebugStatesMap,
+    sessid: Key<probe_rs_rpc::Session>,
+    collector: Option<CoverageCollector>,
+}
+
+impl CoveragePoller {
+    /// Returns a poller if coverage collection was started for the session.
+    pub fn take(ctx: &RpcSpawnContext, sessid: Key<probe_rs_rpc::Session>) -> Option<Self> {
+        let debug_states = ctx.debug_states();
+        let collector = debug_states
+            .blocking_lock()
+            .get_mut(&sessid)
+            .and_then(|state| state.coverage.take())?;
+
+        Some(Self {
+            debug_states,
+            sessid,
+            collector: Some(collector),
+        })
+    }
+}
+
+impl RunLoopPoller for CoveragePoller {
+    fn start(&mut self, session: &mut Session, core_id: usize) -> anyhow::Result<()> {
+        match &mut self.collector {
+            Some(collector) => collector.start(session, core_id),
+            None => Ok(()),
+        }
+    }
+
+    fn poll(&mut self, session: &mut Session, core_id: usize) -> anyhow::Result<Duration> {
+        if let Some(collector) = &mut self.collector {
+            collector.poll(session, core_id)?;
+        }
+        Ok(POLL_INTERVAL)
+    }
+
+    fn exit(&mut self, session: &mut Session, _core_id: usize) -> anyhow::Result<()> {
+        let Some(mut collector) = self.collector.take() else {
+            return Ok(());
+        };
+        let result = collector.exit(session);
+
+        self.debug_states
+            .blocking_lock()
+            .entry(self.sessid)
+            .or_default()
+            .coverage = Some(collector);
+
+        result
+    }
+}
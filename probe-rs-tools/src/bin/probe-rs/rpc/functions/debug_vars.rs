@@ -1,11 +1,14 @@
 use postcard_rpc::header::VarHeader;
+use probe_rs::MemoryInterface;
 use probe_rs_debug::{
-    DebugInfo, DebugRegisters, ObjectRef, StackFrameInfo, Variable, VariableCache, VariableName,
+    DebugInfo, DebugRegisters, ObjectRef, StackFrameInfo, Variable, VariableCache,
+    VariableLocation, VariableName,
 };
 use probe_rs_rpc::debug_vars::{
-    ClearCoreDebugStateRequest, EvaluateRequest, EvaluateResponse, LoadSvdRequest, LoadSvdResponse,
-    ScopesRequest, ScopesResponse, SetVariableRequest, SetVariableResult, VariablesRequest,
-    VariablesResponse, WireEvaluateResponse, WireScope, WireSetVariableResponse, WireVariable,
+    ClearCoreDebugStateRequest, DataBreakpointInfoRequest, DataBreakpointInfoResponse,
+    EvaluateRequest, EvaluateResponse, LoadSvdRequest, LoadSvdResponse, ScopesRequest,
+    ScopesResponse, SetVariableRequest, SetVariableResult, VariablesRequest, VariablesResponse,
+    WireDataBreakpointInfo, WireEvaluateResponse, WireScope, WireSetVariableResponse, WireVariable,
 };
 
 use crate::rpc::functions::{RpcContext, convert::lift};
@@ -66,6 +69,17 @@ pub async fn scopes(
                 });
             }
 
+            if let Some(tasks_cache) = &core_state.embassy_tasks {
+                scopes.push(WireScope {
+                    name: "Tasks".to_string(),
+                    presentation_hint: None,
+                    variables_reference: i64::from(tasks_cache.root_variable().variable_key()),
+                    expensive: true,
+                    line: None,
+                    column: None,
+                });
+            }
+
             if let Some(frame) = core_state.stack_frames.iter().find(|f| f.id == frame_ref) {
                 // Registers scope: reuse the frame id as its variables_reference.
                 scopes.push(WireScope {
@@ -183,7 +197,13 @@ pub async fn variables(
         return Ok(dap_variables);
     }
 
-    if let Some(search_cache) = core_state.static_variables.as_mut()
+    // The statics and the futures of the embassy tasks are evaluated with the registers of the
+    // innermost frame.
+    let search_cache = match core_state.static_variables.as_mut() {
+        Some(statics) if statics.get_variable_by_key(variable_ref).is_some() => Some(statics),
+        _ => core_state.embassy_tasks.as_mut(),
+    };
+    if let Some(search_cache) = search_cache
         && let Some(search_variable) = search_cache.get_variable_by_key(variable_ref)
     {
         parent_variable = Some(search_variable);
@@ -364,8 +384,9 @@ pub async fn set_variable(
 }
 
 /// Resolve a DAP evaluate expression against a `VariableCache`, expanding the
-/// single-root deferred case first. Returns `None` if the expression names no
-/// variable in `cache`.
+/// single-root deferred case first. The expression is a variable key, or a
+/// variable name followed by `.field` accessors, like `state.counter`. Returns
+/// `None` if the expression names no variable in `cache`.
 fn resolve_expression(
     debug_info: &DebugInfo,
     core: &mut probe_rs::Core,
@@ -382,10 +403,24 @@ fn resolve_expression(
         }
     }
     let mut variable = if let Ok(key) = expression.parse::<ObjectRef>() {
-        cache.get_variable_by_key(key)
+        cache.get_variable_by_key(key)?
     } else {
-        cache.get_variable_by_name(&VariableName::Named(expression.to_string()))
-    }?;
+        let mut fields = expression.split('.');
+        let name = fields.next()?;
+        let mut variable = cache.get_variable_by_name(&VariableName::Named(name.to_string()))?;
+        for field in fields {
+            if variable.variable_node_type.is_deferred() && !cache.has_children(&variable) {
+                debug_info
+                    .cache_deferred_variables(cache, core, &mut variable, frame_info)
+                    .ok()?;
+            }
+            variable = cache.get_variable_by_name_and_parent(
+                &VariableName::Named(field.to_string()),
+                variable.variable_key(),
+            )?;
+        }
+        variable
+    };
     let (vr, named, indexed) = variable_reference(&variable, cache);
     variable.extract_value(core, cache);
     cache.update_variable(&variable).ok()?;
@@ -511,5 +546,80 @@ pub async fn evaluate(
         return Ok(resp);
     }
 
+    // Paths qualified by their module, like `my_mod::STATE.counter`, and array indices are
+    // only resolved for scalar statics.
+    if let Ok(variable) = debug_info.resolve_static_variable(&request.expression) {
+        let mut bytes = vec![0; variable.scalar_type.byte_size()];
+        if core.read(variable.address, &mut bytes).is_ok() {
+            return Ok(WireEvaluateResponse {
+                result: variable
+                    .scalar_type
+                    .decode(&bytes, debug_info.endianness())
+                    .to_string(),
+                type_: None,
+                variables_reference: 0,
+                named_variables: None,
+                indexed_variables: None,
+                memory_reference: Some(VariableLocation::Address(variable.address).to_string()),
+            });
+        }
+    }
+
     Ok(invalid())
 }
+
+/// Resolve the memory backing a variable for a DAP `dataBreakpointInfo`
+/// request. Children are looked up by name under `variables_reference`;
+/// top-level names are searched in the frame's locals, then in the statics.
+pub async fn data_breakpoint_info(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: DataBreakpointInfoRequest,
+) -> DataBreakpointInfoResponse {
+    let variable = ctx
+        .with_core_debug_state_mut(request.sessid, request.core, |core_state| {
+            let variable_name = VariableName::Named(request.name.clone());
+            let frame_ref = request
+                .frame_id
+                .map(|id| ObjectRef::from(id as i64))
+                .or_else(|| core_state.stack_frames.first().map(|f| f.id));
+
+            let frame_caches = core_state
+                .stack_frames
+                .iter()
+                .filter(|frame| {
+                    request.variables_reference.is_some() || Some(frame.id) == frame_ref
+                })
+                .filter_map(|frame| frame.local_variables.as_ref());
+            let mut caches = frame_caches.chain(core_state.static_variables.as_ref());
+
+            caches.find_map(|cache| match request.variables_reference {
+                Some(parent) => {
+                    cache.get_variable_by_name_and_parent(&variable_name, ObjectRef::from(parent))
+                }
+                None => cache.get_variable_by_name(&variable_name),
+            })
+        })
+        .await?;
+
+    let Some(variable) = variable else {
+        Err(format!(
+            "No variable information found for {}!",
+            request.name
+        ))?
+    };
+
+    let VariableLocation::Address(address) = variable.memory_location else {
+        return Ok(None);
+    };
+    let Some(size) = variable.byte_size.filter(|size| *size > 0) else {
+        return Ok(None);
+    };
+
+    Ok(Some(WireDataBreakpointInfo {
+        name: variable.name.to_string(),
+        type_: variable.type_name(),
+        address,
+        size,
+    }))
+}
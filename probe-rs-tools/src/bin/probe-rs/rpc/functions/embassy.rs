@@ -0,0 +1,65 @@
+use postcard_rpc::header::VarHeader;
+use probe_rs::Error;
+use probe_rs_rpc::embassy::{EmbassyTasksRequest, EmbassyTasksResponse};
+
+use crate::rpc::functions::{RpcContext, convert::lift};
+
+/// Reads the spawned tasks of the embassy executor from their task pools,
+/// using the debug info preloaded with `load_debug_info`.
+pub async fn embassy_tasks(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: EmbassyTasksRequest,
+) -> EmbassyTasksResponse {
+    let Some((embassy, debug_info)) = ctx
+        .with_server_debug_state_mut(request.sessid, |state| {
+            Some((state.embassy()?, state.debug_info.clone()?))
+        })
+        .await
+    else {
+        return Ok(None);
+    };
+
+    let mut session = ctx.session(request.sessid).await;
+    let tasks = lift(session.halted_access(|session| {
+        let mut core = session.core(request.core as usize)?;
+        embassy
+            .tasks(&debug_info, &mut core)
+            .map_err(|error| Error::Other(error.to_string()))
+    }))?;
+
+    Ok(Some(convert::to_wire_embassy_tasks(&tasks)))
+}
+
+pub(crate) mod convert {
+    use probe_rs_debug::embassy::EmbassyTasks;
+    use probe_rs_rpc::embassy::{WireEmbassyExecutor, WireEmbassyTask, WireEmbassyTasks};
+
+    pub(crate) fn to_wire_embassy_tasks(tasks: &EmbassyTasks) -> WireEmbassyTasks {
+        WireEmbassyTasks {
+            tasks: tasks
+                .tasks
+                .iter()
+                .map(|task| WireEmbassyTask {
+                    address: task.address,
+                    name: task.name.clone(),
+                    future_type: task.future_type.clone(),
+                    future_address: task.future_address,
+                    spawned: task.state.spawned,
+                    run_queued: task.state.run_queued,
+                    timer_queued: task.state.timer_queued,
+                    executor: task.executor,
+                })
+                .collect(),
+            executors: tasks
+                .executors
+                .iter()
+                .map(|executor| WireEmbassyExecutor {
+                    address: executor.address,
+                    run_queue: executor.run_queue.clone(),
+                })
+                .collect(),
+            arena: tasks.arena.map(|arena| (arena.used, arena.size)),
+        }
+    }
+}
@@ -15,7 +15,7 @@ use tokio::sync::mpsc::Sender;
 use probe_rs_rpc::{NoResponse, ProgressEventTopic};
 
 use crate::{
-    rpc::functions::{RpcContext, RpcSpawnContext, convert::lift},
+    rpc::functions::{RpcContext, RpcSpawnContext, convert::lift, stack_paint::paint_stack},
     util::flash::build_loader,
 };
 
@@ -152,6 +152,9 @@ pub async fn boot(ctx: &mut RpcContext, _header: VarHeader, request: BootRequest
         &mut session,
         request.core_id as usize,
     ))?;
+    if let Some(region) = request.paint_stack {
+        lift(paint_stack(&mut session, request.core_id as usize, region))?;
+    }
     if request.resume {
         lift(session.resume_all_cores())?;
     }
@@ -0,0 +1,35 @@
+use postcard_rpc::header::VarHeader;
+use probe_rs::architecture::arm::{component::TraceSink, swo::SwoConfig};
+use probe_rs_rpc::NoResponse;
+use probe_rs_rpc::itm::{ItmSink, ReadItmRequest, ReadItmResponse, StartItmRequest};
+
+use crate::rpc::functions::{RpcContext, convert::lift};
+
+pub async fn start_itm(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: StartItmRequest,
+) -> NoResponse {
+    let mut session = ctx.session(request.sessid).await;
+
+    session
+        .setup_tracing(request.core as usize, trace_sink(request.sink))
+        .map_err(crate::rpc::functions::convert::rpc_error_probe_rs)
+}
+
+pub async fn read_itm(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: ReadItmRequest,
+) -> ReadItmResponse {
+    let mut session = ctx.session(request.sessid).await;
+
+    lift(session.read_trace_data())
+}
+
+pub(crate) fn trace_sink(sink: ItmSink) -> TraceSink {
+    match sink {
+        ItmSink::TraceMemory => TraceSink::TraceMemory,
+        ItmSink::Swo { clock, baud } => TraceSink::Swo(SwoConfig::new(clock).set_baud(baud)),
+    }
+}
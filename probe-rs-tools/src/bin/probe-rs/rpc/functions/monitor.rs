@@ -2,7 +2,10 @@ use std::time::Duration;
 
 use crate::rpc::{
     ObjectStorageSlot,
-    functions::{MultiTopicPublisher, MultiTopicWriter, RpcSpawnContext, WireTxImpl},
+    functions::{
+        MultiTopicPublisher, MultiTopicWriter, RpcSpawnContext, WireTxImpl,
+        coverage::CoveragePoller, itm::trace_sink, stack_paint::paint_stack,
+    },
     utils::{
         run_loop::{ReturnReason, RunLoop, RunLoopPoller, VectorCatchConfig},
         semihosting::SemihostingFileManager,
@@ -10,13 +13,17 @@ use crate::rpc::{
 };
 use anyhow::Context;
 use postcard_rpc::{header::VarHeader, server::Sender};
-use probe_rs::{BreakpointCause, Core, HaltReason, semihosting::SemihostingCommand};
+use probe_rs::{
+    Architecture, BreakpointCause, Core, HaltReason, Session, architecture::riscv::registers,
+    semihosting::SemihostingCommand,
+};
+use probe_rs_rpc::itm::{ItmEvent, ItmSink};
 use probe_rs_rpc::monitor::{
     ChannelInfo, MonitorExitReason, MonitorMode, MonitorRequest, RttEvent, SemihostingEvent,
     SemihostingExitError,
 };
 use probe_rs_rpc::semihosting_options::SemihostingOptions;
-use probe_rs_rpc::{MonitorEndpoint, RttTopic, SemihostingTopic};
+use probe_rs_rpc::{ItmTopic, MonitorEndpoint, RttTopic, SemihostingTopic};
 use tokio::sync::mpsc::{self, error::SendError};
 use tokio_util::sync::CancellationToken;
 
@@ -53,6 +60,7 @@ pub async fn monitor(
 pub(crate) struct MonitorSender {
     rtt: mpsc::Sender<RttEvent>,
     semihosting_output: mpsc::Sender<SemihostingEvent>,
+    itm: mpsc::Sender<ItmEvent>,
 }
 impl MonitorSender {
     pub(crate) fn send_semihosting_event(
@@ -65,11 +73,16 @@ impl MonitorSender {
     pub(crate) fn send_rtt_event(&self, event: RttEvent) -> Result<(), SendError<RttEvent>> {
         self.rtt.blocking_send(event)
     }
+
+    pub(crate) fn send_itm_event(&self, event: ItmEvent) -> Result<(), SendError<ItmEvent>> {
+        self.itm.blocking_send(event)
+    }
 }
 
 pub(crate) struct MonitorPublisher {
     rtt: <RttTopic as MultiTopicWriter>::Publisher,
     semihosting_output: <SemihostingTopic as MultiTopicWriter>::Publisher,
+    itm: <ItmTopic as MultiTopicWriter>::Publisher,
 }
 
 impl MultiTopicWriter for MonitorSender {
@@ -78,16 +91,19 @@ impl MultiTopicWriter for MonitorSender {
 
     fn create(token: CancellationToken) -> (Self::Sender, Self::Publisher) {
         let (rtt_sender, rtt_publisher) = RttTopic::create(token.clone());
-        let (semihosting_sender, semihosting_publisher) = SemihostingTopic::create(token);
+        let (semihosting_sender, semihosting_publisher) = SemihostingTopic::create(token.clone());
+        let (itm_sender, itm_publisher) = ItmTopic::create(token);
 
         (
             Self {
                 rtt: rtt_sender,
                 semihosting_output: semihosting_sender,
+                itm: itm_sender,
             },
             MonitorPublisher {
                 rtt: rtt_publisher,
                 semihosting_output: semihosting_publisher,
+                itm: itm_publisher,
             },
         )
     }
@@ -97,7 +113,8 @@ impl MultiTopicPublisher for MonitorPublisher {
     async fn publish(self, sender: &Sender<WireTxImpl>) {
         tokio::join!(
             self.rtt.publish(sender),
-            self.semihosting_output.publish(sender)
+            self.semihosting_output.publish(sender),
+            self.itm.publish(sender)
         );
     }
 }
@@ -127,6 +144,22 @@ fn monitor_impl(
     {
         let mut session = shared_session.session_blocking();
         prepare_monitor_mode(&request.mode, &mut session, run_loop.core_id)?;
+
+        // The stack is painted before the program starts using it, which a running program
+        // already does.
+        if let MonitorMode::Run(_) = request.mode
+            && let Some(region) = request.options.paint_stack.clone()
+        {
+            paint_stack(&mut session, run_loop.core_id, region)?;
+        }
+
+        // The MTB is reset together with the target, so it is only set up once the target has
+        // been prepared.
+        if let Some(buffer) = request.options.branch_trace_buffer {
+            session
+                .setup_mtb_tracing(buffer)
+                .context("Failed to set up the MTB branch trace")?;
+        }
     }
 
     let poller = client_key.map(|client| RttPoller {
@@ -139,6 +172,17 @@ fn monitor_impl(
         },
     });
 
+    let coverage = CoveragePoller::take(&ctx, request.sessid);
+
+    let itm = request.options.itm.map(|sink| ItmPoller {
+        sink,
+        sender: |event| {
+            sender
+                .send_itm_event(event)
+                .context("Failed to send ITM event")
+        },
+    });
+
     let exit_reason = run_loop.run_until(
         &shared_session,
         VectorCatchConfig {
@@ -147,7 +191,7 @@ fn monitor_impl(
             catch_svc: request.options.catch_svc,
             catch_hlt: request.options.catch_hlt,
         },
-        poller,
+        (poller, (coverage, itm)),
         None,
         |halt_reason, core| semihosting_sink.handle_halt(halt_reason, core),
     )?;
@@ -173,15 +217,17 @@ impl<S> RunLoopPoller for RttPoller<S>
 where
     S: FnMut(RttEvent) -> anyhow::Result<()>,
 {
-    fn start(&mut self, core: &mut Core<'_>) -> anyhow::Result<()> {
+    fn start(&mut self, session: &mut Session, core_id: usize) -> anyhow::Result<()> {
         if self.clear_control_block {
             let mut rtt_client = self.rtt_client.get_blocking();
-            rtt_client.clear_control_block(core)?;
+            rtt_client.clear_control_block(&mut session.core(core_id)?)?;
         }
         Ok(())
     }
 
-    fn poll(&mut self, core: &mut Core<'_>) -> anyhow::Result<Duration> {
+    fn poll(&mut self, session: &mut Session, core_id: usize) -> anyhow::Result<Duration> {
+        let mut core = session.core(core_id)?;
+        let core = &mut core;
         let mut rtt_client = self.rtt_client.get_blocking();
         if !rtt_client.is_attached() && matches!(rtt_client.try_attach(core), Ok(true)) {
             tracing::debug!("Attached to RTT");
@@ -225,9 +271,59 @@ where
         Ok(next_poll)
     }
 
-    fn exit(&mut self, core: &mut Core<'_>) -> anyhow::Result<()> {
+    fn exit(&mut self, session: &mut Session, core_id: usize) -> anyhow::Result<()> {
         let mut rtt_client = self.rtt_client.get_blocking();
-        rtt_client.clean_up(core)?;
+        rtt_client.clean_up(&mut session.core(core_id)?)?;
+        Ok(())
+    }
+}
+
+/// Reads the ITM trace data while the run loop runs the target.
+pub struct ItmPoller<S>
+where
+    S: FnMut(ItmEvent) -> anyhow::Result<()>,
+{
+    pub sink: ItmSink,
+    pub sender: S,
+}
+
+impl<S> ItmPoller<S>
+where
+    S: FnMut(ItmEvent) -> anyhow::Result<()>,
+{
+    fn read(&mut self, session: &mut Session) -> anyhow::Result<bool> {
+        let bytes = session.read_trace_data()?;
+        if bytes.is_empty() {
+            return Ok(false);
+        }
+
+        (self.sender)(ItmEvent { bytes })?;
+        Ok(true)
+    }
+}
+
+impl<S> RunLoopPoller for ItmPoller<S>
+where
+    S: FnMut(ItmEvent) -> anyhow::Result<()>,
+{
+    fn start(&mut self, session: &mut Session, core_id: usize) -> anyhow::Result<()> {
+        // Tracing is reset together with the target, so it is set up once the target has been
+        // prepared.
+        session
+            .setup_tracing(core_id, trace_sink(self.sink))
+            .context("Failed to set up ITM tracing")
+    }
+
+    fn poll(&mut self, session: &mut Session, _core_id: usize) -> anyhow::Result<Duration> {
+        if self.read(session)? {
+            Ok(Duration::ZERO)
+        } else {
+            Ok(Duration::from_millis(10))
+        }
+    }
+
+    fn exit(&mut self, session: &mut Session, _core_id: usize) -> anyhow::Result<()> {
+        self.read(session)?;
         Ok(())
     }
 }
@@ -251,8 +347,9 @@ impl<F: FnMut(SemihostingEvent)> MonitorEventHandler<F> {
         core: &mut Core<'_>,
     ) -> anyhow::Result<Option<MonitorExitReason>> {
         let HaltReason::Breakpoint(BreakpointCause::Semihosting(cmd)) = halt_reason else {
-            return Ok(Some(MonitorExitReason::UnexpectedExit(format!(
-                "{halt_reason:?}"
+            return Ok(Some(MonitorExitReason::UnexpectedExit(describe_halt(
+                halt_reason,
+                core,
             ))));
         };
 
@@ -295,3 +392,16 @@ impl<F: FnMut(SemihostingEvent)> MonitorEventHandler<F> {
         }
     }
 }
+
+/// Describes an unexpected halt, with the trap cause of exceptions caught on RISC-V cores.
+fn describe_halt(halt_reason: HaltReason, core: &mut Core<'_>) -> String {
+    if halt_reason == HaltReason::Exception && core.architecture() == Architecture::Riscv {
+        let mcause = core.read_core_reg::<u64>(registers::MCAUSE);
+        let mtval = core.read_core_reg::<u64>(registers::MTVAL);
+        if let (Ok(mcause), Ok(mtval)) = (mcause, mtval) {
+            return format!("{halt_reason:?} (mcause: {mcause:#x}, mtval: {mtval:#x})");
+        }
+    }
+
+    format!("{halt_reason:?}")
+}
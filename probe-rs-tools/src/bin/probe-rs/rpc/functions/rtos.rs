@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use postcard_rpc::header::VarHeader;
+use probe_rs::Error;
+use probe_rs_debug::rtos::RtosAwareness;
+use probe_rs_rpc::rtos::{RtosThreadsRequest, RtosThreadsResponse, WireRtosThreads};
+
+use crate::rpc::{
+    Key, Session,
+    functions::{RpcContext, convert::lift},
+};
+
+/// Looks up the RTOS awareness for the cores of a session, or only for `core` if given. Cores
+/// whose program does not use a supported RTOS are left out.
+pub(crate) async fn rtos_for_cores(
+    ctx: &mut RpcContext,
+    sessid: Key<Session>,
+    core: Option<u32>,
+) -> HashMap<usize, Arc<dyn RtosAwareness>> {
+    let cores = ctx.session(sessid).await.list_cores();
+    ctx.with_server_debug_state_mut(sessid, |state| {
+        cores
+            .into_iter()
+            .filter(|(idx, _)| core.is_none_or(|core| core as usize == *idx))
+            .filter_map(|(idx, core_type)| Some((idx, state.rtos(core_type)?)))
+            .collect()
+    })
+    .await
+}
+
+/// Reads the threads of the RTOS from the kernel's data structures, using
+/// the debug info preloaded with `load_debug_info`.
+pub async fn rtos_threads(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: RtosThreadsRequest,
+) -> RtosThreadsResponse {
+    let Some(rtos) = rtos_for_cores(ctx, request.sessid, Some(request.core))
+        .await
+        .remove(&(request.core as usize))
+    else {
+        return Ok(None);
+    };
+
+    let mut session = ctx.session(request.sessid).await;
+    let threads = lift(session.halted_access(|session| {
+        let mut core = session.core(request.core as usize)?;
+        let core_registers = core.registers();
+        rtos.threads(&mut core, core_registers)
+            .map_err(|error| Error::Other(error.to_string()))
+    }))?;
+
+    Ok(Some(WireRtosThreads {
+        rtos: rtos.name().to_string(),
+        threads: threads.iter().map(convert::to_wire_rtos_thread).collect(),
+    }))
+}
+
+pub(crate) mod convert {
+    use probe_rs_debug::rtos::{RtosThread, ThreadState};
+    use probe_rs_rpc::rtos::{WireRtosThread, WireThreadState};
+
+    use crate::rpc::functions::stack_trace::convert::to_wire_debug_register;
+
+    pub(crate) fn to_wire_rtos_thread(thread: &RtosThread) -> WireRtosThread {
+        WireRtosThread {
+            id: thread.id,
+            name: thread.name.clone(),
+            priority: thread.priority,
+            state: match thread.state {
+                ThreadState::Running => WireThreadState::Running,
+                ThreadState::Ready => WireThreadState::Ready,
+                ThreadState::Blocked => WireThreadState::Blocked,
+                ThreadState::Suspended => WireThreadState::Suspended,
+                ThreadState::Deleted => WireThreadState::Deleted,
+            },
+            registers: thread
+                .registers
+                .iter()
+                .flat_map(|registers| registers.0.iter().map(to_wire_debug_register))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::ops::Range;
+
+use anyhow::Context;
+use postcard_rpc::header::VarHeader;
+use probe_rs::{Error, Session};
+use probe_rs_debug::stack_paint::{StackUsage, paint_stack as paint};
+use probe_rs_rpc::stack_paint::{
+    StackUsageRequest, StackUsageResponse, WireStackUsageReport, WireThreadStackUsage,
+};
+
+use crate::rpc::functions::{RpcContext, convert::lift, rtos::rtos_for_cores};
+
+/// Paints the stack at `region`, once the core has been prepared to run the program.
+pub(crate) fn paint_stack(
+    session: &mut Session,
+    core_id: usize,
+    region: Range<u64>,
+) -> anyhow::Result<()> {
+    tracing::info!("Painting the stack at {region:#010x?}");
+    let mut core = session.core(core_id)?;
+    paint(&mut core, region).context("Failed to paint the stack")
+}
+
+/// Measures the usage of the painted stack, and of the stacks of the RTOS threads, using the
+/// debug info preloaded with `load_debug_info`.
+pub async fn stack_usage(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: StackUsageRequest,
+) -> StackUsageResponse {
+    let rtos = rtos_for_cores(ctx, request.sessid, Some(request.core))
+        .await
+        .remove(&(request.core as usize));
+
+    let mut session = ctx.session(request.sessid).await;
+    lift(session.halted_access(|session| {
+        let mut core = session.core(request.core as usize)?;
+        let to_error = |error: probe_rs_debug::DebugError| Error::Other(error.to_string());
+
+        let painted_stack = match request.painted_stack {
+            Some(region) => Some(convert::to_wire_stack_usage(
+                StackUsage::of_painted_stack(&mut core, region).map_err(to_error)?,
+            )),
+            None => None,
+        };
+
+        let mut threads = Vec::new();
+        if let Some(rtos) = &rtos {
+            let core_registers = core.registers();
+            for thread in rtos.threads(&mut core, core_registers).map_err(to_error)? {
+                let Some(stack) = thread.stack else {
+                    continue;
+                };
+                match StackUsage::of_thread_stack(&mut core, &stack) {
+                    Ok(usage) => threads.push(WireThreadStackUsage {
+                        name: thread.name,
+                        usage: convert::to_wire_stack_usage(usage),
+                    }),
+                    Err(error) => tracing::warn!(
+                        "Failed to measure the stack of thread {}: {error}",
+                        thread.name
+                    ),
+                }
+            }
+        }
+
+        Ok(WireStackUsageReport {
+            painted_stack,
+            rtos: rtos.as_ref().map(|rtos| rtos.name().to_string()),
+            threads,
+        })
+    }))
+}
+
+pub(crate) mod convert {
+    use probe_rs_debug::stack_paint::StackUsage;
+    use probe_rs_rpc::stack_paint::WireStackUsage;
+
+    pub(crate) fn to_wire_stack_usage(usage: StackUsage) -> WireStackUsage {
+        WireStackUsage {
+            bottom: usage.bottom,
+            top: usage.top,
+            high_water_mark: usage.high_water_mark,
+        }
+    }
+}
@@ -1,17 +1,20 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use postcard_rpc::header::VarHeader;
 use probe_rs::{CoreInterface, Error};
 use probe_rs_debug::{
-    DebugInfo, DebugRegisters, StackFrame, VariableCache, exception_handler_for_core,
+    DebugInfo, DebugRegisters, StackFrame, StackFrameInfo, VariableCache, embassy::Embassy,
+    exception_handler_for_core, rtos::RtosThread,
 };
 use probe_rs_rpc::stack_trace::{
     LoadDebugInfoRequest, LoadDebugInfoResponse, RichStackTrace, RichStackTraceFrame,
-    RichStackTraces, SourceLocation, StackTrace, StackTraceFrame, StackTraces,
-    TakeRichStackTraceRequest, TakeRichStackTraceResponse, TakeStackTraceRequest,
+    RichStackTraces, RichThreadStackTrace, SourceLocation, StackTrace, StackTraceFrame,
+    StackTraces, TakeRichStackTraceRequest, TakeRichStackTraceResponse, TakeStackTraceRequest,
     TakeStackTraceResponse, WireDebugRegister,
 };
 
+use crate::rpc::functions::rtos::{convert::to_wire_rtos_thread, rtos_for_cores};
 use crate::rpc::functions::{RpcContext, convert::lift};
 
 /// Eagerly load and cache the authoritative server-side [`DebugInfo`] for a
@@ -101,6 +104,10 @@ pub async fn take_stack_trace(
 /// Requires preloaded session debug state from [`load_debug_info`]. Missing
 /// state is a deterministic lifecycle error; no path upload or fallback
 /// DWARF parsing is performed here.
+///
+/// With `rtos_threads`, the threads of the RTOS that are not running are
+/// unwound from their saved registers too. Their frames are cached after the
+/// ones of the core, so their variables resolve the same way.
 pub async fn take_rich_stack_trace(
     ctx: &mut RpcContext,
     _header: VarHeader,
@@ -109,17 +116,20 @@ pub async fn take_rich_stack_trace(
     let debug_info = ctx
         .with_server_debug_state(request.sessid, |state| state.debug_info.clone())
         .await;
+    let rtos = if request.rtos_threads {
+        rtos_for_cores(ctx, request.sessid, request.core).await
+    } else {
+        HashMap::new()
+    };
+    let embassy = ctx
+        .with_server_debug_state_mut(request.sessid, |state| state.embassy())
+        .await;
 
     let mut session = ctx.session(request.sessid).await;
 
     // Per core: unwind, build locals via `get_stackframe_info`, build the
     // static scope cache.
-    let cores: Vec<(
-        u32,
-        Vec<StackFrame>,
-        VariableCache,
-        Vec<RichStackTraceFrame>,
-    )> = lift(session.halted_access(|session| {
+    let cores: Vec<UnwoundCore> = lift(session.halted_access(|session| {
         let mut cores = Vec::new();
         for (idx, core_type) in session.list_cores() {
             if let Some(requested_core) = request.core
@@ -152,61 +162,54 @@ pub async fn take_rich_stack_trace(
                 instruction_set,
                 request.stack_frame_limit as usize,
             )?;
+            add_local_variables(&debug_info, &mut core, &mut stack_frames);
 
-            let static_variables = debug_info.create_static_scope_cache();
-
-            // Group consecutive frames sharing a register dump (an inlined
-            // chain from one `get_stackframe_info` call) and populate
-            // `local_variables` for each frame in the group.
-            let mut i = 0;
-            while i < stack_frames.len() {
-                let group_start = i;
-                let group_regs = stack_frames[i].registers.clone();
-                while i < stack_frames.len() && stack_frames[i].registers == group_regs {
-                    i += 1;
-                }
-                let step_pc: u64 = stack_frames[group_start].pc.try_into().unwrap_or(0);
-                let cfa = stack_frames[group_start].canonical_frame_address;
-                let mut chain = debug_info
-                    .get_stackframe_info(&mut core, step_pc, cfa, &group_regs)
-                    .ok()
-                    .unwrap_or_default();
-                // DIE order is outermost-first; wire order is innermost-first.
-                chain.reverse();
-                for (offset, frame) in stack_frames[group_start..i].iter_mut().enumerate() {
-                    if let Some(cf) = chain.get(offset) {
-                        frame.local_variables = cf.local_variables.clone();
-                        if frame.source_location.is_none() {
-                            frame.source_location = cf.source_location.clone();
+            let mut threads = Vec::new();
+            if let Some(rtos) = rtos.get(&idx) {
+                let core_registers = core.registers();
+                match rtos.threads(&mut core, core_registers) {
+                    Ok(rtos_threads) => {
+                        for thread in rtos_threads {
+                            let Some(registers) = thread.registers.clone() else {
+                                threads.push((thread, Vec::new()));
+                                continue;
+                            };
+                            let mut frames = debug_info
+                                .unwind(
+                                    &mut core,
+                                    registers,
+                                    exception_interface.as_ref(),
+                                    instruction_set,
+                                    request.stack_frame_limit as usize,
+                                )
+                                .unwrap_or_else(|error| {
+                                    tracing::warn!(
+                                        "Failed to unwind the stack of thread {}: {error}",
+                                        thread.name
+                                    );
+                                    Vec::new()
+                                });
+                            add_local_variables(&debug_info, &mut core, &mut frames);
+                            threads.push((thread, frames));
                         }
                     }
+                    Err(error) => {
+                        tracing::warn!("Failed to read the threads of {}: {error}", rtos.name());
+                    }
                 }
             }
 
-            let rich_frames = stack_frames
-                .iter()
-                .map(|f| RichStackTraceFrame {
-                    function_name: f.function_name.clone(),
-                    program_counter:
-                        crate::rpc::functions::core_ops::convert::to_wire_register_value(f.pc),
-                    is_inlined: f.is_inlined,
-                    location: f
-                        .source_location
-                        .as_ref()
-                        .map(convert::to_wire_source_location),
-                    frame_base: f.frame_base,
-                    canonical_frame_address: f.canonical_frame_address,
-                    registers: f
-                        .registers
-                        .0
-                        .iter()
-                        .map(convert::to_wire_debug_register)
-                        .collect(),
-                    id: i64::from(f.id) as u32,
-                })
-                .collect();
+            let embassy_tasks = embassy.as_ref().and_then(|embassy| {
+                embassy_task_variables(embassy, &debug_info, &mut core, &stack_frames)
+            });
 
-            cores.push((idx as u32, stack_frames, static_variables, rich_frames));
+            cores.push(UnwoundCore {
+                core: idx as u32,
+                static_variables: debug_info.create_static_scope_cache(),
+                frames: stack_frames,
+                threads,
+                embassy_tasks,
+            });
         }
         Ok(cores)
     }))?;
@@ -217,11 +220,32 @@ pub async fn take_rich_stack_trace(
         .with_server_debug_state_mut(request.sessid, |state| {
             let wire_cores: Vec<RichStackTrace> = cores
                 .into_iter()
-                .map(|(core, frames, static_variables, rich_frames)| {
-                    state.store_core(core as usize, frames, Some(static_variables));
+                .map(|unwound| {
+                    let frames = unwound.frames.iter().map(to_rich_frame).collect();
+                    let threads = unwound
+                        .threads
+                        .iter()
+                        .map(|(thread, frames)| RichThreadStackTrace {
+                            thread: to_wire_rtos_thread(thread),
+                            frames: frames.iter().map(to_rich_frame).collect(),
+                        })
+                        .collect();
+
+                    let mut stack_frames = unwound.frames;
+                    for (_, frames) in unwound.threads {
+                        stack_frames.extend(frames);
+                    }
+                    state.store_core(
+                        unwound.core as usize,
+                        stack_frames,
+                        Some(unwound.static_variables),
+                        unwound.embassy_tasks,
+                    );
+
                     RichStackTrace {
-                        core,
-                        frames: rich_frames,
+                        core: unwound.core,
+                        frames,
+                        threads,
                     }
                 })
                 .collect();
@@ -230,6 +254,96 @@ pub async fn take_rich_stack_trace(
         .await)
 }
 
+/// The stack frames of a core, and of the RTOS threads that run on it.
+struct UnwoundCore {
+    core: u32,
+    frames: Vec<StackFrame>,
+    static_variables: VariableCache,
+    threads: Vec<(RtosThread, Vec<StackFrame>)>,
+    embassy_tasks: Option<VariableCache>,
+}
+
+/// Reads the embassy tasks and builds the variables of their futures, evaluated with the
+/// registers of the innermost frame.
+fn embassy_task_variables(
+    embassy: &Embassy,
+    debug_info: &DebugInfo,
+    core: &mut probe_rs::Core<'_>,
+    stack_frames: &[StackFrame],
+) -> Option<VariableCache> {
+    let registers = stack_frames
+        .first()
+        .map(|frame| frame.registers.clone())
+        .unwrap_or_default();
+    let frame_info = StackFrameInfo {
+        registers: &registers,
+        frame_base: None,
+        canonical_frame_address: None,
+    };
+
+    embassy
+        .tasks(debug_info, core)
+        .and_then(|tasks| embassy.task_variables(debug_info, core, &tasks.tasks, frame_info))
+        .inspect_err(|error| tracing::warn!("Failed to read the embassy tasks: {error}"))
+        .ok()
+}
+
+/// Populates the `local_variables` of the frames of one unwound stack.
+fn add_local_variables(
+    debug_info: &DebugInfo,
+    core: &mut probe_rs::Core<'_>,
+    stack_frames: &mut [StackFrame],
+) {
+    // Group consecutive frames sharing a register dump (an inlined
+    // chain from one `get_stackframe_info` call) and populate
+    // `local_variables` for each frame in the group.
+    let mut i = 0;
+    while i < stack_frames.len() {
+        let group_start = i;
+        let group_regs = stack_frames[i].registers.clone();
+        while i < stack_frames.len() && stack_frames[i].registers == group_regs {
+            i += 1;
+        }
+        let step_pc: u64 = stack_frames[group_start].pc.try_into().unwrap_or(0);
+        let cfa = stack_frames[group_start].canonical_frame_address;
+        let mut chain = debug_info
+            .get_stackframe_info(core, step_pc, cfa, &group_regs)
+            .ok()
+            .unwrap_or_default();
+        // DIE order is outermost-first; wire order is innermost-first.
+        chain.reverse();
+        for (offset, frame) in stack_frames[group_start..i].iter_mut().enumerate() {
+            if let Some(cf) = chain.get(offset) {
+                frame.local_variables = cf.local_variables.clone();
+                if frame.source_location.is_none() {
+                    frame.source_location = cf.source_location.clone();
+                }
+            }
+        }
+    }
+}
+
+fn to_rich_frame(f: &StackFrame) -> RichStackTraceFrame {
+    RichStackTraceFrame {
+        function_name: f.function_name.clone(),
+        program_counter: crate::rpc::functions::core_ops::convert::to_wire_register_value(f.pc),
+        is_inlined: f.is_inlined,
+        location: f
+            .source_location
+            .as_ref()
+            .map(convert::to_wire_source_location),
+        frame_base: f.frame_base,
+        canonical_frame_address: f.canonical_frame_address,
+        registers: f
+            .registers
+            .0
+            .iter()
+            .map(convert::to_wire_debug_register)
+            .collect(),
+        id: i64::from(f.id) as u32,
+    }
+}
+
 pub(crate) mod convert {
     use super::{SourceLocation, StackTraceFrame, WireDebugRegister};
     use crate::rpc::functions::core_ops::convert::{to_wire_register_id, to_wire_register_value};
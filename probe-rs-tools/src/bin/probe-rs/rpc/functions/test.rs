@@ -12,6 +12,7 @@ use crate::rpc::{
     functions::{
         RpcContext, RpcSpawnContext, WireTxImpl,
         convert::lift,
+        coverage::CoveragePoller,
         monitor::{MonitorSender, RttPoller},
     },
     utils::{
@@ -166,6 +167,8 @@ fn run_test_impl(
         },
     });
 
+    let coverage = CoveragePoller::take(&ctx, request.sessid);
+
     match run_loop.run_until(
         &shared_session,
         VectorCatchConfig {
@@ -174,7 +177,7 @@ fn run_test_impl(
             catch_svc: true,
             catch_hlt: true,
         },
-        poller,
+        (poller, coverage),
         Some(timeout),
         |halt_reason, core| run_handler.handle_halt(halt_reason, core),
     )? {
@@ -5,7 +5,7 @@ use std::thread;
 use std::time::{Duration, Instant};
 
 use anyhow::Result;
-use probe_rs::{Core, CoreType, Error, HaltReason, VectorCatchCondition};
+use probe_rs::{Core, CoreType, Error, HaltReason, Session, VectorCatchCondition};
 
 use crate::rpc::SessionState;
 
@@ -110,8 +110,10 @@ impl RunLoop {
                 }
             }
 
-            poller.start(&mut core)?;
+            drop(core);
+            poller.start(&mut session, self.core_id)?;
 
+            let mut core = session.core(self.core_id)?;
             if core.core_halted()? {
                 core.run()?;
             }
@@ -121,9 +123,8 @@ impl RunLoop {
 
         // Clean up run loop
         let mut session = shared_session.session_blocking();
-        let mut core = session.core(self.core_id)?;
-        // Always clean up after RTT but don't overwrite the original result.
-        let poller_exit_result = poller.exit(&mut core);
+        // Always clean up the poller but don't overwrite the original result.
+        let poller_exit_result = poller.exit(&mut session, self.core_id);
         if result.is_ok() {
             // If the result is Ok, we return the potential error during cleanup.
             poller_exit_result?;
@@ -201,7 +202,8 @@ impl RunLoop {
             probe_rs::CoreStatus::LockedUp => Some(Ok(ReturnReason::LockedUp)),
         };
 
-        let poller_result = poller.poll(&mut core);
+        drop(core);
+        let poller_result = poller.poll(&mut session, self.core_id);
 
         if let Some(reason) = return_reason {
             return reason.map(ControlFlow::Break);
@@ -215,24 +217,28 @@ impl RunLoop {
     }
 }
 
+/// Work done alongside the run loop, like reading RTT output or collecting coverage.
+///
+/// Pollers get the whole session, so they can use debug components other than the core, e.g.
+/// trace buffers. The core the run loop controls is given as `core_id`.
 pub trait RunLoopPoller {
-    fn start(&mut self, core: &mut Core<'_>) -> Result<()>;
-    fn poll(&mut self, core: &mut Core<'_>) -> Result<Duration>;
-    fn exit(&mut self, core: &mut Core<'_>) -> Result<()>;
+    fn start(&mut self, session: &mut Session, core_id: usize) -> Result<()>;
+    fn poll(&mut self, session: &mut Session, core_id: usize) -> Result<Duration>;
+    fn exit(&mut self, session: &mut Session, core_id: usize) -> Result<()>;
 }
 
 pub struct NoopPoller;
 
 impl RunLoopPoller for NoopPoller {
-    fn start(&mut self, _core: &mut Core<'_>) -> Result<()> {
+    fn start(&mut self, _session: &mut Session, _core_id: usize) -> Result<()> {
         Ok(())
     }
 
-    fn poll(&mut self, _core: &mut Core<'_>) -> Result<Duration> {
+    fn poll(&mut self, _session: &mut Session, _core_id: usize) -> Result<Duration> {
         Ok(Duration::from_secs(u64::MAX))
     }
 
-    fn exit(&mut self, _core: &mut Core<'_>) -> Result<()> {
+    fn exit(&mut self, _session: &mut Session, _core_id: usize) -> Result<()> {
         Ok(())
     }
 }
@@ -241,27 +247,51 @@ impl<T> RunLoopPoller for Option<T>
 where
     T: RunLoopPoller,
 {
-    fn start(&mut self, core: &mut Core<'_>) -> Result<()> {
+    fn start(&mut self, session: &mut Session, core_id: usize) -> Result<()> {
         if let Some(poller) = self {
-            poller.start(core)
+            poller.start(session, core_id)
         } else {
-            NoopPoller.start(core)
+            NoopPoller.start(session, core_id)
         }
     }
 
-    fn poll(&mut self, core: &mut Core<'_>) -> Result<Duration> {
+    fn poll(&mut self, session: &mut Session, core_id: usize) -> Result<Duration> {
         if let Some(poller) = self {
-            poller.poll(core)
+            poller.poll(session, core_id)
         } else {
-            NoopPoller.poll(core)
+            NoopPoller.poll(session, core_id)
         }
     }
 
-    fn exit(&mut self, core: &mut Core<'_>) -> Result<()> {
+    fn exit(&mut self, session: &mut Session, core_id: usize) -> Result<()> {
         if let Some(poller) = self {
-            poller.exit(core)
+            poller.exit(session, core_id)
         } else {
-            NoopPoller.exit(core)
+            NoopPoller.exit(session, core_id)
         }
     }
 }
+
+impl<A, B> RunLoopPoller for (A, B)
+where
+    A: RunLoopPoller,
+    B: RunLoopPoller,
+{
+    fn start(&mut self, session: &mut Session, core_id: usize) -> Result<()> {
+        self.0.start(session, core_id)?;
+        self.1.start(session, core_id)
+    }
+
+    fn poll(&mut self, session: &mut Session, core_id: usize) -> Result<Duration> {
+        let first = self.0.poll(session, core_id)?;
+        let second = self.1.poll(session, core_id)?;
+        Ok(first.min(second))
+    }
+
+    fn exit(&mut self, session: &mut Session, core_id: usize) -> Result<()> {
+        // Both pollers are cleaned up, even if the first one fails.
+        let first = self.0.exit(session, core_id);
+        let second = self.1.exit(session, core_id);
+        first.and(second)
+    }
+}
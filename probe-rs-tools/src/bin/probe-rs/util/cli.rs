@@ -4,7 +4,12 @@ use std::fmt::Display;
 use std::future::pending;
 use std::io::Write;
 use std::time::Duration;
-use std::{future::Future, ops::DerefMut, path::Path, time::Instant};
+use std::{
+    future::Future,
+    ops::{DerefMut, Range},
+    path::Path,
+    time::Instant,
+};
 
 use anyhow::Context;
 use libtest_mimic::{Failed, Trial};
@@ -31,12 +36,15 @@ use crate::util::pwr::power_reset;
 use crate::util::{
     common_options::{BinaryDownloadOptions, ProbeOptions},
     flash::CliProgressBars,
+    itm::{ItmPortConfig, ItmStream},
     logging,
     rtt::{DefmtProcessor, DefmtState, RttDecoder},
 };
 use probe_rs_rpc::CancelTopic;
+use probe_rs_rpc::breakpoints::WireSourceLocation;
 use probe_rs_rpc::flash::{BootInfo, DownloadOptions, FlashLayout, ProgressEvent, VerifyResult};
 use probe_rs_rpc::format::FormatOptions;
+use probe_rs_rpc::itm::ItmEvent;
 use probe_rs_rpc::monitor::{ChannelInfo, MonitorExitReason};
 use probe_rs_rpc::monitor::{MonitorMode, MonitorOptions, RttEvent, SemihostingEvent};
 use probe_rs_rpc::probe::{
@@ -501,6 +509,29 @@ pub async fn rtt_client(
     })
 }
 
+/// Starts decoding the ITM stimulus ports configured in `monitor_options`.
+fn itm_stream(
+    monitor_options: &MonitoringOptions,
+    rtt_client: Option<&CliRttClient>,
+) -> anyhow::Result<ItmStream> {
+    let ports = monitor_options
+        .itm_port
+        .iter()
+        .map(|port| ItmPortConfig {
+            show_timestamps: !monitor_options.no_timestamps,
+            show_location: !monitor_options.no_location,
+            log_format: monitor_options.log_format.clone(),
+            ..port.clone()
+        })
+        .collect();
+
+    ItmStream::spawn(
+        ports,
+        rtt_client.and_then(|client| client.defmt_data.clone()),
+        rtt_client.and_then(|client| client.timestamp_offset),
+    )
+}
+
 pub async fn flash(
     session: &SessionInterface,
     path: &Path,
@@ -680,6 +711,7 @@ pub async fn monitor(
     monitor_options: &MonitoringOptions,
     mut rtt_client: Option<CliRttClient>,
     vector_catch: VectorCatchConfig,
+    paint_stack: Option<Range<u64>>,
 ) -> anyhow::Result<()> {
     let semihosting_options = parse_semihosting_options(&monitor_options.semihosting_file)?;
     let mut target_output_files =
@@ -692,6 +724,14 @@ pub async fn monitor(
         catch_hlt: vector_catch.catch_hlt,
         rtt_client: rtt_client.as_ref().map(|client| client.handle()),
         semihosting_options,
+        branch_trace_buffer: monitor_options.mtb_buffer.clone(),
+        itm: monitor_options.itm_sink(),
+        paint_stack,
+    };
+
+    let itm = match options.itm {
+        Some(_) => Some(itm_stream(monitor_options, rtt_client.as_ref())?),
+        None => None,
     };
 
     // The mutex around the context should only be held for a short period of time.
@@ -717,6 +757,7 @@ pub async fn monitor(
 
         handle_monitor_event(
             &mut client,
+            itm.as_ref(),
             msg,
             &mut target_output_files,
             &async |message| ui_context.lock().await.print(message),
@@ -873,6 +914,12 @@ pub async fn monitor(
         _ = terminate => unreachable!(),
     };
 
+    if let Some(itm) = itm
+        && let Err(error) = itm.finish()
+    {
+        tracing::error!("Failed to decode the ITM trace: {error:?}");
+    }
+
     let (print_stack_trace, result) = match result {
         Ok(MonitorExitReason::Success | MonitorExitReason::SemihostingExit(Ok(_))) => {
             println!("Firmware exited successfully");
@@ -938,6 +985,10 @@ pub async fn monitor(
         } else {
             eprintln!("Can not print stack trace because firmware is not available");
         }
+
+        if monitor_options.mtb_buffer.is_some() {
+            display_branch_trace(session, path).await?;
+        }
     }
 
     result
@@ -1014,6 +1065,7 @@ pub async fn test(
         while let Some(event) = receiver.recv().await {
             handle_monitor_event(
                 &mut rtt_client.as_mut(),
+                None,
                 event,
                 &mut target_output_files,
                 &async |message| print!("{message}"),
@@ -1121,6 +1173,58 @@ async fn display_stack_trace(
     Ok(())
 }
 
+async fn display_branch_trace(
+    session: &SessionInterface,
+    path: Option<&Path>,
+) -> anyhow::Result<()> {
+    if let Some(path) = path {
+        session.load_debug_info(path.to_path_buf()).await?;
+    }
+
+    println!("Branch history (oldest first):");
+    for branch in branch_trace(session).await? {
+        println!("    {branch}");
+    }
+
+    Ok(())
+}
+
+/// Stops the branch trace recorded by the MTB, and describes the recorded branches with their
+/// source locations, oldest first.
+pub(crate) async fn branch_trace(session: &SessionInterface) -> anyhow::Result<Vec<String>> {
+    let branches = session.take_branch_trace().await?;
+    let addresses = branches
+        .iter()
+        .flat_map(|branch| [branch.source, branch.target])
+        .collect();
+    let locations = session.resolve_source_locations(addresses).await?;
+
+    let location = |location: &Option<WireSourceLocation>| match location {
+        Some(WireSourceLocation {
+            path,
+            line: Some(line),
+            ..
+        }) => format!("{path}:{line}"),
+        Some(location) => location.path.clone(),
+        None => "<unknown>".to_string(),
+    };
+
+    Ok(branches
+        .iter()
+        .zip(locations.chunks_exact(2))
+        .map(|(branch, locations)| {
+            format!(
+                "{:#010x} -> {:#010x}{}  {} -> {}",
+                branch.source,
+                branch.target,
+                if branch.exception { " (exception)" } else { "" },
+                location(&locations[0]),
+                location(&locations[1]),
+            )
+        })
+        .collect())
+}
+
 /// Formats a single stack frame for display.
 ///
 /// `colorize` controls ANSI styling: `None` uses the `PROBE_RS_COLOR` default,
@@ -1254,6 +1358,7 @@ impl CliRttClient {
 
 async fn handle_monitor_event(
     rtt_client: &mut Option<impl DerefMut<Target = CliRttClient>>,
+    itm: Option<&ItmStream>,
     event: MonitorEvent,
     target_output_files: &mut TargetOutputFiles,
     shared_writer: &impl AsyncFn(&str),
@@ -1293,6 +1398,11 @@ async fn handle_monitor_event(
                 )
                 .await;
         }
+        MonitorEvent::Itm(ItmEvent { bytes }) => {
+            if let Some(itm) = itm {
+                itm.push(bytes);
+            }
+        }
         MonitorEvent::Semihosting(SemihostingEvent::Output { stream, data }) => {
             match stream.as_str() {
                 "stdout" => print!("{data}"),
@@ -1,11 +1,13 @@
 use std::{
     io::Write,
-    path::PathBuf,
+    ops::Range,
+    path::{Path, PathBuf},
     time::{Duration, Instant},
 };
 
 use super::cargo::ArtifactError;
-use crate::util::parse_u64;
+use crate::cmd::stack_watermark::stack_region;
+use crate::util::{parse_range, parse_u64};
 use probe_rs::{
     Permissions, Session, Target,
     config::{Registry, RegistryError, TargetSelector},
@@ -74,6 +76,33 @@ pub struct BinaryDownloadOptions {
     pub ram_chunk_size: Option<u64>,
 }
 
+/// Options for measuring the stack usage of the firmware, by painting its stack before it
+/// starts.
+#[derive(Debug, Clone, clap::Args)]
+pub struct StackPaintOptions {
+    /// Fill the stack with a known pattern before the firmware starts, so the high-water mark
+    /// of the stack can be measured with `probe-rs stack-watermark`. `probe-rs run` reports it
+    /// when the firmware stops.
+    #[arg(long, help_heading = "STACK USAGE")]
+    pub paint_stack: bool,
+
+    /// The stack to paint, for example `0x20000000..0x20001000`. By default the stack is found
+    /// from the `_stack_start` and `_stack_end` linker symbols of the ELF file.
+    #[arg(long, value_parser = parse_range, requires = "paint_stack", help_heading = "STACK USAGE")]
+    pub stack_range: Option<Range<u64>>,
+}
+
+impl StackPaintOptions {
+    /// Returns the stack to paint, or `None` if the stack is not painted.
+    pub fn region(&self, path: &Path) -> anyhow::Result<Option<Range<u64>>> {
+        if !self.paint_stack {
+            return Ok(None);
+        }
+
+        stack_region(path, self.stack_range.clone()).map(Some)
+    }
+}
+
 /// Supported bit-widths for read/write commands (not every device may support each width).
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, clap::ValueEnum)]
 pub enum ReadWriteBitWidth {
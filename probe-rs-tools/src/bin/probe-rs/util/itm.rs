@@ -0,0 +1,372 @@
+//! Routing of the data that the target writes to the ITM stimulus ports.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+
+use anyhow::{Context, anyhow, bail};
+use itm::TracePacket;
+use probe_rs_rpc::rtt_config::DataFormat;
+use serde::{Deserialize, Serialize};
+use time::UtcOffset;
+
+use crate::util::rtt::{DefmtProcessor, DefmtState, ProcessedRttData, RttDecoder};
+use crate::util::tcp::TcpPublisher;
+
+/// Where the data written to a stimulus port is sent to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ItmDestination {
+    /// Print to the standard output.
+    #[default]
+    Stdout,
+    /// Append to a file.
+    File(PathBuf),
+    /// Send to a TCP server.
+    Tcp(SocketAddr),
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// How the data written to an ITM stimulus port is decoded, and where it is sent to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ItmPortConfig {
+    /// The number of the stimulus port, from 0 to 31.
+    pub port: u8,
+    #[serde(default)]
+    pub format: DataFormat,
+    #[serde(default)]
+    pub destination: ItmDestination,
+    /// Controls the inclusion of timestamps for [`DataFormat::String`] and [`DataFormat::Defmt`].
+    #[serde(default = "default_true")]
+    pub show_timestamps: bool,
+    /// Controls the inclusion of source location information for [`DataFormat::Defmt`].
+    #[serde(default = "default_true")]
+    pub show_location: bool,
+    /// Controls the output format for [`DataFormat::Defmt`].
+    #[serde(default)]
+    pub log_format: Option<String>,
+}
+
+impl FromStr for ItmPortConfig {
+    type Err = anyhow::Error;
+
+    /// Parses `<PORT>[=<FORMAT>[,<DESTINATION>]]`, where the format is `string`, `defmt` or
+    /// `binary`, and the destination is `stdout`, `file:<PATH>` or `tcp:<ADDRESS>`.
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (port, rest) = s.split_once('=').unwrap_or((s, "string"));
+        let (format, destination) = rest.split_once(',').unwrap_or((rest, "stdout"));
+
+        let port = port
+            .parse::<u8>()
+            .ok()
+            .filter(|port| *port < 32)
+            .with_context(|| format!("`{port}` is not a stimulus port number from 0 to 31"))?;
+
+        let format = match format {
+            "string" => DataFormat::String,
+            "defmt" => DataFormat::Defmt,
+            "binary" => DataFormat::BinaryLE,
+            other => bail!("`{other}` is not one of the formats `string`, `defmt` or `binary`"),
+        };
+
+        let destination = match destination.split_once(':') {
+            None if destination == "stdout" => ItmDestination::Stdout,
+            Some(("file", path)) => ItmDestination::File(path.into()),
+            Some(("tcp", address)) => ItmDestination::Tcp(
+                address
+                    .parse()
+                    .with_context(|| format!("`{address}` is not a socket address"))?,
+            ),
+            _ => bail!(
+                "`{destination}` is not one of the destinations `stdout`, `file:<PATH>` or `tcp:<ADDRESS>`"
+            ),
+        };
+
+        Ok(Self {
+            port,
+            format,
+            destination,
+            show_timestamps: true,
+            show_location: true,
+            log_format: None,
+        })
+    }
+}
+
+/// Decodes the data written to the stimulus ports, and sends it to the destination of each port.
+///
+/// Data written to ports without a configuration is dropped.
+pub struct ItmDemux {
+    ports: HashMap<u8, PortSink>,
+}
+
+struct PortSink {
+    decoder: RttDecoder,
+    output: PortOutput,
+}
+
+enum PortOutput {
+    Stdout,
+    File(std::fs::File),
+    Tcp(TcpPublisher),
+}
+
+impl ItmDemux {
+    pub fn new(
+        ports: &[ItmPortConfig],
+        defmt_data: Option<&DefmtState>,
+        timestamp_offset: Option<UtcOffset>,
+    ) -> anyhow::Result<Self> {
+        let mut sinks = HashMap::new();
+        for config in ports {
+            let decoder = match config.format {
+                DataFormat::String => RttDecoder::String {
+                    timestamp_offset,
+                    last_line_done: true,
+                    show_timestamps: config.show_timestamps,
+                },
+                DataFormat::BinaryLE => RttDecoder::BinaryLE,
+                DataFormat::Defmt => {
+                    let defmt_data = defmt_data.with_context(|| {
+                        format!(
+                            "ITM port {} is configured for defmt, but the firmware contains no defmt data",
+                            config.port
+                        )
+                    })?;
+                    RttDecoder::Defmt {
+                        processor: DefmtProcessor::new(
+                            defmt_data.clone(),
+                            config.show_timestamps,
+                            config.show_location,
+                            config.log_format.as_deref(),
+                        ),
+                    }
+                }
+            };
+
+            let output = match &config.destination {
+                ItmDestination::Stdout => PortOutput::Stdout,
+                ItmDestination::File(path) => PortOutput::File(
+                    std::fs::OpenOptions::new()
+                        .append(true)
+                        .create(true)
+                        .open(path)
+                        .with_context(|| format!("Failed to open {}", path.display()))?,
+                ),
+                ItmDestination::Tcp(address) => PortOutput::Tcp(TcpPublisher::new(*address)),
+            };
+
+            if sinks
+                .insert(config.port, PortSink { decoder, output })
+                .is_some()
+            {
+                bail!("ITM port {} is configured more than once", config.port);
+            }
+        }
+
+        Ok(Self { ports: sinks })
+    }
+
+    /// Handles a decoded trace packet. Packets other than stimulus port writes are ignored.
+    pub fn process(&mut self, packet: &TracePacket) -> anyhow::Result<()> {
+        let TracePacket::Instrumentation { port, payload } = packet else {
+            return Ok(());
+        };
+        let Some(sink) = self.ports.get_mut(port) else {
+            return Ok(());
+        };
+        let Some(data) = sink.decoder.process(payload)? else {
+            return Ok(());
+        };
+
+        match (&mut sink.output, data) {
+            (PortOutput::Stdout, data) => {
+                let mut stdout = std::io::stdout().lock();
+                write!(stdout, "{data}")?;
+                stdout.flush()?;
+            }
+            (PortOutput::File(file), ProcessedRttData::String(string)) => {
+                file.write_all(string.as_bytes())?
+            }
+            (PortOutput::File(file), ProcessedRttData::Binary(bytes)) => file.write_all(bytes)?,
+            (PortOutput::Tcp(tcp), ProcessedRttData::String(string)) => tcp.send(string.as_bytes()),
+            (PortOutput::Tcp(tcp), ProcessedRttData::Binary(bytes)) => tcp.send(bytes),
+        }
+
+        Ok(())
+    }
+}
+
+/// Decodes ITM trace data that arrives in chunks, on a background thread.
+pub struct ItmStream {
+    sender: mpsc::Sender<Vec<u8>>,
+    thread: JoinHandle<anyhow::Result<()>>,
+}
+
+impl ItmStream {
+    /// Starts decoding into an [`ItmDemux`] for `ports`.
+    pub fn spawn(
+        ports: Vec<ItmPortConfig>,
+        defmt_data: Option<DefmtState>,
+        timestamp_offset: Option<UtcOffset>,
+    ) -> anyhow::Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        let (ready_sender, ready) = mpsc::channel();
+
+        // The demultiplexer is created on the decoding thread, because the defmt decoder cannot be
+        // moved between threads.
+        let thread = std::thread::spawn(move || {
+            let mut demux = match ItmDemux::new(&ports, defmt_data.as_ref(), timestamp_offset) {
+                Ok(demux) => demux,
+                Err(error) => {
+                    _ = ready_sender.send(Err(error));
+                    return Ok(());
+                }
+            };
+            _ = ready_sender.send(Ok(()));
+
+            let reader = ChunkReader {
+                receiver,
+                chunk: Vec::new(),
+                position: 0,
+            };
+            let decoder = itm::Decoder::new(reader, itm::DecoderOptions { ignore_eof: false });
+            for packet in decoder.singles() {
+                match packet {
+                    Ok(packet) => demux.process(&packet)?,
+                    Err(itm::DecoderError::MalformedPacket(error)) => {
+                        tracing::warn!("Skipping a malformed ITM packet: {error}")
+                    }
+                    Err(error) => return Err(error.into()),
+                }
+            }
+
+            Ok(())
+        });
+
+        ready
+            .recv()
+            .context("The ITM decoder exited unexpectedly")??;
+
+        Ok(Self { sender, thread })
+    }
+
+    /// Queues trace data for decoding.
+    pub fn push(&self, bytes: Vec<u8>) {
+        if !bytes.is_empty() {
+            // If the decoder has stopped, its error is returned by `finish`.
+            _ = self.sender.send(bytes);
+        }
+    }
+
+    /// Decodes the remaining trace data, and waits for the decoder to stop.
+    pub fn finish(self) -> anyhow::Result<()> {
+        drop(self.sender);
+        self.thread
+            .join()
+            .map_err(|_| anyhow!("The ITM decoder panicked"))?
+    }
+}
+
+/// Reads the chunks sent to a channel, until the sender is dropped.
+struct ChunkReader {
+    receiver: mpsc::Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.position == self.chunk.len() {
+            let Ok(chunk) = self.receiver.recv() else {
+                return Ok(0);
+            };
+            self.chunk = chunk;
+            self.position = 0;
+        }
+
+        let len = buf.len().min(self.chunk.len() - self.position);
+        buf[..len].copy_from_slice(&self.chunk[self.position..][..len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_port_config() {
+        let config = "3".parse::<ItmPortConfig>().unwrap();
+        assert_eq!(config.port, 3);
+        assert_eq!(config.format, DataFormat::String);
+        assert_eq!(config.destination, ItmDestination::Stdout);
+
+        let config = "1=defmt,file:out/defmt.txt"
+            .parse::<ItmPortConfig>()
+            .unwrap();
+        assert_eq!(config.format, DataFormat::Defmt);
+        assert_eq!(
+            config.destination,
+            ItmDestination::File("out/defmt.txt".into())
+        );
+
+        let config = "2=binary,tcp:127.0.0.1:9000"
+            .parse::<ItmPortConfig>()
+            .unwrap();
+        assert_eq!(config.format, DataFormat::BinaryLE);
+        assert_eq!(
+            config.destination,
+            ItmDestination::Tcp("127.0.0.1:9000".parse().unwrap())
+        );
+
+        assert!("32".parse::<ItmPortConfig>().is_err());
+        assert!("0=hex".parse::<ItmPortConfig>().is_err());
+        assert!(
+            "0=string,udp:127.0.0.1:9000"
+                .parse::<ItmPortConfig>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn stream_demultiplexes_ports() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = dir.path().join("text.txt");
+        let binary = dir.path().join("binary.bin");
+
+        let stream = ItmStream::spawn(
+            vec![
+                format!("0=string,file:{}", text.display()).parse().unwrap(),
+                format!("5=binary,file:{}", binary.display())
+                    .parse()
+                    .unwrap(),
+            ],
+            None,
+            None,
+        )
+        .unwrap();
+
+        // Instrumentation packets: the header holds the port and the payload size. The second
+        // packet is split across chunks, and port 7 is not configured.
+        stream.push(vec![0x01, b'h', 0x02, b'i']);
+        stream.push(vec![b'\n', 0x29, 0x34, 0x39, 0xff]);
+        stream.push(vec![0x2b, 0x01, 0x02, 0x03, 0x04]);
+        stream.finish().unwrap();
+
+        assert_eq!(std::fs::read_to_string(text).unwrap(), "hi\n");
+        assert_eq!(
+            std::fs::read(binary).unwrap(),
+            [0x34, 0x01, 0x02, 0x03, 0x04]
+        );
+    }
+}
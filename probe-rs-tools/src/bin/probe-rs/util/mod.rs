@@ -2,11 +2,15 @@ pub mod cargo;
 pub mod cli;
 pub mod common_options;
 pub mod flash;
+pub mod itm;
 pub mod logging;
 pub mod meta;
+pub mod protobuf;
 pub mod pwr;
 pub mod rtt;
 pub mod setup_hints;
+pub mod signals;
+pub mod tcp;
 pub mod visualizer;
 
 use std::num::ParseIntError;
@@ -15,6 +19,10 @@ pub fn parse_u64(input: &str) -> Result<u64, ParseIntError> {
     parse_int::parse(input)
 }
 
+pub fn parse_u32(input: &str) -> Result<u32, ParseIntError> {
+    parse_int::parse(input)
+}
+
 pub fn parse_duration_secs(input: &str) -> Result<std::time::Duration, String> {
     let seconds = input
         .parse::<f64>()
@@ -22,3 +30,16 @@ pub fn parse_duration_secs(input: &str) -> Result<std::time::Duration, String> {
 
     std::time::Duration::try_from_secs_f64(seconds).map_err(|error| error.to_string())
 }
+
+/// Parses an address range like `0x1000..0x2000`.
+pub fn parse_range(input: &str) -> Result<std::ops::Range<u64>, String> {
+    let (start, end) = input
+        .split_once("..")
+        .ok_or_else(|| format!("`{input}` is not an address range like `0x1000..0x2000`"))?;
+    let start = parse_u64(start).map_err(|error| error.to_string())?;
+    let end = parse_u64(end).map_err(|error| error.to_string())?;
+    if start >= end {
+        return Err(format!("`{input}` is an empty address range"));
+    }
+    Ok(start..end)
+}
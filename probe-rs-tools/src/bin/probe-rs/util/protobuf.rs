@@ -0,0 +1,61 @@
+//! Minimal encoding of protobuf messages, for the trace and profile formats that use them.
+
+/// Appends a base 128 varint.
+pub fn varint(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+/// Appends a field with a varint value, like integers, booleans and enums.
+pub fn varint_field(buffer: &mut Vec<u8>, field: u32, value: u64) {
+    varint(buffer, u64::from(field) << 3);
+    varint(buffer, value);
+}
+
+/// Appends a length delimited field, like strings and bytes.
+pub fn bytes_field(buffer: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    varint(buffer, u64::from(field) << 3 | 2);
+    varint(buffer, bytes.len() as u64);
+    buffer.extend_from_slice(bytes);
+}
+
+/// Appends a repeated varint field in the packed encoding.
+pub fn packed_field(buffer: &mut Vec<u8>, field: u32, values: impl IntoIterator<Item = u64>) {
+    let mut packed = Vec::new();
+    for value in values {
+        varint(&mut packed, value);
+    }
+    bytes_field(buffer, field, &packed);
+}
+
+/// Appends an embedded message, which is written by `write`.
+pub fn message(buffer: &mut Vec<u8>, field: u32, write: impl FnOnce(&mut Vec<u8>)) {
+    let mut message = Vec::new();
+    write(&mut message);
+    bytes_field(buffer, field, &message);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encoding() {
+        let mut buffer = Vec::new();
+        varint_field(&mut buffer, 8, 300);
+        assert_eq!(buffer, [0x40, 0xac, 0x02]);
+
+        let mut buffer = Vec::new();
+        message(&mut buffer, 1, |packet| {
+            bytes_field(packet, 23, b"a");
+        });
+        assert_eq!(buffer, [0x0a, 0x04, 0xba, 0x01, 0x01, b'a']);
+
+        let mut buffer = Vec::new();
+        packed_field(&mut buffer, 1, [3, 270]);
+        assert_eq!(buffer, [0x0a, 0x03, 0x03, 0x8e, 0x02]);
+    }
+}
@@ -0,0 +1,267 @@
+//! Writing sampled values of target variables as CSV, JSON lines or a VCD waveform.
+
+use std::io::Write;
+use std::time::Duration;
+
+use probe_rs_debug::static_variable::{ScalarType, ScalarValue};
+
+/// The file format of the sampled values.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SignalFormat {
+    /// A header naming the variables, followed by a row per sample.
+    #[default]
+    Csv,
+    /// A JSON object per sample, with the time and the value of each variable.
+    Jsonl,
+    /// A Value Change Dump, for waveform viewers like GTKWave or Surfer.
+    Vcd,
+}
+
+/// A variable whose values are written.
+#[derive(Debug, Clone)]
+pub struct Signal {
+    pub name: String,
+    pub scalar_type: ScalarType,
+}
+
+/// Writes the sampled values of a fixed set of signals.
+pub struct SignalWriter<W: Write> {
+    writer: W,
+    format: SignalFormat,
+    signals: Vec<Signal>,
+    /// The last value written for each signal, to only write changes to a VCD.
+    last: Vec<Option<ScalarValue>>,
+}
+
+impl<W: Write> SignalWriter<W> {
+    /// Creates the writer and writes the header of the format.
+    pub fn new(mut writer: W, format: SignalFormat, signals: Vec<Signal>) -> std::io::Result<Self> {
+        match format {
+            SignalFormat::Csv => {
+                write!(writer, "time_us")?;
+                for signal in &signals {
+                    write!(writer, ",{}", csv_field(&signal.name))?;
+                }
+                writeln!(writer)?;
+            }
+            SignalFormat::Jsonl => {}
+            SignalFormat::Vcd => {
+                writeln!(
+                    writer,
+                    "$version probe-rs {} $end",
+                    env!("PROBE_RS_VERSION")
+                )?;
+                writeln!(writer, "$timescale 1 us $end")?;
+                writeln!(writer, "$scope module target $end")?;
+                for (index, signal) in signals.iter().enumerate() {
+                    let (kind, width) = match signal.scalar_type {
+                        ScalarType::Float(_) => ("real", 64),
+                        scalar_type => ("wire", scalar_type.bit_size()),
+                    };
+                    writeln!(
+                        writer,
+                        "$var {kind} {width} {} {} $end",
+                        vcd_identifier(index),
+                        signal.name.replace(char::is_whitespace, "_")
+                    )?;
+                }
+                writeln!(writer, "$upscope $end")?;
+                writeln!(writer, "$enddefinitions $end")?;
+            }
+        }
+
+        Ok(Self {
+            writer,
+            format,
+            last: vec![None; signals.len()],
+            signals,
+        })
+    }
+
+    /// Writes the values sampled at `time`, in the order of the signals. Signals without a new
+    /// value are `None`.
+    pub fn write(&mut self, time: Duration, values: &[Option<ScalarValue>]) -> std::io::Result<()> {
+        let time_us = time.as_micros();
+
+        match self.format {
+            SignalFormat::Csv => {
+                write!(self.writer, "{time_us}")?;
+                for value in values {
+                    match value {
+                        Some(value) => write!(self.writer, ",{value}")?,
+                        None => write!(self.writer, ",")?,
+                    }
+                }
+                writeln!(self.writer)?;
+            }
+            SignalFormat::Jsonl => {
+                // Written by hand to keep the keys in the order of the signals.
+                write!(self.writer, "{{\"time_us\":{time_us}")?;
+                for (signal, value) in self.signals.iter().zip(values) {
+                    if let Some(value) = value {
+                        write!(
+                            self.writer,
+                            ",{}:{}",
+                            serde_json::Value::from(signal.name.as_str()),
+                            json_value(*value)
+                        )?;
+                    }
+                }
+                writeln!(self.writer, "}}")?;
+            }
+            SignalFormat::Vcd => {
+                let mut time_written = false;
+                for (index, value) in values.iter().enumerate() {
+                    let Some(value) = *value else {
+                        continue;
+                    };
+                    if self.last[index] == Some(value) {
+                        continue;
+                    }
+                    self.last[index] = Some(value);
+
+                    if !time_written {
+                        writeln!(self.writer, "#{time_us}")?;
+                        time_written = true;
+                    }
+                    let identifier = vcd_identifier(index);
+                    match (self.signals[index].scalar_type, value) {
+                        (_, ScalarValue::Float(value)) => {
+                            writeln!(self.writer, "r{value} {identifier}")?
+                        }
+                        (ScalarType::Bool(_), value) => {
+                            writeln!(self.writer, "{}{identifier}", value.to_bits() & 1)?
+                        }
+                        (scalar_type, value) => {
+                            let width = scalar_type.bit_size();
+                            let bits = value.to_bits() & (u64::MAX >> (64 - width));
+                            writeln!(self.writer, "b{bits:b} {identifier}")?
+                        }
+                    }
+                }
+            }
+        }
+
+        self.writer.flush()
+    }
+}
+
+fn json_value(value: ScalarValue) -> serde_json::Value {
+    match value {
+        ScalarValue::Bool(value) => value.into(),
+        ScalarValue::Unsigned(value) => value.into(),
+        ScalarValue::Signed(value) => value.into(),
+        // NaN and infinities have no JSON representation and become `null`.
+        ScalarValue::Float(value) => value.into(),
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// The short identifier of a VCD variable, made of the printable ASCII characters.
+fn vcd_identifier(mut index: usize) -> String {
+    const FIRST: u8 = b'!';
+    const COUNT: usize = (b'~' - b'!' + 1) as usize;
+
+    let mut identifier = String::new();
+    loop {
+        identifier.push((FIRST + (index % COUNT) as u8) as char);
+        index /= COUNT;
+        if index == 0 {
+            return identifier;
+        }
+        index -= 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn signals() -> Vec<Signal> {
+        vec![
+            Signal {
+                name: "STATE.counter".to_string(),
+                scalar_type: ScalarType::Signed(2),
+            },
+            Signal {
+                name: "LEVEL".to_string(),
+                scalar_type: ScalarType::Float(4),
+            },
+        ]
+    }
+
+    fn write(format: SignalFormat) -> String {
+        let mut writer = SignalWriter::new(Vec::new(), format, signals()).unwrap();
+        writer
+            .write(
+                Duration::from_micros(0),
+                &[Some(ScalarValue::Signed(-1)), Some(ScalarValue::Float(0.5))],
+            )
+            .unwrap();
+        writer
+            .write(
+                Duration::from_micros(1500),
+                &[
+                    Some(ScalarValue::Signed(-1)),
+                    Some(ScalarValue::Float(0.25)),
+                ],
+            )
+            .unwrap();
+        writer
+            .write(
+                Duration::from_micros(3000),
+                &[Some(ScalarValue::Signed(2)), None],
+            )
+            .unwrap();
+        String::from_utf8(writer.writer).unwrap()
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(
+            write(SignalFormat::Csv),
+            "time_us,STATE.counter,LEVEL\n0,-1,0.5\n1500,-1,0.25\n3000,2,\n"
+        );
+    }
+
+    #[test]
+    fn jsonl() {
+        assert_eq!(
+            write(SignalFormat::Jsonl),
+            concat!(
+                "{\"time_us\":0,\"STATE.counter\":-1,\"LEVEL\":0.5}\n",
+                "{\"time_us\":1500,\"STATE.counter\":-1,\"LEVEL\":0.25}\n",
+                "{\"time_us\":3000,\"STATE.counter\":2}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn vcd() {
+        let vcd = write(SignalFormat::Vcd);
+        let (header, changes) = vcd.split_once("$enddefinitions $end\n").unwrap();
+
+        assert!(header.contains("$var wire 16 ! STATE.counter $end"));
+        assert!(header.contains("$var real 64 \" LEVEL $end"));
+        // Only changed values are written.
+        assert_eq!(
+            changes,
+            "#0\nb1111111111111111 !\nr0.5 \"\n#1500\nr0.25 \"\n#3000\nb10 !\n"
+        );
+    }
+
+    #[test]
+    fn vcd_identifiers() {
+        assert_eq!(vcd_identifier(0), "!");
+        assert_eq!(vcd_identifier(93), "~");
+        assert_eq!(vcd_identifier(94), "!!");
+        assert_eq!(vcd_identifier(95), "\"!");
+    }
+}
@@ -0,0 +1,342 @@
+//! Arm Cross Trigger Interface (CTI) CoreSight Component
+//!
+//! # Description
+//! A CTI connects the trigger signals of a core, such as "entered Debug state", "debug request"
+//! or "restart", to a small number of broadcast channels. The channels of all CTIs in a system
+//! are connected through the Cross Trigger Matrix (CTM), which lets an event on one core raise
+//! trigger outputs on other cores.
+//!
+//! This module uses that to build run-control groups: every core in a group sends its halted
+//! trigger to the group's halt channel, and receives debug requests from it, so one core halting
+//! stops the whole group. A pulse on the group's restart channel resumes every core in it.
+use std::time::{Duration, Instant};
+
+use crate::MemoryMappedRegister;
+use crate::architecture::arm::ArmError;
+use crate::architecture::arm::core::armv8a_debug_regs::{
+    CtiApppulse, CtiControl, CtiDevid, CtiGate, CtiInen, CtiIntack, CtiLar, CtiOuten,
+    CtiTrigoutstatus,
+};
+use crate::memory::MemoryInterface;
+
+/// How long to wait for a trigger output to clear after it was acknowledged.
+const ACK_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// The CTI trigger lines of a core that are used for run control.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunControlTriggers {
+    /// Trigger input raised when the core enters Debug state.
+    pub halted: u8,
+    /// Trigger output that requests the core to enter Debug state.
+    pub debug_request: u8,
+    /// Trigger output that restarts the core.
+    pub restart: u8,
+}
+
+impl RunControlTriggers {
+    /// The trigger assignment defined by the ARMv8-A architecture.
+    pub const ARMV8A: Self = Self {
+        halted: 0,
+        debug_request: 0,
+        restart: 1,
+    };
+
+    /// The trigger assignment of the ARMv7-A/R cores with a CTI, such as the Cortex-A9 and
+    /// Cortex-R4/R5, where DBGRESTART is on trigger output 7.
+    pub const ARMV7AR: Self = Self {
+        halted: 0,
+        debug_request: 0,
+        restart: 7,
+    };
+}
+
+/// The pair of CTI channels a run-control group halts and restarts on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunControlChannels {
+    /// Channel carrying the halt event of the group.
+    pub halt: u8,
+    /// Channel carrying the restart event of the group.
+    pub restart: u8,
+}
+
+impl RunControlChannels {
+    /// Returns the channels of run-control group `group`.
+    ///
+    /// Channels 0 and 1 are used to halt and restart individual ARMv8-A cores, so groups are
+    /// allocated from channel 2 upwards.
+    pub fn for_group(group: u8) -> Self {
+        Self {
+            halt: 2 + 2 * group,
+            restart: 3 + 2 * group,
+        }
+    }
+}
+
+/// Cross Trigger Interface of a core, accessed through the memory interface of its debug AP.
+pub struct CrossTriggerInterface<'a> {
+    memory: &'a mut dyn MemoryInterface<ArmError>,
+    base_address: u64,
+}
+
+impl<'a> CrossTriggerInterface<'a> {
+    /// Construct a new CTI component located at `base_address`.
+    pub fn new(memory: &'a mut dyn MemoryInterface<ArmError>, base_address: u64) -> Self {
+        Self {
+            memory,
+            base_address,
+        }
+    }
+
+    fn address<R: MemoryMappedRegister<u32>>(&self, index: u8) -> Result<u64, ArmError> {
+        Ok(R::get_mmio_address_from_base(self.base_address)? + 4 * index as u64)
+    }
+
+    fn modify<R>(&mut self, index: u8, f: impl FnOnce(&mut R)) -> Result<(), ArmError>
+    where
+        R: MemoryMappedRegister<u32> + From<u32> + Into<u32>,
+    {
+        let address = self.address::<R>(index)?;
+        let mut value = R::from(self.memory.read_word_32(address)?);
+        f(&mut value);
+        self.memory.write_word_32(address, value.into())
+    }
+
+    /// Unlock the CTI registers for writing and enable the mapping of triggers to channels.
+    pub fn enable(&mut self) -> Result<(), ArmError> {
+        let address = self.address::<CtiLar>(0)?;
+        self.memory.write_word_32(address, 0xC5AC_CE55)?;
+
+        self.modify::<CtiControl>(0, |control| control.set_glben(true))
+    }
+
+    /// Returns the number of channels the CTI implements.
+    pub fn channel_count(&mut self) -> Result<u8, ArmError> {
+        let address = self.address::<CtiDevid>(0)?;
+        Ok(CtiDevid(self.memory.read_word_32(address)?).numchan())
+    }
+
+    /// Connects (or disconnects) input trigger `trigger` to `channel`.
+    pub fn map_input(&mut self, trigger: u8, channel: u8, enabled: bool) -> Result<(), ArmError> {
+        self.modify::<CtiInen>(trigger, |inen| {
+            inen.set_inen(channel as usize, enabled as u32)
+        })
+    }
+
+    /// Connects (or disconnects) `channel` to output trigger `trigger`.
+    pub fn map_output(&mut self, trigger: u8, channel: u8, enabled: bool) -> Result<(), ArmError> {
+        self.modify::<CtiOuten>(trigger, |outen| {
+            outen.set_outen(channel as usize, enabled as u32)
+        })
+    }
+
+    /// Opens (or closes) the gate that passes events on `channel` to the cross trigger matrix.
+    pub fn set_gate(&mut self, channel: u8, open: bool) -> Result<(), ArmError> {
+        self.modify::<CtiGate>(0, |gate| gate.set_en(channel as usize, open as u32))
+    }
+
+    /// Generates an event on `channel`.
+    pub fn pulse(&mut self, channel: u8) -> Result<(), ArmError> {
+        let mut pulse = CtiApppulse(0);
+        pulse.set_apppulse(channel as usize, 1);
+
+        let address = self.address::<CtiApppulse>(0)?;
+        self.memory.write_word_32(address, pulse.into())
+    }
+
+    /// Acknowledges output trigger `trigger`, and waits until it is deasserted.
+    pub fn acknowledge(&mut self, trigger: u8) -> Result<(), ArmError> {
+        let mut ack = CtiIntack(0);
+        ack.set_ack(trigger as usize, 1);
+
+        let address = self.address::<CtiIntack>(0)?;
+        self.memory.write_word_32(address, ack.into())?;
+
+        let address = self.address::<CtiTrigoutstatus>(0)?;
+        let start = Instant::now();
+        while CtiTrigoutstatus(self.memory.read_word_32(address)?).status(trigger as usize) != 0 {
+            if start.elapsed() > ACK_TIMEOUT {
+                return Err(ArmError::Timeout);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Routes the run-control triggers of the core onto the channels of a run-control group.
+    pub fn join_run_control_group(
+        &mut self,
+        triggers: RunControlTriggers,
+        channels: RunControlChannels,
+    ) -> Result<(), ArmError> {
+        self.enable()?;
+
+        let available = self.channel_count()?;
+        if channels.restart >= available {
+            return Err(ArmError::Other(format!(
+                "The CTI at {:#x} implements {available} channels, \
+                 too few for a run-control group on channels {} and {}",
+                self.base_address, channels.halt, channels.restart
+            )));
+        }
+
+        self.map_input(triggers.halted, channels.halt, true)?;
+        self.map_output(triggers.debug_request, channels.halt, true)?;
+        self.map_output(triggers.restart, channels.restart, true)?;
+        self.set_gate(channels.halt, true)?;
+        self.set_gate(channels.restart, true)
+    }
+
+    /// Disconnects the run-control triggers of the core from a run-control group.
+    pub fn leave_run_control_group(
+        &mut self,
+        triggers: RunControlTriggers,
+        channels: RunControlChannels,
+    ) -> Result<(), ArmError> {
+        self.set_gate(channels.halt, false)?;
+        self.set_gate(channels.restart, false)?;
+        self.map_input(triggers.halted, channels.halt, false)?;
+        self.map_output(triggers.debug_request, channels.halt, false)?;
+        self.map_output(triggers.restart, channels.restart, false)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    const CTI_BASE: u64 = 0x8002_0000;
+
+    /// Register file of a CTI with four channels.
+    #[derive(Default)]
+    struct MockCti {
+        registers: BTreeMap<u64, u32>,
+        writes: Vec<(u64, u32)>,
+    }
+
+    impl MockCti {
+        fn new() -> Self {
+            let mut cti = Self::default();
+            cti.registers.insert(CTI_BASE + 0xFC8, 4 << 16);
+            cti
+        }
+
+        fn register(&self, offset: u64) -> u32 {
+            self.registers
+                .get(&(CTI_BASE + offset))
+                .copied()
+                .unwrap_or(0)
+        }
+    }
+
+    impl MemoryInterface<ArmError> for MockCti {
+        fn supports_native_64bit_access(&mut self) -> bool {
+            false
+        }
+
+        fn read_64(&mut self, _address: u64, _data: &mut [u64]) -> Result<(), ArmError> {
+            unimplemented!()
+        }
+
+        fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
+            for (i, word) in data.iter_mut().enumerate() {
+                let address = address + 4 * i as u64;
+                // Trigger outputs deassert as soon as they are acknowledged.
+                *word = match address - CTI_BASE {
+                    0x134 => 0,
+                    _ => self.registers.get(&address).copied().unwrap_or(0),
+                };
+            }
+            Ok(())
+        }
+
+        fn read_16(&mut self, _address: u64, _data: &mut [u16]) -> Result<(), ArmError> {
+            unimplemented!()
+        }
+
+        fn read_8(&mut self, _address: u64, _data: &mut [u8]) -> Result<(), ArmError> {
+            unimplemented!()
+        }
+
+        fn write_64(&mut self, _address: u64, _data: &[u64]) -> Result<(), ArmError> {
+            unimplemented!()
+        }
+
+        fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
+            for (i, word) in data.iter().enumerate() {
+                let address = address + 4 * i as u64;
+                self.registers.insert(address, *word);
+                self.writes.push((address - CTI_BASE, *word));
+            }
+            Ok(())
+        }
+
+        fn write_16(&mut self, _address: u64, _data: &[u16]) -> Result<(), ArmError> {
+            unimplemented!()
+        }
+
+        fn write_8(&mut self, _address: u64, _data: &[u8]) -> Result<(), ArmError> {
+            unimplemented!()
+        }
+
+        fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
+            Ok(false)
+        }
+
+        fn flush(&mut self) -> Result<(), ArmError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn join_and_leave_run_control_group() {
+        let mut mock = MockCti::new();
+        // Channel 1 is already open for a single-core restart.
+        mock.registers.insert(CTI_BASE + 0x140, 0b10);
+
+        let channels = RunControlChannels::for_group(0);
+        let mut cti = CrossTriggerInterface::new(&mut mock, CTI_BASE);
+        cti.join_run_control_group(RunControlTriggers::ARMV7AR, channels)
+            .unwrap();
+
+        assert_eq!(mock.register(0xFB0), 0xC5AC_CE55);
+        assert_eq!(mock.register(0x000), 1);
+        assert_eq!(mock.register(0x020), 1 << 2);
+        assert_eq!(mock.register(0x0A0), 1 << 2);
+        assert_eq!(mock.register(0x0A0 + 4 * 7), 1 << 3);
+        assert_eq!(mock.register(0x140), 0b1110);
+
+        let mut cti = CrossTriggerInterface::new(&mut mock, CTI_BASE);
+        cti.leave_run_control_group(RunControlTriggers::ARMV7AR, channels)
+            .unwrap();
+
+        assert_eq!(mock.register(0x020), 0);
+        assert_eq!(mock.register(0x0A0), 0);
+        assert_eq!(mock.register(0x0A0 + 4 * 7), 0);
+        assert_eq!(mock.register(0x140), 0b10);
+    }
+
+    #[test]
+    fn join_rejects_groups_beyond_channel_count() {
+        let mut mock = MockCti::new();
+
+        let mut cti = CrossTriggerInterface::new(&mut mock, CTI_BASE);
+        let result = cti
+            .join_run_control_group(RunControlTriggers::ARMV8A, RunControlChannels::for_group(1));
+
+        assert!(matches!(result, Err(ArmError::Other(_))));
+        assert_eq!(mock.register(0x020), 0);
+    }
+
+    #[test]
+    fn pulse_and_acknowledge() {
+        let mut mock = MockCti::new();
+
+        let mut cti = CrossTriggerInterface::new(&mut mock, CTI_BASE);
+        cti.pulse(3).unwrap();
+        cti.acknowledge(7).unwrap();
+
+        assert_eq!(mock.writes, vec![(0x01C, 1 << 3), (0x010, 1 << 7)]);
+    }
+}
@@ -0,0 +1,438 @@
+//! Module for using the Embedded Trace Macrocell (ETM).
+//!
+//! The ETM generates a compressed trace of the instructions executed by a core. Only the program
+//! flow is traced: the trace contains an atom for every branch, telling whether it was taken or
+//! not, and an address whenever the destination of a branch can not be deduced from the program
+//! image. See [`crate::architecture::arm::trace`] for reconstructing the executed instructions.
+//!
+//! This driver supports the ETMv4 programmers' model of the Cortex-M7, Cortex-M33, Cortex-M55 and
+//! Cortex-M85. The ETMv3 of the Cortex-M3 and Cortex-M4, and the PTM of the Cortex-A9, are not
+//! supported yet.
+
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use super::super::memory::romtable::CoresightComponent;
+use super::DebugComponentInterface;
+use crate::architecture::arm::{ArmDebugInterface, ArmError};
+use crate::memory_mapped_bitfield_register;
+
+const REGISTER_OFFSET_TRCEVENTCTL0R: u32 = 0x020;
+const REGISTER_OFFSET_TRCEVENTCTL1R: u32 = 0x024;
+const REGISTER_OFFSET_TRCSTALLCTLR: u32 = 0x02C;
+const REGISTER_OFFSET_TRCTSCTLR: u32 = 0x030;
+const REGISTER_OFFSET_TRCBBCTLR: u32 = 0x03C;
+const REGISTER_OFFSET_TRCVISSCTLR: u32 = 0x088;
+const REGISTER_OFFSET_TRCOSLAR: u32 = 0x300;
+const REGISTER_OFFSET_ACCESS: u32 = 0xFB0;
+
+/// Offset of the first address comparator value register, TRCACVR0. Every register is 64 bits wide.
+const REGISTER_OFFSET_TRCACVR: u32 = 0x400;
+/// Offset of the first address comparator access type register, TRCACATR0.
+const REGISTER_OFFSET_TRCACATR: u32 = 0x480;
+
+/// Resource selector 1 is hardwired to be always true.
+const RESOURCE_TRUE: u32 = 0x01;
+
+/// How long to wait for the trace unit to become idle or leave the idle state.
+const IDLE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Configuration of the instruction trace generated by the ETM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EtmConfig {
+    /// The trace ID (ATID) of the trace stream, used to tell it apart from other trace sources in
+    /// formatted trace data. Valid IDs are 0x01 to 0x6F.
+    pub trace_id: u8,
+
+    /// Only trace instructions within these address ranges. If empty, all instructions are traced.
+    ///
+    /// Every range uses one address range comparator pair of the ETM.
+    pub address_ranges: Vec<Range<u64>>,
+
+    /// Emit cycle counts, with the given threshold in cycles. The threshold is raised to the
+    /// minimum supported by the trace unit.
+    pub cycle_counting: Option<u16>,
+
+    /// Emit the context ID of the current process whenever it changes.
+    pub context_id: bool,
+}
+
+impl Default for EtmConfig {
+    fn default() -> Self {
+        Self {
+            trace_id: 0x10,
+            address_ranges: Vec::new(),
+            cycle_counting: None,
+            context_id: false,
+        }
+    }
+}
+
+/// Properties of an ETM implementation that are required to decode its trace stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EtmParameters {
+    /// Size of the context ID in bytes, or 0 if context ID tracing is not supported.
+    pub context_id_size: u8,
+
+    /// Size of the virtual machine ID in bytes, or 0 if VMID tracing is not supported.
+    pub vmid_size: u8,
+
+    /// Whether cycle count packets use the alternative commit encoding (TRCIDR0.COMMOPT).
+    pub commit_opt: bool,
+
+    /// Number of address range comparator pairs.
+    pub address_range_pairs: u8,
+
+    /// Whether cycle counting is supported.
+    pub cycle_counting: bool,
+
+    /// Maximum speculation depth of the instruction trace.
+    pub max_speculation: u32,
+}
+
+/// An interface to control the ETM (Embedded Trace Macrocell) of a core.
+pub struct Etm<'a> {
+    component: &'a CoresightComponent,
+    interface: &'a mut dyn ArmDebugInterface,
+}
+
+impl<'a> Etm<'a> {
+    /// Create a new ETM interface from a probe and a ROM table component.
+    pub fn new(
+        interface: &'a mut dyn ArmDebugInterface,
+        component: &'a CoresightComponent,
+    ) -> Self {
+        Etm {
+            interface,
+            component,
+        }
+    }
+
+    /// Unlock the software lock and the OS lock of the ETM, to allow programming it.
+    pub fn unlock(&mut self) -> Result<(), ArmError> {
+        self.component
+            .write_reg(self.interface, REGISTER_OFFSET_ACCESS, 0xC5AC_CE55)?;
+        self.component
+            .write_reg(self.interface, REGISTER_OFFSET_TRCOSLAR, 0)?;
+
+        Ok(())
+    }
+
+    /// Read the properties of the trace unit.
+    ///
+    /// Returns an error if the ETM does not implement the ETMv4 architecture.
+    pub fn parameters(&mut self) -> Result<EtmParameters, ArmError> {
+        // The ETMIDR of ETMv3 and PTM is at the same offset as TRCIDR1, with the major version
+        // one lower in the same field.
+        let idr1 = Trcidr1::load(self.component, self.interface)?;
+        match idr1.trcarchmaj() {
+            4 => {}
+            2 | 3 => {
+                tracing::warn!(
+                    "Found an ETMv3 or PTM trace unit, ETMIDR version {}.{}",
+                    idr1.trcarchmaj(),
+                    idr1.trcarchmin()
+                );
+                return Err(ArmError::NotImplemented(
+                    "ETMv3 and PTM trace, as implemented by the Cortex-M3, Cortex-M4 and Cortex-A9",
+                ));
+            }
+            major => {
+                tracing::warn!(
+                    "Unsupported ETM architecture version {major}.{}",
+                    idr1.trcarchmin()
+                );
+                return Err(ArmError::NotImplemented(
+                    "ETM architectures other than ETMv4",
+                ));
+            }
+        }
+
+        let idr0 = Trcidr0::load(self.component, self.interface)?;
+        let idr2 = Trcidr2::load(self.component, self.interface)?;
+        let idr4 = Trcidr4::load(self.component, self.interface)?;
+        let idr8 = Trcidr8::load(self.component, self.interface)?;
+
+        Ok(EtmParameters {
+            context_id_size: idr2.cidsize(),
+            vmid_size: idr2.vmidsize(),
+            commit_opt: idr0.commopt(),
+            address_range_pairs: idr4.numacpairs(),
+            cycle_counting: idr0.trccci(),
+            max_speculation: idr8.maxspec(),
+        })
+    }
+
+    /// Program the trace unit with `config` and enable it.
+    ///
+    /// The trace unit is disabled while it is being programmed.
+    pub fn configure(&mut self, config: &EtmConfig) -> Result<EtmParameters, ArmError> {
+        self.unlock()?;
+        let parameters = self.parameters()?;
+
+        if config.address_ranges.len() > parameters.address_range_pairs as usize {
+            return Err(ArmError::Other(format!(
+                "The ETM supports {} address ranges, but {} were requested",
+                parameters.address_range_pairs,
+                config.address_ranges.len()
+            )));
+        }
+        if config.cycle_counting.is_some() && !parameters.cycle_counting {
+            return Err(ArmError::ExtensionRequired(&["ETM cycle counting"]));
+        }
+        if config.context_id && parameters.context_id_size == 0 {
+            return Err(ArmError::ExtensionRequired(&["ETM context ID tracing"]));
+        }
+
+        self.disable()?;
+
+        let mut trcconfigr = Trcconfigr(0);
+        trcconfigr.set_cci(config.cycle_counting.is_some());
+        trcconfigr.set_cid(config.context_id);
+        trcconfigr.store(self.component, self.interface)?;
+
+        // No events, stalls, timestamps or branch broadcasting.
+        for register in [
+            REGISTER_OFFSET_TRCEVENTCTL0R,
+            REGISTER_OFFSET_TRCEVENTCTL1R,
+            REGISTER_OFFSET_TRCSTALLCTLR,
+            REGISTER_OFFSET_TRCTSCTLR,
+            REGISTER_OFFSET_TRCBBCTLR,
+        ] {
+            self.component.write_reg(self.interface, register, 0)?;
+        }
+
+        // Emit a synchronization point every 2^12 bytes, unless the period is fixed.
+        let idr3 = Trcidr3::load(self.component, self.interface)?;
+        if !idr3.syncpr() {
+            Trcsyncpr(12).store(self.component, self.interface)?;
+        }
+
+        if let Some(threshold) = config.cycle_counting {
+            let threshold = u32::from(threshold).max(idr3.ccitmin()).max(1);
+            Trcccctlr(threshold).store(self.component, self.interface)?;
+        }
+
+        Trctraceidr(u32::from(config.trace_id)).store(self.component, self.interface)?;
+
+        let mut include = 0u32;
+        for (pair, range) in config.address_ranges.iter().enumerate() {
+            // The upper comparator matches the last byte that is still part of the range.
+            let start = range.start;
+            let end = range.end.saturating_sub(1).max(start);
+            self.write_address_comparator(2 * pair, start)?;
+            self.write_address_comparator(2 * pair + 1, end)?;
+            include |= 1 << pair;
+        }
+
+        // Trace is always enabled, the start/stop logic is in the started state and all
+        // exception levels are traced.
+        let mut victlr = Trcvictlr(0);
+        victlr.set_event(RESOURCE_TRUE);
+        victlr.set_ssstatus(true);
+        victlr.store(self.component, self.interface)?;
+
+        let mut viiectlr = Trcviiectlr(0);
+        viiectlr.set_include(include);
+        viiectlr.store(self.component, self.interface)?;
+        self.component
+            .write_reg(self.interface, REGISTER_OFFSET_TRCVISSCTLR, 0)?;
+
+        self.enable()?;
+
+        Ok(parameters)
+    }
+
+    /// Enable the trace unit and wait until it starts tracing.
+    pub fn enable(&mut self) -> Result<(), ArmError> {
+        Trcprgctlr(1).store(self.component, self.interface)?;
+        self.wait_for_idle(false)
+    }
+
+    /// Disable the trace unit and wait until all trace data has been emitted.
+    pub fn disable(&mut self) -> Result<(), ArmError> {
+        Trcprgctlr(0).store(self.component, self.interface)?;
+        self.wait_for_idle(true)
+    }
+
+    fn write_address_comparator(&mut self, index: usize, address: u64) -> Result<(), ArmError> {
+        let value = REGISTER_OFFSET_TRCACVR + 8 * index as u32;
+        self.component
+            .write_reg(self.interface, value, address as u32)?;
+        self.component
+            .write_reg(self.interface, value + 4, (address >> 32) as u32)?;
+
+        // Compare instruction addresses in any context and exception level.
+        let access_type = REGISTER_OFFSET_TRCACATR + 8 * index as u32;
+        self.component.write_reg(self.interface, access_type, 0)?;
+        self.component
+            .write_reg(self.interface, access_type + 4, 0)?;
+
+        Ok(())
+    }
+
+    fn wait_for_idle(&mut self, idle: bool) -> Result<(), ArmError> {
+        let start = Instant::now();
+        while Trcstatr::load(self.component, self.interface)?.idle() != idle {
+            if start.elapsed() > IDLE_TIMEOUT {
+                return Err(ArmError::Timeout);
+            }
+        }
+        Ok(())
+    }
+}
+
+memory_mapped_bitfield_register! {
+    pub struct Trcprgctlr(u32);
+    0x004, "TRCPRGCTLR",
+    impl From;
+
+    pub en, set_en: 0;
+}
+
+impl DebugComponentInterface for Trcprgctlr {}
+
+memory_mapped_bitfield_register! {
+    pub struct Trcstatr(u32);
+    0x00C, "TRCSTATR",
+    impl From;
+
+    pub pmstable, _: 1;
+    pub idle, _: 0;
+}
+
+impl DebugComponentInterface for Trcstatr {}
+
+memory_mapped_bitfield_register! {
+    pub struct Trcconfigr(u32);
+    0x010, "TRCCONFIGR",
+    impl From;
+
+    pub ts, set_ts: 11;
+    pub vmid, set_vmid: 7;
+    pub cid, set_cid: 6;
+    pub cci, set_cci: 4;
+    pub bb, set_bb: 3;
+}
+
+impl DebugComponentInterface for Trcconfigr {}
+
+memory_mapped_bitfield_register! {
+    pub struct Trcsyncpr(u32);
+    0x034, "TRCSYNCPR",
+    impl From;
+
+    pub u8, period, set_period: 4, 0;
+}
+
+impl DebugComponentInterface for Trcsyncpr {}
+
+memory_mapped_bitfield_register! {
+    pub struct Trcccctlr(u32);
+    0x038, "TRCCCCTLR",
+    impl From;
+
+    pub u16, threshold, set_threshold: 11, 0;
+}
+
+impl DebugComponentInterface for Trcccctlr {}
+
+memory_mapped_bitfield_register! {
+    pub struct Trctraceidr(u32);
+    0x040, "TRCTRACEIDR",
+    impl From;
+
+    pub u8, traceid, set_traceid: 6, 0;
+}
+
+impl DebugComponentInterface for Trctraceidr {}
+
+memory_mapped_bitfield_register! {
+    pub struct Trcvictlr(u32);
+    0x080, "TRCVICTLR",
+    impl From;
+
+    pub u8, exlevel_ns, set_exlevel_ns: 23, 20;
+    pub u8, exlevel_s, set_exlevel_s: 19, 16;
+    pub trcerr, set_trcerr: 11;
+    pub trcreset, set_trcreset: 10;
+    pub ssstatus, set_ssstatus: 9;
+    pub u32, event, set_event: 7, 0;
+}
+
+impl DebugComponentInterface for Trcvictlr {}
+
+memory_mapped_bitfield_register! {
+    pub struct Trcviiectlr(u32);
+    0x084, "TRCVIIECTLR",
+    impl From;
+
+    pub u32, exclude, set_exclude: 23, 16;
+    pub u32, include, set_include: 7, 0;
+}
+
+impl DebugComponentInterface for Trcviiectlr {}
+
+memory_mapped_bitfield_register! {
+    pub struct Trcidr8(u32);
+    0x180, "TRCIDR8",
+    impl From;
+
+    pub u32, maxspec, _: 31, 0;
+}
+
+impl DebugComponentInterface for Trcidr8 {}
+
+memory_mapped_bitfield_register! {
+    pub struct Trcidr0(u32);
+    0x1E0, "TRCIDR0",
+    impl From;
+
+    pub commopt, _: 29;
+    pub trccci, _: 7;
+}
+
+impl DebugComponentInterface for Trcidr0 {}
+
+memory_mapped_bitfield_register! {
+    pub struct Trcidr1(u32);
+    0x1E4, "TRCIDR1",
+    impl From;
+
+    pub u8, trcarchmaj, _: 11, 8;
+    pub u8, trcarchmin, _: 7, 4;
+}
+
+impl DebugComponentInterface for Trcidr1 {}
+
+memory_mapped_bitfield_register! {
+    pub struct Trcidr2(u32);
+    0x1E8, "TRCIDR2",
+    impl From;
+
+    pub u8, vmidsize, _: 14, 10;
+    pub u8, cidsize, _: 9, 5;
+}
+
+impl DebugComponentInterface for Trcidr2 {}
+
+memory_mapped_bitfield_register! {
+    pub struct Trcidr3(u32);
+    0x1EC, "TRCIDR3",
+    impl From;
+
+    pub syncpr, _: 25;
+    pub u32, ccitmin, _: 11, 0;
+}
+
+impl DebugComponentInterface for Trcidr3 {}
+
+memory_mapped_bitfield_register! {
+    pub struct Trcidr4(u32);
+    0x1F0, "TRCIDR4",
+    impl From;
+
+    pub u8, numacpairs, _: 3, 0;
+}
+
+impl DebugComponentInterface for Trcidr4 {}
@@ -1,13 +1,18 @@
 //! Types and functions for interacting with CoreSight Components
 
+mod cti;
 mod dwt;
+mod etm;
 mod itm;
+mod mtb;
 mod scs;
 mod swo;
 mod tmc;
 mod tpiu;
 mod trace_funnel;
 
+use std::ops::Range;
+
 use crate::{
     Core, Error, MemoryInterface, MemoryMappedRegister,
     architecture::arm::{
@@ -19,7 +24,10 @@ use crate::{
 };
 
 pub use self::itm::Itm;
+pub use cti::{CrossTriggerInterface, RunControlChannels, RunControlTriggers};
 pub use dwt::Dwt;
+pub use etm::{Etm, EtmConfig, EtmParameters};
+pub use mtb::{Mtb, MtbBranch};
 pub use scs::Scs;
 pub use swo::Swo;
 pub use tmc::TraceMemoryController;
@@ -322,16 +330,22 @@ pub(crate) fn read_trace_memory(
     // need to deserialize the frames and pull out only the data source of interest. For now, all
     // we care about is the ITM data.
 
+    // ITM ATID, see Itm::tx_enable()
+    Ok(demux_trace_frames(&etf_trace, 13))
+}
+
+/// Extracts the data of the trace source with the trace ID (ATID) `trace_id` from formatted trace
+/// frames.
+fn demux_trace_frames(frames: &[u8], trace_id: u8) -> Vec<u8> {
     let mut id = 0.into();
-    let mut itm_trace = Vec::new();
+    let mut trace = Vec::new();
 
     // Process each formatted frame and extract the multiplexed trace data.
-    for frame_buffer in etf_trace.chunks_exact(16) {
+    for frame_buffer in frames.chunks_exact(16) {
         let mut frame = tmc::Frame::new(frame_buffer, id);
         for (id, data) in &mut frame {
             match id.into() {
-                // ITM ATID, see Itm::tx_enable()
-                13 => itm_trace.push(data),
+                id if id == trace_id => trace.push(data),
                 0 => (),
                 id => tracing::warn!("Unexpected trace source ATID {id}: {data}, ignoring"),
             }
@@ -339,7 +353,78 @@ pub(crate) fn read_trace_memory(
         id = frame.id();
     }
 
-    Ok(itm_trace)
+    trace
+}
+
+/// Sets up the ETM to record instruction trace into trace memory, which is used as a circular
+/// buffer.
+///
+/// Expects to be given a list of all ROM table `components` as the second argument.
+pub(crate) fn setup_etm_tracing(
+    interface: &mut dyn ArmDebugInterface,
+    components: &[CoresightComponent],
+    config: &EtmConfig,
+) -> Result<EtmParameters, Error> {
+    let mut tmc =
+        TraceMemoryController::new(interface, find_component(components, PeripheralType::Tmc)?);
+
+    tmc.disable_capture()?;
+    while !tmc.ready()? {}
+
+    // Keep the most recent trace, so the trace leading up to a halt can be inspected.
+    tmc.set_mode(tmc::Mode::Circular)?;
+    tmc.set_write_pointer(0)?;
+    tmc.enable_capture()?;
+
+    let mut etm = Etm::new(interface, find_component(components, PeripheralType::Etm)?);
+    Ok(etm.configure(config)?)
+}
+
+/// Stops the ETM and reads the instruction trace with the trace ID `trace_id` from trace memory.
+///
+/// Expects to be given a list of all ROM table `components` as the second argument.
+pub(crate) fn read_etm_trace(
+    interface: &mut dyn ArmDebugInterface,
+    components: &[CoresightComponent],
+    trace_id: u8,
+) -> Result<Vec<u8>, Error> {
+    let mut etm = Etm::new(interface, find_component(components, PeripheralType::Etm)?);
+    etm.disable()?;
+
+    let mut tmc =
+        TraceMemoryController::new(interface, find_component(components, PeripheralType::Tmc)?);
+
+    // Flush the trace still in flight into trace memory and stop the capture.
+    tmc.stop_on_flush(true)?;
+    tmc.manual_flush()?;
+    while !tmc.ready()? {}
+
+    let frames = tmc.read_circular_buffer()?;
+    Ok(demux_trace_frames(&frames, trace_id))
+}
+
+/// Sets up the MTB to record branches into `buffer`.
+///
+/// Expects to be given a list of all ROM table `components` as the second argument.
+pub(crate) fn setup_mtb_tracing(
+    interface: &mut dyn ArmDebugInterface,
+    components: &[CoresightComponent],
+    buffer: &Range<u64>,
+) -> Result<(), ArmError> {
+    let mut mtb = Mtb::new(interface, find_component(components, PeripheralType::Mtb)?);
+    mtb.enable(buffer)
+}
+
+/// Stops the MTB and reads the branches recorded into `buffer`, oldest first.
+///
+/// Expects to be given a list of all ROM table `components` as the second argument.
+pub(crate) fn read_mtb_branches(
+    interface: &mut dyn ArmDebugInterface,
+    components: &[CoresightComponent],
+    buffer: &Range<u64>,
+) -> Result<Vec<MtbBranch>, ArmError> {
+    let mut mtb = Mtb::new(interface, find_component(components, PeripheralType::Mtb)?);
+    mtb.read_branches(buffer)
 }
 
 /// Configures DWT trace unit `unit` to begin tracing `address`.
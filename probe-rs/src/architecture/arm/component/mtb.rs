@@ -0,0 +1,225 @@
+//! Module for using the Micro Trace Buffer (MTB).
+//!
+//! The MTB of Cortex-M0+ and Cortex-M23 cores records every non-sequential change of the program
+//! flow as a pair of source and destination addresses. The records are written into a region of
+//! the system SRAM, which the firmware has to leave unused. The region is used as a circular
+//! buffer, so it always holds the most recent branches when the core halts.
+
+use std::ops::Range;
+
+use super::super::memory::romtable::CoresightComponent;
+use super::DebugComponentInterface;
+use crate::architecture::arm::{ArmDebugInterface, ArmError};
+use crate::memory_mapped_bitfield_register;
+
+const REGISTER_OFFSET_MTB_FLOW: u32 = 0x008;
+const REGISTER_OFFSET_MTB_BASE: u32 = 0x00C;
+
+/// The smallest buffer supported by the MTB, which holds two records.
+const MIN_BUFFER_SIZE: u64 = 16;
+
+/// A branch recorded by the MTB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MtbBranch {
+    /// Address of the branch instruction, or the address execution was interrupted at when an
+    /// exception was taken.
+    pub source: u64,
+    /// Address execution continued at.
+    pub target: u64,
+    /// The branch was caused by an exception entry or an exception return.
+    pub exception: bool,
+    /// This is the first branch recorded after tracing was started, the branches before it are
+    /// not related to it.
+    pub trace_start: bool,
+}
+
+/// The Micro Trace Buffer.
+pub struct Mtb<'a> {
+    component: &'a CoresightComponent,
+    interface: &'a mut dyn ArmDebugInterface,
+}
+
+impl<'a> Mtb<'a> {
+    /// Create a new MTB interface from a probe and a ROM table component.
+    pub fn new(
+        interface: &'a mut dyn ArmDebugInterface,
+        component: &'a CoresightComponent,
+    ) -> Self {
+        Self {
+            component,
+            interface,
+        }
+    }
+
+    /// Returns the address of the SRAM that the MTB records into.
+    pub fn sram_base(&mut self) -> Result<u64, ArmError> {
+        let base = self
+            .component
+            .read_reg(self.interface, REGISTER_OFFSET_MTB_BASE)?;
+        Ok(u64::from(base))
+    }
+
+    /// Start recording branches into `buffer`.
+    ///
+    /// The buffer has to be located in the MTB SRAM, its size has to be a power of two of at least
+    /// 16 bytes, and it has to be aligned to its size.
+    pub fn enable(&mut self, buffer: &Range<u64>) -> Result<(), ArmError> {
+        let mask = buffer_mask(buffer)?;
+        let offset = self.buffer_offset(buffer)?;
+
+        self.disable()?;
+
+        let mut position = MtbPosition(0);
+        position.set_pointer(offset >> 3);
+        position.store(self.component, self.interface)?;
+
+        // Never stop or halt on a watermark, the buffer is used as a circular buffer.
+        self.component
+            .write_reg(self.interface, REGISTER_OFFSET_MTB_FLOW, 0)?;
+
+        let mut master = MtbMaster(0);
+        master.set_mask(mask);
+        master.set_en(true);
+        master.store(self.component, self.interface)?;
+
+        Ok(())
+    }
+
+    /// Stop recording branches.
+    pub fn disable(&mut self) -> Result<(), ArmError> {
+        let mut master = MtbMaster::load(self.component, self.interface)?;
+        master.set_en(false);
+        master.store(self.component, self.interface)?;
+        Ok(())
+    }
+
+    /// Stop recording and read the branches recorded into `buffer`, oldest first.
+    pub fn read_branches(&mut self, buffer: &Range<u64>) -> Result<Vec<MtbBranch>, ArmError> {
+        let offset = self.buffer_offset(buffer)?;
+        self.disable()?;
+
+        let position = MtbPosition::load(self.component, self.interface)?;
+        let size = buffer.end - buffer.start;
+        let written = u64::from(position.pointer() << 3).wrapping_sub(u64::from(offset)) % size;
+
+        let mut memory = self
+            .interface
+            .memory_interface(&self.component.ap_address)?;
+        let words = if position.wrap() {
+            // The buffer is full, the oldest record is the one that is overwritten next.
+            let mut words = vec![0; (size / 4) as usize];
+            memory.read_32(buffer.start, &mut words)?;
+            words.rotate_left((written / 4) as usize);
+            words
+        } else {
+            let mut words = vec![0; (written / 4) as usize];
+            memory.read_32(buffer.start, &mut words)?;
+            words
+        };
+
+        Ok(decode_records(&words))
+    }
+
+    /// Returns the offset of `buffer` from the start of the MTB SRAM.
+    fn buffer_offset(&mut self, buffer: &Range<u64>) -> Result<u32, ArmError> {
+        let base = self.sram_base()?;
+        buffer
+            .start
+            .checked_sub(base)
+            .and_then(|offset| u32::try_from(offset).ok())
+            .ok_or_else(|| {
+                ArmError::Other(format!(
+                    "The MTB buffer at {:#010x} is not located in the MTB SRAM at {base:#010x}",
+                    buffer.start
+                ))
+            })
+    }
+}
+
+/// Returns the value of the MASK field of MTB_MASTER that confines the trace to `buffer`.
+fn buffer_mask(buffer: &Range<u64>) -> Result<u8, ArmError> {
+    let size = buffer.end.saturating_sub(buffer.start);
+    if size < MIN_BUFFER_SIZE || !size.is_power_of_two() || !buffer.start.is_multiple_of(size) {
+        return Err(ArmError::Other(format!(
+            "The MTB buffer {:#010x}..{:#010x} has to be a power of two of at least {MIN_BUFFER_SIZE} bytes, aligned to its size",
+            buffer.start, buffer.end
+        )));
+    }
+
+    Ok((size.trailing_zeros() - 4) as u8)
+}
+
+/// Decodes the records stored in the MTB buffer, given as a list of words.
+fn decode_records(words: &[u32]) -> Vec<MtbBranch> {
+    words
+        .chunks_exact(2)
+        .map(|record| MtbBranch {
+            source: u64::from(record[0] & !1),
+            target: u64::from(record[1] & !1),
+            exception: record[0] & 1 != 0,
+            trace_start: record[1] & 1 != 0,
+        })
+        .collect()
+}
+
+memory_mapped_bitfield_register! {
+    pub struct MtbPosition(u32);
+    0x000, "MTB_POSITION",
+    impl From;
+
+    pub pointer, set_pointer: 31, 3;
+    pub wrap, set_wrap: 2;
+}
+
+impl DebugComponentInterface for MtbPosition {}
+
+memory_mapped_bitfield_register! {
+    pub struct MtbMaster(u32);
+    0x004, "MTB_MASTER",
+    impl From;
+
+    pub en, set_en: 31;
+    pub haltreq, set_haltreq: 9;
+    pub tstopen, set_tstopen: 6;
+    pub tstarten, set_tstarten: 5;
+    pub u8, mask, set_mask: 4, 0;
+}
+
+impl DebugComponentInterface for MtbMaster {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn buffer_masks() {
+        assert_eq!(buffer_mask(&(0x2000_0000..0x2000_0010)).unwrap(), 0);
+        assert_eq!(buffer_mask(&(0x2000_0400..0x2000_0800)).unwrap(), 6);
+        // Too small, not a power of two and not aligned to its size.
+        assert!(buffer_mask(&(0x2000_0000..0x2000_0008)).is_err());
+        assert!(buffer_mask(&(0x2000_0000..0x2000_0030)).is_err());
+        assert!(buffer_mask(&(0x2000_0010..0x2000_0030)).is_err());
+    }
+
+    #[test]
+    fn records() {
+        let words = [0x0000_1235, 0x0000_2001, 0x0000_2010, 0x0000_1100];
+        assert_eq!(
+            decode_records(&words),
+            [
+                MtbBranch {
+                    source: 0x1234,
+                    target: 0x2000,
+                    exception: true,
+                    trace_start: true,
+                },
+                MtbBranch {
+                    source: 0x2010,
+                    target: 0x1100,
+                    exception: false,
+                    trace_start: false,
+                },
+            ]
+        );
+    }
+}
@@ -13,6 +13,8 @@ use crate::{
 
 const REGISTER_OFFSET_RSZ: u32 = 0x04;
 const REGISTER_OFFSET_RRD: u32 = 0x10;
+const REGISTER_OFFSET_RRP: u32 = 0x14;
+const REGISTER_OFFSET_RWP: u32 = 0x18;
 const REGISTER_OFFSET_CTL: u32 = 0x20;
 const REGISTER_OFFSET_CBUFLVL: u32 = 0x30;
 
@@ -147,6 +149,46 @@ impl<'a> TraceMemoryController<'a> {
         Ok(())
     }
 
+    /// Set the position in trace memory where the next trace data is written.
+    ///
+    /// # Note
+    /// The pointer can only be changed while capture is disabled.
+    pub fn set_write_pointer(&mut self, pointer: u32) -> Result<(), ArmError> {
+        self.component
+            .write_reg(self.interface, REGISTER_OFFSET_RWP, pointer)
+    }
+
+    /// Read the contents of trace memory that is used as a circular buffer, from the oldest to the
+    /// most recent data.
+    ///
+    /// # Note
+    /// Capture has to be stopped, and it must have been started with a write pointer of zero.
+    pub fn read_circular_buffer(&mut self) -> Result<Vec<u8>, Error> {
+        let size = self.fifo_size()?;
+        let write_pointer = self
+            .component
+            .read_reg(self.interface, REGISTER_OFFSET_RWP)?;
+
+        // Once the buffer wrapped, the oldest data is found at the write pointer.
+        let (start, length) = if self.full()? {
+            (write_pointer, size)
+        } else {
+            (0, write_pointer)
+        };
+        self.component
+            .write_reg(self.interface, REGISTER_OFFSET_RRP, start)?;
+
+        let mut data = Vec::with_capacity(length as usize);
+        while data.len() < length as usize {
+            let word = self
+                .component
+                .read_reg(self.interface, REGISTER_OFFSET_RRD)?;
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+
+        Ok(data)
+    }
+
     /// Get the size of the FIFO in bytes.
     pub fn fifo_size(&mut self) -> Result<u32, ArmError> {
         let size_words = self
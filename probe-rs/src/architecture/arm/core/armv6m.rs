@@ -1,11 +1,15 @@
 //! Register types and the core interface for armv6-M
 
-use super::{CortexMState, Dfsr, registers::cortex_m::*};
+use super::{
+    CortexMState, Dfsr,
+    cortex_m::{self, DwtVersion},
+    registers::cortex_m::*,
+};
 use crate::{
     Architecture, BreakpointCause, CoreInformation, CoreInterface, CoreRegister, CoreStatus,
     CoreType, HaltReason, InstructionSet, MemoryInterface, MemoryMappedRegister,
     architecture::arm::{ArmError, memory::ArmMemoryInterface, sequences::ArmDebugSequence},
-    core::{CoreRegisters, RegisterId, RegisterValue, VectorCatchCondition},
+    core::{CoreRegisters, RegisterId, RegisterValue, VectorCatchCondition, Watchpoint},
     error::Error,
     memory::{CoreMemoryInterface, valid_32bit_address},
 };
@@ -846,6 +850,51 @@ impl CoreInterface for Armv6m<'_> {
         Ok(())
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        Ok(cortex_m::available_watchpoint_units(
+            &mut *self.memory,
+            &mut self.state.dwt_watchpoints,
+        )?)
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        Ok(cortex_m::hw_watchpoints(
+            &mut *self.memory,
+            DwtVersion::V7,
+            &mut self.state.dwt_watchpoints,
+        )?)
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        cortex_m::set_hw_watchpoint(
+            &mut *self.memory,
+            DwtVersion::V7,
+            &mut self.state.dwt_watchpoints,
+            unit_index,
+            watchpoint,
+        )
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        Ok(cortex_m::clear_hw_watchpoint(
+            &mut *self.memory,
+            &mut self.state.dwt_watchpoints,
+            unit_index,
+        )?)
+    }
+
+    fn triggered_watchpoint(&mut self) -> Result<Option<Watchpoint>, Error> {
+        Ok(cortex_m::triggered_watchpoint(
+            &mut *self.memory,
+            DwtVersion::V7,
+            &mut self.state.dwt_watchpoints,
+        )?)
+    }
+
     fn registers(&self) -> &'static CoreRegisters {
         &CORTEX_M_CORE_REGISTERS
     }
@@ -20,13 +20,14 @@ use crate::{
     architecture::arm::{
         ArmError, DapAccess, FullyQualifiedApAddress,
         ap::{ApRegister, BD0, BD1, BD2, BD3, TAR, TAR2},
+        component::{CrossTriggerInterface, RunControlChannels, RunControlTriggers},
         core::armv7ar_debug_regs::*,
         memory::ArmMemoryInterface,
-        sequences::ArmDebugSequence,
+        sequences::{ArmDebugSequence, ArmDebugSequenceError},
     },
     core::{
         BreakpointCause, CoreRegisters, MemoryMappedRegister, RegisterId, RegisterValue,
-        VectorCatchCondition,
+        RunControlEvent, VectorCatchCondition,
     },
     error::Error,
     memory::{MemoryNotAlignedError, valid_32bit_address},
@@ -144,6 +145,8 @@ pub struct Armv7ar<'probe> {
 
     base_address: u64,
 
+    cti_address: Option<u64>,
+
     sequence: Arc<dyn ArmDebugSequence>,
 
     num_breakpoints: Option<u32>,
@@ -160,6 +163,7 @@ impl<'probe> Armv7ar<'probe> {
         mut memory: Box<dyn ArmMemoryInterface + 'probe>,
         state: &'probe mut CortexARState,
         base_address: u64,
+        cti_address: Option<u64>,
         sequence: Arc<dyn ArmDebugSequence>,
         core_type: CoreType,
     ) -> Result<Self, Error> {
@@ -187,6 +191,7 @@ impl<'probe> Armv7ar<'probe> {
             memory,
             state,
             base_address,
+            cti_address,
             sequence,
             num_breakpoints: None,
             itr_enabled: false,
@@ -400,6 +405,69 @@ impl<'probe> Armv7ar<'probe> {
         super::update_core_status(&mut self.memory, &mut self.state.current_state, new_status);
     }
 
+    /// Get the halted core ready to be restarted, either by DBGDRCR or by a run-control group.
+    fn prepare_for_restart(&mut self) -> Result<(), Error> {
+        if self.state.semihosting_command.is_some() {
+            // We're in an exception mode after vector catch (SVC mode for
+            // SVC vector catch, or Undefined mode for HLT/UNDEF vector catch).
+            // Need to:
+            // 1. Restore CPSR from SPSR (to return to original mode)
+            // 2. Set PC to LR (return address)
+
+            // Read LR (r14) - this is the banked LR for the current exception mode
+            let lr: u32 = self.read_core_reg(RegisterId(14))?.try_into()?;
+
+            // Save r0 in case it was modified by semihosting result
+            self.prepare_for_clobber(0)?;
+
+            // Read SPSR into r0: mrs r0, spsr
+            let mrs_spsr = build_mrs_spsr(0);
+            self.execute_instruction(mrs_spsr)?;
+
+            // Write r0 to CPSR: msr cpsr_fsxc, r0
+            let msr_cpsr = build_msr(0);
+            self.execute_instruction(msr_cpsr)?;
+
+            // Set PC to LR (return address)
+            self.write_core_reg(self.program_counter().into(), lr.into())?;
+
+            tracing::debug!(
+                "Semihosting resume: restoring CPSR from SPSR, setting PC to LR={:#x}",
+                lr
+            );
+        }
+
+        // Clear cached semihosting command
+        self.state.semihosting_command = None;
+
+        // set writeback values
+        self.writeback_registers()?;
+
+        // Disable ITRen before sending RRQ (per ARM C5.7)
+        if self.itr_enabled {
+            let address = Dbgdscr::get_mmio_address_from_base(self.base_address)?;
+            let mut dbgdscr = Dbgdscr(self.memory.read_word_32(address)?);
+            dbgdscr.set_itren(false);
+            self.memory.write_word_32(address, dbgdscr.into())?;
+            self.itr_enabled = false;
+        }
+
+        // A halt request from the run-control group stays asserted until acknowledged
+        if let Some(cti_address) = self.cti_address
+            && self.state.run_control_gate != 0
+        {
+            CrossTriggerInterface::new(&mut *self.memory, cti_address)
+                .acknowledge(RunControlTriggers::ARMV7AR.debug_request)?;
+        }
+
+        Ok(())
+    }
+
+    fn cti_address(&self) -> Result<u64, Error> {
+        self.cti_address
+            .ok_or_else(|| ArmError::from(ArmDebugSequenceError::CtiBaseNotSpecified).into())
+    }
+
     pub(crate) fn halted_access<R>(
         &mut self,
         op: impl FnOnce(&mut Self) -> Result<R, Error>,
@@ -786,50 +854,7 @@ impl CoreInterface for Armv7ar<'_> {
             return Ok(());
         }
 
-        if self.state.semihosting_command.is_some() {
-            // We're in an exception mode after vector catch (SVC mode for
-            // SVC vector catch, or Undefined mode for HLT/UNDEF vector catch).
-            // Need to:
-            // 1. Restore CPSR from SPSR (to return to original mode)
-            // 2. Set PC to LR (return address)
-
-            // Read LR (r14) - this is the banked LR for the current exception mode
-            let lr: u32 = self.read_core_reg(RegisterId(14))?.try_into()?;
-
-            // Save r0 in case it was modified by semihosting result
-            self.prepare_for_clobber(0)?;
-
-            // Read SPSR into r0: mrs r0, spsr
-            let mrs_spsr = build_mrs_spsr(0);
-            self.execute_instruction(mrs_spsr)?;
-
-            // Write r0 to CPSR: msr cpsr_fsxc, r0
-            let msr_cpsr = build_msr(0);
-            self.execute_instruction(msr_cpsr)?;
-
-            // Set PC to LR (return address)
-            self.write_core_reg(self.program_counter().into(), lr.into())?;
-
-            tracing::debug!(
-                "Semihosting resume: restoring CPSR from SPSR, setting PC to LR={:#x}",
-                lr
-            );
-        }
-
-        // Clear cached semihosting command
-        self.state.semihosting_command = None;
-
-        // set writeback values
-        self.writeback_registers()?;
-
-        // Disable ITRen before sending RRQ (per ARM C5.7)
-        if self.itr_enabled {
-            let address = Dbgdscr::get_mmio_address_from_base(self.base_address)?;
-            let mut dbgdscr = Dbgdscr(self.memory.read_word_32(address)?);
-            dbgdscr.set_itren(false);
-            self.memory.write_word_32(address, dbgdscr.into())?;
-            self.itr_enabled = false;
-        }
+        self.prepare_for_restart()?;
 
         run(&mut *self.memory, self.base_address)?;
 
@@ -1290,6 +1315,65 @@ impl CoreInterface for Armv7ar<'_> {
         self.memory.write_word_32(address, dbgvcr.into())?;
         Ok(())
     }
+
+    fn join_run_control_group(&mut self, group: u8) -> Result<(), Error> {
+        let cti_address = self.cti_address()?;
+        let channels = RunControlChannels::for_group(group);
+
+        CrossTriggerInterface::new(&mut *self.memory, cti_address)
+            .join_run_control_group(RunControlTriggers::ARMV7AR, channels)?;
+        self.state.run_control_gate |= (1 << channels.halt) | (1 << channels.restart);
+
+        Ok(())
+    }
+
+    fn leave_run_control_group(&mut self, group: u8) -> Result<(), Error> {
+        let cti_address = self.cti_address()?;
+        let channels = RunControlChannels::for_group(group);
+
+        CrossTriggerInterface::new(&mut *self.memory, cti_address)
+            .leave_run_control_group(RunControlTriggers::ARMV7AR, channels)?;
+        self.state.run_control_gate &= !((1 << channels.halt) | (1 << channels.restart));
+
+        Ok(())
+    }
+
+    fn trigger_run_control_group(
+        &mut self,
+        group: u8,
+        event: RunControlEvent,
+    ) -> Result<(), Error> {
+        let cti_address = self.cti_address()?;
+        let channels = RunControlChannels::for_group(group);
+        let channel = match event {
+            RunControlEvent::Halt => channels.halt,
+            RunControlEvent::Restart => channels.restart,
+        };
+
+        CrossTriggerInterface::new(&mut *self.memory, cti_address).pulse(channel)?;
+
+        Ok(())
+    }
+
+    fn prepare_run_control_group_restart(&mut self) -> Result<(), Error> {
+        if matches!(self.state.current_state, CoreStatus::Running) {
+            return Ok(());
+        }
+
+        self.prepare_for_restart()
+    }
+
+    fn run_control_group_restarted(&mut self) -> Result<(), Error> {
+        // DBGRESTART is held until acknowledged, and would restart the core on its next halt
+        let cti_address = self.cti_address()?;
+        CrossTriggerInterface::new(&mut *self.memory, cti_address)
+            .acknowledge(RunControlTriggers::ARMV7AR.restart)?;
+
+        self.set_core_status(CoreStatus::Running);
+        let _ = self.status()?;
+
+        Ok(())
+    }
 }
 
 impl MemoryInterface for Armv7ar<'_> {
@@ -2171,6 +2255,7 @@ mod test {
             mock_mem,
             &mut CortexARState::new(),
             TEST_BASE_ADDRESS,
+            None,
             DefaultArmSequence::create(),
             CoreType::Armv7a,
         )
@@ -2207,6 +2292,7 @@ mod test {
             mock_mem,
             &mut state,
             TEST_BASE_ADDRESS,
+            None,
             DefaultArmSequence::create(),
             CoreType::Armv7a,
         )
@@ -2247,6 +2333,7 @@ mod test {
             mock_mem,
             &mut state,
             TEST_BASE_ADDRESS,
+            None,
             DefaultArmSequence::create(),
             CoreType::Armv7a,
         )
@@ -2282,6 +2369,7 @@ mod test {
             mock_mem,
             &mut state,
             TEST_BASE_ADDRESS,
+            None,
             DefaultArmSequence::create(),
             CoreType::Armv7a,
         )
@@ -2315,6 +2403,7 @@ mod test {
             mock_mem,
             &mut state,
             TEST_BASE_ADDRESS,
+            None,
             DefaultArmSequence::create(),
             CoreType::Armv7a,
         )
@@ -2349,6 +2438,7 @@ mod test {
             mock_mem,
             &mut state,
             TEST_BASE_ADDRESS,
+            None,
             DefaultArmSequence::create(),
             CoreType::Armv7a,
         )
@@ -2389,6 +2479,7 @@ mod test {
             mock_mem,
             &mut state,
             TEST_BASE_ADDRESS,
+            None,
             DefaultArmSequence::create(),
             CoreType::Armv7a,
         )
@@ -2429,6 +2520,7 @@ mod test {
             mock_mem,
             &mut state,
             TEST_BASE_ADDRESS,
+            None,
             DefaultArmSequence::create(),
             CoreType::Armv7a,
         )
@@ -2483,6 +2575,7 @@ mod test {
             mock_mem,
             &mut state,
             TEST_BASE_ADDRESS,
+            None,
             DefaultArmSequence::create(),
             CoreType::Armv7a,
         )
@@ -2542,6 +2635,7 @@ mod test {
             mock_mem,
             &mut state,
             TEST_BASE_ADDRESS,
+            None,
             DefaultArmSequence::create(),
             CoreType::Armv7a,
         )
@@ -2571,6 +2665,7 @@ mod test {
             mock_mem,
             &mut state,
             TEST_BASE_ADDRESS,
+            None,
             DefaultArmSequence::create(),
             CoreType::Armv7a,
         )
@@ -2639,6 +2734,7 @@ mod test {
             mock_mem,
             &mut state,
             TEST_BASE_ADDRESS,
+            None,
             DefaultArmSequence::create(),
             CoreType::Armv7a,
         )
@@ -2688,6 +2784,7 @@ mod test {
             mock_mem,
             &mut state,
             TEST_BASE_ADDRESS,
+            None,
             DefaultArmSequence::create(),
             CoreType::Armv7a,
         )
@@ -2723,6 +2820,7 @@ mod test {
             mock_mem,
             &mut state,
             TEST_BASE_ADDRESS,
+            None,
             DefaultArmSequence::create(),
             CoreType::Armv7a,
         )
@@ -2754,6 +2852,7 @@ mod test {
             mock_mem,
             &mut state,
             TEST_BASE_ADDRESS,
+            None,
             DefaultArmSequence::create(),
             CoreType::Armv7a,
         )
@@ -2785,6 +2884,7 @@ mod test {
             mock_mem,
             &mut state,
             TEST_BASE_ADDRESS,
+            None,
             DefaultArmSequence::create(),
             CoreType::Armv7a,
         )
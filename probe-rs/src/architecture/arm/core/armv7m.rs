@@ -2,7 +2,7 @@
 
 use super::{
     CortexMState, Dfsr,
-    cortex_m::Mvfr0,
+    cortex_m::{self, DwtVersion, Mvfr0},
     registers::cortex_m::{
         CORTEX_M_CORE_REGISTERS, CORTEX_M_WITH_FP_CORE_REGISTERS, FP, PC, RA, SP,
     },
@@ -15,7 +15,7 @@ use crate::{
     },
     core::{
         Architecture, CoreInformation, CoreInterface, CoreRegisters, CoreStatus, HaltReason,
-        MemoryMappedRegister, RegisterId, RegisterValue, VectorCatchCondition,
+        MemoryMappedRegister, RegisterId, RegisterValue, VectorCatchCondition, Watchpoint,
     },
     error::Error,
     memory::{CoreMemoryInterface, valid_32bit_address},
@@ -1123,6 +1123,51 @@ impl CoreInterface for Armv7m<'_> {
         Ok(())
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        Ok(cortex_m::available_watchpoint_units(
+            &mut *self.memory,
+            &mut self.state.dwt_watchpoints,
+        )?)
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        Ok(cortex_m::hw_watchpoints(
+            &mut *self.memory,
+            DwtVersion::V7,
+            &mut self.state.dwt_watchpoints,
+        )?)
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        cortex_m::set_hw_watchpoint(
+            &mut *self.memory,
+            DwtVersion::V7,
+            &mut self.state.dwt_watchpoints,
+            unit_index,
+            watchpoint,
+        )
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        Ok(cortex_m::clear_hw_watchpoint(
+            &mut *self.memory,
+            &mut self.state.dwt_watchpoints,
+            unit_index,
+        )?)
+    }
+
+    fn triggered_watchpoint(&mut self) -> Result<Option<Watchpoint>, Error> {
+        Ok(cortex_m::triggered_watchpoint(
+            &mut *self.memory,
+            DwtVersion::V7,
+            &mut self.state.dwt_watchpoints,
+        )?)
+    }
+
     fn registers(&self) -> &'static CoreRegisters {
         if self.state.fp_present {
             &CORTEX_M_WITH_FP_CORE_REGISTERS
@@ -12,11 +12,15 @@ use crate::{
     Architecture, CoreInformation, CoreInterface, CoreRegister, CoreStatus, CoreType,
     InstructionSet, MemoryInterface,
     architecture::arm::{
-        ArmError, core::armv8a_debug_regs::*, memory::ArmMemoryInterface,
+        ArmError,
+        component::{CrossTriggerInterface, RunControlChannels, RunControlTriggers},
+        core::armv8a_debug_regs::*,
+        memory::ArmMemoryInterface,
         sequences::ArmDebugSequence,
     },
     core::{
-        CoreRegisters, RegisterId, RegisterValue, memory_mapped_registers::MemoryMappedRegister,
+        CoreRegisters, RegisterId, RegisterValue, RunControlEvent,
+        memory_mapped_registers::MemoryMappedRegister,
     },
     error::Error,
     memory::{MemoryNotAlignedError, valid_32bit_address},
@@ -1106,7 +1110,7 @@ impl CoreInterface for Armv8a<'_> {
     fn halt(&mut self, timeout: Duration) -> Result<CoreInformation, Error> {
         if !matches!(self.state.current_state, CoreStatus::Halted(_)) {
             // Ungate halt CTI channel
-            let mut cti_gate = CtiGate(0);
+            let mut cti_gate = CtiGate(self.state.run_control_gate);
             cti_gate.set_en(0, 1);
 
             let address = CtiGate::get_mmio_address_from_base(self.cti_address)?;
@@ -1130,7 +1134,7 @@ impl CoreInterface for Armv8a<'_> {
         let _ = self.status()?;
 
         // Gate halt channel
-        let cti_gate = CtiGate(0);
+        let cti_gate = CtiGate(self.state.run_control_gate);
 
         let address = CtiGate::get_mmio_address_from_base(self.cti_address)?;
         self.memory.write_word_32(address, cti_gate.into())?;
@@ -1155,7 +1159,7 @@ impl CoreInterface for Armv8a<'_> {
         self.ack_cti_halt()?;
 
         // Ungate restart CTI channel
-        let mut cti_gate = CtiGate(0);
+        let mut cti_gate = CtiGate(self.state.run_control_gate);
         cti_gate.set_en(1, 1);
 
         let address = CtiGate::get_mmio_address_from_base(self.cti_address)?;
@@ -1187,7 +1191,7 @@ impl CoreInterface for Armv8a<'_> {
         let _ = self.status()?;
 
         // Gate restart channel
-        let cti_gate = CtiGate(0);
+        let cti_gate = CtiGate(self.state.run_control_gate);
 
         let address = CtiGate::get_mmio_address_from_base(self.cti_address)?;
         self.memory.write_word_32(address, cti_gate.into())?;
@@ -1506,6 +1510,58 @@ impl CoreInterface for Armv8a<'_> {
         Ok(())
     }
 
+    fn join_run_control_group(&mut self, group: u8) -> Result<(), Error> {
+        let channels = RunControlChannels::for_group(group);
+
+        CrossTriggerInterface::new(&mut *self.memory, self.cti_address)
+            .join_run_control_group(RunControlTriggers::ARMV8A, channels)?;
+        self.state.run_control_gate |= (1 << channels.halt) | (1 << channels.restart);
+
+        Ok(())
+    }
+
+    fn leave_run_control_group(&mut self, group: u8) -> Result<(), Error> {
+        let channels = RunControlChannels::for_group(group);
+
+        CrossTriggerInterface::new(&mut *self.memory, self.cti_address)
+            .leave_run_control_group(RunControlTriggers::ARMV8A, channels)?;
+        self.state.run_control_gate &= !((1 << channels.halt) | (1 << channels.restart));
+
+        Ok(())
+    }
+
+    fn trigger_run_control_group(
+        &mut self,
+        group: u8,
+        event: RunControlEvent,
+    ) -> Result<(), Error> {
+        let channels = RunControlChannels::for_group(group);
+        let channel = match event {
+            RunControlEvent::Halt => channels.halt,
+            RunControlEvent::Restart => channels.restart,
+        };
+
+        CrossTriggerInterface::new(&mut *self.memory, self.cti_address).pulse(channel)?;
+
+        Ok(())
+    }
+
+    fn prepare_run_control_group_restart(&mut self) -> Result<(), Error> {
+        if matches!(self.state.current_state, CoreStatus::Running) {
+            return Ok(());
+        }
+
+        self.writeback_registers()?;
+        self.ack_cti_halt()
+    }
+
+    fn run_control_group_restarted(&mut self) -> Result<(), Error> {
+        self.set_core_status(CoreStatus::Running);
+        let _ = self.status()?;
+
+        Ok(())
+    }
+
     fn is_64_bit(&self) -> bool {
         self.state.is_64_bit
     }
@@ -406,3 +406,36 @@ memory_mapped_bitfield_register! {
     /// Status on channel N
     pub status, _ : 0, 0, 32;
 }
+
+memory_mapped_bitfield_register! {
+    /// CTIINEN<n> - CTI input trigger to output channel enable register
+    pub struct CtiInen(u32);
+    0x020, "CTIINEN",
+    impl From;
+
+    /// Enables or disables input trigger <n> generating an event on channel N
+    pub inen, set_inen : 0, 0, 32;
+}
+
+memory_mapped_bitfield_register! {
+    /// CTIDEVID - CTI device ID register
+    pub struct CtiDevid(u32);
+    0xFC8, "CTIDEVID",
+    impl From;
+
+    /// Number of channels implemented
+    pub u8, numchan, _ : 21, 16;
+
+    /// Number of triggers implemented
+    pub u8, numtrig, _ : 15, 8;
+}
+
+memory_mapped_bitfield_register! {
+    /// CTILAR - CTI lock access register
+    pub struct CtiLar(u32);
+    0xFB0, "CTILAR",
+    impl From;
+
+    /// Lock value
+    pub value, set_value : 31, 0;
+}
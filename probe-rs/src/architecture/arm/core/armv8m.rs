@@ -2,7 +2,7 @@
 
 use super::{
     CortexMState, Dfsr,
-    cortex_m::{IdPfr1, Mvfr0},
+    cortex_m::{self, DwtVersion, IdPfr1, Mvfr0},
     registers::armv8m::{
         V8M_BASE_SEC_FP_REGISTERS, V8M_BASE_SEC_REGISTERS, V8M_MAIN_FP_REGISTERS,
         V8M_MAIN_REGISTERS, V8M_MAIN_SEC_FP_REGISTERS, V8M_MAIN_SEC_REGISTERS,
@@ -18,7 +18,7 @@ use crate::{
         ArmError, core::registers::cortex_m::XPSR, memory::ArmMemoryInterface,
         sequences::ArmDebugSequence,
     },
-    core::{CoreRegisters, RegisterId, RegisterValue, VectorCatchCondition},
+    core::{CoreRegisters, RegisterId, RegisterValue, VectorCatchCondition, Watchpoint},
     error::Error,
     memory::{CoreMemoryInterface, valid_32bit_address},
 };
@@ -471,6 +471,51 @@ impl CoreInterface for Armv8m<'_> {
         Ok(())
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        Ok(cortex_m::available_watchpoint_units(
+            &mut *self.memory,
+            &mut self.state.dwt_watchpoints,
+        )?)
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        Ok(cortex_m::hw_watchpoints(
+            &mut *self.memory,
+            DwtVersion::V8,
+            &mut self.state.dwt_watchpoints,
+        )?)
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        cortex_m::set_hw_watchpoint(
+            &mut *self.memory,
+            DwtVersion::V8,
+            &mut self.state.dwt_watchpoints,
+            unit_index,
+            watchpoint,
+        )
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        Ok(cortex_m::clear_hw_watchpoint(
+            &mut *self.memory,
+            &mut self.state.dwt_watchpoints,
+            unit_index,
+        )?)
+    }
+
+    fn triggered_watchpoint(&mut self) -> Result<Option<Watchpoint>, Error> {
+        Ok(cortex_m::triggered_watchpoint(
+            &mut *self.memory,
+            DwtVersion::V8,
+            &mut self.state.dwt_watchpoints,
+        )?)
+    }
+
     fn registers(&self) -> &'static CoreRegisters {
         let main = true; // TODO m33 is mainline, no one has m23 (baseline) yet
         let security = self.security;
@@ -1,9 +1,11 @@
 //! Common functions and data types for Cortex-M core variants
 
+use super::armv6m::Demcr;
 use crate::{
-    CoreInterface, Error, MemoryMappedRegister,
+    BreakpointError, CoreInterface, Error, MemoryMappedRegister, Watchpoint, WatchpointKind,
     architecture::arm::{ArmError, memory::ArmMemoryInterface},
     core::RegisterId,
+    memory::valid_32bit_address,
     memory_mapped_bitfield_register,
     semihosting::SemihostingCommand,
     semihosting::decode_semihosting_syscall,
@@ -225,3 +227,341 @@ fn wait_for_core_register_transfer(
     }
     Err(ArmError::Timeout)
 }
+
+memory_mapped_bitfield_register! {
+    /// DWT Control Register
+    pub struct DwtCtrl(u32);
+    0xE000_1000, "DWT_CTRL",
+    impl From;
+    /// Number of comparators implemented.
+    pub numcomp, _: 31, 28;
+}
+
+memory_mapped_bitfield_register! {
+    /// DWT Comparator Register 0. Further comparators follow every [`DWT_COMPARATOR_STRIDE`] bytes.
+    pub struct DwtComp(u32);
+    0xE000_1020, "DWT_COMP0",
+    impl From;
+}
+
+memory_mapped_bitfield_register! {
+    /// DWT Comparator Mask Register 0. Not present on ARMv8-M.
+    pub struct DwtMask(u32);
+    0xE000_1024, "DWT_MASK0",
+    impl From;
+    pub mask, set_mask: 4, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// DWT Comparator Function Register 0.
+    pub struct DwtFunction(u32);
+    0xE000_1028, "DWT_FUNCTION0",
+    impl From;
+    /// Set when the comparator matched since the register was last read.
+    pub matched, _: 24;
+    /// Access size of a data comparison. On ARMv8-M, this is also the size of the watched region.
+    pub datavsize, set_datavsize: 11, 10;
+    /// ARMv8-M only: the action taken on a match.
+    pub action, set_action: 5, 4;
+    /// `FUNCTION` on ARMv6-M and ARMv7-M, `MATCH` on ARMv8-M.
+    pub function, set_function: 3, 0;
+}
+
+/// Distance between the register sets of consecutive DWT comparators.
+pub(crate) const DWT_COMPARATOR_STRIDE: u64 = 0x10;
+
+/// The DWT comparator programming model differs between ARMv6-M/ARMv7-M and ARMv8-M.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DwtVersion {
+    /// ARMv6-M and ARMv7-M: `FUNCTION` selects the watchpoint kind, `MASK` the size.
+    V7,
+    /// ARMv8-M: `MATCH` and `ACTION` select the watchpoint kind, `DATAVSIZE` the size.
+    V8,
+}
+
+impl DwtFunction {
+    /// Builds the function register value for a watchpoint, without enabling it.
+    fn watchpoint_configuration(
+        version: DwtVersion,
+        watchpoint: &Watchpoint,
+    ) -> Result<Self, Error> {
+        let mut function = DwtFunction(0);
+
+        match version {
+            DwtVersion::V7 => function.set_function(match watchpoint.kind {
+                WatchpointKind::Read => 0b0101,
+                WatchpointKind::Write => 0b0110,
+                WatchpointKind::Access => 0b0111,
+            }),
+            DwtVersion::V8 => {
+                // Larger regions would need a pair of linked comparators.
+                if watchpoint.size > 4 {
+                    return Err(Error::BreakpointOperation(
+                        BreakpointError::UnsupportedWatchpointSize(watchpoint.size),
+                    ));
+                }
+                function.set_datavsize(watchpoint.mask_bits());
+                // Generate a debug event on match
+                function.set_action(0b01);
+                function.set_function(match watchpoint.kind {
+                    WatchpointKind::Read => 0b0110,
+                    WatchpointKind::Write => 0b0101,
+                    WatchpointKind::Access => 0b0100,
+                });
+            }
+        }
+
+        Ok(function)
+    }
+
+    /// Returns the watchpoint kind this comparator is configured for, if it is a watchpoint.
+    fn watchpoint_kind(&self, version: DwtVersion) -> Option<WatchpointKind> {
+        match version {
+            DwtVersion::V7 => match self.function() {
+                0b0101 => Some(WatchpointKind::Read),
+                0b0110 => Some(WatchpointKind::Write),
+                0b0111 => Some(WatchpointKind::Access),
+                _ => None,
+            },
+            DwtVersion::V8 if self.action() == 0b01 => match self.function() {
+                0b0110 => Some(WatchpointKind::Read),
+                0b0101 => Some(WatchpointKind::Write),
+                0b0100 => Some(WatchpointKind::Access),
+                _ => None,
+            },
+            DwtVersion::V8 => None,
+        }
+    }
+}
+
+/// Enables the DWT. DEMCR.DWTENA (ARMv6-M) and DEMCR.TRCENA share the same bit.
+fn enable_dwt(memory: &mut dyn ArmMemoryInterface) -> Result<(), ArmError> {
+    let mut demcr = Demcr(memory.read_word_32(Demcr::get_mmio_address())?);
+    if !demcr.dwtena() {
+        demcr.set_dwtena(true);
+        memory.write_word_32(Demcr::get_mmio_address(), demcr.into())?;
+    }
+    Ok(())
+}
+
+fn dwt_unit_address(register: u64, unit_index: usize) -> u64 {
+    register + unit_index as u64 * DWT_COMPARATOR_STRIDE
+}
+
+/// The watchpoints configured in the DWT comparators.
+///
+/// Reading `DWT_FUNCTION` clears its sticky `MATCHED` bit, so the comparators are only read
+/// once, to find the watchpoints set before probe-rs attached, and the configuration is cached
+/// after that.
+#[derive(Debug, Default)]
+pub(crate) struct DwtWatchpoints {
+    num_units: Option<u32>,
+    watchpoints: Option<Vec<Option<Watchpoint>>>,
+}
+
+/// Returns the number of DWT comparators usable as watchpoints.
+///
+/// The DWT registers may not be readable while it is disabled, so it is enabled while reading
+/// the number of comparators, and disabled again afterwards.
+pub(crate) fn available_watchpoint_units(
+    memory: &mut dyn ArmMemoryInterface,
+    cache: &mut DwtWatchpoints,
+) -> Result<u32, ArmError> {
+    if let Some(num_units) = cache.num_units {
+        return Ok(num_units);
+    }
+
+    let demcr = Demcr(memory.read_word_32(Demcr::get_mmio_address())?);
+    enable_dwt(memory)?;
+    let ctrl = DwtCtrl(memory.read_word_32(DwtCtrl::get_mmio_address())?);
+    if !demcr.dwtena() {
+        memory.write_word_32(Demcr::get_mmio_address(), demcr.into())?;
+    }
+
+    cache.num_units = Some(ctrl.numcomp());
+    Ok(ctrl.numcomp())
+}
+
+fn read_watchpoint(
+    memory: &mut dyn ArmMemoryInterface,
+    version: DwtVersion,
+    unit_index: usize,
+    function: DwtFunction,
+) -> Result<Option<Watchpoint>, ArmError> {
+    let Some(kind) = function.watchpoint_kind(version) else {
+        return Ok(None);
+    };
+
+    let address =
+        memory.read_word_32(dwt_unit_address(DwtComp::get_mmio_address(), unit_index))? as u64;
+    let mask_bits = match version {
+        DwtVersion::V7 => {
+            DwtMask(memory.read_word_32(dwt_unit_address(DwtMask::get_mmio_address(), unit_index))?)
+                .mask()
+        }
+        DwtVersion::V8 => function.datavsize(),
+    };
+
+    Ok(Some(Watchpoint {
+        address,
+        size: 1 << mask_bits,
+        kind,
+    }))
+}
+
+/// Returns the watchpoints configured in the DWT comparators.
+///
+/// Comparators used for anything else (e.g. data trace) are reported as `None`.
+pub(crate) fn hw_watchpoints(
+    memory: &mut dyn ArmMemoryInterface,
+    version: DwtVersion,
+    cache: &mut DwtWatchpoints,
+) -> Result<Vec<Option<Watchpoint>>, ArmError> {
+    if let Some(watchpoints) = &cache.watchpoints {
+        return Ok(watchpoints.clone());
+    }
+
+    let num_units = available_watchpoint_units(memory, cache)? as usize;
+    let mut watchpoints = Vec::with_capacity(num_units);
+    // With the DWT disabled, no watchpoint can be set.
+    if Demcr(memory.read_word_32(Demcr::get_mmio_address())?).dwtena() {
+        for unit_index in 0..num_units {
+            let function = DwtFunction(memory.read_word_32(dwt_unit_address(
+                DwtFunction::get_mmio_address(),
+                unit_index,
+            ))?);
+            watchpoints.push(read_watchpoint(memory, version, unit_index, function)?);
+        }
+    } else {
+        watchpoints.resize(num_units, None);
+    }
+
+    cache.watchpoints = Some(watchpoints.clone());
+    Ok(watchpoints)
+}
+
+/// Configures DWT comparator `unit_index` as a watchpoint.
+pub(crate) fn set_hw_watchpoint(
+    memory: &mut dyn ArmMemoryInterface,
+    version: DwtVersion,
+    cache: &mut DwtWatchpoints,
+    unit_index: usize,
+    watchpoint: Watchpoint,
+) -> Result<(), Error> {
+    let address = valid_32bit_address(watchpoint.address)?;
+    let function = DwtFunction::watchpoint_configuration(version, &watchpoint)?;
+    let mut watchpoints = hw_watchpoints(memory, version, cache)?;
+
+    enable_dwt(memory)?;
+
+    // Disable the comparator while it is being reconfigured.
+    let function_address = dwt_unit_address(DwtFunction::get_mmio_address(), unit_index);
+    memory.write_word_32(function_address, 0)?;
+    if let Some(cached) = watchpoints.get_mut(unit_index) {
+        *cached = None;
+    }
+    cache.watchpoints = Some(watchpoints.clone());
+    memory.write_word_32(
+        dwt_unit_address(DwtComp::get_mmio_address(), unit_index),
+        address,
+    )?;
+
+    if version == DwtVersion::V7 {
+        let mask_address = dwt_unit_address(DwtMask::get_mmio_address(), unit_index);
+        let mut mask = DwtMask(0);
+        mask.set_mask(watchpoint.mask_bits());
+        memory.write_word_32(mask_address, mask.into())?;
+
+        // The maximum mask size is implementation defined, unsupported bits read back as zero.
+        if DwtMask(memory.read_word_32(mask_address)?).mask() != watchpoint.mask_bits() {
+            return Err(Error::BreakpointOperation(
+                BreakpointError::UnsupportedWatchpointSize(watchpoint.size),
+            ));
+        }
+    }
+
+    memory.write_word_32(function_address, function.into())?;
+    if let Some(cached) = watchpoints.get_mut(unit_index) {
+        *cached = Some(watchpoint);
+    }
+    cache.watchpoints = Some(watchpoints);
+
+    Ok(())
+}
+
+/// Disables DWT comparator `unit_index`.
+pub(crate) fn clear_hw_watchpoint(
+    memory: &mut dyn ArmMemoryInterface,
+    cache: &mut DwtWatchpoints,
+    unit_index: usize,
+) -> Result<(), ArmError> {
+    memory.write_word_32(
+        dwt_unit_address(DwtFunction::get_mmio_address(), unit_index),
+        0,
+    )?;
+    if let Some(cached) = cache
+        .watchpoints
+        .as_mut()
+        .and_then(|watchpoints| watchpoints.get_mut(unit_index))
+    {
+        *cached = None;
+    }
+
+    Ok(())
+}
+
+/// Finds the watchpoint whose comparator matched.
+///
+/// Reading `DWT_FUNCTION` clears the `MATCHED` bit, so this only reports a watchpoint once.
+pub(crate) fn triggered_watchpoint(
+    memory: &mut dyn ArmMemoryInterface,
+    version: DwtVersion,
+    cache: &mut DwtWatchpoints,
+) -> Result<Option<Watchpoint>, ArmError> {
+    let watchpoints = hw_watchpoints(memory, version, cache)?;
+
+    for (unit_index, watchpoint) in watchpoints.into_iter().enumerate() {
+        let Some(watchpoint) = watchpoint else {
+            continue;
+        };
+        let function = DwtFunction(memory.read_word_32(dwt_unit_address(
+            DwtFunction::get_mmio_address(),
+            unit_index,
+        ))?);
+        if function.matched() {
+            return Ok(Some(watchpoint));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dwt_watchpoint_function_encoding() {
+        let watchpoint = Watchpoint::new(0x2000_0000, 4, WatchpointKind::Write).unwrap();
+
+        let v7 = DwtFunction::watchpoint_configuration(DwtVersion::V7, &watchpoint).unwrap();
+        assert_eq!(u32::from(v7), 0b0110);
+        assert_eq!(
+            v7.watchpoint_kind(DwtVersion::V7),
+            Some(WatchpointKind::Write)
+        );
+
+        let v8 = DwtFunction::watchpoint_configuration(DwtVersion::V8, &watchpoint).unwrap();
+        assert_eq!(u32::from(v8), 0b10 << 10 | 0b01 << 4 | 0b0101);
+        assert_eq!(
+            v8.watchpoint_kind(DwtVersion::V8),
+            Some(WatchpointKind::Write)
+        );
+    }
+
+    #[test]
+    fn dwt_v8_rejects_large_watchpoints() {
+        let watchpoint = Watchpoint::new(0x2000_0000, 8, WatchpointKind::Read).unwrap();
+        DwtFunction::watchpoint_configuration(DwtVersion::V8, &watchpoint).unwrap_err();
+    }
+}
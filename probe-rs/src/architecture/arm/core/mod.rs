@@ -150,6 +150,9 @@ pub struct CortexMState {
     /// `pending_step` tracks whether we're waiting for a step so that `CoreInterface::status()`
     /// can return `HaltReason::Step` instead of `HaltReason::Request` if a step was pending.
     pending_step: bool,
+
+    /// The watchpoints configured in the DWT.
+    dwt_watchpoints: cortex_m::DwtWatchpoints,
 }
 
 impl CortexMState {
@@ -161,6 +164,7 @@ impl CortexMState {
             fp_present: false,
             semihosting_command: None,
             pending_step: false,
+            dwt_watchpoints: cortex_m::DwtWatchpoints::default(),
         }
     }
 
@@ -215,6 +219,9 @@ pub struct CortexARState {
 
     // Cached semihosting command (for A/R-profile semihosting)
     pub(crate) semihosting_command: Option<SemihostingCommand>,
+
+    // CTI channels kept open for the run-control group of the core
+    run_control_gate: u32,
 }
 
 impl CortexARState {
@@ -226,6 +233,7 @@ impl CortexARState {
             register_cache: vec![],
             fp_reg_count: 0,
             semihosting_command: None,
+            run_control_gate: 0,
         }
     }
 
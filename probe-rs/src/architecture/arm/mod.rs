@@ -10,6 +10,7 @@ pub mod dp;
 pub mod memory;
 pub mod sequences;
 pub mod swo;
+pub mod trace;
 pub(crate) mod traits;
 
 pub use self::core::{Dump, armv6m, armv7ar, armv7m, armv8a, armv8m};
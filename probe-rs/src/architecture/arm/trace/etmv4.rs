@@ -0,0 +1,568 @@
+//! Decoder for the ETMv4 instruction trace protocol.
+
+use std::collections::VecDeque;
+
+use super::{CodeImage, InstructionRange, TraceElement, thumb::Branch};
+use crate::architecture::arm::component::EtmParameters;
+
+/// Number of zero bytes in front of the 0x80 byte of an alignment synchronization packet.
+const ASYNC_ZEROS: usize = 11;
+
+/// The trace stream could not be decoded.
+#[derive(Debug)]
+enum PacketError {
+    /// The trace ended in the middle of a packet.
+    Truncated,
+    /// The packet with this header is not supported.
+    Unsupported(u8),
+}
+
+/// Decoder for an ETMv4 instruction trace stream.
+///
+/// The decoder follows the trace through the [`CodeImage`] and yields the executed instructions
+/// as [`TraceElement`]s. Data before the first alignment synchronization packet is skipped, so the
+/// trace may start anywhere, for example in a circular buffer that has wrapped.
+///
+/// Conditional instruction trace, data trace and speculative trace are not supported. Only T32
+/// code can be followed: when the trace continues in the A32 or A64 instruction set, the decoder
+/// yields [`TraceElement::UnsupportedInstructionSet`] and skips the trace up to the next address
+/// in T32 code.
+pub struct Etmv4Decoder<'a> {
+    data: &'a [u8],
+    image: &'a CodeImage,
+    parameters: EtmParameters,
+    position: usize,
+    synchronized: bool,
+    /// Address of the next instruction to be executed, if it is known.
+    address: Option<u64>,
+    /// The address history used for address compression, most recent first, with whether each
+    /// address is in T32 code.
+    history: [(u64, bool); 3],
+    /// Whether the trace is in an instruction set other than T32.
+    unsupported_instruction_set: bool,
+    timestamp: u64,
+    /// Exception type of an exception packet that waits for its return address.
+    pending_exception: Option<u16>,
+    elements: VecDeque<TraceElement>,
+}
+
+impl<'a> Etmv4Decoder<'a> {
+    /// Create a decoder for the trace in `data`, generated by an ETM with `parameters` while
+    /// executing the program in `image`.
+    pub fn new(data: &'a [u8], image: &'a CodeImage, parameters: EtmParameters) -> Self {
+        Self {
+            data,
+            image,
+            parameters,
+            position: 0,
+            synchronized: false,
+            address: None,
+            history: [(0, true); 3],
+            unsupported_instruction_set: false,
+            timestamp: 0,
+            pending_exception: None,
+            elements: VecDeque::new(),
+        }
+    }
+
+    fn synchronize(&mut self) {
+        let sync = self.data[self.position..]
+            .windows(ASYNC_ZEROS + 1)
+            .position(|window| {
+                window[ASYNC_ZEROS] == 0x80 && window[..ASYNC_ZEROS].iter().all(|&b| b == 0)
+            });
+
+        match sync {
+            Some(offset) => {
+                self.position += offset + ASYNC_ZEROS + 1;
+                self.synchronized = true;
+            }
+            None => self.position = self.data.len(),
+        }
+    }
+
+    fn packet(&mut self) -> Result<(), PacketError> {
+        let header = self.byte()?;
+
+        match header {
+            0x00 => self.extension()?,
+            0x01 => self.trace_info()?,
+            0x02 | 0x03 => self.timestamp(header)?,
+            // Trace On
+            0x04 => self.lose_track(),
+            0x06 => self.exception()?,
+            // Exception return. The return itself is traced like any other branch.
+            0x07 => {}
+            // Cycle count format 2
+            0x0C | 0x0D => {
+                let payload = self.byte()?;
+                self.elements
+                    .push_back(TraceElement::CycleCount(u64::from(payload & 0xF)));
+            }
+            // Cycle count format 1
+            0x0E | 0x0F => {
+                if !self.parameters.commit_opt {
+                    self.continued(5)?;
+                }
+                if header & 1 == 0 {
+                    let count = self.continued(3)?;
+                    self.elements.push_back(TraceElement::CycleCount(count));
+                }
+            }
+            // Cycle count format 3
+            0x10..=0x1F => {
+                self.elements
+                    .push_back(TraceElement::CycleCount(u64::from(header & 0x3)));
+            }
+            // Data synchronization markers
+            0x20..=0x2C => {}
+            // Commit and cancel format 1
+            0x2D..=0x2F => {
+                self.continued(5)?;
+            }
+            // Mispredict, cancel formats 2 and 3
+            0x30..=0x3F => {}
+            // Ignore and event
+            0x70..=0x7F => {}
+            // Context, unchanged
+            0x80 => {}
+            0x81 => self.context()?,
+            0x82 | 0x83 => {
+                let address = self.long_address(header == 0x83, 4)?;
+                self.context()?;
+                self.address(address, header == 0x83);
+            }
+            0x85 | 0x86 => {
+                let address = self.long_address(header == 0x86, 8)?;
+                self.context()?;
+                self.address(address, header == 0x86);
+            }
+            // Exact match address
+            0x90..=0x92 => {
+                let (address, thumb) = self.history[usize::from(header & 0x3)];
+                self.address(address, thumb);
+            }
+            0x95 | 0x96 => {
+                let address = self.short_address(header == 0x96)?;
+                self.address(address, header == 0x96);
+            }
+            0x9A | 0x9B => {
+                let address = self.long_address(header == 0x9B, 4)?;
+                self.address(address, header == 0x9B);
+            }
+            0x9D | 0x9E => {
+                let address = self.long_address(header == 0x9E, 8)?;
+                self.address(address, header == 0x9E);
+            }
+            // Atom format 6: 3 to 23 E atoms, followed by an E or N atom.
+            0xC0..=0xD4 | 0xE0..=0xF4 => {
+                let count = u32::from(header & 0x1F) + 3;
+                let mut pattern = (1 << count) - 1;
+                if header & 0x20 == 0 {
+                    pattern |= 1 << count;
+                }
+                self.atoms(pattern, count + 1);
+            }
+            // Atom format 5
+            0xD5..=0xD7 | 0xF5 => {
+                let pattern = match ((header >> 3) & 0x4) | (header & 0x3) {
+                    5 => 0b11110,
+                    1 => 0b00000,
+                    2 => 0b01010,
+                    _ => 0b10101,
+                };
+                self.atoms(pattern, 5);
+            }
+            // Atom format 2
+            0xD8..=0xDB => self.atoms(u32::from(header & 0x3), 2),
+            // Atom format 4
+            0xDC..=0xDF => {
+                let pattern = [0b1110, 0b0000, 0b1010, 0b0101][usize::from(header & 0x3)];
+                self.atoms(pattern, 4);
+            }
+            // Atom format 1
+            0xF6 | 0xF7 => self.atoms(u32::from(header & 0x1), 1),
+            // Atom format 3
+            0xF8..=0xFF => self.atoms(u32::from(header & 0x7), 3),
+            _ => return Err(PacketError::Unsupported(header)),
+        }
+
+        Ok(())
+    }
+
+    fn extension(&mut self) -> Result<(), PacketError> {
+        match self.byte()? {
+            // Alignment synchronization
+            0x00 => loop {
+                match self.byte()? {
+                    0x00 => {}
+                    0x80 => break,
+                    other => return Err(PacketError::Unsupported(other)),
+                }
+            },
+            // Discard and overflow
+            0x03 | 0x05 => self.lose_track(),
+            other => return Err(PacketError::Unsupported(other)),
+        }
+        Ok(())
+    }
+
+    fn trace_info(&mut self) -> Result<(), PacketError> {
+        let sections = self.continued(2)?;
+        for section in 0..4 {
+            if sections & (1 << section) != 0 {
+                self.continued(5)?;
+            }
+        }
+        self.history = [(0, true); 3];
+        Ok(())
+    }
+
+    fn timestamp(&mut self, header: u8) -> Result<(), PacketError> {
+        let mut value = 0;
+        let mut bits = 0;
+        for index in 0..9 {
+            let byte = self.byte()?;
+            if index == 8 {
+                value |= u64::from(byte) << 56;
+                bits = 64;
+                break;
+            }
+            value |= u64::from(byte & 0x7F) << (7 * index);
+            bits += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+
+        let mask = u64::MAX >> (64 - bits);
+        self.timestamp = (self.timestamp & !mask) | value;
+        self.elements
+            .push_back(TraceElement::Timestamp(self.timestamp));
+
+        if header & 1 != 0 {
+            let count = self.continued(3)?;
+            self.elements.push_back(TraceElement::CycleCount(count));
+        }
+        Ok(())
+    }
+
+    fn exception(&mut self) -> Result<(), PacketError> {
+        let info = self.byte()?;
+        let mut number = u16::from(info >> 1) & 0x1F;
+        if info & 0x80 != 0 {
+            number |= (u16::from(self.byte()?) & 0x1F) << 5;
+        }
+        // The exception return address follows in an address packet.
+        self.pending_exception = Some(number);
+        Ok(())
+    }
+
+    fn context(&mut self) -> Result<(), PacketError> {
+        let info = self.byte()?;
+        if info & 0x40 != 0 {
+            self.bytes(self.parameters.vmid_size)?;
+        }
+        if info & 0x80 != 0 {
+            let context_id = self.bytes(self.parameters.context_id_size)?;
+            self.elements
+                .push_back(TraceElement::ContextId(context_id as u32));
+        }
+        Ok(())
+    }
+
+    /// Reads a short address packet, which replaces the lowest 8 or 16 bits of the last address
+    /// for the T32 instruction set, or 9 or 17 bits for A32.
+    fn short_address(&mut self, thumb: bool) -> Result<u64, PacketError> {
+        let shift = if thumb { 1 } else { 2 };
+        let first = self.byte()?;
+        let mut value = u64::from(first & 0x7F) << shift;
+        let mut bits = 7 + shift;
+        if first & 0x80 != 0 {
+            value |= u64::from(self.byte()?) << (7 + shift);
+            bits += 8;
+        }
+        let mask = (1 << bits) - 1;
+        Ok((self.history[0].0 & !mask) | value)
+    }
+
+    /// Reads a long address packet with a payload of `size` bytes.
+    fn long_address(&mut self, thumb: bool, size: usize) -> Result<u64, PacketError> {
+        let first = self.byte()?;
+        let second = self.byte()?;
+        let mut value = if thumb {
+            u64::from(first & 0x7F) << 1 | u64::from(second) << 8
+        } else {
+            u64::from(first & 0x7F) << 2 | u64::from(second & 0x7F) << 9
+        };
+        for index in 2..size {
+            value |= u64::from(self.byte()?) << (8 * index);
+        }
+        let mask = u64::MAX >> (64 - 8 * size);
+        Ok((self.history[0].0 & !mask) | value)
+    }
+
+    /// Handles the target of a branch or the return address of an exception, with whether it is
+    /// in T32 code.
+    fn address(&mut self, address: u64, thumb: bool) {
+        self.history = [(address, thumb), self.history[0], self.history[1]];
+
+        if !thumb {
+            // The instructions can not be followed until the trace returns to T32 code.
+            if !self.unsupported_instruction_set {
+                self.elements
+                    .push_back(TraceElement::UnsupportedInstructionSet { address });
+            }
+            self.unsupported_instruction_set = true;
+            self.pending_exception = None;
+            self.address = None;
+            return;
+        }
+        self.unsupported_instruction_set = false;
+
+        let Some(number) = self.pending_exception.take() else {
+            self.address = Some(address);
+            return;
+        };
+
+        // The instructions up to the return address were executed before the exception was taken.
+        if let Some(start) = self.address.filter(|&start| start != address) {
+            let element = match self.image.last_instruction_before(start, address) {
+                Some(last) => TraceElement::Instructions(InstructionRange {
+                    start,
+                    end: address,
+                    last,
+                    taken: false,
+                }),
+                None => TraceElement::Discontinuity,
+            };
+            self.elements.push_back(element);
+        }
+        self.elements.push_back(TraceElement::Exception {
+            number,
+            return_address: address,
+        });
+
+        // The address of the exception handler follows in the next address packet.
+        self.address = None;
+    }
+
+    fn atoms(&mut self, pattern: u32, count: u32) {
+        for index in 0..count {
+            self.atom(pattern & (1 << index) != 0);
+        }
+    }
+
+    fn atom(&mut self, taken: bool) {
+        let Some(start) = self.address else {
+            return;
+        };
+        let Some((branch, instruction)) = self.image.next_branch(start) else {
+            tracing::debug!(
+                "Lost track of the trace at {start:#010x}, no branch in the code image"
+            );
+            self.lose_track();
+            return;
+        };
+
+        let end = branch + u64::from(instruction.size);
+        self.elements
+            .push_back(TraceElement::Instructions(InstructionRange {
+                start,
+                end,
+                last: branch,
+                taken,
+            }));
+
+        self.address = match (taken, instruction.branch) {
+            (false, _) => Some(end),
+            (true, Branch::Direct(target)) => Some(target),
+            // The target of an indirect branch follows in an address packet.
+            (true, _) => None,
+        };
+    }
+
+    fn lose_track(&mut self) {
+        if self.address.take().is_some() {
+            self.elements.push_back(TraceElement::Discontinuity);
+        }
+        self.pending_exception = None;
+    }
+
+    fn byte(&mut self) -> Result<u8, PacketError> {
+        let byte = *self.data.get(self.position).ok_or(PacketError::Truncated)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    /// Reads a little endian value of `size` bytes.
+    fn bytes(&mut self, size: u8) -> Result<u64, PacketError> {
+        let mut value = 0;
+        for index in 0..size {
+            value |= u64::from(self.byte()?) << (8 * index);
+        }
+        Ok(value)
+    }
+
+    /// Reads a value of at most `max_size` bytes, where bit 7 of every byte indicates that
+    /// another byte follows.
+    fn continued(&mut self, max_size: usize) -> Result<u64, PacketError> {
+        let mut value = 0;
+        for index in 0..max_size {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7F) << (7 * index);
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        Ok(value)
+    }
+}
+
+impl Iterator for Etmv4Decoder<'_> {
+    type Item = TraceElement;
+
+    fn next(&mut self) -> Option<TraceElement> {
+        while self.elements.is_empty() && self.position < self.data.len() {
+            if !self.synchronized {
+                self.synchronize();
+                continue;
+            }
+
+            let start = self.position;
+            match self.packet() {
+                Ok(()) => {}
+                Err(PacketError::Truncated) => self.position = self.data.len(),
+                Err(PacketError::Unsupported(header)) => {
+                    tracing::warn!(
+                        "Unsupported ETMv4 packet {header:#04x} at offset {start}, resynchronizing"
+                    );
+                    self.synchronized = false;
+                    self.lose_track();
+                }
+            }
+        }
+
+        self.elements.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn image() -> CodeImage {
+        let code: [u16; 7] = [
+            0x2000, // 0x1000: movs r0, #0
+            0xE001, // 0x1002: b.n 0x1008
+            0xBF00, // 0x1004: nop
+            0xBF00, // 0x1006: nop
+            0x2801, // 0x1008: cmp r0, #1
+            0xD1F9, // 0x100a: bne.n 0x1000
+            0x4770, // 0x100c: bx lr
+        ];
+        let mut image = CodeImage::new();
+        image.add_segment(
+            0x1000,
+            code.iter().flat_map(|hw| hw.to_le_bytes()).collect(),
+        );
+        image
+    }
+
+    fn range(start: u64, end: u64, last: u64, taken: bool) -> TraceElement {
+        TraceElement::Instructions(InstructionRange {
+            start,
+            end,
+            last,
+            taken,
+        })
+    }
+
+    #[test]
+    fn decode_program_flow() {
+        let mut trace = vec![0x12, 0x34];
+        // Alignment synchronization and trace info
+        trace.extend([0; ASYNC_ZEROS]);
+        trace.extend([0x80, 0x01, 0x00]);
+        trace.extend([
+            0x9B, 0x00, 0x10, 0x00, 0x00, // Address 0x1000
+            0xF7, // E
+            0xF6, // N
+            0xF7, // E
+            0x96, 0x20, // Short address 0x1040
+            0xF7, // E, outside of the code image
+            0x91, // Exact match address 0x1000
+            0x06, 0x06, // Exception 3
+            0x9B, 0x04, 0x10, 0x00, 0x00, // Return address 0x1008
+            0x9B, 0x00, 0x10, 0x00, 0x00, // Exception handler at 0x1000
+            0x04, // Trace on
+            0x02, 0x85, 0x01, // Timestamp
+            0x9B, 0x00, // Truncated address
+        ]);
+
+        let image = image();
+        let decoder = Etmv4Decoder::new(&trace, &image, EtmParameters::default());
+
+        assert_eq!(
+            decoder.collect::<Vec<_>>(),
+            [
+                range(0x1000, 0x1004, 0x1002, true),
+                range(0x1008, 0x100C, 0x100A, false),
+                range(0x100C, 0x100E, 0x100C, true),
+                TraceElement::Discontinuity,
+                range(0x1000, 0x1008, 0x1006, false),
+                TraceElement::Exception {
+                    number: 3,
+                    return_address: 0x1008
+                },
+                TraceElement::Discontinuity,
+                TraceElement::Timestamp(0x85),
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_atom_formats() {
+        let mut trace = vec![0; ASYNC_ZEROS];
+        trace.extend([
+            0x80, 0x9B, 0x04, 0x10, 0x00, 0x00, // Address 0x1008
+            0xDC, // Atom format 4: N, E, E, E
+        ]);
+
+        let image = image();
+        let decoder = Etmv4Decoder::new(&trace, &image, EtmParameters::default());
+
+        assert_eq!(
+            decoder.collect::<Vec<_>>(),
+            [
+                range(0x1008, 0x100C, 0x100A, false),
+                range(0x100C, 0x100E, 0x100C, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn a32_code_is_skipped() {
+        let mut trace = vec![0; ASYNC_ZEROS];
+        trace.extend([
+            0x80, 0x9A, 0x00, 0x10, 0x00, 0x00, // A32 address 0x2000
+            0xF7, // E
+            0x95, 0x01, // A32 short address 0x2004
+            0xF7, // E
+            0x9B, 0x04, 0x10, 0x00, 0x00, // Address 0x1008
+            0xDC, // Atom format 4: N, E, E, E
+        ]);
+
+        let image = image();
+        let decoder = Etmv4Decoder::new(&trace, &image, EtmParameters::default());
+
+        assert_eq!(
+            decoder.collect::<Vec<_>>(),
+            [
+                TraceElement::UnsupportedInstructionSet { address: 0x2000 },
+                range(0x1008, 0x100C, 0x100A, false),
+                range(0x100C, 0x100E, 0x100C, true),
+            ]
+        );
+    }
+}
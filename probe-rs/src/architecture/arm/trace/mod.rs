@@ -0,0 +1,138 @@
+//! Reconstruction of the executed instructions from a program flow trace.
+//!
+//! Program flow trace sources like the ETM only record the outcome of branches, and the target
+//! address of branches that can not be determined from the program itself. To recover the
+//! executed instructions, the decoders in this module follow the trace through a [`CodeImage`]
+//! of the program running on the target.
+//!
+//! Only the T32 (Thumb) instruction set is supported, as used by Cortex-M cores. Trace of A32 (ARM)
+//! or A64 code is reported as [`TraceElement::UnsupportedInstructionSet`], and only ETMv4 trace
+//! streams can be decoded. The ETMv3 and PTM trace of cores like the Cortex-M3, Cortex-M4 and
+//! Cortex-A9 is not supported.
+
+mod etmv4;
+pub(crate) mod thumb;
+
+pub use etmv4::Etmv4Decoder;
+
+/// Upper bound for the number of instructions that are followed until the next branch is found.
+const MAX_INSTRUCTIONS_WITHOUT_BRANCH: usize = 0x10000;
+
+/// The code of the program running on the target.
+#[derive(Debug, Clone, Default)]
+pub struct CodeImage {
+    segments: Vec<(u64, Vec<u8>)>,
+}
+
+impl CodeImage {
+    /// Create an empty code image.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `data` that is located at `address` in the memory of the target.
+    pub fn add_segment(&mut self, address: u64, data: Vec<u8>) {
+        self.segments.push((address, data));
+    }
+
+    /// Returns true if the image contains code at `address`.
+    pub fn contains(&self, address: u64) -> bool {
+        self.read_u16(address).is_some()
+    }
+
+    fn read_u16(&self, address: u64) -> Option<u16> {
+        self.segments.iter().find_map(|(start, data)| {
+            let offset = usize::try_from(address.checked_sub(*start)?).ok()?;
+            let bytes = data.get(offset..offset + 2)?;
+            Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+        })
+    }
+
+    /// Decodes the instruction at `address`.
+    pub(crate) fn instruction(&self, address: u64) -> Option<thumb::Instruction> {
+        let hw1 = self.read_u16(address)?;
+        let hw2 = if thumb::is_32bit(hw1) {
+            self.read_u16(address + 2)?
+        } else {
+            0
+        };
+        Some(thumb::decode(address, hw1, hw2))
+    }
+
+    /// Finds the first branch instruction at or after `address`.
+    ///
+    /// Returns the address of the branch and the decoded instruction.
+    pub(crate) fn next_branch(&self, mut address: u64) -> Option<(u64, thumb::Instruction)> {
+        for _ in 0..MAX_INSTRUCTIONS_WITHOUT_BRANCH {
+            let instruction = self.instruction(address)?;
+            if instruction.branch != thumb::Branch::None {
+                return Some((address, instruction));
+            }
+            address += u64::from(instruction.size);
+        }
+        None
+    }
+
+    /// Finds the address of the last instruction before `end`, when executing straight from
+    /// `start`.
+    pub(crate) fn last_instruction_before(&self, mut address: u64, end: u64) -> Option<u64> {
+        let mut last = None;
+        while address < end {
+            last = Some(address);
+            address += u64::from(self.instruction(address)?.size);
+        }
+        (address == end).then_some(last).flatten()
+    }
+}
+
+/// A sequence of instructions that were executed one after another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstructionRange {
+    /// Address of the first executed instruction.
+    pub start: u64,
+    /// Address after the last executed instruction.
+    pub end: u64,
+    /// Address of the last executed instruction.
+    pub last: u64,
+    /// Whether the last instruction is a branch that was taken. Execution continues at the start
+    /// of the next range.
+    pub taken: bool,
+}
+
+/// An element of the reconstructed program flow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceElement {
+    /// A sequence of instructions was executed.
+    Instructions(InstructionRange),
+
+    /// An exception was taken. Execution continues in the exception handler at the start of the
+    /// next range.
+    Exception {
+        /// The architecture specific exception type.
+        number: u16,
+        /// The address execution returns to once the exception handler is done.
+        return_address: u64,
+    },
+
+    /// Some instructions were not traced, for example because the trace unit overflowed or the
+    /// trace went through code that is not part of the code image. Execution continues at the
+    /// start of the next range.
+    Discontinuity,
+
+    /// Execution continued in the A32 or A64 instruction set at `address`, which can not be
+    /// decoded. The instructions are not traced until execution returns to T32 code, which
+    /// starts the next range.
+    UnsupportedInstructionSet {
+        /// The first address in the unsupported instruction set.
+        address: u64,
+    },
+
+    /// Cycle count, as encoded in the trace.
+    CycleCount(u64),
+
+    /// The value of the global timestamp.
+    Timestamp(u64),
+
+    /// The context ID changed.
+    ContextId(u32),
+}
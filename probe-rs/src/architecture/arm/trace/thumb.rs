@@ -0,0 +1,160 @@
+//! Classification of T32 (Thumb) instructions, as far as it is needed to follow the program flow.
+
+/// How an instruction changes the program flow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Branch {
+    /// The instruction is not a branch.
+    None,
+    /// A branch to an address encoded in the instruction.
+    Direct(u64),
+    /// A branch to an address taken from a register or from memory.
+    Indirect,
+}
+
+/// A decoded instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Instruction {
+    /// Size of the instruction in bytes.
+    pub size: u8,
+    /// The branch performed by the instruction, if it is taken.
+    pub branch: Branch,
+}
+
+/// Returns true if `halfword` is the first halfword of a 32-bit instruction.
+pub(crate) fn is_32bit(halfword: u16) -> bool {
+    matches!(halfword >> 11, 0b11101..=0b11111)
+}
+
+/// Decodes the instruction at `address`, consisting of `hw1` and, for 32-bit instructions, `hw2`.
+pub(crate) fn decode(address: u64, hw1: u16, hw2: u16) -> Instruction {
+    if is_32bit(hw1) {
+        Instruction {
+            size: 4,
+            branch: decode_32bit(address, hw1, hw2),
+        }
+    } else {
+        Instruction {
+            size: 2,
+            branch: decode_16bit(address, hw1),
+        }
+    }
+}
+
+fn decode_16bit(address: u64, hw1: u16) -> Branch {
+    let pc = address + 4;
+
+    if hw1 & 0xF000 == 0xD000 {
+        // B<c> T1. Condition codes 0b1110 and 0b1111 encode UDF and SVC.
+        if (hw1 >> 8) & 0xF < 0xE {
+            let offset = sign_extend(u32::from(hw1 & 0xFF) << 1, 9);
+            return Branch::Direct(pc.wrapping_add_signed(offset));
+        }
+    } else if hw1 & 0xF800 == 0xE000 {
+        // B T2
+        let offset = sign_extend(u32::from(hw1 & 0x7FF) << 1, 12);
+        return Branch::Direct(pc.wrapping_add_signed(offset));
+    } else if hw1 & 0xF500 == 0xB100 {
+        // CBZ, CBNZ
+        let offset = (u64::from(hw1 >> 9) & 1) << 6 | (u64::from(hw1 >> 3) & 0x1F) << 1;
+        return Branch::Direct(pc + offset);
+    } else if hw1 & 0xFF00 == 0x4700 || hw1 & 0xFF00 == 0xBD00 || hw1 & 0xFD87 == 0x4487 {
+        // BX and BLX (register), POP {..., pc}, and ADD or MOV to pc.
+        return Branch::Indirect;
+    }
+
+    Branch::None
+}
+
+fn decode_32bit(address: u64, hw1: u16, hw2: u16) -> Branch {
+    let pc = address + 4;
+
+    if hw1 & 0xF800 == 0xF000 && hw2 & 0x8000 != 0 {
+        let s = u32::from(hw1 >> 10) & 1;
+        let j1 = u32::from(hw2 >> 13) & 1;
+        let j2 = u32::from(hw2 >> 11) & 1;
+        let imm11 = u32::from(hw2 & 0x7FF);
+
+        match hw2 & 0x5000 {
+            // B<c> T3. Condition codes 0b111x encode miscellaneous control instructions.
+            0x0000 if (hw1 >> 6) & 0xF < 0xE => {
+                let imm6 = u32::from(hw1 & 0x3F);
+                let offset =
+                    sign_extend(s << 20 | j2 << 19 | j1 << 18 | imm6 << 12 | imm11 << 1, 21);
+                return Branch::Direct(pc.wrapping_add_signed(offset));
+            }
+            // B T4, BL and BLX (immediate)
+            0x1000 | 0x4000 | 0x5000 => {
+                let imm10 = u32::from(hw1 & 0x3FF);
+                let i1 = !(j1 ^ s) & 1;
+                let i2 = !(j2 ^ s) & 1;
+                let offset =
+                    sign_extend(s << 24 | i1 << 23 | i2 << 22 | imm10 << 12 | imm11 << 1, 25);
+                let base = if hw2 & 0x5000 == 0x4000 { pc & !3 } else { pc };
+                return Branch::Direct(base.wrapping_add_signed(offset));
+            }
+            _ => {}
+        }
+    } else if hw1 & 0xFF70 == 0xF850 && hw2 >> 12 == 0xF {
+        // LDR pc, [...]
+        return Branch::Indirect;
+    } else if (hw1 & 0xFFD0 == 0xE890 || hw1 & 0xFFD0 == 0xE910) && hw2 & 0x8000 != 0 {
+        // LDM and POP {..., pc}
+        return Branch::Indirect;
+    } else if hw1 & 0xFFF0 == 0xE8D0 && hw2 & 0xFFE0 == 0xF000 {
+        // TBB, TBH
+        return Branch::Indirect;
+    }
+
+    Branch::None
+}
+
+/// Sign-extends the lowest `bits` bits of `value`.
+fn sign_extend(value: u32, bits: u32) -> i64 {
+    let shift = 32 - bits;
+    i64::from(((value << shift) as i32) >> shift)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn branches() {
+        // b.n 0x1008
+        assert_eq!(decode(0x1002, 0xE001, 0).branch, Branch::Direct(0x1008));
+        // bne.n 0x1000
+        assert_eq!(decode(0x100A, 0xD1F9, 0).branch, Branch::Direct(0x1000));
+        // cbz r0, 0x101c
+        assert_eq!(decode(0x1000, 0xB160, 0).branch, Branch::Direct(0x101C));
+        // bl 0x2000
+        assert_eq!(
+            decode(0x1000, 0xF000, 0xFFFE),
+            Instruction {
+                size: 4,
+                branch: Branch::Direct(0x2000)
+            }
+        );
+        // bl 0x0800 (backwards)
+        assert_eq!(decode(0x1000, 0xF7FF, 0xFBFE).branch, Branch::Direct(0x800));
+        // beq.w 0x1100
+        assert_eq!(
+            decode(0x1000, 0xF000, 0x807E).branch,
+            Branch::Direct(0x1100)
+        );
+        // bx lr, pop {r4, pc}, ldr pc, [sp], #4, tbb [pc, r0]
+        assert_eq!(decode(0x1000, 0x4770, 0).branch, Branch::Indirect);
+        assert_eq!(decode(0x1000, 0xBD10, 0).branch, Branch::Indirect);
+        assert_eq!(decode(0x1000, 0xF85D, 0xFB04).branch, Branch::Indirect);
+        assert_eq!(decode(0x1000, 0xE8DF, 0xF000).branch, Branch::Indirect);
+        // movs r0, #0, svc 0, ldr.w r0, [r1]
+        assert_eq!(decode(0x1000, 0x2000, 0).branch, Branch::None);
+        assert_eq!(decode(0x1000, 0xDF00, 0).branch, Branch::None);
+        assert_eq!(
+            decode(0x1000, 0xF8D1, 0x0000),
+            Instruction {
+                size: 4,
+                branch: Branch::None
+            }
+        );
+    }
+}
@@ -8,7 +8,8 @@ use crate::architecture::riscv::dtm::DtmAccess;
 use crate::memory::valid_32bit_address;
 use crate::probe::queue::DeferredResultIndex;
 use crate::{
-    Error as ProbeRsError, architecture::riscv::*, config::Target, memory_mapped_bitfield_register,
+    Error as ProbeRsError, RunControlEvent, architecture::riscv::*, config::Target,
+    memory_mapped_bitfield_register,
 };
 use std::any::Any;
 use std::collections::HashMap;
@@ -78,6 +79,9 @@ pub enum RiscvError {
     /// The hart is unavailable
     #[error("The requested hart is unavailable.")]
     HartUnavailable,
+    /// The debug module cannot select several harts at once.
+    #[error("The debug module does not support selecting multiple harts through the hart array.")]
+    HartArrayNotSupported,
 }
 
 impl From<RiscvError> for ProbeRsError {
@@ -346,6 +350,12 @@ pub struct RiscvCommunicationInterfaceState {
     /// The current value of the `dmcontrol` register.
     current_dmcontrol: Dmcontrol,
 
+    /// The harts of each run-control group, as a hart array mask for each 32-hart window.
+    hart_groups: HashMap<u8, Vec<u32>>,
+
+    /// The number of hart array windows written to select a group so far.
+    hart_array_windows: usize,
+
     memory_access_config: MemoryAccessConfig,
 
     sw_breakpoint_debug_enabled: bool,
@@ -366,6 +376,9 @@ pub struct RiscvCommunicationInterfaceState {
 /// Timeout for RISC-V operations.
 const RISCV_TIMEOUT: Duration = Duration::from_secs(5);
 
+/// Time to wait for all harts of a run-control group to acknowledge a halt or resume request.
+const HART_GROUP_TIMEOUT: Duration = Duration::from_millis(100);
+
 /// RiscV only supports 12bit CSRs. See
 /// [Zicsr](https://riscv.org/wp-content/uploads/2019/06/riscv-spec.pdf#chapter.9) extension
 const RISCV_MAX_CSR_ADDR: u16 = 0xFFF;
@@ -409,6 +422,9 @@ impl RiscvCommunicationInterfaceState {
 
             current_dmcontrol: Dmcontrol(0),
 
+            hart_groups: HashMap::new(),
+            hart_array_windows: 0,
+
             memory_access_config: MemoryAccessConfig::default(),
 
             sw_breakpoint_debug_enabled: false,
@@ -540,6 +556,9 @@ impl<'state> RiscvCommunicationInterface<'state> {
             return Ok(());
         }
 
+        // The cached halt state belongs to the previously selected hart.
+        self.state.is_halted = false;
+
         // Since we changed harts, we don't know the state of the Dmcontrol register anymore.
         let mut control = self.read_dm_register::<Dmcontrol>()?;
         control.set_dmactive(true);
@@ -2686,6 +2705,154 @@ impl<'state> RiscvCommunicationInterface<'state> {
         }
     }
 
+    /// Adds the selected hart to run-control group `group`.
+    ///
+    /// The hart is moved into the matching halt group of the Debug Module, so that the hardware
+    /// halts every hart of the group as soon as one of them halts. Harts are not placed in a resume
+    /// group: the group is resumed through the hart array instead, so single-stepping one hart
+    /// never releases the others.
+    pub(crate) fn join_hart_group(&mut self, group: u8) -> Result<(), RiscvError> {
+        if !self.supports_hart_array()? {
+            return Err(RiscvError::HartArrayNotSupported);
+        }
+
+        let halt_group = group.checked_add(1).filter(|halt_group| *halt_group < 32);
+        if !halt_group.is_some_and(|halt_group| self.set_halt_group(halt_group).unwrap_or(false)) {
+            tracing::warn!(
+                "The debug module has no halt group for run-control group {group}, its harts only halt together when requested by the debugger."
+            );
+        }
+
+        let hart = self.state.last_selected_hart;
+        let (window, bit) = (hart as usize / 32, hart % 32);
+        let masks = self.state.hart_groups.entry(group).or_default();
+        if masks.len() <= window {
+            masks.resize(window + 1, 0);
+        }
+        masks[window] |= 1 << bit;
+
+        Ok(())
+    }
+
+    /// Removes the selected hart from run-control group `group`.
+    pub(crate) fn leave_hart_group(&mut self, group: u8) -> Result<(), RiscvError> {
+        let hart = self.state.last_selected_hart;
+        let (window, bit) = (hart as usize / 32, hart % 32);
+        if let Some(masks) = self.state.hart_groups.get_mut(&group) {
+            if let Some(mask) = masks.get_mut(window) {
+                *mask &= !(1 << bit);
+            }
+            if masks.iter().all(|mask| *mask == 0) {
+                self.state.hart_groups.remove(&group);
+            }
+        }
+
+        self.set_halt_group(0)?;
+
+        Ok(())
+    }
+
+    /// Halts or resumes all harts of run-control group `group` with a single `dmcontrol` write.
+    pub(crate) fn trigger_hart_group(
+        &mut self,
+        group: u8,
+        event: RunControlEvent,
+    ) -> Result<(), RiscvError> {
+        let Some(masks) = self.state.hart_groups.get(&group) else {
+            return Ok(());
+        };
+
+        // The hart array keeps the harts selected for other groups, so every window written
+        // before is written again, with no harts for the windows this group has no harts in.
+        let windows = masks.len().max(self.state.hart_array_windows);
+        let masks = (0..windows)
+            .map(|window| masks.get(window).copied().unwrap_or_default())
+            .collect::<Vec<_>>();
+        self.state.hart_array_windows = windows;
+        for (window, mask) in masks.into_iter().enumerate() {
+            let mut hawindowsel = Hawindowsel(0);
+            hawindowsel.set_hawindowsel(window as u32);
+            self.schedule_write_dm_register(hawindowsel)?;
+            self.schedule_write_dm_register(Hawindow(mask))?;
+        }
+
+        let mut idle = self.state.current_dmcontrol;
+        idle.set_dmactive(true);
+        idle.set_hasel(true);
+        idle.set_haltreq(false);
+        idle.set_resumereq(false);
+
+        let mut dmcontrol = idle;
+        match event {
+            RunControlEvent::Halt => dmcontrol.set_haltreq(true),
+            RunControlEvent::Restart => dmcontrol.set_resumereq(true),
+        }
+        self.schedule_write_dm_register(dmcontrol)?;
+
+        // Wait while the hart array is still selected, so `dmstatus` covers all harts of the
+        // group. The halt request has to stay asserted until every hart has halted.
+        let start = Instant::now();
+        let result = loop {
+            let status: Dmstatus = self.read_dm_register()?;
+            let done = match event {
+                RunControlEvent::Halt => status.allhalted(),
+                RunControlEvent::Restart => status.allresumeack() || status.allrunning(),
+            };
+            if done {
+                break Ok(());
+            }
+            if start.elapsed() >= HART_GROUP_TIMEOUT {
+                break Err(RiscvError::RequestNotAcknowledged);
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        };
+
+        // Clear the request for the whole array before going back to the single selected hart,
+        // otherwise the other harts keep a pending halt request.
+        self.schedule_write_dm_register(idle)?;
+        idle.set_hasel(false);
+        self.write_dm_register(idle)?;
+
+        // `false` will re-query the DM for the selected hart.
+        self.state.is_halted = false;
+
+        result
+    }
+
+    /// Checks whether the Debug Module implements `hasel`, by setting it and reading it back.
+    fn supports_hart_array(&mut self) -> Result<bool, RiscvError> {
+        let mut dmcontrol = self.state.current_dmcontrol;
+        dmcontrol.set_dmactive(true);
+        dmcontrol.set_hasel(true);
+        self.schedule_write_dm_register(dmcontrol)?;
+
+        let readback: Dmcontrol = self.read_dm_register()?;
+
+        dmcontrol.set_hasel(false);
+        self.write_dm_register(dmcontrol)?;
+
+        Ok(readback.hasel())
+    }
+
+    /// Moves the selected hart into halt group `group`, where group 0 means no group.
+    ///
+    /// Returns whether the Debug Module accepted the group. `dmcs2` only exists on spec 1.0
+    /// Debug Modules, and the number of halt groups is implementation defined.
+    fn set_halt_group(&mut self, group: u8) -> Result<bool, RiscvError> {
+        if self.state.debug_version != DebugModuleVersion::Version1_0 {
+            return Ok(group == 0);
+        }
+
+        let mut dmcs2 = Dmcs2(0);
+        dmcs2.set_group(group as u32);
+        dmcs2.set_hgwrite(true);
+        self.schedule_write_dm_register(dmcs2)?;
+
+        let readback: Dmcs2 = self.read_dm_register()?;
+
+        Ok(readback.group() == group as u32)
+    }
+
     /// Perform a reset of all harts on the target and halt them at the first instruction.
     pub fn reset_hart_and_halt(&mut self, timeout: Duration) -> Result<(), RiscvError> {
         tracing::debug!("Resetting core, setting hartreset bit");
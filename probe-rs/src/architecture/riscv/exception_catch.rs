@@ -0,0 +1,399 @@
+//! Halting on traps, the RISC-V counterpart of vector catch.
+//!
+//! Exceptions are caught with an exception trigger (`etrigger`), which halts the hart right after
+//! the trap was taken, with `mcause` and `mtval` describing it. Non-maskable interrupts are caught
+//! with an interrupt trigger (`itrigger`) where one is available. Harts without an exception
+//! trigger get an execution breakpoint on the trap vector in `mtvec` instead. All traps enter the
+//! vector there, so halts on traps that are not caught are resumed transparently.
+
+use super::{
+    RiscvCore, XlenMode,
+    communication_interface::{AbstractCommandErrorKind, RiscvError},
+    registers::{MCAUSE, MTVAL},
+};
+use crate::{BreakpointCause, CoreInterface, Error, HaltReason, VectorCatchCondition};
+use bitfield::bitfield;
+use std::time::Duration;
+
+const TSELECT: u16 = 0x7a0;
+const TDATA1: u16 = 0x7a1;
+const TDATA2: u16 = 0x7a2;
+const TINFO: u16 = 0x7a4;
+const MTVEC: u16 = 0x305;
+const DPC: u16 = 0x7b1;
+
+/// Trigger type of `mcontrol`, `itrigger`, `etrigger` and `mcontrol6`.
+const MCONTROL: u32 = 2;
+const ITRIGGER: u32 = 4;
+const ETRIGGER: u32 = 5;
+const MCONTROL6: u32 = 6;
+
+/// Exception codes of faults: misaligned accesses, access faults, illegal instructions, page
+/// faults, software checks and hardware errors.
+const FAULT_EXCEPTIONS: u64 = (1 << 0)
+    | (1 << 1)
+    | (1 << 2)
+    | (1 << 4)
+    | (1 << 5)
+    | (1 << 6)
+    | (1 << 7)
+    | (1 << 12)
+    | (1 << 13)
+    | (1 << 15)
+    | (1 << 18)
+    | (1 << 19);
+
+bitfield! {
+    /// The XLEN-independent low bits of an `itrigger` (type 4) or `etrigger` (type 5).
+    struct TrapTrigger(u32);
+    impl Debug;
+
+    vs, set_vs: 12;
+    vu, set_vu: 11;
+    /// `itrigger` only: fire on non-maskable interrupts.
+    nmi, set_nmi: 10;
+    m, set_m: 9;
+    s, set_s: 7;
+    u, set_u: 6;
+    action, set_action: 5, 0;
+}
+
+/// The exception catch configuration of a hart.
+#[derive(Debug, Default)]
+pub(super) struct ExceptionCatch {
+    /// Exception codes that halt the hart, as a bit mask.
+    exceptions: u64,
+
+    /// Whether non-maskable interrupts halt the hart.
+    nmi: bool,
+
+    /// The trigger configured as `etrigger`.
+    etrigger: Option<usize>,
+
+    /// The trigger configured as `itrigger`.
+    itrigger: Option<usize>,
+
+    /// The trigger holding the breakpoint on the trap vector, and the vector address.
+    trap_vector: Option<(usize, u64)>,
+
+    /// Set while a trap that is not caught is stepped over.
+    resuming: bool,
+}
+
+impl ExceptionCatch {
+    /// Returns `true` if trigger `unit` is used by the exception catch.
+    pub(super) fn reserves(&self, unit: usize) -> bool {
+        self.reserved_units().contains(&unit)
+    }
+
+    /// The triggers used by the exception catch.
+    pub(super) fn reserved_units(&self) -> Vec<usize> {
+        [
+            self.etrigger,
+            self.itrigger,
+            self.trap_vector.map(|(unit, _)| unit),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    fn is_active(&self) -> bool {
+        self.etrigger.is_some() || self.itrigger.is_some() || self.trap_vector.is_some()
+    }
+}
+
+/// How a halt on a trigger relates to the exception catch.
+#[derive(Debug, PartialEq)]
+enum TrapHalt {
+    /// The halt was not caused by the exception catch.
+    None,
+    /// The hart halted on a caught trap.
+    Caught,
+    /// The hart halted on the trap vector breakpoint for a trap that is not caught.
+    Uncaught,
+}
+
+/// Returns `true` if `mcause` reports one of the exception codes in `exceptions`.
+fn is_caught_exception(mcause: u64, xlen: u32, exceptions: u64) -> bool {
+    let interrupt = mcause >> (xlen - 1) & 1 == 1;
+    let code = mcause & !(1 << (xlen - 1));
+
+    !interrupt && code < 64 && exceptions >> code & 1 == 1
+}
+
+impl<X: XlenMode> RiscvCore<'_, X> {
+    /// Enables or disables halting on the traps of `condition`.
+    pub(super) fn set_exception_catch(
+        &mut self,
+        condition: VectorCatchCondition,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        match condition {
+            VectorCatchCondition::HardFault | VectorCatchCondition::All => {}
+            // Halting after reset is done through `resethaltreq` by the reset sequences, and
+            // environment calls are how RISC-V firmware enters its kernel, catching them is not
+            // useful.
+            VectorCatchCondition::CoreReset
+            | VectorCatchCondition::SecureFault
+            | VectorCatchCondition::Svc
+            | VectorCatchCondition::Hlt => {
+                return Err(Error::NotImplemented(
+                    "vector catch condition CoreReset/SecureFault/Svc/Hlt",
+                ));
+            }
+        };
+
+        let catch = &mut self.state.exception_catch;
+        catch.exceptions = if enabled { FAULT_EXCEPTIONS } else { 0 };
+        catch.nmi = enabled;
+
+        let was_running = !self.core_halted()?;
+        if was_running {
+            self.halt(Duration::from_millis(100))?;
+        }
+
+        let result = self.configure_exception_catch();
+
+        if was_running {
+            self.resume_core()?;
+        }
+
+        result
+    }
+
+    /// Releases all triggers of the exception catch, and configures the ones the current set of
+    /// caught traps needs.
+    fn configure_exception_catch(&mut self) -> Result<(), Error> {
+        let catch = &mut self.state.exception_catch;
+        let units = [
+            catch.etrigger.take(),
+            catch.itrigger.take(),
+            catch.trap_vector.take().map(|(unit, _)| unit),
+        ];
+        for unit in units.into_iter().flatten() {
+            self.interface.write_csr(TSELECT, unit as u64)?;
+            self.interface.write_csr(TDATA1, 0)?;
+            self.interface.write_csr(TDATA2, 0)?;
+        }
+
+        let exceptions = self.state.exception_catch.exceptions;
+        if exceptions != 0 {
+            self.state.exception_catch.etrigger =
+                self.configure_trap_trigger(ETRIGGER, exceptions, false)?;
+
+            if self.state.exception_catch.etrigger.is_none() {
+                let base = self.interface.read_csr(MTVEC)? & !0b11;
+                let unit = self
+                    .find_free_trigger(&[MCONTROL, MCONTROL6])?
+                    .ok_or_else(|| {
+                        Error::Other("No trigger is available to catch exceptions".to_string())
+                    })?;
+                self.configure_breakpoint_trigger(unit, base)?;
+                self.state.exception_catch.trap_vector = Some((unit, base));
+
+                tracing::info!(
+                    "No exception trigger available, catching exceptions with a breakpoint on the trap vector at {base:#x}"
+                );
+            }
+        }
+
+        if self.state.exception_catch.nmi {
+            self.state.exception_catch.itrigger = self.configure_trap_trigger(ITRIGGER, 0, true)?;
+
+            if self.state.exception_catch.itrigger.is_none() {
+                tracing::debug!("No interrupt trigger available, NMIs are not caught");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Configures a free `etrigger` or `itrigger` to halt on the traps in `causes`, and on
+    /// non-maskable interrupts if `nmi` is set.
+    fn configure_trap_trigger(
+        &mut self,
+        trigger_type: u32,
+        causes: u64,
+        nmi: bool,
+    ) -> Result<Option<usize>, Error> {
+        let Some(unit) = self.find_free_trigger(&[trigger_type])? else {
+            return Ok(None);
+        };
+
+        let mut trigger = TrapTrigger(0);
+        // Enter debug mode on trigger fire
+        trigger.set_action(1);
+        trigger.set_m(true);
+        trigger.set_s(true);
+        trigger.set_u(true);
+        trigger.set_nmi(nmi);
+
+        self.interface.write_csr(TSELECT, unit as u64)?;
+        self.interface.write_csr(TDATA1, 0)?;
+        self.interface.write_csr(TDATA2, causes)?;
+        self.interface
+            .write_csr(TDATA1, X::build_new_exec_tdata1(trigger_type, trigger.0))?;
+
+        // Without `tinfo`, the type is only known to be supported once it sticks.
+        let (readback, _) = X::unpack_tdata1(self.interface.read_csr(TDATA1)?);
+        if readback != trigger_type {
+            self.interface.write_csr(TDATA1, 0)?;
+            return Ok(None);
+        }
+
+        Ok(Some(unit))
+    }
+
+    /// Finds a trigger that is neither used by a breakpoint, a watchpoint nor the exception catch
+    /// and supports one of `types`.
+    ///
+    /// The search starts from the last trigger, which keeps the low triggers free for
+    /// breakpoints.
+    fn find_free_trigger(&mut self, types: &[u32]) -> Result<Option<usize>, Error> {
+        let breakpoints = self.hw_breakpoints()?;
+        let watchpoints = self.hw_watchpoints()?;
+
+        for unit in (0..breakpoints.len()).rev() {
+            if breakpoints[unit].is_some()
+                || watchpoints.get(unit).is_some_and(Option::is_some)
+                || self.state.exception_catch.reserves(unit)
+            {
+                continue;
+            }
+
+            self.interface.write_csr(TSELECT, unit as u64)?;
+            let supported = match self.interface.read_csr(TINFO) {
+                Ok(tinfo) => types.iter().any(|ty| (tinfo & 0xffff) >> ty & 1 == 1),
+                // Without `tinfo`, only the current type of the trigger is known.
+                Err(RiscvError::AbstractCommand(AbstractCommandErrorKind::Exception)) => {
+                    let (current, _) = X::unpack_tdata1(self.interface.read_csr(TDATA1)?);
+                    types.contains(&current)
+                }
+                Err(other) => return Err(other.into()),
+            };
+
+            if supported {
+                return Ok(Some(unit));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Prepares the exception catch for resuming the hart.
+    ///
+    /// This clears the hit bits of the trap triggers, and moves the trap vector breakpoint to the
+    /// current value of `mtvec`, which the firmware usually sets up after the exception catch
+    /// was configured.
+    pub(super) fn prepare_exception_catch_resume(&mut self) -> Result<(), Error> {
+        let hit_bit = if self.is_64_bit() { 58 } else { 26 };
+        let catch = &self.state.exception_catch;
+        for unit in [catch.itrigger, catch.etrigger].into_iter().flatten() {
+            self.interface.write_csr(TSELECT, unit as u64)?;
+            let tdata1 = self.interface.read_csr(TDATA1)?;
+            if tdata1 >> hit_bit & 1 == 1 {
+                self.interface.write_csr(TDATA1, tdata1 & !(1 << hit_bit))?;
+            }
+        }
+
+        let Some((unit, base)) = self.state.exception_catch.trap_vector else {
+            return Ok(());
+        };
+
+        let current = self.interface.read_csr(MTVEC)? & !0b11;
+        if current != base {
+            tracing::debug!("Trap vector moved to {current:#x}, moving the exception catch");
+            self.configure_breakpoint_trigger(unit, current)?;
+            self.state.exception_catch.trap_vector = Some((unit, current));
+        }
+
+        Ok(())
+    }
+
+    /// Determines whether a halt on a trigger was caused by the exception catch, and reports
+    /// `mcause` of caught traps.
+    fn trap_halt(&mut self) -> Result<TrapHalt, Error> {
+        let catch = &self.state.exception_catch;
+        if !catch.is_active() || catch.resuming {
+            return Ok(TrapHalt::None);
+        }
+        let trap_triggers = [catch.itrigger, catch.etrigger];
+
+        let xlen = if self.is_64_bit() { 64 } else { 32 };
+        let hit_bit = xlen - 6;
+
+        // The hit bit of the trap triggers is optional, harts without it are recognized by
+        // halting on the trap vector below. The bits are cleared before the hart resumes.
+        let mut hit = false;
+        for unit in trap_triggers.into_iter().flatten() {
+            self.interface.write_csr(TSELECT, unit as u64)?;
+            hit |= self.interface.read_csr(TDATA1)? >> hit_bit & 1 == 1;
+        }
+
+        let halt = if hit {
+            TrapHalt::Caught
+        } else {
+            let base = self.interface.read_csr(MTVEC)? & !0b11;
+            if self.interface.read_csr(DPC)? != base {
+                TrapHalt::None
+            } else if is_caught_exception(
+                self.interface.read_csr(MCAUSE.0)?,
+                xlen,
+                self.state.exception_catch.exceptions,
+            ) {
+                TrapHalt::Caught
+            } else if self.state.exception_catch.trap_vector.is_some() {
+                TrapHalt::Uncaught
+            } else {
+                TrapHalt::None
+            }
+        };
+
+        if halt == TrapHalt::Caught {
+            let mcause = self.interface.read_csr(MCAUSE.0)?;
+            let mtval = self.interface.read_csr(MTVAL.0)?;
+            tracing::debug!("Halted on a trap: mcause = {mcause:#x}, mtval = {mtval:#x}");
+        }
+
+        Ok(halt)
+    }
+
+    /// Resumes a hart that halted on the trap vector breakpoint for a trap that is not caught.
+    pub(super) fn resume_uncaught_trap(&mut self) -> Result<(), Error> {
+        self.state.exception_catch.resuming = true;
+        let result = self.run();
+        self.state.exception_catch.resuming = false;
+
+        result
+    }
+
+    /// Returns the halt reason of a halt on a trigger.
+    ///
+    /// Returns `None` for traps that are not caught, which have to be resumed.
+    pub(super) fn trigger_halt_reason(&mut self) -> Result<Option<HaltReason>, Error> {
+        Ok(match self.trap_halt()? {
+            TrapHalt::None => Some(HaltReason::Breakpoint(BreakpointCause::Hardware)),
+            TrapHalt::Caught => Some(HaltReason::Exception),
+            TrapHalt::Uncaught => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn caught_exceptions() {
+        // Illegal instruction
+        assert!(is_caught_exception(2, 32, FAULT_EXCEPTIONS));
+        // Environment call from M-mode
+        assert!(!is_caught_exception(11, 32, FAULT_EXCEPTIONS));
+        // Load access fault
+        assert!(is_caught_exception(5, 64, FAULT_EXCEPTIONS));
+        // Machine timer interrupt
+        assert!(!is_caught_exception(0x8000_0007, 32, u64::MAX));
+        assert!(!is_caught_exception(0x8000_0000_0000_0007, 64, u64::MAX));
+    }
+}
@@ -1,8 +1,9 @@
 //! All the interface bits for RISC-V.
 
 use crate::{
-    CoreInterface, CoreRegister, CoreStatus, CoreType, Error, HaltReason, InstructionSet,
-    MemoryInterface, MemoryMappedRegister,
+    BreakpointError, CoreInterface, CoreRegister, CoreStatus, CoreType, Error, HaltReason,
+    InstructionSet, MemoryInterface, MemoryMappedRegister, RunControlEvent, VectorCatchCondition,
+    Watchpoint, WatchpointKind,
     architecture::riscv::sequences::RiscvDebugSequence,
     core::{
         Architecture, BreakpointCause, CoreInformation, CoreRegisters, RegisterId, RegisterValue,
@@ -15,6 +16,7 @@ use crate::{
 };
 use bitfield::bitfield;
 use communication_interface::{AbstractCommandErrorKind, RiscvCommunicationInterface, RiscvError};
+use exception_catch::ExceptionCatch;
 use registers::{FP, RA, RISCV_CORE_REGISTERS, RISCV_WITH_FP_CORE_REGISTERS, SP};
 use registers64::{FP64, PC64, RA64, RISCV64_CORE_REGISTERS, RISCV64_WITH_FP_CORE_REGISTERS, SP64};
 use std::{
@@ -30,6 +32,7 @@ pub use registers::PC;
 pub(crate) mod assembly;
 pub mod communication_interface;
 pub mod dtm;
+mod exception_catch;
 pub mod sequences;
 
 pub use dtm::jtag_dtm::JtagDtmBuilder;
@@ -457,6 +460,53 @@ impl<'state, X: XlenMode> RiscvCore<'state, X> {
         Ok(tselect_index as u32)
     }
 
+    /// Configures trigger `bp_unit_index` as an execution breakpoint at `addr`.
+    fn configure_breakpoint_trigger(
+        &mut self,
+        bp_unit_index: usize,
+        addr: u64,
+    ) -> Result<(), Error> {
+        let addr = X::validate_bp_address(addr)?;
+
+        const TSELECT: u16 = 0x7a0;
+        const TDATA1: u16 = 0x7a1;
+        const TDATA2: u16 = 0x7a2;
+
+        tracing::info!("Setting breakpoint {} at {:#x}", bp_unit_index, addr);
+
+        // select requested trigger
+        self.interface.write_csr(TSELECT, bp_unit_index as u64)?;
+
+        // Verify the trigger is a supported type for execution breakpoints:
+        // type 2 = mcontrol (spec 0.13), type 6 = mcontrol6 (spec 1.0).
+        let (trigger_type, _) = X::unpack_tdata1(self.interface.read_csr(TDATA1)?);
+        if trigger_type != 2 && trigger_type != 6 {
+            return Err(RiscvError::UnexpectedTriggerType(trigger_type).into());
+        }
+
+        // Build the control word. Bits 0–15 are position-compatible between
+        // mcontrol and mcontrol6, so a single Mcontrol value covers both.
+        let mut instruction_breakpoint = Mcontrol(0);
+        // Enter debug mode on trigger fire
+        instruction_breakpoint.set_action(1);
+        // Exact address match
+        instruction_breakpoint.set_match(0);
+        instruction_breakpoint.set_m(true);
+        instruction_breakpoint.set_u(true);
+        // Trigger on instruction fetch
+        instruction_breakpoint.set_execute(true);
+        // Match on address, not data value
+        instruction_breakpoint.set_select(false);
+
+        let tdata1_val = X::build_new_exec_tdata1(trigger_type, instruction_breakpoint.0);
+
+        self.interface.write_csr(TDATA1, 0)?;
+        self.interface.write_csr(TDATA2, addr)?;
+        self.interface.write_csr(TDATA1, tdata1_val)?;
+
+        Ok(())
+    }
+
     fn on_halted(&mut self) -> Result<(), Error> {
         let status = self.status()?;
         tracing::debug!("Core halted: {:#?}", status);
@@ -506,7 +556,13 @@ impl<X: XlenMode> CoreInterface for RiscvCore<'_, X> {
                     //       operations are skipped
                 }
                 // Trigger module caused halt
-                2 => HaltReason::Breakpoint(BreakpointCause::Hardware),
+                2 => match self.trigger_halt_reason()? {
+                    Some(reason) => reason,
+                    None => {
+                        self.resume_uncaught_trap()?;
+                        return Ok(CoreStatus::Running);
+                    }
+                },
                 // Debugger requested a halt
                 3 => HaltReason::Request,
                 // Core halted after single step
@@ -542,6 +598,7 @@ impl<X: XlenMode> CoreInterface for RiscvCore<'_, X> {
         // Before we run, we always perform a single instruction step, to
         // account for possible breakpoints that might get us stuck on the
         // current instruction.
+        self.prepare_exception_catch_resume()?;
         if !self.state.pc_written {
             self.step()?;
         }
@@ -577,29 +634,36 @@ impl<X: XlenMode> CoreInterface for RiscvCore<'_, X> {
             // If we are halted on a software breakpoint, we can skip the
             // single step and manually advance the dpc.
             let mut debug_pc = self.read_core_reg(RegisterId(0x7b1))?;
-            // Advance the dpc by the size of the EBREAK (ebreak or c.ebreak) instruction.
-            if self.instruction_set()? == X::compressed_instruction_set() {
-                // We may have been halted by either an EBREAK or a C.EBREAK instruction.
-                // We need to read back the instruction to determine how many bytes to skip.
-                let instruction = self.read_word_32(debug_pc.try_into().unwrap())?;
-                if instruction & 0x3 != 0x3 {
-                    // Compressed instruction.
-                    debug_pc.increment_address(2)?;
-                } else {
-                    debug_pc.increment_address(4)?;
-                }
+            // We may have been halted by either an EBREAK or a C.EBREAK instruction.
+            // We need to read back the instruction to determine how many bytes to skip.
+            // If neither is there anymore, a software breakpoint was replaced by the
+            // original instruction, which has to be stepped normally.
+            let instruction = self.read_word_32(debug_pc.try_into()?)?;
+            let ebreak_size = if instruction == 0x00100073 {
+                Some(4)
+            } else if self.instruction_set()? == X::compressed_instruction_set()
+                && instruction & 0xFFFF == 0x9002
+            {
+                Some(2)
             } else {
-                debug_pc.increment_address(4)?;
+                None
+            };
+            if let Some(ebreak_size) = ebreak_size {
+                // Advance the dpc by the size of the EBREAK (ebreak or c.ebreak) instruction.
+                debug_pc.increment_address(ebreak_size)?;
+                self.write_core_reg(RegisterId(0x7b1), debug_pc)?;
+                return Ok(CoreInformation {
+                    pc: debug_pc.try_into()?,
+                });
             }
-            self.write_core_reg(RegisterId(0x7b1), debug_pc)?;
-            return Ok(CoreInformation {
-                pc: debug_pc.try_into()?,
-            });
         } else if matches!(
             halt_reason,
-            CoreStatus::Halted(HaltReason::Breakpoint(BreakpointCause::Hardware))
+            CoreStatus::Halted(
+                HaltReason::Breakpoint(BreakpointCause::Hardware) | HaltReason::Exception
+            )
         ) {
-            // If we are halted on a hardware breakpoint.
+            // If we are halted on a hardware breakpoint, or on the trap vector breakpoint of the
+            // exception catch.
             self.enable_breakpoints(false)?;
         }
 
@@ -635,7 +699,9 @@ impl<X: XlenMode> CoreInterface for RiscvCore<'_, X> {
         // Re-enable breakpoints before we continue.
         if matches!(
             halt_reason,
-            CoreStatus::Halted(HaltReason::Breakpoint(BreakpointCause::Hardware))
+            CoreStatus::Halted(
+                HaltReason::Breakpoint(BreakpointCause::Hardware) | HaltReason::Exception
+            )
         ) {
             // If we are halted on a hardware breakpoint.
             self.enable_breakpoints(true)?;
@@ -677,8 +743,6 @@ impl<X: XlenMode> CoreInterface for RiscvCore<'_, X> {
     }
 
     /// See docs on the [`CoreInterface::hw_breakpoints`] trait.
-    ///
-    /// NOTE: For riscv, this assumes that only execution breakpoints are used.
     fn hw_breakpoints(&mut self) -> Result<Vec<Option<u64>>, Error> {
         // This can be called w/o halting the core via Session::new -
         // temporarily halt if not halted.
@@ -694,6 +758,12 @@ impl<X: XlenMode> CoreInterface for RiscvCore<'_, X> {
         let mut breakpoints = vec![];
         let num_hw_breakpoints = self.available_breakpoint_units()? as usize;
         for bp_unit_index in 0..num_hw_breakpoints {
+            // The triggers of the exception catch are not breakpoints of the user.
+            if self.state.exception_catch.reserves(bp_unit_index) {
+                breakpoints.push(None);
+                continue;
+            }
+
             // Select the trigger.
             self.interface.write_csr(TSELECT, bp_unit_index as u64)?;
             // Read the trigger "configuration" data.
@@ -710,16 +780,15 @@ impl<X: XlenMode> CoreInterface for RiscvCore<'_, X> {
 
             // The trigger must be active in at least a single mode
             let trigger_any_mode_active = tdata_value.m() || tdata_value.s() || tdata_value.u();
-            let trigger_any_action_enabled =
-                tdata_value.execute() || tdata_value.store() || tdata_value.load();
 
             // Only return if the trigger is for an execution debug action in all modes.
+            // Load/store triggers are watchpoints, see `hw_watchpoints`.
             // Accept both type 2 (mcontrol) and type 6 (mcontrol6, spec 1.0).
             if (trigger_type == 2 || trigger_type == 6)
                 && tdata_value.action() == 1
                 && tdata_value.match_() == 0
                 && trigger_any_mode_active
-                && trigger_any_action_enabled
+                && tdata_value.execute()
             {
                 let breakpoint = self.interface.read_csr(TDATA2)?;
                 breakpoints.push(Some(breakpoint));
@@ -775,70 +844,208 @@ impl<X: XlenMode> CoreInterface for RiscvCore<'_, X> {
     }
 
     fn set_hw_breakpoint(&mut self, bp_unit_index: usize, addr: u64) -> Result<(), Error> {
-        let addr = X::validate_bp_address(addr)?;
+        if self.state.exception_catch.reserves(bp_unit_index) {
+            return Err(Error::Other(format!(
+                "Trigger {bp_unit_index} is used to catch exceptions"
+            )));
+        }
+
+        self.configure_breakpoint_trigger(bp_unit_index, addr)
+    }
+
+    fn clear_hw_breakpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        // This can be called w/o halting the core via Session::new -
+        // temporarily halt if not halted.
+        tracing::info!("Clearing breakpoint {}", unit_index);
+
+        let was_running = !self.core_halted()?;
+        if was_running {
+            self.halt(Duration::from_millis(100))?;
+        }
 
         const TSELECT: u16 = 0x7a0;
         const TDATA1: u16 = 0x7a1;
         const TDATA2: u16 = 0x7a2;
 
-        tracing::info!("Setting breakpoint {} at {:#x}", bp_unit_index, addr);
+        self.interface.write_csr(TSELECT, unit_index as u64)?;
+        self.interface.write_csr(TDATA1, 0)?;
+        self.interface.write_csr(TDATA2, 0)?;
 
-        // select requested trigger
-        self.interface.write_csr(TSELECT, bp_unit_index as u64)?;
+        if was_running {
+            self.resume_core()?;
+        }
 
-        // Verify the trigger is a supported type for execution breakpoints:
-        // type 2 = mcontrol (spec 0.13), type 6 = mcontrol6 (spec 1.0).
-        let (trigger_type, _) = X::unpack_tdata1(self.interface.read_csr(TDATA1)?);
+        Ok(())
+    }
+
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        // Watchpoints use the same triggers as breakpoints.
+        self.available_breakpoint_units()
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        let was_running = !self.core_halted()?;
+        if was_running {
+            self.halt(Duration::from_millis(100))?;
+        }
+
+        const TSELECT: u16 = 0x7a0;
+        const TDATA1: u16 = 0x7a1;
+        const TDATA2: u16 = 0x7a2;
+
+        let mut watchpoints = vec![];
+        for unit_index in 0..self.available_watchpoint_units()? as usize {
+            self.interface.write_csr(TSELECT, unit_index as u64)?;
+            let (trigger_type, ctrl_low32) = X::unpack_tdata1(self.interface.read_csr(TDATA1)?);
+            let tdata_value = Mcontrol(ctrl_low32);
+
+            let watchpoint = if (trigger_type == 2 || trigger_type == 6)
+                && tdata_value.action() == 1
+                && (tdata_value.m() || tdata_value.s() || tdata_value.u())
+                && !tdata_value.execute()
+            {
+                let tdata2 = self.interface.read_csr(TDATA2)?;
+                decode_watchpoint(tdata_value, tdata2)
+            } else {
+                None
+            };
+            watchpoints.push(watchpoint);
+        }
+
+        if was_running {
+            self.resume_core()?;
+        }
+
+        Ok(watchpoints)
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        let tdata2 = X::validate_bp_address(watchpoint.address)?;
+
+        const TSELECT: u16 = 0x7a0;
+        const TDATA1: u16 = 0x7a1;
+        const TDATA2: u16 = 0x7a2;
+
+        tracing::info!("Setting watchpoint {} at {:?}", unit_index, watchpoint);
+
+        if self.state.exception_catch.reserves(unit_index) {
+            return Err(Error::Other(format!(
+                "Trigger {unit_index} is used to catch exceptions"
+            )));
+        }
+
+        self.interface.write_csr(TSELECT, unit_index as u64)?;
+
+        let (trigger_type, ctrl_low32) = X::unpack_tdata1(self.interface.read_csr(TDATA1)?);
         if trigger_type != 2 && trigger_type != 6 {
             return Err(RiscvError::UnexpectedTriggerType(trigger_type).into());
         }
 
-        // Build the control word. Bits 0–15 are position-compatible between
-        // mcontrol and mcontrol6, so a single Mcontrol value covers both.
-        let mut instruction_breakpoint = Mcontrol(0);
+        // mcontrol reports the largest supported NAPOT range, mcontrol6 has no such field.
+        if trigger_type == 2 && watchpoint.mask_bits() > Mcontrol(ctrl_low32).maskmax() {
+            return Err(Error::BreakpointOperation(
+                BreakpointError::UnsupportedWatchpointSize(watchpoint.size),
+            ));
+        }
+
+        let mut data_watchpoint = Mcontrol(0);
         // Enter debug mode on trigger fire
-        instruction_breakpoint.set_action(1);
-        // Exact address match
-        instruction_breakpoint.set_match(0);
-        instruction_breakpoint.set_m(true);
-        instruction_breakpoint.set_u(true);
-        // Trigger on instruction fetch
-        instruction_breakpoint.set_execute(true);
+        data_watchpoint.set_action(1);
+        data_watchpoint.set_m(true);
+        data_watchpoint.set_u(true);
+        data_watchpoint.set_load(watchpoint.kind.on_read());
+        data_watchpoint.set_store(watchpoint.kind.on_write());
         // Match on address, not data value
-        instruction_breakpoint.set_select(false);
+        data_watchpoint.set_select(false);
 
-        let tdata1_val = X::build_new_exec_tdata1(trigger_type, instruction_breakpoint.0);
+        // Larger regions are encoded as a naturally aligned power-of-two range, where the
+        // number of trailing ones in tdata2 selects the size.
+        let tdata2 = if watchpoint.size == 1 {
+            data_watchpoint.set_match(0);
+            tdata2
+        } else {
+            data_watchpoint.set_match(1);
+            tdata2 | ((watchpoint.size >> 1) - 1)
+        };
+
+        let tdata1_val = X::build_new_exec_tdata1(trigger_type, data_watchpoint.0);
 
         self.interface.write_csr(TDATA1, 0)?;
-        self.interface.write_csr(TDATA2, addr)?;
+        self.interface.write_csr(TDATA2, tdata2)?;
         self.interface.write_csr(TDATA1, tdata1_val)?;
 
+        // Unsupported match modes are not written, so verify the trigger took the configuration.
+        let (_, readback) = X::unpack_tdata1(self.interface.read_csr(TDATA1)?);
+        if Mcontrol(readback).match_() != data_watchpoint.match_() {
+            self.interface.write_csr(TDATA1, 0)?;
+            return Err(Error::BreakpointOperation(
+                BreakpointError::UnsupportedWatchpointSize(watchpoint.size),
+            ));
+        }
+
         Ok(())
     }
 
-    fn clear_hw_breakpoint(&mut self, unit_index: usize) -> Result<(), Error> {
-        // This can be called w/o halting the core via Session::new -
-        // temporarily halt if not halted.
-        tracing::info!("Clearing breakpoint {}", unit_index);
-
-        let was_running = !self.core_halted()?;
-        if was_running {
-            self.halt(Duration::from_millis(100))?;
-        }
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        self.clear_hw_breakpoint(unit_index)
+    }
 
+    fn triggered_watchpoint(&mut self) -> Result<Option<Watchpoint>, Error> {
         const TSELECT: u16 = 0x7a0;
         const TDATA1: u16 = 0x7a1;
         const TDATA2: u16 = 0x7a2;
 
-        self.interface.write_csr(TSELECT, unit_index as u64)?;
-        self.interface.write_csr(TDATA1, 0)?;
-        self.interface.write_csr(TDATA2, 0)?;
+        // The hit bits are optional, so this only finds the watchpoint on harts that implement them.
+        for unit_index in 0..self.available_watchpoint_units()? as usize {
+            self.interface.write_csr(TSELECT, unit_index as u64)?;
+            let tdata_raw = self.interface.read_csr(TDATA1)?;
+            let (trigger_type, ctrl_low32) = X::unpack_tdata1(tdata_raw);
 
-        if was_running {
-            self.resume_core()?;
+            let hit = match trigger_type {
+                2 => Mcontrol(ctrl_low32).hit(),
+                6 => Mcontrol6(ctrl_low32).hit0() || Mcontrol6(ctrl_low32).hit1(),
+                _ => false,
+            };
+            let tdata_value = Mcontrol(ctrl_low32);
+            if !hit || tdata_value.execute() || tdata_value.action() != 1 {
+                continue;
+            }
+
+            let tdata2 = self.interface.read_csr(TDATA2)?;
+
+            // Clear the hit bits so the next halt is reported correctly.
+            let cleared = match trigger_type {
+                2 => {
+                    let mut ctrl = Mcontrol(ctrl_low32);
+                    ctrl.set_hit(false);
+                    ctrl.0
+                }
+                _ => {
+                    let mut ctrl = Mcontrol6(ctrl_low32);
+                    ctrl.set_hit0(false);
+                    ctrl.set_hit1(false);
+                    ctrl.0
+                }
+            };
+            self.interface
+                .write_csr(TDATA1, X::repack_tdata1(tdata_raw, cleared))?;
+
+            return Ok(decode_watchpoint(tdata_value, tdata2));
         }
 
-        Ok(())
+        Ok(None)
+    }
+
+    fn watchpoints_share_breakpoint_units(&self) -> bool {
+        true
+    }
+
+    fn reserved_breakpoint_units(&mut self) -> Result<Vec<usize>, Error> {
+        Ok(self.state.exception_catch.reserved_units())
     }
 
     fn registers(&self) -> &'static CoreRegisters {
@@ -913,6 +1120,54 @@ impl<X: XlenMode> CoreInterface for RiscvCore<'_, X> {
         self.interface.disable_debug_module()?;
         Ok(())
     }
+
+    fn enable_vector_catch(&mut self, condition: VectorCatchCondition) -> Result<(), Error> {
+        self.set_exception_catch(condition, true)
+    }
+
+    fn disable_vector_catch(&mut self, condition: VectorCatchCondition) -> Result<(), Error> {
+        self.set_exception_catch(condition, false)
+    }
+
+    fn join_run_control_group(&mut self, group: u8) -> Result<(), Error> {
+        self.interface.join_hart_group(group)?;
+        Ok(())
+    }
+
+    fn leave_run_control_group(&mut self, group: u8) -> Result<(), Error> {
+        self.interface.leave_hart_group(group)?;
+        Ok(())
+    }
+
+    fn trigger_run_control_group(
+        &mut self,
+        group: u8,
+        event: RunControlEvent,
+    ) -> Result<(), Error> {
+        self.interface.trigger_hart_group(group, event)?;
+        Ok(())
+    }
+
+    fn prepare_run_control_group_restart(&mut self) -> Result<(), Error> {
+        if !self.core_halted()? {
+            return Ok(());
+        }
+
+        // Step off the current instruction like `run` does, a breakpoint on it would otherwise
+        // halt the whole group again right away.
+        self.prepare_exception_catch_resume()?;
+        if !self.state.pc_written {
+            self.step()?;
+        }
+        self.state.semihosting_command = None;
+
+        Ok(())
+    }
+
+    fn run_control_group_restarted(&mut self) -> Result<(), Error> {
+        // The hart array resume already waited for every hart to acknowledge it.
+        Ok(())
+    }
 }
 
 impl<X: XlenMode> CoreMemoryInterface for RiscvCore<'_, X> {
@@ -947,6 +1202,9 @@ pub struct RiscvCoreState {
 
     /// Whether the MISA CSR has been read.
     misa_read: bool,
+
+    /// The traps the core halts on, and the triggers used to catch them.
+    exception_catch: ExceptionCatch,
 }
 
 impl RiscvCoreState {
@@ -958,6 +1216,7 @@ impl RiscvCoreState {
             semihosting_command: None,
             fp_present: false,
             misa_read: false,
+            exception_catch: ExceptionCatch::default(),
         }
     }
 }
@@ -1175,6 +1434,49 @@ memory_mapped_bitfield_register! {
     dataaddr, _: 11, 0;
 }
 
+memory_mapped_bitfield_register! {
+    /// Hart Array Window Select (see 3.14.4)
+    pub struct Hawindowsel(u32);
+    0x14, "hawindowsel",
+    impl From;
+
+    /// Selects which 32-hart window of the hart array is visible in `hawindow`.
+    pub hawindowsel, set_hawindowsel: 14, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// Hart Array Window (see 3.14.5)
+    pub struct Hawindow(u32);
+    0x15, "hawindow",
+    impl From;
+
+    /// One bit per hart in the selected window. Harts with a set bit are selected when
+    /// `hasel` is set in `dmcontrol`.
+    pub maskdata, set_maskdata: 31, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// Debug Module Control and Status 2 (see 3.14.18, spec 1.0)
+    pub struct Dmcs2(u32);
+    0x32, "dmcs2",
+    impl From;
+
+    /// 0 to access halt groups, 1 to access resume groups.
+    pub grouptype, set_grouptype: 11;
+
+    /// Selects the external trigger when `hgselect` is 1.
+    pub dmexttrigger, set_dmexttrigger: 10, 7;
+
+    /// The group of the selected hart or external trigger. Group 0 means no group.
+    pub group, set_group: 6, 2;
+
+    /// Writing 1 moves the selected harts or external trigger into `group`.
+    pub _, set_hgwrite: 1;
+
+    /// 0 to operate on harts, 1 to operate on external triggers.
+    pub hgselect, set_hgselect: 0;
+}
+
 memory_mapped_bitfield_register! { pub struct Data0(u32); 0x04, "data0", impl From; }
 memory_mapped_bitfield_register! { pub struct Data1(u32); 0x05, "data1", impl From; }
 memory_mapped_bitfield_register! { pub struct Data2(u32); 0x06, "data2", impl From; }
@@ -1207,6 +1509,34 @@ memory_mapped_bitfield_register! { pub struct Progbuf13(u32); 0x2D, "progbuf13",
 memory_mapped_bitfield_register! { pub struct Progbuf14(u32); 0x2E, "progbuf14", impl From; }
 memory_mapped_bitfield_register! { pub struct Progbuf15(u32); 0x2F, "progbuf15", impl From; }
 
+/// Decodes the watched region of a load/store trigger.
+fn decode_watchpoint(ctrl: Mcontrol, tdata2: u64) -> Option<Watchpoint> {
+    if !ctrl.load() && !ctrl.store() {
+        return None;
+    }
+
+    let kind = match (ctrl.load(), ctrl.store()) {
+        (true, true) => WatchpointKind::Access,
+        (true, false) => WatchpointKind::Read,
+        (false, _) => WatchpointKind::Write,
+    };
+
+    let (address, size) = match ctrl.match_() {
+        0 => (tdata2, 1),
+        1 => {
+            let size = 2u64 << tdata2.trailing_ones();
+            (tdata2 & !(size - 1), size)
+        }
+        _ => return None,
+    };
+
+    Some(Watchpoint {
+        address,
+        size,
+        kind,
+    })
+}
+
 bitfield! {
     /// Trigger type 2 — legacy address/data match control (mcontrol).
     struct Mcontrol(u32);
@@ -35,6 +35,13 @@ pub const PC: CoreRegister = CoreRegister {
     unwind_rule: UnwindRule::Clear,
 };
 
+/// The `mcause` CSR, which holds the cause of the last trap. CSRs use their address as the
+/// register id.
+pub const MCAUSE: RegisterId = RegisterId(0x342);
+
+/// The `mtval` CSR, which holds the faulting address or instruction of the last trap.
+pub const MTVAL: RegisterId = RegisterId(0x343);
+
 // ── General-purpose registers (RV32) ─────────────────────────────────────────
 
 /// The zero register, x0.
@@ -9,7 +9,8 @@ use std::{
 use zerocopy::IntoBytes;
 
 use crate::{
-    BreakpointCause, Error as ProbeRsError, HaltReason, MemoryInterface,
+    BreakpointCause, Error as ProbeRsError, HaltReason, MemoryInterface, Watchpoint,
+    WatchpointKind,
     architecture::xtensa::{
         arch::{CpuRegister, Register, SpecialRegister, instruction::Instruction},
         register_cache::RegisterCache,
@@ -45,6 +46,9 @@ pub enum XtensaError {
 
     /// The result index of a batched command is not available.
     BatchedResultNotAvailable,
+
+    /// The core has no trace memory enabled.
+    NoTraceMemory,
 }
 
 impl From<XtensaError> for ProbeRsError {
@@ -128,6 +132,9 @@ pub struct XtensaCoreProperties {
     /// The number of hardware breakpoints the target supports. CPU-specific configuration value.
     pub hw_breakpoint_num: u32,
 
+    /// The number of hardware data breakpoints the target supports. CPU-specific configuration value.
+    pub hw_watchpoint_num: u32,
+
     /// The interrupt level at which debug exceptions are generated. CPU-specific configuration value.
     pub debug_level: DebugLevel,
 
@@ -142,6 +149,7 @@ impl Default for XtensaCoreProperties {
     fn default() -> Self {
         Self {
             hw_breakpoint_num: 2,
+            hw_watchpoint_num: 2,
             debug_level: DebugLevel::L6,
             memory_ranges: HashMap::new(),
             window_option_properties: WindowProperties::lx(64),
@@ -307,6 +315,13 @@ impl<'probe> XtensaCommunicationInterface<'probe> {
         self.core_properties.hw_breakpoint_num
     }
 
+    /// Returns the number of hardware data breakpoints the target supports.
+    ///
+    /// On the Xtensa architecture this is the `DBREAKA`/`DBREAKC` register pair count.
+    pub fn available_watchpoint_units(&self) -> u32 {
+        self.core_properties.hw_watchpoint_num
+    }
+
     /// Returns whether the core is halted.
     pub fn core_halted(&mut self) -> Result<bool, XtensaError> {
         if !self.state.is_halted {
@@ -1118,6 +1133,56 @@ macro_rules! u32_register {
     };
 }
 
+bitfield::bitfield! {
+    /// The `DBREAKCn` registers, controlling data breakpoint `n`.
+    #[derive(Copy, Clone)]
+    pub struct DBreakC(u32);
+    impl Debug;
+
+    /// Break on stores
+    pub sb,   set_sb  : 31;
+
+    /// Break on loads
+    pub lb,   set_lb  : 30;
+
+    /// Ones in the upper bits of the mask, zeros for the address bits that are ignored.
+    pub mask, set_mask: 5, 0;
+}
+
+impl DBreakC {
+    /// Returns the configuration that watches `watchpoint`, if the size is supported.
+    pub fn watchpoint_configuration(watchpoint: &Watchpoint) -> Option<Self> {
+        // The mask can ignore at most 6 address bits.
+        if watchpoint.mask_bits() > 6 {
+            return None;
+        }
+
+        let mut dbreakc = DBreakC(0);
+        dbreakc.set_mask((0x3F << watchpoint.mask_bits()) & 0x3F);
+        dbreakc.set_lb(watchpoint.kind.on_read());
+        dbreakc.set_sb(watchpoint.kind.on_write());
+        Some(dbreakc)
+    }
+
+    /// Returns the size and kind of the watched region, if this data breakpoint is enabled.
+    pub fn watchpoint(&self, address: u32) -> Option<Watchpoint> {
+        let kind = match (self.lb(), self.sb()) {
+            (true, true) => WatchpointKind::Access,
+            (true, false) => WatchpointKind::Read,
+            (false, true) => WatchpointKind::Write,
+            (false, false) => return None,
+        };
+
+        let size = 1 << (!self.mask() & 0x3F).trailing_ones();
+
+        Some(Watchpoint {
+            address: address as u64,
+            size,
+            kind,
+        })
+    }
+}
+
 bitfield::bitfield! {
     /// The `DEBUGCAUSE` register.
     #[derive(Copy, Clone)]
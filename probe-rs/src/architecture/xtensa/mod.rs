@@ -5,14 +5,16 @@ use std::{sync::Arc, time::Duration};
 use probe_rs_target::{Architecture, CoreType, InstructionSet};
 
 use crate::{
-    CoreInformation, CoreInterface, CoreRegister, CoreStatus, Error, HaltReason, MemoryInterface,
+    BreakpointError, CoreInformation, CoreInterface, CoreRegister, CoreStatus, Error, HaltReason,
+    MemoryInterface, Watchpoint,
     architecture::xtensa::{
         arch::{
             CpuRegister, Register, SpecialRegister,
             instruction::{Instruction, InstructionEncoding},
         },
         communication_interface::{
-            DebugCause, IBreakEn, ProgramStatus, WindowProperties, XtensaCommunicationInterface,
+            DBreakC, DebugCause, IBreakEn, ProgramStatus, WindowProperties,
+            XtensaCommunicationInterface,
         },
         registers::{FP, PC, RA, SP, XTENSA_CORE_REGISTERS},
         sequences::XtensaDebugSequence,
@@ -33,6 +35,7 @@ pub mod communication_interface;
 pub(crate) mod register_cache;
 pub mod registers;
 pub mod sequences;
+pub mod trax;
 
 /// Xtensa core state.
 #[derive(Debug)]
@@ -87,6 +90,10 @@ pub struct Xtensa<'probe> {
 impl<'probe> Xtensa<'probe> {
     const IBREAKA_REGS: [SpecialRegister; 2] =
         [SpecialRegister::IBreakA0, SpecialRegister::IBreakA1];
+    const DBREAKA_REGS: [SpecialRegister; 2] =
+        [SpecialRegister::DBreakA0, SpecialRegister::DBreakA1];
+    const DBREAKC_REGS: [SpecialRegister; 2] =
+        [SpecialRegister::DBreakC0, SpecialRegister::DBreakC1];
 
     /// Create a new Xtensa interface for a particular core.
     pub fn new(
@@ -172,6 +179,14 @@ impl<'probe> Xtensa<'probe> {
                 0
             };
 
+            // If a software breakpoint was replaced by the original instruction since the
+            // core halted, the instruction must be executed instead of skipped.
+            let pc_increment = if pc_increment > 0 && !self.is_break_instruction_at_pc()? {
+                0
+            } else {
+                pc_increment
+            };
+
             if pc_increment > 0 {
                 // Step through the breakpoint
                 let mut pc_value = self.interface.read_register_untyped(Register::CurrentPc)?;
@@ -196,6 +211,23 @@ impl<'probe> Xtensa<'probe> {
         Ok(())
     }
 
+    /// Check if the instruction at the PC is a `BREAK` or `BREAK.N` instruction.
+    fn is_break_instruction_at_pc(&mut self) -> Result<bool, Error> {
+        // BREAK s, t: 0000 0000 0100 ssss tttt 0000
+        const BREAK_MASK: u32 = 0xFF_F00F;
+        const BREAK: u32 = 0x00_4000;
+        // BREAK.N s: 1111 ssss 0010 1101
+        const BREAK_N_MASK: u32 = 0xF0FF;
+        const BREAK_N: u32 = 0xF02D;
+
+        let pc = self.interface.read_register_untyped(Register::CurrentPc)?;
+        let mut instruction = [0u8; 3];
+        self.read_8(pc as u64, &mut instruction)?;
+        let instruction = u32::from_le_bytes([instruction[0], instruction[1], instruction[2], 0]);
+
+        Ok(instruction & BREAK_MASK == BREAK || instruction & BREAK_N_MASK == BREAK_N)
+    }
+
     /// Check if the current breakpoint is a semihosting call
     // OpenOCD implementation: https://github.com/espressif/openocd-esp32/blob/93dd01511fd13d4a9fb322cd9b600c337becef9e/src/target/espressif/esp_xtensa_semihosting.c#L42-L103
     fn check_for_semihosting(&mut self) -> Result<Option<SemihostingCommand>, Error> {
@@ -292,6 +324,22 @@ impl<'probe> Xtensa<'probe> {
         Ok(self.interface.read_register::<DebugCause>()?)
     }
 
+    fn read_watchpoint(&mut self, unit_index: usize) -> Result<Option<Watchpoint>, Error> {
+        let dbreakc = DBreakC(
+            self.interface
+                .read_register_untyped(Self::DBREAKC_REGS[unit_index])?,
+        );
+        if !dbreakc.lb() && !dbreakc.sb() {
+            return Ok(None);
+        }
+
+        let address = self
+            .interface
+            .read_register_untyped(Self::DBREAKA_REGS[unit_index])?;
+
+        Ok(dbreakc.watchpoint(address))
+    }
+
     fn spill_registers(&mut self) -> Result<(), Error> {
         if self.current_ps()?.excm() {
             // We are in an exception, possibly WindowOverflowN or WindowUnderflowN.
@@ -518,6 +566,68 @@ impl CoreInterface for Xtensa<'_> {
         })
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        // Only the registers of the first units are known, even if the core is configured with
+        // more of them.
+        Ok(self
+            .interface
+            .available_watchpoint_units()
+            .min(Self::DBREAKA_REGS.len() as u32))
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        self.halted_access(|this| {
+            let mut watchpoints = Vec::with_capacity(this.available_watchpoint_units()? as usize);
+
+            for i in 0..this.available_watchpoint_units()? as usize {
+                watchpoints.push(this.read_watchpoint(i)?);
+            }
+
+            Ok(watchpoints)
+        })
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        let dbreakc = DBreakC::watchpoint_configuration(&watchpoint).ok_or(
+            Error::BreakpointOperation(BreakpointError::UnsupportedWatchpointSize(watchpoint.size)),
+        )?;
+
+        self.halted_access(|this| {
+            this.interface
+                .write_register_untyped(Self::DBREAKC_REGS[unit_index], 0)?;
+            this.interface.write_register_untyped(
+                Self::DBREAKA_REGS[unit_index],
+                watchpoint.address as u32,
+            )?;
+            this.interface
+                .write_register_untyped(Self::DBREAKC_REGS[unit_index], dbreakc.0)?;
+
+            Ok(())
+        })
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        self.halted_access(|this| {
+            this.interface
+                .write_register_untyped(Self::DBREAKC_REGS[unit_index], 0)?;
+
+            Ok(())
+        })
+    }
+
+    fn triggered_watchpoint(&mut self) -> Result<Option<Watchpoint>, Error> {
+        let debug_cause = self.debug_cause()?;
+        if !debug_cause.dbreak_exception() {
+            return Ok(None);
+        }
+
+        self.read_watchpoint(debug_cause.dbreak_num() as usize)
+    }
+
     fn registers(&self) -> &'static CoreRegisters {
         &XTENSA_CORE_REGISTERS
     }
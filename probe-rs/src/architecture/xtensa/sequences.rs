@@ -23,6 +23,17 @@ pub trait XtensaDebugSequence: Send + Sync + Debug {
         Ok(())
     }
 
+    /// Enables the trace memory that TRAX of the core with `core_id` records into.
+    ///
+    /// Executed before tracing is started with [`crate::Session::setup_trax_tracing`].
+    fn trax_memory_setup(
+        &self,
+        _interface: &mut XtensaCommunicationInterface,
+        _core_id: usize,
+    ) -> Result<(), crate::Error> {
+        Ok(())
+    }
+
     /// Executes a system-wide reset without debug domain (or warm-reset that preserves debug connection) via software mechanisms.
     fn reset_system_and_halt(
         &self,
@@ -0,0 +1,707 @@
+//! Program flow tracing with TRAX, the trace module of Xtensa cores.
+//!
+//! TRAX records indirect branches and exceptions into a trace memory of the chip, as messages
+//! in the Nexus 5001 format. The trace memory is used as a circular buffer, and read out through
+//! the Xtensa Debug Module once tracing stopped.
+
+use std::time::{Duration, Instant};
+
+use crate::architecture::xtensa::{
+    communication_interface::{XtensaCommunicationInterface, XtensaError},
+    xdm::NexusRegister,
+};
+
+const NARADR_TRAXCTRL: u8 = 0x01;
+const NARADR_TRAXSTAT: u8 = 0x02;
+const NARADR_TRAXDATA: u8 = 0x03;
+const NARADR_TRAXADDR: u8 = 0x04;
+const NARADR_TRIGGERPC: u8 = 0x05;
+const NARADR_PCMATCHCTRL: u8 = 0x06;
+const NARADR_DELAYCNT: u8 = 0x07;
+
+/// The number of words of trace memory that are read in a single batch.
+const READ_BATCH_WORDS: usize = 256;
+
+bitfield::bitfield! {
+    /// TRAX Control register
+    #[derive(Copy, Clone)]
+    pub struct TraxControl(u32);
+    impl Debug;
+
+    /// Set to start tracing
+    pub trace_enable,          set_trace_enable:          0;
+
+    /// Set to stop tracing, after the post-stop-trigger delay
+    pub trace_stop,            set_trace_stop:            1;
+
+    /// Enable the program counter match stop trigger
+    pub pc_match_enable,       set_pc_match_enable:       2;
+
+    /// Enable the processor trigger input as a stop trigger
+    pub pti_enable,            set_pti_enable:            4;
+
+    /// Enable the cross trigger input as a stop trigger
+    pub cti_enable,            set_cti_enable:            5;
+
+    /// Store the trace in the trace memory
+    pub trace_memory_enable,   set_trace_memory_enable:   7;
+
+    /// Count the post-stop-trigger delay in trace words instead of instructions
+    pub count_words,           set_count_words:           9;
+
+    /// Enable the synchronization of the trace with the processor in debug mode
+    pub sync_enable,           set_sync_enable:           11;
+
+    /// The period of synchronization messages, 2^(SMPER + 4) messages. 0 disables them.
+    pub u8, sync_period,       set_sync_period:           14, 12;
+
+    /// Assert the processor trigger output when tracing stopped because of a stop trigger
+    pub pto_when_triggered,    set_pto_when_triggered:    16;
+
+    /// Assert the processor trigger output when tracing stopped
+    pub pto_when_stopped,      set_pto_when_stopped:      17;
+
+    /// Assert the cross trigger output when tracing stopped because of a stop trigger
+    pub cto_when_triggered,    set_cto_when_triggered:    20;
+
+    /// Assert the cross trigger output when tracing stopped
+    pub cto_when_stopped,      set_cto_when_stopped:      21;
+}
+
+impl NexusRegister for TraxControl {
+    const ADDRESS: u8 = NARADR_TRAXCTRL;
+    const NAME: &'static str = "TRAXCTRL";
+
+    fn from_bits(bits: u32) -> Result<Self, XtensaError> {
+        Ok(Self(bits))
+    }
+
+    fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+bitfield::bitfield! {
+    /// TRAX Status register
+    #[derive(Copy, Clone)]
+    pub struct TraxStatus(u32);
+    impl Debug;
+
+    /// Tracing is active
+    pub trace_active,      _: 0;
+
+    /// A stop trigger was seen, tracing stops after the post-stop-trigger delay
+    pub triggered,         _: 1;
+
+    /// The stop trigger was a program counter match
+    pub pc_match_trigger,  _: 2;
+
+    /// The trace is stopped by the processor trigger input
+    pub pti_trigger,       _: 4;
+
+    /// The trace is stopped by the cross trigger input
+    pub cti_trigger,       _: 5;
+
+    /// The size of the trace memory, as the base two logarithm of its size in bytes
+    pub u8, memory_size,   _: 12, 8;
+
+    /// The processor trigger output is asserted
+    pub pto,               _: 16;
+
+    /// The cross trigger output is asserted
+    pub cto,               _: 17;
+}
+
+impl NexusRegister for TraxStatus {
+    const ADDRESS: u8 = NARADR_TRAXSTAT;
+    const NAME: &'static str = "TRAXSTAT";
+
+    fn from_bits(bits: u32) -> Result<Self, XtensaError> {
+        Ok(Self(bits))
+    }
+
+    fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+bitfield::bitfield! {
+    /// TRAX Address register
+    #[derive(Copy, Clone)]
+    pub struct TraxAddress(u32);
+    impl Debug;
+
+    /// The word address of the trace memory that is written or read next
+    pub address,        set_address: 20, 0;
+
+    /// How many times the trace memory wrapped around
+    pub wrap_count,     _: 30, 21;
+
+    /// Set when the wrap count overflowed
+    pub wrap_saturated, _: 31;
+}
+
+impl TraxAddress {
+    /// Returns whether older trace was overwritten, so the trace memory is full.
+    pub fn wrapped(&self) -> bool {
+        self.wrap_count() != 0 || self.wrap_saturated()
+    }
+}
+
+impl NexusRegister for TraxAddress {
+    const ADDRESS: u8 = NARADR_TRAXADDR;
+    const NAME: &'static str = "TRAXADDR";
+
+    fn from_bits(bits: u32) -> Result<Self, XtensaError> {
+        Ok(Self(bits))
+    }
+
+    fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+/// Reads the trace memory at TRAXADDR, and increments TRAXADDR.
+#[derive(Copy, Clone, Debug)]
+struct TraxData(u32);
+
+impl NexusRegister for TraxData {
+    const ADDRESS: u8 = NARADR_TRAXDATA;
+    const NAME: &'static str = "TRAXDATA";
+
+    fn from_bits(bits: u32) -> Result<Self, XtensaError> {
+        Ok(Self(bits))
+    }
+
+    fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+/// The program counter that triggers the stop of the trace.
+#[derive(Copy, Clone, Debug)]
+struct TriggerPc(u32);
+
+impl NexusRegister for TriggerPc {
+    const ADDRESS: u8 = NARADR_TRIGGERPC;
+    const NAME: &'static str = "TRIGGERPC";
+
+    fn from_bits(bits: u32) -> Result<Self, XtensaError> {
+        Ok(Self(bits))
+    }
+
+    fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+/// Program counter match control. Zero matches the exact value of TRIGGERPC.
+#[derive(Copy, Clone, Debug)]
+struct PcMatchControl(u32);
+
+impl NexusRegister for PcMatchControl {
+    const ADDRESS: u8 = NARADR_PCMATCHCTRL;
+    const NAME: &'static str = "PCMATCHCTRL";
+
+    fn from_bits(bits: u32) -> Result<Self, XtensaError> {
+        Ok(Self(bits))
+    }
+
+    fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+/// The post-stop-trigger delay, in instructions or trace words.
+#[derive(Copy, Clone, Debug)]
+struct DelayCount(u32);
+
+impl NexusRegister for DelayCount {
+    const ADDRESS: u8 = NARADR_DELAYCNT;
+    const NAME: &'static str = "DELAYCNT";
+
+    fn from_bits(bits: u32) -> Result<Self, XtensaError> {
+        Ok(Self(bits))
+    }
+
+    fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+/// The configuration of a TRAX trace.
+#[derive(Debug, Clone, Default)]
+pub struct TraxConfig {
+    /// Stop tracing once the program counter reaches this address, and halt the core.
+    pub stop_pc: Option<u32>,
+
+    /// The number of instructions that are still traced after the stop address was reached.
+    pub post_stop_instructions: u32,
+}
+
+/// The content of the trace memory.
+#[derive(Debug, Clone)]
+pub struct TraxTrace {
+    /// The trace data, from the oldest to the newest byte.
+    pub data: Vec<u8>,
+
+    /// Whether the trace memory wrapped around, so the data starts in the middle of a message.
+    pub wrapped: bool,
+}
+
+impl XtensaCommunicationInterface<'_> {
+    /// Starts recording the program flow into the trace memory, discarding the previous trace.
+    pub fn start_trax(&mut self, config: &TraxConfig) -> Result<(), XtensaError> {
+        let mut stop = TraxControl(0);
+        stop.set_trace_stop(true);
+        self.xdm.schedule_write_nexus_register(stop);
+        self.xdm.schedule_write_nexus_register(TraxAddress(0));
+        self.xdm
+            .schedule_write_nexus_register(DelayCount(config.post_stop_instructions));
+
+        let mut control = TraxControl(0);
+        control.set_trace_enable(true);
+        control.set_trace_memory_enable(true);
+        // Synchronize every 64 messages, so the trace can be decoded after wrapping around.
+        control.set_sync_period(2);
+        if let Some(stop_pc) = config.stop_pc {
+            self.xdm.schedule_write_nexus_register(TriggerPc(stop_pc));
+            self.xdm.schedule_write_nexus_register(PcMatchControl(0));
+            control.set_pc_match_enable(true);
+            // The processor trigger output raises a debug interrupt, which halts the core.
+            control.set_pto_when_triggered(true);
+        }
+        self.xdm.schedule_write_nexus_register(control);
+
+        self.xdm.execute()
+    }
+
+    /// Stops tracing and reads the trace memory.
+    ///
+    /// The trace does not need to be started by [`Self::start_trax`]. This also reads a trace
+    /// that was started by the firmware, or the trace left over from a previous run, for example
+    /// leading up to a crash.
+    pub fn read_trax_trace(&mut self) -> Result<TraxTrace, XtensaError> {
+        let mut stop = TraxControl(0);
+        stop.set_trace_stop(true);
+        self.xdm.write_nexus_register(stop)?;
+
+        let start = Instant::now();
+        let status = loop {
+            let status = self.xdm.read_nexus_register::<TraxStatus>()?;
+            if !status.trace_active() {
+                break status;
+            }
+            if start.elapsed() > Duration::from_millis(100) {
+                return Err(XtensaError::Timeout);
+            }
+        };
+
+        let memory_words = (1usize << status.memory_size()) / 4;
+        if memory_words == 0 {
+            return Err(XtensaError::NoTraceMemory);
+        }
+        let address = self.xdm.read_nexus_register::<TraxAddress>()?;
+        let end = address.address() as usize % memory_words;
+
+        // The oldest trace is right after the newest one, if the memory was filled.
+        let (start, words) = if address.wrapped() {
+            (end, memory_words)
+        } else {
+            (0, end)
+        };
+        tracing::debug!(
+            "Reading {words} words of TRAX trace, {} bytes of trace memory",
+            memory_words * 4
+        );
+
+        self.xdm.write_nexus_register(TraxAddress(start as u32))?;
+
+        let mut data = Vec::with_capacity(words * 4);
+        let mut remaining = words;
+        while remaining > 0 {
+            let batch = remaining.min(READ_BATCH_WORDS);
+            let reads = (0..batch)
+                .map(|_| self.xdm.schedule_read_nexus_register::<TraxData>())
+                .collect::<Vec<_>>();
+            for read in reads {
+                let word = self.xdm.read_deferred_result(read)?.into_u32();
+                data.extend_from_slice(&word.to_le_bytes());
+            }
+            remaining -= batch;
+        }
+
+        Ok(TraxTrace {
+            data,
+            wrapped: address.wrapped(),
+        })
+    }
+}
+
+/// A change of the program flow, decoded from the trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraxElement {
+    /// After executing `instructions` instructions, an indirect branch or an exception continued
+    /// the program flow at `target`.
+    Branch {
+        /// The number of instructions executed since the previous element.
+        instructions: u32,
+        /// The address the program flow continued at.
+        target: u32,
+        /// Whether the program flow was changed by an exception or interrupt.
+        exception: bool,
+    },
+
+    /// The full address of the program flow, which is sent when tracing starts and periodically
+    /// afterwards.
+    Sync {
+        /// The number of instructions executed since the previous element.
+        instructions: u32,
+        /// The address of the program flow.
+        address: u32,
+    },
+
+    /// An event like the core entering debug mode, or the trace being stopped.
+    Event {
+        /// The implementation defined event code.
+        code: u8,
+        /// The number of instructions executed since the previous element.
+        instructions: u32,
+    },
+
+    /// Some messages could not be decoded, or the address of a branch is not known. The program
+    /// flow continues at the next element.
+    Discontinuity,
+}
+
+/// The message types of the trace.
+const TCODE_INDIRECT_BRANCH: u8 = 4;
+const TCODE_SYNC: u8 = 9;
+const TCODE_INDIRECT_BRANCH_SYNC: u8 = 12;
+const TCODE_CORRELATION: u8 = 33;
+
+/// The end of a message, or idle when outside of a message.
+const MSEO_END_OF_MESSAGE: u8 = 0b11;
+/// The end of a variable length field.
+const MSEO_END_OF_FIELD: u8 = 0b01;
+
+/// A field of a message, with the value of the fixed length fields in front of it.
+#[derive(Debug, Default, Clone, Copy)]
+struct Field {
+    value: u64,
+    bits: u32,
+}
+
+impl Field {
+    fn push(&mut self, data: u8) {
+        // Fields longer than 64 bits don't occur in valid traces, the upper bits are dropped.
+        if self.bits < u64::BITS {
+            self.value |= u64::from(data) << self.bits;
+        }
+        self.bits += 6;
+    }
+
+    /// Takes `bits` bits of a fixed length field from the front.
+    fn take(&mut self, bits: u32) -> u64 {
+        let value = self.value & ((1 << bits) - 1);
+        self.value >>= bits;
+        self.bits = self.bits.saturating_sub(bits);
+        value
+    }
+}
+
+/// Decodes the messages of a TRAX trace into the program flow.
+pub struct TraxDecoder<'a> {
+    data: &'a [u8],
+    /// The address of the previous branch, as a reference for the truncated addresses.
+    address: Option<u32>,
+}
+
+impl<'a> TraxDecoder<'a> {
+    /// Creates a decoder of the trace read from the trace memory.
+    pub fn new(trace: &'a TraxTrace) -> Self {
+        Self::from_data(&trace.data, trace.wrapped)
+    }
+
+    /// Creates a decoder of raw trace data. If `wrapped` is set, the data may start in the middle
+    /// of a message, which is skipped.
+    pub fn from_data(data: &'a [u8], wrapped: bool) -> Self {
+        let data = if wrapped {
+            match data
+                .iter()
+                .position(|byte| byte & 0b11 == MSEO_END_OF_MESSAGE)
+            {
+                Some(end) => &data[end + 1..],
+                None => &[],
+            }
+        } else {
+            data
+        };
+
+        Self {
+            data,
+            address: None,
+        }
+    }
+
+    /// Splits the next message off the data, skipping idle bytes. Returns `None` at the end of the
+    /// data, and `Some(None)` if the message is malformed.
+    fn next_message(&mut self) -> Option<Option<Vec<Field>>> {
+        let start = self
+            .data
+            .iter()
+            .position(|byte| byte & 0b11 != MSEO_END_OF_MESSAGE)?;
+        self.data = &self.data[start..];
+
+        let mut fields = vec![];
+        let mut field = Field::default();
+        for (index, &byte) in self.data.iter().enumerate() {
+            field.push(byte >> 2);
+            match byte & 0b11 {
+                MSEO_END_OF_MESSAGE => {
+                    fields.push(field);
+                    self.data = &self.data[index + 1..];
+                    return Some(Some(fields));
+                }
+                MSEO_END_OF_FIELD => {
+                    fields.push(std::mem::take(&mut field));
+                }
+                0b00 => {}
+                _ => {
+                    self.data = &self.data[index + 1..];
+                    return Some(None);
+                }
+            }
+        }
+
+        // The trace ended in the middle of a message.
+        self.data = &[];
+        None
+    }
+
+    /// Decodes a message. Returns `None` for messages that don't change the program flow.
+    fn decode(&mut self, fields: &[Field]) -> Option<TraxElement> {
+        let mut first = fields[0];
+        // The variable length field following the first one, which holds an address.
+        let address_field = fields.get(1).map(|field| field.value as u32);
+
+        let element = match first.take(6) as u8 {
+            TCODE_INDIRECT_BRANCH => {
+                let exception = first.take(2) == 1;
+                let instructions = first.value as u32;
+                match (self.address, address_field) {
+                    (Some(address), Some(truncated)) => {
+                        let target = address ^ truncated;
+                        self.address = Some(target);
+                        TraxElement::Branch {
+                            instructions,
+                            target,
+                            exception,
+                        }
+                    }
+                    // The full address is not known before the first synchronization.
+                    _ => TraxElement::Discontinuity,
+                }
+            }
+            TCODE_INDIRECT_BRANCH_SYNC => {
+                let _reason = first.take(4);
+                let exception = first.take(2) == 1;
+                let instructions = first.value as u32;
+                self.address = address_field;
+                match address_field {
+                    Some(target) => TraxElement::Branch {
+                        instructions,
+                        target,
+                        exception,
+                    },
+                    None => TraxElement::Discontinuity,
+                }
+            }
+            TCODE_SYNC => {
+                let _reason = first.take(4);
+                let instructions = first.value as u32;
+                self.address = address_field;
+                match address_field {
+                    Some(address) => TraxElement::Sync {
+                        instructions,
+                        address,
+                    },
+                    None => TraxElement::Discontinuity,
+                }
+            }
+            TCODE_CORRELATION => {
+                let code = first.take(4) as u8;
+                let _discontinuity = first.take(2);
+                TraxElement::Event {
+                    code,
+                    instructions: first.value as u32,
+                }
+            }
+            tcode => {
+                tracing::debug!("Skipping TRAX message with unknown TCODE {tcode}");
+                return None;
+            }
+        };
+
+        Some(element)
+    }
+}
+
+impl Iterator for TraxDecoder<'_> {
+    type Item = TraxElement;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some(fields) = self.next_message()? else {
+                self.address = None;
+                return Some(TraxElement::Discontinuity);
+            };
+
+            if let Some(element) = self.decode(&fields) {
+                return Some(element);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Encodes a message, given as the values of its variable length fields with the fixed
+    /// length fields already packed in front of them.
+    fn message(fields: &[u64]) -> Vec<u8> {
+        let mut bytes = vec![];
+        for (index, &field) in fields.iter().enumerate() {
+            let slots = (u64::BITS - field.leading_zeros()).div_ceil(6).max(1);
+            let end = if index + 1 == fields.len() {
+                MSEO_END_OF_MESSAGE
+            } else {
+                MSEO_END_OF_FIELD
+            };
+            for slot in 0..slots {
+                let mseo = if slot + 1 == slots { end } else { 0 };
+                bytes.push(((field >> (slot * 6)) as u8 & 0x3f) << 2 | mseo);
+            }
+        }
+        bytes
+    }
+
+    fn sync(instructions: u64, address: u32) -> Vec<u8> {
+        message(&[u64::from(TCODE_SYNC) | instructions << 10, address.into()])
+    }
+
+    fn branch(instructions: u64, truncated: u32, exception: bool) -> Vec<u8> {
+        message(&[
+            u64::from(TCODE_INDIRECT_BRANCH) | u64::from(exception) << 6 | instructions << 8,
+            truncated.into(),
+        ])
+    }
+
+    #[test]
+    fn branches_after_sync() {
+        let data = [
+            sync(0, 0x4008_1000),
+            branch(5, 0x0000_0124, false),
+            // Idle bytes between messages are skipped.
+            vec![0xff, 0xff],
+            branch(12, 0x000c_0000, true),
+        ]
+        .concat();
+
+        let elements = TraxDecoder::from_data(&data, false).collect::<Vec<_>>();
+
+        assert_eq!(
+            elements,
+            [
+                TraxElement::Sync {
+                    instructions: 0,
+                    address: 0x4008_1000
+                },
+                TraxElement::Branch {
+                    instructions: 5,
+                    target: 0x4008_1124,
+                    exception: false
+                },
+                TraxElement::Branch {
+                    instructions: 12,
+                    target: 0x4004_1124,
+                    exception: true
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn branches_before_sync_are_unknown() {
+        let data = [branch(3, 0x10, false), sync(1, 0x4000_0400)].concat();
+
+        let elements = TraxDecoder::from_data(&data, false).collect::<Vec<_>>();
+
+        assert_eq!(
+            elements,
+            [
+                TraxElement::Discontinuity,
+                TraxElement::Sync {
+                    instructions: 1,
+                    address: 0x4000_0400
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn wrapped_trace_skips_partial_message() {
+        let data = [
+            // The end of a message whose start was overwritten.
+            vec![0x10, 0x21, 0x13],
+            message(&[
+                u64::from(TCODE_INDIRECT_BRANCH_SYNC) | 1 << 10 | 7 << 12,
+                0x4200_0008,
+            ]),
+            message(&[u64::from(TCODE_CORRELATION) | 2 << 6 | 4 << 12]),
+        ]
+        .concat();
+
+        let elements = TraxDecoder::from_data(&data, true).collect::<Vec<_>>();
+
+        assert_eq!(
+            elements,
+            [
+                TraxElement::Branch {
+                    instructions: 7,
+                    target: 0x4200_0008,
+                    exception: true
+                },
+                TraxElement::Event {
+                    code: 2,
+                    instructions: 4
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_trace() {
+        let mut data = sync(0, 0x4008_1000);
+        data.extend_from_slice(&branch(5, 0x124, false)[..2]);
+
+        let elements = TraxDecoder::from_data(&data, false).collect::<Vec<_>>();
+
+        assert_eq!(
+            elements,
+            [TraxElement::Sync {
+                instructions: 0,
+                address: 0x4008_1000
+            }]
+        );
+    }
+
+    #[test]
+    fn trace_address_wrapped() {
+        assert!(!TraxAddress(0x0000_0100).wrapped());
+        assert!(TraxAddress(0x0020_0100).wrapped());
+        assert!(TraxAddress(0x8000_0000).wrapped());
+        assert_eq!(TraxAddress(0x0020_0100).address(), 0x100);
+    }
+}
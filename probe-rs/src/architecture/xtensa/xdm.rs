@@ -123,6 +123,13 @@ pub enum Error {
 
 fn print_narsel(narsel: &u8) -> String {
     let name = match *narsel {
+        0x01 => "the TRAXCTRL",
+        0x02 => "the TRAXSTAT",
+        0x03 => "the TRAXDATA",
+        0x04 => "the TRAXADDR",
+        0x05 => "the TRIGGERPC",
+        0x06 => "the PCMATCHCTRL",
+        0x07 => "the DELAYCNT",
         0x40 => "the OCDID",
         0x42 => "the DCRCLR",
         0x43 => "the DCRSET",
@@ -506,7 +513,7 @@ impl<'probe> Xdm<'probe> {
         self.schedule_dbg_read(R::ADDRESS)
     }
 
-    fn read_nexus_register<R: NexusRegister>(&mut self) -> Result<R, XtensaError> {
+    pub(super) fn read_nexus_register<R: NexusRegister>(&mut self) -> Result<R, XtensaError> {
         let bits_reader = self.schedule_read_nexus_register::<R>();
 
         let bits = self.read_deferred_result(bits_reader)?.into_u32();
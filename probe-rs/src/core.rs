@@ -7,13 +7,13 @@ use crate::{
     },
     config::DebugSequence,
     error::{BreakpointError, Error},
-    memory::CoreMemoryInterface,
+    memory::Operation,
 };
 pub use probe_rs_target::{Architecture, CoreAccessOptions};
 use probe_rs_target::{
     ArmCoreAccessOptions, MemoryRegion, RiscvCoreAccessOptions, XtensaCoreAccessOptions,
 };
-use std::{sync::Arc, time::Duration};
+use std::{borrow::Cow, sync::Arc, time::Duration};
 
 pub mod core_state;
 pub mod core_status;
@@ -21,11 +21,17 @@ pub mod core_status;
 pub mod dump;
 pub mod memory_mapped_registers;
 pub mod registers;
+pub mod run_control;
+pub mod software_breakpoints;
+pub mod watchpoints;
 
 pub use core_state::*;
 pub use core_status::*;
 pub use memory_mapped_registers::MemoryMappedRegister;
 pub use registers::*;
+pub use run_control::{RunControlEvent, RunControlGroups};
+pub use software_breakpoints::SoftwareBreakpoints;
+pub use watchpoints::*;
 
 /// An struct for storing the current state of a core.
 #[derive(Debug, Clone)]
@@ -92,6 +98,50 @@ pub trait CoreInterface: MemoryInterface {
     /// Clears the breakpoint configured in unit `unit_index`.
     fn clear_hw_breakpoint(&mut self, unit_index: usize) -> Result<(), Error>;
 
+    /// Returns the number of hardware watchpoint units of the core.
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        Ok(0)
+    }
+
+    /// Read the configured hardware watchpoints.
+    /// A value of None in any position of the Vector indicates that the unit is unset/available.
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        Ok(vec![])
+    }
+
+    /// Configures unit `unit_index` to halt the core on accesses matching `watchpoint`.
+    fn set_hw_watchpoint(
+        &mut self,
+        _unit_index: usize,
+        _watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        Err(Error::NotImplemented("watchpoints"))
+    }
+
+    /// Clears the watchpoint configured in unit `unit_index`.
+    fn clear_hw_watchpoint(&mut self, _unit_index: usize) -> Result<(), Error> {
+        Err(Error::NotImplemented("watchpoints"))
+    }
+
+    /// Returns the watchpoint that caused the last halt, if the hardware can tell.
+    ///
+    /// Only meaningful when the core halted with [`HaltReason::Watchpoint`].
+    fn triggered_watchpoint(&mut self) -> Result<Option<Watchpoint>, Error> {
+        Ok(None)
+    }
+
+    /// Returns `true` if breakpoints and watchpoints are allocated from the same hardware units,
+    /// in which case a unit index used by one is not available to the other.
+    fn watchpoints_share_breakpoint_units(&self) -> bool {
+        false
+    }
+
+    /// Returns the hardware breakpoint units probe-rs uses internally (e.g. to catch
+    /// exceptions), which are neither available for breakpoints nor for watchpoints.
+    fn reserved_breakpoint_units(&mut self) -> Result<Vec<usize>, Error> {
+        Ok(vec![])
+    }
+
     /// Returns a list of all the registers of this core.
     fn registers(&self) -> &'static CoreRegisters;
 
@@ -163,6 +213,39 @@ pub trait CoreInterface: MemoryInterface {
         Err(Error::NotImplemented("vector catch"))
     }
 
+    /// Adds this core to run-control group `group`, so that it halts when any other core in the
+    /// group halts, and restarts on the group's restart event.
+    fn join_run_control_group(&mut self, _group: u8) -> Result<(), Error> {
+        Err(Error::NotImplemented("run-control groups"))
+    }
+
+    /// Removes this core from run-control group `group`.
+    fn leave_run_control_group(&mut self, _group: u8) -> Result<(), Error> {
+        Err(Error::NotImplemented("run-control groups"))
+    }
+
+    /// Broadcasts `event` to every core in run-control group `group`, including this one.
+    fn trigger_run_control_group(
+        &mut self,
+        _group: u8,
+        _event: RunControlEvent,
+    ) -> Result<(), Error> {
+        Err(Error::NotImplemented("run-control groups"))
+    }
+
+    /// Prepares the halted core to be restarted by its run-control group: writes back cached
+    /// registers and clears the pending halt request, like [`CoreInterface::run`] does before it
+    /// restarts the core.
+    fn prepare_run_control_group_restart(&mut self) -> Result<(), Error> {
+        Err(Error::NotImplemented("run-control groups"))
+    }
+
+    /// Called after the run-control group of this core was restarted, to acknowledge the restart
+    /// and update the cached core status.
+    fn run_control_group_restarted(&mut self) -> Result<(), Error> {
+        Err(Error::NotImplemented("run-control groups"))
+    }
+
     /// Check if the integer size is 64-bit
     fn is_64_bit(&self) -> bool {
         false
@@ -188,17 +271,141 @@ pub struct Core<'probe> {
     target: &'probe Target,
 
     inner: Box<dyn CoreInterface + 'probe>,
+    sw_breakpoints: &'probe mut SoftwareBreakpoints,
 }
 
-impl CoreMemoryInterface for Core<'_> {
-    type ErrorType = Error;
+/// Memory accesses see the original instructions instead of the software breakpoints of the
+/// core, and writes over a software breakpoint update the instruction it replaced.
+impl MemoryInterface for Core<'_> {
+    fn supports_native_64bit_access(&mut self) -> bool {
+        self.inner.supports_native_64bit_access()
+    }
+
+    fn read_word_64(&mut self, address: u64) -> Result<u64, Error> {
+        let mut data = [self.inner.read_word_64(address)?];
+        self.hide_sw_breakpoints(address, &mut data, u64::to_le_bytes, u64::from_le_bytes);
+        Ok(data[0])
+    }
+
+    fn read_word_32(&mut self, address: u64) -> Result<u32, Error> {
+        let mut data = [self.inner.read_word_32(address)?];
+        self.hide_sw_breakpoints(address, &mut data, u32::to_le_bytes, u32::from_le_bytes);
+        Ok(data[0])
+    }
+
+    fn read_word_16(&mut self, address: u64) -> Result<u16, Error> {
+        let mut data = [self.inner.read_word_16(address)?];
+        self.hide_sw_breakpoints(address, &mut data, u16::to_le_bytes, u16::from_le_bytes);
+        Ok(data[0])
+    }
+
+    fn read_word_8(&mut self, address: u64) -> Result<u8, Error> {
+        let mut data = [self.inner.read_word_8(address)?];
+        self.hide_sw_breakpoints(address, &mut data, u8::to_le_bytes, u8::from_le_bytes);
+        Ok(data[0])
+    }
+
+    fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), Error> {
+        self.inner.read_64(address, data)?;
+        self.hide_sw_breakpoints(address, data, u64::to_le_bytes, u64::from_le_bytes);
+        Ok(())
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), Error> {
+        self.inner.read_32(address, data)?;
+        self.hide_sw_breakpoints(address, data, u32::to_le_bytes, u32::from_le_bytes);
+        Ok(())
+    }
+
+    fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), Error> {
+        self.inner.read_16(address, data)?;
+        self.hide_sw_breakpoints(address, data, u16::to_le_bytes, u16::from_le_bytes);
+        Ok(())
+    }
+
+    fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), Error> {
+        self.inner.read_8(address, data)?;
+        self.hide_sw_breakpoints(address, data, u8::to_le_bytes, u8::from_le_bytes);
+        Ok(())
+    }
+
+    fn read(&mut self, address: u64, data: &mut [u8]) -> Result<(), Error> {
+        self.inner.read(address, data)?;
+        self.hide_sw_breakpoints(address, data, u8::to_le_bytes, u8::from_le_bytes);
+        Ok(())
+    }
+
+    fn write_word_64(&mut self, address: u64, data: u64) -> Result<(), Error> {
+        let data = [data];
+        let data = self.keep_sw_breakpoints(address, &data, u64::to_le_bytes, u64::from_le_bytes);
+        self.inner.write_word_64(address, data[0])
+    }
+
+    fn write_word_32(&mut self, address: u64, data: u32) -> Result<(), Error> {
+        let data = [data];
+        let data = self.keep_sw_breakpoints(address, &data, u32::to_le_bytes, u32::from_le_bytes);
+        self.inner.write_word_32(address, data[0])
+    }
+
+    fn write_word_16(&mut self, address: u64, data: u16) -> Result<(), Error> {
+        let data = [data];
+        let data = self.keep_sw_breakpoints(address, &data, u16::to_le_bytes, u16::from_le_bytes);
+        self.inner.write_word_16(address, data[0])
+    }
+
+    fn write_word_8(&mut self, address: u64, data: u8) -> Result<(), Error> {
+        let data = [data];
+        let data = self.keep_sw_breakpoints(address, &data, u8::to_le_bytes, u8::from_le_bytes);
+        self.inner.write_word_8(address, data[0])
+    }
+
+    fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), Error> {
+        let data = self.keep_sw_breakpoints(address, data, u64::to_le_bytes, u64::from_le_bytes);
+        self.inner.write_64(address, &data)
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), Error> {
+        let data = self.keep_sw_breakpoints(address, data, u32::to_le_bytes, u32::from_le_bytes);
+        self.inner.write_32(address, &data)
+    }
+
+    fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), Error> {
+        let data = self.keep_sw_breakpoints(address, data, u16::to_le_bytes, u16::from_le_bytes);
+        self.inner.write_16(address, &data)
+    }
+
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), Error> {
+        let data = self.keep_sw_breakpoints(address, data, u8::to_le_bytes, u8::from_le_bytes);
+        self.inner.write_8(address, &data)
+    }
 
-    fn memory(&self) -> &dyn MemoryInterface<Self::ErrorType> {
-        self.inner.as_ref()
+    fn write(&mut self, address: u64, data: &[u8]) -> Result<(), Error> {
+        let data = self.keep_sw_breakpoints(address, data, u8::to_le_bytes, u8::from_le_bytes);
+        self.inner.write(address, &data)
     }
 
-    fn memory_mut(&mut self) -> &mut dyn MemoryInterface<Self::ErrorType> {
-        self.inner.as_mut()
+    fn supports_8bit_transfers(&self) -> Result<bool, Error> {
+        self.inner.supports_8bit_transfers()
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+
+    fn execute_memory_operations(&mut self, operations: &mut [Operation<'_>]) {
+        if self.sw_breakpoints.is_empty() {
+            return self.inner.execute_memory_operations(operations);
+        }
+
+        // Executed one by one, so that every access goes through the breakpoint bookkeeping.
+        for operation in operations {
+            let result = self.execute_single_memory_operation(operation.reborrow());
+            let success = result.is_ok();
+            operation.result = Some(result);
+            if !success {
+                break;
+            }
+        }
     }
 }
 
@@ -214,15 +421,68 @@ impl<'probe> Core<'probe> {
         name: &'probe str,
         target: &'probe Target,
         core: impl CoreInterface + 'probe,
+        sw_breakpoints: &'probe mut SoftwareBreakpoints,
     ) -> Core<'probe> {
         Self {
             id,
             name,
             target,
             inner: Box::new(core),
+            sw_breakpoints,
+        }
+    }
+
+    /// Replaces the software breakpoints in `data`, read from `address`, with the original
+    /// instructions. The words are converted to and from their little endian bytes.
+    fn hide_sw_breakpoints<W: Copy, const N: usize>(
+        &self,
+        address: u64,
+        data: &mut [W],
+        to_bytes: fn(W) -> [u8; N],
+        from_bytes: fn([u8; N]) -> W,
+    ) {
+        let len = std::mem::size_of_val(data);
+        if !self.sw_breakpoints.overlaps(self.id, address, len) {
+            return;
+        }
+
+        let mut bytes = data
+            .iter()
+            .flat_map(|word| to_bytes(*word))
+            .collect::<Vec<_>>();
+        self.sw_breakpoints.hide(self.id, address, &mut bytes);
+        for (word, bytes) in data.iter_mut().zip(bytes.chunks_exact(N)) {
+            *word = from_bytes(bytes.try_into().unwrap());
         }
     }
 
+    /// Returns `data`, to be written to `address`, with the software breakpoints it overlaps
+    /// kept in place, and saves the overwritten bytes as the original instructions.
+    fn keep_sw_breakpoints<'d, W: Copy, const N: usize>(
+        &mut self,
+        address: u64,
+        data: &'d [W],
+        to_bytes: fn(W) -> [u8; N],
+        from_bytes: fn([u8; N]) -> W,
+    ) -> Cow<'d, [W]> {
+        let len = std::mem::size_of_val(data);
+        if !self.sw_breakpoints.overlaps(self.id, address, len) {
+            return Cow::Borrowed(data);
+        }
+
+        let mut bytes = data
+            .iter()
+            .flat_map(|word| to_bytes(*word))
+            .collect::<Vec<_>>();
+        self.sw_breakpoints.keep(self.id, address, &mut bytes);
+        Cow::Owned(
+            bytes
+                .chunks_exact(N)
+                .map(|bytes| from_bytes(bytes.try_into().unwrap()))
+                .collect(),
+        )
+    }
+
     /// Returns the memory regions associated with this core.
     pub fn memory_regions(&self) -> impl Iterator<Item = &MemoryRegion> {
         self.target
@@ -276,8 +536,15 @@ impl<'probe> Core<'probe> {
     }
 
     /// Continue to execute instructions.
+    ///
+    /// If the core is halted on a software breakpoint, the original instruction is executed
+    /// first, with the breakpoint patched in again before the core is resumed.
     #[tracing::instrument(skip(self))]
     pub fn run(&mut self) -> Result<(), Error> {
+        if !self.sw_breakpoints.is_empty() && self.inner.core_halted()? {
+            self.ensure_sw_breakpoints_coherent()?;
+            self.step_over_sw_breakpoint()?;
+        }
         self.inner.run()
     }
 
@@ -300,11 +567,42 @@ impl<'probe> Core<'probe> {
     }
 
     /// Steps one instruction and then enters halted state again.
+    ///
+    /// Stepping from a software breakpoint executes the instruction it replaced.
     #[tracing::instrument(skip(self))]
     pub fn step(&mut self) -> Result<CoreInformation, Error> {
+        if !self.sw_breakpoints.is_empty() {
+            self.ensure_sw_breakpoints_coherent()?;
+            if let Some(info) = self.step_over_sw_breakpoint()? {
+                return Ok(info);
+            }
+        }
         self.inner.step()
     }
 
+    /// Single steps with the original instruction restored, if the core is halted on a
+    /// software breakpoint. Returns `None` if there is no software breakpoint at the PC.
+    fn step_over_sw_breakpoint(&mut self) -> Result<Option<CoreInformation>, Error> {
+        let pc: u64 = self
+            .inner
+            .read_core_reg(self.inner.program_counter().id)?
+            .try_into()?;
+        if !self.sw_breakpoints.contains(self.id, pc) {
+            return Ok(None);
+        }
+
+        self.sw_breakpoints
+            .with_original(&mut *self.inner, self.id, pc, |core| core.step())
+            .map(Some)
+    }
+
+    /// Re-patches software breakpoints whose memory was overwritten since they were set.
+    fn ensure_sw_breakpoints_coherent(&mut self) -> Result<(), Error> {
+        let instruction_set = self.inner.instruction_set()?;
+        self.sw_breakpoints
+            .ensure_coherent(&mut *self.inner, self.id, instruction_set)
+    }
+
     /// Returns the current status of the core.
     #[tracing::instrument(level = "trace", skip(self))]
     pub fn status(&mut self) -> Result<CoreStatus, Error> {
@@ -408,12 +706,15 @@ impl<'probe> Core<'probe> {
 
         // If there is a breakpoint set already, return its bp_unit_index, else find the next free index.
         let breakpoints = self.inner.hw_breakpoints()?;
+        let mut unavailable = self.units_used_by_watchpoints()?;
+        unavailable.extend(self.inner.reserved_breakpoint_units()?);
         let breakpoint_comparator_index =
             match breakpoints.iter().position(|&bp| bp == Some(address)) {
                 Some(breakpoint_comparator_index) => breakpoint_comparator_index,
                 None => breakpoints
                     .iter()
-                    .position(|bp| bp.is_none())
+                    .enumerate()
+                    .position(|(index, bp)| bp.is_none() && !unavailable.contains(&index))
                     .ok_or_else(|| Error::Other("No available hardware breakpoints".to_string()))?,
             };
 
@@ -490,6 +791,182 @@ impl<'probe> Core<'probe> {
         Ok(())
     }
 
+    /// Set a software breakpoint at `address`, by replacing the instruction there with a
+    /// break instruction.
+    ///
+    /// This does not use any hardware breakpoint units, but only works for code that runs
+    /// from RAM. The breakpoint applies to all cores that share the RAM, and the original
+    /// instruction is restored when one of them has to execute it, and when the breakpoint is
+    /// cleared.
+    #[tracing::instrument(skip(self))]
+    pub fn set_sw_breakpoint(&mut self, address: u64) -> Result<(), Error> {
+        let mut cores = vec![self.id];
+        let mut in_ram = false;
+        for region in self.memory_regions() {
+            if !region
+                .as_ram_region()
+                .is_some_and(|ram| ram.range.contains(&address))
+            {
+                continue;
+            }
+            in_ram = true;
+
+            for (id, core) in self.target.cores.iter().enumerate() {
+                if !cores.contains(&id) && region.cores().contains(&core.name) {
+                    cores.push(id);
+                }
+            }
+        }
+        if !in_ram {
+            return Err(Error::BreakpointOperation(
+                BreakpointError::SoftwareBreakpointNotInRam(address),
+            ));
+        }
+
+        let instruction_set = self.inner.instruction_set()?;
+        self.sw_breakpoints
+            .insert(&mut *self.inner, instruction_set, address, cores)
+    }
+
+    /// Clear the software breakpoint at `address`, restoring the original instruction.
+    ///
+    /// This also clears the breakpoint for the other cores that share the memory.
+    #[tracing::instrument(skip(self))]
+    pub fn clear_sw_breakpoint(&mut self, address: u64) -> Result<(), Error> {
+        self.sw_breakpoints
+            .remove(&mut *self.inner, self.id, address)
+    }
+
+    /// Clear all software breakpoints of this core.
+    ///
+    /// Also used as a helper function in [`Session::drop`](crate::session::Session).
+    #[tracing::instrument(skip(self))]
+    pub fn clear_all_sw_breakpoints(&mut self) -> Result<(), Error> {
+        self.sw_breakpoints.remove_all(&mut *self.inner, self.id)
+    }
+
+    /// Returns the addresses of all software breakpoints of this core, including the ones set
+    /// through other cores that share its memory.
+    pub fn sw_breakpoints(&self) -> Vec<u64> {
+        self.sw_breakpoints.addresses(self.id).collect()
+    }
+
+    /// Returns the number of hardware watchpoint units of the core.
+    pub fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        self.inner.available_watchpoint_units()
+    }
+
+    /// Set a hardware watchpoint
+    ///
+    /// This function will try to configure a free watchpoint unit to halt the core when
+    /// `size` bytes starting at `address` are accessed as described by `kind`.
+    ///
+    /// The amount of hardware watchpoints which are supported is chip specific,
+    /// and can be queried using the `available_watchpoint_units` function.
+    #[tracing::instrument(skip(self))]
+    pub fn set_hw_watchpoint(
+        &mut self,
+        address: u64,
+        size: u64,
+        kind: WatchpointKind,
+    ) -> Result<(), Error> {
+        let watchpoint = Watchpoint::new(address, size, kind)?;
+
+        // Reuse the unit if the same region is already watched for the same kind of access, else
+        // find the next free index.
+        let watchpoints = self.inner.hw_watchpoints()?;
+        let used_by_breakpoints = if self.inner.watchpoints_share_breakpoint_units() {
+            self.inner.hw_breakpoints()?
+        } else {
+            vec![]
+        };
+        let reserved = self.inner.reserved_breakpoint_units()?;
+        let unit_index = match watchpoints.iter().position(|wp| *wp == Some(watchpoint)) {
+            Some(unit_index) => unit_index,
+            None => watchpoints
+                .iter()
+                .enumerate()
+                .position(|(index, wp)| {
+                    wp.is_none()
+                        && used_by_breakpoints.get(index).is_none_or(Option::is_none)
+                        && !reserved.contains(&index)
+                })
+                .ok_or(Error::BreakpointOperation(
+                    BreakpointError::NoFreeWatchpoint,
+                ))?,
+        };
+
+        tracing::debug!(
+            "Trying to set HW watchpoint #{} on {:#08x} ({} bytes, {:?})",
+            unit_index,
+            address,
+            size,
+            kind
+        );
+
+        self.inner.set_hw_watchpoint(unit_index, watchpoint)
+    }
+
+    /// Clear a hardware watchpoint
+    ///
+    /// This function will try to clear the hardware watchpoint of `kind` covering `size` bytes
+    /// starting at `address`.
+    #[tracing::instrument(skip(self))]
+    pub fn clear_hw_watchpoint(
+        &mut self,
+        address: u64,
+        size: u64,
+        kind: WatchpointKind,
+    ) -> Result<(), Error> {
+        let unit_index = self.inner.hw_watchpoints()?.iter().position(|wp| {
+            wp.is_some_and(|wp| wp.address == address && wp.size == size && wp.kind == kind)
+        });
+
+        match unit_index {
+            Some(unit_index) => self.inner.clear_hw_watchpoint(unit_index),
+            None => Err(Error::BreakpointOperation(
+                BreakpointError::WatchpointNotFound(address),
+            )),
+        }
+    }
+
+    /// Clear all hardware watchpoints
+    ///
+    /// This function will clear all HW watchpoints which are configured on the target,
+    /// regardless if they are set by probe-rs.
+    #[tracing::instrument(skip(self))]
+    pub fn clear_all_hw_watchpoints(&mut self) -> Result<(), Error> {
+        let watchpoints = self.inner.hw_watchpoints()?;
+        for (unit_index, _) in watchpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, wp)| wp.is_some())
+        {
+            self.inner.clear_hw_watchpoint(unit_index)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the watchpoint that caused the last halt, if the hardware can tell.
+    pub fn triggered_watchpoint(&mut self) -> Result<Option<Watchpoint>, Error> {
+        self.inner.triggered_watchpoint()
+    }
+
+    /// Returns the unit indices occupied by watchpoints, if those units are shared with breakpoints.
+    fn units_used_by_watchpoints(&mut self) -> Result<Vec<usize>, Error> {
+        if !self.inner.watchpoints_share_breakpoint_units() {
+            return Ok(vec![]);
+        }
+
+        Ok(self
+            .inner
+            .hw_watchpoints()?
+            .iter()
+            .enumerate()
+            .filter_map(|(index, wp)| wp.map(|_| index))
+            .collect())
+    }
+
     /// Returns the architecture of the core.
     pub fn architecture(&self) -> Architecture {
         self.inner.architecture()
@@ -542,6 +1019,36 @@ impl<'probe> Core<'probe> {
         self.inner.disable_vector_catch(condition)
     }
 
+    pub(crate) fn join_run_control_group(&mut self, group: u8) -> Result<(), Error> {
+        self.inner.join_run_control_group(group)
+    }
+
+    pub(crate) fn leave_run_control_group(&mut self, group: u8) -> Result<(), Error> {
+        self.inner.leave_run_control_group(group)
+    }
+
+    pub(crate) fn trigger_run_control_group(
+        &mut self,
+        group: u8,
+        event: RunControlEvent,
+    ) -> Result<(), Error> {
+        self.inner.trigger_run_control_group(group, event)
+    }
+
+    /// Prepares the core for a group restart. If the core is halted on a software breakpoint, the
+    /// original instruction is executed first, like [`Core::run`] does.
+    pub(crate) fn prepare_run_control_group_restart(&mut self) -> Result<(), Error> {
+        if !self.sw_breakpoints.is_empty() {
+            self.ensure_sw_breakpoints_coherent()?;
+            self.step_over_sw_breakpoint()?;
+        }
+        self.inner.prepare_run_control_group_restart()
+    }
+
+    pub(crate) fn run_control_group_restarted(&mut self) -> Result<(), Error> {
+        self.inner.run_control_group_restarted()
+    }
+
     /// Check if the integer size is 64-bit
     pub fn is_64_bit(&self) -> bool {
         self.inner.is_64_bit()
@@ -615,6 +1122,38 @@ impl CoreInterface for Core<'_> {
         Ok(())
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        self.available_watchpoint_units()
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        self.inner.hw_watchpoints()
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        self.inner.set_hw_watchpoint(unit_index, watchpoint)
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        self.inner.clear_hw_watchpoint(unit_index)
+    }
+
+    fn triggered_watchpoint(&mut self) -> Result<Option<Watchpoint>, Error> {
+        self.triggered_watchpoint()
+    }
+
+    fn watchpoints_share_breakpoint_units(&self) -> bool {
+        self.inner.watchpoints_share_breakpoint_units()
+    }
+
+    fn reserved_breakpoint_units(&mut self) -> Result<Vec<usize>, Error> {
+        self.inner.reserved_breakpoint_units()
+    }
+
     fn registers(&self) -> &'static CoreRegisters {
         self.registers()
     }
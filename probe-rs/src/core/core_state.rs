@@ -11,7 +11,7 @@ use crate::{
     },
 };
 
-use super::ResolvedCoreOptions;
+use super::{ResolvedCoreOptions, SoftwareBreakpoints};
 
 #[derive(Debug)]
 pub(crate) struct CombinedCoreState {
@@ -43,6 +43,7 @@ impl CombinedCoreState {
         &'probe mut self,
         target: &'probe Target,
         arm_interface: &'probe mut Box<dyn ArmDebugInterface>,
+        sw_breakpoints: &'probe mut SoftwareBreakpoints,
     ) -> Result<Core<'probe>, Error> {
         let name = &target.cores[self.id].name;
 
@@ -68,6 +69,7 @@ impl CombinedCoreState {
                 name,
                 target,
                 crate::architecture::arm::armv6m::Armv6m::new(memory, s, debug_sequence)?,
+                sw_breakpoints,
             ),
             SpecificCoreState::Armv7a(s) => Core::new(
                 self.id,
@@ -77,9 +79,11 @@ impl CombinedCoreState {
                     memory,
                     s,
                     options.debug_base.expect("base_address not specified"),
+                    options.cti_base,
                     debug_sequence,
                     CoreType::Armv7a,
                 )?,
+                sw_breakpoints,
             ),
             SpecificCoreState::Armv7r(s) => Core::new(
                 self.id,
@@ -89,15 +93,18 @@ impl CombinedCoreState {
                     memory,
                     s,
                     options.debug_base.expect("base_address not specified"),
+                    options.cti_base,
                     debug_sequence,
                     CoreType::Armv7r,
                 )?,
+                sw_breakpoints,
             ),
             SpecificCoreState::Armv7m(s) | SpecificCoreState::Armv7em(s) => Core::new(
                 self.id,
                 name,
                 target,
                 crate::architecture::arm::armv7m::Armv7m::new(memory, s, debug_sequence)?,
+                sw_breakpoints,
             ),
             SpecificCoreState::Armv8a(s) => Core::new(
                 self.id,
@@ -110,12 +117,14 @@ impl CombinedCoreState {
                     options.cti_base.expect("cti_address not specified"),
                     debug_sequence,
                 )?,
+                sw_breakpoints,
             ),
             SpecificCoreState::Armv8m(s) => Core::new(
                 self.id,
                 name,
                 target,
                 crate::architecture::arm::armv8m::Armv8m::new(memory, s, debug_sequence)?,
+                sw_breakpoints,
             ),
             _ => {
                 unreachable!(
@@ -178,6 +187,7 @@ impl CombinedCoreState {
         &'probe mut self,
         target: &'probe Target,
         mut interface: RiscvCommunicationInterface<'probe>,
+        sw_breakpoints: &'probe mut SoftwareBreakpoints,
     ) -> Result<Core<'probe>, Error> {
         let name = &target.cores[self.id].name;
 
@@ -199,12 +209,14 @@ impl CombinedCoreState {
                 name,
                 target,
                 crate::architecture::riscv::Riscv32::new(interface, s, debug_sequence)?,
+                sw_breakpoints,
             )),
             SpecificCoreState::Riscv64(s) => Ok(Core::new(
                 self.id,
                 name,
                 target,
                 Riscv64::new(interface, s, debug_sequence)?,
+                sw_breakpoints,
             )),
             _ => unreachable!(
                 "The stored core state is not compatible with the RISC-V architecture. \
@@ -217,6 +229,7 @@ impl CombinedCoreState {
         &'probe mut self,
         target: &'probe Target,
         interface: XtensaCommunicationInterface<'probe>,
+        sw_breakpoints: &'probe mut SoftwareBreakpoints,
     ) -> Result<Core<'probe>, Error> {
         let name = &target.cores[self.id].name;
 
@@ -241,6 +254,7 @@ impl CombinedCoreState {
             name,
             target,
             crate::architecture::xtensa::Xtensa::new(interface, s, debug_sequence)?,
+            sw_breakpoints,
         ))
     }
 
@@ -0,0 +1,85 @@
+//! Run-control groups: sets of cores that halt and resume at the same moment.
+//!
+//! Halting or resuming cores one after the other lets the remaining cores run on for the time the
+//! debug probe needs to reach them, which can be milliseconds. Cores in a run-control group are
+//! instead wired together in hardware (through the CoreSight cross-trigger matrix on ARM), so that
+//! one core halting stops the whole group, and a single restart event resumes every core in it.
+
+use std::collections::BTreeMap;
+
+/// A run-control event that is broadcast to every core in a group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunControlEvent {
+    /// Request all cores in the group to halt.
+    Halt,
+    /// Restart all cores in the group.
+    Restart,
+}
+
+/// Bookkeeping for the run-control groups of a session.
+///
+/// Groups are identified by a small number, which the core implementations map onto the hardware
+/// resources of the group, e.g. a pair of cross-trigger channels.
+#[derive(Debug, Default)]
+pub struct RunControlGroups {
+    groups: BTreeMap<u8, Vec<usize>>,
+}
+
+impl RunControlGroups {
+    /// Returns `true` if no run-control groups are configured.
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Returns the group `core_index` is a member of, and all cores in that group.
+    pub fn group_of(&self, core_index: usize) -> Option<(u8, &[usize])> {
+        self.groups
+            .iter()
+            .find(|(_, cores)| cores.contains(&core_index))
+            .map(|(group, cores)| (*group, cores.as_slice()))
+    }
+
+    /// Iterates over all groups and their member cores.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &[usize])> + '_ {
+        self.groups
+            .iter()
+            .map(|(group, cores)| (*group, cores.as_slice()))
+    }
+
+    /// Returns the lowest group number that is not in use.
+    pub(crate) fn next_free(&self) -> u8 {
+        (0..=u8::MAX)
+            .find(|group| !self.groups.contains_key(group))
+            .unwrap_or(u8::MAX)
+    }
+
+    pub(crate) fn insert(&mut self, group: u8, cores: Vec<usize>) {
+        self.groups.insert(group, cores);
+    }
+
+    pub(crate) fn remove(&mut self, group: u8) -> Option<Vec<usize>> {
+        self.groups.remove(&group)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn group_lookup_and_allocation() {
+        let mut groups = RunControlGroups::default();
+        assert_eq!(groups.next_free(), 0);
+
+        groups.insert(0, vec![0, 1]);
+        groups.insert(1, vec![2, 3]);
+        assert_eq!(groups.group_of(1), Some((0, &[0, 1][..])));
+        assert_eq!(groups.group_of(3), Some((1, &[2, 3][..])));
+        assert_eq!(groups.group_of(4), None);
+        assert_eq!(groups.next_free(), 2);
+
+        assert_eq!(groups.remove(0), Some(vec![0, 1]));
+        assert_eq!(groups.group_of(0), None);
+        assert_eq!(groups.next_free(), 0);
+    }
+}
@@ -0,0 +1,430 @@
+//! Software breakpoints, implemented by patching break instructions into target memory.
+//!
+//! Hardware breakpoint units are scarce, but code that runs from RAM can be patched with
+//! the architecture's break instruction (`BKPT`, `BRK`, `ebreak` or `BREAK`) instead.
+//! The original instruction bytes are kept here so they can be restored when the core has
+//! to execute the patched instruction, and when the breakpoint is removed. Memory accesses
+//! through a [`Core`](crate::Core) see the original instructions, and writes over a patched
+//! instruction replace the saved original, keeping the breakpoint in place.
+
+use crate::{
+    InstructionSet, MemoryInterface,
+    error::{BreakpointError, Error},
+};
+
+/// `BKPT #0`
+const THUMB_BKPT: &[u8] = &[0x00, 0xBE];
+/// `BKPT #0`
+const A32_BKPT: &[u8] = &[0x70, 0x00, 0x20, 0xE1];
+/// `BRK #0`
+const A64_BRK: &[u8] = &[0x00, 0x00, 0x20, 0xD4];
+/// `ebreak`
+const RISCV_EBREAK: &[u8] = &[0x73, 0x00, 0x10, 0x00];
+/// `c.ebreak`
+const RISCV_C_EBREAK: &[u8] = &[0x02, 0x90];
+/// `BREAK 1, 15`
+const XTENSA_BREAK: &[u8] = &[0xF0, 0x41, 0x00];
+/// `BREAK.N 1`
+const XTENSA_BREAK_N: &[u8] = &[0x2D, 0xF1];
+
+/// Returns the break instruction that replaces the instruction starting with `original`.
+///
+/// On architectures with variable length instructions, the break instruction is chosen to
+/// be no longer than the instruction it replaces, so that the following instruction stays
+/// intact.
+pub(crate) fn break_instruction(instruction_set: InstructionSet, original: &[u8]) -> &'static [u8] {
+    match instruction_set {
+        InstructionSet::Thumb2 => THUMB_BKPT,
+        InstructionSet::A32 => A32_BKPT,
+        InstructionSet::A64 => A64_BRK,
+        InstructionSet::RV32 | InstructionSet::RV64 => RISCV_EBREAK,
+        // The two lowest bits are `0b11` for all 32-bit instructions.
+        InstructionSet::RV32C | InstructionSet::RV64C if original[0] & 0b11 != 0b11 => {
+            RISCV_C_EBREAK
+        }
+        InstructionSet::RV32C | InstructionSet::RV64C => RISCV_EBREAK,
+        // Narrow (16-bit) instructions use op0 values 8 to 15.
+        InstructionSet::Xtensa if original[0] & 0x0F >= 8 => XTENSA_BREAK_N,
+        InstructionSet::Xtensa => XTENSA_BREAK,
+    }
+}
+
+/// The number of bytes needed to decide which break instruction to use.
+pub(crate) fn longest_break_instruction(instruction_set: InstructionSet) -> usize {
+    match instruction_set {
+        InstructionSet::Thumb2 => THUMB_BKPT.len(),
+        InstructionSet::Xtensa => XTENSA_BREAK.len(),
+        _ => 4,
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PatchedInstruction {
+    address: u64,
+    /// The cores that execute from the patched memory.
+    cores: Vec<usize>,
+    /// The instruction bytes that were replaced.
+    original: Vec<u8>,
+    /// The break instruction written in their place.
+    breakpoint: &'static [u8],
+}
+
+impl PatchedInstruction {
+    fn is_visible_to(&self, core: usize, address: u64) -> bool {
+        self.address == address && self.cores.contains(&core)
+    }
+
+    /// The offsets into the patched instruction and into an access of `len` bytes at `address`
+    /// of the bytes they have in common.
+    fn overlap(&self, address: u64, len: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.original.len()).filter_map(move |offset| {
+            let index = (self.address + offset as u64).checked_sub(address)?;
+            let index = usize::try_from(index).ok().filter(|&index| index < len)?;
+            Some((offset, index))
+        })
+    }
+}
+
+/// Bookkeeping for the software breakpoints of a session.
+///
+/// A break instruction patched into memory shared by several cores is hit by all of them, so
+/// each breakpoint records the cores it applies to, and any of them can step over it.
+#[derive(Debug, Default)]
+pub struct SoftwareBreakpoints {
+    patched: Vec<PatchedInstruction>,
+}
+
+impl SoftwareBreakpoints {
+    /// Returns `true` if no software breakpoints are set on any core.
+    pub fn is_empty(&self) -> bool {
+        self.patched.is_empty()
+    }
+
+    /// Returns `true` if a software breakpoint is set at `address` for `core`.
+    pub fn contains(&self, core: usize, address: u64) -> bool {
+        self.get(core, address).is_some()
+    }
+
+    /// The addresses of all software breakpoints of `core`, in ascending order.
+    pub fn addresses(&self, core: usize) -> impl Iterator<Item = u64> + '_ {
+        let mut addresses = self
+            .patched
+            .iter()
+            .filter(|patched| patched.cores.contains(&core))
+            .map(|patched| patched.address)
+            .collect::<Vec<_>>();
+        addresses.sort_unstable();
+        addresses.into_iter()
+    }
+
+    fn get(&self, core: usize, address: u64) -> Option<&PatchedInstruction> {
+        self.patched
+            .iter()
+            .find(|patched| patched.is_visible_to(core, address))
+    }
+
+    /// Returns `true` if an access of `len` bytes at `address` by `core` overlaps any of its
+    /// break instructions.
+    pub(crate) fn overlaps(&self, core: usize, address: u64, len: usize) -> bool {
+        self.patched.iter().any(|patched| {
+            patched.cores.contains(&core) && patched.overlap(address, len).next().is_some()
+        })
+    }
+
+    /// Replaces the break instructions of `core` in `data`, which was read from `address`, with
+    /// the original instructions.
+    pub(crate) fn hide(&self, core: usize, address: u64, data: &mut [u8]) {
+        for patched in self.patched.iter().filter(|p| p.cores.contains(&core)) {
+            for (offset, index) in patched.overlap(address, data.len()) {
+                data[index] = patched.original[offset];
+            }
+        }
+    }
+
+    /// Saves the bytes of `data`, which is about to be written to `address`, that overlap the
+    /// break instructions of `core` as the original instructions, and replaces them with the
+    /// break instructions so that the breakpoints stay in place.
+    pub(crate) fn keep(&mut self, core: usize, address: u64, data: &mut [u8]) {
+        for patched in self.patched.iter_mut().filter(|p| p.cores.contains(&core)) {
+            let overlap = patched.overlap(address, data.len()).collect::<Vec<_>>();
+            for (offset, index) in overlap {
+                patched.original[offset] = data[index];
+                data[index] = patched.breakpoint[offset];
+            }
+        }
+    }
+
+    /// Patches a break instruction into memory at `address`, saving the original instruction.
+    ///
+    /// `cores` are the cores that execute from the memory at `address`, the first of them is
+    /// the one `memory` belongs to.
+    ///
+    /// The patch is read back, so memory that silently ignores writes (e.g. flash or ROM) is
+    /// detected and left untouched.
+    pub(crate) fn insert<M: MemoryInterface + ?Sized>(
+        &mut self,
+        memory: &mut M,
+        instruction_set: InstructionSet,
+        address: u64,
+        cores: Vec<usize>,
+    ) -> Result<(), Error> {
+        if self.contains(cores[0], address) {
+            return Ok(());
+        }
+
+        let mut original = vec![0; longest_break_instruction(instruction_set)];
+        memory.read_8(address, &mut original)?;
+        let breakpoint = break_instruction(instruction_set, &original);
+        original.truncate(breakpoint.len());
+
+        memory.write_8(address, breakpoint)?;
+        if read_back(memory, address, breakpoint.len())? != breakpoint {
+            memory.write_8(address, &original)?;
+            return Err(Error::BreakpointOperation(
+                BreakpointError::SoftwareBreakpointNotWritable(address),
+            ));
+        }
+
+        self.patched.push(PatchedInstruction {
+            address,
+            cores,
+            original,
+            breakpoint,
+        });
+
+        Ok(())
+    }
+
+    /// Restores the original instruction at `address`, for all cores that share it.
+    pub(crate) fn remove<M: MemoryInterface + ?Sized>(
+        &mut self,
+        memory: &mut M,
+        core: usize,
+        address: u64,
+    ) -> Result<(), Error> {
+        let Some(index) = self
+            .patched
+            .iter()
+            .position(|patched| patched.is_visible_to(core, address))
+        else {
+            return Err(Error::BreakpointOperation(BreakpointError::NotFound(
+                address,
+            )));
+        };
+
+        let patched = self.patched.remove(index);
+        memory.write_8(address, &patched.original)
+    }
+
+    /// Restores the original instructions of all software breakpoints of `core`.
+    pub(crate) fn remove_all<M: MemoryInterface + ?Sized>(
+        &mut self,
+        memory: &mut M,
+        core: usize,
+    ) -> Result<(), Error> {
+        while let Some(index) = self
+            .patched
+            .iter()
+            .position(|patched| patched.cores.contains(&core))
+        {
+            let patched = self.patched.remove(index);
+            memory.write_8(patched.address, &patched.original)?;
+        }
+
+        Ok(())
+    }
+
+    /// Temporarily restores the original instruction at `address` while running `f`,
+    /// e.g. to single step `core` over the breakpoint.
+    pub(crate) fn with_original<M: MemoryInterface + ?Sized, T>(
+        &mut self,
+        memory: &mut M,
+        core: usize,
+        address: u64,
+        f: impl FnOnce(&mut M) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let Some(patched) = self.get(core, address) else {
+            return f(memory);
+        };
+
+        memory.write_8(address, &patched.original)?;
+        let result = f(memory);
+        memory.write_8(address, patched.breakpoint)?;
+
+        result
+    }
+
+    /// Checks that all break instructions of `core` are still in place.
+    ///
+    /// If the memory at a breakpoint was overwritten since it was set (for example because a
+    /// new image was loaded into RAM), the new contents are saved as the original
+    /// instruction and the breakpoint is patched in again.
+    pub(crate) fn ensure_coherent<M: MemoryInterface + ?Sized>(
+        &mut self,
+        memory: &mut M,
+        core: usize,
+        instruction_set: InstructionSet,
+    ) -> Result<(), Error> {
+        for patched in self
+            .patched
+            .iter_mut()
+            .filter(|patched| patched.cores.contains(&core))
+        {
+            let address = patched.address;
+            let current = read_back(memory, address, patched.breakpoint.len())?;
+            if current == patched.breakpoint {
+                continue;
+            }
+
+            tracing::debug!(
+                "Software breakpoint at {address:#010x} was overwritten, patching it again"
+            );
+
+            let mut original = vec![0; longest_break_instruction(instruction_set)];
+            memory.read_8(address, &mut original)?;
+            let breakpoint = break_instruction(instruction_set, &original);
+            original.truncate(breakpoint.len());
+
+            memory.write_8(address, breakpoint)?;
+            patched.original = original;
+            patched.breakpoint = breakpoint;
+        }
+
+        Ok(())
+    }
+}
+
+fn read_back<M: MemoryInterface + ?Sized>(
+    memory: &mut M,
+    address: u64,
+    len: usize,
+) -> Result<Vec<u8>, Error> {
+    let mut data = vec![0; len];
+    memory.read_8(address, &mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::MockMemory;
+
+    #[test]
+    fn break_instruction_matches_replaced_length() {
+        // `addi sp, sp, -16` (32-bit) and `c.addi sp, -16` (16-bit)
+        assert_eq!(
+            break_instruction(InstructionSet::RV32C, &[0x13, 0x01, 0x01, 0xFF]),
+            RISCV_EBREAK
+        );
+        assert_eq!(
+            break_instruction(InstructionSet::RV32C, &[0x41, 0x11, 0x00, 0x00]),
+            RISCV_C_EBREAK
+        );
+
+        // `entry a1, 32` (24-bit) and `retw.n` (16-bit)
+        assert_eq!(
+            break_instruction(InstructionSet::Xtensa, &[0x36, 0x41, 0x00]),
+            XTENSA_BREAK
+        );
+        assert_eq!(
+            break_instruction(InstructionSet::Xtensa, &[0x1D, 0xF0, 0x00]),
+            XTENSA_BREAK_N
+        );
+    }
+
+    #[test]
+    fn insert_and_remove_restore_original() {
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, vec![0x80, 0xB5, 0x00, 0xAF]);
+
+        let mut breakpoints = SoftwareBreakpoints::default();
+        breakpoints
+            .insert(&mut memory, InstructionSet::Thumb2, 0x2000_0002, vec![0, 1])
+            .unwrap();
+        // The breakpoint is shared by the cores that run from the patched memory.
+        assert!(breakpoints.contains(0, 0x2000_0002));
+        assert!(breakpoints.contains(1, 0x2000_0002));
+        assert!(!breakpoints.contains(2, 0x2000_0002));
+        assert_eq!(
+            read_back(&mut memory, 0x2000_0000, 4).unwrap(),
+            [0x80, 0xB5, 0x00, 0xBE]
+        );
+
+        breakpoints
+            .with_original(&mut memory, 1, 0x2000_0002, |memory| {
+                assert_eq!(
+                    read_back(memory, 0x2000_0000, 4).unwrap(),
+                    [0x80, 0xB5, 0x00, 0xAF]
+                );
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(read_back(&mut memory, 0x2000_0002, 2).unwrap(), THUMB_BKPT);
+
+        breakpoints.remove(&mut memory, 1, 0x2000_0002).unwrap();
+        assert!(breakpoints.is_empty());
+        assert_eq!(
+            read_back(&mut memory, 0x2000_0000, 4).unwrap(),
+            [0x80, 0xB5, 0x00, 0xAF]
+        );
+    }
+
+    #[test]
+    fn overwritten_breakpoint_is_patched_again() {
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, vec![0x13, 0x01, 0x01, 0xFF]);
+
+        let mut breakpoints = SoftwareBreakpoints::default();
+        breakpoints
+            .insert(&mut memory, InstructionSet::RV32C, 0x2000_0000, vec![0])
+            .unwrap();
+
+        // A new image is loaded over the breakpoint.
+        memory
+            .write_8(0x2000_0000, &[0x41, 0x11, 0x06, 0xC6])
+            .unwrap();
+        breakpoints
+            .ensure_coherent(&mut memory, 0, InstructionSet::RV32C)
+            .unwrap();
+        assert_eq!(
+            read_back(&mut memory, 0x2000_0000, 4).unwrap(),
+            [0x02, 0x90, 0x06, 0xC6]
+        );
+
+        breakpoints.remove_all(&mut memory, 0).unwrap();
+        assert_eq!(
+            read_back(&mut memory, 0x2000_0000, 4).unwrap(),
+            [0x41, 0x11, 0x06, 0xC6]
+        );
+    }
+
+    #[test]
+    fn accesses_see_the_original_instruction() {
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, vec![0x80, 0xB5, 0x00, 0xAF]);
+
+        let mut breakpoints = SoftwareBreakpoints::default();
+        breakpoints
+            .insert(&mut memory, InstructionSet::Thumb2, 0x2000_0002, vec![0])
+            .unwrap();
+
+        let mut data = read_back(&mut memory, 0x2000_0001, 3).unwrap();
+        assert!(breakpoints.overlaps(0, 0x2000_0001, 3));
+        assert!(!breakpoints.overlaps(1, 0x2000_0001, 3));
+        assert!(!breakpoints.overlaps(0, 0x2000_0000, 2));
+        breakpoints.hide(0, 0x2000_0001, &mut data);
+        assert_eq!(data, [0xB5, 0x00, 0xAF]);
+
+        // A write over the breakpoint keeps it, and replaces the original instruction.
+        let mut data = [0x01, 0x02, 0x03];
+        breakpoints.keep(0, 0x2000_0000, &mut data);
+        assert_eq!(data, [0x01, 0x02, 0x00]);
+        memory.write_8(0x2000_0000, &data).unwrap();
+
+        breakpoints.remove(&mut memory, 0, 0x2000_0002).unwrap();
+        assert_eq!(
+            read_back(&mut memory, 0x2000_0000, 4).unwrap(),
+            [0x01, 0x02, 0x03, 0xAF]
+        );
+    }
+}
@@ -0,0 +1,90 @@
+//! Types describing hardware data watchpoints.
+
+use crate::error::{BreakpointError, Error};
+
+/// The kind of memory access a watchpoint triggers on.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum WatchpointKind {
+    /// Halt when the watched memory is read.
+    Read,
+    /// Halt when the watched memory is written.
+    Write,
+    /// Halt on any access to the watched memory.
+    Access,
+}
+
+impl WatchpointKind {
+    /// Returns `true` if this kind triggers on loads.
+    pub fn on_read(&self) -> bool {
+        matches!(self, WatchpointKind::Read | WatchpointKind::Access)
+    }
+
+    /// Returns `true` if this kind triggers on stores.
+    pub fn on_write(&self) -> bool {
+        matches!(self, WatchpointKind::Write | WatchpointKind::Access)
+    }
+}
+
+/// A hardware data watchpoint.
+///
+/// A watchpoint covers a naturally aligned, power-of-two sized region of memory starting
+/// at `address`. Hardware compares addresses by masking off the low bits, which is why
+/// arbitrary ranges cannot be expressed with a single comparator.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub struct Watchpoint {
+    /// The first address covered by the watchpoint.
+    pub address: u64,
+    /// The number of bytes covered by the watchpoint.
+    pub size: u64,
+    /// The kind of access the watchpoint triggers on.
+    pub kind: WatchpointKind,
+}
+
+impl Watchpoint {
+    /// Creates a new watchpoint, checking that `size` is a power of two and that `address`
+    /// is aligned to it.
+    pub fn new(address: u64, size: u64, kind: WatchpointKind) -> Result<Self, Error> {
+        if !size.is_power_of_two() || address & (size - 1) != 0 {
+            return Err(Error::BreakpointOperation(
+                BreakpointError::InvalidWatchpoint { address, size },
+            ));
+        }
+
+        Ok(Self {
+            address,
+            size,
+            kind,
+        })
+    }
+
+    /// The number of low address bits ignored by the comparator.
+    pub fn mask_bits(&self) -> u32 {
+        self.size.trailing_zeros()
+    }
+
+    /// Returns `true` if `address` falls into the watched region.
+    pub fn contains(&self, address: u64) -> bool {
+        address >= self.address && address - self.address < self.size
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn watchpoint_size_must_be_power_of_two() {
+        Watchpoint::new(0x2000_0000, 3, WatchpointKind::Write).unwrap_err();
+        Watchpoint::new(0x2000_0000, 0, WatchpointKind::Write).unwrap_err();
+    }
+
+    #[test]
+    fn watchpoint_must_be_aligned() {
+        Watchpoint::new(0x2000_0002, 4, WatchpointKind::Read).unwrap_err();
+
+        let wp = Watchpoint::new(0x2000_0010, 16, WatchpointKind::Access).unwrap();
+        assert_eq!(wp.mask_bits(), 4);
+        assert!(wp.contains(0x2000_001F));
+        assert!(!wp.contains(0x2000_0020));
+    }
+}
@@ -81,6 +81,25 @@ pub enum Error {
 pub enum BreakpointError {
     /// No breakpoint found at address {0:#010x}
     NotFound(u64),
+    /// No watchpoint found at address {0:#010x}
+    WatchpointNotFound(u64),
+    /// A watchpoint of {size} bytes at address {address:#010x} is not naturally aligned to a power of two
+    InvalidWatchpoint {
+        /// The requested start address.
+        address: u64,
+        /// The requested size in bytes.
+        size: u64,
+    },
+    /// No available hardware watchpoints
+    NoFreeWatchpoint,
+    /// Watchpoints covering {0} bytes are not supported by this core
+    UnsupportedWatchpointSize(u64),
+    /// Software breakpoints can only be set in RAM, but address {0:#010x} is not in a RAM region
+    SoftwareBreakpointNotInRam(u64),
+    /// The break instruction could not be written to address {0:#010x}
+    SoftwareBreakpointNotWritable(u64),
+    /// Flash breakpoints can only be set in flash, but address {0:#010x} is not in a flash sector
+    FlashBreakpointNotInFlash(u64),
 }
 
 impl From<ArmError> for Error {
@@ -0,0 +1,184 @@
+//! Flash breakpoints, implemented by reprogramming flash sectors with break instructions.
+//!
+//! Code in flash can't be patched with a plain memory write like the
+//! [software breakpoints](crate::core::SoftwareBreakpoints) in RAM. Instead, the sector containing
+//! the breakpoint is reprogrammed through the flash algorithm, with the break instruction patched
+//! into the original sector contents. Reprogramming is slow, so changes are batched until the core
+//! is resumed.
+
+use std::collections::BTreeMap;
+
+/// Bookkeeping for the flash breakpoints of a session.
+#[derive(Debug, Default)]
+pub struct FlashBreakpoints {
+    /// The original contents of the sectors containing flash breakpoints, by base address.
+    sectors: BTreeMap<u64, Vec<u8>>,
+    /// The requested breakpoints, and the break instructions replacing the original code.
+    requested: BTreeMap<u64, &'static [u8]>,
+    /// The breakpoints currently programmed into flash.
+    programmed: BTreeMap<u64, &'static [u8]>,
+}
+
+impl FlashBreakpoints {
+    /// Returns `true` if no flash breakpoints are set or programmed.
+    pub fn is_empty(&self) -> bool {
+        self.requested.is_empty() && self.programmed.is_empty()
+    }
+
+    /// Returns `true` if a flash breakpoint is set at `address`.
+    pub fn contains(&self, address: u64) -> bool {
+        self.requested.contains_key(&address)
+    }
+
+    /// Returns `true` if a break instruction is currently programmed at `address`.
+    pub fn is_programmed(&self, address: u64) -> bool {
+        self.programmed.contains_key(&address)
+    }
+
+    /// The addresses of all flash breakpoints, in ascending order.
+    pub fn addresses(&self) -> impl Iterator<Item = u64> + '_ {
+        self.requested.keys().copied()
+    }
+
+    /// Returns `true` if breakpoints were set or cleared since flash was last programmed.
+    pub fn has_pending_changes(&self) -> bool {
+        self.requested != self.programmed
+    }
+
+    /// Returns `true` if the original contents of the sector at `base_address` are cached.
+    pub(crate) fn has_sector(&self, base_address: u64) -> bool {
+        self.sectors.contains_key(&base_address)
+    }
+
+    /// Caches the original contents of a sector, before any breakpoint is programmed into it.
+    pub(crate) fn add_sector(&mut self, base_address: u64, original: Vec<u8>) {
+        self.sectors.entry(base_address).or_insert(original);
+    }
+
+    /// Returns the original bytes at `address`, if they are part of a cached sector.
+    pub(crate) fn original(&self, address: u64, len: usize) -> Option<&[u8]> {
+        let (base, data) = self.sectors.range(..=address).next_back()?;
+        let offset = (address - base) as usize;
+
+        data.get(offset..offset + len)
+    }
+
+    pub(crate) fn insert(&mut self, address: u64, breakpoint: &'static [u8]) {
+        self.requested.insert(address, breakpoint);
+    }
+
+    pub(crate) fn remove(&mut self, address: u64) -> bool {
+        self.requested.remove(&address).is_some()
+    }
+
+    pub(crate) fn remove_all(&mut self) {
+        self.requested.clear();
+    }
+
+    /// Returns the new contents of every sector that has to be reprogrammed, so that exactly the
+    /// requested breakpoints except `skip` are programmed.
+    pub(crate) fn sector_images(&self, skip: Option<u64>) -> Vec<(u64, Vec<u8>)> {
+        let wanted = self.wanted(skip);
+
+        self.sectors
+            .iter()
+            .filter_map(|(&base, original)| {
+                let range = base..base + original.len() as u64;
+                let in_sector = |breakpoints: &BTreeMap<u64, &'static [u8]>| {
+                    breakpoints
+                        .range(range.clone())
+                        .map(|(address, breakpoint)| (*address, *breakpoint))
+                        .collect::<Vec<_>>()
+                };
+
+                let patches = in_sector(&wanted);
+                if patches == in_sector(&self.programmed) {
+                    return None;
+                }
+
+                let mut image = original.clone();
+                for (address, breakpoint) in patches {
+                    let offset = (address - base) as usize;
+                    image[offset..offset + breakpoint.len()].copy_from_slice(breakpoint);
+                }
+
+                Some((base, image))
+            })
+            .collect()
+    }
+
+    /// Records that the images returned by [`Self::sector_images`] were programmed.
+    ///
+    /// Sectors without any breakpoints left are restored at this point, so their cached contents
+    /// are dropped.
+    pub(crate) fn mark_programmed(&mut self, skip: Option<u64>) {
+        self.programmed = self.wanted(skip);
+
+        let requested = &self.requested;
+        let programmed = &self.programmed;
+        self.sectors.retain(|&base, original| {
+            let range = base..base + original.len() as u64;
+            requested.range(range.clone()).next().is_some()
+                || programmed.range(range).next().is_some()
+        });
+    }
+
+    fn wanted(&self, skip: Option<u64>) -> BTreeMap<u64, &'static [u8]> {
+        let mut wanted = self.requested.clone();
+        if let Some(skip) = skip {
+            wanted.remove(&skip);
+        }
+        wanted
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BKPT: &[u8] = &[0x00, 0xBE];
+
+    #[test]
+    fn only_changed_sectors_are_reprogrammed() {
+        let mut breakpoints = FlashBreakpoints::default();
+        breakpoints.add_sector(0x0000, vec![0xAA; 0x400]);
+        breakpoints.add_sector(0x0400, vec![0x55; 0x400]);
+        breakpoints.insert(0x0010, BKPT);
+        breakpoints.insert(0x0402, BKPT);
+        assert!(breakpoints.has_pending_changes());
+
+        let images = breakpoints.sector_images(None);
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].1[0x10..0x12], *BKPT);
+        assert_eq!(images[1].1[0x02..0x04], *BKPT);
+        breakpoints.mark_programmed(None);
+        assert!(!breakpoints.has_pending_changes());
+        assert!(breakpoints.sector_images(None).is_empty());
+
+        // Removing one breakpoint only touches its own sector, and restores the original.
+        assert!(breakpoints.remove(0x0402));
+        let images = breakpoints.sector_images(None);
+        assert_eq!(images, vec![(0x0400, vec![0x55; 0x400])]);
+        breakpoints.mark_programmed(None);
+        assert!(!breakpoints.has_sector(0x0400));
+        assert_eq!(breakpoints.original(0x0010, 2), Some(&[0xAA, 0xAA][..]));
+    }
+
+    #[test]
+    fn skipped_breakpoint_is_restored_temporarily() {
+        let mut breakpoints = FlashBreakpoints::default();
+        breakpoints.add_sector(0x0000, vec![0xAA; 0x400]);
+        breakpoints.insert(0x0010, BKPT);
+        breakpoints.mark_programmed(None);
+
+        // Stepping over the breakpoint restores the original instruction...
+        let images = breakpoints.sector_images(Some(0x0010));
+        assert_eq!(images, vec![(0x0000, vec![0xAA; 0x400])]);
+        breakpoints.mark_programmed(Some(0x0010));
+        assert!(breakpoints.contains(0x0010));
+        assert!(!breakpoints.is_programmed(0x0010));
+
+        // ...and it is programmed again on the next resume.
+        assert_eq!(breakpoints.sector_images(None).len(), 1);
+    }
+}
@@ -138,6 +138,12 @@ pub struct DownloadOptions<'p> {
     pub preferred_algos: Vec<String>,
     /// RAM chunk size relevant for loading into RAM. [None] disables the chunking.
     pub ram_chunk_size: Option<u64>,
+    /// Halt the cores instead of resetting them before running the flash algorithm, and restore
+    /// the core registers and the RAM used by the algorithm afterwards.
+    ///
+    /// This allows reprogramming flash while an application is being debugged, e.g. to patch
+    /// in flash breakpoints.
+    pub preserve_target_state: bool,
 }
 
 impl DownloadOptions<'_> {
@@ -6,6 +6,7 @@ use probe_rs_target::{
 };
 use std::collections::BTreeMap;
 use std::mem::size_of_val;
+use std::ops::Range;
 
 /// A flash algorithm, which has been assembled for a specific
 /// chip.
@@ -77,29 +78,7 @@ impl FlashAlgorithm {
     /// If the `address` is not part of the flash, None will
     /// be returned.
     pub fn sector_info(&self, address: u64) -> Option<SectorInfo> {
-        if !self.flash_properties.address_range.contains(&address) {
-            tracing::trace!("Address {:08x} not contained in this flash device", address);
-            return None;
-        }
-
-        let offset_address = address - self.flash_properties.address_range.start;
-
-        let containing_sector = self
-            .flash_properties
-            .sectors
-            .iter()
-            .rfind(|s| s.address <= offset_address)?;
-
-        let sector_index = (offset_address - containing_sector.address) / containing_sector.size;
-
-        let sector_address = self.flash_properties.address_range.start
-            + containing_sector.address
-            + sector_index * containing_sector.size;
-
-        Some(SectorInfo {
-            base_address: sector_address,
-            size: containing_sector.size,
-        })
+        sector_info(&self.flash_properties, address)
     }
 
     /// Returns the necessary information about the page which `address` resides in
@@ -167,6 +146,34 @@ impl FlashAlgorithm {
         })
     }
 
+    /// Returns the RAM ranges the algorithm occupies while it runs: code, data, stack and
+    /// page buffers.
+    pub(super) fn ram_ranges(&self) -> Vec<Range<u64>> {
+        let code_end = self.load_address + size_of_val(self.instructions.as_slice()) as u64;
+        let page_size = self.flash_properties.page_size as u64;
+
+        let mut ranges = vec![
+            self.load_address..code_end,
+            self.stack_top - self.stack_size..self.stack_top,
+        ];
+        ranges.extend(
+            self.page_buffers
+                .iter()
+                .map(|&buffer| buffer..buffer + page_size),
+        );
+        ranges.sort_by_key(|range| range.start);
+
+        let mut merged: Vec<Range<u64>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+
+        merged
+    }
+
     /// Returns true if the entire contents of the argument array equal the erased byte value.
     pub fn is_erased(&self, data: &[u8]) -> bool {
         for b in data {
@@ -504,6 +511,33 @@ impl FlashAlgorithm {
     }
 }
 
+/// Returns the flash sector which contains `address`, if the address is part of the flash
+/// described by `properties`.
+pub(crate) fn sector_info(properties: &FlashProperties, address: u64) -> Option<SectorInfo> {
+    if !properties.address_range.contains(&address) {
+        tracing::trace!("Address {:08x} not contained in this flash device", address);
+        return None;
+    }
+
+    let offset_address = address - properties.address_range.start;
+
+    let containing_sector = properties
+        .sectors
+        .iter()
+        .rfind(|s| s.address <= offset_address)?;
+
+    let sector_index = (offset_address - containing_sector.address) / containing_sector.size;
+
+    let sector_address = properties.address_range.start
+        + containing_sector.address
+        + sector_index * containing_sector.size;
+
+    Some(SectorInfo {
+        base_address: sector_address,
+        size: containing_sector.size,
+    })
+}
+
 /// Returns whether the given RAM region is usable for downloading the flash algorithm.
 fn is_ram_suitable_for_algo(ram: &RamRegion, load_address: Option<u64>) -> bool {
     if !ram.is_executable() {
@@ -569,6 +603,27 @@ mod test {
         assert_eq!(Some(expected_first), config.sector_info(0x10ea));
     }
 
+    #[test]
+    fn ram_ranges_merge_adjacent_blocks() {
+        let config = FlashAlgorithm {
+            load_address: 0x2000_0000,
+            instructions: vec![0; 0x40],
+            stack_top: 0x2000_0200,
+            stack_size: 0x100,
+            page_buffers: vec![0x2000_1000, 0x2000_1100],
+            flash_properties: FlashProperties {
+                page_size: 0x100,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            config.ram_ranges(),
+            vec![0x2000_0000..0x2000_0200, 0x2000_1000..0x2000_1200]
+        );
+    }
+
     #[test]
     fn flash_sector_single_size_weird_sector_size() {
         let config = FlashAlgorithm {
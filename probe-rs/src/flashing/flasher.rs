@@ -9,7 +9,9 @@ use crate::flashing::encoder::FlashEncoder;
 use crate::flashing::{FlashLayout, FlashSector};
 use crate::memory::MemoryInterface;
 use crate::rtt::{Rtt, ScanRegion};
-use crate::{Core, InstructionSet, RegisterValue, core::CoreRegisters, session::Session};
+use crate::{
+    Core, InstructionSet, RegisterId, RegisterValue, core::CoreRegisters, session::Session,
+};
 use crate::{CoreStatus, Target};
 use std::borrow::Cow;
 use std::marker::PhantomData;
@@ -145,6 +147,14 @@ pub struct Flasher {
     pub(super) loaded: bool,
     pub(super) regions: Vec<LoadedRegion>,
     pub(super) read_flasher_rtt: bool,
+    /// Halt instead of resetting the core before loading the algorithm.
+    pub(super) preserve_state: bool,
+}
+
+/// The registers and algorithm RAM of a core, saved before running a flash algorithm on it.
+pub(super) struct SavedTargetState {
+    registers: Vec<(RegisterId, RegisterValue)>,
+    ram: Vec<(u64, Vec<u8>)>,
 }
 
 /// The byte used to fill the stack when checking for stack overflows.
@@ -169,6 +179,7 @@ impl Flasher {
             loaded: false,
             regions: Vec::new(),
             read_flasher_rtt: false,
+            preserve_state: false,
         })
     }
 
@@ -196,10 +207,16 @@ impl Flasher {
         // Attach to memory and core.
         let mut core = session.core(self.core_index).map_err(FlashError::Core)?;
 
-        // TODO: we probably want a full system reset here to make sure peripherals don't interfere.
-        tracing::debug!("Reset and halt core {}", self.core_index);
-        core.reset_and_halt(Duration::from_millis(500))
-            .map_err(FlashError::ResetAndHalt)?;
+        if self.preserve_state {
+            tracing::debug!("Halt core {}", self.core_index);
+            core.halt(Duration::from_millis(500))
+                .map_err(FlashError::Core)?;
+        } else {
+            // TODO: we probably want a full system reset here to make sure peripherals don't interfere.
+            tracing::debug!("Reset and halt core {}", self.core_index);
+            core.reset_and_halt(Duration::from_millis(500))
+                .map_err(FlashError::ResetAndHalt)?;
+        }
 
         // TODO: Possible special preparation of the target such as enabling faster clocks for the flash e.g.
 
@@ -259,6 +276,60 @@ impl Flasher {
         Ok(())
     }
 
+    /// Halts the core and saves its registers and the RAM the flash algorithm will overwrite.
+    ///
+    /// The core is only halted, not reset, when the algorithm is loaded afterwards.
+    pub(super) fn save_target_state(
+        &mut self,
+        session: &mut Session,
+    ) -> Result<SavedTargetState, FlashError> {
+        self.preserve_state = true;
+
+        let mut core = session.core(self.core_index).map_err(FlashError::Core)?;
+        core.halt(Duration::from_millis(500))
+            .map_err(FlashError::Core)?;
+
+        let mut registers = Vec::new();
+        for register in core.registers().all_registers() {
+            let value = core
+                .read_core_reg(register.id())
+                .map_err(FlashError::Core)?;
+            registers.push((register.id(), value));
+        }
+
+        let mut ram = Vec::new();
+        for range in self.flash_algorithm.ram_ranges() {
+            let mut data = vec![0; (range.end - range.start) as usize];
+            core.read(range.start, &mut data)
+                .map_err(FlashError::Core)?;
+            ram.push((range.start, data));
+        }
+
+        Ok(SavedTargetState { registers, ram })
+    }
+
+    /// Restores the state saved by [`Self::save_target_state`].
+    pub(super) fn restore_target_state(
+        &mut self,
+        session: &mut Session,
+        state: SavedTargetState,
+    ) -> Result<(), FlashError> {
+        let mut core = session.core(self.core_index).map_err(FlashError::Core)?;
+
+        for (address, data) in state.ram {
+            core.write(address, &data).map_err(FlashError::Core)?;
+        }
+        for (register, value) in state.registers {
+            core.write_core_reg(register, value)
+                .map_err(FlashError::Core)?;
+        }
+
+        // The algorithm has to be loaded again before it can be used.
+        self.loaded = false;
+
+        Ok(())
+    }
+
     /// Prepares the flashing algorithm.
     ///
     /// This function ensures that the flashing algorithm has been loaded into memory, and
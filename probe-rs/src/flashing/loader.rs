@@ -695,12 +695,11 @@ impl FlashLoader {
         for mut flasher in algos {
             tracing::debug!("Flashing ranges for algo: {}", flasher.flash_algorithm.name);
 
-            if do_chip_erase {
-                tracing::debug!("    Doing chip erase...");
-                flasher.run_erase_all(session, &mut options.progress)?;
-                do_chip_erase = false;
-                did_chip_erase = true;
-            }
+            let saved_state = if options.preserve_target_state {
+                Some(flasher.save_target_state(session)?)
+            } else {
+                None
+            };
 
             let mut do_use_double_buffering = flasher.double_buffering_supported();
             if do_use_double_buffering && options.disable_double_buffering {
@@ -710,15 +709,34 @@ impl FlashLoader {
                 do_use_double_buffering = false;
             }
 
-            // Program the data.
-            flasher.program(
-                session,
-                &mut options.progress,
-                options.keep_unwritten_bytes,
-                do_use_double_buffering,
-                options.skip_erase || did_chip_erase,
-                options.verify,
-            )?;
+            let result = (|| {
+                if do_chip_erase {
+                    tracing::debug!("    Doing chip erase...");
+                    flasher.run_erase_all(session, &mut options.progress)?;
+                    do_chip_erase = false;
+                    did_chip_erase = true;
+                }
+
+                // Program the data.
+                flasher.program(
+                    session,
+                    &mut options.progress,
+                    options.keep_unwritten_bytes,
+                    do_use_double_buffering,
+                    options.skip_erase || did_chip_erase,
+                    options.verify,
+                )
+            })();
+
+            // Restore the state of the core even if flashing failed, so that it can still be
+            // debugged. The error of flashing takes precedence.
+            if let Some(saved_state) = saved_state {
+                let restored = flasher.restore_target_state(session, saved_state);
+                result?;
+                restored?;
+            } else {
+                result?;
+            }
         }
 
         tracing::debug!("Committing RAM!");
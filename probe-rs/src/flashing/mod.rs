@@ -42,6 +42,7 @@
 //! # Ok::<(), anyhow::Error>(())
 //! ```
 
+mod breakpoints;
 mod builder;
 mod download;
 mod encoder;
@@ -54,6 +55,7 @@ mod progress;
 
 use builder::*;
 
+pub use breakpoints::FlashBreakpoints;
 pub use builder::{FlashDataBlockSpan, FlashFill, FlashLayout, FlashPage, FlashSector};
 pub use download::*;
 pub use erase::*;
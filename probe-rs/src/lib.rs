@@ -97,7 +97,8 @@ pub use crate::core::registers::UnwindRule;
 pub use crate::core::{
     Architecture, BreakpointCause, Core, CoreInformation, CoreInterface, CoreRegister,
     CoreRegisters, CoreState, CoreStatus, HaltReason, MemoryMappedRegister, RegisterId,
-    RegisterRole, RegisterValue, SpecificCoreState, VectorCatchCondition,
+    RegisterRole, RegisterValue, RunControlEvent, SpecificCoreState, VectorCatchCondition,
+    Watchpoint, WatchpointKind,
 };
 pub use crate::error::{BreakpointError, Error};
 pub use crate::memory::MemoryInterface;
@@ -1,10 +1,10 @@
 use crate::{
-    Core, CoreType, Error,
+    Core, CoreType, Error, InstructionSet, MemoryInterface,
     architecture::{
         arm::{
             ArmError, FullyQualifiedApAddress, SwoReader,
             communication_interface::ArmDebugInterface,
-            component::{TraceSink, get_arm_components},
+            component::{EtmConfig, EtmParameters, MtbBranch, TraceSink, get_arm_components},
             dp::DpAddress,
             memory::CoresightComponent,
             sequences::{ArmDebugSequence, DefaultArmSequence},
@@ -15,18 +15,25 @@ use crate::{
             },
             dtm::mem_ap_dtm::MemApDtm,
         },
-        xtensa::communication_interface::{
-            XtensaCommunicationInterface, XtensaDebugInterfaceState, XtensaError,
+        xtensa::{
+            communication_interface::{
+                XtensaCommunicationInterface, XtensaDebugInterfaceState, XtensaError,
+            },
+            trax::TraxConfig,
         },
     },
     config::{CoreExt, DebugSequence, RegistryError, Target, TargetSelector, registry::Registry},
-    core::{Architecture, CombinedCoreState},
+    core::{
+        Architecture, CombinedCoreState, RunControlEvent, RunControlGroups, SoftwareBreakpoints,
+    },
+    error::BreakpointError,
+    flashing::{DownloadOptions, FlashBreakpoints, FlashError},
     probe::{
         AttachMethod, DebugProbeError, Probe, ProbeCreationError, WireProtocol,
         fake_probe::FakeProbe, list::Lister,
     },
 };
-use std::ops::DerefMut;
+use std::ops::{DerefMut, Range};
 use std::{fmt, sync::Arc, time::Duration};
 
 /// The `Session` struct represents an active debug session.
@@ -53,6 +60,11 @@ pub struct Session {
     interfaces: ArchitectureInterface,
     cores: Vec<CombinedCoreState>,
     configured_trace_sink: Option<TraceSink>,
+    configured_etm_trace_id: Option<u8>,
+    configured_mtb_buffer: Option<Range<u64>>,
+    flash_breakpoints: FlashBreakpoints,
+    sw_breakpoints: SoftwareBreakpoints,
+    run_control_groups: RunControlGroups,
 }
 
 /// The `SessionConfig` struct is used to configure a new `Session` during auto-attach.
@@ -131,9 +143,12 @@ impl ArchitectureInterface {
         &'probe mut self,
         target: &'probe Target,
         combined_state: &'probe mut CombinedCoreState,
+        sw_breakpoints: &'probe mut SoftwareBreakpoints,
     ) -> Result<Core<'probe>, Error> {
         match self {
-            ArchitectureInterface::Arm(interface) => combined_state.attach_arm(target, interface),
+            ArchitectureInterface::Arm(interface) => {
+                combined_state.attach_arm(target, interface, sw_breakpoints)
+            }
             ArchitectureInterface::ArmWithRiscv {
                 arm,
                 riscv_mem_ap_cores,
@@ -144,9 +159,9 @@ impl ArchitectureInterface {
                     let dtm = MemApDtm::new(memory);
                     let iface =
                         RiscvCommunicationInterface::new(Box::new(dtm), &mut state.interface_state);
-                    combined_state.attach_riscv(target, iface)
+                    combined_state.attach_riscv(target, iface, sw_breakpoints)
                 } else {
-                    combined_state.attach_arm(target, arm)
+                    combined_state.attach_arm(target, arm, sw_breakpoints)
                 }
             }
             ArchitectureInterface::Jtag(probe, ifaces) => {
@@ -158,11 +173,11 @@ impl ArchitectureInterface {
                     JtagInterface::Riscv(state) => {
                         let factory = probe.try_get_riscv_interface_builder()?;
                         let iface = factory.attach_auto(target, state)?;
-                        combined_state.attach_riscv(target, iface)
+                        combined_state.attach_riscv(target, iface, sw_breakpoints)
                     }
                     JtagInterface::Xtensa(state) => {
                         let iface = probe.try_get_xtensa_interface(state)?;
-                        combined_state.attach_xtensa(target, iface)
+                        combined_state.attach_xtensa(target, iface, sw_breakpoints)
                     }
                     JtagInterface::Unknown => {
                         unreachable!(
@@ -333,6 +348,11 @@ impl Session {
                 interfaces,
                 cores,
                 configured_trace_sink: None,
+                configured_etm_trace_id: None,
+                configured_mtb_buffer: None,
+                flash_breakpoints: FlashBreakpoints::default(),
+                sw_breakpoints: SoftwareBreakpoints::default(),
+                run_control_groups: RunControlGroups::default(),
             };
 
             {
@@ -375,6 +395,11 @@ impl Session {
                 interfaces,
                 cores,
                 configured_trace_sink: None,
+                configured_etm_trace_id: None,
+                configured_mtb_buffer: None,
+                flash_breakpoints: FlashBreakpoints::default(),
+                sw_breakpoints: SoftwareBreakpoints::default(),
+                run_control_groups: RunControlGroups::default(),
             })
         }
     }
@@ -516,6 +541,11 @@ impl Session {
             interfaces,
             cores,
             configured_trace_sink: None,
+            configured_etm_trace_id: None,
+            configured_mtb_buffer: None,
+            flash_breakpoints: FlashBreakpoints::default(),
+            sw_breakpoints: SoftwareBreakpoints::default(),
+            run_control_groups: RunControlGroups::default(),
         };
 
         // Connect to the cores
@@ -653,7 +683,7 @@ impl Session {
             .ok_or(Error::CoreNotFound(core_index))?;
 
         self.interfaces
-            .attach(&self.target, combined_state)
+            .attach(&self.target, combined_state, &mut self.sw_breakpoints)
             .map_err(|e| {
                 if matches!(
                     e,
@@ -958,6 +988,111 @@ impl Session {
         Ok(())
     }
 
+    /// Configure the ETM to record the instruction trace of the core with `core_index` into
+    /// trace memory (ETB/ETF), which is used as a circular buffer.
+    ///
+    /// Returns the properties of the ETM that are needed to decode the trace, see
+    /// [`Etmv4Decoder`](crate::architecture::arm::trace::Etmv4Decoder).
+    ///
+    /// Only ETMv4 trace of T32 code can be decoded, so this fails if the ETM implements an older
+    /// architecture, or if the core executes A32 or A64 code.
+    pub fn setup_etm_tracing(
+        &mut self,
+        core_index: usize,
+        config: &EtmConfig,
+    ) -> Result<EtmParameters, Error> {
+        {
+            let mut core = self.core(core_index)?;
+            if core.instruction_set()? != InstructionSet::Thumb2 {
+                return Err(Error::NotImplemented(
+                    "ETM trace of cores executing A32 or A64 code",
+                ));
+            }
+            crate::architecture::arm::component::enable_tracing(&mut core)?;
+        }
+
+        let sequence_handle = match &self.target.debug_sequence {
+            DebugSequence::Arm(sequence) => sequence.clone(),
+            _ => unreachable!("Mismatch between architecture and sequence type!"),
+        };
+
+        let components = self.get_arm_components(DpAddress::Default)?;
+        let interface = self.get_arm_interface()?;
+
+        sequence_handle.trace_start(interface, &components, &TraceSink::TraceMemory)?;
+        let parameters =
+            crate::architecture::arm::component::setup_etm_tracing(interface, &components, config)?;
+
+        self.configured_etm_trace_id.replace(config.trace_id);
+
+        Ok(parameters)
+    }
+
+    /// Stop the instruction trace configured with [`Session::setup_etm_tracing`] and read the
+    /// recorded ETM trace stream.
+    pub fn read_etm_trace_data(&mut self) -> Result<Vec<u8>, Error> {
+        let trace_id = self
+            .configured_etm_trace_id
+            .ok_or(ArmError::TracingUnconfigured)?;
+
+        let components = self.get_arm_components(DpAddress::Default)?;
+        let interface = self.get_arm_interface()?;
+        crate::architecture::arm::component::read_etm_trace(interface, &components, trace_id)
+    }
+
+    /// Enable the trace memory of the Xtensa core with `core_index`, and start recording its
+    /// program flow with TRAX, discarding the previous trace.
+    ///
+    /// The trace is read with [`XtensaCommunicationInterface::read_trax_trace`].
+    pub fn setup_trax_tracing(
+        &mut self,
+        core_index: usize,
+        config: &TraxConfig,
+    ) -> Result<(), Error> {
+        let DebugSequence::Xtensa(sequence) = self.target.debug_sequence.clone() else {
+            return Err(XtensaError::NoXtensaTarget.into());
+        };
+
+        let mut interface = self.get_xtensa_interface(core_index)?;
+        sequence.trax_memory_setup(&mut interface, core_index)?;
+        interface.start_trax(config)?;
+
+        Ok(())
+    }
+
+    /// Configure the MTB to record the branches of the core into `buffer`, a region of the MTB
+    /// SRAM that is not used by the firmware.
+    ///
+    /// The size of the buffer has to be a power of two of at least 16 bytes, and the buffer has
+    /// to be aligned to its size. As the MTB is reset together with the target, tracing has to be
+    /// set up again after every reset.
+    pub fn setup_mtb_tracing(&mut self, buffer: Range<u64>) -> Result<(), Error> {
+        let components = self.get_arm_components(DpAddress::Default)?;
+        let interface = self.get_arm_interface()?;
+        crate::architecture::arm::component::setup_mtb_tracing(interface, &components, &buffer)?;
+
+        self.configured_mtb_buffer.replace(buffer);
+
+        Ok(())
+    }
+
+    /// Stop the branch trace configured with [`Session::setup_mtb_tracing`] and read the
+    /// recorded branches, oldest first.
+    pub fn read_mtb_branches(&mut self) -> Result<Vec<MtbBranch>, Error> {
+        let buffer = self
+            .configured_mtb_buffer
+            .clone()
+            .ok_or(ArmError::TracingUnconfigured)?;
+
+        let components = self.get_arm_components(DpAddress::Default)?;
+        let interface = self.get_arm_interface()?;
+        Ok(crate::architecture::arm::component::read_mtb_branches(
+            interface,
+            &components,
+            &buffer,
+        )?)
+    }
+
     /// Configure the target to stop emitting SWV trace data.
     #[tracing::instrument(skip(self))]
     pub fn disable_swv(&mut self, core_index: usize) -> Result<(), Error> {
@@ -1002,14 +1137,17 @@ impl Session {
         }
     }
 
-    /// Clears all hardware breakpoints on all cores
+    /// Clears all hardware breakpoints and watchpoints on all cores
     pub fn clear_all_hw_breakpoints(&mut self) -> Result<(), Error> {
         self.halted_access(|session| {
             { 0..session.cores.len() }.try_for_each(|core| {
                 tracing::info!("Clearing breakpoints for core {core}");
 
                 match session.core(core) {
-                    Ok(mut core) => core.clear_all_hw_breakpoints(),
+                    Ok(mut core) => {
+                        core.clear_all_hw_breakpoints()?;
+                        core.clear_all_hw_watchpoints()
+                    }
                     Err(Error::CoreDisabled(_)) => Ok(()),
                     Err(Error::Riscv(
                         crate::architecture::riscv::communication_interface::RiscvError::Timeout,
@@ -1025,10 +1163,227 @@ impl Session {
         })
     }
 
+    /// Returns the flash breakpoints of this session.
+    pub fn flash_breakpoints(&self) -> &FlashBreakpoints {
+        &self.flash_breakpoints
+    }
+
+    /// Sets a flash breakpoint at `address`, for code executed by the core with `core_index`.
+    ///
+    /// The sector containing `address` is only reprogrammed with the break instruction by
+    /// [`Session::prepare_flash_breakpoints_for_run`] or
+    /// [`Session::prepare_flash_breakpoints_for_step`], so that several breakpoints set in a row
+    /// are programmed at once. All cores are halted while the sector is reprogrammed.
+    pub fn set_flash_breakpoint(
+        &mut self,
+        core_index: usize,
+        address: u64,
+    ) -> Result<(), FlashError> {
+        if self.flash_breakpoints.contains(address) {
+            return Ok(());
+        }
+
+        let not_in_flash = || {
+            FlashError::Core(Error::BreakpointOperation(
+                BreakpointError::FlashBreakpointNotInFlash(address),
+            ))
+        };
+
+        let sector = self
+            .target
+            .flash_algorithms
+            .iter()
+            .filter_map(|algorithm| {
+                crate::flashing::sector_info(&algorithm.flash_properties, address)
+                    .map(|sector| (algorithm.default, sector))
+            })
+            .max_by_key(|(default, _)| *default)
+            .map(|(_, sector)| sector)
+            .ok_or_else(not_in_flash)?;
+
+        let sector_cached = self.flash_breakpoints.has_sector(sector.base_address);
+        let mut core = self.core(core_index).map_err(FlashError::Core)?;
+        let instruction_set = core.instruction_set().map_err(FlashError::Core)?;
+        let original = if sector_cached {
+            None
+        } else {
+            let mut original = vec![0; sector.size as usize];
+            core.read(sector.base_address, &mut original)
+                .map_err(FlashError::Core)?;
+            Some(original)
+        };
+        drop(core);
+
+        if let Some(original) = original {
+            self.flash_breakpoints
+                .add_sector(sector.base_address, original);
+        }
+
+        let len = crate::core::software_breakpoints::longest_break_instruction(instruction_set);
+        let end = sector.base_address + sector.size;
+        let original = self
+            .flash_breakpoints
+            .original(address, len.min((end - address) as usize))
+            .ok_or_else(not_in_flash)?;
+        let breakpoint =
+            crate::core::software_breakpoints::break_instruction(instruction_set, original);
+        if address + breakpoint.len() as u64 > end {
+            return Err(not_in_flash());
+        }
+
+        self.flash_breakpoints.insert(address, breakpoint);
+
+        Ok(())
+    }
+
+    /// Clears the flash breakpoint at `address`.
+    ///
+    /// The original code is restored the next time flash breakpoints are programmed.
+    pub fn clear_flash_breakpoint(&mut self, address: u64) -> Result<(), FlashError> {
+        if self.flash_breakpoints.remove(address) {
+            Ok(())
+        } else {
+            Err(FlashError::Core(Error::BreakpointOperation(
+                BreakpointError::NotFound(address),
+            )))
+        }
+    }
+
+    /// Programs pending flash breakpoint changes before the core with `core_index` is resumed.
+    ///
+    /// If the core is halted on a flash breakpoint, the original instruction is restored and
+    /// stepped over first.
+    pub fn prepare_flash_breakpoints_for_run(
+        &mut self,
+        core_index: usize,
+    ) -> Result<(), FlashError> {
+        if self.flash_breakpoints.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(pc) = self.halted_on_flash_breakpoint(core_index)? {
+            self.program_flash_breakpoints(Some(pc))?;
+            self.core(core_index)
+                .and_then(|mut core| core.step())
+                .map_err(FlashError::Core)?;
+        }
+
+        self.program_flash_breakpoints(None)
+    }
+
+    /// Programs pending flash breakpoint changes before the core with `core_index` is stepped.
+    ///
+    /// A flash breakpoint at the current program counter stays restored to the original
+    /// instruction until the core is resumed.
+    pub fn prepare_flash_breakpoints_for_step(
+        &mut self,
+        core_index: usize,
+    ) -> Result<(), FlashError> {
+        if self.flash_breakpoints.is_empty() {
+            return Ok(());
+        }
+
+        let pc = self.halted_on_flash_breakpoint(core_index)?;
+        self.program_flash_breakpoints(pc)
+    }
+
+    /// Returns the program counter if the core is halted on a flash breakpoint.
+    fn halted_on_flash_breakpoint(&mut self, core_index: usize) -> Result<Option<u64>, FlashError> {
+        let pc = {
+            let mut core = self.core(core_index).map_err(FlashError::Core)?;
+            if !core.core_halted().map_err(FlashError::Core)? {
+                return Ok(None);
+            }
+
+            let pc_id = core.program_counter().id;
+            core.read_core_reg::<u64>(pc_id).map_err(FlashError::Core)?
+        };
+
+        Ok(self.flash_breakpoints.contains(pc).then_some(pc))
+    }
+
+    /// Reprograms the sectors whose breakpoints changed, leaving out the breakpoint at `skip`.
+    fn program_flash_breakpoints(&mut self, skip: Option<u64>) -> Result<(), FlashError> {
+        let images = self.flash_breakpoints.sector_images(skip);
+        if images.is_empty() {
+            return Ok(());
+        }
+
+        let mut loader = self.target.flash_loader();
+        for (address, image) in &images {
+            tracing::debug!("Reprogramming sector {address:#010x} for flash breakpoints");
+            loader.add_data(*address, image)?;
+        }
+
+        // No core may execute from flash while it is erased and programmed, so the cores that
+        // are still running are halted until the sectors are reprogrammed.
+        let mut halted_cores = Vec::new();
+        let halted = self.halt_running_cores(&mut halted_cores);
+
+        let result = halted.and_then(|()| {
+            loader.commit(
+                self,
+                DownloadOptions {
+                    preserve_target_state: true,
+                    ..DownloadOptions::default()
+                },
+            )
+        });
+
+        // Every halted core is resumed, even if halting another core or flashing failed.
+        let mut resumed = Ok(());
+        for core_index in halted_cores {
+            if let Err(error) = self.core(core_index).and_then(|mut core| core.run()) {
+                tracing::warn!("Failed to resume core {core_index}: {error}");
+                if resumed.is_ok() {
+                    resumed = Err(FlashError::Core(error));
+                }
+            }
+        }
+        result?;
+        resumed?;
+
+        self.flash_breakpoints.mark_programmed(skip);
+
+        Ok(())
+    }
+
+    /// Halts the cores that are running, and adds them to `halted_cores`.
+    fn halt_running_cores(&mut self, halted_cores: &mut Vec<usize>) -> Result<(), FlashError> {
+        for core_index in 0..self.cores.len() {
+            match self.core(core_index) {
+                Ok(mut core) => {
+                    if !core.core_halted().map_err(FlashError::Core)? {
+                        core.halt(Duration::from_millis(500))
+                            .map_err(FlashError::Core)?;
+                        halted_cores.push(core_index);
+                    }
+                }
+                Err(Error::CoreDisabled(i)) => tracing::debug!("Core {i} is disabled"),
+                Err(error) => return Err(FlashError::Core(error)),
+            }
+        }
+
+        Ok(())
+    }
+
     /// Resume all cores
+    ///
+    /// Cores in a run-control group are resumed together, see
+    /// [`Session::resume_run_control_group`].
     pub fn resume_all_cores(&mut self) -> Result<(), Error> {
+        let mut resumed_groups = Vec::new();
+
         // Resume cores
         for core_id in 0..self.cores.len() {
+            if let Some((group, _)) = self.run_control_groups.group_of(core_id) {
+                if !resumed_groups.contains(&group) {
+                    resumed_groups.push(group);
+                    self.resume_run_control_group(core_id)?;
+                }
+                continue;
+            }
+
             match self.core(core_id) {
                 Ok(mut core) => {
                     if core.core_halted()? {
@@ -1042,6 +1397,153 @@ impl Session {
 
         Ok(())
     }
+
+    /// Returns the run-control groups of this session.
+    pub fn run_control_groups(&self) -> &RunControlGroups {
+        &self.run_control_groups
+    }
+
+    /// Returns all cores in the run-control group of the core with `core_index`, or `None` if it
+    /// halts and resumes on its own.
+    pub fn run_control_group(&self, core_index: usize) -> Option<&[usize]> {
+        self.run_control_groups
+            .group_of(core_index)
+            .map(|(_, cores)| cores)
+    }
+
+    /// Creates a run-control group of `cores`, so that one core halting halts all of them, and
+    /// resuming one resumes all of them at the same moment.
+    ///
+    /// The cores are wired together in hardware, so this also applies to halts the debugger did
+    /// not request, like breakpoints. Returns the number of the new group.
+    pub fn create_run_control_group(&mut self, cores: &[usize]) -> Result<u8, Error> {
+        let mut members = cores.to_vec();
+        members.sort_unstable();
+        members.dedup();
+
+        if members.len() < 2 {
+            return Err(Error::Other(
+                "A run-control group needs at least two cores".to_string(),
+            ));
+        }
+        for &core in &members {
+            if core >= self.cores.len() {
+                return Err(Error::CoreNotFound(core));
+            }
+            if let Some((group, _)) = self.run_control_groups.group_of(core) {
+                return Err(Error::Other(format!(
+                    "Core {core} is already part of run-control group {group}"
+                )));
+            }
+        }
+
+        let group = self.run_control_groups.next_free();
+        for (joined, &core) in members.iter().enumerate() {
+            let result = self.core(core)?.join_run_control_group(group);
+            if let Err(error) = result {
+                // Leave the cores already joined in their previous state.
+                for &core in &members[..joined] {
+                    if let Err(error) = self
+                        .core(core)
+                        .and_then(|mut core| core.leave_run_control_group(group))
+                    {
+                        tracing::warn!("Failed to leave run-control group {group}: {error}");
+                    }
+                }
+                return Err(error);
+            }
+        }
+
+        tracing::info!("Created run-control group {group} of cores {members:?}");
+        self.run_control_groups.insert(group, members);
+
+        Ok(group)
+    }
+
+    /// Removes the run-control group of the core with `core_index`, so its cores halt and resume
+    /// on their own again. Does nothing if the core is not in a group.
+    pub fn remove_run_control_group(&mut self, core_index: usize) -> Result<(), Error> {
+        let Some((group, _)) = self.run_control_groups.group_of(core_index) else {
+            return Ok(());
+        };
+        let members = self.run_control_groups.remove(group).unwrap_or_default();
+
+        for core in members {
+            self.core(core)?.leave_run_control_group(group)?;
+        }
+
+        Ok(())
+    }
+
+    /// Halts the core with `core_index`, together with all cores in its run-control group.
+    ///
+    /// If the core is not in a group, only the core itself is halted.
+    pub fn halt_run_control_group(
+        &mut self,
+        core_index: usize,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        let Some((group, members)) = self
+            .run_control_groups
+            .group_of(core_index)
+            .map(|(group, cores)| (group, cores.to_vec()))
+        else {
+            let mut core = self.core(core_index)?;
+            if !core.core_halted()? {
+                core.halt(timeout)?;
+            }
+            return Ok(());
+        };
+
+        {
+            let mut core = self.core(core_index)?;
+            if !core.core_halted()? {
+                core.trigger_run_control_group(group, RunControlEvent::Halt)?;
+            }
+        }
+
+        for core in members {
+            let mut core = self.core(core)?;
+            core.wait_for_core_halted(timeout)?;
+            core.status()?;
+        }
+
+        Ok(())
+    }
+
+    /// Resumes the core with `core_index`, together with all cores in its run-control group.
+    ///
+    /// If the core is not in a group, only the core itself is resumed.
+    pub fn resume_run_control_group(&mut self, core_index: usize) -> Result<(), Error> {
+        let Some((group, members)) = self
+            .run_control_groups
+            .group_of(core_index)
+            .map(|(group, cores)| (group, cores.to_vec()))
+        else {
+            let mut core = self.core(core_index)?;
+            if core.core_halted()? {
+                core.run()?;
+            }
+            return Ok(());
+        };
+
+        if !self.core(core_index)?.core_halted()? {
+            return Ok(());
+        }
+
+        for &core in &members {
+            self.core(core)?.prepare_run_control_group_restart()?;
+        }
+
+        self.core(core_index)?
+            .trigger_run_control_group(group, RunControlEvent::Restart)?;
+
+        for &core in &members {
+            self.core(core)?.run_control_group_restarted()?;
+        }
+
+        Ok(())
+    }
 }
 
 // This test ensures that [Session] is fully [Send] + [Sync].
@@ -1054,6 +1556,49 @@ const _: fn() = || {
 impl Drop for Session {
     #[tracing::instrument(name = "session_drop", skip(self))]
     fn drop(&mut self) {
+        // Restore the instructions patched by software breakpoints.
+        if let Err(err) = { 0..self.cores.len() }.try_for_each(|core| {
+            if self.sw_breakpoints.is_empty() {
+                return Ok(());
+            }
+            match self.core(core) {
+                Ok(mut core) => core.clear_all_sw_breakpoints(),
+                Err(Error::CoreDisabled(_)) => Ok(()),
+                Err(err) => Err(err),
+            }
+        }) {
+            tracing::warn!(
+                "Could not clear all software breakpoints: {:?}",
+                anyhow::anyhow!(err)
+            );
+        }
+
+        // Restore the flash sectors patched by flash breakpoints.
+        if !self.flash_breakpoints.is_empty() {
+            self.flash_breakpoints.remove_all();
+            if let Err(err) = self.program_flash_breakpoints(None) {
+                tracing::warn!(
+                    "Could not restore the flash contents patched by flash breakpoints: {:?}",
+                    anyhow::anyhow!(err)
+                );
+            }
+        }
+
+        // Disconnect the cross triggers, so the cores run independently after detaching.
+        let grouped_cores: Vec<usize> = self
+            .run_control_groups
+            .iter()
+            .map(|(_, cores)| cores[0])
+            .collect();
+        for core in grouped_cores {
+            if let Err(err) = self.remove_run_control_group(core) {
+                tracing::warn!(
+                    "Could not remove run-control group: {:?}",
+                    anyhow::anyhow!(err)
+                );
+            }
+        }
+
         if let Err(err) = self.clear_all_hw_breakpoints() {
             tracing::warn!(
                 "Could not clear all hardware breakpoints: {:?}",
@@ -101,8 +101,11 @@ impl MemoryInterface for MockMemory {
         Ok(u32::from_le_bytes(bytes))
     }
 
-    fn read_word_8(&mut self, _address: u64) -> Result<u8, crate::Error> {
-        todo!()
+    fn read_word_8(&mut self, address: u64) -> Result<u8, crate::Error> {
+        let mut byte = [0u8; 1];
+        self.read_8(address, &mut byte)?;
+
+        Ok(byte[0])
     }
 
     fn read_word_16(&mut self, _address: u64) -> Result<u16, crate::Error> {
@@ -196,16 +199,35 @@ impl MemoryInterface for MockMemory {
         todo!()
     }
 
-    fn write_32(&mut self, _address: u64, _data: &[u32]) -> Result<(), crate::Error> {
-        todo!()
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), crate::Error> {
+        let bytes = data
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect::<Vec<_>>();
+        self.write_8(address, &bytes)
     }
 
     fn write_16(&mut self, _address: u64, _data: &[u16]) -> Result<(), crate::Error> {
         todo!()
     }
 
-    fn write_8(&mut self, _address: u64, _data: &[u8]) -> Result<(), crate::Error> {
-        todo!()
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), crate::Error> {
+        let index = match self
+            .values
+            .binary_search_by_key(&address, |(addr, _data)| *addr)
+        {
+            Ok(index) => index,
+            Err(0) => self.missing_range(address, address + data.len() as u64),
+            Err(index) => index - 1,
+        };
+
+        let offset = (address - self.values[index].0) as usize;
+        if offset + data.len() > self.values[index].1.len() {
+            self.missing_range(address, address + data.len() as u64)
+        }
+
+        self.values[index].1[offset..offset + data.len()].copy_from_slice(data);
+        Ok(())
     }
 
     fn flush(&mut self) -> Result<(), crate::Error> {